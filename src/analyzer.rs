@@ -0,0 +1,72 @@
+use crate::{
+    cancel::CancellationToken,
+    errors::SourceCodeError,
+    observer::{AnalysisObserver, NoopObserver},
+    options::AnalysisOptions,
+    provider::MetricProvider,
+    repository::RepositoryInfo,
+};
+
+/// Analyses repositories into [`RepositoryInfo`]s, as configured by its builder methods. A
+/// long-running service re-analysing the same repository can pass the previous
+/// [`RepositoryInfo`] with [`RepositoryAnalyzer::with_baseline`] to reuse its per-file results.
+/// Custom per-file metrics are added by registering [`MetricProvider`]s with
+/// [`RepositoryAnalyzer::with_metric_provider`].
+pub struct RepositoryAnalyzer<'a> {
+    options: AnalysisOptions,
+    observer: &'a dyn AnalysisObserver,
+    cancellation: CancellationToken,
+    baseline: Option<&'a RepositoryInfo>,
+    metric_providers: Vec<&'a dyn MetricProvider>,
+}
+impl<'a> RepositoryAnalyzer<'a> {
+    pub fn new(options: AnalysisOptions) -> Self {
+        Self {
+            options,
+            observer: &NoopObserver,
+            cancellation: CancellationToken::new(),
+            baseline: None,
+            metric_providers: Vec::new(),
+        }
+    }
+    /// Reports the progress of the analyses to the [`AnalysisObserver`]
+    pub fn with_observer(mut self, observer: &'a dyn AnalysisObserver) -> Self {
+        self.observer = observer;
+        self
+    }
+    /// Stops the analyses early when the [`CancellationToken`] is cancelled
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+    /// Reuses the per-file history results of a previous [`RepositoryInfo`] of the same
+    /// repository, so that only the commits made since it are walked. The baseline is ignored
+    /// if it was analysed with another merge strategy or its HEAD is no longer in the history.
+    pub fn with_baseline(mut self, baseline: &'a RepositoryInfo) -> Self {
+        self.baseline = Some(baseline);
+        self
+    }
+    /// Registers a [`MetricProvider`], whose metrics are computed for each source file and added
+    /// to the `custom` metrics of its [`crate::data::Statistics`]; providers run in the order
+    /// they are registered
+    pub fn with_metric_provider(mut self, provider: &'a dyn MetricProvider) -> Self {
+        self.metric_providers.push(provider);
+        self
+    }
+    /// Analyses the repository at `repo_path`
+    pub fn analyse(
+        &self,
+        name: String,
+        repo_path: &str,
+    ) -> Result<RepositoryInfo, SourceCodeError> {
+        RepositoryInfo::new_with_baseline(
+            name,
+            repo_path,
+            &self.options,
+            self.observer,
+            &self.cancellation,
+            self.baseline,
+            &self.metric_providers,
+        )
+    }
+}
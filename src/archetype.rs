@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs, path::Path};
+use walkdir::WalkDir;
+
+use crate::{data::saturating_sum, ratio::ratio, source::SourceFileInfo};
+
+/// Manifests that mark a (sub)project; used to spot multiple projects in one repository
+const PROJECT_MANIFESTS: [&str; 7] = [
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "setup.py",
+    "pom.xml",
+    "build.gradle",
+];
+/// Root files that are only present in monorepo tooling setups
+const MONOREPO_MARKERS: [&str; 6] = [
+    "lerna.json",
+    "pnpm-workspace.yaml",
+    "nx.json",
+    "turbo.json",
+    "go.work",
+    "rush.json",
+];
+/// Directories that conventionally hold the packages of a monorepo
+const PACKAGE_DIRS: [&str; 6] = ["packages", "apps", "services", "crates", "libs", "modules"];
+/// Languages that are (almost) only used for infrastructure definitions
+const INFRA_LANGUAGES: [&str; 5] = ["HCL", "Dockerfile", "YAML", "Nix", "Jsonnet"];
+/// Languages that are used for prose documentation
+const DOCS_LANGUAGES: [&str; 5] = [
+    "Markdown",
+    "ReStructuredText",
+    "AsciiDoc",
+    "Plain Text",
+    "Org",
+];
+/// Frontend frameworks identified from `package.json` dependencies
+const FRONTEND_PACKAGES: [&str; 7] = [
+    "\"react\"",
+    "\"vue\"",
+    "\"@angular/core\"",
+    "\"svelte\"",
+    "\"next\"",
+    "\"nuxt\"",
+    "\"solid-js\"",
+];
+/// Paths that indicate a long-running executable (service) rather than a library
+const SERVICE_ENTRY_POINTS: [&str; 9] = [
+    "src/main.rs",
+    "main.go",
+    "cmd",
+    "manage.py",
+    "app.py",
+    "server.js",
+    "server.ts",
+    "Procfile",
+    "docker-compose.yml",
+];
+/// Paths that indicate a library
+const LIBRARY_ENTRY_POINTS: [&str; 5] = [
+    "src/lib.rs",
+    "setup.py",
+    "pyproject.toml",
+    "index.js",
+    "lib",
+];
+
+/// The broad kind of a repository, used to group repositories in portfolio reports
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RepositoryArchetype {
+    Library,
+    Service,
+    FrontendApp,
+    Infrastructure,
+    Documentation,
+    Monorepo,
+    #[default]
+    Unknown,
+}
+impl fmt::Display for RepositoryArchetype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RepositoryArchetype::Library => "library",
+            RepositoryArchetype::Service => "service",
+            RepositoryArchetype::FrontendApp => "frontend_app",
+            RepositoryArchetype::Infrastructure => "infrastructure",
+            RepositoryArchetype::Documentation => "documentation",
+            RepositoryArchetype::Monorepo => "monorepo",
+            RepositoryArchetype::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+impl RepositoryArchetype {
+    /// Classifies the repository at `repo_path` from its manifests, directory layout and the
+    /// language mix (by size) of its [`SourceFileInfo`]s.
+    ///
+    /// The checks are applied in order of specificity: monorepo, documentation, infrastructure,
+    /// frontend app, service and finally library.
+    pub fn classify(repo_path: &str, source_files: &[SourceFileInfo]) -> Self {
+        if source_files.is_empty() {
+            return RepositoryArchetype::Unknown;
+        }
+        let root = Path::new(repo_path);
+        if Self::is_monorepo(root) {
+            return RepositoryArchetype::Monorepo;
+        }
+
+        let shares = Self::get_language_shares(source_files);
+        let share_of = |languages: &[&str]| -> f32 {
+            languages
+                .iter()
+                .filter_map(|language| shares.get(*language))
+                .sum()
+        };
+        if share_of(&DOCS_LANGUAGES) >= 0.6 {
+            return RepositoryArchetype::Documentation;
+        }
+        if share_of(&INFRA_LANGUAGES) >= 0.6
+            || (Self::has_terraform(source_files) && share_of(&INFRA_LANGUAGES) >= 0.3)
+        {
+            return RepositoryArchetype::Infrastructure;
+        }
+
+        let package_json = read_root_file(root, "package.json");
+        if FRONTEND_PACKAGES
+            .iter()
+            .any(|package| package_json.contains(package))
+        {
+            return RepositoryArchetype::FrontendApp;
+        }
+        if SERVICE_ENTRY_POINTS
+            .iter()
+            .any(|entry| root.join(entry).exists())
+            || root.join("Dockerfile").exists()
+        {
+            return RepositoryArchetype::Service;
+        }
+        if LIBRARY_ENTRY_POINTS
+            .iter()
+            .any(|entry| root.join(entry).exists())
+            || package_json.contains("\"main\"")
+            || package_json.contains("\"exports\"")
+        {
+            return RepositoryArchetype::Library;
+        }
+
+        RepositoryArchetype::Unknown
+    }
+    /// A repository is a monorepo if it declares a workspace, uses monorepo tooling, or holds
+    /// two or more projects under the conventional package directories
+    fn is_monorepo(root: &Path) -> bool {
+        if read_root_file(root, "Cargo.toml").contains("[workspace]")
+            || read_root_file(root, "package.json").contains("\"workspaces\"")
+            || MONOREPO_MARKERS
+                .iter()
+                .any(|marker| root.join(marker).exists())
+        {
+            return true;
+        }
+
+        let nested_projects = PACKAGE_DIRS
+            .iter()
+            .map(|dir| root.join(dir))
+            .filter(|dir| dir.is_dir())
+            .flat_map(|dir| {
+                WalkDir::new(dir)
+                    .min_depth(2)
+                    .max_depth(2)
+                    .into_iter()
+                    .filter_map(Result::ok)
+            })
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .map(|name| PROJECT_MANIFESTS.contains(&name))
+                    .unwrap_or(false)
+            })
+            .count();
+        nested_projects >= 2
+    }
+    /// Gets the share (0.0 - 1.0) of the total size held by each language
+    fn get_language_shares(source_files: &[SourceFileInfo]) -> HashMap<&str, f32> {
+        let mut sizes: HashMap<&str, u64> = HashMap::new();
+        for sfi in source_files {
+            if let Some(language) = &sfi.language {
+                let size = sizes.entry(language.name.as_str()).or_insert(0);
+                *size = size.saturating_add(sfi.statistics.size);
+            }
+        }
+        let total = saturating_sum(sizes.values().copied());
+        sizes
+            .into_iter()
+            .map(|(name, size)| {
+                let share = ratio(size as f64, total as f64).unwrap_or_default();
+                (name, share)
+            })
+            .collect()
+    }
+    fn has_terraform(source_files: &[SourceFileInfo]) -> bool {
+        source_files
+            .iter()
+            .any(|sfi| sfi.name.ends_with(".tf") || sfi.name == "Chart.yaml")
+    }
+}
+
+/// Reads a file at the root of the repository, returning an empty string if it can't be read
+fn read_root_file(root: &Path, name: &str) -> String {
+    fs::read_to_string(root.join(name)).unwrap_or_default()
+}
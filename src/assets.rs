@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::data::{saturating_sum, to_count};
+
+/// The number of largest [`AssetFile`]s kept in the [`AssetSummary`]
+const MAX_LARGEST: usize = 10;
+
+/// The kind of a (binary) asset file, by its extension
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    Image,
+    Audio,
+    Video,
+    Font,
+    /// Archives and packages, e.g., '.zip' or '.jar'
+    Archive,
+    /// Machine learning models and serialised data, e.g., '.onnx' or '.pkl'
+    Model,
+    /// Office documents and PDFs
+    Document,
+    /// Compiled code, e.g., '.exe', '.so' or '.class'
+    Executable,
+    Other,
+}
+impl AssetKind {
+    fn from_extension(extension: &str) -> Self {
+        match extension.to_lowercase().as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "bmp" | "ico" | "webp" | "tif" | "tiff" | "psd"
+            | "heic" => AssetKind::Image,
+            "mp3" | "wav" | "ogg" | "flac" | "aac" | "m4a" => AssetKind::Audio,
+            "mp4" | "mov" | "avi" | "mkv" | "webm" => AssetKind::Video,
+            "ttf" | "otf" | "woff" | "woff2" | "eot" => AssetKind::Font,
+            "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" | "jar" | "war" | "whl"
+            | "nupkg" => AssetKind::Archive,
+            "onnx" | "pt" | "pth" | "h5" | "pb" | "tflite" | "safetensors" | "ckpt" | "gguf"
+            | "pkl" | "npy" | "npz" => AssetKind::Model,
+            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" => AssetKind::Document,
+            "exe" | "dll" | "so" | "dylib" | "a" | "lib" | "o" | "class" | "wasm" | "pyc" => {
+                AssetKind::Executable
+            }
+            _ => AssetKind::Other,
+        }
+    }
+}
+
+/// A binary file in the repository that isn't a source file, e.g., an image or a model
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `kind` - The [`AssetKind`] of the file
+/// * `size` - The size of the file in bytes
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AssetFile {
+    pub path: String,
+    pub kind: AssetKind,
+    pub size: u64,
+}
+impl AssetFile {
+    pub(crate) fn new(path: String, size: u64) -> Self {
+        let kind = Path::new(&path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(AssetKind::from_extension)
+            .unwrap_or(AssetKind::Other);
+        Self { path, kind, size }
+    }
+}
+
+/// The number and total size of the asset files of an [`AssetKind`]
+///
+/// #Fields:
+/// * `kind` - The [`AssetKind`]
+/// * `num_files` - The number of asset files of the kind
+/// * `total_size` - The total size of the asset files of the kind in bytes
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AssetKindTotal {
+    pub kind: AssetKind,
+    pub num_files: u64,
+    pub total_size: u64,
+}
+
+/// The binary asset files of a repository, which aren't counted in its source files, detected
+/// from their contents (a NUL byte near the start, as git does) without decoding them
+///
+/// #Fields:
+/// * `num_files` - The number of asset files
+/// * `total_size` - The total size of the asset files in bytes
+/// * `kinds` - The [`AssetKindTotal`] of each [`AssetKind`] present
+/// * `largest` - The largest [`AssetFile`]s, largest first
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AssetSummary {
+    pub num_files: u64,
+    pub total_size: u64,
+    pub kinds: Vec<AssetKindTotal>,
+    pub largest: Vec<AssetFile>,
+}
+impl AssetSummary {
+    pub(crate) fn new(mut assets: Vec<AssetFile>) -> Self {
+        let mut kinds: BTreeMap<AssetKind, (u64, u64)> = BTreeMap::new();
+        for asset in &assets {
+            let entry = kinds.entry(asset.kind).or_default();
+            entry.0 += 1;
+            entry.1 = entry.1.saturating_add(asset.size);
+        }
+        assets.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        let num_files = to_count(assets.len());
+        let total_size = saturating_sum(assets.iter().map(|asset| asset.size));
+        assets.truncate(MAX_LARGEST);
+        Self {
+            num_files,
+            total_size,
+            kinds: kinds
+                .into_iter()
+                .map(|(kind, (num_files, total_size))| AssetKindTotal {
+                    kind,
+                    num_files,
+                    total_size,
+                })
+                .collect(),
+            largest: assets,
+        }
+    }
+}
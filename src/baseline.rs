@@ -0,0 +1,172 @@
+//! Warm-starting an analysis from a previous [`RepositoryInfo`] of the same repository, so that
+//! the change frequency of each file is updated from the new commits only, rather than walking
+//! the whole history once per file
+use chrono::{DateTime, Utc};
+use git2::{Oid, Repository};
+use std::collections::HashMap;
+
+use crate::{
+    errors::SourceCodeError,
+    head::{self, HeadState},
+    history::{HistoryMode, HistorySampling},
+    messages,
+    paths::RepoRelativePath,
+    ratio::Percentage,
+    repository::RepositoryInfo,
+    source::{MergeStrategy, SourceFileChangeFrequency},
+};
+
+/// The commits made to a path since a baseline
+struct NewCommits {
+    num_commits: u64,
+    num_bugfix: u64,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+}
+
+/// The change frequencies of the files of a baseline [`RepositoryInfo`], brought up to date
+/// with the commits made since it was analysed
+pub(crate) struct BaselineHistory {
+    files: HashMap<RepoRelativePath, SourceFileChangeFrequency>,
+}
+impl BaselineHistory {
+    /// Brings the change frequencies of the files of the `baseline` up to date with the
+    /// repository at `repo_path`
+    ///
+    /// #Returns:
+    /// * The [`BaselineHistory`], or `None` if the `baseline` can't be reused, i.e., it was
+    ///   analysed with another [`MergeStrategy`] or [`HistoryMode`], either history is sampled
+    ///   with a [`HistorySampling`], its HEAD isn't an ancestor of the current HEAD (e.g., after a
+    ///   force push) or either has no commits
+    pub(crate) fn new(
+        repo_path: &str,
+        baseline: &RepositoryInfo,
+        merge_strategy: MergeStrategy,
+        history_mode: HistoryMode,
+        sampling: HistorySampling,
+    ) -> Result<Option<BaselineHistory>, SourceCodeError> {
+        // The counts of a sampled history can't be brought up to date by adding new commits
+        if sampling.is_limited() {
+            tracing::info!("Not reusing the baseline, as the history is sampled");
+            return Ok(None);
+        }
+        let baseline_options = baseline.metadata.as_ref().map(|metadata| {
+            (
+                metadata.options.merge_strategy,
+                metadata.options.history_mode,
+                metadata.options.history_sampling.is_limited(),
+            )
+        });
+        if baseline_options != Some((merge_strategy, history_mode, false)) {
+            tracing::info!("Not reusing the baseline, as it was analysed with other options");
+            return Ok(None);
+        }
+        let repo = Repository::open(repo_path)?;
+        let Some(baseline_head) = baseline
+            .history
+            .as_ref()
+            .and_then(|history| Oid::from_str(&history.head).ok())
+        else {
+            return Ok(None);
+        };
+        let Some(head) = HeadState::resolve(&repo)?
+            .get_commit()
+            .and_then(|head| Oid::from_str(head).ok())
+        else {
+            return Ok(None);
+        };
+        if head != baseline_head
+            && !repo
+                .graph_descendant_of(head, baseline_head)
+                .unwrap_or(false)
+        {
+            tracing::info!("Not reusing the baseline, as its HEAD is not an ancestor of HEAD");
+            return Ok(None);
+        }
+
+        // The commits since the baseline, by the paths they changed
+        let mut new_commits: HashMap<String, NewCommits> = HashMap::new();
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(head)?;
+        revwalk.hide(baseline_head)?;
+        history_mode.configure(&mut revwalk)?;
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            let Some(changed) = merge_strategy.get_changed_paths(&repo, &commit)? else {
+                continue;
+            };
+            let date = DateTime::<Utc>::from_timestamp(commit.time().seconds(), 0);
+            let is_bugfix = messages::is_bugfix(&String::from_utf8_lossy(commit.message_bytes()));
+            for path in changed {
+                let entry = new_commits.entry(path).or_insert(NewCommits {
+                    num_commits: 0,
+                    num_bugfix: 0,
+                    first: DateTime::<Utc>::MAX_UTC,
+                    last: DateTime::<Utc>::MIN_UTC,
+                });
+                entry.num_commits += 1;
+                entry.num_bugfix += is_bugfix as u64;
+                if let Some(date) = date {
+                    entry.first = entry.first.min(date);
+                    entry.last = entry.last.max(date);
+                }
+            }
+        }
+        let total_commits = Self::count_commits(&repo, merge_strategy, history_mode)?;
+
+        let files = baseline
+            .source_files
+            .iter()
+            .map(|sfi| {
+                let mut file_commits = sfi.statistics.num_commits;
+                let mut bugfix_commits = sfi.statistics.bugfix_commits;
+                let mut first_commit_date = sfi.first_commit_date;
+                let mut last_commit_date = sfi.last_commit_date;
+                if let Some(new) = new_commits.get(sfi.relative_path.as_str()) {
+                    file_commits = file_commits.saturating_add(new.num_commits);
+                    bugfix_commits = bugfix_commits.saturating_add(new.num_bugfix);
+                    let (first, last) = (new.first, new.last);
+                    if first <= last {
+                        first_commit_date = Some(first_commit_date.map_or(first, |d| d.min(first)));
+                        last_commit_date = Some(last_commit_date.map_or(last, |d| d.max(last)));
+                    }
+                }
+                let change_frequency = SourceFileChangeFrequency {
+                    file_commits,
+                    total_commits,
+                    frequency: Percentage::of(file_commits as f64, total_commits as f64),
+                    first_commit_date,
+                    last_commit_date,
+                    bugfix_commits,
+                };
+                (sfi.relative_path.clone(), change_frequency)
+            })
+            .collect();
+        Ok(Some(BaselineHistory { files }))
+    }
+    /// Gets the up to date [`SourceFileChangeFrequency`] of the file at `relative_path`, if it
+    /// was in the baseline
+    pub(crate) fn get(&self, relative_path: &str) -> Option<&SourceFileChangeFrequency> {
+        self.files.get(relative_path)
+    }
+    /// Counts the commits reachable from HEAD as [`SourceFileChangeFrequency`] does, i.e.,
+    /// those of the [`HistoryMode`] without the merge commits skipped by the [`MergeStrategy`]
+    fn count_commits(
+        repo: &Repository,
+        merge_strategy: MergeStrategy,
+        history_mode: HistoryMode,
+    ) -> Result<u64, SourceCodeError> {
+        let mut revwalk = repo.revwalk()?;
+        head::push_head(repo, &mut revwalk)?;
+        history_mode.configure(&mut revwalk)?;
+        let mut total_commits: u64 = 0;
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 && merge_strategy == MergeStrategy::SkipMerges {
+                continue;
+            }
+            total_commits += 1;
+        }
+        Ok(total_commits)
+    }
+}
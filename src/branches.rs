@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::{BranchType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "git")]
+use crate::{data::to_count, errors::SourceCodeError};
+
+/// The names of the local branches taken as the default branch, in order, if the remote
+/// 'origin' has no HEAD
+const DEFAULT_BRANCH_NAMES: [&str; 2] = ["main", "master"];
+
+/// The options for the branch analysis
+///
+/// #Fields:
+/// * `stale_after_days` - The days since its last commit after which a branch is stale
+/// * `include_remote` - Whether to include the remote-tracking branches, e.g., 'origin/feature'
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct BranchOptions {
+    pub stale_after_days: i64,
+    pub include_remote: bool,
+}
+impl Default for BranchOptions {
+    fn default() -> Self {
+        Self {
+            stale_after_days: 90,
+            include_remote: true,
+        }
+    }
+}
+
+/// Whether a branch is local or remote-tracking
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BranchKind {
+    Local,
+    Remote,
+}
+
+/// A branch of a repository, compared with the default branch
+///
+/// #Fields:
+/// * `name` - The name of the branch, e.g., 'feature' or 'origin/feature'
+/// * `kind` - The [`BranchKind`] of the branch
+/// * `ahead` - The number of commits on the branch that aren't on the default branch, or `None`
+///   if there is no default branch
+/// * `behind` - The number of commits on the default branch that aren't on the branch, or `None`
+///   if there is no default branch
+/// * `last_commit_date` - The (author) date of the commit at the tip of the branch
+/// * `author` - The author of the commit at the tip of the branch
+/// * `is_stale` - Whether the `last_commit_date` is older than the
+///   [`BranchOptions::stale_after_days`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BranchInfo {
+    pub name: String,
+    pub kind: BranchKind,
+    pub ahead: Option<u64>,
+    pub behind: Option<u64>,
+    pub last_commit_date: Option<DateTime<Utc>>,
+    pub author: Option<String>,
+    pub is_stale: bool,
+}
+
+/// The branches of a repository, e.g., for repository hygiene dashboards
+///
+/// #Fields:
+/// * `default_branch` - The name of the default branch the branches are compared with: the
+///   HEAD of the remote 'origin', else a local 'main' or 'master', else the checked out branch
+/// * `num_stale` - The number of stale branches
+/// * `branches` - The [`BranchInfo`] of each branch, by name
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BranchReport {
+    pub default_branch: Option<String>,
+    pub num_stale: u64,
+    pub branches: Vec<BranchInfo>,
+}
+impl BranchReport {
+    /// Compares the branches of the repository at `repo_path` with its default branch
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository
+    /// * `options` - The [`BranchOptions`]
+    /// * `at` - The time branches are stale relative to, e.g., the time of the analysis
+    #[cfg(feature = "git")]
+    pub fn new(
+        repo_path: &str,
+        options: &BranchOptions,
+        at: DateTime<Utc>,
+    ) -> Result<BranchReport, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let default_branch = Self::find_default_branch(&repo);
+        let default_commit = default_branch.as_ref().map(|(_, commit)| *commit);
+        let branch_type = match options.include_remote {
+            true => None,
+            false => Some(BranchType::Local),
+        };
+
+        let mut branches: Vec<BranchInfo> = Vec::new();
+        for branch in repo.branches(branch_type)? {
+            let (branch, branch_type) = branch?;
+            // Symbolic references, e.g., 'origin/HEAD', are aliases of other branches
+            if branch.get().symbolic_target().is_some() {
+                continue;
+            }
+            let Some(name) = branch.name()?.map(String::from) else {
+                continue;
+            };
+            let commit = branch.get().peel_to_commit()?;
+            let (ahead, behind) = match default_commit {
+                Some(default_commit) => {
+                    let (ahead, behind) = repo.graph_ahead_behind(commit.id(), default_commit)?;
+                    (Some(to_count(ahead)), Some(to_count(behind)))
+                }
+                None => (None, None),
+            };
+            let last_commit_date =
+                DateTime::<Utc>::from_timestamp(commit.author().when().seconds(), 0);
+            branches.push(BranchInfo {
+                name,
+                kind: match branch_type {
+                    BranchType::Local => BranchKind::Local,
+                    BranchType::Remote => BranchKind::Remote,
+                },
+                ahead,
+                behind,
+                last_commit_date,
+                author: commit.author().name().map(String::from),
+                is_stale: last_commit_date
+                    .is_some_and(|date| (at - date).num_days() > options.stale_after_days),
+            });
+        }
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(BranchReport {
+            default_branch: default_branch.map(|(name, _)| name),
+            num_stale: to_count(branches.iter().filter(|branch| branch.is_stale).count()),
+            branches,
+        })
+    }
+    /// Finds the default branch of the `repo`: the HEAD of the remote 'origin', else a local
+    /// 'main' or 'master', else the checked out branch
+    ///
+    /// #Returns:
+    /// * The name of the default branch and the id of its commit, or `None` if there is none,
+    ///   e.g., in a repository without commits
+    #[cfg(feature = "git")]
+    fn find_default_branch(repo: &Repository) -> Option<(String, Oid)> {
+        let branch_commit = |name: &str, branch_type: BranchType| {
+            let branch = repo.find_branch(name, branch_type).ok()?;
+            let commit = branch.get().peel_to_commit().ok()?;
+            Some((name.to_string(), commit.id()))
+        };
+        if let Ok(origin_head) = repo.find_reference("refs/remotes/origin/HEAD") {
+            if let Some(name) = origin_head
+                .symbolic_target()
+                .and_then(|target| target.strip_prefix("refs/remotes/"))
+            {
+                if let Some(default_branch) = branch_commit(name, BranchType::Remote) {
+                    return Some(default_branch);
+                }
+            }
+        }
+        DEFAULT_BRANCH_NAMES
+            .iter()
+            .find_map(|name| branch_commit(name, BranchType::Local))
+            .or_else(|| {
+                let head = repo.head().ok()?;
+                match head.is_branch() {
+                    true => branch_commit(head.shorthand()?, BranchType::Local),
+                    false => None,
+                }
+            })
+    }
+}
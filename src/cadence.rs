@@ -0,0 +1,123 @@
+//! The cadence of a contributor's commits, e.g., how regularly they commit and whether they are
+//! committing more or less than before, for team-health reporting
+use chrono::{DateTime, Datelike, Months, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::ratio::quotient;
+
+/// The options for the [`ContributorCadence`] of each contributor
+///
+/// #Fields:
+/// * `trend_months` - The months before the analysis whose commits are compared with those of
+///   the months before them for the [`ActivityTrend`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CadenceOptions {
+    pub trend_months: u32,
+}
+impl Default for CadenceOptions {
+    /// The last six months are compared with the six months before them
+    fn default() -> Self {
+        Self { trend_months: 6 }
+    }
+}
+
+/// Whether a contributor is committing more or less often than before
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityTrend {
+    /// More commits in the last window than in the window before
+    Increasing,
+    /// As many commits in the last window as in the window before
+    #[default]
+    Stable,
+    /// Fewer commits in the last window than in the window before
+    Decreasing,
+}
+
+/// The cadence of a contributor's commits, by the calendar weeks (from Monday, in UTC) they
+/// committed in
+///
+/// #Fields:
+/// * `active_weeks` - The number of weeks with a commit
+/// * `commits_per_active_week` - The mean number of commits in a week with a commit
+/// * `longest_streak_weeks` - The most weeks in a row with a commit
+/// * `longest_gap_days` - The most whole days between two consecutive commits
+/// * `recent_commits` - The number of commits in the `trend_months` of the [`CadenceOptions`]
+///   before the analysis
+/// * `previous_commits` - The number of commits in the `trend_months` before those
+/// * `trend` - The [`ActivityTrend`] from the `previous_commits` to the `recent_commits`
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ContributorCadence {
+    pub active_weeks: u64,
+    pub commits_per_active_week: f32,
+    pub longest_streak_weeks: u64,
+    pub longest_gap_days: i64,
+    pub recent_commits: u64,
+    pub previous_commits: u64,
+    pub trend: ActivityTrend,
+}
+impl ContributorCadence {
+    /// Gets the [`ContributorCadence`] of the commits made at the `dates`, in any order, as of
+    /// the time `at`, e.g., the time of the analysis
+    pub fn new(dates: &[DateTime<Utc>], at: DateTime<Utc>, options: &CadenceOptions) -> Self {
+        let mut sorted: Vec<DateTime<Utc>> = dates.to_vec();
+        sorted.sort_unstable();
+        let weeks: BTreeSet<i32> = sorted.iter().map(|date| week_number(*date)).collect();
+        let mut longest_streak_weeks: u64 = 0;
+        let mut streak: u64 = 0;
+        let mut previous_week: Option<i32> = None;
+        for week in &weeks {
+            streak = match previous_week {
+                Some(previous_week) if week - previous_week == 1 => streak + 1,
+                _ => 1,
+            };
+            longest_streak_weeks = longest_streak_weeks.max(streak);
+            previous_week = Some(*week);
+        }
+        let longest_gap_days = sorted
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).num_days())
+            .max()
+            .unwrap_or_default();
+
+        let months = Months::new(options.trend_months);
+        let recent_start = at.checked_sub_months(months).unwrap_or(at);
+        let previous_start = recent_start
+            .checked_sub_months(months)
+            .unwrap_or(recent_start);
+        let count = |from: DateTime<Utc>, to: DateTime<Utc>| {
+            sorted
+                .iter()
+                .filter(|date| **date > from && **date <= to)
+                .count() as u64
+        };
+        let recent_commits = count(recent_start, at);
+        let previous_commits = count(previous_start, recent_start);
+        ContributorCadence {
+            active_weeks: weeks.len() as u64,
+            commits_per_active_week: quotient(sorted.len() as f64, weeks.len() as f64)
+                .unwrap_or_default(),
+            longest_streak_weeks,
+            longest_gap_days,
+            recent_commits,
+            previous_commits,
+            trend: match recent_commits.cmp(&previous_commits) {
+                std::cmp::Ordering::Greater => ActivityTrend::Increasing,
+                std::cmp::Ordering::Equal => ActivityTrend::Stable,
+                std::cmp::Ordering::Less => ActivityTrend::Decreasing,
+            },
+        }
+    }
+}
+
+/// Gets the number of the calendar week, from Monday, of the `date`, counted from the start of
+/// the common era, so that consecutive weeks have consecutive numbers across years
+fn week_number(date: DateTime<Utc>) -> i32 {
+    let date = date.date_naive();
+    (date.num_days_from_ce() - date.weekday().num_days_from_monday() as i32).div_euclid(7)
+}
@@ -0,0 +1,75 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{
+    embedding::EmbeddingProgress, errors::SourceCodeError, observer::AnalysisStage,
+    source::SourceFileInfo,
+};
+
+/// A token for cancelling a long analysis or embedding from another thread, e.g., when a user
+/// presses Ctrl-C. Clones share the same state, so cancelling any clone cancels them all.
+///
+/// The token is checked between files and commits; a cancelled analysis returns a
+/// [`SourceCodeError::Cancelled`] with the [`PartialResults`] gathered so far.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Cancels the analyses using this token (or a clone of it)
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+    /// Checks whether the token is cancelled during the `stage`
+    ///
+    /// #Returns:
+    /// * `Ok(())` if not cancelled
+    /// * Err([`SourceCodeError::Cancelled`]) with the `partial` results if cancelled
+    pub(crate) fn check(
+        &self,
+        stage: AnalysisStage,
+        partial: impl FnOnce() -> PartialResults,
+    ) -> Result<(), SourceCodeError> {
+        match self.is_cancelled() {
+            true => Err(SourceCodeError::Cancelled {
+                stage,
+                partial: Box::new(partial()),
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Gets the `result` of a stage that runs after the source files were analysed, e.g., the
+/// ownership, with the analysed `source_files` as its [`PartialResults`] if it was cancelled.
+/// The `source_files` are moved into the error, so they're left empty if it is.
+pub(crate) fn with_source_files<T>(
+    result: Result<T, SourceCodeError>,
+    source_files: &mut Vec<SourceFileInfo>,
+) -> Result<T, SourceCodeError> {
+    result.map_err(|err| match err {
+        SourceCodeError::Cancelled { stage, .. } => SourceCodeError::Cancelled {
+            stage,
+            partial: Box::new(PartialResults::SourceFiles(std::mem::take(source_files))),
+        },
+        err => err,
+    })
+}
+
+/// The results gathered by an analysis before it was cancelled
+#[derive(Clone, Debug, PartialEq)]
+pub enum PartialResults {
+    /// The [`SourceFileInfo`]s analysed before the cancellation
+    SourceFiles(Vec<SourceFileInfo>),
+    /// The [`EmbeddingProgress`] when the embedding was cancelled; the points it counts are
+    /// already in the vector store
+    Embedding(EmbeddingProgress),
+}
@@ -0,0 +1,614 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{derived::DerivedMetric, repository::RepositoryInfo};
+
+/// The unit a metric is measured in
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MetricUnit {
+    Bytes,
+    Lines,
+    Files,
+    Commits,
+    Directories,
+    /// TODO, FIXME, HACK and XXX markers
+    Markers,
+    /// A percentage, 0 to 100
+    Percent,
+    /// A date and time, as RFC 3339
+    Timestamp,
+    /// A number of whole days
+    Days,
+    /// A value without a unit, e.g., of a [`DerivedMetric`]
+    Number,
+}
+
+/// The span of the repository a metric is computed over
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MetricWindow {
+    /// The files on disk when the analysis ran
+    Snapshot,
+    /// The commits reachable from HEAD
+    History,
+    /// The uncommitted changes when the analysis ran
+    WorkingTree,
+}
+
+/// What a metric is reported for
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MetricScope {
+    Repository,
+    Language,
+    SourceFile,
+    Contributor,
+    Directory,
+    Release,
+}
+
+/// The definition of a metric in the output, so that consumers, e.g., dashboards, can render
+/// metrics they don't know of
+///
+/// #Fields:
+/// * `path` - The location of the metric in the JSON output; `[]` marks each element of an array
+/// * `scope` - The [`MetricScope`] the metric is reported for
+/// * `unit` - The [`MetricUnit`] of the metric
+/// * `window` - The [`MetricWindow`] the metric is computed over
+/// * `description` - A description of the metric
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MetricDefinition {
+    pub path: String,
+    pub scope: MetricScope,
+    pub unit: MetricUnit,
+    pub window: MetricWindow,
+    pub description: String,
+}
+impl MetricDefinition {
+    /// Gets the [`MetricDefinition`]s of the metrics present in the [`RepositoryInfo`], including
+    /// the [`DerivedMetric`]s it was analysed with
+    pub fn catalog(
+        repository_info: &RepositoryInfo,
+        derived_metrics: &[DerivedMetric],
+    ) -> Vec<MetricDefinition> {
+        let mut catalog: Vec<MetricDefinition> = METRICS
+            .iter()
+            .filter(|(_, _, _, window, _)| {
+                *window != MetricWindow::WorkingTree || repository_info.working_tree.is_some()
+            })
+            .map(
+                |(path, scope, unit, window, description)| MetricDefinition {
+                    path: path.to_string(),
+                    scope: *scope,
+                    unit: *unit,
+                    window: *window,
+                    description: description.to_string(),
+                },
+            )
+            .collect();
+        if let Some(change_frequency) = catalog
+            .iter_mut()
+            .find(|metric| metric.path == "source_files[].statistics.change_frequency")
+        {
+            change_frequency.description = repository_info.frequency_denominator.describe();
+        }
+        for metric in derived_metrics {
+            let window = match metric.uses_history(derived_metrics) {
+                true => MetricWindow::History,
+                false => MetricWindow::Snapshot,
+            };
+            let description = format!("Derived: {}", metric.expression);
+            for (path, scope) in [
+                ("statistics.derived", MetricScope::Repository),
+                ("source_files[].statistics.derived", MetricScope::SourceFile),
+            ] {
+                catalog.push(MetricDefinition {
+                    path: format!("{}.{}", path, metric.name),
+                    scope,
+                    unit: MetricUnit::Number,
+                    window,
+                    description: description.clone(),
+                });
+            }
+        }
+        catalog
+    }
+    /// Adds the [`MetricDefinition`] to the `catalog`, unless it has a metric at its path, e.g.,
+    /// for the metrics of reports applied after the analysis
+    pub(crate) fn add_to(self, catalog: &mut Vec<MetricDefinition>) {
+        if !catalog.iter().any(|metric| metric.path == self.path) {
+            catalog.push(self);
+        }
+    }
+}
+
+/// The built-in metrics: (path, scope, unit, window, description)
+const METRICS: [(&str, MetricScope, MetricUnit, MetricWindow, &str); 69] = [
+    (
+        "statistics.size",
+        MetricScope::Repository,
+        MetricUnit::Bytes,
+        MetricWindow::Snapshot,
+        "The total size of the source files",
+    ),
+    (
+        "statistics.loc",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The total lines of code of the source files",
+    ),
+    (
+        "statistics.num_files",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of source files",
+    ),
+    (
+        "statistics.num_commits",
+        MetricScope::Repository,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of commits",
+    ),
+    (
+        "statistics.num_markers",
+        MetricScope::Repository,
+        MetricUnit::Markers,
+        MetricWindow::Snapshot,
+        "The number of tech-debt markers in the comments of the source files",
+    ),
+    (
+        "statistics.bugfix_commits",
+        MetricScope::Repository,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of commits whose messages are (likely) bug fixes",
+    ),
+    (
+        "assets.num_files",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of binary asset files, e.g., images, archives and models",
+    ),
+    (
+        "assets.total_size",
+        MetricScope::Repository,
+        MetricUnit::Bytes,
+        MetricWindow::Snapshot,
+        "The total size of the binary asset files",
+    ),
+    (
+        "encodings.num_non_utf8",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of source files that aren't UTF-8, e.g., UTF-16 or Latin-1",
+    ),
+    (
+        "whitespace.num_mixed",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of source files with both LF and CRLF line endings",
+    ),
+    (
+        "whitespace.num_trailing_whitespace",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of source files with lines that end with whitespace",
+    ),
+    (
+        "whitespace.num_missing_final_newline",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of source files that don't end with a line break",
+    ),
+    (
+        "layout.max_depth",
+        MetricScope::Repository,
+        MetricUnit::Directories,
+        MetricWindow::Snapshot,
+        "The most directories a file is nested in",
+    ),
+    (
+        "layout.average_depth",
+        MetricScope::Repository,
+        MetricUnit::Directories,
+        MetricWindow::Snapshot,
+        "The mean number of directories the files are nested in",
+    ),
+    (
+        "layout.max_fan_out",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The most files and directories directly in any directory",
+    ),
+    (
+        "languages[].statistics.size",
+        MetricScope::Language,
+        MetricUnit::Bytes,
+        MetricWindow::Snapshot,
+        "The total size of the language's source files",
+    ),
+    (
+        "languages[].statistics.loc",
+        MetricScope::Language,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The total lines of code of the language's source files, and of its code embedded in the files of other languages",
+    ),
+    (
+        "languages[].statistics.num_files",
+        MetricScope::Language,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of the language's source files",
+    ),
+    (
+        "languages[].statistics.num_commits",
+        MetricScope::Language,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The sum of the commits to each of the language's source files",
+    ),
+    (
+        "languages[].statistics.num_markers",
+        MetricScope::Language,
+        MetricUnit::Markers,
+        MetricWindow::Snapshot,
+        "The number of tech-debt markers in the comments of the language's source files",
+    ),
+    (
+        "languages[].statistics.bugfix_commits",
+        MetricScope::Language,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of bug-fix commits to the language's source files, summed over the files",
+    ),
+    (
+        "languages[].statistics.language_share",
+        MetricScope::Language,
+        MetricUnit::Percent,
+        MetricWindow::Snapshot,
+        "The language's share of the repository's lines of code",
+    ),
+    (
+        "source_files[].statistics.size",
+        MetricScope::SourceFile,
+        MetricUnit::Bytes,
+        MetricWindow::Snapshot,
+        "The size of the source file",
+    ),
+    (
+        "source_files[].statistics.loc",
+        MetricScope::SourceFile,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The lines of code of the source file",
+    ),
+    (
+        "source_files[].embedded[].loc",
+        MetricScope::SourceFile,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The lines of code of a language embedded in the source file, e.g., JavaScript in HTML",
+    ),
+    (
+        "source_files[].whitespace.trailing_whitespace_lines",
+        MetricScope::SourceFile,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The lines of the source file that end with whitespace",
+    ),
+    (
+        "source_files[].statistics.num_commits",
+        MetricScope::SourceFile,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of commits that changed the source file",
+    ),
+    (
+        "source_files[].statistics.change_frequency",
+        MetricScope::SourceFile,
+        MetricUnit::Percent,
+        MetricWindow::History,
+        "The share of the repository's commits that changed the source file",
+    ),
+    (
+        "source_files[].statistics.num_markers",
+        MetricScope::SourceFile,
+        MetricUnit::Markers,
+        MetricWindow::Snapshot,
+        "The number of tech-debt markers in the comments of the source file",
+    ),
+    (
+        "source_files[].statistics.bugfix_commits",
+        MetricScope::SourceFile,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of bug-fix commits that changed the file, a proxy for defect density",
+    ),
+    (
+        "source_files[].first_commit_date",
+        MetricScope::SourceFile,
+        MetricUnit::Timestamp,
+        MetricWindow::History,
+        "The time of the earliest commit that added or changed the source file",
+    ),
+    (
+        "source_files[].last_commit_date",
+        MetricScope::SourceFile,
+        MetricUnit::Timestamp,
+        MetricWindow::History,
+        "The time of the latest commit that changed the source file",
+    ),
+    (
+        "source_files[].age_days",
+        MetricScope::SourceFile,
+        MetricUnit::Days,
+        MetricWindow::History,
+        "The days since the source file was first committed",
+    ),
+    (
+        "source_files[].days_since_last_change",
+        MetricScope::SourceFile,
+        MetricUnit::Days,
+        MetricWindow::History,
+        "The days since the source file was last changed",
+    ),
+    (
+        "source_files[].num_authors",
+        MetricScope::SourceFile,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The number of authors of the commits that added or changed the source file",
+    ),
+    (
+        "statistics_summary.loc.median",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The median lines of code of the source files",
+    ),
+    (
+        "statistics_summary.loc.p90",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The 90th percentile of the lines of code of the source files",
+    ),
+    (
+        "statistics_summary.churn.median",
+        MetricScope::Repository,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The median number of commits changing each source file",
+    ),
+    (
+        "generated_code.generated.loc",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The lines of code in generated source files, e.g., with a '@generated' marker",
+    ),
+    (
+        "generated_code.without_generated.loc",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The lines of code in the source files that aren't generated",
+    ),
+    (
+        "identical_files.wasted_bytes",
+        MetricScope::Repository,
+        MetricUnit::Bytes,
+        MetricWindow::Snapshot,
+        "The size of the source files that are identical copies of another, after the first",
+    ),
+    (
+        "identical_files.percentage",
+        MetricScope::Repository,
+        MetricUnit::Percent,
+        MetricWindow::Snapshot,
+        "The percentage of the lines of code in identical copies of another source file",
+    ),
+    (
+        "roles.code.loc",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The lines of code in source and test files, without config, build, docs and CI files",
+    ),
+    (
+        "contributors[].last_contribution",
+        MetricScope::Contributor,
+        MetricUnit::Timestamp,
+        MetricWindow::History,
+        "The time of the contributor's latest commit",
+    ),
+    (
+        "contributors[].percentage_contribution",
+        MetricScope::Contributor,
+        MetricUnit::Percent,
+        MetricWindow::History,
+        "The contributor's share of the repository's commits",
+    ),
+    (
+        "contributors[].statistics.contribution_share",
+        MetricScope::Contributor,
+        MetricUnit::Percent,
+        MetricWindow::History,
+        "The contributor's share of the repository's commits",
+    ),
+    (
+        "contributors[].statistics.num_commits",
+        MetricScope::Contributor,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of commits authored by the contributor",
+    ),
+    (
+        "contributors[].cadence.commits_per_active_week",
+        MetricScope::Contributor,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The mean number of commits in a week the contributor committed in",
+    ),
+    (
+        "contributors[].cadence.longest_streak_weeks",
+        MetricScope::Contributor,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The most weeks in a row the contributor committed in",
+    ),
+    (
+        "contributors[].cadence.longest_gap_days",
+        MetricScope::Contributor,
+        MetricUnit::Days,
+        MetricWindow::History,
+        "The most whole days between two consecutive commits of the contributor",
+    ),
+    (
+        "contributor_retention.num_active",
+        MetricScope::Repository,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The number of contributors with a commit in the 90 days before the analysis",
+    ),
+    (
+        "contributor_retention.retention_rate",
+        MetricScope::Repository,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The share (0.0 - 1.0) of the contributors active in the 90 days before the last 90 days that are still active",
+    ),
+    (
+        "organisations.organisations[].num_commits",
+        MetricScope::Repository,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of commits from the email domains of the organisation",
+    ),
+    (
+        "organisations.organisations[].loc",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::Snapshot,
+        "The lines of code of the source files the organisation made the most commits to",
+    ),
+    (
+        "releases[].days_since_previous",
+        MetricScope::Release,
+        MetricUnit::Days,
+        MetricWindow::History,
+        "The whole days since the previous release",
+    ),
+    (
+        "releases[].num_commits",
+        MetricScope::Release,
+        MetricUnit::Commits,
+        MetricWindow::History,
+        "The number of commits in the release that aren't in any earlier release",
+    ),
+    (
+        "contributors[].commit_messages.fix_to_feature_ratio",
+        MetricScope::Contributor,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The number of fix commits per feature commit authored by the contributor",
+    ),
+    (
+        "history.commit_messages.average_length",
+        MetricScope::Repository,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The mean length of the commit messages, in characters",
+    ),
+    (
+        "history.commit_messages.fix_to_feature_ratio",
+        MetricScope::Repository,
+        MetricUnit::Number,
+        MetricWindow::History,
+        "The number of fix commits per feature commit",
+    ),
+    (
+        "layout.directories[].depth",
+        MetricScope::Directory,
+        MetricUnit::Directories,
+        MetricWindow::Snapshot,
+        "The number of directories the directory's files are nested in",
+    ),
+    (
+        "layout.directories[].num_files",
+        MetricScope::Directory,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of files directly in the directory",
+    ),
+    (
+        "layout.directories[].num_subdirectories",
+        MetricScope::Directory,
+        MetricUnit::Directories,
+        MetricWindow::Snapshot,
+        "The number of directories directly in the directory",
+    ),
+    (
+        "layout.directories[].num_children",
+        MetricScope::Directory,
+        MetricUnit::Files,
+        MetricWindow::Snapshot,
+        "The number of files and directories directly in the directory",
+    ),
+    (
+        "working_tree.staged.num_files",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::WorkingTree,
+        "The number of files with staged changes",
+    ),
+    (
+        "working_tree.staged.lines_added",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::WorkingTree,
+        "The lines added by staged changes",
+    ),
+    (
+        "working_tree.staged.lines_removed",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::WorkingTree,
+        "The lines removed by staged changes",
+    ),
+    (
+        "working_tree.unstaged.num_files",
+        MetricScope::Repository,
+        MetricUnit::Files,
+        MetricWindow::WorkingTree,
+        "The number of files with unstaged changes",
+    ),
+    (
+        "working_tree.unstaged.lines_added",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::WorkingTree,
+        "The lines added by unstaged changes",
+    ),
+    (
+        "working_tree.unstaged.lines_removed",
+        MetricScope::Repository,
+        MetricUnit::Lines,
+        MetricWindow::WorkingTree,
+        "The lines removed by unstaged changes",
+    ),
+];
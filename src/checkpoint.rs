@@ -0,0 +1,350 @@
+//! Checkpointing the intermediate state of a long analysis, i.e., the commits diffed and the
+//! source files scanned, to a file, so that an interrupted analysis of a massive repository can
+//! be resumed with [`crate::repository::RepositoryInfo::resume_from_checkpoint`] rather than
+//! started again
+#[cfg(feature = "git")]
+use git2::Oid;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "git")]
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::Instant,
+};
+use std::{path::PathBuf, time::Duration};
+
+#[cfg(feature = "git")]
+use crate::{
+    errors::SourceCodeError, options::AnalysisOptions, source::SourceFileInfo,
+    warning::AnalysisWarning,
+};
+
+/// The version of the checkpoint file; checkpoints of other versions aren't resumed
+#[cfg(feature = "git")]
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// The options for checkpointing an analysis
+///
+/// #Fields:
+/// * `path` - The path of the checkpoint file, which each checkpoint replaces, and which is
+///   removed once the analysis completes
+/// * `interval_secs` - The fewest seconds between checkpoints, so that writing them, which takes
+///   longer the more has been analysed, doesn't slow the analysis down
+/// * `resume` - Whether to resume from the checkpoint at the `path`, if it is of the same
+///   repository, HEAD and options, rather than replace it, see
+///   [`crate::repository::RepositoryInfo::resume_from_checkpoint`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CheckpointOptions {
+    pub path: PathBuf,
+    pub interval_secs: u64,
+    pub resume: bool,
+}
+impl Default for CheckpointOptions {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("analysis.checkpoint.json"),
+            interval_secs: 60,
+            resume: false,
+        }
+    }
+}
+impl CheckpointOptions {
+    /// Checkpoints to the file at `path`, every minute at most
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+    /// Checkpoints every `interval` at most
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval_secs = interval.as_secs();
+        self
+    }
+}
+
+/// The paths a commit changed, and those it added as a root commit, when it was diffed
+#[cfg(feature = "git")]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub(crate) struct DiffedCommit {
+    pub(crate) changed: Option<BTreeSet<String>>,
+    pub(crate) added: Vec<String>,
+}
+
+/// A source file scanned, with the warnings of scanning it
+#[cfg(feature = "git")]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct ScannedFile {
+    source_file: SourceFileInfo,
+    warnings: Vec<AnalysisWarning>,
+}
+
+/// The contents of a checkpoint file
+///
+/// #Fields:
+/// * `version` - The [`CHECKPOINT_VERSION`] of the file
+/// * `key` - The [`Checkpointer::get_key`] of the analysis, which must match to resume it
+/// * `commits` - The [`DiffedCommit`]s, by commit id
+/// * `source_files` - The [`ScannedFile`]s, by relative path
+#[cfg(feature = "git")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Checkpoint {
+    version: u32,
+    key: String,
+    commits: HashMap<String, DiffedCommit>,
+    source_files: HashMap<String, ScannedFile>,
+}
+
+/// Records the progress of an analysis, and writes it to the checkpoint file at most once per
+/// interval of the [`CheckpointOptions`]; the progress of a resumed checkpoint is taken from it
+/// as the analysis reaches it. Unless the analysis completes, the progress left unwritten is
+/// written when the [`Checkpointer`] is dropped, e.g., when the analysis fails or is cancelled.
+#[cfg(feature = "git")]
+pub(crate) struct Checkpointer {
+    options: CheckpointOptions,
+    resumed: Checkpoint,
+    progress: Mutex<Checkpoint>,
+    last_saved: Mutex<Instant>,
+    unsaved: Mutex<bool>,
+}
+#[cfg(feature = "git")]
+impl Checkpointer {
+    /// Starts checkpointing the analysis with the `key`, resuming from the checkpoint file if
+    /// the [`CheckpointOptions`] resume and it has the same key; otherwise it is replaced at the
+    /// first checkpoint
+    pub(crate) fn new(options: &CheckpointOptions, key: String) -> Checkpointer {
+        let resumed = match options.resume {
+            true => Self::load(&options.path, &key),
+            false => Checkpoint::default(),
+        };
+        Checkpointer {
+            options: options.clone(),
+            resumed,
+            progress: Mutex::new(Checkpoint {
+                version: CHECKPOINT_VERSION,
+                key,
+                ..Default::default()
+            }),
+            last_saved: Mutex::new(Instant::now()),
+            unsaved: Mutex::new(false),
+        }
+    }
+    /// Gets the key of an analysis of the repository at `repo_path`, at the `head` commit, with
+    /// the [`AnalysisOptions`], which is only resumed from a checkpoint of the same key
+    pub(crate) fn get_key(
+        repo_path: &str,
+        head: Option<&str>,
+        options: &AnalysisOptions,
+    ) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(repo_path.as_bytes());
+        hasher.update([0]);
+        hasher.update(head.unwrap_or_default().as_bytes());
+        hasher.update([0]);
+        hasher.update(serde_json::to_vec(options).unwrap_or_default());
+        format!("{:x}", hasher.finalize())
+    }
+    /// Gets the [`DiffedCommit`] of the commit `id` from the resumed checkpoint, if it has it,
+    /// and records it in the progress
+    pub(crate) fn resume_commit(&self, id: Oid) -> Option<DiffedCommit> {
+        let diffed = self.resumed.commits.get(&id.to_string())?.clone();
+        self.record(|progress| {
+            progress.commits.insert(id.to_string(), diffed.clone());
+        });
+        Some(diffed)
+    }
+    /// Records the commit `id` as diffed, with the paths it `changed` and `added`
+    pub(crate) fn add_commit(&self, id: Oid, changed: Option<&BTreeSet<String>>, added: &[String]) {
+        self.record(|progress| {
+            progress.commits.insert(
+                id.to_string(),
+                DiffedCommit {
+                    changed: changed.cloned(),
+                    added: added.to_vec(),
+                },
+            );
+        });
+    }
+    /// Gets the [`SourceFileInfo`] of the source file at the `relative_path` from the resumed
+    /// checkpoint, if it has it, with the warnings of scanning it, and records it in the
+    /// progress
+    pub(crate) fn resume_source_file(
+        &self,
+        relative_path: &str,
+    ) -> Option<(SourceFileInfo, Vec<AnalysisWarning>)> {
+        let scanned = self.resumed.source_files.get(relative_path)?.clone();
+        let resumed = (scanned.source_file.clone(), scanned.warnings.clone());
+        self.record(|progress| {
+            progress
+                .source_files
+                .insert(relative_path.to_string(), scanned);
+        });
+        Some(resumed)
+    }
+    /// Records the source file as scanned, with the `warnings` of scanning it
+    pub(crate) fn add_source_file(
+        &self,
+        source_file: &SourceFileInfo,
+        warnings: &[AnalysisWarning],
+    ) {
+        self.record(|progress| {
+            progress.source_files.insert(
+                source_file.relative_path.to_string(),
+                ScannedFile {
+                    source_file: source_file.clone(),
+                    warnings: warnings.to_vec(),
+                },
+            );
+        });
+    }
+    /// Removes the checkpoint file once the analysis completes, as there is nothing to resume
+    pub(crate) fn finish(&self) {
+        *self.unsaved.lock().unwrap_or_else(PoisonError::into_inner) = false;
+        if let Err(err) = fs::remove_file(&self.options.path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(
+                    "Failed to remove the checkpoint {}: {}",
+                    self.options.path.display(),
+                    err
+                );
+            }
+        }
+    }
+    /// Updates the progress, and writes it if the interval since the last checkpoint has passed
+    fn record(&self, update: impl FnOnce(&mut Checkpoint)) {
+        let mut progress = self.progress.lock().unwrap_or_else(PoisonError::into_inner);
+        update(&mut progress);
+        *self.unsaved.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        let mut last_saved = self
+            .last_saved
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if last_saved.elapsed() >= Duration::from_secs(self.options.interval_secs) {
+            self.save(&progress);
+            *last_saved = Instant::now();
+        }
+    }
+    /// Writes the `progress` to the checkpoint file, through a temporary file so that an
+    /// interrupted write doesn't leave a corrupt checkpoint; a checkpoint that can't be written
+    /// is only warned of, as the analysis can go on without it
+    fn save(&self, progress: &Checkpoint) {
+        let path = &self.options.path;
+        let temporary = path.with_extension("tmp");
+        let written = File::create(&temporary)
+            .map(BufWriter::new)
+            .map_err(SourceCodeError::for_path(&temporary))
+            .and_then(|mut file| {
+                serde_json::to_writer(&mut file, progress)
+                    .map_err(|err| SourceCodeError::SerializationError(err.into()))?;
+                file.flush().map_err(SourceCodeError::for_path(&temporary))
+            })
+            .and_then(|_| fs::rename(&temporary, path).map_err(SourceCodeError::for_path(path)));
+        match written {
+            Ok(()) => {
+                *self.unsaved.lock().unwrap_or_else(PoisonError::into_inner) = false;
+                tracing::info!(
+                    "Checkpointed {} commits and {} source files to {}",
+                    progress.commits.len(),
+                    progress.source_files.len(),
+                    path.display()
+                );
+            }
+            Err(err) => tracing::warn!("Failed to checkpoint the analysis: {}", err),
+        }
+    }
+    /// Reads the checkpoint file at `path`, if it is of the analysis with the `key`
+    fn load(path: &Path, key: &str) -> Checkpoint {
+        let checkpoint: Checkpoint = match File::open(path) {
+            Ok(file) => match serde_json::from_reader(BufReader::new(file)) {
+                Ok(checkpoint) => checkpoint,
+                Err(err) => {
+                    tracing::warn!(
+                        "Not resuming the corrupt checkpoint {}: {}",
+                        path.display(),
+                        err
+                    );
+                    return Checkpoint::default();
+                }
+            },
+            Err(err) => {
+                tracing::info!("No checkpoint to resume at {}: {}", path.display(), err);
+                return Checkpoint::default();
+            }
+        };
+        if checkpoint.version != CHECKPOINT_VERSION || checkpoint.key != key {
+            tracing::warn!(
+                "Not resuming the checkpoint {}, of another version, repository, HEAD or options",
+                path.display()
+            );
+            return Checkpoint::default();
+        }
+        tracing::info!(
+            "Resuming {} commits and {} source files from the checkpoint {}",
+            checkpoint.commits.len(),
+            checkpoint.source_files.len(),
+            path.display()
+        );
+        checkpoint
+    }
+}
+#[cfg(feature = "git")]
+impl Drop for Checkpointer {
+    fn drop(&mut self) {
+        if *self
+            .unsaved
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            self.save(&self.progress.lock().unwrap_or_else(PoisonError::into_inner));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "git"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_a_checkpoint_of_the_same_analysis() -> Result<(), SourceCodeError> {
+        let dir = tempfile::tempdir().map_err(|err| SourceCodeError::FileReadError {
+            source: err,
+            path: None,
+        })?;
+        let options = CheckpointOptions {
+            resume: true,
+            ..CheckpointOptions::new(dir.path().join("analysis.checkpoint.json"))
+        }
+        .with_interval(Duration::ZERO);
+        let id = Oid::from_str("4b825dc642cb6eb9a060e54bf8d69288fbe4904d")?;
+        let changed = BTreeSet::from(["src/lib.rs".to_string()]);
+        {
+            let checkpointer = Checkpointer::new(&options, "key".to_string());
+            checkpointer.add_commit(id, Some(&changed), &[]);
+        }
+        assert!(options.path.exists());
+
+        let checkpointer = Checkpointer::new(&options, "key".to_string());
+        let diffed = checkpointer.resume_commit(id);
+        assert_eq!(
+            diffed,
+            Some(DiffedCommit {
+                changed: Some(changed),
+                added: Vec::new(),
+            })
+        );
+        // A checkpoint of another repository, HEAD or options isn't resumed
+        let other = Checkpointer::new(&options, "other".to_string());
+        assert_eq!(other.resume_commit(id), None);
+
+        checkpointer.finish();
+        assert!(!options.path.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Splitting source file contents into chunks that fit an embedding model's context
+#[cfg(feature = "tree-sitter")]
+mod syntax;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "tree-sitter")]
+use syntax::chunk_by_syntax;
+
+/// How source file contents are split into chunks
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// Fixed windows of lines, overlapping by `overlap_lines`
+    #[default]
+    LineWindow,
+    /// One chunk per function, method or type definition, parsed with tree-sitter; falls back
+    /// to [`ChunkStrategy::LineWindow`] for unsupported languages, or without the
+    /// `tree-sitter` feature
+    Syntax,
+}
+
+/// The options for chunking source file contents
+///
+/// #Fields:
+/// * `strategy` - The [`ChunkStrategy`]
+/// * `window_lines` - The maximum number of lines in a chunk
+/// * `overlap_lines` - The number of lines shared by consecutive line-window chunks
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChunkingOptions {
+    pub strategy: ChunkStrategy,
+    pub window_lines: usize,
+    pub overlap_lines: usize,
+}
+impl Default for ChunkingOptions {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkStrategy::LineWindow,
+            window_lines: 60,
+            overlap_lines: 10,
+        }
+    }
+}
+
+/// A chunk of a source file, with its position in the file
+///
+/// #Fields:
+/// * `index` - The position of the chunk in the file's chunks
+/// * `start_line` - The first line of the chunk (1-based)
+/// * `end_line` - The last line of the chunk (1-based, inclusive)
+/// * `kind` - The syntax node kind for syntax chunks, e.g., 'function_item'
+/// * `contents` - The text of the chunk
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SourceChunk {
+    pub index: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: Option<String>,
+    pub contents: String,
+}
+
+/// Splits the `contents` of a source file in `language` (the [`crate::source::LanguageType`]
+/// name) into [`SourceChunk`]s, as configured by the [`ChunkingOptions`]
+pub fn chunk_contents(
+    contents: &str,
+    language: &str,
+    options: &ChunkingOptions,
+) -> Vec<SourceChunk> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut chunks: Vec<SourceChunk> = match options.strategy {
+        ChunkStrategy::Syntax => chunk_by_syntax(contents, language, &lines, options),
+        ChunkStrategy::LineWindow => None,
+    }
+    .unwrap_or_else(|| chunk_by_lines(&lines, 0, lines.len(), options));
+    for (index, chunk) in chunks.iter_mut().enumerate() {
+        chunk.index = index;
+    }
+    chunks
+}
+
+/// Splits the lines `start..end` (0-based, exclusive) into overlapping windows of lines
+pub(crate) fn chunk_by_lines(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    options: &ChunkingOptions,
+) -> Vec<SourceChunk> {
+    let window_lines = options.window_lines.max(1);
+    let step = window_lines.saturating_sub(options.overlap_lines).max(1);
+    let mut chunks: Vec<SourceChunk> = Vec::new();
+    let mut window_start = start;
+    while window_start < end {
+        let window_end = (window_start + window_lines).min(end);
+        let contents = lines[window_start..window_end].join("\n");
+        if !contents.trim().is_empty() {
+            chunks.push(SourceChunk {
+                index: 0,
+                start_line: window_start + 1,
+                end_line: window_end,
+                kind: None,
+                contents,
+            });
+        }
+        if window_end == end {
+            break;
+        }
+        window_start += step;
+    }
+    chunks
+}
+
+#[cfg(not(feature = "tree-sitter"))]
+fn chunk_by_syntax(
+    _contents: &str,
+    _language: &str,
+    _lines: &[&str],
+    _options: &ChunkingOptions,
+) -> Option<Vec<SourceChunk>> {
+    None
+}
@@ -0,0 +1,141 @@
+use tree_sitter::{Language, Node, Parser};
+
+use crate::chunk::{chunk_by_lines, ChunkingOptions, SourceChunk};
+
+/// Gets the tree-sitter grammar for a language, and the node kinds that are chunked on their own
+fn get_grammar(language: &str) -> Option<(Language, &'static [&'static str])> {
+    match language {
+        "Rust" => Some((
+            tree_sitter_rust::language(),
+            &[
+                "function_item",
+                "impl_item",
+                "trait_item",
+                "struct_item",
+                "enum_item",
+                "mod_item",
+                "macro_definition",
+            ],
+        )),
+        "Python" => Some((
+            tree_sitter_python::language(),
+            &["function_definition", "class_definition"],
+        )),
+        "JavaScript" | "JSX" => Some((
+            tree_sitter_javascript::language(),
+            &[
+                "function_declaration",
+                "generator_function_declaration",
+                "class_declaration",
+                "method_definition",
+            ],
+        )),
+        "Go" => Some((
+            tree_sitter_go::language(),
+            &[
+                "function_declaration",
+                "method_declaration",
+                "type_declaration",
+            ],
+        )),
+        _ => None,
+    }
+}
+
+/// Chunks the `contents` on its definitions (functions, types, etc.), with the lines between
+/// definitions chunked by line window. Definitions longer than the window are split on their
+/// nested definitions, or else by line window.
+///
+/// #Returns:
+/// * The chunks ordered by position, or `None` if the language has no grammar or fails to parse
+pub(crate) fn chunk_by_syntax(
+    contents: &str,
+    language: &str,
+    lines: &[&str],
+    options: &ChunkingOptions,
+) -> Option<Vec<SourceChunk>> {
+    let (grammar, kinds) = get_grammar(language)?;
+    let mut parser = Parser::new();
+    parser.set_language(grammar).ok()?;
+    let tree = parser.parse(contents, None)?;
+
+    let mut chunks: Vec<SourceChunk> = Vec::new();
+    collect_definitions(tree.root_node(), kinds, lines, options, &mut chunks);
+
+    // Chunk the gaps (imports, top-level statements, etc.) between definitions
+    let mut covered: Vec<(usize, usize)> = chunks
+        .iter()
+        .map(|chunk| (chunk.start_line - 1, chunk.end_line))
+        .collect();
+    covered.sort();
+    let mut gaps: Vec<SourceChunk> = Vec::new();
+    let mut position = 0;
+    for (start, end) in covered.into_iter().chain([(lines.len(), lines.len())]) {
+        if start > position {
+            gaps.extend(chunk_by_lines(lines, position, start, options));
+        }
+        position = position.max(end);
+    }
+    chunks.extend(gaps);
+    chunks.sort_by_key(|chunk| chunk.start_line);
+    Some(chunks)
+}
+
+fn collect_definitions(
+    node: Node<'_>,
+    kinds: &[&str],
+    lines: &[&str],
+    options: &ChunkingOptions,
+    chunks: &mut Vec<SourceChunk>,
+) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if !kinds.contains(&child.kind()) {
+            // Definitions may be nested, e.g., in exports, decorators or class bodies
+            collect_definitions(child, kinds, lines, options, chunks);
+            continue;
+        }
+        let start = get_leading_row(child);
+        let end = (child.end_position().row + 1).min(lines.len());
+        if end - start <= options.window_lines {
+            chunks.push(SourceChunk {
+                index: 0,
+                start_line: start + 1,
+                end_line: end,
+                kind: Some(child.kind().to_string()),
+                contents: lines[start..end].join("\n"),
+            });
+            continue;
+        }
+        let num_chunks = chunks.len();
+        collect_definitions(child, kinds, lines, options, chunks);
+        if chunks.len() == num_chunks {
+            chunks.extend(
+                chunk_by_lines(lines, start, end, options)
+                    .into_iter()
+                    .map(|chunk| SourceChunk {
+                        kind: Some(child.kind().to_string()),
+                        ..chunk
+                    }),
+            );
+        }
+    }
+}
+
+/// Gets the first row of a definition, including the comments and attributes directly above it
+fn get_leading_row(node: Node<'_>) -> usize {
+    let mut row = node.start_position().row;
+    let mut sibling = node.prev_sibling();
+    while let Some(previous) = sibling {
+        let leading = matches!(
+            previous.kind(),
+            "comment" | "line_comment" | "block_comment" | "attribute_item"
+        );
+        if !leading || previous.end_position().row + 1 < row {
+            break;
+        }
+        row = previous.start_position().row;
+        sibling = previous.prev_sibling();
+    }
+    row
+}
@@ -0,0 +1,326 @@
+//! Function-level churn: attributing the lines each commit changed in a source file to the
+//! functions and methods they are in, so that the most frequently modified functions can be
+//! listed, rather than only the files, e.g., to prioritise refactoring in large files
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::{Blob, DiffOptions, Oid, Patch, Repository};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::{collections::HashMap, path::Path};
+
+use crate::symbols::SymbolKind;
+#[cfg(feature = "git")]
+use crate::{
+    cancel::{CancellationToken, PartialResults},
+    errors::SourceCodeError,
+    graph::CommitGraph,
+    observer::AnalysisStage,
+    source::SourceFileInfo,
+    symbols::{self, Symbol},
+};
+
+/// The options for the function-level churn analysis
+///
+/// #Fields:
+/// * `max_commits_per_file` - The most recent commits that changed a file which are diffed for
+///   its functions' churn, as each is diffed and parsed
+/// * `max_changeset_size` - Commits that change more source files than this, e.g., reformats or
+///   mass renames, are skipped, as they would count as changes to every function
+/// * `max_functions` - The number of most changed [`FunctionChurn`]s kept in the
+///   [`FunctionChurnReport`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct FunctionChurnOptions {
+    pub max_commits_per_file: usize,
+    pub max_changeset_size: usize,
+    pub max_functions: usize,
+}
+impl Default for FunctionChurnOptions {
+    fn default() -> Self {
+        Self {
+            max_commits_per_file: 500,
+            max_changeset_size: 30,
+            max_functions: 50,
+        }
+    }
+}
+
+/// The churn of a function or method, as of HEAD
+///
+/// #Fields:
+/// * `path` - The relative path of the source file the function is in
+/// * `name` - The qualified name of the function, see
+///   [`crate::symbols::Symbol::get_qualified_name`]
+/// * `kind` - The [`SymbolKind`] of the function, i.e., a function or method
+/// * `start_line` - The first line of the function at HEAD (1-based)
+/// * `end_line` - The last line of the function at HEAD (1-based, inclusive)
+/// * `num_commits` - The number of commits that changed lines of the function, not counting the
+///   commit that added its file
+/// * `lines_added` - The lines added to the function by those commits
+/// * `lines_deleted` - The lines deleted from the function by those commits
+/// * `last_changed` - The time of the latest commit that changed the function
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FunctionChurn {
+    pub path: String,
+    pub name: String,
+    pub kind: SymbolKind,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub num_commits: u32,
+    pub lines_added: u64,
+    pub lines_deleted: u64,
+    pub last_changed: Option<DateTime<Utc>>,
+}
+
+/// The functions and methods of a repository's source files that change most often, from the
+/// diffs of the commits that changed each file, with each changed line attributed to the
+/// innermost function it is in, parsed from that version of the file with tree-sitter; only the
+/// functions that exist at HEAD are reported, and renamed functions start again under their new
+/// name. Without the `tree-sitter` feature, or for languages without a grammar, see
+/// [`crate::symbols`], no functions are found.
+///
+/// #Fields:
+/// * `num_files` - The number of source files whose history was diffed
+/// * `num_functions` - The number of functions that changed, before they are limited to the
+///   `max_functions` of the [`FunctionChurnOptions`]
+/// * `functions` - The [`FunctionChurn`]s, by the number of commits and then lines changed
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FunctionChurnReport {
+    pub num_files: usize,
+    pub num_functions: usize,
+    pub functions: Vec<FunctionChurn>,
+}
+impl FunctionChurnReport {
+    /// Gets the [`FunctionChurnReport`] for the `source_files` of the repository at `repo_path`
+    /// from the commits of the [`CommitGraph`] that changed them, as counted by its
+    /// [`crate::source::MergeStrategy`]; a merge commit is diffed against its first parent
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        repo_path: &str,
+        graph: &CommitGraph,
+        source_files: &[SourceFileInfo],
+        options: &FunctionChurnOptions,
+        cancellation: &CancellationToken,
+    ) -> Result<FunctionChurnReport, SourceCodeError> {
+        let mut report = FunctionChurnReport::default();
+        let Some(head) = graph.head() else {
+            return Ok(report);
+        };
+        let repo = Repository::open(repo_path)?;
+        let changes = graph.changes()?;
+        let head = repo.find_commit(head)?;
+        let mut functions: Vec<FunctionChurn> = vec![];
+        for source_file in source_files {
+            cancellation.check(AnalysisStage::FunctionChurn, || {
+                PartialResults::SourceFiles(vec![])
+            })?;
+            let Some(language) = &source_file.language else {
+                continue;
+            };
+            let path = source_file.relative_path.as_str();
+            let mut parser = FileParser::new(&language.name);
+            let Some(current) = get_blob(&repo, head.id(), path)
+                .and_then(|blob| parser.get_functions(&blob).cloned())
+                .filter(|current| !current.is_empty())
+            else {
+                continue;
+            };
+            report.num_files += 1;
+
+            let mut churn: HashMap<String, ChurnCounts> = HashMap::new();
+            for &index in changes
+                .commits_changing(path)
+                .iter()
+                .take(options.max_commits_per_file)
+            {
+                if changes
+                    .changed_paths(index)
+                    .is_some_and(|changed| changed.len() > options.max_changeset_size)
+                {
+                    continue;
+                }
+                let node = &graph.commits()[index];
+                let Some(&parent) = node.parents.first() else {
+                    continue;
+                };
+                let changed = match diff_functions(&repo, &mut parser, node.id, parent, path) {
+                    Ok(changed) => changed,
+                    Err(err) => {
+                        // E.g., the parent is beyond the boundary of a shallow clone
+                        tracing::debug!("Could not diff {} at {}: {}", path, node.id, err);
+                        continue;
+                    }
+                };
+                for (name, (added, deleted)) in changed {
+                    let counts = churn.entry(name).or_default();
+                    counts.num_commits += 1;
+                    counts.lines_added += added;
+                    counts.lines_deleted += deleted;
+                    counts.last_changed = counts.last_changed.max(node.commit_date());
+                }
+            }
+            for symbol in current {
+                let name = symbol.get_qualified_name();
+                if let Some(counts) = churn.remove(&name) {
+                    functions.push(FunctionChurn {
+                        path: path.to_string(),
+                        name,
+                        kind: symbol.kind,
+                        start_line: symbol.start_line,
+                        end_line: symbol.end_line,
+                        num_commits: counts.num_commits,
+                        lines_added: counts.lines_added,
+                        lines_deleted: counts.lines_deleted,
+                        last_changed: counts.last_changed,
+                    });
+                }
+            }
+        }
+        functions.sort_by(|a, b| {
+            b.num_commits
+                .cmp(&a.num_commits)
+                .then_with(|| {
+                    (b.lines_added + b.lines_deleted).cmp(&(a.lines_added + a.lines_deleted))
+                })
+                .then_with(|| (&a.path, &a.name).cmp(&(&b.path, &b.name)))
+        });
+        report.num_functions = functions.len();
+        functions.truncate(options.max_functions);
+        report.functions = functions;
+        Ok(report)
+    }
+    /// Gets the [`FunctionChurn`]s of the file at `relative_path`
+    pub fn get_functions_in<'a>(
+        &'a self,
+        relative_path: &'a str,
+    ) -> impl Iterator<Item = &'a FunctionChurn> + 'a {
+        self.functions
+            .iter()
+            .filter(move |function| function.path == relative_path)
+    }
+}
+
+/// The changes to a function over the commits diffed
+#[cfg(feature = "git")]
+#[derive(Default)]
+struct ChurnCounts {
+    num_commits: u32,
+    lines_added: u64,
+    lines_deleted: u64,
+    last_changed: Option<DateTime<Utc>>,
+}
+
+/// Parses the versions of a source file for their functions, once per blob
+#[cfg(feature = "git")]
+struct FileParser<'a> {
+    language: &'a str,
+    functions: HashMap<Oid, Vec<Symbol>>,
+}
+#[cfg(feature = "git")]
+impl<'a> FileParser<'a> {
+    fn new(language: &'a str) -> Self {
+        Self {
+            language,
+            functions: HashMap::new(),
+        }
+    }
+    /// Gets the functions and methods of the version of the file in the `blob`, or `None` if it
+    /// isn't UTF-8, or its language has no grammar
+    fn get_functions(&mut self, blob: &Blob<'_>) -> Option<&Vec<Symbol>> {
+        if !self.functions.contains_key(&blob.id()) {
+            let contents = std::str::from_utf8(blob.content()).ok()?;
+            let functions = symbols::extract_symbols(contents, self.language)?
+                .into_iter()
+                .filter(|symbol| matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method))
+                .collect();
+            self.functions.insert(blob.id(), functions);
+        }
+        self.functions.get(&blob.id())
+    }
+}
+
+/// Gets the blob of the file at `path` in the tree of the commit `id`, or `None` if the commit
+/// doesn't have the file
+#[cfg(feature = "git")]
+fn get_blob<'r>(repo: &'r Repository, id: Oid, path: &str) -> Option<Blob<'r>> {
+    let entry = repo
+        .find_commit(id)
+        .ok()?
+        .tree()
+        .ok()?
+        .get_path(Path::new(path))
+        .ok()?;
+    repo.find_blob(entry.id()).ok()
+}
+
+/// Diffs the file at `path` in the commit `id` against its `parent`, attributing each line
+/// added to the innermost function it is in after the commit, and each line deleted to that it
+/// was in before
+///
+/// #Returns:
+/// * The lines added and deleted of each function changed, by qualified name; none if the
+///   commit added the file
+#[cfg(feature = "git")]
+fn diff_functions(
+    repo: &Repository,
+    parser: &mut FileParser<'_>,
+    id: Oid,
+    parent: Oid,
+    path: &str,
+) -> Result<HashMap<String, (u64, u64)>, git2::Error> {
+    let mut changed: HashMap<String, (u64, u64)> = HashMap::new();
+    // The parent is looked up first, so a missing one is an error rather than an added file
+    repo.find_commit(parent)?;
+    let (Some(old), Some(new)) = (get_blob(repo, parent, path), get_blob(repo, id, path)) else {
+        return Ok(changed);
+    };
+    if old.id() == new.id() {
+        return Ok(changed);
+    }
+    let (Some(old_functions), Some(new_functions)) = (
+        parser.get_functions(&old).cloned(),
+        parser.get_functions(&new).cloned(),
+    ) else {
+        return Ok(changed);
+    };
+    let patch = Patch::from_blobs(
+        &old,
+        None,
+        &new,
+        None,
+        Some(DiffOptions::new().context_lines(0)),
+    )?;
+    for hunk in 0..patch.num_hunks() {
+        for index in 0..patch.num_lines_in_hunk(hunk)? {
+            let line = patch.line_in_hunk(hunk, index)?;
+            let (functions, line_number, added) = match line.origin() {
+                '+' => (&new_functions, line.new_lineno(), true),
+                '-' => (&old_functions, line.old_lineno(), false),
+                _ => continue,
+            };
+            let Some(function) =
+                line_number.and_then(|line_number| get_innermost(functions, line_number as usize))
+            else {
+                continue;
+            };
+            let counts = changed.entry(function.get_qualified_name()).or_default();
+            match added {
+                true => counts.0 += 1,
+                false => counts.1 += 1,
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Gets the innermost of the `functions` whose definition spans the `line` (1-based)
+#[cfg(feature = "git")]
+fn get_innermost(functions: &[Symbol], line: usize) -> Option<&Symbol> {
+    functions
+        .iter()
+        .filter(|function| function.contains(line))
+        .min_by_key(|function| function.end_line - function.start_line)
+}
@@ -0,0 +1,543 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::collections::{HashMap, HashSet};
+use std::{collections::BTreeMap, fs, path::Path};
+
+#[cfg(feature = "git")]
+use crate::{
+    cancel::CancellationToken,
+    errors::SourceCodeError,
+    graph::CommitGraph,
+    history::{HistoryMode, HistorySampling},
+    identity::IdentityOptions,
+    observer::NoopObserver,
+    ratio,
+    source::{MergeStrategy, SourceFileInfo},
+};
+use crate::{identity::Author, ratio::Ratio};
+
+/// The locations of a CODEOWNERS file that GitHub and GitLab read, in the order they are looked
+/// for
+const LOCATIONS: [&str; 4] = [
+    ".github/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+];
+
+/// A rule of a CODEOWNERS file
+///
+/// #Fields:
+/// * `pattern` - The gitignore-style pattern of the paths the rule applies to, e.g., '/docs/'
+/// * `owners` - The owners of the matching paths, e.g., '@user', '@org/team' or an email
+///   address; empty if the paths are explicitly unowned
+/// * `section` - The GitLab section of the rule, e.g., 'Documentation', if any
+/// * `line` - The line number (from 1) of the rule in the file
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeOwnersRule {
+    pub pattern: String,
+    pub owners: Vec<String>,
+    pub section: Option<String>,
+    pub line: usize,
+}
+
+/// The rules of a CODEOWNERS file, in the GitHub or GitLab syntax
+///
+/// As on GitHub, the last matching rule applies. GitLab sections are each matched that way,
+/// and the owners of the rules that match in each section are combined.
+#[derive(Clone, Debug)]
+pub struct CodeOwners {
+    rules: Vec<(CodeOwnersRule, Regex)>,
+}
+impl CodeOwners {
+    /// Reads the CODEOWNERS file of the repository at `repo_path`, from the first of its
+    /// locations that exists
+    ///
+    /// #Returns:
+    /// * The relative path of the file and its [`CodeOwners`], or `None` if the repository
+    ///   doesn't have one
+    pub fn load(repo_path: &str) -> Option<(String, CodeOwners)> {
+        LOCATIONS.iter().find_map(|location| {
+            let contents = fs::read_to_string(Path::new(repo_path).join(location)).ok()?;
+            Some((location.to_string(), Self::parse(&contents)))
+        })
+    }
+    /// Parses the `contents` of a CODEOWNERS file; lines that can't be parsed are skipped with
+    /// a warning
+    pub fn parse(contents: &str) -> CodeOwners {
+        let mut rules = Vec::new();
+        let mut section: Option<String> = None;
+        let mut default_owners: Vec<String> = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // A GitLab section, e.g., '^[Documentation][2] @docs-team', with its default owners
+            if let Some(header) = line.strip_prefix('^').unwrap_or(line).strip_prefix('[') {
+                let Some((name, rest)) = header.split_once(']') else {
+                    tracing::warn!("Skipping CODEOWNERS line {}: {}", index + 1, line);
+                    continue;
+                };
+                let rest = match rest.strip_prefix('[') {
+                    Some(approvals) => approvals.split_once(']').map_or("", |(_, rest)| rest),
+                    None => rest,
+                };
+                section = Some(name.trim().to_string());
+                default_owners = split_tokens(rest);
+                continue;
+            }
+            let mut tokens = split_tokens(line).into_iter();
+            let Some(pattern) = tokens.next() else {
+                continue;
+            };
+            let mut owners: Vec<String> = tokens.collect();
+            if owners.is_empty() && section.is_some() {
+                owners = default_owners.clone();
+            }
+            match pattern_to_regex(&pattern) {
+                Ok(regex) => rules.push((
+                    CodeOwnersRule {
+                        pattern,
+                        owners,
+                        section: section.clone(),
+                        line: index + 1,
+                    },
+                    regex,
+                )),
+                Err(err) => tracing::warn!("Skipping CODEOWNERS line {}: {}", index + 1, err),
+            }
+        }
+        CodeOwners { rules }
+    }
+    /// Gets the [`CodeOwnersRule`]s
+    pub fn rules(&self) -> impl Iterator<Item = &CodeOwnersRule> {
+        self.rules.iter().map(|(rule, _)| rule)
+    }
+    /// Gets the [`CodeOwnersRule`]s that apply to the file at `relative_path`, i.e., the last
+    /// matching rule of each section
+    pub fn matching_rules(&self, relative_path: &str) -> Vec<&CodeOwnersRule> {
+        let mut by_section: BTreeMap<Option<&str>, &CodeOwnersRule> = BTreeMap::new();
+        for (rule, regex) in &self.rules {
+            if regex.is_match(relative_path) {
+                by_section.insert(rule.section.as_deref(), rule);
+            }
+        }
+        let mut rules: Vec<&CodeOwnersRule> = by_section.into_values().collect();
+        rules.sort_by_key(|rule| rule.line);
+        rules
+    }
+    /// Gets the owners of the file at `relative_path`, empty if it has none
+    pub fn owners_of(&self, relative_path: &str) -> Vec<String> {
+        let mut owners: Vec<String> = Vec::new();
+        for rule in self.matching_rules(relative_path) {
+            for owner in &rule.owners {
+                if !owners.contains(owner) {
+                    owners.push(owner.clone());
+                }
+            }
+        }
+        owners
+    }
+}
+
+/// Splits a CODEOWNERS line into its whitespace-separated tokens, up to a comment, keeping
+/// escaped spaces and '#'s
+fn split_tokens(line: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    token.push(escaped);
+                }
+            }
+            '#' if token.is_empty() => break,
+            c if c.is_whitespace() => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Converts a gitignore-style CODEOWNERS `pattern` into a [`Regex`] over relative paths
+///
+/// A pattern with a '/' before its end is anchored at the root, and otherwise matches at any
+/// depth. A pattern ending with '/' matches everything under a directory, as does one whose
+/// last segment has no wildcard; e.g., 'docs/*' only matches the files directly in 'docs'.
+fn pattern_to_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let is_directory = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let is_anchored = trimmed.contains('/');
+    let trimmed = trimmed.trim_start_matches('/');
+    let last_segment = trimmed.rsplit('/').next().unwrap_or(trimmed);
+
+    let mut regex = String::from(match is_anchored {
+        true => "^",
+        false => "^(?:.*/)?",
+    });
+    let mut chars = trimmed.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                match chars.peek() {
+                    Some('/') => {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    }
+                    _ => regex.push_str(".*"),
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str(match (is_directory, last_segment.contains(['*', '?'])) {
+        (true, _) => "/.*$",
+        (false, false) => "(?:/.*)?$",
+        (false, true) => "$",
+    });
+    Regex::new(&regex)
+}
+
+/// The declared and commit-based ownership of a source file
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `owners` - The owners declared in the CODEOWNERS file, empty if the file isn't covered
+/// * `rule_lines` - The line numbers of the [`CodeOwnersRule`]s that apply to the file
+/// * `top_contributor` - The [`Author`] of the most commits that changed the file, if any
+/// * `top_contributor_share` - The [`Ratio`] (0.0 - 1.0) of the file's commits by the
+///   `top_contributor`
+/// * `owner_matches` - Whether the `top_contributor` is one of the declared `owners`, or `None`
+///   if it can't be told, e.g., the file has no owners or no commits, or is only owned by teams
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileOwnership {
+    pub path: String,
+    pub owners: Vec<String>,
+    pub rule_lines: Vec<usize>,
+    pub top_contributor: Option<Author>,
+    pub top_contributor_share: Option<Ratio>,
+    pub owner_matches: Option<bool>,
+}
+
+/// How the ownership declared in a repository's CODEOWNERS file covers its source files, and
+/// how it compares with the ownership by commits
+///
+/// A git author is taken to be a declared owner if the owner is one of their email addresses,
+/// or if the owner's username, e.g., '@jane-doe', equals their name, e.g., 'Jane Doe', when
+/// both are lowercased and stripped of anything but letters and digits. Teams, e.g.,
+/// '@org/team', can't be resolved to authors.
+///
+/// #Fields:
+/// * `path` - The relative path of the CODEOWNERS file
+/// * `num_rules` - The number of rules in the file
+/// * `num_files` - The number of source files
+/// * `num_owned_files` - The number of source files with at least one owner
+/// * `coverage` - The percentage of the source files with at least one owner, or `None` if
+///   there are no source files
+/// * `num_mismatches` - The number of owned files whose top contributor isn't an owner
+/// * `files` - The [`FileOwnership`] of each source file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnershipReport {
+    pub path: String,
+    pub num_rules: usize,
+    pub num_files: usize,
+    pub num_owned_files: usize,
+    pub coverage: Option<f32>,
+    pub num_mismatches: usize,
+    pub files: Vec<FileOwnership>,
+}
+impl OwnershipReport {
+    /// Gets the [`OwnershipReport`] for the `source_files` of the repository at `repo_path`,
+    /// with the commit-based ownership from the commits reachable from HEAD
+    ///
+    /// #Returns:
+    /// * The [`OwnershipReport`], or `None` if the repository doesn't have a CODEOWNERS file
+    #[cfg(feature = "git")]
+    pub fn new(
+        repo_path: &str,
+        source_files: &[SourceFileInfo],
+        merge_strategy: MergeStrategy,
+    ) -> Result<Option<OwnershipReport>, SourceCodeError> {
+        Self::new_with_cancellation(
+            repo_path,
+            source_files,
+            merge_strategy,
+            &CancellationToken::new(),
+        )
+    }
+    /// Gets the [`OwnershipReport`] as [`OwnershipReport::new`] does, stopping early if the
+    /// [`CancellationToken`] is cancelled
+    #[cfg(feature = "git")]
+    pub fn new_with_cancellation(
+        repo_path: &str,
+        source_files: &[SourceFileInfo],
+        merge_strategy: MergeStrategy,
+        cancellation: &CancellationToken,
+    ) -> Result<Option<OwnershipReport>, SourceCodeError> {
+        if CodeOwners::load(repo_path).is_none() {
+            return Ok(None);
+        }
+        let graph = CommitGraph::new(
+            repo_path,
+            merge_strategy,
+            HistoryMode::default(),
+            HistorySampling::default(),
+            &IdentityOptions::default(),
+            &NoopObserver,
+            cancellation,
+        )?;
+        Self::from_graph(repo_path, &graph, source_files)
+    }
+    /// Gets the [`OwnershipReport`] for the `source_files` of the repository at `repo_path`,
+    /// with the commit-based ownership from the commits of the [`CommitGraph`]
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        repo_path: &str,
+        graph: &CommitGraph,
+        source_files: &[SourceFileInfo],
+    ) -> Result<Option<OwnershipReport>, SourceCodeError> {
+        let Some((path, codeowners)) = CodeOwners::load(repo_path) else {
+            return Ok(None);
+        };
+        let changes = graph.changes()?;
+        let files: HashMap<&str, usize> = source_files
+            .iter()
+            .enumerate()
+            .map(|(index, sfi)| (sfi.relative_path.as_str(), index))
+            .collect();
+
+        // The number of commits changing each file, per author's canonical id, and the last
+        // author and email addresses of each
+        let mut file_authors: HashMap<usize, HashMap<&str, i32>> = HashMap::new();
+        let mut authors: HashMap<&str, (&Author, HashSet<String>)> = HashMap::new();
+        for (index, commit) in graph.commits().iter().enumerate() {
+            let Some(changed) = changes.changed_paths(index) else {
+                continue;
+            };
+            let id = commit.author.canonical_id.as_str();
+            let (_, emails) = authors
+                .entry(id)
+                .or_insert_with(|| (&commit.author, HashSet::new()));
+            if let Some(email) = &commit.author.email {
+                emails.insert(email.to_lowercase());
+            }
+            for file in changed.iter().filter_map(|path| files.get(path.as_str())) {
+                *file_authors
+                    .entry(*file)
+                    .or_default()
+                    .entry(id)
+                    .or_default() += 1;
+            }
+        }
+
+        let files: Vec<FileOwnership> = source_files
+            .iter()
+            .enumerate()
+            .map(|(index, source_file)| {
+                let rules = codeowners.matching_rules(&source_file.relative_path);
+                let owners = codeowners.owners_of(&source_file.relative_path);
+                let top = file_authors.get(&index).and_then(|commits| {
+                    let total: i32 = commits.values().sum();
+                    commits
+                        .iter()
+                        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                        .map(|(id, commits)| (&authors[id], *commits, total))
+                });
+                let owner_matches = top
+                    .and_then(|((author, emails), _, _)| is_owner(&owners, &author.name, emails));
+                FileOwnership {
+                    path: source_file.relative_path.to_string(),
+                    owners,
+                    rule_lines: rules.iter().map(|rule| rule.line).collect(),
+                    top_contributor_share: top
+                        .as_ref()
+                        .and_then(|(_, commits, total)| Ratio::of(*commits as f64, *total as f64)),
+                    top_contributor: top.map(|((author, _), _, _)| (*author).clone()),
+                    owner_matches,
+                }
+            })
+            .collect();
+
+        let num_owned_files = files.iter().filter(|file| !file.owners.is_empty()).count();
+        Ok(Some(OwnershipReport {
+            path,
+            num_rules: codeowners.rules.len(),
+            num_files: files.len(),
+            num_owned_files,
+            coverage: ratio::percentage(num_owned_files as f64, files.len() as f64),
+            num_mismatches: files
+                .iter()
+                .filter(|file| file.owner_matches == Some(false))
+                .count(),
+            files,
+        }))
+    }
+    /// Gets the [`FileOwnership`]s of the files without a declared owner
+    pub fn unowned_files(&self) -> impl Iterator<Item = &FileOwnership> {
+        self.files.iter().filter(|file| file.owners.is_empty())
+    }
+    /// Gets the [`FileOwnership`]s of the owned files whose top contributor isn't an owner
+    pub fn mismatches(&self) -> impl Iterator<Item = &FileOwnership> {
+        self.files
+            .iter()
+            .filter(|file| file.owner_matches == Some(false))
+    }
+}
+
+/// Whether the git author `name`, with the `emails`, is one of the `owners`, or `None` if it
+/// can't be told, i.e., none of the owners are users or email addresses
+#[cfg(feature = "git")]
+fn is_owner(owners: &[String], name: &str, emails: &HashSet<String>) -> Option<bool> {
+    let normalise = |value: &str| -> String {
+        value
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let name = normalise(name);
+    let mut can_tell = false;
+    for owner in owners {
+        match owner.strip_prefix('@') {
+            // A team, e.g., '@org/team'
+            Some(username) if username.contains('/') => {}
+            Some(username) => {
+                can_tell = true;
+                if !name.is_empty() && normalise(username) == name {
+                    return Some(true);
+                }
+            }
+            None => {
+                can_tell = true;
+                if emails.contains(&owner.to_lowercase()) {
+                    return Some(true);
+                }
+            }
+        }
+    }
+    can_tell.then_some(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "git")]
+    use crate::{options::AnalysisOptions, testing::FixtureRepo};
+
+    const CODEOWNERS: &str = include_str!("../tests/fixtures/codeowners/CODEOWNERS");
+
+    #[test]
+    fn parses_the_rules_with_their_sections_and_lines() {
+        let codeowners = CodeOwners::parse(CODEOWNERS);
+        let rules: Vec<(&str, Option<&str>, usize)> = codeowners
+            .rules()
+            .map(|rule| (rule.pattern.as_str(), rule.section.as_deref(), rule.line))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("*", None, 2),
+                ("/docs/", None, 5),
+                ("config/*", None, 7),
+                ("**/logs", None, 9),
+                ("My Notes.md", None, 11),
+                ("/vendor/", None, 13),
+                ("/src/server/", Some("Backend"), 16),
+                ("/src/server/legacy.rs", Some("Backend"), 17),
+                ("*.ts", Some("Frontend"), 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_the_last_rule_of_each_section() {
+        let codeowners = CodeOwners::parse(CODEOWNERS);
+        let cases: [(&str, &[&str]); 12] = [
+            ("README.md", &["@org/everyone"]),
+            ("docs/guide.md", &["@docs-team"]),
+            ("src/docs/guide.md", &["@org/everyone"]),
+            ("config/app.toml", &["@ops"]),
+            ("config/prod/app.toml", &["@org/everyone"]),
+            ("services/api/logs/today.log", &["@ops"]),
+            ("notes/My Notes.md", &["@alice"]),
+            ("vendor/lib/index.js", &[]),
+            ("src/server/main.rs", &["@org/everyone", "@backend-team"]),
+            ("src/server/legacy.rs", &["@org/everyone", "@legacy-owner"]),
+            ("web/app.ts", &["@org/everyone", "@frontend-team"]),
+            (
+                "src/server/client.ts",
+                &["@org/everyone", "@backend-team", "@frontend-team"],
+            ),
+        ];
+        for (path, owners) in cases {
+            assert_eq!(codeowners.owners_of(path), owners, "the owners of {}", path);
+        }
+        let lines: Vec<usize> = codeowners
+            .matching_rules("src/server/legacy.rs")
+            .iter()
+            .map(|rule| rule.line)
+            .collect();
+        assert_eq!(lines, vec![2, 17]);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let codeowners = CodeOwners::parse("# Owners\n\n   \n*.rs @rustacean # comment\n");
+        let rules: Vec<&CodeOwnersRule> = codeowners.rules().collect();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].owners, vec!["@rustacean"]);
+        assert_eq!(rules[0].line, 4);
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn reports_the_files_whose_top_contributor_isnt_an_owner() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("README.md", "# Fixture\n")
+            .commit("Add a README")?
+            .with_file(".github/CODEOWNERS", "/src/ @alice\n")
+            .with_file("src/lib.rs", "pub fn one() -> i32 {\n    1\n}\n")
+            .commit("Add the library")?
+            .with_author("Bob", "bob@example.com")
+            .with_file("src/main.rs", "fn main() {}\n")
+            .commit("Add the binary")?;
+        let repository_info = fixture.analyse(&AnalysisOptions::default())?;
+        let ownership = repository_info
+            .ownership
+            .expect("the fixture has a CODEOWNERS file");
+        assert_eq!(ownership.path, ".github/CODEOWNERS");
+        assert_eq!(ownership.num_rules, 1);
+        assert_eq!(ownership.num_owned_files, 2);
+        let owned: Vec<(&str, Option<bool>)> = ownership
+            .files
+            .iter()
+            .filter(|file| !file.owners.is_empty())
+            .map(|file| (file.path.as_str(), file.owner_matches))
+            .collect();
+        assert_eq!(
+            owned,
+            vec![("src/lib.rs", Some(true)), ("src/main.rs", Some(false))]
+        );
+        assert_eq!(ownership.num_mismatches, 1);
+        assert!(ownership
+            .unowned_files()
+            .any(|file| file.path == "README.md"));
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+//! A lower-level API over the raw commits of a repository, for tools that need the author,
+//! message and changed files of each commit rather than the aggregated statistics of a
+//! [`crate::repository::RepositoryInfo`]
+use chrono::{DateTime, Utc};
+use git2::{Commit, Delta, Diff, Oid, Patch, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, vec::IntoIter};
+
+use crate::{
+    data::saturating_sum,
+    errors::SourceCodeError,
+    history::HistoryMode,
+    identity::{Author, IdentityOptions, IdentityResolver},
+    paths,
+    source::MergeStrategy,
+};
+
+/// The options for walking the commits with [`iter_commits`]
+///
+/// #Fields:
+/// * `history_mode` - The [`HistoryMode`] of the commits walked, e.g., only the first-parent
+///   line
+/// * `merge_strategy` - The [`MergeStrategy`] of the files a merge commit changed; merge commits
+///   are left out with [`MergeStrategy::SkipMerges`]
+/// * `identity` - The [`IdentityOptions`] for resolving the authors, e.g., with the mailmap
+/// * `max_commits` - The most commits yielded, newest first, or `None` for all of them
+/// * `line_stats` - Whether to count the lines added and removed in each file, which diffs the
+///   contents of the files rather than only their trees
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CommitOptions {
+    pub history_mode: HistoryMode,
+    pub merge_strategy: MergeStrategy,
+    pub identity: IdentityOptions,
+    pub max_commits: Option<usize>,
+    pub line_stats: bool,
+}
+impl Default for CommitOptions {
+    fn default() -> Self {
+        Self {
+            history_mode: HistoryMode::default(),
+            merge_strategy: MergeStrategy::default(),
+            identity: IdentityOptions::default(),
+            max_commits: None,
+            line_stats: true,
+        }
+    }
+}
+impl CommitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Walks the commits of the [`HistoryMode`]
+    pub fn with_history_mode(mut self, history_mode: HistoryMode) -> Self {
+        self.history_mode = history_mode;
+        self
+    }
+    /// Gets the files changed by merge commits with the [`MergeStrategy`]
+    pub fn with_merge_strategy(mut self, merge_strategy: MergeStrategy) -> Self {
+        self.merge_strategy = merge_strategy;
+        self
+    }
+    /// Resolves the authors with the [`IdentityOptions`]
+    pub fn with_identity(mut self, identity: IdentityOptions) -> Self {
+        self.identity = identity;
+        self
+    }
+    /// Yields at most `max_commits` commits
+    pub fn with_max_commits(mut self, max_commits: usize) -> Self {
+        self.max_commits = Some(max_commits);
+        self
+    }
+    /// Whether to count the lines added and removed in each file
+    pub fn with_line_stats(mut self, line_stats: bool) -> Self {
+        self.line_stats = line_stats;
+        self
+    }
+}
+
+/// How a commit changed a file
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    /// Another change, e.g., of the file's type or mode
+    Other,
+}
+impl From<Delta> for FileChangeKind {
+    fn from(delta: Delta) -> Self {
+        match delta {
+            Delta::Added | Delta::Copied => FileChangeKind::Added,
+            Delta::Modified => FileChangeKind::Modified,
+            Delta::Deleted => FileChangeKind::Deleted,
+            Delta::Renamed => FileChangeKind::Renamed,
+            _ => FileChangeKind::Other,
+        }
+    }
+}
+
+/// A file changed by a commit
+///
+/// #Fields:
+/// * `path` - The relative path of the file after the commit, or before it if it was deleted
+/// * `old_path` - The relative path of the file before the commit, if it was renamed
+/// * `kind` - The [`FileChangeKind`] of the change
+/// * `lines_added` - The lines added to the file, if counted
+/// * `lines_removed` - The lines removed from the file, if counted
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommitFileChange {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub kind: FileChangeKind,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+
+/// A commit, as yielded by [`iter_commits`]
+///
+/// #Fields:
+/// * `id` - The id of the commit
+/// * `parents` - The ids of the parent commits, the first parent first
+/// * `author` - The [`Author`], as resolved by the [`IdentityOptions`]
+/// * `date` - The time the commit was authored, or `None` if it is out of range
+/// * `message` - The commit message, with invalid UTF-8 replaced
+/// * `files` - The [`CommitFileChange`]s, by path: for a root commit, the files it added, and for
+///   a merge commit, the files changed as the [`MergeStrategy`] counts them
+/// * `lines_added` - The lines added across the `files`
+/// * `lines_removed` - The lines removed across the `files`
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommitInfo {
+    pub id: String,
+    pub parents: Vec<String>,
+    pub author: Author,
+    pub date: Option<DateTime<Utc>>,
+    pub message: String,
+    pub files: Vec<CommitFileChange>,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+impl CommitInfo {
+    pub fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+    /// Gets the [`CommitInfo`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
+
+/// An iterator over the [`CommitInfo`]s of the commits reachable from a reference, newest
+/// first, as returned by [`iter_commits`]. Each commit is only diffed when it is yielded.
+pub struct CommitIter {
+    repo: Repository,
+    resolver: IdentityResolver,
+    commits: IntoIter<Oid>,
+    options: CommitOptions,
+}
+impl Iterator for CommitIter {
+    type Item = Result<CommitInfo, SourceCodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let oid = self.commits.next()?;
+        Some(self.get_commit_info(oid))
+    }
+}
+impl CommitIter {
+    fn get_commit_info(&self, oid: Oid) -> Result<CommitInfo, SourceCodeError> {
+        let commit = self.repo.find_commit(oid)?;
+        let files = self.get_file_changes(&commit)?;
+        let author = commit.author();
+        Ok(CommitInfo {
+            id: oid.to_string(),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            author: self.resolver.resolve(&author),
+            date: DateTime::<Utc>::from_timestamp(author.when().seconds(), 0),
+            message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+            lines_added: saturating_sum(files.iter().map(|file| file.lines_added)),
+            lines_removed: saturating_sum(files.iter().map(|file| file.lines_removed)),
+            files,
+        })
+    }
+    /// Gets the files the `commit` changed against its first parent, or against an empty tree
+    /// for a root commit, keeping only those the [`MergeStrategy`] counts for a merge commit
+    fn get_file_changes(
+        &self,
+        commit: &Commit<'_>,
+    ) -> Result<Vec<CommitFileChange>, SourceCodeError> {
+        let parent_tree: Option<Tree<'_>> = match commit.parent_count() {
+            0 => None,
+            _ => Some(commit.parent(0)?.tree()?),
+        };
+        let mut diff =
+            self.repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        diff.find_similar(None)?;
+        let counted: Option<BTreeSet<String>> = match commit.parent_count() {
+            0 | 1 => None,
+            _ => self
+                .options
+                .merge_strategy
+                .get_changed_paths(&self.repo, commit)?,
+        };
+        get_diff_changes(&diff, self.options.line_stats, counted.as_ref())
+    }
+}
+
+/// Gets the [`CommitFileChange`]s of the `diff`, by path, counting the lines added and removed
+/// if `line_stats` is set, and keeping only the `counted` paths, if given
+pub(crate) fn get_diff_changes(
+    diff: &Diff<'_>,
+    line_stats: bool,
+    counted: Option<&BTreeSet<String>>,
+) -> Result<Vec<CommitFileChange>, SourceCodeError> {
+    let mut files: Vec<CommitFileChange> = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+            continue;
+        };
+        let path = paths::to_slash(path);
+        if counted.is_some_and(|counted| !counted.contains(&path)) {
+            continue;
+        }
+        let (lines_added, lines_removed) = match line_stats {
+            true => get_line_stats(diff, index)?,
+            false => (0, 0),
+        };
+        files.push(CommitFileChange {
+            old_path: match delta.status() {
+                Delta::Renamed => delta.old_file().path().map(paths::to_slash),
+                _ => None,
+            },
+            path,
+            kind: delta.status().into(),
+            lines_added,
+            lines_removed,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+/// Gets the lines added and removed by the delta at `index` of the `diff`
+fn get_line_stats(diff: &Diff<'_>, index: usize) -> Result<(u64, u64), SourceCodeError> {
+    Ok(match Patch::from_diff(diff, index)? {
+        Some(patch) => {
+            let (_, added, removed) = patch.line_stats()?;
+            (added as u64, removed as u64)
+        }
+        None => (0, 0),
+    })
+}
+
+/// Walks the commits reachable from the `reference`, e.g., 'HEAD', a branch or a tag, of the
+/// repository at `repo_path`, newest first, as the [`CommitOptions`] set
+///
+/// #Returns:
+/// * The [`CommitIter`] over the [`CommitInfo`]s of the commits
+/// * Err([`SourceCodeError::GitError`]) if the repository can't be opened or the `reference`
+///   doesn't resolve to a commit
+pub fn iter_commits(
+    repo_path: &str,
+    reference: &str,
+    options: &CommitOptions,
+) -> Result<CommitIter, SourceCodeError> {
+    let repo = Repository::open(repo_path)?;
+    let head = repo
+        .revparse_single(reference)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(SourceCodeError::for_reference(reference))?
+        .id();
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    options.history_mode.configure(&mut revwalk)?;
+    let mut commits: Vec<Oid> = Vec::new();
+    for oid in revwalk {
+        let oid = oid?;
+        if options.merge_strategy == MergeStrategy::SkipMerges
+            && repo.find_commit(oid)?.parent_count() > 1
+        {
+            continue;
+        }
+        commits.push(oid);
+        if options.max_commits.is_some_and(|max| commits.len() >= max) {
+            break;
+        }
+    }
+    let resolver = IdentityResolver::new(&repo, &options.identity);
+    Ok(CommitIter {
+        repo,
+        resolver,
+        commits: commits.into_iter(),
+        options: options.clone(),
+    })
+}
@@ -0,0 +1,209 @@
+//! A compact digest of a repository's statistics, bounded in size to fit into an LLM prompt
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archetype::RepositoryArchetype, errors::SourceCodeError, repository::RepositoryInfo,
+    source::SourceFileInfo,
+};
+
+/// The number of items in each list of a [`CompactContext`], before it is truncated to fit
+const MAX_ITEMS: usize = 10;
+
+/// A language of a [`CompactContext`]
+///
+/// #Fields:
+/// * `name` - The name of the language
+/// * `loc` - The lines of code in the language
+/// * `num_files` - The number of source files in the language
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContextLanguage {
+    pub name: String,
+    pub loc: u64,
+    pub num_files: u64,
+}
+
+/// A source file of a [`CompactContext`]
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `language` - The name of the language of the file, if known
+/// * `loc` - The lines of code in the file
+/// * `num_commits` - The number of commits that changed the file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContextFile {
+    pub path: String,
+    pub language: Option<String>,
+    pub loc: u64,
+    pub num_commits: u64,
+}
+impl From<&SourceFileInfo> for ContextFile {
+    fn from(source_file: &SourceFileInfo) -> Self {
+        Self {
+            path: source_file.relative_path.to_string(),
+            language: source_file
+                .language
+                .as_ref()
+                .map(|language| language.name.clone()),
+            loc: source_file.statistics.loc,
+            num_commits: source_file.statistics.num_commits,
+        }
+    }
+}
+
+/// A contributor of a [`CompactContext`]
+///
+/// #Fields:
+/// * `name` - The name of the contributor
+/// * `num_commits` - The number of commits of the contributor
+/// * `percentage_contribution` - The contributor's percentage of the commits
+/// * `is_active` - Whether the contributor committed recently
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContextContributor {
+    pub name: String,
+    pub num_commits: u64,
+    pub percentage_contribution: f32,
+    pub is_active: bool,
+}
+
+/// A digest of the statistics of a repository, for the context of an LLM prompt, as
+/// [`RepositoryInfo::to_compact_context`] serialises it
+///
+/// #Fields:
+/// * `name` - The name of the repository
+/// * `predominant_language` - The name of the predominant language, if any
+/// * `archetype` - The [`RepositoryArchetype`] of the repository
+/// * `frameworks` - The names of the frameworks detected
+/// * `loc` - The lines of code in the repository
+/// * `size` - The size of the source files, in bytes
+/// * `num_files` - The number of source files
+/// * `num_commits` - The number of commits
+/// * `num_contributors` - The number of contributors
+/// * `languages` - The [`ContextLanguage`]s with the most lines of code, most first
+/// * `largest_files` - The [`ContextFile`]s with the most lines of code, most first
+/// * `hotspots` - The [`ContextFile`]s changed by the most commits, most first
+/// * `contributors` - The [`ContextContributor`]s with the most commits, most first
+/// * `truncated` - Whether items were left out of the lists to fit the size
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct CompactContext {
+    pub name: String,
+    pub predominant_language: Option<String>,
+    pub archetype: RepositoryArchetype,
+    pub frameworks: Vec<String>,
+    pub loc: u64,
+    pub size: u64,
+    pub num_files: u64,
+    pub num_commits: u64,
+    pub num_contributors: u64,
+    pub languages: Vec<ContextLanguage>,
+    pub largest_files: Vec<ContextFile>,
+    pub hotspots: Vec<ContextFile>,
+    pub contributors: Vec<ContextContributor>,
+    pub truncated: bool,
+}
+impl CompactContext {
+    /// Gets the [`CompactContext`] of the repository, with up to 10 items in each list
+    pub fn new(repository_info: &RepositoryInfo) -> Self {
+        let mut languages: Vec<ContextLanguage> = repository_info
+            .languages
+            .iter()
+            .map(|language| {
+                let statistics = language.statistics.clone().unwrap_or_default();
+                ContextLanguage {
+                    name: language.name.clone(),
+                    loc: statistics.loc,
+                    num_files: statistics.num_files,
+                }
+            })
+            .collect();
+        languages.sort_by(|a, b| b.loc.cmp(&a.loc).then_with(|| a.name.cmp(&b.name)));
+        languages.truncate(MAX_ITEMS);
+
+        let mut contributors: Vec<ContextContributor> = repository_info
+            .contributors
+            .iter()
+            .map(|contributor| ContextContributor {
+                name: contributor.author.name.clone(),
+                num_commits: contributor.statistics.num_commits,
+                percentage_contribution: contributor.percentage_contribution,
+                is_active: contributor.is_active,
+            })
+            .collect();
+        contributors.sort_by(|a, b| {
+            b.num_commits
+                .cmp(&a.num_commits)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        contributors.truncate(MAX_ITEMS);
+
+        let files = |source_files: Vec<&SourceFileInfo>| -> Vec<ContextFile> {
+            source_files.into_iter().map(ContextFile::from).collect()
+        };
+        let statistics = &repository_info.statistics;
+        let mut frameworks: Vec<String> = repository_info
+            .frameworks
+            .iter()
+            .map(|framework| framework.name.clone())
+            .collect();
+        frameworks.sort();
+        frameworks.dedup();
+        Self {
+            name: repository_info.name.clone(),
+            predominant_language: repository_info
+                .predominant_language
+                .as_ref()
+                .map(|language| language.name.clone()),
+            archetype: repository_info.archetype,
+            frameworks,
+            loc: statistics.loc,
+            size: statistics.size,
+            num_files: statistics.num_files,
+            num_commits: statistics.num_commits,
+            num_contributors: repository_info.contributors.len() as u64,
+            languages,
+            largest_files: files(repository_info.top_files_by_loc(MAX_ITEMS)),
+            hotspots: files(repository_info.top_files_by_churn(MAX_ITEMS)),
+            contributors,
+            truncated: false,
+        }
+    }
+    /// Serialises the context as compact JSON of at most `max_bytes`, leaving out the last item
+    /// of the longest list (the largest files, hotspots, contributors, then languages, on a
+    /// tie) until it fits, and marking it `truncated`, so that the same statistics always give
+    /// the same output
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if it doesn't fit even without the lists
+    pub fn to_json(mut self, max_bytes: usize) -> Result<String, SourceCodeError> {
+        loop {
+            let json = serde_json::to_string(&self)
+                .map_err(|err| SourceCodeError::SerializationError(err.into()))?;
+            if json.len() <= max_bytes {
+                return Ok(json);
+            }
+            let lengths = [
+                self.largest_files.len(),
+                self.hotspots.len(),
+                self.contributors.len(),
+                self.languages.len(),
+            ];
+            let Some((longest, _)) = lengths
+                .iter()
+                .enumerate()
+                .filter(|(_, length)| **length > 0)
+                .max_by(|(a_index, a), (b_index, b)| a.cmp(b).then(b_index.cmp(a_index)))
+            else {
+                return Err(SourceCodeError::ConfigError(format!(
+                    "the compact context of {} needs more than {} bytes",
+                    self.name, max_bytes
+                )));
+            };
+            match longest {
+                0 => self.largest_files.pop().map(|_| ()),
+                1 => self.hotspots.pop().map(|_| ()),
+                2 => self.contributors.pop().map(|_| ()),
+                _ => self.languages.pop().map(|_| ()),
+            };
+            self.truncated = true;
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! The co-ownership of a repository's directories: a matrix of the commits of each contributor
+//! to each top-level directory, so that it can be seen at a glance which contributors
+//! concentrate on which subsystems, and which subsystems depend on few contributors
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{errors::SourceCodeError, identity::Author, ratio::Percentage};
+#[cfg(feature = "git")]
+use crate::{graph::CommitGraph, identity::BotHandling};
+
+/// The directory of the files at the root of the repository
+pub const ROOT_DIRECTORY: &str = ".";
+
+/// The options for the co-ownership matrix
+///
+/// #Fields:
+/// * `depth` - The number of leading path components of the directories, e.g., 1 for the
+///   top-level directories, or 2 for 'crates/parser'; files in a shallower directory count
+///   towards it
+/// * `max_contributors` - The number of contributors with the most commits kept in the
+///   [`CoOwnershipMatrix`]; the directories' commits still count those of the others
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CoOwnershipOptions {
+    pub depth: usize,
+    pub max_contributors: usize,
+}
+impl Default for CoOwnershipOptions {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            max_contributors: 100,
+        }
+    }
+}
+
+/// A row of a [`CoOwnershipMatrix`]: the commits of a contributor to each of its directories
+///
+/// #Fields:
+/// * `author` - The [`Author`] of the contributor's commits, with the name and email address of
+///   the last one
+/// * `num_commits` - The number of the contributor's commits that changed a file, counted once
+///   however many directories they changed
+/// * `commits` - The number of the contributor's commits that changed a file in each directory,
+///   in the order of the `directories`
+/// * `shares` - The [`Percentage`] of the commits to each directory that are the contributor's,
+///   i.e., how much of the directory the contributor co-owns
+/// * `focus` - The [`Percentage`] of the contributor's commits that changed each directory,
+///   i.e., how much the contributor concentrates on it; a commit changing several directories
+///   counts towards each, so these may add up to more than 100
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CoOwnershipRow {
+    pub author: Author,
+    pub num_commits: u64,
+    pub commits: Vec<u64>,
+    pub shares: Vec<Percentage>,
+    pub focus: Vec<Percentage>,
+}
+
+/// The matrix of the commits of each contributor to each directory of a repository, from the
+/// files changed by the commits of the history, as counted by its
+/// [`crate::source::MergeStrategy`]; a root commit counts as changing the files it adds
+///
+/// #Fields:
+/// * `depth` - The `depth` of the directories, see [`CoOwnershipOptions`]
+/// * `directories` - The directories, i.e., the columns, in alphabetical order;
+///   [`ROOT_DIRECTORY`] for the files at the root of the repository
+/// * `directory_commits` - The number of commits that changed a file in each directory
+/// * `num_contributors` - The number of contributors, before they are limited to the
+///   `max_contributors` of the [`CoOwnershipOptions`]
+/// * `contributors` - The [`CoOwnershipRow`]s, i.e., the rows, most commits first
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CoOwnershipMatrix {
+    pub depth: usize,
+    pub directories: Vec<String>,
+    pub directory_commits: Vec<u64>,
+    pub num_contributors: usize,
+    pub contributors: Vec<CoOwnershipRow>,
+}
+impl CoOwnershipMatrix {
+    /// Gets the [`CoOwnershipMatrix`] of the commits of the [`CommitGraph`], grouping them by
+    /// the canonical id of their author; commits authored by bots are skipped unless the
+    /// [`BotHandling`] is [`BotHandling::Include`]
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        graph: &CommitGraph,
+        options: &CoOwnershipOptions,
+        bots: BotHandling,
+    ) -> Result<CoOwnershipMatrix, SourceCodeError> {
+        let changes = graph.changes()?;
+        let depth = options.depth.max(1);
+
+        // The author of the last commit, its time, the number of commits, and the commits by
+        // directory, by canonical id
+        let mut contributions = HashMap::<&str, (&Author, i64, u64, BTreeMap<String, u64>)>::new();
+        let mut directory_commits: BTreeMap<String, u64> = BTreeMap::new();
+        for (index, commit) in graph.commits().iter().enumerate() {
+            if commit.author.is_bot && bots != BotHandling::Include {
+                continue;
+            }
+            let directories: BTreeSet<String> = match changes.changed_paths(index) {
+                Some(paths) => paths
+                    .iter()
+                    .map(|path| get_directory(path, depth))
+                    .collect(),
+                None => changes
+                    .paths_added_by(index)
+                    .map(|path| get_directory(path, depth))
+                    .collect(),
+            };
+            if directories.is_empty() {
+                continue;
+            }
+            let seconds = commit.author_time.seconds();
+            let entry = contributions
+                .entry(commit.author.canonical_id.as_str())
+                .or_insert((&commit.author, seconds, 0, BTreeMap::new()));
+            if seconds > entry.1 {
+                entry.0 = &commit.author;
+                entry.1 = seconds;
+            }
+            entry.2 += 1;
+            for directory in directories {
+                *entry.3.entry(directory.clone()).or_default() += 1;
+                *directory_commits.entry(directory).or_default() += 1;
+            }
+        }
+
+        let directories: Vec<String> = directory_commits.keys().cloned().collect();
+        let directory_commits: Vec<u64> = directory_commits.into_values().collect();
+        let mut contributors: Vec<CoOwnershipRow> = contributions
+            .into_values()
+            .map(|(author, _, num_commits, by_directory)| {
+                let commits: Vec<u64> = directories
+                    .iter()
+                    .map(|directory| by_directory.get(directory).copied().unwrap_or(0))
+                    .collect();
+                CoOwnershipRow {
+                    author: author.clone(),
+                    num_commits,
+                    shares: commits
+                        .iter()
+                        .zip(&directory_commits)
+                        .map(|(&commits, &total)| {
+                            Percentage::of(commits as f64, total as f64).unwrap_or_default()
+                        })
+                        .collect(),
+                    focus: commits
+                        .iter()
+                        .map(|&commits| {
+                            Percentage::of(commits as f64, num_commits as f64).unwrap_or_default()
+                        })
+                        .collect(),
+                    commits,
+                }
+            })
+            .collect();
+        contributors.sort_by(|a, b| {
+            b.num_commits
+                .cmp(&a.num_commits)
+                .then_with(|| a.author.name.cmp(&b.author.name))
+                .then_with(|| a.author.canonical_id.cmp(&b.author.canonical_id))
+        });
+        let num_contributors = contributors.len();
+        contributors.truncate(options.max_contributors);
+        Ok(CoOwnershipMatrix {
+            depth,
+            directories,
+            directory_commits,
+            num_contributors,
+            contributors,
+        })
+    }
+    /// Gets the [`CoOwnershipRow`] of the contributor with the `canonical_id`, if kept
+    pub fn get_contributor(&self, canonical_id: &str) -> Option<&CoOwnershipRow> {
+        self.contributors
+            .iter()
+            .find(|row| row.author.canonical_id == canonical_id)
+    }
+    /// Gets the [`CoOwnershipMatrix`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Gets the commits of the [`CoOwnershipMatrix`] as CSV, with a row per contributor, after
+    /// their name, email address and total commits, and a column per directory, e.g.:
+    ///
+    /// ```text
+    /// contributor,email,total,.,docs,src
+    /// Ada Lovelace,ada@example.com,12,2,1,10
+    /// ```
+    pub fn get_as_csv(&self) -> String {
+        self.get_csv(|row| row.commits.iter().map(u64::to_string).collect())
+    }
+    /// Gets the `shares` of the [`CoOwnershipMatrix`] as CSV, laid out as [`Self::get_as_csv`]
+    /// lays out the commits
+    pub fn get_shares_as_csv(&self) -> String {
+        self.get_csv(|row| {
+            row.shares
+                .iter()
+                .map(|share| format!("{:.2}", share.value()))
+                .collect()
+        })
+    }
+    /// Gets the CSV of the matrix with the `cells` of each row
+    fn get_csv(&self, cells: impl Fn(&CoOwnershipRow) -> Vec<String>) -> String {
+        let mut csv = ["contributor", "email", "total"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(
+                self.directories
+                    .iter()
+                    .map(|directory| escape_csv(directory)),
+            )
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push('\n');
+        for row in &self.contributors {
+            let line = [
+                escape_csv(&row.author.name),
+                escape_csv(row.author.email.as_deref().unwrap_or_default()),
+                row.num_commits.to_string(),
+            ]
+            .into_iter()
+            .chain(cells(row))
+            .collect::<Vec<_>>()
+            .join(",");
+            csv.push_str(&line);
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Gets the directory of the file at the relative `path`, of at most `depth` components, or
+/// [`ROOT_DIRECTORY`] if it is at the root
+#[cfg(feature = "git")]
+fn get_directory(path: &str, depth: usize) -> String {
+    let components: Vec<&str> = path.split('/').collect();
+    let directory = &components[..(components.len() - 1).min(depth)];
+    match directory.is_empty() {
+        true => ROOT_DIRECTORY.to_string(),
+        false => directory.join("/"),
+    }
+}
+
+/// Quotes the CSV `field` if it has a comma, quote or line break, doubling its quotes
+fn escape_csv(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+#[cfg(feature = "git")]
+use tokei::{Config, LanguageType};
+
+use crate::{errors::SourceCodeError, role};
+
+/// The file extensions and names of a custom language, counted as the `tokei` language it is
+/// keyed by in the [`CountingOptions`], e.g., '.tpl' templates as HTML
+///
+/// #Fields:
+/// * `extensions` - The file extensions, without the '.', e.g., 'tpl'
+/// * `filenames` - The file names, e.g., 'Tiltfile'
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CustomLanguage {
+    pub extensions: Vec<String>,
+    pub filenames: Vec<String>,
+}
+
+/// The options for counting the lines of code with `tokei`, e.g., to align the counts with the
+/// settings of the `tokei` CLI
+///
+/// #Fields:
+/// * `config_files` - Whether to start from the 'tokei.toml' or '.tokeirc' configuration files
+///   the `tokei` CLI reads, in the home and current directories; the options below that are set
+///   override them
+/// * `treat_doc_strings_as_comments` - Whether to count doc strings, e.g., Python's, as comments
+///   rather than code
+/// * `hidden` - Whether to count hidden files, i.e., those starting with '.'
+/// * `ci_definitions` - Whether to count the CI definitions that are hidden, e.g., in
+///   '.github/workflows' or '.gitlab-ci.yml', even if the other hidden files aren't counted
+/// * `no_ignore` - Whether to count the files ignored by any ignore file
+/// * `no_ignore_parent` - Whether to count the files ignored by the ignore files of the parent
+///   directories of the repository
+/// * `no_ignore_dot` - Whether to count the files ignored by '.ignore' files
+/// * `no_ignore_vcs` - Whether to count the files ignored by '.gitignore' files
+/// * `follow_symlinks` - Whether to count the lines of a symlinked file through the symlink, if
+///   its target is a file in the repository that isn't counted otherwise, e.g., in an excluded
+///   directory; symlinks are otherwise recorded without lines, and targets outside the
+///   repository are never read
+/// * `languages` - The [`CustomLanguage`]s, by the name of the `tokei` language they are
+///   counted as, for files `tokei` doesn't recognise, e.g., from a 'languages.toml'
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CountingOptions {
+    pub config_files: bool,
+    pub treat_doc_strings_as_comments: bool,
+    pub hidden: bool,
+    pub ci_definitions: bool,
+    pub no_ignore: bool,
+    pub no_ignore_parent: bool,
+    pub no_ignore_dot: bool,
+    pub no_ignore_vcs: bool,
+    pub follow_symlinks: bool,
+    pub languages: BTreeMap<String, CustomLanguage>,
+}
+impl CountingOptions {
+    /// Adds the [`CustomLanguage`]s of a 'languages.toml', keyed by the `tokei` language they
+    /// are counted as, e.g.:
+    ///
+    /// ```toml
+    /// [HTML]
+    /// extensions = ["tpl", "hbs"]
+    ///
+    /// [Python]
+    /// filenames = ["Tiltfile"]
+    /// ```
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if the file isn't valid, or names a language
+    ///   `tokei` doesn't have
+    pub fn with_languages_toml(mut self, config: &str) -> Result<Self, SourceCodeError> {
+        let languages: BTreeMap<String, CustomLanguage> =
+            toml::from_str(config).map_err(|err| SourceCodeError::ConfigError(err.to_string()))?;
+        self.languages.extend(languages);
+        self.validate()?;
+        Ok(self)
+    }
+    /// Checks that each of the custom `languages` is counted as a language `tokei` has
+    #[cfg(feature = "git")]
+    pub(crate) fn validate(&self) -> Result<(), SourceCodeError> {
+        match self
+            .languages
+            .keys()
+            .find(|name| name.parse::<LanguageType>().is_err())
+        {
+            Some(name) => Err(SourceCodeError::ConfigError(format!(
+                "unknown tokei language '{}'",
+                name
+            ))),
+            None => Ok(()),
+        }
+    }
+    /// Without `tokei`, the custom `languages` can't be checked, so are taken as they are
+    #[cfg(not(feature = "git"))]
+    pub(crate) fn validate(&self) -> Result<(), SourceCodeError> {
+        Ok(())
+    }
+    /// Gets the `tokei` [`Config`] of the options
+    #[cfg(feature = "git")]
+    pub(crate) fn get_config(&self) -> Config {
+        let mut config = match self.config_files {
+            true => Config::from_config_files(),
+            false => Config::default(),
+        };
+        let flags = [
+            (
+                &mut config.treat_doc_strings_as_comments,
+                self.treat_doc_strings_as_comments,
+            ),
+            // Any hidden files counted are filtered by `counts_hidden` after `tokei` counts them
+            (&mut config.hidden, self.hidden || self.ci_definitions),
+            (&mut config.no_ignore, self.no_ignore),
+            (&mut config.no_ignore_parent, self.no_ignore_parent),
+            (&mut config.no_ignore_dot, self.no_ignore_dot),
+            (&mut config.no_ignore_vcs, self.no_ignore_vcs),
+        ];
+        for (flag, set) in flags {
+            if set {
+                *flag = Some(true);
+            }
+        }
+        config
+    }
+    /// Gets the `tokei` language a file at `path` is counted as, if it is of a
+    /// [`CustomLanguage`], by its file name and then its extension
+    #[cfg(feature = "git")]
+    pub(crate) fn get_custom_language(&self, path: &Path) -> Option<LanguageType> {
+        let file_name = path.file_name()?.to_str()?;
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let (name, _) = self
+            .languages
+            .iter()
+            .find(|(_, language)| language.filenames.iter().any(|name| name == file_name))
+            .or_else(|| {
+                self.languages.iter().find(|(_, language)| {
+                    extension.is_some_and(|extension| {
+                        language
+                            .extensions
+                            .iter()
+                            .any(|custom| custom.eq_ignore_ascii_case(extension))
+                    })
+                })
+            })?;
+        name.parse().ok()
+    }
+    /// Whether the file at the `relative` path is counted if it is hidden: any hidden file if
+    /// the `hidden` files are counted, or else a CI definition if the `ci_definitions` are
+    pub(crate) fn counts_hidden(&self, relative: &Path) -> bool {
+        self.hidden || (self.ci_definitions && role::is_ci_definition(relative))
+    }
+    /// Whether the files ignored by '.gitignore' files are counted
+    pub(crate) fn counts_git_ignored(&self) -> bool {
+        self.no_ignore || self.no_ignore_vcs
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::collections::HashMap;
+
+#[cfg(feature = "git")]
+use crate::{
+    cancel::CancellationToken,
+    errors::SourceCodeError,
+    graph::CommitGraph,
+    history::{HistoryMode, HistorySampling},
+    identity::IdentityOptions,
+    observer::NoopObserver,
+    ratio,
+    source::{MergeStrategy, SourceFileInfo},
+};
+
+/// The options for the temporal coupling analysis
+///
+/// #Fields:
+/// * `min_shared_commits` - The fewest commits two files must change in together to be coupled
+/// * `max_changeset_size` - Commits that change more source files than this, e.g., reformats or
+///   mass renames, are skipped, as they would couple unrelated files
+/// * `max_pairs` - The number of most coupled file pairs kept in the [`CouplingReport`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CouplingOptions {
+    pub min_shared_commits: i32,
+    pub max_changeset_size: usize,
+    pub max_pairs: usize,
+}
+impl Default for CouplingOptions {
+    fn default() -> Self {
+        Self {
+            min_shared_commits: 2,
+            max_changeset_size: 30,
+            max_pairs: 50,
+        }
+    }
+}
+
+/// The temporal coupling of two source files, i.e., how often they change in the same commits
+///
+/// #Fields:
+/// * `file_a` - The relative path of one file, ordered before `file_b`
+/// * `file_b` - The relative path of the other file
+/// * `shared_commits` - The number of commits that changed both files
+/// * `commits_a` - The number of commits analysed that changed `file_a`
+/// * `commits_b` - The number of commits analysed that changed `file_b`
+/// * `score` - The coupling, 0.0 - 1.0: the shared commits as a share of the commits that
+///   changed either file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileCoupling {
+    pub file_a: String,
+    pub file_b: String,
+    pub shared_commits: i32,
+    pub commits_a: i32,
+    pub commits_b: i32,
+    pub score: f32,
+}
+
+/// The temporal coupling between the source files of a repository, which reveals dependencies
+/// that aren't visible in the code, e.g., a schema and the queries on it
+///
+/// #Fields:
+/// * `num_commits` - The number of commits analysed, i.e., not skipped by the [`CouplingOptions`]
+///   or the [`MergeStrategy`]
+/// * `pairs` - The most coupled [`FileCoupling`]s, by score and then shared commits
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CouplingReport {
+    pub num_commits: i32,
+    pub pairs: Vec<FileCoupling>,
+}
+impl CouplingReport {
+    /// Gets the [`CouplingReport`] for the `source_files` of the repository at `repo_path`, from
+    /// the commits reachable from HEAD. Files that no longer exist aren't reported.
+    #[cfg(feature = "git")]
+    pub fn new(
+        repo_path: &str,
+        source_files: &[SourceFileInfo],
+        options: &CouplingOptions,
+        merge_strategy: MergeStrategy,
+    ) -> Result<CouplingReport, SourceCodeError> {
+        Self::new_with_cancellation(
+            repo_path,
+            source_files,
+            options,
+            merge_strategy,
+            &CancellationToken::new(),
+        )
+    }
+    /// Gets the [`CouplingReport`] as [`CouplingReport::new`] does, stopping early if the
+    /// [`CancellationToken`] is cancelled
+    #[cfg(feature = "git")]
+    pub fn new_with_cancellation(
+        repo_path: &str,
+        source_files: &[SourceFileInfo],
+        options: &CouplingOptions,
+        merge_strategy: MergeStrategy,
+        cancellation: &CancellationToken,
+    ) -> Result<CouplingReport, SourceCodeError> {
+        let graph = CommitGraph::new(
+            repo_path,
+            merge_strategy,
+            HistoryMode::default(),
+            HistorySampling::default(),
+            &IdentityOptions::default(),
+            &NoopObserver,
+            cancellation,
+        )?;
+        Self::from_graph(&graph, source_files, options)
+    }
+    /// Gets the [`CouplingReport`] for the `source_files` from the paths changed by the commits
+    /// of the [`CommitGraph`], as counted by its [`MergeStrategy`]
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        graph: &CommitGraph,
+        source_files: &[SourceFileInfo],
+        options: &CouplingOptions,
+    ) -> Result<CouplingReport, SourceCodeError> {
+        let changes = graph.changes()?;
+        let files: HashMap<&str, usize> = source_files
+            .iter()
+            .enumerate()
+            .map(|(index, sfi)| (sfi.relative_path.as_str(), index))
+            .collect();
+
+        let mut num_commits: i32 = 0;
+        let mut file_commits: HashMap<usize, i32> = HashMap::new();
+        let mut shared_commits: HashMap<(usize, usize), i32> = HashMap::new();
+        for index in 0..graph.commits().len() {
+            let Some(changed) = changes.changed_paths(index) else {
+                continue;
+            };
+            let changed: Vec<usize> = changed
+                .iter()
+                .filter_map(|path| files.get(path.as_str()).copied())
+                .collect();
+            if changed.len() > options.max_changeset_size {
+                continue;
+            }
+            num_commits += 1;
+            for (position, &a) in changed.iter().enumerate() {
+                *file_commits.entry(a).or_default() += 1;
+                for &b in &changed[position + 1..] {
+                    *shared_commits.entry((a.min(b), a.max(b))).or_default() += 1;
+                }
+            }
+        }
+
+        let mut pairs: Vec<FileCoupling> = shared_commits
+            .into_iter()
+            .filter(|(_, shared)| *shared >= options.min_shared_commits)
+            .map(|((a, b), shared)| {
+                let (a, b) = match source_files[a].relative_path <= source_files[b].relative_path {
+                    true => (a, b),
+                    false => (b, a),
+                };
+                let commits_a = file_commits.get(&a).copied().unwrap_or_default();
+                let commits_b = file_commits.get(&b).copied().unwrap_or_default();
+                FileCoupling {
+                    file_a: source_files[a].relative_path.to_string(),
+                    file_b: source_files[b].relative_path.to_string(),
+                    shared_commits: shared,
+                    commits_a,
+                    commits_b,
+                    score: ratio::ratio(shared as f64, (commits_a + commits_b - shared) as f64)
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+        pairs.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.shared_commits.cmp(&a.shared_commits))
+                .then_with(|| (&a.file_a, &a.file_b).cmp(&(&b.file_a, &b.file_b)))
+        });
+        pairs.truncate(options.max_pairs);
+        Ok(CouplingReport { num_commits, pairs })
+    }
+    /// Gets the [`FileCoupling`]s of the file at `relative_path`
+    pub fn get_pairs_for<'a>(
+        &'a self,
+        relative_path: &'a str,
+    ) -> impl Iterator<Item = &'a FileCoupling> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |pair| pair.file_a == relative_path || pair.file_b == relative_path)
+    }
+}
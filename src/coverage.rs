@@ -0,0 +1,436 @@
+//! The line coverage of test runs, read from lcov or Cobertura XML reports, attached to the
+//! statistics of the source files, e.g., to find the files that change often but are poorly
+//! tested
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use crate::{
+    catalog::{MetricDefinition, MetricScope, MetricUnit, MetricWindow},
+    data::to_count,
+    errors::SourceCodeError,
+    paths::ReportPathMatcher,
+    ratio,
+    repository::RepositoryInfo,
+    source::SourceFileInfo,
+};
+
+/// The pattern of a `<source>` directory of a Cobertura report
+const SOURCE_PATTERN: &str = r"<source>\s*([^<]*?)\s*</source>";
+/// The pattern of a `<class>` of a Cobertura report, with its attributes and body, if any
+const CLASS_PATTERN: &str = r"(?s)<class\b([^>]*?)(?:/>|>(.*?)</class>)";
+/// The pattern of a `<line>` of a Cobertura report, with its attributes
+const LINE_PATTERN: &str = r"<line\b([^>]*)>";
+/// The pattern of an attribute of an XML tag, with its name and value
+const ATTRIBUTE_PATTERN: &str = r#"\b([a-z-]+)="([^"]*)""#;
+
+/// The format of a coverage report
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageFormat {
+    /// The lcov tracefile format, e.g., of `cargo llvm-cov --lcov` or Istanbul
+    Lcov,
+    /// The Cobertura XML format, e.g., of `coverage.py xml` or `cargo tarpaulin`
+    Cobertura,
+}
+impl CoverageFormat {
+    /// Detects the format of the `contents` of a coverage report: XML is taken as Cobertura,
+    /// and a report with source file ('SF:') records as lcov
+    pub fn detect(contents: &str) -> Option<CoverageFormat> {
+        let contents = contents.trim_start_matches('\u{feff}').trim_start();
+        if contents.starts_with('<') {
+            Some(CoverageFormat::Cobertura)
+        } else if contents
+            .lines()
+            .any(|line| line.trim_start().starts_with("SF:"))
+        {
+            Some(CoverageFormat::Lcov)
+        } else {
+            None
+        }
+    }
+}
+
+/// The line coverage of a file in a coverage report
+///
+/// #Fields:
+/// * `path` - The path of the file, as in the report
+/// * `lines_found` - The number of instrumented lines, i.e., those that could be executed
+/// * `lines_hit` - The number of instrumented lines executed at least once
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileCoverage {
+    pub path: String,
+    pub lines_found: u64,
+    pub lines_hit: u64,
+}
+impl FileCoverage {
+    /// Gets the percentage of the instrumented lines executed, or `None` if there are none
+    pub fn line_coverage(&self) -> Option<f32> {
+        ratio::percentage(self.lines_hit as f64, self.lines_found as f64)
+    }
+}
+
+/// A coverage report of a test run
+///
+/// #Fields:
+/// * `format` - The [`CoverageFormat`] of the report
+/// * `sources` - The directories the paths of the files are relative to, from the `<source>`s
+///   of a Cobertura report
+/// * `files` - The [`FileCoverage`] of each file, by path
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CoverageReport {
+    pub format: CoverageFormat,
+    pub sources: Vec<String>,
+    pub files: Vec<FileCoverage>,
+}
+impl CoverageReport {
+    /// Reads the coverage report at `path`, in the format detected from its contents
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if it is neither lcov nor Cobertura XML
+    pub fn read(path: &Path) -> Result<CoverageReport, SourceCodeError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+    /// Parses the `contents` of a coverage report, in the format detected from them
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if it is neither lcov nor Cobertura XML
+    pub fn parse(contents: &str) -> Result<CoverageReport, SourceCodeError> {
+        match CoverageFormat::detect(contents) {
+            Some(CoverageFormat::Lcov) => Ok(Self::parse_lcov(contents)),
+            Some(CoverageFormat::Cobertura) => Self::parse_cobertura(contents),
+            None => Err(SourceCodeError::ConfigError(
+                "the coverage report is neither lcov nor Cobertura XML".to_string(),
+            )),
+        }
+    }
+    /// Parses an lcov tracefile: the 'DA' line records of each 'SF' source file, merged if a
+    /// file has several records, or else its 'LF' and 'LH' totals
+    pub fn parse_lcov(contents: &str) -> CoverageReport {
+        let mut lines_by_path: BTreeMap<String, BTreeMap<u32, bool>> = BTreeMap::new();
+        let mut totals_by_path: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        let mut path: Option<String> = None;
+        for line in contents.lines().map(str::trim) {
+            if let Some(source_file) = line.strip_prefix("SF:") {
+                path = Some(source_file.to_string());
+                lines_by_path.entry(source_file.to_string()).or_default();
+            } else if line == "end_of_record" {
+                path = None;
+            } else if let Some(path) = &path {
+                if let Some(record) = line.strip_prefix("DA:") {
+                    let mut fields = record.split(',');
+                    let (Some(Ok(number)), Some(hits)) = (
+                        fields.next().map(str::parse::<u32>),
+                        fields.next().map(|hits| hits.trim() != "0"),
+                    ) else {
+                        continue;
+                    };
+                    *lines_by_path
+                        .entry(path.clone())
+                        .or_default()
+                        .entry(number)
+                        .or_default() |= hits;
+                } else if let Some(found) = line.strip_prefix("LF:") {
+                    let totals = totals_by_path.entry(path.clone()).or_default();
+                    totals.0 = totals.0.saturating_add(found.parse().unwrap_or_default());
+                } else if let Some(hit) = line.strip_prefix("LH:") {
+                    let totals = totals_by_path.entry(path.clone()).or_default();
+                    totals.1 = totals.1.saturating_add(hit.parse().unwrap_or_default());
+                }
+            }
+        }
+        let files = lines_by_path
+            .into_iter()
+            .map(|(path, lines)| match lines.is_empty() {
+                true => {
+                    let (lines_found, lines_hit) =
+                        totals_by_path.get(&path).copied().unwrap_or_default();
+                    FileCoverage {
+                        path,
+                        lines_found,
+                        lines_hit,
+                    }
+                }
+                false => Self::get_file_coverage(path, &lines),
+            })
+            .collect();
+        CoverageReport {
+            format: CoverageFormat::Lcov,
+            sources: Vec::new(),
+            files,
+        }
+    }
+    /// Parses a Cobertura XML report: the `<line>`s of each `<class>`, merged by its
+    /// `filename`, as a file can have several classes, whose methods repeat their lines
+    pub fn parse_cobertura(contents: &str) -> Result<CoverageReport, SourceCodeError> {
+        let source_pattern = Regex::new(SOURCE_PATTERN)?;
+        let class_pattern = Regex::new(CLASS_PATTERN)?;
+        let line_pattern = Regex::new(LINE_PATTERN)?;
+        let attribute_pattern = Regex::new(ATTRIBUTE_PATTERN)?;
+        let attributes = |tag: &str| -> HashMap<String, String> {
+            attribute_pattern
+                .captures_iter(tag)
+                .map(|captures| (captures[1].to_string(), unescape(&captures[2])))
+                .collect()
+        };
+        let sources: Vec<String> = source_pattern
+            .captures_iter(contents)
+            .map(|captures| unescape(&captures[1]))
+            .filter(|source| !source.is_empty())
+            .collect();
+        let mut lines_by_path: BTreeMap<String, BTreeMap<u32, bool>> = BTreeMap::new();
+        for class in class_pattern.captures_iter(contents) {
+            let Some(path) = attributes(&class[1]).remove("filename") else {
+                continue;
+            };
+            let lines = lines_by_path.entry(path).or_default();
+            let Some(body) = class.get(2) else {
+                continue;
+            };
+            for line in line_pattern.captures_iter(body.as_str()) {
+                let line = attributes(&line[1]);
+                let (Some(Ok(number)), Some(hits)) = (
+                    line.get("number").map(|number| number.parse::<u32>()),
+                    line.get("hits").map(|hits| hits.trim() != "0"),
+                ) else {
+                    continue;
+                };
+                *lines.entry(number).or_default() |= hits;
+            }
+        }
+        Ok(CoverageReport {
+            format: CoverageFormat::Cobertura,
+            sources,
+            files: lines_by_path
+                .into_iter()
+                .map(|(path, lines)| Self::get_file_coverage(path, &lines))
+                .collect(),
+        })
+    }
+    /// Gets the [`FileCoverage`] of the file at `path` from whether each of its `lines` was hit
+    fn get_file_coverage(path: String, lines: &BTreeMap<u32, bool>) -> FileCoverage {
+        FileCoverage {
+            path,
+            lines_found: to_count(lines.len()),
+            lines_hit: to_count(lines.values().filter(|hit| **hit).count()),
+        }
+    }
+}
+
+/// The outcome of applying a [`CoverageReport`] to a [`RepositoryInfo`]
+///
+/// #Fields:
+/// * `num_files` - The number of source files given a line coverage
+/// * `line_coverage` - The percentage of the instrumented lines of those files executed, or
+///   `None` if none are instrumented
+/// * `unmatched` - The paths of the files of the report that aren't source files of the
+///   repository, e.g., generated files or files of another checkout
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CoverageSummary {
+    pub num_files: u64,
+    pub line_coverage: Option<f32>,
+    pub unmatched: Vec<String>,
+}
+
+/// Attaches the line coverage of the `report` to the statistics of the source files of the
+/// `repository_info` of the repository at `repo_path`, and their total to the repository's
+/// statistics, adding the metrics to its catalog. The files of the report are matched by their
+/// path relative to the repository, resolved against the report's sources, or else by the
+/// longest relative path of a source file they end with.
+pub fn apply(
+    repository_info: &mut RepositoryInfo,
+    repo_path: &str,
+    report: &CoverageReport,
+) -> CoverageSummary {
+    let matches: Vec<Option<usize>> = {
+        let relative_paths = repository_info
+            .source_files
+            .iter()
+            .map(|source_file| source_file.relative_path.as_str())
+            .collect();
+        let matcher = ReportPathMatcher::new(relative_paths, repo_path);
+        report
+            .files
+            .iter()
+            .map(|file| matcher.find(&file.path, &report.sources))
+            .collect()
+    };
+    // The lines found and hit of each source file
+    let mut coverage_by_index: BTreeMap<usize, (u64, u64)> = BTreeMap::new();
+    let mut unmatched: Vec<String> = Vec::new();
+    for (file, index) in report.files.iter().zip(matches) {
+        match index {
+            Some(index) => {
+                let coverage = coverage_by_index.entry(index).or_default();
+                coverage.0 = coverage.0.saturating_add(file.lines_found);
+                coverage.1 = coverage.1.saturating_add(file.lines_hit);
+            }
+            None => unmatched.push(file.path.clone()),
+        }
+    }
+    let (mut lines_found, mut lines_hit) = (0u64, 0u64);
+    for (index, (found, hit)) in &coverage_by_index {
+        let source_file: &mut SourceFileInfo = &mut repository_info.source_files[*index];
+        source_file.statistics.line_coverage =
+            ratio::percentage(*hit as f64, *found as f64).map(|coverage| coverage.min(100.0));
+        lines_found = lines_found.saturating_add(*found);
+        lines_hit = lines_hit.saturating_add(*hit);
+    }
+    let line_coverage = ratio::percentage(lines_hit as f64, lines_found as f64);
+    repository_info.statistics.line_coverage = line_coverage;
+    for (path, scope, description) in [
+        (
+            "statistics.line_coverage",
+            MetricScope::Repository,
+            "The percentage of the instrumented lines of the covered source files executed",
+        ),
+        (
+            "source_files[].statistics.line_coverage",
+            MetricScope::SourceFile,
+            "The percentage of the instrumented lines of the source file executed",
+        ),
+    ] {
+        MetricDefinition {
+            path: path.to_string(),
+            scope,
+            unit: MetricUnit::Percent,
+            window: MetricWindow::Snapshot,
+            description: description.to_string(),
+        }
+        .add_to(&mut repository_info.metrics_catalog);
+    }
+    CoverageSummary {
+        num_files: to_count(
+            coverage_by_index
+                .values()
+                .filter(|(found, _)| *found > 0)
+                .count(),
+        ),
+        line_coverage,
+        unmatched,
+    }
+}
+
+/// Replaces the predefined entities of XML in the `text`
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "git")]
+    use crate::{options::AnalysisOptions, testing::FixtureRepo};
+
+    const LCOV: &str = include_str!("../tests/fixtures/coverage/lcov.info");
+    const COBERTURA: &str = include_str!("../tests/fixtures/coverage/cobertura.xml");
+
+    /// Gets the path, lines found and lines hit of each file of the `report`
+    fn get_files(report: &CoverageReport) -> Vec<(&str, u64, u64)> {
+        report
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.lines_found, file.lines_hit))
+            .collect()
+    }
+
+    #[test]
+    fn detects_the_format() {
+        assert_eq!(CoverageFormat::detect(LCOV), Some(CoverageFormat::Lcov));
+        assert_eq!(
+            CoverageFormat::detect(COBERTURA),
+            Some(CoverageFormat::Cobertura)
+        );
+        assert_eq!(
+            CoverageFormat::detect("\u{feff}  <coverage/>"),
+            Some(CoverageFormat::Cobertura)
+        );
+        assert_eq!(CoverageFormat::detect("not a coverage report"), None);
+        assert!(matches!(
+            CoverageReport::parse("not a coverage report"),
+            Err(SourceCodeError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn merges_the_records_of_an_lcov_file() -> Result<(), SourceCodeError> {
+        let report = CoverageReport::read(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/coverage/lcov.info"),
+        )?;
+        assert_eq!(report.format, CoverageFormat::Lcov);
+        assert!(report.sources.is_empty());
+        // The line records of the two records of 'src/lib.rs' are merged, while the totals are
+        // taken for a file without line records
+        assert_eq!(
+            get_files(&report),
+            vec![
+                ("/build/checkout/src/main.rs", 10, 5),
+                ("generated/bindings.rs", 1, 1),
+                ("src/lib.rs", 4, 3),
+            ]
+        );
+        assert_eq!(report.files[2].line_coverage(), Some(75.0));
+        Ok(())
+    }
+
+    #[test]
+    fn merges_the_classes_of_a_cobertura_file() -> Result<(), SourceCodeError> {
+        let report = CoverageReport::parse(COBERTURA)?;
+        assert_eq!(report.format, CoverageFormat::Cobertura);
+        assert_eq!(report.sources, vec!["/build/checkout/src"]);
+        // The lines of the methods repeat those of their class
+        assert_eq!(
+            get_files(&report),
+            vec![("a & b.rs", 1, 0), ("lib.rs", 3, 2), ("main.rs", 0, 0)]
+        );
+        assert_eq!(report.files[2].line_coverage(), None);
+        Ok(())
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn applies_the_coverage_to_the_source_files() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("src/lib.rs", "pub fn one() -> i32 {\n    1\n}\n")
+            .with_file("src/main.rs", "fn main() {\n    println!(\"{}\", 1);\n}\n")
+            .commit("Add the library and binary")?;
+        let mut repository_info = fixture.analyse(&AnalysisOptions::default())?;
+        let repo_path = fixture.path().to_string_lossy();
+        let summary = apply(
+            &mut repository_info,
+            &repo_path,
+            &CoverageReport::parse(LCOV)?,
+        );
+        assert_eq!(summary.num_files, 2);
+        assert_eq!(summary.unmatched, vec!["generated/bindings.rs"]);
+        let coverage = |path: &str| {
+            repository_info
+                .source_files
+                .iter()
+                .find(|source_file| source_file.relative_path.as_str() == path)
+                .and_then(|source_file| source_file.statistics.line_coverage)
+        };
+        assert_eq!(coverage("src/lib.rs"), Some(75.0));
+        assert_eq!(coverage("src/main.rs"), Some(50.0));
+        // The lines of all the matched files: 8 of 14 hit
+        let line_coverage = repository_info.statistics.line_coverage;
+        assert_eq!(line_coverage, summary.line_coverage);
+        assert!(line_coverage.is_some_and(|coverage| (coverage - 57.142857).abs() < 1e-4));
+        assert!(repository_info
+            .metrics_catalog
+            .iter()
+            .any(|metric| metric.path == "source_files[].statistics.line_coverage"));
+        Ok(())
+    }
+}
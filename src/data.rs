@@ -1,23 +1,71 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+#[cfg(feature = "git")]
+use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    iter::Sum,
+    ops::{Add, AddAssign},
+};
 
-use crate::{errors::SourceCodeError, source::SourceFileChangeFrequency};
+#[cfg(feature = "git")]
+use crate::{errors::SourceCodeError, source::MergeStrategy};
+use crate::{
+    ratio::Percentage,
+    sarif::FindingCounts,
+    source::{SourceFileChangeFrequency, SourceFileInfo},
+};
 
 /// Struct to hold statistics on the code in a repository
 ///
 /// # Fields:
-/// * `size` - The size of the repository in bytes
+/// * `size` - The size in bytes of the raw contents analysed, of a source file or the total of
+///   those of the repository; see [`SourceFileInfo::byte_size`] and
+///   [`SourceFileInfo::git_blob_size`] for the sizes on disk and in git
 /// * `loc` - The number of lines of code in the repository
 /// * `num_file` - The number of files in the repository
 /// * `num_commits` - The number of commits in the repository
-/// * `frequency` - The frequency of commits to the repository, as a ratio of commits to total commits in the repository
+/// * `change_frequency` - The [`Percentage`] of the commits of the repository that changed the
+///   source file; `0.0` where it is undefined, e.g., in a repository without commits, or for
+///   statistics other than a source file's. Serialised as `frequency` before schema version 4.
+/// * `language_share` - The [`Percentage`] of the lines of code of the repository in the
+///   language, for the statistics of a language
+/// * `contribution_share` - The [`Percentage`] of the commits of the repository by the
+///   contributor, for the statistics of a contributor
+/// * `num_markers` - The number of TODO, FIXME, HACK and XXX markers in the comments of the code
+/// * `bugfix_commits` - The number of the commits that are (likely) bug fixes, e.g., 'fix: ...'
+///   or referencing an issue, a proxy for defect density
+/// * `derived` - The values of the [`crate::derived::DerivedMetric`]s, by name
+/// * `custom` - The values of the metrics of the [`crate::provider::MetricProvider`]s, by name,
+///   for the statistics of a source file
+/// * `line_coverage` - The percentage of the instrumented lines executed by the tests, if a
+///   coverage report was applied with [`crate::coverage::apply`]
+/// * `findings` - The [`crate::sarif::FindingCounts`] of the static analysis findings, if a
+///   SARIF log was applied with [`crate::sarif::apply`]
 #[derive(Clone, Default, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Statistics {
-    pub size: i64, // Higher is worse for files; too big to be maintainable
-    pub loc: i64,  // Higher is worse for files; too big to be maintainable
-    pub num_files: i32,
-    pub num_commits: i32,
-    pub frequency: f32, // Higher is worse for files; better for contributors (though worse for a team)
+    pub size: u64, // Higher is worse for files; too big to be maintainable
+    pub loc: u64,  // Higher is worse for files; too big to be maintainable
+    pub num_files: u64,
+    pub num_commits: u64,
+    #[serde(alias = "frequency")]
+    pub change_frequency: Percentage, // Higher is worse for files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_share: Option<Percentage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contribution_share: Option<Percentage>, // Better for contributors (though worse for a team)
+    #[serde(default)]
+    pub num_markers: u64,
+    #[serde(default)]
+    pub bugfix_commits: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub derived: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_coverage: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub findings: Option<FindingCounts>,
 }
 impl Statistics {
     pub fn new() -> Self {
@@ -26,22 +74,248 @@ impl Statistics {
             loc: 0,
             num_files: 0,
             num_commits: 0,
-            frequency: 0.0,
+            change_frequency: Percentage::default(),
+            language_share: None,
+            contribution_share: None,
+            num_markers: 0,
+            bugfix_commits: 0,
+            derived: BTreeMap::new(),
+            custom: BTreeMap::new(),
+            line_coverage: None,
+            findings: None,
         }
     }
-    /// Gets a [`Statistics`] struct for a given source file path
+    /// Gets a [`Statistics`] struct for a given source file path, counting merge commits with
+    /// `merge_strategy`
+    #[cfg(feature = "git")]
     pub fn get_statistics_for_source_file(
         repo_path: &str,
-        source_file_path: &PathBuf,
+        source_file_path: &Path,
+        merge_strategy: MergeStrategy,
     ) -> Result<Self, SourceCodeError> {
-        let scf = SourceFileChangeFrequency::get_from_source_file(repo_path, source_file_path)?;
+        let scf = SourceFileChangeFrequency::get_from_source_file(
+            repo_path,
+            source_file_path,
+            merge_strategy,
+        )?;
 
-        Ok(Self {
+        Ok(Self::new_from_change_frequency(&scf))
+    }
+    /// Gets a [`Statistics`] struct for a source file from its [`SourceFileChangeFrequency`]
+    pub(crate) fn new_from_change_frequency(scf: &SourceFileChangeFrequency) -> Self {
+        Self {
             size: 0, // Should be sourced from tokei
             loc: 0,  // Should be sourced from tokei
             num_files: 1,
             num_commits: scf.file_commits,
-            frequency: scf.frequency,
-        })
+            change_frequency: scf.frequency.unwrap_or_default(),
+            language_share: None,
+            contribution_share: None,
+            num_markers: 0,
+            bugfix_commits: scf.bugfix_commits,
+            derived: BTreeMap::new(),
+            custom: BTreeMap::new(),
+            line_coverage: None,
+            findings: None,
+        }
+    }
+    /// Merges the `other` statistics into these, as adding them does, except that each
+    /// percentage, i.e., the `change_frequency`, `language_share` and `contribution_share`, is
+    /// the mean of the two weighted by `weight` and `other_weight`, e.g., the totals each is a
+    /// percentage of, so that the percentages of parts with different totals, e.g., of two
+    /// repositories, can be combined
+    pub fn merge_weighted(&self, other: &Statistics, weight: f64, other_weight: f64) -> Self {
+        let mean = |percentage: Percentage, other_percentage: Percentage| match weight
+            + other_weight
+            > 0.0
+        {
+            true => Percentage(
+                ((percentage.0 as f64 * weight + other_percentage.0 as f64 * other_weight)
+                    / (weight + other_weight)) as f32,
+            ),
+            false => Percentage::default(),
+        };
+        let mean_of =
+            |share: Option<Percentage>, other_share: Option<Percentage>| match (share, other_share)
+            {
+                (None, None) => None,
+                (share, other_share) => Some(mean(
+                    share.unwrap_or_default(),
+                    other_share.unwrap_or_default(),
+                )),
+            };
+        let mut merged = self.clone() + other;
+        merged.change_frequency = mean(self.change_frequency, other.change_frequency);
+        merged.language_share = mean_of(self.language_share, other.language_share);
+        merged.contribution_share = mean_of(self.contribution_share, other.contribution_share);
+        merged
+    }
+}
+/// Adds the `other` statistics of a part, e.g., of a source file, to these of the whole, e.g.,
+/// of a directory, language or package: the sizes, lines and counts are summed, saturating at
+/// `u64::MAX` rather than wrapping, as are the shares, which are taken as shares of the same
+/// total, e.g., of the lines of code of the repository (see [`Statistics::merge_weighted`]
+/// otherwise); the `change_frequency`, which is only defined for a source file, is `0.0`; the
+/// `line_coverage` is the mean, weighted by the lines of code, of those that are known; and the
+/// `derived` and `custom` metrics, which aren't additive, are cleared
+impl AddAssign<&Statistics> for Statistics {
+    fn add_assign(&mut self, other: &Statistics) {
+        self.line_coverage = match (self.line_coverage, other.line_coverage) {
+            (Some(coverage), Some(other_coverage)) => match self.loc.saturating_add(other.loc) {
+                0 => Some((coverage + other_coverage) / 2.0),
+                loc => Some(
+                    ((coverage as f64 * self.loc as f64 + other_coverage as f64 * other.loc as f64)
+                        / loc as f64) as f32,
+                ),
+            },
+            (coverage, other_coverage) => coverage.or(other_coverage),
+        };
+        self.findings = match (self.findings, other.findings) {
+            (Some(mut findings), Some(other_findings)) => {
+                findings.add_counts(&other_findings);
+                Some(findings)
+            }
+            (findings, other_findings) => findings.or(other_findings),
+        };
+        self.size = self.size.saturating_add(other.size);
+        self.loc = self.loc.saturating_add(other.loc);
+        self.num_files = self.num_files.saturating_add(other.num_files);
+        self.num_commits = self.num_commits.saturating_add(other.num_commits);
+        self.change_frequency = Percentage::default();
+        self.language_share = add_shares(self.language_share, other.language_share);
+        self.contribution_share = add_shares(self.contribution_share, other.contribution_share);
+        self.num_markers = self.num_markers.saturating_add(other.num_markers);
+        self.bugfix_commits = self.bugfix_commits.saturating_add(other.bugfix_commits);
+        self.derived.clear();
+        self.custom.clear();
+    }
+}
+/// Adds the `share` and `other_share`, if either is known
+fn add_shares(share: Option<Percentage>, other_share: Option<Percentage>) -> Option<Percentage> {
+    match (share, other_share) {
+        (None, None) => None,
+        (share, other_share) => Some(Percentage(
+            share.unwrap_or_default().0 + other_share.unwrap_or_default().0,
+        )),
+    }
+}
+impl AddAssign for Statistics {
+    fn add_assign(&mut self, other: Statistics) {
+        *self += &other;
+    }
+}
+impl Add<&Statistics> for Statistics {
+    type Output = Statistics;
+    fn add(mut self, other: &Statistics) -> Statistics {
+        self += other;
+        self
+    }
+}
+impl Add for Statistics {
+    type Output = Statistics;
+    fn add(mut self, other: Statistics) -> Statistics {
+        self += &other;
+        self
+    }
+}
+impl<'a> Sum<&'a Statistics> for Statistics {
+    fn sum<I: Iterator<Item = &'a Statistics>>(iter: I) -> Statistics {
+        iter.fold(Statistics::new(), |total, statistics| total + statistics)
+    }
+}
+impl Sum for Statistics {
+    fn sum<I: Iterator<Item = Statistics>>(iter: I) -> Statistics {
+        iter.fold(Statistics::new(), |total, statistics| total + statistics)
+    }
+}
+/// Converts a signed `count`, e.g., of the commits of a [`crate::history::CommitHistory`], to
+/// an unsigned one, checked, as zero if it is negative
+pub(crate) fn to_count(count: impl TryInto<u64>) -> u64 {
+    count.try_into().unwrap_or_default()
+}
+/// Sums the `counts`, e.g., the lines of code of the source files, saturating at `u64::MAX`
+/// rather than wrapping
+pub(crate) fn saturating_sum(counts: impl IntoIterator<Item = u64>) -> u64 {
+    counts.into_iter().fold(0, u64::saturating_add)
+}
+/// Gets the signed change in a count from `base` to `head`, saturating at the bounds of `i64`
+pub(crate) fn count_delta(base: u64, head: u64) -> i64 {
+    i64::try_from(head as i128 - base as i128).unwrap_or(match head > base {
+        true => i64::MAX,
+        false => i64::MIN,
+    })
+}
+
+/// The distribution of a statistic over the source files of a repository
+///
+/// #Fields:
+/// * `min` - The smallest value
+/// * `max` - The largest value
+/// * `mean` - The mean value
+/// * `median` - The median value, i.e., half the files have a smaller value
+/// * `p90` - The 90th percentile, i.e., 90% of the files have a smaller value
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Distribution {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+}
+impl Distribution {
+    /// Gets the [`Distribution`] of the `values`, with the percentiles interpolated linearly
+    /// between the nearest values; all zero if there are none
+    pub fn new(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f64::total_cmp);
+        Self {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            median: Self::percentile(&values, 0.5),
+            p90: Self::percentile(&values, 0.9),
+        }
+    }
+    /// Gets the `fraction` (0.0 - 1.0) percentile of the (sorted, non-empty) `values`
+    fn percentile(values: &[f64], fraction: f64) -> f64 {
+        let rank = fraction * (values.len() - 1) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)
+    }
+}
+
+/// Summaries of how the lines of code, size and churn are distributed over the source files of
+/// a repository, e.g., to report that half the files are under 120 lines of code
+///
+/// #Fields:
+/// * `loc` - The [`Distribution`] of the lines of code of the files
+/// * `size` - The [`Distribution`] of the size of the files, in bytes
+/// * `churn` - The [`Distribution`] of the churn of the files, i.e., the commits changing them
+#[derive(Clone, Default, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StatisticsSummary {
+    pub loc: Distribution,
+    pub size: Distribution,
+    pub churn: Distribution,
+}
+impl StatisticsSummary {
+    /// Gets the [`StatisticsSummary`] of the `source_files`
+    pub fn new(source_files: &[SourceFileInfo]) -> Self {
+        let distribution = |value: fn(&Statistics) -> f64| {
+            Distribution::new(
+                source_files
+                    .iter()
+                    .map(|source_file| value(&source_file.statistics))
+                    .collect(),
+            )
+        };
+        Self {
+            loc: distribution(|statistics| statistics.loc as f64),
+            size: distribution(|statistics| statistics.size as f64),
+            churn: distribution(|statistics| statistics.num_commits as f64),
+        }
     }
 }
@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    data::{count_delta, saturating_sum},
+    errors::SourceCodeError,
+    repository::RepositoryInfo,
+    source::SourceFileInfo,
+};
+
+/// The differences between two [`RepositoryInfo`] snapshots, from a `base` to a `head` snapshot
+///
+/// #Fields:
+/// * `added_files` - The relative paths of files only present in `head`
+/// * `removed_files` - The relative paths of files only present in `base`
+/// * `changed_files` - The [`FileDelta`]s for files present in both whose contents changed
+/// * `languages` - The [`LanguageDelta`]s for every language present in either snapshot
+/// * `new_contributors` - The names of contributors only present in `head`
+/// * `departed_contributors` - The names of contributors only present in `base`
+/// * `loc_delta` - The change in total lines of code
+/// * `size_delta` - The change in total size, in bytes
+/// * `num_commits_delta` - The change in the number of commits
+/// * `churn_delta` - The change in churn, i.e., the sum of the per-file commit counts
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct RepositoryDelta {
+    pub added_files: Vec<String>,
+    pub removed_files: Vec<String>,
+    pub changed_files: Vec<FileDelta>,
+    pub languages: Vec<LanguageDelta>,
+    pub new_contributors: Vec<String>,
+    pub departed_contributors: Vec<String>,
+    pub loc_delta: i64,
+    pub size_delta: i64,
+    pub num_commits_delta: i64,
+    pub churn_delta: i64,
+}
+impl RepositoryDelta {
+    /// Computes the [`RepositoryDelta`] from `base` to `head`
+    pub fn new(base: &RepositoryInfo, head: &RepositoryInfo) -> Self {
+        let base_files: HashMap<&str, &SourceFileInfo> = Self::index_files(base);
+        let head_files: HashMap<&str, &SourceFileInfo> = Self::index_files(head);
+
+        let mut added_files: Vec<String> = head_files
+            .keys()
+            .filter(|path| !base_files.contains_key(*path))
+            .map(|path| path.to_string())
+            .collect();
+        added_files.sort();
+        let mut removed_files: Vec<String> = base_files
+            .keys()
+            .filter(|path| !head_files.contains_key(*path))
+            .map(|path| path.to_string())
+            .collect();
+        removed_files.sort();
+        let mut changed_files: Vec<FileDelta> = head_files
+            .iter()
+            .filter_map(|(path, head_file)| {
+                let base_file = base_files.get(path)?;
+                (base_file.id_hash != head_file.id_hash)
+                    .then(|| FileDelta::new(path, base_file, head_file))
+            })
+            .collect();
+        changed_files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        let base_contributors: HashSet<&str> = base
+            .contributors
+            .iter()
+            .map(|c| c.author.name.as_str())
+            .collect();
+        let head_contributors: HashSet<&str> = head
+            .contributors
+            .iter()
+            .map(|c| c.author.name.as_str())
+            .collect();
+        let mut new_contributors: Vec<String> = head_contributors
+            .difference(&base_contributors)
+            .map(|name| name.to_string())
+            .collect();
+        new_contributors.sort();
+        let mut departed_contributors: Vec<String> = base_contributors
+            .difference(&head_contributors)
+            .map(|name| name.to_string())
+            .collect();
+        departed_contributors.sort();
+
+        Self {
+            added_files,
+            removed_files,
+            changed_files,
+            languages: LanguageDelta::new_from(base, head),
+            new_contributors,
+            departed_contributors,
+            loc_delta: count_delta(base.statistics.loc, head.statistics.loc),
+            size_delta: count_delta(base.statistics.size, head.statistics.size),
+            num_commits_delta: count_delta(
+                base.statistics.num_commits,
+                head.statistics.num_commits,
+            ),
+            churn_delta: count_delta(Self::get_churn(base), Self::get_churn(head)),
+        }
+    }
+    /// Checks whether the two snapshots have no file, language or contributor differences
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty()
+            && self.removed_files.is_empty()
+            && self.changed_files.is_empty()
+            && self.new_contributors.is_empty()
+            && self.departed_contributors.is_empty()
+            && self.languages.iter().all(|l| l.loc_delta == 0)
+    }
+    /// Gets the [`RepositoryDelta`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    fn index_files(repository_info: &RepositoryInfo) -> HashMap<&str, &SourceFileInfo> {
+        repository_info
+            .source_files
+            .iter()
+            .map(|sfi| (sfi.relative_path.as_str(), sfi))
+            .collect()
+    }
+    /// Gets the churn of a snapshot, i.e., the sum of the per-file commit counts
+    pub(crate) fn get_churn(repository_info: &RepositoryInfo) -> u64 {
+        saturating_sum(
+            repository_info
+                .source_files
+                .iter()
+                .map(|sfi| sfi.statistics.num_commits),
+        )
+    }
+}
+
+/// The change to a single file between two snapshots
+///
+/// #Fields:
+/// * `relative_path` - The relative path of the file
+/// * `loc_delta` - The change in lines of code
+/// * `size_delta` - The change in size, in bytes
+/// * `num_commits_delta` - The change in the number of commits touching the file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FileDelta {
+    pub relative_path: String,
+    pub loc_delta: i64,
+    pub size_delta: i64,
+    pub num_commits_delta: i64,
+}
+impl FileDelta {
+    fn new(relative_path: &str, base: &SourceFileInfo, head: &SourceFileInfo) -> Self {
+        Self {
+            relative_path: relative_path.to_string(),
+            loc_delta: count_delta(base.statistics.loc, head.statistics.loc),
+            size_delta: count_delta(base.statistics.size, head.statistics.size),
+            num_commits_delta: count_delta(
+                base.statistics.num_commits,
+                head.statistics.num_commits,
+            ),
+        }
+    }
+}
+
+/// The change in lines of code for a language between two snapshots
+///
+/// #Fields:
+/// * `language` - The name of the language
+/// * `base_loc` - The lines of code in the `base` snapshot
+/// * `head_loc` - The lines of code in the `head` snapshot
+/// * `loc_delta` - The change in lines of code
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LanguageDelta {
+    pub language: String,
+    pub base_loc: u64,
+    pub head_loc: u64,
+    pub loc_delta: i64,
+}
+impl LanguageDelta {
+    fn new_from(base: &RepositoryInfo, head: &RepositoryInfo) -> Vec<Self> {
+        let mut totals: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+        for sfi in &base.source_files {
+            if let Some(language) = &sfi.language {
+                let total = totals.entry(language.name.as_str()).or_insert((0, 0));
+                total.0 = total.0.saturating_add(sfi.statistics.loc);
+            }
+        }
+        for sfi in &head.source_files {
+            if let Some(language) = &sfi.language {
+                let total = totals.entry(language.name.as_str()).or_insert((0, 0));
+                total.1 = total.1.saturating_add(sfi.statistics.loc);
+            }
+        }
+        totals
+            .into_iter()
+            .map(|(language, (base_loc, head_loc))| Self {
+                language: language.to_string(),
+                base_loc,
+                head_loc,
+                loc_delta: count_delta(base_loc, head_loc),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,508 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use crate::{data::to_count, source::SourceFileInfo, walk};
+
+/// The tag that declares the license of a source file in its header
+const SPDX_TAG: &str = "SPDX-License-Identifier:";
+/// The number of lines at the start of a source file searched for an SPDX header
+const SPDX_HEADER_LINES: usize = 20;
+/// The file name prefixes (upper case) of license files, e.g., 'LICENSE-MIT' or 'COPYING.txt'
+const LICENSE_FILE_PREFIXES: [&str; 3] = ["LICENSE", "LICENCE", "COPYING"];
+/// The number of non-empty lines at the start of a license file searched for its title
+const LICENSE_TITLE_LINES: usize = 8;
+/// The phrases of the title of common licenses, by SPDX identifier, matched in the first lines
+/// of a license file, as their texts refer to each other, e.g., the GPL-3.0 to the AGPL-3.0;
+/// the license whose title comes first is taken, or of those with the same title, the first
+/// in order, the most specific first, e.g., version 2.1 before version 2
+const LICENSE_TITLES: [(&str, &[&str]); 8] = [
+    (
+        "AGPL-3.0",
+        &["gnu affero general public license", "version 3"],
+    ),
+    (
+        "LGPL-3.0",
+        &["gnu lesser general public license", "version 3"],
+    ),
+    (
+        "LGPL-2.1",
+        &["gnu lesser general public license", "version 2.1"],
+    ),
+    (
+        "LGPL-2.0",
+        &["gnu library general public license", "version 2"],
+    ),
+    ("GPL-3.0", &["gnu general public license", "version 3"]),
+    ("GPL-2.0", &["gnu general public license", "version 2"]),
+    ("Apache-2.0", &["apache license", "version 2.0"]),
+    ("MPL-2.0", &["mozilla public license", "2.0"]),
+];
+/// Phrases of the text of common licenses without a title, by SPDX identifier, checked in
+/// order so that more specific licenses (e.g., the BSD-3-Clause) are matched before those whose
+/// phrases they contain
+const LICENSE_PHRASES: [(&str, &[&str]); 5] = [
+    (
+        "Apache-2.0",
+        &["licensed under the apache license, version 2.0"],
+    ),
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["redistribution and use in source and binary forms"],
+    ),
+    ("MIT", &["permission is hereby granted, free of charge"]),
+    (
+        "Unlicense",
+        &["free and unencumbered software released into the public domain"],
+    ),
+];
+
+/// The identifiers, separated by whitespace, of the licenses of the SPDX license list that the
+/// SPDX headers are checked against, i.e., the licenses in common use, with the deprecated
+/// identifiers still seen in headers, e.g., 'GPL-2.0'; compared case-insensitively, as the SPDX
+/// specification requires
+const SPDX_LICENSE_IDS: &str = "\
+    0BSD AAL AFL-1.1 AFL-1.2 AFL-2.0 AFL-2.1 AFL-3.0 AGPL-1.0 AGPL-1.0-only AGPL-1.0-or-later \
+    AGPL-3.0 AGPL-3.0-only AGPL-3.0-or-later Apache-1.0 Apache-1.1 Apache-2.0 APSL-1.0 \
+    APSL-1.1 APSL-1.2 APSL-2.0 Artistic-1.0 Artistic-1.0-Perl Artistic-2.0 Beerware \
+    BlueOak-1.0.0 BSD-1-Clause BSD-2-Clause BSD-2-Clause-Patent BSD-3-Clause \
+    BSD-3-Clause-Clear BSD-3-Clause-LBNL BSD-4-Clause BSL-1.0 BUSL-1.1 bzip2-1.0.6 CAL-1.0 \
+    CC-BY-1.0 CC-BY-2.0 CC-BY-2.5 CC-BY-3.0 CC-BY-4.0 CC-BY-NC-4.0 CC-BY-NC-SA-4.0 \
+    CC-BY-ND-4.0 CC-BY-SA-3.0 CC-BY-SA-4.0 CC0-1.0 CDDL-1.0 CDDL-1.1 CECILL-2.1 CECILL-B \
+    CECILL-C CPAL-1.0 CPL-1.0 curl ECL-2.0 EFL-2.0 EPL-1.0 EPL-2.0 EUPL-1.1 EUPL-1.2 FSFAP \
+    FSFUL FTL GFDL-1.3 GFDL-1.3-only GFDL-1.3-or-later GPL-1.0 GPL-1.0-only GPL-1.0-or-later \
+    GPL-2.0 GPL-2.0-only GPL-2.0-or-later GPL-3.0 GPL-3.0-only GPL-3.0-or-later HPND ICU IJG \
+    ImageMagick IPA IPL-1.0 ISC JSON LGPL-2.0 LGPL-2.0-only LGPL-2.0-or-later LGPL-2.1 \
+    LGPL-2.1-only LGPL-2.1-or-later LGPL-3.0 LGPL-3.0-only LGPL-3.0-or-later Libpng \
+    libpng-2.0 LPL-1.02 LPPL-1.3c MirOS MIT MIT-0 MIT-CMU MIT-Modern-Variant MPL-1.0 MPL-1.1 \
+    MPL-2.0 MPL-2.0-no-copyleft-exception MS-PL MS-RL MulanPSL-2.0 NCSA NTP ODbL-1.0 OFL-1.0 \
+    OFL-1.1 OGL-UK-3.0 OpenSSL OSL-1.0 OSL-2.0 OSL-2.1 OSL-3.0 PHP-3.0 PHP-3.01 PostgreSQL \
+    PSF-2.0 Python-2.0 Python-2.0.1 QPL-1.0 Ruby SGI-B-2.0 SISSL SMLNJ Sleepycat SSPL-1.0 TCL \
+    UCL-1.0 Unicode-3.0 Unicode-DFS-2015 Unicode-DFS-2016 Unlicense UPL-1.0 Vim W3C \
+    W3C-20150513 WTFPL X11 XFree86-1.1 Xnet Zend-2.0 Zlib zlib-acknowledgement ZPL-2.0 \
+    ZPL-2.1";
+
+/// The ecosystem of a dependency manifest
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyEcosystem {
+    /// 'Cargo.toml'
+    Cargo,
+    /// 'package.json'
+    Npm,
+    /// 'go.mod'
+    Go,
+    /// 'requirements.txt'
+    Python,
+}
+impl DependencyEcosystem {
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        match file_name {
+            "Cargo.toml" => Some(DependencyEcosystem::Cargo),
+            "package.json" => Some(DependencyEcosystem::Npm),
+            "go.mod" => Some(DependencyEcosystem::Go),
+            "requirements.txt" => Some(DependencyEcosystem::Python),
+            _ => None,
+        }
+    }
+}
+
+/// The direct dependencies declared in a dependency manifest
+///
+/// #Fields:
+/// * `path` - The relative path of the manifest
+/// * `ecosystem` - The [`DependencyEcosystem`] of the manifest
+/// * `dependencies` - The names of the (runtime) dependencies, in alphabetical order
+/// * `dev_dependencies` - The names of the development and build dependencies, in alphabetical
+///   order
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DependencyManifest {
+    pub path: String,
+    pub ecosystem: DependencyEcosystem,
+    pub dependencies: Vec<String>,
+    pub dev_dependencies: Vec<String>,
+}
+impl DependencyManifest {
+    /// Parses the manifest at the relative `path` with `contents`, or `None` if it isn't valid
+    fn parse(path: &str, ecosystem: DependencyEcosystem, contents: &str) -> Option<Self> {
+        let (dependencies, dev_dependencies) = match ecosystem {
+            DependencyEcosystem::Cargo => Self::parse_cargo(contents)?,
+            DependencyEcosystem::Npm => Self::parse_package_json(contents)?,
+            DependencyEcosystem::Go => (Self::parse_go_mod(contents), BTreeSet::new()),
+            DependencyEcosystem::Python => (Self::parse_requirements(contents), BTreeSet::new()),
+        };
+        Some(Self {
+            path: path.to_string(),
+            ecosystem,
+            dependencies: dependencies.into_iter().collect(),
+            dev_dependencies: dev_dependencies.into_iter().collect(),
+        })
+    }
+    /// Gets the keys of the `[dependencies]` and the `[dev-dependencies]` and
+    /// `[build-dependencies]` tables, including those for specific targets
+    fn parse_cargo(contents: &str) -> Option<(BTreeSet<String>, BTreeSet<String>)> {
+        let manifest = contents.parse::<toml::Table>().ok()?;
+        let targets = manifest
+            .get("target")
+            .and_then(|targets| targets.as_table())
+            .into_iter()
+            .flat_map(|targets| targets.values().filter_map(|target| target.as_table()));
+        let mut dependencies: BTreeSet<String> = BTreeSet::new();
+        let mut dev_dependencies: BTreeSet<String> = BTreeSet::new();
+        for table in std::iter::once(&manifest).chain(targets) {
+            let keys = |name: &str| {
+                table
+                    .get(name)
+                    .and_then(|dependencies| dependencies.as_table())
+                    .into_iter()
+                    .flat_map(|dependencies| dependencies.keys().cloned())
+            };
+            dependencies.extend(keys("dependencies"));
+            dev_dependencies.extend(keys("dev-dependencies").chain(keys("build-dependencies")));
+        }
+        Some((dependencies, dev_dependencies))
+    }
+    /// Gets the keys of the `dependencies`, `peerDependencies` and `optionalDependencies`, and
+    /// of the `devDependencies`
+    fn parse_package_json(contents: &str) -> Option<(BTreeSet<String>, BTreeSet<String>)> {
+        let package = serde_json::from_str::<serde_json::Value>(contents).ok()?;
+        let keys = |name: &str| {
+            package
+                .get(name)
+                .and_then(|dependencies| dependencies.as_object())
+                .into_iter()
+                .flat_map(|dependencies| dependencies.keys().cloned())
+                .collect::<Vec<String>>()
+        };
+        let dependencies = ["dependencies", "peerDependencies", "optionalDependencies"]
+            .into_iter()
+            .flat_map(keys)
+            .collect();
+        Some((dependencies, keys("devDependencies").into_iter().collect()))
+    }
+    /// Gets the modules of the `require` directives, except those marked `// indirect`
+    fn parse_go_mod(contents: &str) -> BTreeSet<String> {
+        let mut dependencies: BTreeSet<String> = BTreeSet::new();
+        let mut in_require_block = false;
+        for line in contents.lines().map(str::trim) {
+            let requirement = match in_require_block {
+                true if line == ")" => {
+                    in_require_block = false;
+                    continue;
+                }
+                true => line,
+                false if line == "require (" => {
+                    in_require_block = true;
+                    continue;
+                }
+                false => match line.strip_prefix("require ") {
+                    Some(requirement) => requirement,
+                    None => continue,
+                },
+            };
+            if requirement.contains("// indirect") {
+                continue;
+            }
+            if let Some(module) = requirement.split_whitespace().next() {
+                if !module.starts_with("//") {
+                    dependencies.insert(module.to_string());
+                }
+            }
+        }
+        dependencies
+    }
+    /// Gets the (lower case) project names of the requirements, skipping comments and options,
+    /// e.g., '-r other.txt'
+    fn parse_requirements(contents: &str) -> BTreeSet<String> {
+        contents
+            .lines()
+            .map(|line| line.split(" #").next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty() && !line.starts_with(['#', '-']))
+            .filter_map(|line| {
+                let name = line
+                    .split(['=', '<', '>', '!', '~', ';', '[', '@', ' '])
+                    .next()?
+                    .trim();
+                (!name.is_empty()).then(|| name.to_lowercase())
+            })
+            .collect()
+    }
+}
+
+/// The number of direct dependencies of an ecosystem, over all of its manifests in the
+/// repository; a dependency of several manifests, e.g., in a workspace, is counted once
+///
+/// #Fields:
+/// * `ecosystem` - The [`DependencyEcosystem`]
+/// * `num_manifests` - The number of manifests of the ecosystem
+/// * `num_dependencies` - The number of distinct (runtime) dependencies
+/// * `num_dev_dependencies` - The number of distinct development and build dependencies
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EcosystemDependencies {
+    pub ecosystem: DependencyEcosystem,
+    pub num_manifests: u64,
+    pub num_dependencies: u64,
+    pub num_dev_dependencies: u64,
+}
+
+/// How a [`DetectedLicense`] was detected
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseEvidence {
+    /// A license file in the root of the repository, e.g., 'LICENSE'
+    LicenseFile,
+    /// `SPDX-License-Identifier` headers in source files
+    SpdxHeader,
+}
+
+/// A license the repository, or some of its source files, are under
+///
+/// #Fields:
+/// * `spdx_id` - The SPDX identifier (or expression) of the license, e.g., 'MIT', or `None` if a
+///   license file's text wasn't recognised
+/// * `evidence` - The [`LicenseEvidence`] of the license
+/// * `detected_in` - The path of the license file, or of the first source file with the header
+/// * `num_files` - The number of files the license was detected in
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DetectedLicense {
+    pub spdx_id: Option<String>,
+    pub evidence: LicenseEvidence,
+    pub detected_in: String,
+    pub num_files: u64,
+}
+
+/// The licenses and direct dependencies of a repository, from its license files, SPDX headers
+/// and its 'Cargo.toml', 'package.json', 'go.mod' and 'requirements.txt' manifests
+///
+/// #Fields:
+/// * `licenses` - The [`DetectedLicense`]s, license files first
+/// * `manifests` - The [`DependencyManifest`]s, in path order
+/// * `ecosystems` - The [`EcosystemDependencies`] of each ecosystem with a manifest
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DependencySummary {
+    pub licenses: Vec<DetectedLicense>,
+    pub manifests: Vec<DependencyManifest>,
+    pub ecosystems: Vec<EcosystemDependencies>,
+}
+impl DependencySummary {
+    /// Detects the licenses and dependencies of the repository at `repo_path`, skipping
+    /// vendored directories. SPDX headers are read from the [`SourceFileInfo`] contents.
+    pub fn detect(repo_path: &str, source_files: &[SourceFileInfo]) -> DependencySummary {
+        let mut manifests: Vec<DependencyManifest> = walk::read_files(repo_path, |name| {
+            DependencyEcosystem::from_file_name(name).is_some()
+        })
+        .into_iter()
+        .filter_map(|(path, contents)| {
+            let file_name = Path::new(&path).file_name()?.to_str()?;
+            let ecosystem = DependencyEcosystem::from_file_name(file_name)?;
+            let manifest = DependencyManifest::parse(&path, ecosystem, &contents);
+            if manifest.is_none() {
+                tracing::warn!("Failed to parse dependency manifest: {}", path);
+            }
+            manifest
+        })
+        .collect();
+        manifests.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut ecosystems: BTreeMap<DependencyEcosystem, (u64, BTreeSet<&str>, BTreeSet<&str>)> =
+            BTreeMap::new();
+        for manifest in &manifests {
+            let entry = ecosystems.entry(manifest.ecosystem).or_default();
+            entry.0 += 1;
+            entry
+                .1
+                .extend(manifest.dependencies.iter().map(String::as_str));
+            entry
+                .2
+                .extend(manifest.dev_dependencies.iter().map(String::as_str));
+        }
+        let ecosystems = ecosystems
+            .into_iter()
+            .map(
+                |(ecosystem, (num_manifests, dependencies, dev_dependencies))| {
+                    EcosystemDependencies {
+                        ecosystem,
+                        num_manifests,
+                        num_dependencies: to_count(dependencies.len()),
+                        num_dev_dependencies: to_count(dev_dependencies.len()),
+                    }
+                },
+            )
+            .collect();
+
+        let mut licenses = Self::detect_license_files(repo_path);
+        licenses.extend(Self::detect_spdx_headers(source_files));
+        DependencySummary {
+            licenses,
+            manifests,
+            ecosystems,
+        }
+    }
+    /// Detects the licenses of the license files in the root of the repository
+    fn detect_license_files(repo_path: &str) -> Vec<DetectedLicense> {
+        let mut licenses: Vec<DetectedLicense> = walk::read_files(repo_path, |name| {
+            let name = name.to_uppercase();
+            LICENSE_FILE_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        })
+        .into_iter()
+        .filter(|(path, _)| !path.contains('/'))
+        .map(|(path, contents)| DetectedLicense {
+            spdx_id: Self::identify_license(&contents),
+            evidence: LicenseEvidence::LicenseFile,
+            detected_in: path,
+            num_files: 1,
+        })
+        .collect();
+        licenses.sort_by(|a, b| a.detected_in.cmp(&b.detected_in));
+        licenses
+    }
+    /// Identifies the SPDX identifier of a license from its text, by an SPDX tag, the title of
+    /// common licenses in its first lines, or else the phrases of common licenses
+    fn identify_license(contents: &str) -> Option<String> {
+        if let Some(spdx_id) = contents
+            .lines()
+            .find_map(|line| Self::get_spdx_id(line, false))
+        {
+            return Some(spdx_id);
+        }
+        let normalise = |text: &str| {
+            text.split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .to_lowercase()
+        };
+        let title = normalise(
+            &contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .take(LICENSE_TITLE_LINES)
+                .collect::<Vec<&str>>()
+                .join(" "),
+        );
+        // The title comes first, before any references to other licenses, e.g., in the
+        // preamble of the LGPL to the GPL
+        let titled = LICENSE_TITLES
+            .iter()
+            .filter(|(_, phrases)| phrases.iter().all(|phrase| title.contains(phrase)))
+            .min_by_key(|(_, phrases)| title.find(phrases[0]))
+            .map(|(spdx_id, _)| spdx_id.to_string());
+        titled.or_else(|| Self::find_license(&normalise(contents), &LICENSE_PHRASES))
+    }
+    /// Gets the SPDX identifier of the first of the `licenses` whose phrases are all in the
+    /// (normalised) `text`
+    fn find_license(text: &str, licenses: &[(&str, &[&str])]) -> Option<String> {
+        licenses
+            .iter()
+            .find(|(_, phrases)| phrases.iter().all(|phrase| text.contains(phrase)))
+            .map(|(spdx_id, _)| spdx_id.to_string())
+    }
+    /// Detects the licenses of the `SPDX-License-Identifier` headers of the source files
+    fn detect_spdx_headers(source_files: &[SourceFileInfo]) -> Vec<DetectedLicense> {
+        let mut licenses: BTreeMap<String, DetectedLicense> = BTreeMap::new();
+        for source_file in source_files {
+            let Some(spdx_id) = source_file.get_contents().and_then(|contents| {
+                contents
+                    .lines()
+                    .take(SPDX_HEADER_LINES)
+                    .find_map(|line| Self::get_spdx_id(line, true))
+            }) else {
+                continue;
+            };
+            licenses
+                .entry(spdx_id.clone())
+                .or_insert_with(|| DetectedLicense {
+                    spdx_id: Some(spdx_id),
+                    evidence: LicenseEvidence::SpdxHeader,
+                    detected_in: source_file.relative_path.to_string(),
+                    num_files: 0,
+                })
+                .num_files += 1;
+        }
+        licenses.into_values().collect()
+    }
+    /// Gets the SPDX identifier (or expression) of an `SPDX-License-Identifier` tag in a `line`,
+    /// without any comment terminator after it
+    ///
+    /// #Arguments:
+    /// * `line` - The line of a source or license file
+    /// * `in_comment` - Whether the tag must be in a comment, i.e., preceded by nothing but a
+    ///   comment marker, e.g., '//' or '#', so that, e.g., a string literal of the tag isn't
+    ///   taken for a header
+    ///
+    /// #Returns:
+    /// * The identifier, or `None` if the line has no tag, or a license of its expression isn't
+    ///   on the SPDX license list
+    fn get_spdx_id(line: &str, in_comment: bool) -> Option<String> {
+        let (before, spdx_id) = line.split_once(SPDX_TAG)?;
+        if in_comment && !Self::is_comment_marker(before.trim()) {
+            return None;
+        }
+        let spdx_id = spdx_id
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim();
+        Self::is_spdx_expression(spdx_id).then(|| spdx_id.to_string())
+    }
+    /// Checks whether the `text` before a tag is only a comment marker, e.g., '//', '#', '/*',
+    /// '*', '--', '<!--' or ';'
+    fn is_comment_marker(text: &str) -> bool {
+        let rem = text.eq_ignore_ascii_case("rem");
+        rem || (!text.is_empty() && text.chars().all(|c| "/*#;%!<-'({".contains(c)))
+    }
+    /// Checks whether the `expression` is a valid SPDX license expression, e.g., 'MIT OR
+    /// Apache-2.0' or 'GPL-2.0-or-later WITH Classpath-exception-2.0', whose licenses are all on
+    /// the SPDX license list, or are user-defined 'LicenseRef-' licenses
+    fn is_spdx_expression(expression: &str) -> bool {
+        let tokens: Vec<&str> = expression
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .filter(|token| !token.is_empty())
+            .collect();
+        let mut is_exception = false;
+        let mut num_licenses = 0;
+        for token in tokens {
+            match token.to_uppercase().as_str() {
+                "AND" | "OR" => is_exception = false,
+                "WITH" => is_exception = true,
+                _ if is_exception => {
+                    let is_id = token
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+                    if !is_id {
+                        return false;
+                    }
+                }
+                _ => {
+                    let license = token.trim_end_matches('+');
+                    let is_listed = license.starts_with("LicenseRef-")
+                        || license.starts_with("DocumentRef-")
+                        || SPDX_LICENSE_IDS
+                            .split_whitespace()
+                            .any(|id| id.eq_ignore_ascii_case(license));
+                    if !is_listed {
+                        return false;
+                    }
+                    num_licenses += 1;
+                }
+            }
+        }
+        num_licenses > 0
+    }
+}
@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, iter::Peekable, str::Chars};
+
+use crate::{data::Statistics, errors::SourceCodeError, repository::RepositoryInfo};
+
+/// The built-in metrics of each source file (and the repository) a derived metric can use
+const FILE_VARIABLES: [&str; 7] = [
+    "size",
+    "loc",
+    "num_files",
+    "num_commits",
+    "frequency",
+    "num_markers",
+    "bugfix_commits",
+];
+/// The built-in metrics of only the repository a derived metric can use
+const REPOSITORY_VARIABLES: [&str; 2] = ["num_contributors", "num_languages"];
+/// The built-in metrics of only the source files a derived metric can use
+const SOURCE_FILE_VARIABLES: [&str; 2] = ["age_days", "days_since_last_change"];
+/// The built-in metrics that are computed over the commit history
+const HISTORY_VARIABLES: [&str; 6] = [
+    "num_commits",
+    "frequency",
+    "bugfix_commits",
+    "num_contributors",
+    "age_days",
+    "days_since_last_change",
+];
+
+/// A metric derived from the built-in metrics by an arithmetic expression, e.g.,
+/// `churn = loc * num_commits / 100`, computed for each source file and for the repository.
+///
+/// Expressions support numbers, `+`, `-`, `*`, `/` and parentheses over the variables `size`,
+/// `loc`, `num_files`, `num_commits`, `frequency` (the `change_frequency`), `num_markers` and `bugfix_commits`, the repository-only
+/// `num_contributors` and `num_languages`, the source-file-only `age_days` and
+/// `days_since_last_change`, and any derived metric defined before, e.g.,
+/// `debt_density = num_markers * 1000 / loc`. Metrics that use repository-only variables are only computed
+/// for the repository, and those that use source-file-only variables only for the source files
+/// (with commits). Results that are not finite, e.g., from a division by zero, are left out.
+///
+/// #Fields:
+/// * `name` - The name of the metric, e.g., 'churn'
+/// * `expression` - The arithmetic expression, e.g., 'loc * num_commits / 100'
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DerivedMetric {
+    pub name: String,
+    pub expression: String,
+}
+impl DerivedMetric {
+    /// Creates a [`DerivedMetric`], checking that the `expression` parses
+    pub fn new(name: &str, expression: &str) -> Result<Self, SourceCodeError> {
+        let metric = Self {
+            name: name.to_string(),
+            expression: expression.to_string(),
+        };
+        metric.parse()?;
+        Ok(metric)
+    }
+    /// Gets the names of the variables the expression uses
+    pub fn get_variables(&self) -> Result<Vec<String>, SourceCodeError> {
+        let mut variables: Vec<String> = Vec::new();
+        self.parse()?.collect_variables(&mut variables);
+        Ok(variables)
+    }
+    /// Whether the metric uses a metric computed over the commit history
+    pub fn uses_history(&self, derived_metrics: &[DerivedMetric]) -> bool {
+        self.get_variables()
+            .unwrap_or_default()
+            .iter()
+            .any(|variable| {
+                HISTORY_VARIABLES.contains(&variable.as_str())
+                    || derived_metrics
+                        .iter()
+                        .take_while(|metric| metric.name != self.name)
+                        .any(|metric| {
+                            &metric.name == variable && metric.uses_history(derived_metrics)
+                        })
+            })
+    }
+    fn parse(&self) -> Result<Expression, SourceCodeError> {
+        Parser::new(&self.expression).parse().map_err(|message| {
+            SourceCodeError::ConfigError(format!(
+                "Invalid expression for derived metric '{}': {}",
+                self.name, message
+            ))
+        })
+    }
+}
+
+/// Computes the `derived_metrics`, in order, for each source file and for the repository, and
+/// adds them to the `derived` of their [`Statistics`]
+pub(crate) fn compute_derived_metrics(
+    repository_info: &mut RepositoryInfo,
+    derived_metrics: &[DerivedMetric],
+) -> Result<(), SourceCodeError> {
+    let mut expressions: Vec<(&str, Expression)> = Vec::with_capacity(derived_metrics.len());
+    for (index, metric) in derived_metrics.iter().enumerate() {
+        let expression = metric.parse()?;
+        let mut variables: Vec<String> = Vec::new();
+        expression.collect_variables(&mut variables);
+        let unknown = variables.iter().find(|variable| {
+            !FILE_VARIABLES.contains(&variable.as_str())
+                && !REPOSITORY_VARIABLES.contains(&variable.as_str())
+                && !SOURCE_FILE_VARIABLES.contains(&variable.as_str())
+                && !derived_metrics[..index]
+                    .iter()
+                    .any(|metric| &&metric.name == variable)
+        });
+        if let Some(unknown) = unknown {
+            return Err(SourceCodeError::ConfigError(format!(
+                "Unknown metric '{}' in derived metric '{}'",
+                unknown, metric.name
+            )));
+        }
+        expressions.push((&metric.name, expression));
+    }
+
+    for source_file in &mut repository_info.source_files {
+        let source_file_variables: BTreeMap<&str, f64> = [
+            ("age_days", source_file.age_days),
+            ("days_since_last_change", source_file.days_since_last_change),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value? as f64)))
+        .collect();
+        evaluate_all(
+            &expressions,
+            &mut source_file.statistics,
+            &source_file_variables,
+        );
+    }
+    let repository_variables = BTreeMap::from([
+        (
+            "num_contributors",
+            repository_info.contributors.len() as f64,
+        ),
+        ("num_languages", repository_info.languages.len() as f64),
+    ]);
+    evaluate_all(
+        &expressions,
+        &mut repository_info.statistics,
+        &repository_variables,
+    );
+    Ok(())
+}
+/// Evaluates the `expressions` in order against the [`Statistics`] and the `extra` variables
+fn evaluate_all(
+    expressions: &[(&str, Expression)],
+    statistics: &mut Statistics,
+    extra: &BTreeMap<&str, f64>,
+) {
+    for (name, expression) in expressions {
+        let value = expression.evaluate(&|variable| match variable {
+            "size" => Some(statistics.size as f64),
+            "loc" => Some(statistics.loc as f64),
+            "num_files" => Some(statistics.num_files as f64),
+            "num_commits" => Some(statistics.num_commits as f64),
+            "frequency" => Some(statistics.change_frequency.0 as f64),
+            "num_markers" => Some(statistics.num_markers as f64),
+            "bugfix_commits" => Some(statistics.bugfix_commits as f64),
+            _ => extra
+                .get(variable)
+                .or_else(|| statistics.derived.get(variable))
+                .copied(),
+        });
+        if let Some(value) = value.filter(|value| value.is_finite()) {
+            statistics.derived.insert(name.to_string(), value);
+        }
+    }
+}
+
+/// A parsed arithmetic expression
+enum Expression {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, char, Box<Expression>),
+}
+impl Expression {
+    /// Evaluates the expression, or `None` if a variable has no value
+    fn evaluate(&self, lookup: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        match self {
+            Expression::Number(number) => Some(*number),
+            Expression::Variable(name) => lookup(name),
+            Expression::Negate(operand) => operand.evaluate(lookup).map(|value| -value),
+            Expression::Binary(left, operator, right) => {
+                let (left, right) = (left.evaluate(lookup)?, right.evaluate(lookup)?);
+                match operator {
+                    '+' => Some(left + right),
+                    '-' => Some(left - right),
+                    '*' => Some(left * right),
+                    _ => Some(left / right),
+                }
+            }
+        }
+    }
+    fn collect_variables(&self, variables: &mut Vec<String>) {
+        match self {
+            Expression::Number(_) => {}
+            Expression::Variable(name) => {
+                if !variables.contains(name) {
+                    variables.push(name.clone());
+                }
+            }
+            Expression::Negate(operand) => operand.collect_variables(variables),
+            Expression::Binary(left, _, right) => {
+                left.collect_variables(variables);
+                right.collect_variables(variables);
+            }
+        }
+    }
+}
+
+/// A recursive descent parser for expressions:
+///
+/// ```text
+/// expression = term { ("+" | "-") term }
+/// term       = factor { ("*" | "/") factor }
+/// factor     = "-" factor | number | variable | "(" expression ")"
+/// ```
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+impl<'a> Parser<'a> {
+    fn new(expression: &'a str) -> Self {
+        Self {
+            chars: expression.chars().peekable(),
+        }
+    }
+    fn parse(mut self) -> Result<Expression, String> {
+        let expression = self.parse_expression()?;
+        match self.peek() {
+            None => Ok(expression),
+            Some(c) => Err(format!("unexpected '{}'", c)),
+        }
+    }
+    fn parse_expression(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_term()?;
+        while let Some(operator) = self.peek().filter(|c| matches!(c, '+' | '-')) {
+            self.chars.next();
+            let right = self.parse_term()?;
+            expression = Expression::Binary(Box::new(expression), operator, Box::new(right));
+        }
+        Ok(expression)
+    }
+    fn parse_term(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_factor()?;
+        while let Some(operator) = self.peek().filter(|c| matches!(c, '*' | '/')) {
+            self.chars.next();
+            let right = self.parse_factor()?;
+            expression = Expression::Binary(Box::new(expression), operator, Box::new(right));
+        }
+        Ok(expression)
+    }
+    fn parse_factor(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some('-') => {
+                self.chars.next();
+                Ok(Expression::Negate(Box::new(self.parse_factor()?)))
+            }
+            Some('(') => {
+                self.chars.next();
+                let expression = self.parse_expression()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.chars.next();
+                        Ok(expression)
+                    }
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                number
+                    .parse()
+                    .map(Expression::Number)
+                    .map_err(|_| format!("invalid number '{}'", number))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => Ok(Expression::Variable(
+                self.take_while(|c| c.is_ascii_alphanumeric() || c == '_'),
+            )),
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+    /// Peeks at the next character that is not whitespace
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.chars.next_if(|c| predicate(*c)) {
+            taken.push(c);
+        }
+        taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "git")]
+    use crate::{options::AnalysisOptions, testing::FixtureRepo};
+
+    /// Evaluates the `expression` with the `variables`
+    fn evaluate(expression: &str, variables: &[(&str, f64)]) -> Result<Option<f64>, String> {
+        let lookup = |name: &str| {
+            variables
+                .iter()
+                .find(|(variable, _)| *variable == name)
+                .map(|(_, value)| *value)
+        };
+        Ok(Parser::new(expression).parse()?.evaluate(&lookup))
+    }
+
+    #[test]
+    fn evaluates_with_precedence_and_parentheses() {
+        let variables = [("loc", 200.0), ("num_commits", 3.0)];
+        let cases = [
+            ("1 + 2 * 3", 7.0),
+            ("(1 + 2) * 3", 9.0),
+            ("10 - 4 - 3", 3.0),
+            ("12 / 3 / 2", 2.0),
+            ("-2 * -(1 + 2)", 6.0),
+            ("loc * num_commits / 100", 6.0),
+            ("  0.5*loc  ", 100.0),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                evaluate(expression, &variables),
+                Ok(Some(expected)),
+                "{}",
+                expression
+            );
+        }
+        assert_eq!(evaluate("loc / unknown", &variables), Ok(None));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        let cases = [
+            ("", "unexpected end of expression"),
+            ("loc *", "unexpected end of expression"),
+            ("(loc + 1", "missing ')'"),
+            ("1.2.3", "invalid number '1.2.3'"),
+            ("loc $ 2", "unexpected '$'"),
+            ("loc 2", "unexpected '2'"),
+        ];
+        for (expression, message) in cases {
+            assert_eq!(
+                evaluate(expression, &[]).err().as_deref(),
+                Some(message),
+                "{}",
+                expression
+            );
+        }
+        assert!(matches!(
+            DerivedMetric::new("broken", "loc *"),
+            Err(SourceCodeError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn gets_the_variables_and_whether_they_use_the_history() -> Result<(), SourceCodeError> {
+        let churn = DerivedMetric::new("churn", "loc * num_commits / loc")?;
+        assert_eq!(churn.get_variables()?, vec!["loc", "num_commits"]);
+        let density = DerivedMetric::new("density", "num_markers * 1000 / loc")?;
+        let weighted = DerivedMetric::new("weighted", "churn * 2")?;
+        let derived_metrics = [churn.clone(), density.clone(), weighted.clone()];
+        assert!(churn.uses_history(&derived_metrics));
+        assert!(!density.uses_history(&derived_metrics));
+        assert!(weighted.uses_history(&derived_metrics));
+        Ok(())
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn computes_the_metrics_of_the_files_and_the_repository() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("README.md", "# Fixture\n")
+            .commit("Add a README")?
+            .with_file("src/lib.rs", "pub fn one() -> i32 {\n    1\n}\n")
+            .commit("Add the library")?
+            .with_file(
+                "src/lib.rs",
+                "pub fn one() -> i32 {\n    // TODO: two\n    1\n}\n",
+            )
+            .commit("Note a change")?;
+        let options = AnalysisOptions {
+            derived_metrics: vec![
+                DerivedMetric::new("loc_per_commit", "loc / num_commits")?,
+                DerivedMetric::new("doubled", "loc_per_commit * 2")?,
+                DerivedMetric::new("per_contributor", "num_commits / num_contributors")?,
+                DerivedMetric::new("markers_per_loc", "num_markers / (loc - loc)")?,
+            ],
+            ..AnalysisOptions::default()
+        };
+        let repository_info = fixture.analyse(&options)?;
+        let source_file = repository_info
+            .source_files
+            .iter()
+            .find(|source_file| source_file.relative_path.as_str() == "src/lib.rs")
+            .expect("the fixture has a library");
+        let derived = &source_file.statistics.derived;
+        assert_eq!(derived.get("loc_per_commit"), Some(&1.5));
+        assert_eq!(derived.get("doubled"), Some(&3.0));
+        // Only the repository has contributors, and a division by zero is left out
+        assert_eq!(derived.get("per_contributor"), None);
+        assert_eq!(derived.get("markers_per_loc"), None);
+        let derived = &repository_info.statistics.derived;
+        assert_eq!(derived.get("per_contributor"), Some(&3.0));
+
+        let options = AnalysisOptions {
+            derived_metrics: vec![DerivedMetric::new("unknown", "loc * lines")?],
+            ..AnalysisOptions::default()
+        };
+        assert!(matches!(
+            fixture.analyse(&options),
+            Err(SourceCodeError::ConfigError(_))
+        ));
+        Ok(())
+    }
+}
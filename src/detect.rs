@@ -0,0 +1,76 @@
+//! Fallback language detection for files `tokei` doesn't recognise by their extension, e.g.,
+//! extensionless scripts with a shebang, or well-known build and config file names
+use std::path::Path;
+
+/// Well-known file names (or file name prefixes, e.g., 'Dockerfile.dev') and their language names
+const FILE_NAME_RULES: [(&str, &str); 11] = [
+    ("Dockerfile", "Dockerfile"),
+    ("Containerfile", "Dockerfile"),
+    ("Makefile", "Makefile"),
+    ("GNUmakefile", "Makefile"),
+    ("Jenkinsfile", "Groovy"),
+    ("Vagrantfile", "Ruby"),
+    ("Gemfile", "Ruby"),
+    ("Rakefile", "Ruby"),
+    ("Podfile", "Ruby"),
+    ("Brewfile", "Ruby"),
+    ("Justfile", "Just"),
+];
+
+/// Shebang interpreters and their language names
+const INTERPRETER_RULES: [(&str, &str); 18] = [
+    ("python", "Python"),
+    ("bash", "BASH"),
+    ("sh", "Shell"),
+    ("dash", "Shell"),
+    ("ash", "Shell"),
+    ("ksh", "Shell"),
+    ("zsh", "Zsh"),
+    ("fish", "Fish"),
+    ("node", "JavaScript"),
+    ("nodejs", "JavaScript"),
+    ("ts-node", "TypeScript"),
+    ("deno", "TypeScript"),
+    ("ruby", "Ruby"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("lua", "Lua"),
+    ("Rscript", "R"),
+    ("pwsh", "PowerShell"),
+];
+
+/// Detects the language of a file from its file name, then from the shebang of its `contents`
+pub(crate) fn detect_language(path: &Path, contents: &str) -> Option<tokei::LanguageType> {
+    let file_name = path.file_name()?.to_str()?;
+    from_file_name(file_name).or_else(|| from_shebang(contents))
+}
+/// Detects the language from a well-known file name, e.g., 'Jenkinsfile' or 'Dockerfile.dev'
+fn from_file_name(file_name: &str) -> Option<tokei::LanguageType> {
+    FILE_NAME_RULES
+        .iter()
+        .find(|(rule, _)| {
+            file_name.eq_ignore_ascii_case(rule)
+                || file_name
+                    .strip_prefix(rule)
+                    .map(|rest| rest.starts_with('.'))
+                    .unwrap_or(false)
+        })
+        .and_then(|(_, language)| language.parse().ok())
+}
+/// Detects the language from a shebang line, e.g., '#!/usr/bin/env python3' or '#!/bin/bash -e'
+fn from_shebang(contents: &str) -> Option<tokei::LanguageType> {
+    let shebang = contents.lines().next()?.strip_prefix("#!")?;
+    let mut words = shebang.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // Skip any `env` flags, e.g., '#!/usr/bin/env -S deno run'
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+    // Drop version suffixes, e.g., 'python3.11' or 'perl5'
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    INTERPRETER_RULES
+        .iter()
+        .find(|(rule, _)| *rule == interpreter)
+        .and_then(|(_, language)| language.parse().ok())
+}
@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::{data::saturating_sum, ratio::percentage, source::SourceFileInfo};
+
+/// The multiplier of the polynomial rolling hash over the hashes of consecutive lines
+const ROLLING_BASE: u64 = 1_000_003;
+/// Windows of lines that occur more often than this, e.g., license headers or generated code,
+/// aren't compared pairwise, as they would add quadratically many blocks
+const MAX_OCCURRENCES: usize = 20;
+
+/// The options for the duplicate code detection
+///
+/// #Fields:
+/// * `min_lines` - The fewest consecutive (normalised, non-trivial) lines a duplicated block has
+/// * `max_blocks` - The number of largest [`DuplicateBlock`]s kept in the [`DuplicationReport`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct DuplicationOptions {
+    pub min_lines: usize,
+    pub max_blocks: usize,
+}
+impl Default for DuplicationOptions {
+    fn default() -> Self {
+        Self {
+            min_lines: 6,
+            max_blocks: 100,
+        }
+    }
+}
+
+/// A range of lines in a source file
+///
+/// #Fields:
+/// * `path` - The relative path of the source file
+/// * `start_line` - The first line of the range (1-based)
+/// * `end_line` - The last line of the range (1-based, inclusive)
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CodeLocation {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// A block of code that occurs twice, in different files or in two places of the same file
+///
+/// #Fields:
+/// * `first` - The [`CodeLocation`] of one copy, ordered before `second`
+/// * `second` - The [`CodeLocation`] of the other copy
+/// * `num_lines` - The number of (normalised, non-trivial) lines duplicated
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DuplicateBlock {
+    pub first: CodeLocation,
+    pub second: CodeLocation,
+    pub num_lines: usize,
+}
+
+/// The duplicated code in a source file
+///
+/// #Fields:
+/// * `path` - The relative path of the source file
+/// * `duplicated_lines` - The number of the file's lines in any duplicated block
+/// * `percentage` - The `duplicated_lines` as a percentage of the file's lines of code
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileDuplication {
+    pub path: String,
+    pub duplicated_lines: u64,
+    pub percentage: f32,
+}
+
+/// The duplicated code in a repository, found by fingerprinting windows of consecutive lines,
+/// normalised so that indentation and whitespace changes don't hide copies
+///
+/// #Fields:
+/// * `duplicated_lines` - The number of lines in any duplicated block
+/// * `percentage` - The `duplicated_lines` as a percentage of the lines of code
+/// * `blocks` - The largest [`DuplicateBlock`]s, by number of lines
+/// * `files` - The [`FileDuplication`] of each file with duplicated code, most duplicated first
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DuplicationReport {
+    pub duplicated_lines: u64,
+    pub percentage: f32,
+    pub blocks: Vec<DuplicateBlock>,
+    pub files: Vec<FileDuplication>,
+}
+
+/// Source files with identical contents, i.e., the same `id_hash`
+///
+/// #Fields:
+/// * `id_hash` - The hash of the contents of the files
+/// * `paths` - The relative paths of the copies, in alphabetical order
+/// * `size` - The size of each copy, in bytes
+/// * `loc` - The lines of code of each copy
+/// * `wasted_bytes` - The size of the copies after the first, i.e., that deduplicating would save
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IdenticalFileGroup {
+    pub id_hash: String,
+    pub paths: Vec<String>,
+    pub size: u64,
+    pub loc: u64,
+    pub wasted_bytes: u64,
+}
+
+/// The source files of a repository that are identical copies of each other, found by their
+/// `id_hash`; empty files, and files without a hash, e.g., with a
+/// [`crate::options::ContentMode::None`], aren't compared
+///
+/// #Fields:
+/// * `counted_once` - Whether each group was counted once in the lines of code and size of the
+///   repository's [`crate::data::Statistics`], as requested in the
+///   [`crate::options::AnalysisOptions`], rather than once per copy
+/// * `groups` - The [`IdenticalFileGroup`]s, most wasted bytes first
+/// * `duplicate_files` - The number of copies after the first of each group
+/// * `duplicate_loc` - The lines of code of the copies after the first of each group
+/// * `wasted_bytes` - The size of the copies after the first of each group
+/// * `percentage` - The `duplicate_loc` as a percentage of the lines of code of all the files
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IdenticalFilesReport {
+    pub counted_once: bool,
+    pub groups: Vec<IdenticalFileGroup>,
+    pub duplicate_files: u64,
+    pub duplicate_loc: u64,
+    pub wasted_bytes: u64,
+    pub percentage: f32,
+}
+
+/// The normalised, non-trivial lines of a source file
+struct Fingerprints {
+    /// The line number (1-based) of each normalised line
+    line_numbers: Vec<usize>,
+    /// The hash of each normalised line
+    hashes: Vec<u64>,
+}
+impl Fingerprints {
+    /// Normalises the lines of the `contents`, i.e., trims them and collapses whitespace, and
+    /// skips lines without any letters or digits, e.g., blank lines and lone braces
+    fn new(contents: &str) -> Self {
+        let mut fingerprints = Fingerprints {
+            line_numbers: vec![],
+            hashes: vec![],
+        };
+        for (index, line) in contents.lines().enumerate() {
+            if !line.chars().any(char::is_alphanumeric) {
+                continue;
+            }
+            let mut hasher = DefaultHasher::new();
+            for word in line.split_whitespace() {
+                word.hash(&mut hasher);
+            }
+            fingerprints.line_numbers.push(index + 1);
+            fingerprints.hashes.push(hasher.finish());
+        }
+        fingerprints
+    }
+    /// Gets the rolling hash of each window of `window` consecutive lines, by its first line
+    fn get_window_hashes(&self, window: usize) -> Vec<u64> {
+        if window == 0 || self.hashes.len() < window {
+            return vec![];
+        }
+        let highest_power = (1..window).fold(1u64, |power, _| power.wrapping_mul(ROLLING_BASE));
+        let mut hash = self.hashes[..window].iter().fold(0u64, |hash, line| {
+            hash.wrapping_mul(ROLLING_BASE).wrapping_add(*line)
+        });
+        let mut window_hashes = vec![hash];
+        for start in 1..=self.hashes.len() - window {
+            hash = hash
+                .wrapping_sub(self.hashes[start - 1].wrapping_mul(highest_power))
+                .wrapping_mul(ROLLING_BASE)
+                .wrapping_add(self.hashes[start + window - 1]);
+            window_hashes.push(hash);
+        }
+        window_hashes
+    }
+}
+
+impl DuplicationReport {
+    /// Finds the duplicated code in the contents of the `source_files`. Files whose contents
+    /// weren't kept, e.g., binary files, are skipped.
+    pub fn new(source_files: &[SourceFileInfo], options: &DuplicationOptions) -> Self {
+        let window = options.min_lines.max(1);
+        let files: Vec<Fingerprints> = source_files
+            .iter()
+            .map(|source_file| Fingerprints::new(source_file.get_contents().unwrap_or_default()))
+            .collect();
+
+        let mut occurrences: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+        for (file, fingerprints) in files.iter().enumerate() {
+            for (start, hash) in fingerprints
+                .get_window_hashes(window)
+                .into_iter()
+                .enumerate()
+            {
+                occurrences.entry(hash).or_default().push((file, start));
+            }
+        }
+        // The pairs of windows with the same lines, as (file_a, start_a, file_b, start_b)
+        let mut matches: Vec<(usize, usize, usize, usize)> = Vec::new();
+        for locations in occurrences.values() {
+            if locations.len() < 2 || locations.len() > MAX_OCCURRENCES {
+                continue;
+            }
+            for (position, &(file_a, start_a)) in locations.iter().enumerate() {
+                for &(file_b, start_b) in &locations[position + 1..] {
+                    let overlaps = file_a == file_b && start_b < start_a + window;
+                    let same_lines = files[file_a].hashes[start_a..start_a + window]
+                        == files[file_b].hashes[start_b..start_b + window];
+                    if !overlaps && same_lines {
+                        matches.push((file_a, start_a, file_b, start_b));
+                    }
+                }
+            }
+        }
+
+        // Consecutive matching windows on the same diagonal make up one block
+        matches.sort_by_key(|&(file_a, start_a, file_b, start_b)| {
+            (file_a, file_b, start_b as i64 - start_a as i64, start_a)
+        });
+        let mut duplicated: Vec<Vec<bool>> = files
+            .iter()
+            .map(|fingerprints| vec![false; fingerprints.hashes.len()])
+            .collect();
+        let mut blocks: Vec<DuplicateBlock> = Vec::new();
+        let mut index = 0;
+        while index < matches.len() {
+            let (file_a, start_a, file_b, start_b) = matches[index];
+            let mut length = 1;
+            while matches.get(index + length)
+                == Some(&(file_a, start_a + length, file_b, start_b + length))
+            {
+                length += 1;
+            }
+            index += length;
+            let num_lines = length + window - 1;
+            duplicated[file_a][start_a..start_a + num_lines].fill(true);
+            duplicated[file_b][start_b..start_b + num_lines].fill(true);
+            let location = |file: usize, start: usize| CodeLocation {
+                path: source_files[file].relative_path.to_string(),
+                start_line: files[file].line_numbers[start],
+                end_line: files[file].line_numbers[start + num_lines - 1],
+            };
+            blocks.push(DuplicateBlock {
+                first: location(file_a, start_a),
+                second: location(file_b, start_b),
+                num_lines,
+            });
+        }
+        blocks.sort_by_key(|block| std::cmp::Reverse(block.num_lines));
+        blocks.truncate(options.max_blocks);
+
+        let mut duplicated_lines: u64 = 0;
+        let mut file_duplications: Vec<FileDuplication> = Vec::new();
+        for (source_file, lines) in source_files.iter().zip(duplicated) {
+            let file_duplicated_lines = lines.iter().filter(|line| **line).count() as u64;
+            if file_duplicated_lines == 0 {
+                continue;
+            }
+            duplicated_lines += file_duplicated_lines;
+            file_duplications.push(FileDuplication {
+                path: source_file.relative_path.to_string(),
+                duplicated_lines: file_duplicated_lines,
+                percentage: percentage(
+                    file_duplicated_lines as f64,
+                    source_file.statistics.loc as f64,
+                )
+                .unwrap_or_default(),
+            });
+        }
+        file_duplications.sort_by(|a, b| {
+            b.percentage
+                .total_cmp(&a.percentage)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        let loc = saturating_sum(source_files.iter().map(|sfi| sfi.statistics.loc));
+        DuplicationReport {
+            duplicated_lines,
+            percentage: percentage(duplicated_lines as f64, loc as f64).unwrap_or_default(),
+            blocks,
+            files: file_duplications,
+        }
+    }
+}
+
+impl IdenticalFilesReport {
+    /// Groups the `source_files` with the same `id_hash`, noting whether the copies are
+    /// `counted_once` in the repository's totals
+    pub fn new(source_files: &[SourceFileInfo], counted_once: bool) -> Self {
+        let mut by_hash: BTreeMap<&str, Vec<&SourceFileInfo>> = BTreeMap::new();
+        for source_file in source_files {
+            if let Some(id_hash) = source_file.id_hash.as_deref() {
+                if source_file.statistics.size > 0 {
+                    by_hash.entry(id_hash).or_default().push(source_file);
+                }
+            }
+        }
+        let mut report = IdenticalFilesReport {
+            counted_once,
+            ..Default::default()
+        };
+        for (id_hash, copies) in by_hash {
+            if copies.len() < 2 {
+                continue;
+            }
+            let statistics = &copies[0].statistics;
+            let num_duplicates = copies.len() as u64 - 1;
+            let mut paths: Vec<String> = copies
+                .iter()
+                .map(|source_file| source_file.relative_path.to_string())
+                .collect();
+            paths.sort();
+            let group = IdenticalFileGroup {
+                id_hash: id_hash.to_string(),
+                paths,
+                size: statistics.size,
+                loc: statistics.loc,
+                wasted_bytes: statistics.size.saturating_mul(num_duplicates),
+            };
+            report.duplicate_files += num_duplicates;
+            report.duplicate_loc = report
+                .duplicate_loc
+                .saturating_add(group.loc.saturating_mul(num_duplicates));
+            report.wasted_bytes = report.wasted_bytes.saturating_add(group.wasted_bytes);
+            report.groups.push(group);
+        }
+        report.groups.sort_by(|a, b| {
+            b.wasted_bytes
+                .cmp(&a.wasted_bytes)
+                .then_with(|| a.paths.cmp(&b.paths))
+        });
+        let loc = saturating_sum(source_files.iter().map(|sfi| sfi.statistics.loc));
+        report.percentage = percentage(report.duplicate_loc as f64, loc as f64).unwrap_or_default();
+        report
+    }
+}
@@ -1,17 +1,50 @@
-use fastembed::{EmbeddingBase, EmbeddingModel, FlagEmbedding, InitOptions};
-use qdrant_client::client::QdrantClient;
+//! Embedding the source files, and contributors, of a repository into a vector store for
+//! semantic search
+pub mod contributors;
+pub mod export;
+pub mod lifecycle;
+pub mod similar;
+
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "embeddings")]
+use std::thread;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use crate::{errors::SourceCodeError, repository::RepositoryInfo, source::SourceFileInfo};
+use self::{
+    export::{EmbeddingExportFormat, EmbeddingFileWriter},
+    lifecycle::SyncSummary,
+    similar::{SimilarFilesOptions, SimilarFilesReport},
+};
+use crate::{
+    cancel::{CancellationToken, PartialResults},
+    chunk::{chunk_contents, ChunkingOptions, SourceChunk},
+    data::Statistics,
+    embeddings_provider::EmbeddingsProvider,
+    errors::SourceCodeError,
+    export::anonymise::{AnonymisationOptions, PathHasher},
+    limits::ResourceLimits,
+    observer::{AnalysisObserver, AnalysisStage},
+    paths::RepoRelativePath,
+    repository::RepositoryInfo,
+    source::SourceFileInfo,
+    vector_store::{ScoredVectorPoint, VectorPoint, VectorStore},
+};
 
 /// Creates an embedding from the repository data, then stores it in a vector database
 /// For each source file represented as:
 /// {
-///     "source_file": "path/to/source/file",
+///     "name": "file name",
+///     "path": "path/to/source/file",
 ///     "data": {
 ///         "language": "name",
-///         "id_hash": "SHA256 hash of the file contents",
+///         "id_hash": "Hash of the file contents",
 ///         "contents": "Source file contents",
 ///         "size_sentiment": 123123,
 ///         "loc_sentiment": 124124,
@@ -22,6 +55,7 @@ use crate::{errors::SourceCodeError, repository::RepositoryInfo, source::SourceF
 #[derive(Serialize, Deserialize)]
 pub struct FileToEmbed {
     pub name: String,
+    pub path: RepoRelativePath,
     pub data: FileData,
 }
 #[derive(Serialize, Deserialize)]
@@ -34,94 +68,1189 @@ pub struct FileData {
     pub frequency_sentiment: f32,
 }
 
+/// The result of embedding a repository into the vector database
 ///
-pub async fn create_repository_embedding(stats: RepositoryInfo) -> Result<(), SourceCodeError> {
-    log::info!("starting embedding");
-    let model: FlagEmbedding = FlagEmbedding::try_new(InitOptions {
-        model_name: EmbeddingModel::AllMiniLML6V2,
-        show_download_message: true,
-        ..Default::default()
-    })?;
-
-    // Get the list of source files.
-    // Flatten into a Vec<SourceFile>
-    // Serialize each FileEmbedding into a JSON string and add to a Vec<[String, String]>, where the key is the filename and the value is the JSON string.
-    // Embed the Vec<[String, String]>.
-    //
-    // Derive 'sentiment' from Statistics:
-    //
-    //    size_sentiment = -log10(size) (larger size = more negative sentiment)
-    //    loc_sentiment = -log10(loc)
-    //    frequency_sentiment = -log10(frequency)
-    //
-    //    TODO: for contributors, reverse the sentiment:
-    //    contributor_frequency_sentiment = log10(frequency)
-    //
-    // Example:
-    // pub async fn embed_repo<M: EmbeddingsModel + Send + Sync>(
-    //     repository: &Repository,
-    //     files: Vec<File>,
-    //     model: &M,
-    // ) -> Result<RepositoryEmbeddings> {
-    //     let content: Vec<String> = files.par_iter().map(|file| file.content.clone()).collect();
-
-    //     let embeddings: Vec<Embeddings> = model.embed(content)?;
-
-    //     let file_embeddings: Vec<FileEmbeddings> = embeddings
-    //         .into_par_iter()
-    //         .zip(files.into_par_iter())
-    //         .map(|(embeddings, file)| FileEmbeddings {
-    //             path: file.path,
-    //             embeddings,
-    //         })
-    //         .collect();
-
-    //     Ok(RepositoryEmbeddings {
-    //         repo_id: repository.to_string(),
-    //         file_embeddings,
-    //     })
-    // }
-
-    // let stats_json = stats.get_as_json()?;
-
-    // log::info!("Stats JSON size: {}", stats_json.len());
-
-    // iterate over the source files and create a [`File`] struct for each one
-    let files: Vec<FileToEmbed> = stats
-        .source_files
-        .iter()
-        .map(|source_file_info| map_source_file_info_to_file(source_file_info))
-        .collect();
-    // Serialize each File struct into a JSON string
-    let files_json: Vec<String> = files
-        .iter()
-        .map(|file: &FileToEmbed| serde_json::to_string(file).unwrap())
-        .collect();
-    // For each JSON string, flatten it into a Vec<String> after extracting the source_file key
-    let mut result: Vec<String> = Vec::new();
-    for file_json in files_json {
-        let json_value: Value = serde_json::from_str(&file_json).unwrap();
-        let key = json_value["name"].as_str().unwrap().to_string();
-        let flattened_json = flatten_json(&json_value["data"]);
+/// #Fields:
+/// * `repository` - The name of the repository the points were stored for
+/// * `vector_size` - The dimension of the embedding vectors
+/// * `num_files` - The number of source files embedded
+/// * `num_points` - The number of points (chunks) upserted into the vector store
+/// * `num_skipped` - The number of points already stored for unchanged chunks, so not embedded
+///   again, when resuming
+/// * `num_tokens` - The number of tokens embedded, as counted for the model
+/// * `num_truncated_lines` - The number of lines that alone exceed the model's maximum tokens, so
+///   were truncated
+/// * `file_tokens` - The number of tokens embedded for each source file, by relative path
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct EmbeddingSummary {
+    pub repository: String,
+    pub vector_size: u64,
+    pub num_files: usize,
+    pub num_points: usize,
+    #[serde(default)]
+    pub num_skipped: usize,
+    #[serde(default)]
+    pub num_tokens: usize,
+    #[serde(default)]
+    pub num_truncated_lines: usize,
+    #[serde(default)]
+    pub file_tokens: BTreeMap<RepoRelativePath, usize>,
+}
 
-        for value in flattened_json {
-            let entry = format!("{}: {}", key, value);
-            result.push(entry);
+/// A chunk of a source file matched by [`search_repository`]
+///
+/// #Fields:
+/// * `path` - The relative path of the source file
+/// * `name` - The name of the source file
+/// * `score` - The similarity of the chunk to the query; higher is more similar
+/// * `chunk_index` - The position of the chunk in the file's chunks
+/// * `start_line` - The first line of the chunk (1-based)
+/// * `end_line` - The last line of the chunk (1-based, inclusive)
+/// * `kind` - The syntax node kind of the chunk, for syntax chunks
+/// * `symbols` - The qualified names of the [`crate::symbols::Symbol`]s the chunk overlaps, if
+///   the file's symbols were indexed and stored, i.e., unless embedded with
+///   [`PayloadMode::Metadata`]
+/// * `contents` - The contents of the chunk, if stored, i.e., unless embedded with
+///   [`PayloadMode::Metadata`]
+/// * `statistics` - The [`Statistics`] of the source file, if it is in the [`RepositoryInfo`] searched
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SearchMatch {
+    pub path: RepoRelativePath,
+    pub name: String,
+    pub score: f32,
+    pub chunk_index: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    pub contents: Option<String>,
+    pub statistics: Option<Statistics>,
+}
+impl SearchMatch {
+    /// Maps a point, stored by [`create_repository_embedding`], to a [`SearchMatch`]
+    fn from_point(point: ScoredVectorPoint, stats: &RepositoryInfo) -> SearchMatch {
+        let payload = &point.payload;
+        let get_str = |key: &str| payload.get(key).and_then(Value::as_str).unwrap_or_default();
+        let get_usize =
+            |key: &str| payload.get(key).and_then(Value::as_u64).unwrap_or_default() as usize;
+        let path = RepoRelativePath::from(get_str("path"));
+        let name = match get_str("name") {
+            "" => path.rsplit('/').next().unwrap_or_default(),
+            name => name,
+        };
+        let statistics = stats
+            .source_files
+            .iter()
+            .find(|file| file.relative_path == path)
+            .map(|file| file.statistics.clone());
+        SearchMatch {
+            name: name.to_string(),
+            score: point.score,
+            chunk_index: get_usize("chunk_index"),
+            start_line: get_usize("start_line"),
+            end_line: get_usize("end_line"),
+            kind: payload
+                .get("kind")
+                .and_then(Value::as_str)
+                .map(String::from),
+            symbols: payload
+                .get("symbols")
+                .and_then(Value::as_array)
+                .map(|symbols| {
+                    symbols
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            contents: payload
+                .get("contents")
+                .and_then(Value::as_str)
+                .map(String::from),
+            statistics,
+            path,
         }
     }
+}
+
+/// What is stored in the vector store payload of each chunk; the vectors are embedded from the
+/// contents either way
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadMode {
+    /// The contents of the chunk, with the metadata and sentiments of its file, so that search
+    /// results can show the matched code
+    #[default]
+    Full,
+    /// Only the metadata of the file, i.e., its path, language, hash and statistics, and the
+    /// position of the chunk, for organisations that can't ship source text to shared
+    /// infrastructure
+    Metadata,
+}
 
-    let embeddings = model.embed(result, None)?;
+/// The policy for retrying the operations that fail transiently on flaky networks, i.e.,
+/// embedding with a remote model, calling the vector store and downloading a local model
+///
+/// #Fields:
+/// * `max_attempts` - The number of times an operation is attempted; 1 doesn't retry
+/// * `initial_backoff_ms` - The wait before the first retry, in milliseconds, doubled for each
+///   retry after it
+/// * `max_backoff_ms` - The longest wait between retries, in milliseconds
+/// * `timeout_secs` - The time an attempt may take before it is abandoned (and retried), in
+///   seconds, if limited; a local model's download and embedding run to completion
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub timeout_secs: Option<u64>,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+            timeout_secs: None,
+        }
+    }
+}
+impl RetryPolicy {
+    /// A policy that attempts each operation once, without a timeout
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+    /// Gets the wait after the failed `attempt` (1-based), before the next
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff_ms
+            .saturating_mul(1 << (attempt - 1).min(16));
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+    /// Whether the failed `attempt` (1-based) of the operation should be retried; errors in the
+    /// configuration, e.g., a vector size that doesn't match the collection, and cancellations
+    /// are not
+    fn should_retry(&self, attempt: u32, err: &SourceCodeError) -> bool {
+        attempt < self.max_attempts
+            && !matches!(
+                err,
+                SourceCodeError::ConfigError(_) | SourceCodeError::Cancelled { .. }
+            )
+    }
+    /// Runs the async `operation`, timing out each attempt and retrying with exponential
+    /// backoff while it fails
+    ///
+    /// #Returns:
+    /// * The result of the first attempt that succeeds, or the last error
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        description: &str,
+        mut operation: F,
+    ) -> Result<T, SourceCodeError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SourceCodeError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let result = match self.timeout_secs {
+                Some(timeout_secs) => {
+                    tokio::time::timeout(Duration::from_secs(timeout_secs), operation())
+                        .await
+                        .unwrap_or_else(|_| {
+                            Err(SourceCodeError::EmbeddingError(anyhow::anyhow!(
+                                "Timed out after {}s to {}",
+                                timeout_secs,
+                                description
+                            )))
+                        })
+                }
+                None => operation().await,
+            };
+            match result {
+                Err(err) if self.should_retry(attempt, &err) => {
+                    let backoff = self.backoff(attempt);
+                    tracing::warn!(
+                        "Failed to {} (attempt {} of {}), retrying in {:?}: {}",
+                        description,
+                        attempt,
+                        self.max_attempts,
+                        backoff,
+                        err
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// Runs the blocking `operation`, e.g., a model download, retrying with exponential backoff
+    /// while it fails; attempts aren't timed out
+    ///
+    /// #Returns:
+    /// * The result of the first attempt that succeeds, or the last error
+    #[cfg(feature = "embeddings")]
+    pub(crate) fn run_blocking<T>(
+        &self,
+        description: &str,
+        mut operation: impl FnMut() -> Result<T, SourceCodeError>,
+    ) -> Result<T, SourceCodeError> {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(err) if self.should_retry(attempt, &err) => {
+                    let backoff = self.backoff(attempt);
+                    tracing::warn!(
+                        "Failed to {} (attempt {} of {}), retrying in {:?}: {}",
+                        description,
+                        attempt,
+                        self.max_attempts,
+                        backoff,
+                        err
+                    );
+                    thread::sleep(backoff);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// The template of the [`describe_file`] description embedded for each chunk, by default
+pub const DEFAULT_DESCRIPTION_TEMPLATE: &str = "{path} is a {language} {purpose} file named \
+{name}. It has {loc} lines of code in {size} bytes, and was changed in {num_commits} commits, \
+{frequency}% of the repository's commits.\nLines {start_line} to {end_line} ({kind}):\n{contents}";
+
+/// The directories whose files have a purpose, by the purpose given by [`describe_file`]
+const PURPOSE_DIRS: [(&str, &[&str]); 6] = [
+    ("example", &["example", "examples", "samples"]),
+    ("benchmark", &["bench", "benches", "benchmarks"]),
+    ("database migration", &["migrations", "migrate"]),
+    ("script", &["scripts", "bin", "tools"]),
+    ("documentation", &["doc", "docs"]),
+    (
+        "continuous integration",
+        &[".github", ".circleci", ".gitlab"],
+    ),
+];
+/// The file names whose files have a purpose, by the purpose given by [`describe_file`]; names
+/// without an extension match any extension
+const PURPOSE_NAMES: [(&str, &[&str]); 3] = [
+    (
+        "entry point",
+        &[
+            "main", "__main__", "index", "app", "server", "cli", "program",
+        ],
+    ),
+    ("module root", &["lib", "mod", "__init__"]),
+    (
+        "build",
+        &[
+            "build.rs",
+            "makefile",
+            "cmakelists.txt",
+            "dockerfile",
+            "build.gradle",
+            "setup.py",
+            "package.json",
+            "cargo.toml",
+            "pom.xml",
+        ],
+    ),
+];
+/// The file extensions of configuration files, for [`describe_file`]
+const CONFIGURATION_EXTENSIONS: [&str; 9] = [
+    "toml",
+    "yaml",
+    "yml",
+    "json",
+    "ini",
+    "cfg",
+    "conf",
+    "env",
+    "properties",
+];
+
+/// What the document embedded for each chunk is made of
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentFormat {
+    /// A natural-language description of the file and chunk, from [`describe_file`] with the
+    /// template of the [`EmbeddingOptions`], which retrieves better than the flattened entries
+    #[default]
+    Description,
+    /// The file's flattened "name: /key/value" entries, with its statistics as sentiments and
+    /// the chunk in place of its contents, as embedded before descriptions, e.g., to keep
+    /// searching the points of a collection embedded that way
+    Flattened,
+}
+
+/// Describes a source file in natural language, as the document embedded for its chunks, by
+/// filling the `template` (see [`DEFAULT_DESCRIPTION_TEMPLATE`]) with the file's:
+/// * `{path}` - relative path
+/// * `{name}` - file name
+/// * `{language}` - language, or 'plain text'
+/// * `{purpose}` - purpose, guessed from its path, e.g., 'test', 'generated', 'entry point',
+///   'configuration' or 'source'
+/// * `{loc}`, `{size}` and `{num_commits}` - statistics
+/// * `{frequency}` - change frequency, i.e., the percentage of the repository's commits that
+///   changed it
+///
+/// The placeholders of a chunk, `{start_line}`, `{end_line}`, `{kind}` and `{contents}`, are left
+/// for each chunk to fill.
+pub fn describe_file(source_file_info: &SourceFileInfo, template: &str) -> String {
+    let statistics = &source_file_info.statistics;
+    let language = source_file_info
+        .language
+        .as_ref()
+        .map(|language| language.name.as_str())
+        .unwrap_or("plain text");
+    [
+        ("{path}", source_file_info.relative_path.to_string()),
+        ("{name}", source_file_info.name.clone()),
+        ("{language}", language.to_string()),
+        ("{purpose}", get_purpose(source_file_info).to_string()),
+        ("{loc}", statistics.loc.to_string()),
+        ("{size}", statistics.size.to_string()),
+        ("{num_commits}", statistics.num_commits.to_string()),
+        (
+            "{frequency}",
+            format!("{:.2}", statistics.change_frequency.0),
+        ),
+    ]
+    .iter()
+    .fold(template.to_string(), |description, (placeholder, value)| {
+        description.replace(placeholder, value)
+    })
+}
+/// Guesses the purpose of a source file from its classification and path
+fn get_purpose(source_file_info: &SourceFileInfo) -> &'static str {
+    if source_file_info.is_test {
+        return "test";
+    }
+    if source_file_info.is_generated {
+        return "generated";
+    }
+    let path = Path::new(&source_file_info.relative_path);
+    let file_name = source_file_info.name.to_lowercase();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if let Some((purpose, _)) = PURPOSE_NAMES.iter().find(|(_, names)| {
+        names
+            .iter()
+            .any(|name| *name == file_name || (!name.contains('.') && *name == stem))
+    }) {
+        return purpose;
+    }
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if CONFIGURATION_EXTENSIONS.contains(&extension.as_str()) {
+        return "configuration";
+    }
+    path.parent()
+        .and_then(|parent| {
+            parent.iter().find_map(|dir| {
+                let dir = dir.to_string_lossy().to_lowercase();
+                PURPOSE_DIRS
+                    .iter()
+                    .find(|(_, dirs)| dirs.contains(&dir.as_str()))
+                    .map(|(purpose, _)| *purpose)
+            })
+        })
+        .unwrap_or("source")
+}
+
+/// The options for embedding a repository
+///
+/// #Fields:
+/// * `chunking` - The [`ChunkingOptions`] for splitting source files into chunks
+/// * `batch_size` - The number of source files prepared, embedded and upserted at a time, which
+///   bounds the memory used for large repositories
+/// * `limits` - The [`ResourceLimits`] on the threads preparing the chunks
+/// * `payload` - The [`PayloadMode`], i.e., whether the contents of the chunks are stored
+/// * `retry` - The [`RetryPolicy`] for embedding and for the calls to the vector store
+/// * `resume` - Whether to skip the chunks whose points are already stored, unchanged, e.g., to
+///   resume an embedding that failed part way through without embedding them again
+/// * `document` - The [`DocumentFormat`] of the documents embedded
+/// * `description_template` - The template of the description embedded for each chunk, with
+///   [`DocumentFormat::Description`]; see [`describe_file`] for its placeholders
+/// * `anonymisation` - The [`AnonymisationOptions`] the payloads are anonymised with, if any:
+///   the paths and symbols of the chunks are redacted, and the contributors pseudonymised if
+///   set, and the file hashes salted, so that the points can still be resumed and pruned with
+///   the same salt
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct EmbeddingOptions {
+    pub chunking: ChunkingOptions,
+    pub batch_size: usize,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    #[serde(default)]
+    pub payload: PayloadMode,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub resume: bool,
+    #[serde(default)]
+    pub document: DocumentFormat,
+    #[serde(default = "default_description_template")]
+    pub description_template: String,
+    #[serde(default)]
+    pub anonymisation: Option<AnonymisationOptions>,
+}
+fn default_description_template() -> String {
+    DEFAULT_DESCRIPTION_TEMPLATE.to_string()
+}
+impl Default for EmbeddingOptions {
+    fn default() -> Self {
+        Self {
+            chunking: ChunkingOptions::default(),
+            batch_size: 64,
+            limits: ResourceLimits::default(),
+            payload: PayloadMode::default(),
+            retry: RetryPolicy::default(),
+            resume: false,
+            document: DocumentFormat::default(),
+            description_template: default_description_template(),
+            anonymisation: None,
+        }
+    }
+}
+
+/// The progress of [`create_repository_embedding`], reported after each batch
+///
+/// #Fields:
+/// * `num_files` - The number of source files to embed
+/// * `files_embedded` - The number of source files embedded so far
+/// * `points_upserted` - The number of points upserted so far
+/// * `points_skipped` - The number of points skipped so far, as already stored, when resuming
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct EmbeddingProgress {
+    pub num_files: usize,
+    pub files_embedded: usize,
+    pub points_upserted: usize,
+    #[serde(default)]
+    pub points_skipped: usize,
+}
+
+/// A chunk of a source file, with the document embedded for it, its number of tokens and
+/// truncated lines, and the id and payload of its point
+struct PreparedChunk {
+    id: String,
+    path: RepoRelativePath,
+    payload: Map<String, Value>,
+    document: String,
+    num_tokens: usize,
+    num_truncated_lines: usize,
+}
+
+/// Embeds repositories into a [`VectorStore`] with an [`EmbeddingsProvider`], and searches
+/// them; both are taken as traits, so integrations can be tested without a vector database or a
+/// model download, e.g., with the doubles of the `testing` feature
+///
+/// #Fields:
+/// * `provider` - The [`EmbeddingsProvider`], e.g., a
+///   [`crate::embeddings_provider::local::FastEmbedProvider`]
+/// * `store` - The [`VectorStore`], e.g., a [`crate::vector_store::qdrant::QdrantVectorStore`]
+/// * `options` - The [`EmbeddingOptions`]
+/// * `observer` - The [`AnalysisObserver`] notified of the progress, if any
+/// * `cancellation` - The [`CancellationToken`] checked before each batch, if any
+pub struct EmbeddingPipeline<'a> {
+    provider: &'a dyn EmbeddingsProvider,
+    store: &'a dyn VectorStore,
+    options: EmbeddingOptions,
+    observer: Option<&'a dyn AnalysisObserver>,
+    cancellation: Option<&'a CancellationToken>,
+}
+impl<'a> EmbeddingPipeline<'a> {
+    /// Creates the pipeline embedding with the `provider` into the `store`, with the default
+    /// [`EmbeddingOptions`]
+    pub fn new(provider: &'a dyn EmbeddingsProvider, store: &'a dyn VectorStore) -> Self {
+        Self {
+            provider,
+            store,
+            options: EmbeddingOptions::default(),
+            observer: None,
+            cancellation: None,
+        }
+    }
+    pub fn with_options(mut self, options: EmbeddingOptions) -> Self {
+        self.options = options;
+        self
+    }
+    pub fn with_observer(mut self, observer: &'a dyn AnalysisObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+    pub fn with_cancellation(mut self, cancellation: &'a CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+    /// Gets the [`EmbeddingOptions`] of the pipeline
+    pub fn options(&self) -> &EmbeddingOptions {
+        &self.options
+    }
+    /// Splits each source file of the repository into chunks, as configured by the
+    /// [`EmbeddingOptions`], embeds them with the [`EmbeddingsProvider`], and upserts the
+    /// vectors, one point per chunk, into the [`VectorStore`].
+    ///
+    /// Files are processed in batches: the chunks of a batch are prepared in parallel, then
+    /// embedded and upserted before the next batch, with the progress reported to the
+    /// [`AnalysisObserver`] after each. The [`CancellationToken`] is checked before each batch.
+    ///
+    /// Chunks whose documents exceed the maximum tokens of the model are split at line boundaries
+    /// into chunks that fit, so the model doesn't truncate them mid-identifier; a line that alone
+    /// exceeds it is truncated at a word boundary. The tokens embedded per file are recorded in the
+    /// [`EmbeddingSummary`].
+    ///
+    /// Point ids are derived from the file path and chunk index, so re-embedding a repository
+    /// updates its existing points.
+    ///
+    /// The payload of each point is set by the [`PayloadMode`] of the [`EmbeddingOptions`]: with
+    /// [`PayloadMode::Metadata`], the contents are only sent to the [`EmbeddingsProvider`], and
+    /// aren't stored.
+    ///
+    /// Embedding and the calls to the [`VectorStore`] are timed out and retried as the
+    /// [`RetryPolicy`] of the [`EmbeddingOptions`] sets. When resuming, the chunks whose points are
+    /// stored with the same file hash and lines are skipped, so a failed embedding can be run again
+    /// from where it stopped.
+    pub async fn embed_repository(
+        &self,
+        stats: &RepositoryInfo,
+    ) -> Result<EmbeddingSummary, SourceCodeError> {
+        self.embed_into(stats, self.store).await
+    }
+    /// Embeds the source files of the repository, as [`EmbeddingPipeline::embed_repository`]
+    /// does, but writes the points to the file at `path`, in the [`EmbeddingExportFormat`],
+    /// rather than upserting them into the [`VectorStore`], e.g., to inspect them or to load
+    /// them into another system without running a vector database
+    ///
+    /// #Returns:
+    /// * The [`EmbeddingSummary`], with the points written as the `num_points`
+    /// * Err([`SourceCodeError::FileReadError`]) if the files can't be written
+    pub async fn export_to_path(
+        &self,
+        stats: &RepositoryInfo,
+        path: &Path,
+        format: EmbeddingExportFormat,
+    ) -> Result<EmbeddingSummary, SourceCodeError> {
+        let writer = EmbeddingFileWriter::create(path, format)?;
+        let summary = self.embed_into(stats, &writer).await?;
+        writer.finish()?;
+        Ok(summary)
+    }
+    /// Deletes the points of the repository's source files that were removed or changed since
+    /// they were embedded, see [`lifecycle::prune_repository`]
+    ///
+    /// #Returns:
+    /// * The number of points deleted
+    pub async fn prune(&self, stats: &RepositoryInfo) -> Result<usize, SourceCodeError> {
+        let anonymiser = self
+            .options
+            .anonymisation
+            .as_ref()
+            .map(PathHasher::from_options);
+        lifecycle::prune_redacted(stats, anonymiser.as_ref(), self.store, &self.options.retry).await
+    }
+    /// Deletes the points of the repositories that aren't `active`, e.g., of archived
+    /// repositories, see [`lifecycle::drop_repositories`]
+    ///
+    /// #Returns:
+    /// * The names of the repositories whose points were deleted
+    pub async fn drop_repositories(&self, active: &[&str]) -> Result<Vec<String>, SourceCodeError> {
+        lifecycle::drop_repositories(active, self.store, &self.options.retry).await
+    }
+    /// Deletes all the points of the repository and embeds it again from scratch, e.g., after
+    /// changing the embedding model or the chunking options
+    pub async fn recreate(
+        &self,
+        stats: &RepositoryInfo,
+    ) -> Result<EmbeddingSummary, SourceCodeError> {
+        self.options
+            .retry
+            .run("delete the repository", || {
+                self.store.delete_by_repo(&stats.name)
+            })
+            .await?;
+        self.embed_repository(stats).await
+    }
+    /// Reconciles the points of the repository with the snapshot of its
+    /// [`RepositoryInfo::fingerprint`]: the points of removed and changed source files are
+    /// pruned, then the snapshot is embedded, resuming, so only the chunks not already stored
+    /// are embedded
+    pub async fn sync(&self, stats: &RepositoryInfo) -> Result<SyncSummary, SourceCodeError> {
+        let num_pruned = self.prune(stats).await?;
+        let embedding = match self.options.resume {
+            true => self.embed_repository(stats).await?,
+            false => {
+                let mut pipeline = EmbeddingPipeline::new(self.provider, self.store).with_options(
+                    EmbeddingOptions {
+                        resume: true,
+                        ..self.options.clone()
+                    },
+                );
+                pipeline.observer = self.observer;
+                pipeline.cancellation = self.cancellation;
+                pipeline.embed_repository(stats).await?
+            }
+        };
+        Ok(SyncSummary {
+            repository: stats.name.clone(),
+            fingerprint: stats.fingerprint.clone(),
+            num_pruned,
+            embedding,
+        })
+    }
+    /// Embeds the source files of the repository into the `store`, see
+    /// [`EmbeddingPipeline::embed_repository`]
+    #[tracing::instrument(
+        name = "analysis_stage",
+        skip_all,
+        fields(
+            stage = ?AnalysisStage::Embedding,
+            repository = %stats.name,
+            elapsed_ms = tracing::field::Empty
+        )
+    )]
+    async fn embed_into(
+        &self,
+        stats: &RepositoryInfo,
+        store: &dyn VectorStore,
+    ) -> Result<EmbeddingSummary, SourceCodeError> {
+        tracing::info!(
+            "starting embedding with model: {}",
+            self.provider.model_name()
+        );
+        let started = Instant::now();
 
-    // TODO create a viable struct to hold the embeddings
-    // TODO insert into the Qdrant database
+        // Derive 'sentiment' from Statistics:
+        //
+        //    size_sentiment = -log10(size) (larger size = more negative sentiment)
+        //    loc_sentiment = -log10(loc)
+        //    frequency_sentiment = -log10(frequency)
+        //
+        //    TODO: for contributors, reverse the sentiment:
+        //    contributor_frequency_sentiment = log10(frequency)
+        let mut status = EmbeddingProgress {
+            num_files: stats.source_files.len(),
+            ..Default::default()
+        };
+        let mut vector_size: u64 = 0;
+        let mut num_tokens: usize = 0;
+        let mut num_truncated_lines: usize = 0;
+        let mut file_tokens: BTreeMap<RepoRelativePath, usize> = BTreeMap::new();
+        for batch in stats.source_files.chunks(self.options.batch_size.max(1)) {
+            if let Some(cancellation) = self.cancellation {
+                cancellation.check(AnalysisStage::Embedding, || {
+                    PartialResults::Embedding(status.clone())
+                })?;
+            }
+            let mut prepared = prepare_chunks(batch, self.provider, &self.options)?;
+            if self.options.resume {
+                let num_prepared = prepared.len();
+                skip_stored_chunks(&stats.name, store, &self.options.retry, &mut prepared).await?;
+                status.points_skipped += num_prepared - prepared.len();
+            }
+            for prepared in &prepared {
+                num_tokens += prepared.num_tokens;
+                num_truncated_lines += prepared.num_truncated_lines;
+                *file_tokens.entry(prepared.path.clone()).or_default() += prepared.num_tokens;
+            }
+            if !prepared.is_empty() {
+                let documents: Vec<String> = prepared
+                    .iter_mut()
+                    .map(|prepared| std::mem::take(&mut prepared.document))
+                    .collect();
+                let embeddings = self
+                    .options
+                    .retry
+                    .run("embed the chunks", || {
+                        self.provider.embed(documents.clone())
+                    })
+                    .await?;
+                if let Some(embedding) = embeddings.first() {
+                    vector_size = embedding.len() as u64;
+                }
 
-    log::info!("Embeddings length: {:?}", embeddings);
+                let points: Vec<VectorPoint> = prepared
+                    .into_iter()
+                    .zip(embeddings)
+                    .map(|(prepared, vector)| VectorPoint {
+                        id: prepared.id,
+                        vector,
+                        payload: prepared.payload,
+                    })
+                    .collect();
+                status.points_upserted += self
+                    .options
+                    .retry
+                    .run("upsert the points", || {
+                        store.upsert(&stats.name, points.clone())
+                    })
+                    .await?;
+            }
+            status.files_embedded += batch.len();
+            tracing::info!(
+                "embedded {} of {} files",
+                status.files_embedded,
+                status.num_files
+            );
+            if let Some(observer) = self.observer {
+                observer.on_embedding_progress(&status);
+            }
+        }
+        tracing::Span::current().record("elapsed_ms", started.elapsed().as_millis() as i64);
+        if let Some(observer) = self.observer {
+            observer.on_stage_complete(AnalysisStage::Embedding, started.elapsed());
+        }
 
-    // TODO: configure the Qdrant server URL from an environment variable
-    let _client = QdrantClient::from_url("http://localhost:6334").build()?;
+        Ok(EmbeddingSummary {
+            repository: stats.name.clone(),
+            vector_size,
+            num_files: status.num_files,
+            num_points: status.points_upserted,
+            num_skipped: status.points_skipped,
+            num_tokens,
+            num_truncated_lines,
+            file_tokens,
+        })
+    }
+    /// Finds the source file chunks of the repository most similar to a natural-language
+    /// `query`, see [`search_repository`]
+    pub async fn search(
+        &self,
+        stats: &RepositoryInfo,
+        query: &str,
+        top_k: usize,
+    ) -> Result<Vec<SearchMatch>, SourceCodeError> {
+        search_repository(stats, self.provider, self.store, query, top_k).await
+    }
+    /// Finds the pairs of the repository's source files whose embeddings are nearly the same,
+    /// see [`similar::find_similar_files`]
+    pub async fn find_similar_files(
+        &self,
+        stats: &RepositoryInfo,
+        options: &SimilarFilesOptions,
+    ) -> Result<SimilarFilesReport, SourceCodeError> {
+        self.options
+            .retry
+            .run("find the similar files", || {
+                similar::find_similar_files(stats, self.store, options)
+            })
+            .await
+    }
+    /// Embeds the contributors of the analysed repository at `repo_path`, see
+    /// [`contributors::create_contributor_embedding`]
+    #[cfg(feature = "git")]
+    pub async fn embed_contributors(
+        &self,
+        repo_path: &str,
+        stats: &RepositoryInfo,
+    ) -> Result<EmbeddingSummary, SourceCodeError> {
+        contributors::create_contributor_embedding(
+            repo_path,
+            stats,
+            self.provider,
+            self.store,
+            &self.options,
+        )
+        .await
+    }
+    /// Finds the contributors whose work is most similar to that of the contributor with the
+    /// `name`, see [`contributors::find_similar_contributors`]
+    pub async fn find_similar_contributors(
+        &self,
+        stats: &RepositoryInfo,
+        name: &str,
+        top_k: usize,
+    ) -> Result<Vec<contributors::ContributorMatch>, SourceCodeError> {
+        contributors::find_similar_contributors(stats, self.provider, self.store, name, top_k).await
+    }
+    /// Finds the contributors who know most about a source file or a query, see
+    /// [`contributors::who_knows_about`]
+    pub async fn who_knows_about(
+        &self,
+        stats: &RepositoryInfo,
+        path_or_query: &str,
+        top_k: usize,
+    ) -> Result<Vec<contributors::ContributorMatch>, SourceCodeError> {
+        contributors::who_knows_about(stats, self.provider, self.store, path_or_query, top_k).await
+    }
+}
 
+/// Embeds the source files of the repository, as [`EmbeddingPipeline::embed_repository`] does,
+/// with an [`EmbeddingPipeline`] of the arguments
+pub async fn create_repository_embedding(
+    stats: RepositoryInfo,
+    provider: &dyn EmbeddingsProvider,
+    store: &dyn VectorStore,
+    options: &EmbeddingOptions,
+    observer: Option<&dyn AnalysisObserver>,
+    cancellation: Option<&CancellationToken>,
+) -> Result<EmbeddingSummary, SourceCodeError> {
+    let mut pipeline = EmbeddingPipeline::new(provider, store).with_options(options.clone());
+    pipeline.observer = observer;
+    pipeline.cancellation = cancellation;
+    pipeline.embed_repository(&stats).await
+}
+/// Removes the `prepared` chunks whose points are already stored for the `repository` with the
+/// same file hash and lines, i.e., that are unchanged since they were upserted
+async fn skip_stored_chunks(
+    repository: &str,
+    store: &dyn VectorStore,
+    retry: &RetryPolicy,
+    prepared: &mut Vec<PreparedChunk>,
+) -> Result<(), SourceCodeError> {
+    let ids: Vec<String> = prepared
+        .iter()
+        .map(|prepared| prepared.id.clone())
+        .collect();
+    let stored = retry
+        .run("get the stored points", || {
+            store.get_payloads(repository, &ids)
+        })
+        .await?;
+    prepared.retain(|prepared| {
+        !stored.get(&prepared.id).is_some_and(|payload| {
+            ["id_hash", "start_line", "end_line"]
+                .iter()
+                .all(|key| payload.get(*key) == prepared.payload.get(*key))
+        })
+    });
     Ok(())
 }
+/// Chunks a batch of source files, and builds the document embedded for each chunk, across
+/// the worker threads allowed by the [`ResourceLimits`] of the [`EmbeddingOptions`]
+fn prepare_chunks(
+    source_files: &[SourceFileInfo],
+    provider: &dyn EmbeddingsProvider,
+    options: &EmbeddingOptions,
+) -> Result<Vec<PreparedChunk>, SourceCodeError> {
+    let results = options.limits.map_chunks(
+        source_files,
+        |files| -> Result<Vec<PreparedChunk>, SourceCodeError> {
+            let mut prepared: Vec<PreparedChunk> = Vec::new();
+            for source_file_info in files {
+                prepared.extend(prepare_file_chunks(source_file_info, provider, options)?);
+            }
+            Ok(prepared)
+        },
+    );
+    let mut prepared: Vec<PreparedChunk> = Vec::new();
+    for (_, chunks) in results {
+        let chunks = chunks.map_err(|_| {
+            SourceCodeError::EmbeddingError(anyhow::anyhow!("Failed to prepare chunks"))
+        })??;
+        prepared.extend(chunks);
+    }
+    Ok(prepared)
+}
+/// Chunks a source file; each chunk is embedded as a document in the [`DocumentFormat`] of the
+/// [`EmbeddingOptions`], and is split to fit the maximum tokens of the [`EmbeddingsProvider`];
+/// oversized files aren't chunked
+fn prepare_file_chunks(
+    source_file_info: &SourceFileInfo,
+    provider: &dyn EmbeddingsProvider,
+    options: &EmbeddingOptions,
+) -> Result<Vec<PreparedChunk>, SourceCodeError> {
+    if source_file_info.oversized {
+        tracing::debug!("Not embedding oversized file {}", source_file_info.name);
+        return Ok(vec![]);
+    }
+    let file = map_source_file_info_to_file(source_file_info);
+    let mut json_value: Value = serde_json::to_value(&file)
+        .map_err(|err| SourceCodeError::SerializationError(err.into()))?;
+    let description = match options.document {
+        DocumentFormat::Description => Some(describe_file(
+            source_file_info,
+            &options.description_template,
+        )),
+        DocumentFormat::Flattened => None,
+    };
+    let mut get_chunk_document = |chunk: &SourceChunk| match &description {
+        Some(description) => [
+            ("{start_line}", chunk.start_line.to_string()),
+            ("{end_line}", chunk.end_line.to_string()),
+            (
+                "{kind}",
+                chunk.kind.clone().unwrap_or_else(|| "lines".to_string()),
+            ),
+        ]
+        .iter()
+        .fold(description.clone(), |document, (placeholder, value)| {
+            document.replace(placeholder, value)
+        })
+        .replace("{contents}", &chunk.contents),
+        None => {
+            json_value["data"]["contents"] = Value::String(chunk.contents.clone());
+            get_document(&file.name, &json_value)
+        }
+    };
+    let chunks = chunk_contents(&file.data.contents, &file.data.language, &options.chunking);
+    let chunks: Vec<(SourceChunk, usize)> = match (provider.max_tokens(), chunks.first()) {
+        (Some(max_tokens), Some(first)) => {
+            let overhead = provider.count_tokens(&get_chunk_document(&SourceChunk {
+                contents: String::new(),
+                ..first.clone()
+            }));
+            // The lines of flattened documents are escaped, as JSON strings
+            let count_line = |line: &str| match options.document {
+                DocumentFormat::Description => provider.count_tokens(line),
+                DocumentFormat::Flattened => {
+                    provider.count_tokens(&Value::String(line.to_string()).to_string())
+                }
+            };
+            fit_chunks(
+                chunks,
+                max_tokens.saturating_sub(overhead).max(1),
+                &count_line,
+            )
+        }
+        _ => chunks.into_iter().map(|chunk| (chunk, 0)).collect(),
+    };
+    let anonymiser = options.anonymisation.as_ref().map(PathHasher::from_options);
+    let path: RepoRelativePath = match &anonymiser {
+        Some(anonymiser) => anonymiser.redact_path(&file.path).into(),
+        None => file.path.clone(),
+    };
+    let mut prepared: Vec<PreparedChunk> = Vec::with_capacity(chunks.len());
+    for (chunk, num_truncated_lines) in chunks {
+        let document = get_chunk_document(&chunk);
+        let mut payload = get_chunk_payload(source_file_info, &file, &chunk, options.payload);
+        if let Some(anonymiser) = &anonymiser {
+            anonymise_chunk_payload(&mut payload, &path, anonymiser);
+        }
+        prepared.push(PreparedChunk {
+            id: get_point_id(&format!("{}#{}", path, chunk.index)),
+            path: path.clone(),
+            payload,
+            num_tokens: provider.count_tokens(&document),
+            num_truncated_lines,
+            document,
+        });
+    }
+    Ok(prepared)
+}
+/// Gets the document embedded for a file, from the flattened "name: /key/value" entries of its
+/// `data`
+fn get_document(name: &str, json_value: &Value) -> String {
+    flatten_json(&json_value["data"])
+        .into_iter()
+        .map(|value| format!("{}: {}", name, value))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+/// Splits the `chunks` of a file whose contents exceed the `budget` of tokens into chunks of
+/// whole lines that fit it, truncating the lines that alone don't, then renumbers them; lines
+/// are counted with `count_line`, as they appear in the document
+///
+/// #Returns:
+/// * The chunks, each with the number of its lines that were truncated
+fn fit_chunks(
+    chunks: Vec<SourceChunk>,
+    budget: usize,
+    count_line: &impl Fn(&str) -> usize,
+) -> Vec<(SourceChunk, usize)> {
+    let mut fitted: Vec<(SourceChunk, usize)> = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        if count_line(&chunk.contents) <= budget {
+            fitted.push((chunk, 0));
+            continue;
+        }
+        let mut lines: Vec<String> = Vec::new();
+        let mut num_tokens = 0;
+        let mut num_truncated_lines = 0;
+        let mut start_line = chunk.start_line;
+        let mut push = |lines: &mut Vec<String>, start_line: usize, num_truncated_lines: usize| {
+            let contents = lines.join("\n");
+            if !contents.trim().is_empty() {
+                fitted.push((
+                    SourceChunk {
+                        index: 0,
+                        start_line,
+                        end_line: start_line + lines.len() - 1,
+                        kind: chunk.kind.clone(),
+                        contents,
+                    },
+                    num_truncated_lines,
+                ));
+            }
+            lines.clear();
+        };
+        for (offset, line) in chunk.contents.split('\n').enumerate() {
+            let mut line_tokens = count_line(line);
+            let truncated = line_tokens > budget;
+            let line = match truncated {
+                true => {
+                    let prefix = truncate_line(line, budget, count_line);
+                    line_tokens = count_line(prefix);
+                    tracing::debug!(
+                        "Truncated line {} to {} tokens",
+                        chunk.start_line + offset,
+                        line_tokens
+                    );
+                    prefix
+                }
+                false => line,
+            };
+            if !lines.is_empty() && num_tokens + line_tokens > budget {
+                push(&mut lines, start_line, num_truncated_lines);
+                start_line = chunk.start_line + offset;
+                num_tokens = 0;
+                num_truncated_lines = 0;
+            }
+            lines.push(line.to_string());
+            num_tokens += line_tokens;
+            num_truncated_lines += usize::from(truncated);
+        }
+        push(&mut lines, start_line, num_truncated_lines);
+    }
+    for (index, (chunk, _)) in fitted.iter_mut().enumerate() {
+        chunk.index = index;
+    }
+    fitted
+}
+/// Truncates a `line` to the longest prefix within the `budget` of tokens, ending at a word
+/// boundary where there is one, so an identifier isn't cut
+fn truncate_line<'a>(line: &'a str, budget: usize, count_line: &impl Fn(&str) -> usize) -> &'a str {
+    let boundaries: Vec<usize> = line.char_indices().map(|(index, _)| index).collect();
+    let (mut low, mut high) = (0, boundaries.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        let end = boundaries.get(mid).copied().unwrap_or(line.len());
+        if count_line(&line[..end]) <= budget {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    let prefix = &line[..boundaries.get(low).copied().unwrap_or(line.len())];
+    match prefix.rfind(char::is_whitespace) {
+        Some(end) if end > 0 => &prefix[..end],
+        _ => prefix,
+    }
+}
+/// Gets the payload stored with the point of a chunk, as the [`PayloadMode`] sets
+fn get_chunk_payload(
+    source_file_info: &SourceFileInfo,
+    file: &FileToEmbed,
+    chunk: &SourceChunk,
+    mode: PayloadMode,
+) -> Map<String, Value> {
+    let payload = match mode {
+        PayloadMode::Full => json!({
+            "path": file.path,
+            "name": file.name,
+            "language": file.data.language,
+            "id_hash": file.data.id_hash,
+            "size_sentiment": file.data.size_sentiment,
+            "loc_sentiment": file.data.loc_sentiment,
+            "frequency_sentiment": file.data.frequency_sentiment,
+            "chunk_index": chunk.index,
+            "start_line": chunk.start_line,
+            "end_line": chunk.end_line,
+            "kind": chunk.kind,
+            "symbols": source_file_info
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.overlaps(chunk.start_line, chunk.end_line))
+                .map(|symbol| symbol.get_qualified_name())
+                .collect::<Vec<String>>(),
+            "contents": chunk.contents,
+        }),
+        PayloadMode::Metadata => {
+            let statistics = &source_file_info.statistics;
+            json!({
+                "path": file.path,
+                "language": file.data.language,
+                "id_hash": file.data.id_hash,
+                "size": statistics.size,
+                "loc": statistics.loc,
+                "num_commits": statistics.num_commits,
+                "frequency": statistics.change_frequency,
+                "chunk_index": chunk.index,
+                "start_line": chunk.start_line,
+                "end_line": chunk.end_line,
+            })
+        }
+    };
+    payload.as_object().cloned().unwrap_or_default()
+}
+/// Anonymises the payload of a chunk with the [`PathHasher`]: its file's `path` is replaced with
+/// the redacted path, its `id_hash` is salted, as [`PathHasher::anonymise`] salts it, so that
+/// the file can't be matched against known files, and the names of its symbols are redacted
+fn anonymise_chunk_payload(
+    payload: &mut Map<String, Value>,
+    path: &RepoRelativePath,
+    anonymiser: &PathHasher,
+) {
+    payload.insert("path".to_string(), json!(path));
+    if let Some(id_hash) = payload.get("id_hash").and_then(Value::as_str) {
+        let id_hash = anonymiser.hash(id_hash);
+        payload.insert("id_hash".to_string(), json!(id_hash));
+    }
+    if payload.contains_key("name") {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        payload.insert("name".to_string(), json!(name));
+    }
+    if let Some(Value::Array(symbols)) = payload.get_mut("symbols") {
+        for symbol in symbols {
+            if let Some(name) = symbol.as_str() {
+                *symbol = json!(anonymiser.redact_name(name));
+            }
+        }
+    }
+}
+/// Finds the source file chunks of the repository most similar to a natural-language `query`,
+/// e.g., 'where are database connections opened?', by embedding the query with the
+/// [`EmbeddingsProvider`] the repository was embedded with and searching the [`VectorStore`]
+///
+/// #Arguments:
+/// * `stats` - The [`RepositoryInfo`] of the repository, embedded by [`create_repository_embedding`]
+/// * `provider` - The [`EmbeddingsProvider`] to embed the query with
+/// * `store` - The [`VectorStore`] holding the repository's points
+/// * `query` - The natural-language query
+/// * `top_k` - The maximum number of matches
+///
+/// #Returns:
+/// * The [`SearchMatch`]es, most similar first
+pub async fn search_repository(
+    stats: &RepositoryInfo,
+    provider: &dyn EmbeddingsProvider,
+    store: &dyn VectorStore,
+    query: &str,
+    top_k: usize,
+) -> Result<Vec<SearchMatch>, SourceCodeError> {
+    let vector = provider.embed_query(query).await?;
+    let points = store.search(&stats.name, &vector, top_k).await?;
+    Ok(points
+        .into_iter()
+        .map(|point| SearchMatch::from_point(point, stats))
+        .collect())
+}
+/// Gets a stable point id (a UUID formatted from the SHA256 hash of the key) for a chunk
+fn get_point_id(key: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(key.as_bytes()));
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..32]
+    )
+}
 /// Maps a SourceFileInfo to a File struct
 fn map_source_file_info_to_file(source_file_info: &SourceFileInfo) -> FileToEmbed {
     let language = source_file_info
@@ -130,15 +1259,25 @@ fn map_source_file_info_to_file(source_file_info: &SourceFileInfo) -> FileToEmbe
         .map(|l| l.name.clone())
         .unwrap_or_default();
     let id_hash = source_file_info.id_hash.clone().unwrap_or_default();
-    let contents = source_file_info.get_source_file_contents();
+    let contents = source_file_info
+        .get_source_file_contents()
+        .unwrap_or_else(|err| {
+            tracing::debug!(
+                "Embedding {} without its contents: {}",
+                source_file_info.name,
+                err
+            );
+            String::new()
+        });
 
     let statistics = source_file_info.statistics.clone();
     let size_sentiment = negative_sentiment_for_int(statistics.size);
     let loc_sentiment = negative_sentiment_for_int(statistics.loc);
-    let frequency_sentiment = negative_sentiment_for_float(statistics.frequency);
+    let frequency_sentiment = negative_sentiment_for_float(statistics.change_frequency.0);
 
     FileToEmbed {
         name: source_file_info.name.clone(),
+        path: source_file_info.relative_path.clone(),
         data: FileData {
             language,
             id_hash,
@@ -152,7 +1291,7 @@ fn map_source_file_info_to_file(source_file_info: &SourceFileInfo) -> FileToEmbe
 
 /// Creates a negative sentiment value from a number using - ilog10(num)
 /// Used to derive sentiment from the size and loc of a source file
-fn negative_sentiment_for_int(num: i64) -> f32 {
+fn negative_sentiment_for_int(num: u64) -> f32 {
     if num == 0 {
         return 0.0;
     }
@@ -196,3 +1335,53 @@ fn flatten_json(json: &Value) -> Vec<String> {
 
     tokens
 }
+
+#[cfg(all(test, feature = "git", feature = "embeddings"))]
+mod tests {
+    use super::*;
+    use crate::{
+        export::anonymise::PathRedaction,
+        options::AnalysisOptions,
+        testing::{DeterministicModel, FixtureRepo, MockVectorStore},
+    };
+
+    #[test]
+    fn salts_the_file_hashes_of_anonymised_payloads() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("src/lib.rs", "pub fn parse() -> u32 {\n    42\n}\n")
+            .commit("Add the parser")?;
+        let stats = fixture.analyse(&AnalysisOptions::default())?;
+        let anonymisation = AnonymisationOptions {
+            salt: "pepper".to_string(),
+            pseudonymise_contributors: true,
+            paths: PathRedaction::Full,
+        };
+        let anonymiser = PathHasher::from_options(&anonymisation);
+        let model = DeterministicModel::default();
+        let store = MockVectorStore::new();
+        let pipeline = EmbeddingPipeline::new(&model, &store).with_options(EmbeddingOptions {
+            anonymisation: Some(anonymisation),
+            ..EmbeddingOptions::default()
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| SourceCodeError::ConfigError(err.to_string()))?;
+        runtime.block_on(pipeline.embed_repository(&stats))?;
+
+        let id_hash = stats.source_files[0].id_hash.as_deref().unwrap_or_default();
+        let points = store.upserted(&stats.name);
+        assert!(!points.is_empty());
+        for point in &points {
+            assert_eq!(point.payload["id_hash"], json!(anonymiser.hash(id_hash)));
+            assert_eq!(
+                point.payload["path"],
+                json!(anonymiser.redact_path("src/lib.rs"))
+            );
+        }
+        // The points are matched against the salted hashes, so none of the unchanged file is pruned
+        assert_eq!(runtime.block_on(pipeline.prune(&stats))?, 0);
+        assert_eq!(store.len(&stats.name), points.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,423 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(feature = "git")]
+use serde_json::{json, Map};
+#[cfg(feature = "git")]
+use std::collections::HashMap;
+
+#[cfg(feature = "git")]
+use crate::{
+    cancel::CancellationToken,
+    embedding::{EmbeddingOptions, EmbeddingSummary},
+    graph::CommitGraph,
+    identity::Author,
+    observer::NoopObserver,
+    vector_store::VectorPoint,
+};
+use crate::{
+    embedding::{describe_file, get_point_id},
+    embeddings_provider::EmbeddingsProvider,
+    errors::SourceCodeError,
+    export::anonymise::PathHasher,
+    repository::RepositoryInfo,
+    vector_store::{ScoredVectorPoint, VectorStore},
+};
+
+/// The number of files, changed most first, in a [`ContributorProfile`]
+const MAX_PROFILE_FILES: usize = 20;
+/// The template of the description of a source file queried by [`who_knows_about`]
+const FILE_QUERY_TEMPLATE: &str = "Changed {path}, a {language} {purpose} file";
+
+/// The profile of a contributor, embedded by [`create_contributor_embedding`]
+///
+/// #Fields:
+/// * `name` - The name of the contributor
+/// * `canonical_id` - The canonical id of the contributor, see [`crate::identity::Author`]
+/// * `num_commits` - The number of commits of the contributor
+/// * `languages` - The languages of the source files the contributor changed, the most
+///   changed first
+/// * `files` - The relative paths of the source files the contributor changed most, the most
+///   changed first, up to 20
+/// * `first_contribution` - The date of the contributor's first commit, if known
+/// * `last_contribution` - The date of the contributor's last commit, if known
+/// * `is_active` - Whether the contributor committed recently, see [`crate::retention`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContributorProfile {
+    pub name: String,
+    pub canonical_id: String,
+    pub num_commits: u64,
+    pub languages: Vec<String>,
+    pub files: Vec<String>,
+    pub first_contribution: Option<DateTime<Utc>>,
+    pub last_contribution: Option<DateTime<Utc>>,
+    pub is_active: bool,
+}
+impl ContributorProfile {
+    /// Gets the profiles of the contributors to the source files of the analysed repository at
+    /// `repo_path`, from the commits changing each file, with the merge strategy and identity
+    /// options it was analysed with; the contributors with the most commits first
+    #[cfg(feature = "git")]
+    pub fn from_repository(
+        repo_path: &str,
+        stats: &RepositoryInfo,
+    ) -> Result<Vec<ContributorProfile>, SourceCodeError> {
+        let options = stats
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.options.clone())
+            .unwrap_or_default();
+        let graph = CommitGraph::new(
+            repo_path,
+            options.merge_strategy,
+            options.history_mode,
+            options.history_sampling,
+            &options.identity,
+            &NoopObserver,
+            &CancellationToken::new(),
+        )?;
+        let changes = graph.changes()?;
+        let commits = graph.commits();
+
+        // The number of commits changing each file, and each language, by canonical id
+        type Counts<'a> = (&'a Author, HashMap<&'a str, i32>, HashMap<&'a str, i32>);
+        let mut counts: HashMap<&str, Counts> = HashMap::new();
+        for source_file in &stats.source_files {
+            let path = source_file.relative_path.as_str();
+            let language = source_file
+                .language
+                .as_ref()
+                .map(|language| language.name.as_str());
+            for index in changes
+                .commits_changing(path)
+                .iter()
+                .chain(changes.commits_adding(path))
+            {
+                let Some(commit) = commits.get(*index) else {
+                    continue;
+                };
+                let (_, files, languages) = counts
+                    .entry(commit.author.canonical_id.as_str())
+                    .or_insert_with(|| (&commit.author, HashMap::new(), HashMap::new()));
+                *files.entry(path).or_default() += 1;
+                if let Some(language) = language {
+                    *languages.entry(language).or_default() += 1;
+                }
+            }
+        }
+
+        let most_changed = |counts: HashMap<&str, i32>, limit: usize| -> Vec<String> {
+            let mut counts: Vec<(&str, i32)> = counts.into_iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            counts
+                .into_iter()
+                .take(limit)
+                .map(|(name, _)| name.to_string())
+                .collect()
+        };
+        let mut profiles: Vec<ContributorProfile> = counts
+            .into_iter()
+            .map(|(canonical_id, (author, files, languages))| {
+                let contributor = stats
+                    .contributors
+                    .iter()
+                    .find(|contributor| contributor.author.canonical_id == canonical_id);
+                ContributorProfile {
+                    name: author.name.clone(),
+                    canonical_id: canonical_id.to_string(),
+                    num_commits: contributor
+                        .map(|contributor| contributor.statistics.num_commits)
+                        .unwrap_or_default(),
+                    languages: most_changed(languages, usize::MAX),
+                    files: most_changed(files, MAX_PROFILE_FILES),
+                    first_contribution: contributor
+                        .and_then(|contributor| contributor.first_contribution),
+                    last_contribution: contributor.map(|contributor| contributor.last_contribution),
+                    is_active: contributor.is_some_and(|contributor| contributor.is_active),
+                }
+            })
+            .collect();
+        profiles.sort_by(|a, b| b.num_commits.cmp(&a.num_commits).then(a.name.cmp(&b.name)));
+        Ok(profiles)
+    }
+    /// Gets a copy of the profile anonymised by the [`PathHasher`]: its files are redacted, and
+    /// its name and canonical id replaced with pseudonyms if it pseudonymises contributors
+    pub fn anonymised(&self, anonymiser: &PathHasher) -> ContributorProfile {
+        let mut profile = self.clone();
+        if anonymiser.pseudonymises_contributors() {
+            profile.name = anonymiser.pseudonym(&profile.name);
+            profile.canonical_id = anonymiser.pseudonym(&profile.canonical_id);
+        }
+        for file in &mut profile.files {
+            *file = anonymiser.redact_path(file);
+        }
+        profile
+    }
+    /// Describes the contributor in natural language, as the document embedded for them
+    pub fn describe(&self) -> String {
+        let date = |date: Option<DateTime<Utc>>| {
+            date.map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        };
+        format!(
+            "{} made {} commits, from {} to {}, and is {}.\nLanguages: {}.\nFiles changed most: {}.",
+            self.name,
+            self.num_commits,
+            date(self.first_contribution),
+            date(self.last_contribution),
+            if self.is_active { "active" } else { "inactive" },
+            self.languages.join(", "),
+            self.files.join(", ")
+        )
+    }
+}
+
+/// A contributor matched by [`find_similar_contributors`] or [`who_knows_about`]
+///
+/// #Fields:
+/// * `name` - The name of the contributor
+/// * `canonical_id` - The canonical id of the contributor
+/// * `score` - The similarity of the contributor to the query; higher is more similar
+/// * `num_commits` - The number of commits of the contributor
+/// * `languages` - The languages of the source files the contributor changed, the most
+///   changed first
+/// * `files` - The relative paths of the source files the contributor changed most
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContributorMatch {
+    pub name: String,
+    pub canonical_id: String,
+    pub score: f32,
+    pub num_commits: u64,
+    pub languages: Vec<String>,
+    pub files: Vec<String>,
+}
+impl ContributorMatch {
+    /// Maps a point, stored by [`create_contributor_embedding`], to a [`ContributorMatch`]
+    fn from_point(point: ScoredVectorPoint) -> ContributorMatch {
+        let payload = &point.payload;
+        let get_str = |key: &str| {
+            payload
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let get_strings = |key: &str| -> Vec<String> {
+            payload
+                .get(key)
+                .and_then(Value::as_array)
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|value| value.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        ContributorMatch {
+            name: get_str("name"),
+            canonical_id: get_str("canonical_id"),
+            score: point.score,
+            num_commits: payload
+                .get("num_commits")
+                .and_then(Value::as_u64)
+                .unwrap_or_default(),
+            languages: get_strings("languages"),
+            files: get_strings("files"),
+        }
+    }
+}
+
+/// Gets the name the contributors of the `repository` are stored under in a [`VectorStore`],
+/// separately from its source files
+pub fn get_contributor_collection(repository: &str) -> String {
+    format!("{}_contributors", repository)
+}
+
+/// Embeds the [`ContributorProfile`]s of the analysed repository at `repo_path` with the
+/// [`EmbeddingsProvider`], and upserts them, one point per contributor, into the
+/// [`VectorStore`], under the [`get_contributor_collection`] of the repository.
+///
+/// Profiles are embedded in batches of the [`EmbeddingOptions`], with its
+/// [`crate::embedding::RetryPolicy`]. Point ids are derived from the canonical ids of the
+/// contributors, so re-embedding updates their existing points.
+///
+/// With the [`crate::export::anonymise::AnonymisationOptions`] of the [`EmbeddingOptions`], the
+/// payloads are those of the [`ContributorProfile::anonymised`] profiles, and the point ids are
+/// derived from their canonical ids, so that they can be found in a [`RepositoryInfo`]
+/// anonymised with the same options; the vectors are still embedded from the profiles as they
+/// are, so that they can be queried by [`who_knows_about`].
+#[cfg(feature = "git")]
+pub async fn create_contributor_embedding(
+    repo_path: &str,
+    stats: &RepositoryInfo,
+    provider: &dyn EmbeddingsProvider,
+    store: &dyn VectorStore,
+    options: &EmbeddingOptions,
+) -> Result<EmbeddingSummary, SourceCodeError> {
+    let profiles = ContributorProfile::from_repository(repo_path, stats)?;
+    let anonymiser = options.anonymisation.as_ref().map(PathHasher::from_options);
+    let collection = get_contributor_collection(&stats.name);
+    let mut summary = EmbeddingSummary {
+        repository: collection.clone(),
+        ..Default::default()
+    };
+    for batch in profiles.chunks(options.batch_size.max(1)) {
+        let documents: Vec<String> = batch.iter().map(ContributorProfile::describe).collect();
+        summary.num_tokens += documents
+            .iter()
+            .map(|document| provider.count_tokens(document))
+            .sum::<usize>();
+        let embeddings = options
+            .retry
+            .run("embed the contributors", || {
+                provider.embed(documents.clone())
+            })
+            .await?;
+        if let Some(embedding) = embeddings.first() {
+            summary.vector_size = embedding.len() as u64;
+        }
+        let points: Vec<VectorPoint> = batch
+            .iter()
+            .zip(documents)
+            .zip(embeddings)
+            .map(|((profile, description), vector)| {
+                let (profile, description) = match &anonymiser {
+                    Some(anonymiser) => {
+                        let profile = profile.anonymised(anonymiser);
+                        let description = profile.describe();
+                        (profile, description)
+                    }
+                    None => (profile.clone(), description),
+                };
+                VectorPoint {
+                    id: get_point_id(&profile.canonical_id),
+                    vector,
+                    payload: get_contributor_payload(&profile, description),
+                }
+            })
+            .collect();
+        summary.num_points += options
+            .retry
+            .run("upsert the contributors", || {
+                store.upsert(&collection, points.clone())
+            })
+            .await?;
+    }
+    tracing::info!(
+        "embedded {} contributors of {}",
+        summary.num_points,
+        stats.name
+    );
+    Ok(summary)
+}
+/// Gets the payload stored with the point of a contributor, with the `description` embedded
+#[cfg(feature = "git")]
+fn get_contributor_payload(
+    profile: &ContributorProfile,
+    description: String,
+) -> Map<String, Value> {
+    json!({
+        "name": profile.name,
+        "canonical_id": profile.canonical_id,
+        "num_commits": profile.num_commits,
+        "languages": profile.languages,
+        "files": profile.files,
+        "is_active": profile.is_active,
+        "description": description,
+    })
+    .as_object()
+    .cloned()
+    .unwrap_or_default()
+}
+
+/// Finds the contributors of the repository whose work is most similar to that of the
+/// contributor with the `name` (or canonical id), by embedding their stored description and
+/// searching the contributors embedded by [`create_contributor_embedding`]
+///
+/// #Returns:
+/// * The [`ContributorMatch`]es, most similar first, without the contributor themself
+/// * Err([`SourceCodeError::EmbeddingError`]) if no contributor with the `name` is embedded
+pub async fn find_similar_contributors(
+    stats: &RepositoryInfo,
+    provider: &dyn EmbeddingsProvider,
+    store: &dyn VectorStore,
+    name: &str,
+    top_k: usize,
+) -> Result<Vec<ContributorMatch>, SourceCodeError> {
+    let not_found = || {
+        SourceCodeError::EmbeddingError(anyhow::anyhow!(
+            "No contributor named {} is embedded for {}",
+            name,
+            stats.name
+        ))
+    };
+    let canonical_id = stats
+        .contributors
+        .iter()
+        .find(|contributor| {
+            contributor.author.canonical_id == name
+                || contributor.author.name.eq_ignore_ascii_case(name)
+        })
+        .map(|contributor| contributor.author.canonical_id.clone())
+        .ok_or_else(not_found)?;
+    let collection = get_contributor_collection(&stats.name);
+    let id = get_point_id(&canonical_id);
+    let description = store
+        .get_payloads(&collection, std::slice::from_ref(&id))
+        .await?
+        .remove(&id)
+        .and_then(|payload| {
+            payload
+                .get("description")
+                .and_then(Value::as_str)
+                .map(String::from)
+        })
+        .ok_or_else(not_found)?;
+    let vector = provider
+        .embed(vec![description])
+        .await?
+        .pop()
+        .ok_or_else(|| {
+            SourceCodeError::EmbeddingError(anyhow::anyhow!("No embedding returned for {}", name))
+        })?;
+    let mut matches: Vec<ContributorMatch> = store
+        .search(&collection, &vector, top_k + 1)
+        .await?
+        .into_iter()
+        .filter(|point| point.id != id)
+        .map(ContributorMatch::from_point)
+        .collect();
+    matches.truncate(top_k);
+    Ok(matches)
+}
+
+/// Finds the contributors of the repository who know most about a source file, by its
+/// relative path, or about a natural-language query, e.g., 'the database migrations', by
+/// searching the contributors embedded by [`create_contributor_embedding`]
+///
+/// #Returns:
+/// * The [`ContributorMatch`]es, most similar first
+pub async fn who_knows_about(
+    stats: &RepositoryInfo,
+    provider: &dyn EmbeddingsProvider,
+    store: &dyn VectorStore,
+    path_or_query: &str,
+    top_k: usize,
+) -> Result<Vec<ContributorMatch>, SourceCodeError> {
+    let query = match stats
+        .source_files
+        .iter()
+        .find(|source_file| source_file.relative_path == path_or_query)
+    {
+        Some(source_file) => describe_file(source_file, FILE_QUERY_TEMPLATE),
+        None => path_or_query.to_string(),
+    };
+    let vector = provider.embed_query(&query).await?;
+    Ok(store
+        .search(&get_contributor_collection(&stats.name), &vector, top_k)
+        .await?
+        .into_iter()
+        .map(ContributorMatch::from_point)
+        .collect())
+}
@@ -0,0 +1,231 @@
+//! Exporting the embeddings of a repository's source files to files, e.g., to inspect them or
+//! to load them into another system, without a vector database
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    errors::SourceCodeError,
+    vector_store::{ScoredVectorPoint, VectorPoint, VectorStore},
+};
+
+/// The length of the header of a '.npy' file, which is written when the shape of the vectors is
+/// known, after them; a multiple of 64, as the format requires
+const NPY_HEADER_LENGTH: usize = 128;
+
+/// The file format the embeddings are exported in by
+/// [`crate::embedding::EmbeddingPipeline::export_to_path`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingExportFormat {
+    /// JSON Lines, with a line of the id, vector and payload of each point
+    #[default]
+    Jsonl,
+    /// A NumPy '.npy' file of the vectors, as a matrix of 32-bit floats with a row per point,
+    /// and a sidecar JSON file, at the path with the extension 'json', of an array of the id and
+    /// payload of each row
+    Npy,
+}
+
+/// The files the points are written to, and the number and size of the vectors written
+struct ExportFiles {
+    vectors: BufWriter<File>,
+    sidecar: Option<BufWriter<File>>,
+    num_points: usize,
+    vector_size: Option<usize>,
+}
+
+/// A [`VectorStore`] that writes the points upserted into it to the files of an
+/// [`EmbeddingExportFormat`], so that the pipeline embeds into them as into a vector database;
+/// the points are written in the order they are upserted, and can't be read back or searched
+pub(crate) struct EmbeddingFileWriter {
+    path: PathBuf,
+    format: EmbeddingExportFormat,
+    files: Mutex<ExportFiles>,
+}
+impl EmbeddingFileWriter {
+    /// Creates the files of the `format` at `path`, replacing any there
+    pub(crate) fn create(
+        path: &Path,
+        format: EmbeddingExportFormat,
+    ) -> Result<EmbeddingFileWriter, SourceCodeError> {
+        let create = |path: &Path| -> Result<BufWriter<File>, SourceCodeError> {
+            File::create(path)
+                .map(BufWriter::new)
+                .map_err(SourceCodeError::for_path(path))
+        };
+        let mut vectors = create(path)?;
+        let sidecar = match format {
+            EmbeddingExportFormat::Jsonl => None,
+            EmbeddingExportFormat::Npy => {
+                vectors
+                    .write_all(&[b' '; NPY_HEADER_LENGTH])
+                    .map_err(SourceCodeError::for_path(path))?;
+                let sidecar_path = Self::sidecar_path(path);
+                let mut sidecar = create(&sidecar_path)?;
+                sidecar
+                    .write_all(b"[")
+                    .map_err(SourceCodeError::for_path(&sidecar_path))?;
+                Some(sidecar)
+            }
+        };
+        Ok(EmbeddingFileWriter {
+            path: path.to_path_buf(),
+            format,
+            files: Mutex::new(ExportFiles {
+                vectors,
+                sidecar,
+                num_points: 0,
+                vector_size: None,
+            }),
+        })
+    }
+    /// Finishes the files, e.g., writing the header of a '.npy' file once the shape of its
+    /// vectors is known
+    pub(crate) fn finish(self) -> Result<(), SourceCodeError> {
+        let mut files = self
+            .files
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(sidecar) = &mut files.sidecar {
+            let sidecar_path = Self::sidecar_path(&self.path);
+            sidecar
+                .write_all(b"]\n")
+                .and_then(|_| sidecar.flush())
+                .map_err(SourceCodeError::for_path(&sidecar_path))?;
+        }
+        if self.format == EmbeddingExportFormat::Npy {
+            let header = Self::npy_header(files.num_points, files.vector_size.unwrap_or(0));
+            files
+                .vectors
+                .seek(SeekFrom::Start(0))
+                .and_then(|_| files.vectors.write_all(&header))
+                .map_err(SourceCodeError::for_path(&self.path))?;
+        }
+        files
+            .vectors
+            .flush()
+            .map_err(SourceCodeError::for_path(&self.path))
+    }
+    /// Gets the path of the sidecar JSON file of the ids and payloads of a '.npy' file
+    fn sidecar_path(path: &Path) -> PathBuf {
+        path.with_extension("json")
+    }
+    /// Gets the header of a '.npy' file (version 1.0) of a matrix of little-endian 32-bit floats
+    /// with `num_rows` rows of `num_columns`, padded with spaces to [`NPY_HEADER_LENGTH`]
+    fn npy_header(num_rows: usize, num_columns: usize) -> Vec<u8> {
+        let mut header: Vec<u8> = b"\x93NUMPY\x01\x00".to_vec();
+        let dict_length = (NPY_HEADER_LENGTH - header.len() - 2) as u16;
+        header.extend_from_slice(&dict_length.to_le_bytes());
+        let dict = format!(
+            "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+            num_rows, num_columns
+        );
+        header.extend_from_slice(dict.as_bytes());
+        header.resize(NPY_HEADER_LENGTH - 1, b' ');
+        header.push(b'\n');
+        header
+    }
+    /// Writes the `points` to the files
+    fn write(&self, points: &[VectorPoint]) -> Result<(), SourceCodeError> {
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        let files = &mut *files;
+        for point in points {
+            let vector_size = *files.vector_size.get_or_insert(point.vector.len());
+            if point.vector.len() != vector_size {
+                return Err(SourceCodeError::EmbeddingError(anyhow::anyhow!(
+                    "The vector of point {} has {} dimensions, not {}",
+                    point.id,
+                    point.vector.len(),
+                    vector_size
+                )));
+            }
+            match &mut files.sidecar {
+                None => {
+                    serde_json::to_writer(&mut files.vectors, point)
+                        .map_err(|err| SourceCodeError::SerializationError(err.into()))?;
+                    files
+                        .vectors
+                        .write_all(b"\n")
+                        .map_err(SourceCodeError::for_path(&self.path))?;
+                }
+                Some(sidecar) => {
+                    for value in &point.vector {
+                        files
+                            .vectors
+                            .write_all(&value.to_le_bytes())
+                            .map_err(SourceCodeError::for_path(&self.path))?;
+                    }
+                    let separator: &[u8] = match files.num_points {
+                        0 => b"\n",
+                        _ => b",\n",
+                    };
+                    sidecar
+                        .write_all(separator)
+                        .map_err(SourceCodeError::for_path(&Self::sidecar_path(&self.path)))?;
+                    serde_json::to_writer(
+                        &mut *sidecar,
+                        &json!({ "id": point.id, "payload": point.payload }),
+                    )
+                    .map_err(|err| SourceCodeError::SerializationError(err.into()))?;
+                }
+            }
+            files.num_points += 1;
+        }
+        Ok(())
+    }
+}
+#[async_trait]
+impl VectorStore for EmbeddingFileWriter {
+    async fn upsert(
+        &self,
+        _repository: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<usize, SourceCodeError> {
+        self.write(&points)?;
+        Ok(points.len())
+    }
+    /// No points are read back, so none are skipped when resuming
+    async fn get_payloads(
+        &self,
+        _repository: &str,
+        _ids: &[String],
+    ) -> Result<HashMap<String, Map<String, Value>>, SourceCodeError> {
+        Ok(HashMap::new())
+    }
+    async fn search(
+        &self,
+        _repository: &str,
+        _vector: &[f32],
+        _limit: usize,
+    ) -> Result<Vec<ScoredVectorPoint>, SourceCodeError> {
+        Ok(vec![])
+    }
+    async fn delete_by_repo(&self, _repository: &str) -> Result<(), SourceCodeError> {
+        Ok(())
+    }
+    async fn list_repositories(&self) -> Result<Vec<String>, SourceCodeError> {
+        Ok(vec![])
+    }
+    async fn list_points(
+        &self,
+        _repository: &str,
+        _with_vectors: bool,
+    ) -> Result<Vec<VectorPoint>, SourceCodeError> {
+        Ok(vec![])
+    }
+    async fn delete_points(
+        &self,
+        _repository: &str,
+        _ids: &[String],
+    ) -> Result<(), SourceCodeError> {
+        Ok(())
+    }
+}
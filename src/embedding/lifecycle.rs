@@ -0,0 +1,242 @@
+//! Keeping the points of repositories in a [`VectorStore`] in step with their snapshots:
+//! pruning the points of source files that were removed or changed, dropping the points of
+//! repositories that are no longer analysed, e.g., archived ones, and syncing a repository to
+//! its current snapshot
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[cfg(feature = "embeddings")]
+use crate::vector_store::qdrant::QdrantVectorStore;
+use crate::{
+    embedding::{contributors::get_contributor_collection, EmbeddingSummary, RetryPolicy},
+    errors::SourceCodeError,
+    export::anonymise::PathHasher,
+    repository::RepositoryInfo,
+    vector_store::VectorStore,
+};
+
+/// The result of syncing a repository to its snapshot with
+/// [`crate::embedding::EmbeddingPipeline::sync`]
+///
+/// #Fields:
+/// * `repository` - The name of the repository
+/// * `fingerprint` - The [`RepositoryInfo::fingerprint`] of the snapshot synced to, if it has one
+/// * `num_pruned` - The number of points deleted, of source files removed or changed since they
+///   were embedded
+/// * `embedding` - The [`EmbeddingSummary`] of embedding the snapshot, skipping the chunks
+///   already stored
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct SyncSummary {
+    pub repository: String,
+    pub fingerprint: Option<String>,
+    pub num_pruned: usize,
+    pub embedding: EmbeddingSummary,
+}
+
+/// Deletes the points of the repository's source files that aren't in the snapshot, or whose
+/// file hash differs from that in the snapshot, i.e., of files removed or changed since they
+/// were embedded; the chunks of changed files are left to be embedded again
+///
+/// Without file hashes, e.g., with [`crate::source::ContentMode::None`], only the points of
+/// removed files are deleted.
+///
+/// #Returns:
+/// * The number of points deleted
+pub async fn prune_repository(
+    stats: &RepositoryInfo,
+    store: &dyn VectorStore,
+    retry: &RetryPolicy,
+) -> Result<usize, SourceCodeError> {
+    prune_redacted(stats, None, store, retry).await
+}
+
+/// Deletes the points of the repository's source files that were removed or changed, as
+/// [`prune_repository`] does, for points whose paths were redacted by the `anonymiser`, if any,
+/// see [`crate::embedding::EmbeddingOptions`]
+pub(crate) async fn prune_redacted(
+    stats: &RepositoryInfo,
+    anonymiser: Option<&PathHasher>,
+    store: &dyn VectorStore,
+    retry: &RetryPolicy,
+) -> Result<usize, SourceCodeError> {
+    // The paths and file hashes of the points, salted as their payloads are if anonymised
+    let id_hashes: HashMap<String, String> = stats
+        .source_files
+        .iter()
+        .map(|source_file| {
+            let path = source_file.relative_path.as_str();
+            let id_hash = source_file.id_hash.as_deref().unwrap_or_default();
+            match anonymiser {
+                Some(anonymiser) => (anonymiser.redact_path(path), anonymiser.hash(id_hash)),
+                None => (path.to_string(), id_hash.to_string()),
+            }
+        })
+        .collect();
+    let stored = retry
+        .run("list the stored points", || {
+            store.list_points(&stats.name, false)
+        })
+        .await?;
+    let mut stale: Vec<String> = stored
+        .into_iter()
+        .filter(|point| {
+            let path = point.payload.get("path").and_then(Value::as_str);
+            let id_hash = point.payload.get("id_hash").and_then(Value::as_str);
+            match path.and_then(|path| id_hashes.get(path)) {
+                None => true,
+                Some(expected) => id_hash.is_some_and(|id_hash| id_hash != expected),
+            }
+        })
+        .map(|point| point.id)
+        .collect();
+    if stale.is_empty() {
+        return Ok(0);
+    }
+    stale.sort_unstable();
+    retry
+        .run("delete the stale points", || {
+            store.delete_points(&stats.name, &stale)
+        })
+        .await?;
+    tracing::info!("pruned {} points of {}", stale.len(), stats.name);
+    Ok(stale.len())
+}
+
+/// Deletes the points of the repositories in the [`VectorStore`] that aren't `active`, e.g., of
+/// archived repositories, with those of their contributors; the contributors of the `active`
+/// repositories are kept. Only the repositories the store lists are deleted, so a Qdrant store
+/// needs a collection name with a prefix or suffix, e.g., 'code_{repository}', to set its
+/// collections apart from the others on the server.
+///
+/// #Returns:
+/// * The names of the repositories whose points were deleted, as the store lists them
+/// * Err([`SourceCodeError::ConfigError`]) if the store can't tell its repositories apart from
+///   other collections
+pub async fn drop_repositories(
+    active: &[&str],
+    store: &dyn VectorStore,
+    retry: &RetryPolicy,
+) -> Result<Vec<String>, SourceCodeError> {
+    let listed = retry
+        .run("list the stored repositories", || store.list_repositories())
+        .await?;
+    // The store may list the names it stores them under, e.g., the Qdrant collection names
+    let mut kept: Vec<String> = Vec::with_capacity(active.len() * 4);
+    for repository in active {
+        for name in [
+            repository.to_string(),
+            get_contributor_collection(repository),
+        ] {
+            #[cfg(feature = "embeddings")]
+            kept.push(QdrantVectorStore::get_collection_name(&name));
+            kept.push(name);
+        }
+    }
+    let dropped: Vec<String> = listed
+        .into_iter()
+        .filter(|repository| !kept.contains(repository))
+        .collect();
+    for repository in &dropped {
+        retry
+            .run("delete the repository", || store.delete_by_repo(repository))
+            .await?;
+        tracing::info!("dropped the points of {}", repository);
+    }
+    Ok(dropped)
+}
+
+#[cfg(all(test, feature = "embeddings"))]
+mod tests {
+    use super::*;
+    use crate::testing::MockVectorStore;
+    use crate::vector_store::VectorPoint;
+    #[cfg(feature = "git")]
+    use crate::{
+        embedding::EmbeddingPipeline,
+        options::AnalysisOptions,
+        testing::{DeterministicModel, FixtureRepo},
+    };
+    use serde_json::Map;
+    use tokio::runtime::Runtime;
+
+    fn get_runtime() -> Result<Runtime, SourceCodeError> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| SourceCodeError::ConfigError(err.to_string()))
+    }
+
+    #[test]
+    #[cfg(feature = "git")]
+    fn prunes_the_points_of_removed_and_changed_files() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("src/kept.rs", "pub fn kept() {}\n")
+            .with_file("src/changed.rs", "pub fn changed() {}\n")
+            .with_file("src/removed.rs", "pub fn removed() {}\n")
+            .commit("Add the modules")?;
+        let model = DeterministicModel::default();
+        let store = MockVectorStore::new();
+        let runtime = get_runtime()?;
+        let stats = fixture.analyse(&AnalysisOptions::default())?;
+        runtime.block_on(EmbeddingPipeline::new(&model, &store).embed_repository(&stats))?;
+        assert_eq!(store.len(&stats.name), 3);
+
+        let fixture = fixture
+            .with_file("src/changed.rs", "pub fn changed() -> u32 {\n    1\n}\n")
+            .without_file("src/removed.rs")
+            .commit("Change a module and remove another")?;
+        let stats = fixture.analyse(&AnalysisOptions::default())?;
+        let retry = RetryPolicy::default();
+        assert_eq!(
+            runtime.block_on(prune_repository(&stats, &store, &retry))?,
+            2
+        );
+        let points = runtime.block_on(store.list_points(&stats.name, false))?;
+        let paths: Vec<&str> = points
+            .iter()
+            .filter_map(|point| point.payload.get("path").and_then(Value::as_str))
+            .collect();
+        assert_eq!(paths, vec!["src/kept.rs"]);
+        // Pruning again deletes nothing
+        assert_eq!(
+            runtime.block_on(prune_repository(&stats, &store, &retry))?,
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn drops_the_inactive_repositories_and_keeps_the_active_contributors(
+    ) -> Result<(), SourceCodeError> {
+        let store = MockVectorStore::new();
+        let runtime = get_runtime()?;
+        let point = VectorPoint {
+            id: "1".to_string(),
+            vector: vec![1.0, 0.0],
+            payload: Map::new(),
+        };
+        for repository in [
+            "web".to_string(),
+            get_contributor_collection("web"),
+            "archived".to_string(),
+            get_contributor_collection("archived"),
+        ] {
+            runtime.block_on(store.upsert(&repository, vec![point.clone()]))?;
+        }
+        let mut dropped =
+            runtime.block_on(drop_repositories(&["web"], &store, &RetryPolicy::default()))?;
+        dropped.sort_unstable();
+        assert_eq!(
+            dropped,
+            vec![
+                "archived".to_string(),
+                get_contributor_collection("archived")
+            ]
+        );
+        assert_eq!(store.len("web"), 1);
+        assert_eq!(store.len(&get_contributor_collection("web")), 1);
+        assert!(store.is_empty("archived"));
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! Finding the pairs of source files whose embeddings are nearly the same, e.g., near-duplicates
+//! across languages or copy-pasted utilities that were since edited, which the hash-based
+//! [`crate::duplication`] can't find
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{errors::SourceCodeError, repository::RepositoryInfo, vector_store::VectorStore};
+
+/// The options for finding similar files with [`find_similar_files`]
+///
+/// #Fields:
+/// * `threshold` - The least cosine similarity, from 0 to 1, of the files of a pair
+/// * `max_pairs` - The number of most similar [`SimilarFilePair`]s kept in the
+///   [`SimilarFilesReport`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SimilarFilesOptions {
+    pub threshold: f32,
+    pub max_pairs: usize,
+}
+impl Default for SimilarFilesOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.95,
+            max_pairs: 100,
+        }
+    }
+}
+
+/// A pair of source files whose embeddings are similar
+///
+/// #Fields:
+/// * `first` - The relative path of one file, ordered before `second`
+/// * `second` - The relative path of the other file
+/// * `first_language` - The language of the `first` file
+/// * `second_language` - The language of the `second` file
+/// * `similarity` - The cosine similarity of the files' embeddings
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SimilarFilePair {
+    pub first: String,
+    pub second: String,
+    pub first_language: String,
+    pub second_language: String,
+    pub similarity: f32,
+}
+impl SimilarFilePair {
+    /// Checks whether the files are in different languages, e.g., a port of a utility
+    pub fn is_cross_language(&self) -> bool {
+        self.first_language != self.second_language
+    }
+}
+
+/// The pairs of source files of a repository whose embeddings are nearly the same, by the
+/// cosine similarity of the mean of the vectors of each file's chunks; identical files, which
+/// the [`crate::duplication::IdenticalFilesReport`] has, are left out
+///
+/// #Fields:
+/// * `threshold` - The least similarity of the pairs, from the [`SimilarFilesOptions`]
+/// * `num_files` - The number of embedded source files compared
+/// * `pairs` - The [`SimilarFilePair`]s, the most similar first, up to the `max_pairs` of the
+///   [`SimilarFilesOptions`]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SimilarFilesReport {
+    pub threshold: f32,
+    pub num_files: usize,
+    pub pairs: Vec<SimilarFilePair>,
+}
+
+/// The embedding of a source file, the normalised mean of the vectors of its chunks
+struct FileEmbedding<'a> {
+    path: &'a str,
+    language: &'a str,
+    id_hash: &'a str,
+    vector: Vec<f32>,
+}
+
+/// Finds the pairs of source files of the repository, embedded by
+/// [`crate::embedding::EmbeddingPipeline::embed_repository`], whose embeddings have at least
+/// the cosine similarity `threshold` of the [`SimilarFilesOptions`]
+///
+/// Every file is compared with every other, so the time grows with the square of the number of
+/// files embedded; the points of files that aren't in the [`RepositoryInfo`], e.g., embedded
+/// before they were removed, are ignored.
+///
+/// #Returns:
+/// * The [`SimilarFilesReport`], e.g., to set as the `similar_files` of the [`RepositoryInfo`]
+pub async fn find_similar_files(
+    stats: &RepositoryInfo,
+    store: &dyn VectorStore,
+    options: &SimilarFilesOptions,
+) -> Result<SimilarFilesReport, SourceCodeError> {
+    let source_files: HashMap<&str, (&str, &str)> = stats
+        .source_files
+        .iter()
+        .map(|source_file| {
+            (
+                source_file.relative_path.as_str(),
+                (
+                    source_file
+                        .language
+                        .as_ref()
+                        .map_or("", |language| language.name.as_str()),
+                    source_file.id_hash.as_deref().unwrap_or_default(),
+                ),
+            )
+        })
+        .collect();
+    let mut sums: BTreeMap<&str, Vec<f32>> = BTreeMap::new();
+    for point in store.list_points(&stats.name, true).await? {
+        let Some((path, _)) = point
+            .payload
+            .get("path")
+            .and_then(Value::as_str)
+            .and_then(|path| source_files.get_key_value(path))
+        else {
+            continue;
+        };
+        let sum = sums
+            .entry(path)
+            .or_insert_with(|| vec![0.0; point.vector.len()]);
+        if sum.len() == point.vector.len() {
+            sum.iter_mut()
+                .zip(&point.vector)
+                .for_each(|(sum, value)| *sum += value);
+        }
+    }
+    let files: Vec<FileEmbedding> = sums
+        .into_iter()
+        .filter_map(|(path, mut vector)| {
+            let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+            if norm == 0.0 {
+                return None;
+            }
+            vector.iter_mut().for_each(|value| *value /= norm);
+            let (language, id_hash) = source_files.get(path).copied().unwrap_or_default();
+            Some(FileEmbedding {
+                path,
+                language,
+                id_hash,
+                vector,
+            })
+        })
+        .collect();
+
+    let mut pairs: Vec<SimilarFilePair> = vec![];
+    for (index, first) in files.iter().enumerate() {
+        for second in &files[index + 1..] {
+            if first.vector.len() != second.vector.len()
+                || (!first.id_hash.is_empty() && first.id_hash == second.id_hash)
+            {
+                continue;
+            }
+            let similarity: f32 = first
+                .vector
+                .iter()
+                .zip(&second.vector)
+                .map(|(a, b)| a * b)
+                .sum();
+            if similarity >= options.threshold {
+                pairs.push(SimilarFilePair {
+                    first: first.path.to_string(),
+                    second: second.path.to_string(),
+                    first_language: first.language.to_string(),
+                    second_language: second.language.to_string(),
+                    similarity: similarity.min(1.0),
+                });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.first.cmp(&b.first))
+            .then_with(|| a.second.cmp(&b.second))
+    });
+    pairs.truncate(options.max_pairs);
+    Ok(SimilarFilesReport {
+        threshold: options.threshold,
+        num_files: files.len(),
+        pairs,
+    })
+}
@@ -0,0 +1,103 @@
+//! Embedding models used to vectorise a repository's source files
+#[cfg(feature = "embeddings")]
+pub mod local;
+#[cfg(feature = "openai")]
+pub mod openai;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::{embedding::RetryPolicy, errors::SourceCodeError};
+
+/// The configuration for an [`EmbeddingsProvider`], in the style of fastembed's `InitOptions`;
+/// providers ignore the fields that don't apply to them
+///
+/// #Fields:
+/// * `model_name` - The name of the model, e.g., 'AllMiniLML6V2' or 'text-embedding-3-small'
+/// * `batch_size` - The number of texts embedded per batch (or request)
+/// * `max_length` - The maximum number of tokens per text, for local models
+/// * `cache_dir` - The directory local models are downloaded to
+/// * `show_download_message` - Whether to log the download of local models
+/// * `base_url` - The base URL of a remote API, e.g., 'https://api.openai.com/v1'
+/// * `api_key` - The key for a remote API
+/// * `retry` - The [`RetryPolicy`] for downloading local models
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ProviderOptions {
+    pub model_name: String,
+    pub batch_size: usize,
+    pub max_length: usize,
+    pub cache_dir: PathBuf,
+    pub show_download_message: bool,
+    pub base_url: String,
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+impl Default for ProviderOptions {
+    fn default() -> Self {
+        Self {
+            model_name: "AllMiniLML6V2".to_string(),
+            batch_size: 256,
+            max_length: 512,
+            cache_dir: PathBuf::from("local_cache"),
+            show_download_message: true,
+            base_url: "https://api.openai.com/v1".to_string(),
+            api_key: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// The average number of characters of a word piece, for estimating the tokens of a word
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimates the number of tokens a WordPiece or BPE tokenizer splits the `text` into, without
+/// the special tokens the model adds: each punctuation character and each non-ASCII letter is a
+/// token, and words take a token per few characters. Code, with its short identifiers and
+/// symbols, is overestimated rather than under, so texts kept within a model's limit fit it.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut num_tokens = 0;
+    let mut word_len: usize = 0;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word_len += 1;
+            continue;
+        }
+        num_tokens += word_len.div_ceil(CHARS_PER_TOKEN);
+        word_len = 0;
+        if !c.is_whitespace() {
+            num_tokens += 1;
+        }
+    }
+    num_tokens + word_len.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// A model that embeds texts into vectors
+#[async_trait]
+pub trait EmbeddingsProvider: Send + Sync {
+    /// Gets the name of the model
+    fn model_name(&self) -> &str;
+    /// Gets the maximum number of tokens of a text, without the special tokens, beyond which
+    /// the model truncates it, if known
+    fn max_tokens(&self) -> Option<usize> {
+        None
+    }
+    /// Counts the tokens of the `text` for the model, without the special tokens; models
+    /// without a tokenizer at hand use [`estimate_tokens`]
+    fn count_tokens(&self, text: &str) -> usize {
+        estimate_tokens(text)
+    }
+    /// Embeds the `texts` as documents (passages), in order
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, SourceCodeError>;
+    /// Embeds a search query; models that distinguish queries from documents override this
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>, SourceCodeError> {
+        self.embed(vec![query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| {
+                SourceCodeError::EmbeddingError(anyhow::anyhow!("No embedding returned for query"))
+            })
+    }
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+
+use crate::{
+    embeddings_provider::{EmbeddingsProvider, ProviderOptions},
+    errors::SourceCodeError,
+};
+
+/// The prefix of the passages embedded, as the models are trained with
+const PASSAGE_PREFIX: &str = "passage: ";
+/// The prefix of the queries embedded, as the models are trained with
+const QUERY_PREFIX: &str = "query: ";
+
+/// An [`EmbeddingsProvider`] running a fastembed (ONNX) model locally
+pub struct FastEmbedProvider {
+    model: TextEmbedding,
+    model_name: String,
+    batch_size: usize,
+    max_length: usize,
+}
+impl FastEmbedProvider {
+    /// Loads (downloading if needed) the fastembed model named in the [`ProviderOptions`],
+    /// retrying a failed download as its [`crate::embedding::RetryPolicy`] sets
+    pub fn try_new(options: &ProviderOptions) -> Result<Self, SourceCodeError> {
+        let model_name = Self::get_model(&options.model_name)?;
+        let model = options.retry.run_blocking("load the embedding model", || {
+            TextEmbedding::try_new(
+                InitOptions::new(model_name.clone())
+                    .with_max_length(options.max_length)
+                    .with_cache_dir(options.cache_dir.clone())
+                    .with_show_download_progress(options.show_download_message),
+            )
+            .map_err(SourceCodeError::EmbeddingError)
+        })?;
+        Ok(Self {
+            model,
+            model_name: options.model_name.clone(),
+            batch_size: options.batch_size,
+            max_length: options.max_length,
+        })
+    }
+    /// Gets the fastembed model from its variant name or its Hugging Face model id
+    fn get_model(model_name: &str) -> Result<EmbeddingModel, SourceCodeError> {
+        match model_name.to_lowercase().as_str() {
+            "allminilml6v2" | "sentence-transformers/all-minilm-l6-v2" => {
+                Ok(EmbeddingModel::AllMiniLML6V2)
+            }
+            "bgebaseen" | "bgebaseenv15" | "baai/bge-base-en" | "baai/bge-base-en-v1.5" => {
+                Ok(EmbeddingModel::BGEBaseENV15)
+            }
+            "bgesmallen" | "bgesmallenv15" | "baai/bge-small-en" | "baai/bge-small-en-v1.5" => {
+                Ok(EmbeddingModel::BGESmallENV15)
+            }
+            _ => Err(SourceCodeError::ConfigError(format!(
+                "Unsupported fastembed model: {}",
+                model_name
+            ))),
+        }
+    }
+}
+#[async_trait]
+impl EmbeddingsProvider for FastEmbedProvider {
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
+    fn max_tokens(&self) -> Option<usize> {
+        // The '[CLS]' and '[SEP]' tokens are added to each text
+        Some(self.max_length.saturating_sub(2))
+    }
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, SourceCodeError> {
+        let passages: Vec<String> = texts
+            .into_iter()
+            .map(|text| format!("{}{}", PASSAGE_PREFIX, text))
+            .collect();
+        self.model
+            .embed(passages, Some(self.batch_size))
+            .map_err(SourceCodeError::EmbeddingError)
+    }
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>, SourceCodeError> {
+        self.model
+            .embed(vec![format!("{}{}", QUERY_PREFIX, query)], None)
+            .map_err(SourceCodeError::EmbeddingError)?
+            .pop()
+            .ok_or_else(|| {
+                SourceCodeError::EmbeddingError(anyhow::anyhow!("no embedding of the query"))
+            })
+    }
+}
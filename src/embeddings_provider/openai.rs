@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    embeddings_provider::{EmbeddingsProvider, ProviderOptions},
+    errors::SourceCodeError,
+};
+
+/// The maximum number of tokens of an input to the OpenAI embedding models
+const MAX_INPUT_TOKENS: usize = 8191;
+
+/// An [`EmbeddingsProvider`] calling a remote OpenAI-compatible `/embeddings` API
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    model_name: String,
+    batch_size: usize,
+    endpoint: String,
+    api_key: Option<String>,
+}
+impl OpenAiProvider {
+    /// Creates the provider for the `model_name` at the `base_url` of the [`ProviderOptions`];
+    /// the `api_key` defaults to the `OPENAI_API_KEY` environment variable
+    pub fn new(options: &ProviderOptions) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            model_name: options.model_name.clone(),
+            batch_size: options.batch_size.max(1),
+            endpoint: format!("{}/embeddings", options.base_url.trim_end_matches('/')),
+            api_key: options
+                .api_key
+                .clone()
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl EmbeddingsProvider for OpenAiProvider {
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
+    fn max_tokens(&self) -> Option<usize> {
+        Some(MAX_INPUT_TOKENS)
+    }
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, SourceCodeError> {
+        let mut embeddings: Vec<Vec<f32>> = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            let mut request = self.client.post(&self.endpoint).json(&EmbeddingsRequest {
+                model: &self.model_name,
+                input: batch,
+            });
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            let mut response: EmbeddingsResponse = request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|err| SourceCodeError::EmbeddingError(err.into()))?
+                .json()
+                .await
+                .map_err(|err| SourceCodeError::EmbeddingError(err.into()))?;
+            // The API may return the embeddings of a batch out of order
+            response.data.sort_by_key(|data| data.index);
+            embeddings.extend(response.data.into_iter().map(|data| data.embedding));
+        }
+        Ok(embeddings)
+    }
+}
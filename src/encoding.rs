@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::to_count,
+    source::{ContentEncoding, SourceFileInfo},
+};
+
+/// A source file that isn't UTF-8, so was decoded when it was read
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `encoding` - The [`ContentEncoding`] of the file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EncodedFile {
+    pub path: String,
+    pub encoding: ContentEncoding,
+}
+
+/// The source files of a repository that aren't UTF-8, e.g., UTF-16 or Latin-1, which may not
+/// display or diff well in some tools
+///
+/// #Fields:
+/// * `num_non_utf8` - The number of source files that aren't UTF-8, with or without a byte
+///   order mark
+/// * `files` - The [`EncodedFile`]s that aren't UTF-8, by path
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EncodingSummary {
+    pub num_non_utf8: u64,
+    pub files: Vec<EncodedFile>,
+}
+impl EncodingSummary {
+    /// Gets the [`EncodingSummary`] of the `source_files`, from the encodings detected when they
+    /// were read
+    pub fn new(source_files: &[SourceFileInfo]) -> Self {
+        let mut files: Vec<EncodedFile> = source_files
+            .iter()
+            .filter_map(|source_file| {
+                let encoding = source_file.encoding?;
+                (!encoding.is_utf8()).then(|| EncodedFile {
+                    path: source_file.relative_path.to_string(),
+                    encoding,
+                })
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Self {
+            num_non_utf8: to_count(files.len()),
+            files,
+        }
+    }
+}
@@ -1,55 +1,126 @@
-use std::error::Error;
-use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 
-#[derive(Debug)]
+use crate::{cancel::PartialResults, observer::AnalysisStage};
+
+/// The errors from analysing a repository, with a variant per cause so that callers can match
+/// on them. Errors from other crates are kept as the `source`.
+#[derive(Debug, Error)]
 pub enum SourceCodeError {
-    SerializationError(anyhow::Error),
-    QdrantError(anyhow::Error),
-    GitError(git2::Error),
-    ConversionError(std::num::TryFromIntError),
-    FileReadError(std::io::Error),
-    FilePathError(std::path::StripPrefixError),
+    /// `tokei` couldn't count the lines of code at `path`
+    #[error("Failed to count the lines of code in {}: {message}", path.display())]
+    TokeiError { path: PathBuf, message: String },
+    #[error("Failed to serialize: {0}")]
+    SerializationError(#[source] anyhow::Error),
+    #[error("Qdrant request failed: {0}")]
+    QdrantError(#[source] anyhow::Error),
+    /// A git operation failed, for the `reference` (a ref or revision) if there is one
+    #[cfg(feature = "git")]
+    #[error("Git operation failed{}: {source}", format_reference(reference))]
+    GitError {
+        #[source]
+        source: git2::Error,
+        reference: Option<String>,
+    },
+    #[error("Failed to convert an integer: {0}")]
+    ConversionError(#[from] std::num::TryFromIntError),
+    /// Reading (or writing) a file failed, for the `path` if known
+    #[error("Failed to read{}: {source}", format_path(path))]
+    FileReadError {
+        #[source]
+        source: std::io::Error,
+        path: Option<PathBuf>,
+    },
+    #[error("Path is not in the repository: {0}")]
+    FilePathError(#[from] std::path::StripPrefixError),
+    /// A search pattern isn't a valid regular expression
+    #[error("Invalid search pattern: {0}")]
+    PatternError(#[from] regex::Error),
+    #[error("Failed to export: {0}")]
+    ExportError(#[source] anyhow::Error),
+    #[error("Failed to store statistics: {0}")]
+    StoreError(#[source] anyhow::Error),
+    /// Serving the statistics over HTTP failed
+    #[error("Failed to serve: {0}")]
+    ServeError(#[source] anyhow::Error),
+    /// A request to the API of a forge, e.g., GitHub, failed
+    #[error("Failed to query the forge: {0}")]
+    ForgeError(#[source] anyhow::Error),
+    /// Watching the repository for changes failed
+    #[error("Failed to watch: {0}")]
+    WatchError(#[source] anyhow::Error),
+    #[error("Failed to embed: {0}")]
+    EmbeddingError(#[source] anyhow::Error),
+    /// The contents of the source file at `path` weren't kept and can't be read again, for the
+    /// `reason`
+    #[error("The contents of {path} are unavailable: {reason}")]
+    ContentsUnavailable { path: String, reason: String },
+    /// The options or configuration given are invalid, e.g., an unknown model name
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+    /// The repository at `path` can't be analysed, e.g., it is not a git repository or is bare
+    #[error("Unsupported repository at {}: {reason}", path.display())]
+    UnsupportedRepo { path: PathBuf, reason: String },
+    /// The analysis was cancelled through a [`crate::cancel::CancellationToken`] during the
+    /// `stage`, with the [`PartialResults`] gathered so far
+    #[error("Cancelled during the {stage:?} stage")]
+    Cancelled {
+        stage: AnalysisStage,
+        partial: Box<PartialResults>,
+    },
 }
-
-impl fmt::Display for SourceCodeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Source code error: {:?}", self)
+impl SourceCodeError {
+    /// Maps a [`git2::Error`] for the `reference`, e.g., `.map_err(SourceCodeError::for_reference(name))`
+    #[cfg(feature = "git")]
+    pub fn for_reference(reference: &str) -> impl FnOnce(git2::Error) -> SourceCodeError + '_ {
+        move |source| SourceCodeError::GitError {
+            source,
+            reference: Some(reference.to_string()),
+        }
     }
-}
-impl Error for SourceCodeError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            SourceCodeError::SerializationError(err) => Some(&**err),
-            SourceCodeError::QdrantError(err) => Some(&**err),
-            SourceCodeError::GitError(err) => Some(err),
-            SourceCodeError::ConversionError(err) => Some(err),
-            SourceCodeError::FileReadError(err) => Some(err),
-            SourceCodeError::FilePathError(err) => Some(err),
+    /// Maps a [`std::io::Error`] for the `path`, e.g., `.map_err(SourceCodeError::for_path(path))`
+    pub fn for_path(path: &Path) -> impl FnOnce(std::io::Error) -> SourceCodeError + '_ {
+        move |source| SourceCodeError::FileReadError {
+            source,
+            path: Some(path.to_path_buf()),
         }
     }
-}
-impl From<anyhow::Error> for SourceCodeError {
-    fn from(err: anyhow::Error) -> Self {
-        SourceCodeError::QdrantError(err)
+    /// Checks whether the error is of a single file, e.g., it can't be read, or its lines
+    /// counted, rather than of the repository or the analysis, so that an analysis can carry on
+    /// without the file
+    pub fn is_file_error(&self) -> bool {
+        matches!(
+            self,
+            SourceCodeError::FileReadError { .. }
+                | SourceCodeError::TokeiError { .. }
+                | SourceCodeError::ContentsUnavailable { .. }
+        )
     }
 }
+#[cfg(feature = "git")]
 impl From<git2::Error> for SourceCodeError {
-    fn from(error: git2::Error) -> Self {
-        SourceCodeError::GitError(error)
-    }
-}
-impl From<std::num::TryFromIntError> for SourceCodeError {
-    fn from(error: std::num::TryFromIntError) -> Self {
-        SourceCodeError::ConversionError(error)
+    fn from(source: git2::Error) -> Self {
+        SourceCodeError::GitError {
+            source,
+            reference: None,
+        }
     }
 }
 impl From<std::io::Error> for SourceCodeError {
-    fn from(error: std::io::Error) -> Self {
-        SourceCodeError::FileReadError(error)
+    fn from(source: std::io::Error) -> Self {
+        SourceCodeError::FileReadError { source, path: None }
     }
 }
-impl From<std::path::StripPrefixError> for SourceCodeError {
-    fn from(error: std::path::StripPrefixError) -> Self {
-        SourceCodeError::FilePathError(error)
-    }
+
+#[cfg(feature = "git")]
+fn format_reference(reference: &Option<String>) -> String {
+    reference
+        .as_ref()
+        .map(|reference| format!(" for '{}'", reference))
+        .unwrap_or_default()
+}
+fn format_path(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map(|path| format!(" {}", path.display()))
+        .unwrap_or_default()
 }
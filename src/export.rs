@@ -0,0 +1,6 @@
+//! Exporters that write repository statistics to external formats, and the anonymisation
+//! of statistics for export
+pub mod annotations;
+pub mod anonymise;
+#[cfg(feature = "parquet")]
+pub mod parquet;
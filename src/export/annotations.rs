@@ -0,0 +1,273 @@
+//! The annotation of the files of a change set, e.g., a pull request, that exceed thresholds of
+//! churn, complexity or health, as GitHub Actions workflow commands or a GitLab Code Quality
+//! report, so that CI can warn on, or fail, changes to hotspots
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+use crate::{errors::SourceCodeError, repository::RepositoryInfo, scoring::HealthReport};
+
+/// The thresholds above which the files are annotated, as warnings or errors; a threshold that
+/// isn't set isn't checked
+///
+/// #Fields:
+/// * `warning_churn` - The number of commits changing a file at (or above) which it is a warning
+/// * `error_churn` - The number of commits changing a file at (or above) which it is an error
+/// * `warning_loc` - The lines of code, as the complexity measure, at (or above) which a file is
+///   a warning
+/// * `error_loc` - The lines of code at (or above) which a file is an error
+/// * `warning_score` - The health score at (or below) which a file is a warning
+/// * `error_score` - The health score at (or below) which a file is an error
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct AnnotationThresholds {
+    pub warning_churn: Option<u64>,
+    pub error_churn: Option<u64>,
+    pub warning_loc: Option<u64>,
+    pub error_loc: Option<u64>,
+    pub warning_score: Option<f32>,
+    pub error_score: Option<f32>,
+}
+impl Default for AnnotationThresholds {
+    /// Warns of files changed by 20 commits or more, of 1000 lines of code or more, or with a
+    /// health score of 50 or less, without errors
+    fn default() -> Self {
+        Self {
+            warning_churn: Some(20),
+            error_churn: None,
+            warning_loc: Some(1000),
+            error_loc: None,
+            warning_score: Some(50.0),
+            error_score: None,
+        }
+    }
+}
+
+/// The level of an [`Annotation`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLevel {
+    /// A threshold to warn at was exceeded
+    Warning,
+    /// A threshold to fail at was exceeded
+    Error,
+}
+
+/// The check an [`Annotation`] is from
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationCheck {
+    /// The number of commits changing the file
+    Churn,
+    /// The lines of code of the file
+    Complexity,
+    /// The health score of the file, see [`crate::scoring`]
+    Health,
+}
+impl AnnotationCheck {
+    /// Gets the name of the check, as the title of a GitHub annotation or the `check_name` of a
+    /// GitLab code quality issue
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnnotationCheck::Churn => "repository_statistics/churn",
+            AnnotationCheck::Complexity => "repository_statistics/complexity",
+            AnnotationCheck::Health => "repository_statistics/health",
+        }
+    }
+}
+
+/// A file that exceeds a threshold of a check
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `level` - The [`AnnotationLevel`]
+/// * `check` - The [`AnnotationCheck`] whose threshold the file exceeds
+/// * `message` - The message, with the value of the file and the threshold
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Annotation {
+    pub path: String,
+    pub level: AnnotationLevel,
+    pub check: AnnotationCheck,
+    pub message: String,
+}
+
+/// The [`Annotation`]s of the files of a repository, or of those a change set touches
+///
+/// #Fields:
+/// * `annotations` - The [`Annotation`]s, by path then check
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CiAnnotations {
+    pub annotations: Vec<Annotation>,
+}
+impl CiAnnotations {
+    /// Annotates the source files of the `repository_info` that exceed the `thresholds`, with
+    /// their health from the `health` report, e.g., from [`RepositoryInfo::health`]
+    ///
+    /// #Arguments:
+    /// * `repository_info` - The [`RepositoryInfo`] of the repository
+    /// * `health` - The [`HealthReport`] of the repository
+    /// * `touched` - The relative paths of the files to annotate, e.g., those a pull request
+    ///   touches from [`crate::review::ReviewTarget::get_changed_files`], or `None` for all
+    /// * `thresholds` - The [`AnnotationThresholds`]
+    pub fn new(
+        repository_info: &RepositoryInfo,
+        health: &HealthReport,
+        touched: Option<&[String]>,
+        thresholds: &AnnotationThresholds,
+    ) -> CiAnnotations {
+        let touched: Option<HashSet<&str>> =
+            touched.map(|touched| touched.iter().map(String::as_str).collect());
+        let is_touched = |path: &str| {
+            touched
+                .as_ref()
+                .is_none_or(|touched| touched.contains(path))
+        };
+        let mut annotations: Vec<Annotation> = Vec::new();
+        for source_file in &repository_info.source_files {
+            let path = source_file.relative_path.as_str();
+            if !is_touched(path) {
+                continue;
+            }
+            let num_commits = source_file.statistics.num_commits;
+            if let Some((level, threshold)) = Self::exceeded(
+                num_commits,
+                thresholds.warning_churn,
+                thresholds.error_churn,
+            ) {
+                annotations.push(Annotation {
+                    path: path.to_string(),
+                    level,
+                    check: AnnotationCheck::Churn,
+                    message: format!(
+                        "{} is a hotspot: changed by {} commits (threshold {})",
+                        path, num_commits, threshold
+                    ),
+                });
+            }
+            let loc = source_file.statistics.loc;
+            if let Some((level, threshold)) =
+                Self::exceeded(loc, thresholds.warning_loc, thresholds.error_loc)
+            {
+                annotations.push(Annotation {
+                    path: path.to_string(),
+                    level,
+                    check: AnnotationCheck::Complexity,
+                    message: format!(
+                        "{} is complex: {} lines of code (threshold {})",
+                        path, loc, threshold
+                    ),
+                });
+            }
+        }
+        for file in health.files.iter().filter(|file| is_touched(&file.path)) {
+            // A lower score is worse, so the score is negated to be exceeded
+            if let Some((level, threshold)) = Self::exceeded(
+                -file.score,
+                thresholds.warning_score.map(|score| -score),
+                thresholds.error_score.map(|score| -score),
+            ) {
+                annotations.push(Annotation {
+                    path: file.path.clone(),
+                    level,
+                    check: AnnotationCheck::Health,
+                    message: format!(
+                        "{} is unhealthy: a health score of {:.0} out of 100 (threshold {:.0})",
+                        file.path, file.score, -threshold
+                    ),
+                });
+            }
+        }
+        annotations.sort_by(|a, b| a.path.cmp(&b.path).then(a.check.cmp(&b.check)));
+        CiAnnotations { annotations }
+    }
+    /// Checks whether any of the annotations is an error, e.g., to fail the CI job
+    pub fn has_errors(&self) -> bool {
+        self.annotations
+            .iter()
+            .any(|annotation| annotation.level == AnnotationLevel::Error)
+    }
+    /// Gets the annotations as GitHub Actions workflow commands, one per line, e.g.,
+    /// `::warning file=src/main.rs,title=repository_statistics/churn::...`, to print in a step
+    pub fn to_github_actions(&self) -> String {
+        let mut commands = String::new();
+        for annotation in &self.annotations {
+            let command = match annotation.level {
+                AnnotationLevel::Warning => "warning",
+                AnnotationLevel::Error => "error",
+            };
+            commands.push_str(&format!(
+                "::{} file={},title={}::{}\n",
+                command,
+                escape_property(&annotation.path),
+                escape_property(annotation.check.name()),
+                escape_data(&annotation.message)
+            ));
+        }
+        commands
+    }
+    /// Gets the annotations as a GitLab Code Quality report, i.e., a JSON array of issues with
+    /// a fingerprint of the check and path, to upload as a `codequality` report artifact
+    pub fn to_gitlab_code_quality(&self) -> Result<String, SourceCodeError> {
+        let issues: Vec<serde_json::Value> = self
+            .annotations
+            .iter()
+            .map(|annotation| {
+                let fingerprint = Sha256::digest(
+                    format!("{}:{}", annotation.check.name(), annotation.path).as_bytes(),
+                );
+                json!({
+                    "description": annotation.message,
+                    "check_name": annotation.check.name(),
+                    "fingerprint": format!("{:x}", fingerprint),
+                    "severity": match annotation.level {
+                        AnnotationLevel::Warning => "minor",
+                        AnnotationLevel::Error => "major",
+                    },
+                    "location": {
+                        "path": annotation.path,
+                        "lines": { "begin": 1 },
+                    },
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&issues)
+            .map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Gets the [`CiAnnotations`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Gets the level of the `value` against the `warning` and `error` thresholds, and the
+    /// threshold it reached, or `None` if it reached neither
+    fn exceeded<T: PartialOrd + Copy>(
+        value: T,
+        warning: Option<T>,
+        error: Option<T>,
+    ) -> Option<(AnnotationLevel, T)> {
+        match (error, warning) {
+            (Some(error), _) if value >= error => Some((AnnotationLevel::Error, error)),
+            (_, Some(warning)) if value >= warning => Some((AnnotationLevel::Warning, warning)),
+            _ => None,
+        }
+    }
+}
+
+/// Escapes the `data` of a GitHub Actions workflow command, i.e., its message
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property of a GitHub Actions workflow command, e.g., its file
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
@@ -0,0 +1,494 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::{
+    forge::ForgeUser,
+    head::HeadState,
+    identity::Author,
+    repository::{Contributor, RepositoryInfo},
+};
+
+/// The number of hex characters kept from each salted path hash (64 bits)
+const HASH_LENGTH: usize = 16;
+/// The domain of the pseudonymous email addresses of contributors, which is reserved so that it
+/// can't be delivered to
+const PSEUDONYM_EMAIL_DOMAIN: &str = "anonymised.invalid";
+
+/// How much of the paths, and the other names from a repository, e.g., of its branches and
+/// functions, are redacted by a [`PathHasher`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PathRedaction {
+    /// Paths and names are replaced with their salted hashes
+    #[default]
+    Full,
+    /// Only the leaf, i.e., file, names of paths are replaced with salted hashes, keeping their
+    /// extensions, so that the structure of the repository's directories can still be seen;
+    /// other names are hashed as with [`PathRedaction::Full`]
+    LeafNames,
+    /// Paths and names are kept, e.g., to only pseudonymise the contributors
+    None,
+}
+
+/// The options for anonymising the output of an analysis, so that it can be shared outside an
+/// organisation, see [`PathHasher`]
+///
+/// #Fields:
+/// * `salt` - The secret the hashes are salted with, which should be reused across exports so
+///   that the same path or contributor always hashes the same; it isn't serialised, so that it
+///   isn't exported with the options
+/// * `pseudonymise_contributors` - Whether the names and email addresses of contributors are
+///   replaced with stable pseudonyms
+/// * `paths` - The [`PathRedaction`] of the paths and other names
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct AnonymisationOptions {
+    #[serde(skip_serializing)]
+    pub salt: String,
+    pub pseudonymise_contributors: bool,
+    pub paths: PathRedaction,
+}
+
+/// Replaces the file and directory paths of a [`RepositoryInfo`] with salted hashes, and
+/// optionally the names and email addresses of its contributors with pseudonyms, so that its
+/// statistics can be shared outside an organisation without revealing the repository's
+/// structure or personal data.
+///
+/// Numeric metrics, languages and frameworks are kept. Source file contents are dropped and
+/// content hashes are salted too, so files can't be matched against known (e.g., open source)
+/// files. A contributor's pseudonym is the salted hash of their name, or canonical id, so it is
+/// the same in each report they appear in, e.g., the contributors and the ownership of files.
+/// Organisations and the repository name are left to the caller.
+pub struct PathHasher {
+    salt: String,
+    paths: PathRedaction,
+    pseudonymise_contributors: bool,
+}
+impl PathHasher {
+    /// Creates a [`PathHasher`] with the `salt`, which should be kept secret and reused across
+    /// exports so the same path always hashes the same, e.g., to track a file over time; it
+    /// hashes paths fully, and leaves the contributors as they are
+    pub fn new(salt: &str) -> Self {
+        Self {
+            salt: salt.to_string(),
+            paths: PathRedaction::Full,
+            pseudonymise_contributors: false,
+        }
+    }
+    /// Creates a [`PathHasher`] that anonymises as the [`AnonymisationOptions`] set
+    pub fn from_options(options: &AnonymisationOptions) -> Self {
+        Self {
+            salt: options.salt.clone(),
+            paths: options.paths,
+            pseudonymise_contributors: options.pseudonymise_contributors,
+        }
+    }
+    /// Checks whether the names and email addresses of contributors are pseudonymised
+    pub fn pseudonymises_contributors(&self) -> bool {
+        self.pseudonymise_contributors
+    }
+    /// Gets the salted hash of a `path`
+    pub fn hash(&self, path: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update(path.as_bytes());
+        let hash = format!("{:x}", hasher.finalize());
+        hash[..HASH_LENGTH].to_string()
+    }
+    /// Gets the relative `path` of a file redacted as the [`PathRedaction`] sets; with
+    /// [`PathRedaction::LeafNames`], the file name is the salted hash of the whole path, so
+    /// files with the same name in different directories can't be linked
+    pub fn redact_path(&self, path: &str) -> String {
+        match self.paths {
+            PathRedaction::Full => self.hash(path),
+            PathRedaction::LeafNames => {
+                let path = Path::new(path);
+                let mut leaf = self.hash(&path.to_string_lossy());
+                if let Some(extension) = path.extension() {
+                    leaf = format!("{}.{}", leaf, extension.to_string_lossy());
+                }
+                match path
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                {
+                    Some(parent) => format!("{}/{}", parent.to_string_lossy(), leaf),
+                    None => leaf,
+                }
+            }
+            PathRedaction::None => path.to_string(),
+        }
+    }
+    /// Gets the relative `path` of a directory redacted as the [`PathRedaction`] sets: hashed
+    /// with [`PathRedaction::Full`], or else kept
+    pub fn redact_directory(&self, path: &str) -> String {
+        match self.paths {
+            PathRedaction::Full => self.hash(path),
+            PathRedaction::LeafNames | PathRedaction::None => path.to_string(),
+        }
+    }
+    /// Gets a `name` other than a path, e.g., of a branch or function, redacted as the
+    /// [`PathRedaction`] sets: kept with [`PathRedaction::None`], or else hashed
+    pub fn redact_name(&self, name: &str) -> String {
+        match self.paths {
+            PathRedaction::Full | PathRedaction::LeafNames => self.hash(name),
+            PathRedaction::None => name.to_string(),
+        }
+    }
+    /// Gets the pseudonym of a contributor's `name`, or canonical id, e.g.,
+    /// 'contributor-1f2e3d4c5b6a7980'
+    pub fn pseudonym(&self, name: &str) -> String {
+        format!("contributor-{}", self.hash(name))
+    }
+    /// Replaces the name, email address and canonical id of the [`Author`] with pseudonyms;
+    /// the email address is replaced with one at a reserved domain, so that it is still one
+    pub fn pseudonymise(&self, author: &mut Author) {
+        author.name = self.pseudonym(&author.name);
+        author.email = author
+            .email
+            .as_deref()
+            .map(|email| self.pseudonymous_email(email));
+        author.canonical_id = self.pseudonym(&author.canonical_id);
+    }
+    /// Gets the pseudonymous email address of an `email` address, which isn't case-sensitive
+    fn pseudonymous_email(&self, email: &str) -> String {
+        format!(
+            "{}@{}",
+            self.hash(&email.to_lowercase()),
+            PSEUDONYM_EMAIL_DOMAIN
+        )
+    }
+    /// Creates a copy of the [`RepositoryInfo`] with its paths redacted, and its contributors
+    /// pseudonymised if set
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path the repository was analysed at, which is stripped from source
+    ///   file paths so the hashes don't depend on where the repository was cloned
+    /// * `repository_info` - The [`RepositoryInfo`] to anonymise
+    pub fn anonymise(&self, repo_path: &str, repository_info: &RepositoryInfo) -> RepositoryInfo {
+        let mut anonymised = repository_info.clone();
+        for source_file in &mut anonymised.source_files {
+            let full_path = Path::new(&source_file.relative_path);
+            let path = full_path.strip_prefix(repo_path).unwrap_or(full_path);
+            let redacted = self.redact_path(&path.to_string_lossy());
+            source_file.name = redacted.rsplit('/').next().unwrap_or(&redacted).to_string();
+            source_file.relative_path = redacted.into();
+            source_file.id_hash = source_file.id_hash.as_deref().map(|id| self.hash(id));
+            source_file.blob_oid = source_file.blob_oid.as_deref().map(|id| self.hash(id));
+            source_file.source_file = None;
+            source_file.symlink_target = source_file
+                .symlink_target
+                .as_deref()
+                .map(|target| self.redact_path(target));
+            for symbol in &mut source_file.symbols {
+                symbol.name = self.redact_name(&symbol.name);
+                symbol.parent = symbol
+                    .parent
+                    .as_deref()
+                    .map(|parent| self.redact_name(parent));
+            }
+        }
+        anonymised.scope = anonymised
+            .scope
+            .as_deref()
+            .map(|scope| self.redact_directory(scope));
+        self.redact_all(&mut anonymised.unknown_files);
+        self.redact_all(&mut anonymised.generated_code.generated_files);
+        for asset in &mut anonymised.assets.largest {
+            asset.path = self.redact_path(&asset.path);
+        }
+        for file in &mut anonymised.encodings.files {
+            file.path = self.redact_path(&file.path);
+        }
+        self.redact_all(&mut anonymised.whitespace.mixed_files);
+        self.redact_all(&mut anonymised.whitespace.missing_final_newline_files);
+        for warning in &mut anonymised.warnings {
+            warning.path = self.redact_path(&warning.path);
+        }
+
+        let layout = &mut anonymised.layout;
+        for directory in &mut layout.directories {
+            directory.path = self.redact_directory(&directory.path);
+        }
+        self.redact_all(&mut layout.deep_paths);
+        for directory in &mut layout.large_directories {
+            *directory = self.redact_directory(directory);
+        }
+
+        for target in &mut anonymised.targets {
+            target.name = self.redact_name(&target.name);
+            target.entry_point = self.redact_path(&target.entry_point);
+            target.declared_in = self.redact_path(&target.declared_in);
+        }
+        for framework in &mut anonymised.frameworks {
+            framework.detected_in = self.redact_path(&framework.detected_in);
+        }
+        for license in &mut anonymised.dependencies.licenses {
+            license.detected_in = self.redact_path(&license.detected_in);
+        }
+        for manifest in &mut anonymised.dependencies.manifests {
+            manifest.path = self.redact_path(&manifest.path);
+        }
+        for package in &mut anonymised.packages {
+            package.name = self.redact_name(&package.name);
+            package.path = self.redact_directory(&package.path);
+            self.pseudonymise_contributors(&mut package.contributors);
+        }
+        for submodule in &mut anonymised.submodules {
+            let submodule_path = Path::new(repo_path).join(&submodule.path);
+            submodule.repository = submodule.repository.as_deref().map(|repository| {
+                Box::new(self.anonymise(&submodule_path.to_string_lossy(), repository))
+            });
+            submodule.name = self.redact_name(&submodule.name);
+            submodule.path = self.redact_directory(&submodule.path);
+            submodule.url = submodule.url.as_deref().map(|url| self.redact_name(url));
+            if let Some(repository) = &mut submodule.repository {
+                repository.name = submodule.name.clone();
+            }
+        }
+        for root in &mut anonymised.roots {
+            root.path = self.redact_directory(&root.path);
+            root.repository = self.redact_directory(&root.repository);
+            root.scope = root
+                .scope
+                .as_deref()
+                .map(|scope| self.redact_directory(scope));
+            root.prefix = root
+                .prefix
+                .as_deref()
+                .map(|prefix| self.redact_directory(prefix));
+        }
+        self.pseudonymise_contributors(&mut anonymised.contributors);
+        // The text of a marker is source file contents, so it is dropped like them
+        for marker in &mut anonymised.tech_debt_markers {
+            marker.path = self.redact_path(&marker.path);
+            marker.text = String::new();
+            if let Some(author) = &mut marker.author {
+                self.pseudonymise_author(author);
+            }
+        }
+        if let Some(coupling) = &mut anonymised.coupling {
+            for pair in &mut coupling.pairs {
+                pair.file_a = self.redact_path(&pair.file_a);
+                pair.file_b = self.redact_path(&pair.file_b);
+            }
+        }
+        if let Some(function_churn) = &mut anonymised.function_churn {
+            for function in &mut function_churn.functions {
+                function.path = self.redact_path(&function.path);
+                function.name = self.redact_name(&function.name);
+            }
+        }
+        if let Some(branches) = &mut anonymised.branches {
+            branches.default_branch = branches
+                .default_branch
+                .as_deref()
+                .map(|b| self.redact_name(b));
+            for branch in &mut branches.branches {
+                branch.name = self.redact_name(&branch.name);
+                if self.pseudonymise_contributors {
+                    branch.author = branch
+                        .author
+                        .as_deref()
+                        .map(|author| self.pseudonym(author));
+                }
+            }
+        }
+        if let Some(co_ownership) = &mut anonymised.co_ownership {
+            for directory in &mut co_ownership.directories {
+                *directory = self.redact_directory(directory);
+            }
+            for row in &mut co_ownership.contributors {
+                self.pseudonymise_author(&mut row.author);
+            }
+        }
+        if let Some(storage) = &mut anonymised.storage {
+            for blob in &mut storage.largest_blobs {
+                blob.path = self.redact_path(&blob.path);
+            }
+        }
+        if let Some(duplication) = &mut anonymised.duplication {
+            for block in &mut duplication.blocks {
+                block.first.path = self.redact_path(&block.first.path);
+                block.second.path = self.redact_path(&block.second.path);
+            }
+            for file in &mut duplication.files {
+                file.path = self.redact_path(&file.path);
+            }
+        }
+        for group in &mut anonymised.identical_files.groups {
+            group.id_hash = self.hash(&group.id_hash);
+            self.redact_all(&mut group.paths);
+        }
+        if let Some(similar_files) = &mut anonymised.similar_files {
+            for pair in &mut similar_files.pairs {
+                pair.first = self.redact_path(&pair.first);
+                pair.second = self.redact_path(&pair.second);
+            }
+        }
+        if let Some(ownership) = &mut anonymised.ownership {
+            for file in &mut ownership.files {
+                file.path = self.redact_path(&file.path);
+                if let Some(top_contributor) = &mut file.top_contributor {
+                    self.pseudonymise_author(top_contributor);
+                }
+                if self.pseudonymise_contributors {
+                    for owner in &mut file.owners {
+                        *owner = self.pseudonymise_owner(owner);
+                    }
+                }
+            }
+        }
+        if let Some(forge) = &mut anonymised.forge {
+            forge.path = self.redact_name(&forge.path);
+            forge.url = forge.url.as_deref().map(|url| self.redact_name(url));
+        }
+        if let Some(working_tree) = &mut anonymised.working_tree {
+            self.redact_all(&mut working_tree.modified_files);
+            self.redact_all(&mut working_tree.untracked_files);
+        }
+        if let Some(metadata) = &mut anonymised.metadata {
+            metadata.host = None;
+            if let Some(HeadState::Branch { name, .. } | HeadState::Unborn { name }) =
+                &mut metadata.head
+            {
+                *name = self.redact_name(name);
+            }
+            let options = &mut metadata.options;
+            for excluded in &mut options.excluded {
+                *excluded = self.redact_directory(excluded);
+            }
+            self.redact_all(&mut options.included);
+            options.scope = options
+                .scope
+                .as_deref()
+                .map(|scope| self.redact_directory(scope));
+        }
+        anonymised
+    }
+    /// Redacts the relative paths of files
+    fn redact_all(&self, paths: &mut [String]) {
+        for path in paths {
+            *path = self.redact_path(path);
+        }
+    }
+    /// Pseudonymises the `contributors`, if set
+    fn pseudonymise_contributors(&self, contributors: &mut [Contributor]) {
+        for contributor in contributors {
+            self.pseudonymise_author(&mut contributor.author);
+            if let Some(forge_user) = &mut contributor.forge_user {
+                self.pseudonymise_forge_user(forge_user);
+            }
+        }
+    }
+    /// Pseudonymises the [`Author`], if set
+    fn pseudonymise_author(&self, author: &mut Author) {
+        if self.pseudonymise_contributors {
+            self.pseudonymise(author);
+        }
+    }
+    /// Pseudonymises the username of the [`ForgeUser`], if set, dropping the URLs of their
+    /// avatar and profile, which reveal it
+    fn pseudonymise_forge_user(&self, forge_user: &mut ForgeUser) {
+        if self.pseudonymise_contributors {
+            forge_user.username = self.pseudonym(&forge_user.username);
+            forge_user.avatar_url = None;
+            forge_user.profile_url = None;
+        }
+    }
+    /// Pseudonymises a CODEOWNERS owner: a username, e.g., '@jane-doe', or an email address;
+    /// teams, e.g., '@org/team', aren't personal data, so are kept
+    fn pseudonymise_owner(&self, owner: &str) -> String {
+        match owner.strip_prefix('@') {
+            Some(team) if team.contains('/') => owner.to_string(),
+            Some(username) => format!("@{}", self.pseudonym(username)),
+            None => self.pseudonymous_email(owner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "git")]
+    use crate::{errors::SourceCodeError, options::AnalysisOptions, testing::FixtureRepo};
+
+    #[test]
+    fn redacts_paths_as_set() {
+        let hasher = PathHasher::new("salt");
+        let hash = hasher.hash("src/lib.rs");
+        assert_eq!(hash.len(), HASH_LENGTH);
+        assert_eq!(hasher.redact_path("src/lib.rs"), hash);
+        assert_ne!(PathHasher::new("pepper").hash("src/lib.rs"), hash);
+        let hasher = PathHasher::from_options(&AnonymisationOptions {
+            salt: "salt".to_string(),
+            pseudonymise_contributors: true,
+            paths: PathRedaction::LeafNames,
+        });
+        assert_eq!(hasher.redact_path("src/lib.rs"), format!("src/{}.rs", hash));
+        assert_eq!(hasher.redact_directory("src"), "src");
+        assert_eq!(hasher.redact_name("main"), hasher.hash("main"));
+        let mut author = Author::new("Alice", Some("Alice@Example.com"));
+        hasher.pseudonymise(&mut author);
+        assert_eq!(author.name, hasher.pseudonym("Alice"));
+        assert_eq!(
+            author.email,
+            Some(format!(
+                "{}@{}",
+                hasher.hash("alice@example.com"),
+                PSEUDONYM_EMAIL_DOMAIN
+            ))
+        );
+    }
+
+    #[cfg(all(feature = "git", unix))]
+    #[test]
+    fn leaves_no_original_path_or_name_in_a_snapshot() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_author("Alice Secret", "alice.secret@example.com")
+            .with_file(
+                "src/secret_client.rs",
+                "// TODO: rotate the secret_token\npub fn secret_client() {}\n",
+            );
+        let link = fixture.path().join("linked_client.rs");
+        std::os::unix::fs::symlink("src/secret_client.rs", &link)
+            .map_err(SourceCodeError::for_path(&link))?;
+        {
+            let mut index = fixture.repository().index()?;
+            index.add_path(Path::new("linked_client.rs"))?;
+            index.write()?;
+        }
+        let fixture = fixture.commit("Add the secret client")?;
+        let repository_info = fixture.analyse(&AnalysisOptions::default())?;
+        assert!(repository_info
+            .source_files
+            .iter()
+            .any(|source_file| source_file.symlink_target.is_some()));
+
+        let hasher = PathHasher::from_options(&AnonymisationOptions {
+            salt: "salt".to_string(),
+            pseudonymise_contributors: true,
+            paths: PathRedaction::Full,
+        });
+        let repo_path = fixture.path().to_string_lossy();
+        let anonymised = hasher.anonymise(&repo_path, &repository_info);
+        let json = anonymised.get_as_json()?;
+        // The organisations, e.g., 'example.com', are left to the caller
+        for original in ["secret", "client", "Alice", "rotate"] {
+            assert!(!json.contains(original), "'{}' is in {}", original, json);
+        }
+        assert_eq!(
+            anonymised.metadata.and_then(|metadata| metadata.head),
+            Some(HeadState::Branch {
+                name: hasher.redact_name("main"),
+                commit: repository_info
+                    .history
+                    .map(|history| history.head)
+                    .unwrap_or_default(),
+            })
+        );
+        Ok(())
+    }
+}
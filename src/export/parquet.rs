@@ -0,0 +1,224 @@
+#[cfg(feature = "git")]
+use arrow::array::Int32Array;
+use arrow::array::{
+    ArrayRef, Float32Array, Float64Array, StringArray, TimestampSecondArray, UInt64Array,
+};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+#[cfg(feature = "git")]
+use git2::{Repository, Revwalk};
+use parquet::arrow::ArrowWriter;
+use std::{fs::File, path::Path, sync::Arc};
+
+#[cfg(feature = "git")]
+use crate::head;
+use crate::{
+    errors::SourceCodeError,
+    repository::{Contributor, RepositoryInfo},
+    source::SourceFileInfo,
+};
+
+/// File name used for the per-file statistics table
+pub const SOURCE_FILES_FILE_NAME: &str = "source_files.parquet";
+/// File name used for the contributors table
+pub const CONTRIBUTORS_FILE_NAME: &str = "contributors.parquet";
+/// File name used for the commit time series table
+pub const COMMITS_FILE_NAME: &str = "commits.parquet";
+/// File name used for the derived metrics table
+pub const DERIVED_METRICS_FILE_NAME: &str = "derived_metrics.parquet";
+
+/// Writes the [`RepositoryInfo`] as a set of Parquet files into `output_dir`, one per table:
+/// `source_files.parquet`, `contributors.parquet`, `commits.parquet` and `derived_metrics.parquet`.
+///
+/// #Arguments:
+/// * `repository_info` - The [`RepositoryInfo`] to export
+/// * `repo_path` - The path to the repository, used to walk the commit history
+/// * `output_dir` - The directory to write the Parquet files to (must exist)
+#[cfg(feature = "git")]
+pub fn write_repository_info(
+    repository_info: &RepositoryInfo,
+    repo_path: &str,
+    output_dir: &Path,
+) -> Result<(), SourceCodeError> {
+    write_source_files(
+        &repository_info.source_files,
+        &output_dir.join(SOURCE_FILES_FILE_NAME),
+    )?;
+    write_contributors(
+        &repository_info.contributors,
+        &output_dir.join(CONTRIBUTORS_FILE_NAME),
+    )?;
+    write_derived_metrics(repository_info, &output_dir.join(DERIVED_METRICS_FILE_NAME))?;
+    write_commit_time_series(repo_path, &output_dir.join(COMMITS_FILE_NAME))
+}
+/// Writes the per-file statistics for the [`SourceFileInfo`]s as a Parquet file
+pub fn write_source_files(
+    source_files: &[SourceFileInfo],
+    path: &Path,
+) -> Result<(), SourceCodeError> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("relative_path", DataType::Utf8, false),
+        Field::new("language", DataType::Utf8, true),
+        Field::new("id_hash", DataType::Utf8, true),
+        Field::new("size", DataType::UInt64, false),
+        Field::new("loc", DataType::UInt64, false),
+        Field::new("num_commits", DataType::UInt64, false),
+        Field::new("frequency", DataType::Float32, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            source_files.iter().map(|sfi| sfi.name.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            source_files.iter().map(|sfi| sfi.relative_path.as_str()),
+        )),
+        Arc::new(StringArray::from_iter(source_files.iter().map(|sfi| {
+            sfi.language.as_ref().map(|language| language.name.as_str())
+        }))),
+        Arc::new(StringArray::from_iter(
+            source_files.iter().map(|sfi| sfi.id_hash.as_deref()),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            source_files.iter().map(|sfi| sfi.statistics.size),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            source_files.iter().map(|sfi| sfi.statistics.loc),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            source_files.iter().map(|sfi| sfi.statistics.num_commits),
+        )),
+        Arc::new(Float32Array::from_iter_values(
+            source_files
+                .iter()
+                .map(|sfi| sfi.statistics.change_frequency.0),
+        )),
+    ];
+    write_batch(schema, columns, path)
+}
+/// Writes the [`Contributor`]s as a Parquet file
+pub fn write_contributors(
+    contributors: &[Contributor],
+    path: &Path,
+) -> Result<(), SourceCodeError> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new(
+            "last_contribution",
+            DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            false,
+        ),
+        Field::new("percentage_contribution", DataType::Float32, false),
+        Field::new("num_commits", DataType::UInt64, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            contributors.iter().map(|c| c.author.name.as_str()),
+        )),
+        Arc::new(
+            TimestampSecondArray::from_iter_values(
+                contributors.iter().map(|c| c.last_contribution.timestamp()),
+            )
+            .with_timezone("UTC"),
+        ),
+        Arc::new(Float32Array::from_iter_values(
+            contributors.iter().map(|c| c.percentage_contribution),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            contributors.iter().map(|c| c.statistics.num_commits),
+        )),
+    ];
+    write_batch(schema, columns, path)
+}
+/// Writes the values of the [`crate::derived::DerivedMetric`]s as a Parquet file, one row per
+/// source file (or the repository, with a null `relative_path`) and metric
+pub fn write_derived_metrics(
+    repository_info: &RepositoryInfo,
+    path: &Path,
+) -> Result<(), SourceCodeError> {
+    let mut relative_paths: Vec<Option<&str>> = Vec::new();
+    let mut names: Vec<&str> = Vec::new();
+    let mut values: Vec<f64> = Vec::new();
+    let statistics = std::iter::once((None, &repository_info.statistics)).chain(
+        repository_info
+            .source_files
+            .iter()
+            .map(|sfi| (Some(sfi.relative_path.as_str()), &sfi.statistics)),
+    );
+    for (relative_path, statistics) in statistics {
+        for (name, value) in &statistics.derived {
+            relative_paths.push(relative_path);
+            names.push(name);
+            values.push(*value);
+        }
+    }
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("relative_path", DataType::Utf8, true),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("value", DataType::Float64, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(relative_paths)),
+        Arc::new(StringArray::from(names)),
+        Arc::new(Float64Array::from(values)),
+    ];
+    write_batch(schema, columns, path)
+}
+/// Writes the commit time series (one row per commit reachable from HEAD) as a Parquet file
+#[cfg(feature = "git")]
+pub fn write_commit_time_series(repo_path: &str, path: &Path) -> Result<(), SourceCodeError> {
+    let repo: Repository = Repository::open(repo_path)?;
+    let mut revwalk: Revwalk<'_> = repo.revwalk()?;
+    head::push_head(&repo, &mut revwalk)?;
+
+    let mut ids: Vec<String> = Vec::new();
+    let mut authors: Vec<String> = Vec::new();
+    let mut times: Vec<i64> = Vec::new();
+    let mut parent_counts: Vec<i32> = Vec::new();
+
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        ids.push(commit.id().to_string());
+        authors.push(String::from(commit.author().name().unwrap_or_default()));
+        times.push(commit.author().when().seconds());
+        parent_counts.push(commit.parent_count().try_into()?);
+    }
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("author", DataType::Utf8, false),
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            false,
+        ),
+        Field::new("parent_count", DataType::Int32, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(ids)),
+        Arc::new(StringArray::from(authors)),
+        Arc::new(TimestampSecondArray::from(times).with_timezone("UTC")),
+        Arc::new(Int32Array::from(parent_counts)),
+    ];
+    write_batch(schema, columns, path)
+}
+/// Writes a single [`RecordBatch`] built from the `columns` to a Parquet file at `path`
+fn write_batch(
+    schema: Arc<Schema>,
+    columns: Vec<ArrayRef>,
+    path: &Path,
+) -> Result<(), SourceCodeError> {
+    let batch = RecordBatch::try_new(schema.clone(), columns)
+        .map_err(|err| SourceCodeError::ExportError(err.into()))?;
+    let file = File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, schema, None)
+        .map_err(|err| SourceCodeError::ExportError(err.into()))?;
+    writer
+        .write(&batch)
+        .map_err(|err| SourceCodeError::ExportError(err.into()))?;
+    writer
+        .close()
+        .map_err(|err| SourceCodeError::ExportError(err.into()))?;
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! A C API over the analysis of a repository and the diffing of snapshots, so that, e.g., data
+//! science teams can drive them from Python through `ctypes` or `cffi` rather than a CLI. The
+//! shared library is built with `cargo rustc --release --features ffi --crate-type cdylib`.
+//!
+//! The functions take and return NUL-terminated UTF-8 strings. A string returned is owned by the
+//! caller, who frees it with [`repository_statistics_free_string`]; on an error `NULL` is
+//! returned, and [`repository_statistics_last_error`] gets its message, e.g.:
+//!
+//! ```python
+//! import ctypes, json
+//!
+//! lib = ctypes.CDLL("librepository_statistics.so")
+//! lib.repository_statistics_analyze_repository.restype = ctypes.c_void_p
+//! lib.repository_statistics_last_error.restype = ctypes.c_void_p
+//!
+//! def call(result):
+//!     if not result:
+//!         result = lib.repository_statistics_last_error()
+//!         message = ctypes.string_at(result).decode()
+//!         lib.repository_statistics_free_string(ctypes.c_void_p(result))
+//!         raise RuntimeError(message)
+//!     try:
+//!         return json.loads(ctypes.string_at(result).decode())
+//!     finally:
+//!         lib.repository_statistics_free_string(ctypes.c_void_p(result))
+//!
+//! stats = call(lib.repository_statistics_analyze_repository(b"/path/to/repo", None))
+//! ```
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr,
+};
+
+use crate::{errors::SourceCodeError, options::AnalysisOptions, repository::RepositoryInfo};
+
+thread_local! {
+    /// The message of the error of the last call on the thread that failed, if it did
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Analyses the repository at `path`, named after its directory, with the [`AnalysisOptions`]
+/// of the `options_toml`, see [`AnalysisOptions::from_toml`], or else the default options
+///
+/// #Returns:
+/// * The [`RepositoryInfo`], as JSON
+pub fn analyze_repository(
+    path: &str,
+    options_toml: Option<&str>,
+) -> Result<String, SourceCodeError> {
+    let options = match options_toml {
+        Some(options_toml) => AnalysisOptions::from_toml(options_toml)?,
+        None => AnalysisOptions::default(),
+    };
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    RepositoryInfo::new_with_options(name, path, &options)?.get_as_json()
+}
+
+/// Diffs the snapshot `head` against the earlier snapshot `base`, both the JSON of a
+/// [`RepositoryInfo`] of any schema version, see [`RepositoryInfo::deserialize_any_version`]
+///
+/// #Returns:
+/// * The [`crate::delta::RepositoryDelta`] from `base` to `head`, as JSON
+pub fn diff_snapshots(base: &str, head: &str) -> Result<String, SourceCodeError> {
+    let base = RepositoryInfo::deserialize_any_version(base)?;
+    let head = RepositoryInfo::deserialize_any_version(head)?;
+    base.diff(&head).get_as_json()
+}
+
+/// Analyses the repository at `path`, with the options of the 'options.toml' contents
+/// `options_toml`, or the default options if it is `NULL`, see [`analyze_repository`]
+///
+/// #Returns:
+/// * The JSON of the [`RepositoryInfo`], to be freed with [`repository_statistics_free_string`],
+///   or `NULL` on an error
+///
+/// # Safety
+///
+/// `path`, and `options_toml` unless it is `NULL`, must be NUL-terminated strings that are
+/// valid for the call.
+#[no_mangle]
+pub unsafe extern "C" fn repository_statistics_analyze_repository(
+    path: *const c_char,
+    options_toml: *const c_char,
+) -> *mut c_char {
+    call(|| {
+        let path = to_str(path)?
+            .ok_or_else(|| SourceCodeError::ConfigError("The path is NULL".to_string()))?;
+        analyze_repository(path, to_str(options_toml)?)
+    })
+}
+
+/// Diffs the snapshot JSON `head` against the earlier snapshot JSON `base`, see
+/// [`diff_snapshots`]
+///
+/// #Returns:
+/// * The JSON of the [`crate::delta::RepositoryDelta`], to be freed with
+///   [`repository_statistics_free_string`], or `NULL` on an error
+///
+/// # Safety
+///
+/// `base` and `head` must be NUL-terminated strings that are valid for the call.
+#[no_mangle]
+pub unsafe extern "C" fn repository_statistics_diff_snapshots(
+    base: *const c_char,
+    head: *const c_char,
+) -> *mut c_char {
+    call(|| {
+        let null = || SourceCodeError::ConfigError("The snapshot is NULL".to_string());
+        diff_snapshots(
+            to_str(base)?.ok_or_else(null)?,
+            to_str(head)?.ok_or_else(null)?,
+        )
+    })
+}
+
+/// Gets the message of the error of the last call on this thread, if it failed
+///
+/// #Returns:
+/// * The message, to be freed with [`repository_statistics_free_string`], or `NULL` if the
+///   last call succeeded
+#[no_mangle]
+pub extern "C" fn repository_statistics_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null_mut(), |message| message.clone().into_raw())
+    })
+}
+
+/// Frees a string returned by one of the functions of this API; `NULL` is ignored
+///
+/// # Safety
+///
+/// `string` must be `NULL`, or a string returned by this API that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn repository_statistics_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Runs the `operation` of a call, returning its JSON to the caller, or `NULL` with the last
+/// error set if it fails or panics, as a panic can't unwind into the caller
+fn call(operation: impl FnOnce() -> Result<String, SourceCodeError>) -> *mut c_char {
+    let message = match panic::catch_unwind(AssertUnwindSafe(operation)) {
+        Ok(Ok(json)) => match CString::new(json) {
+            Ok(json) => {
+                set_last_error(None);
+                return json.into_raw();
+            }
+            Err(err) => format!("Failed to return the JSON: {}", err),
+        },
+        Ok(Err(err)) => err.to_string(),
+        Err(panic) => format!("Panicked: {}", get_panic_message(panic.as_ref())),
+    };
+    tracing::warn!("FFI call failed: {}", message);
+    set_last_error(Some(message));
+    ptr::null_mut()
+}
+
+/// Sets (or clears) the message of the last error on this thread
+fn set_last_error(message: Option<String>) {
+    // A message with a NUL byte is cut at it
+    let message = message.map(|message| {
+        let end = message.find('\0').unwrap_or(message.len());
+        CString::new(&message[..end]).unwrap_or_default()
+    });
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+/// Gets the message a panic was raised with, if it is a string
+fn get_panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause")
+}
+
+/// Borrows the NUL-terminated UTF-8 `string`, or `None` if it is `NULL`
+///
+/// # Safety
+///
+/// `string` must be `NULL`, or a NUL-terminated string that outlives the borrow.
+unsafe fn to_str<'a>(string: *const c_char) -> Result<Option<&'a str>, SourceCodeError> {
+    if string.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map(Some)
+        .map_err(|err| SourceCodeError::ConfigError(format!("The string isn't UTF-8: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FixtureRepo;
+
+    /// Takes the string returned by a call, freeing it, or `None` if it is `NULL`
+    fn take_string(string: *mut c_char) -> Option<String> {
+        (!string.is_null()).then(|| unsafe {
+            let taken = CStr::from_ptr(string).to_string_lossy().to_string();
+            repository_statistics_free_string(string);
+            taken
+        })
+    }
+
+    #[test]
+    fn analyses_a_repository_through_the_c_api() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("src/main.rs", "fn main() {}\n")
+            .commit("Add the entry point")?;
+        let path = CString::new(fixture.path().to_string_lossy().as_bytes())
+            .map_err(|err| SourceCodeError::ConfigError(err.to_string()))?;
+
+        let json = take_string(unsafe {
+            repository_statistics_analyze_repository(path.as_ptr(), ptr::null())
+        });
+        let stats = RepositoryInfo::deserialize_any_version(&json.unwrap_or_default())?;
+        assert_eq!(stats.source_files.len(), 1);
+        assert_eq!(take_string(repository_statistics_last_error()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn returns_null_with_the_last_error_on_a_failure_or_panic() {
+        let json = unsafe { repository_statistics_analyze_repository(ptr::null(), ptr::null()) };
+        assert!(json.is_null());
+        let message = take_string(repository_statistics_last_error());
+        assert!(message.is_some_and(|message| message.contains("The path is NULL")));
+
+        let json = call(|| panic!("the analysis\0 failed"));
+        assert!(json.is_null());
+        assert_eq!(
+            take_string(repository_statistics_last_error()).as_deref(),
+            Some("Panicked: the analysis")
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "forge")]
+use crate::{errors::SourceCodeError, repository::RepositoryInfo};
+#[cfg(feature = "forge")]
+use std::collections::HashMap;
+
+/// The kind of forge a repository is hosted on
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+/// The metadata of a repository from the API of its forge
+///
+/// #Fields:
+/// * `kind` - The [`ForgeKind`] of the forge
+/// * `path` - The path of the repository on the forge, e.g., 'owner/name' or 'group/sub/name'
+/// * `url` - The web URL of the repository
+/// * `stars` - The number of stars
+/// * `open_issues` - The number of open issues, without pull (or merge) requests
+/// * `num_pull_requests` - The number of pull (or merge) requests, in any state
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ForgeMetadata {
+    pub kind: ForgeKind,
+    pub path: String,
+    pub url: Option<String>,
+    pub stars: i64,
+    pub open_issues: i64,
+    pub num_pull_requests: i64,
+}
+
+/// The forge account of a contributor
+///
+/// #Fields:
+/// * `username` - The username, e.g., 'octocat'
+/// * `avatar_url` - The URL of the avatar image
+/// * `profile_url` - The web URL of the profile
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ForgeUser {
+    pub username: String,
+    pub avatar_url: Option<String>,
+    pub profile_url: Option<String>,
+}
+
+/// The remote of a repository on a forge, parsed from its URL
+///
+/// #Fields:
+/// * `kind` - The [`ForgeKind`], from the host name
+/// * `host` - The host name, e.g., 'github.com'
+/// * `path` - The path of the repository, without a '.git' suffix, e.g., 'owner/name'
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForgeRemote {
+    pub kind: ForgeKind,
+    pub host: String,
+    pub path: String,
+}
+impl ForgeRemote {
+    /// Parses a remote `url`, e.g., 'https://github.com/owner/name.git',
+    /// 'git@gitlab.com:group/name.git' or 'ssh://git@github.com/owner/name'
+    ///
+    /// #Returns:
+    /// * The [`ForgeRemote`], or `None` if the URL isn't of a known forge, i.e., its host name
+    ///   doesn't contain 'github' or 'gitlab'
+    pub fn parse(url: &str) -> Option<ForgeRemote> {
+        let url = url.trim();
+        let (host, path) = match url.split_once("://") {
+            Some((_, rest)) => rest.split_once('/')?,
+            // The scp-like syntax, e.g., 'git@github.com:owner/name.git'
+            None => url.split_once(':')?,
+        };
+        let host = host.rsplit('@').next()?;
+        let host = host.split(':').next()?.to_lowercase();
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path).to_string();
+        let kind = match host.as_str() {
+            host if host.contains("github") => ForgeKind::GitHub,
+            host if host.contains("gitlab") => ForgeKind::GitLab,
+            _ => return None,
+        };
+        match path.contains('/') {
+            true => Some(ForgeRemote { kind, host, path }),
+            false => None,
+        }
+    }
+    /// Gets the base URL of the forge's REST API, e.g., 'https://api.github.com'
+    pub fn api_url(&self) -> String {
+        match (self.kind, self.host.as_str()) {
+            (ForgeKind::GitHub, "github.com") => "https://api.github.com".to_string(),
+            (ForgeKind::GitHub, host) => format!("https://{}/api/v3", host),
+            (ForgeKind::GitLab, host) => format!("https://{}/api/v4", host),
+        }
+    }
+}
+
+/// The options for enriching a [`RepositoryInfo`] from its forge
+///
+/// #Fields:
+/// * `remote_url` - The URL of the repository on the forge; defaults to the URL of the remote
+///   'origin'
+/// * `token` - The API token; defaults to the `GITHUB_TOKEN` or `GITLAB_TOKEN` environment
+///   variable
+/// * `api_url` - The base URL of the API, e.g., of a self-hosted forge; defaults to the one of
+///   the remote's host
+/// * `max_commits` - The most commits read from a GitHub repository to map its authors to
+///   their accounts
+#[cfg(feature = "forge")]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct ForgeOptions {
+    pub remote_url: Option<String>,
+    #[serde(skip_serializing)]
+    pub token: Option<String>,
+    pub api_url: Option<String>,
+    pub max_commits: usize,
+}
+#[cfg(feature = "forge")]
+impl Default for ForgeOptions {
+    fn default() -> Self {
+        Self {
+            remote_url: None,
+            token: None,
+            api_url: None,
+            max_commits: 500,
+        }
+    }
+}
+
+/// The number of items per page of the forges' APIs
+#[cfg(feature = "forge")]
+const PAGE_SIZE: usize = 100;
+
+/// Enriches the `repository_info` of the repository at `repo_path` with the [`ForgeMetadata`]
+/// of the repository, and the [`ForgeUser`] of each contributor, matched by name, from the API
+/// of its forge
+///
+/// #Returns:
+/// * Err([`SourceCodeError::ConfigError`]) if the remote URL isn't of a known forge
+/// * Err([`SourceCodeError::ForgeError`]) if a request to the forge failed
+#[cfg(feature = "forge")]
+pub async fn enrich(
+    repository_info: &mut RepositoryInfo,
+    repo_path: &str,
+    options: &ForgeOptions,
+) -> Result<(), SourceCodeError> {
+    let remote_url = match &options.remote_url {
+        Some(remote_url) => remote_url.clone(),
+        None => git2::Repository::open(repo_path)?
+            .find_remote("origin")
+            .map_err(SourceCodeError::for_reference("origin"))?
+            .url()
+            .map(String::from)
+            .unwrap_or_default(),
+    };
+    let remote = ForgeRemote::parse(&remote_url).ok_or_else(|| {
+        SourceCodeError::ConfigError(format!("Not the URL of a known forge: {}", remote_url))
+    })?;
+    let token = options.token.clone().or_else(|| {
+        let variable = match remote.kind {
+            ForgeKind::GitHub => "GITHUB_TOKEN",
+            ForgeKind::GitLab => "GITLAB_TOKEN",
+        };
+        std::env::var(variable).ok()
+    });
+    let client = ForgeClient {
+        client: reqwest::Client::new(),
+        api_url: options
+            .api_url
+            .clone()
+            .unwrap_or_else(|| remote.api_url())
+            .trim_end_matches('/')
+            .to_string(),
+        kind: remote.kind,
+        token,
+    };
+    let (metadata, users) = match remote.kind {
+        ForgeKind::GitHub => client.get_github(&remote.path, options.max_commits).await?,
+        ForgeKind::GitLab => client.get_gitlab(&remote.path).await?,
+    };
+    for contributor in &mut repository_info.contributors {
+        contributor.forge_user = users.get(&contributor.author.name).cloned();
+    }
+    repository_info.forge = Some(metadata);
+    Ok(())
+}
+
+/// A client of the REST API of a forge
+#[cfg(feature = "forge")]
+struct ForgeClient {
+    client: reqwest::Client,
+    api_url: String,
+    kind: ForgeKind,
+    token: Option<String>,
+}
+#[cfg(feature = "forge")]
+impl ForgeClient {
+    /// Gets the response to a GET request of the `endpoint`, with the `query`
+    async fn get(
+        &self,
+        endpoint: &str,
+        query: &[(&str, String)],
+    ) -> Result<reqwest::Response, SourceCodeError> {
+        let mut request = self
+            .client
+            .get(format!("{}{}", self.api_url, endpoint))
+            .query(query)
+            .header(reqwest::header::USER_AGENT, "repository_statistics");
+        if let Some(token) = &self.token {
+            request = match self.kind {
+                ForgeKind::GitHub => request.bearer_auth(token),
+                ForgeKind::GitLab => request.header("PRIVATE-TOKEN", token),
+            };
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| SourceCodeError::ForgeError(err.into()))
+    }
+    /// Gets the JSON response to a GET request of the `endpoint`, with the `query`
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, SourceCodeError> {
+        self.get(endpoint, query)
+            .await?
+            .json()
+            .await
+            .map_err(|err| SourceCodeError::ForgeError(err.into()))
+    }
+    /// Gets the metadata of the GitHub repository at `path`, and the accounts of the authors of
+    /// its latest `max_commits` commits, by name
+    async fn get_github(
+        &self,
+        path: &str,
+        max_commits: usize,
+    ) -> Result<(ForgeMetadata, HashMap<String, ForgeUser>), SourceCodeError> {
+        let repository: GitHubRepository = self.get_json(&format!("/repos/{}", path), &[]).await?;
+        let count = |kind: &str| {
+            let query = format!("repo:{} type:{}", path, kind);
+            async move {
+                let search: GitHubSearch = self
+                    .get_json(
+                        "/search/issues",
+                        &[("q", query), ("per_page", "1".to_string())],
+                    )
+                    .await?;
+                Ok::<i64, SourceCodeError>(search.total_count)
+            }
+        };
+        let metadata = ForgeMetadata {
+            kind: ForgeKind::GitHub,
+            path: path.to_string(),
+            url: repository.html_url,
+            stars: repository.stargazers_count,
+            open_issues: count("issue state:open").await?,
+            num_pull_requests: count("pr").await?,
+        };
+
+        let mut users: HashMap<String, ForgeUser> = HashMap::new();
+        for page in 1..=max_commits.div_ceil(PAGE_SIZE) {
+            let commits: Vec<GitHubCommit> = self
+                .get_json(
+                    &format!("/repos/{}/commits", path),
+                    &[
+                        ("per_page", PAGE_SIZE.to_string()),
+                        ("page", page.to_string()),
+                    ],
+                )
+                .await?;
+            let is_last_page = commits.len() < PAGE_SIZE;
+            for commit in commits {
+                if let (Some(name), Some(author)) = (commit.commit.author.name, commit.author) {
+                    users.entry(name).or_insert(ForgeUser {
+                        username: author.login,
+                        avatar_url: author.avatar_url,
+                        profile_url: author.html_url,
+                    });
+                }
+            }
+            if is_last_page {
+                break;
+            }
+        }
+        Ok((metadata, users))
+    }
+    /// Gets the metadata of the GitLab project at `path`, and the accounts of its members, by
+    /// name
+    async fn get_gitlab(
+        &self,
+        path: &str,
+    ) -> Result<(ForgeMetadata, HashMap<String, ForgeUser>), SourceCodeError> {
+        let project_endpoint = format!("/projects/{}", path.replace('/', "%2F"));
+        let project: GitLabProject = self.get_json(&project_endpoint, &[]).await?;
+        let merge_requests = self
+            .get(
+                &format!("{}/merge_requests", project_endpoint),
+                &[("state", "all".to_string()), ("per_page", "1".to_string())],
+            )
+            .await?;
+        let num_pull_requests = merge_requests
+            .headers()
+            .get("x-total")
+            .and_then(|total| total.to_str().ok())
+            .and_then(|total| total.parse().ok())
+            .unwrap_or_default();
+        let metadata = ForgeMetadata {
+            kind: ForgeKind::GitLab,
+            path: path.to_string(),
+            url: project.web_url,
+            stars: project.star_count,
+            open_issues: project.open_issues_count.unwrap_or_default(),
+            num_pull_requests,
+        };
+
+        let members: Vec<GitLabUser> = self
+            .get_json(
+                &format!("{}/members/all", project_endpoint),
+                &[("per_page", PAGE_SIZE.to_string())],
+            )
+            .await?;
+        let users = members
+            .into_iter()
+            .map(|member| {
+                let user = ForgeUser {
+                    username: member.username,
+                    avatar_url: member.avatar_url,
+                    profile_url: member.web_url,
+                };
+                (member.name, user)
+            })
+            .collect();
+        Ok((metadata, users))
+    }
+}
+
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitHubRepository {
+    html_url: Option<String>,
+    #[serde(default)]
+    stargazers_count: i64,
+}
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitHubSearch {
+    total_count: i64,
+}
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitHubCommit {
+    commit: GitHubCommitDetails,
+    author: Option<GitHubUser>,
+}
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitHubCommitDetails {
+    author: GitHubSignature,
+}
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitHubSignature {
+    name: Option<String>,
+}
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitHubUser {
+    login: String,
+    avatar_url: Option<String>,
+    html_url: Option<String>,
+}
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitLabProject {
+    web_url: Option<String>,
+    #[serde(default)]
+    star_count: i64,
+    open_issues_count: Option<i64>,
+}
+#[cfg(feature = "forge")]
+#[derive(Deserialize)]
+struct GitLabUser {
+    username: String,
+    name: String,
+    avatar_url: Option<String>,
+    web_url: Option<String>,
+}
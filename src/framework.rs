@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{paths, source::SourceFileInfo, walk};
+
+/// The dependency manifest formats that frameworks are detected from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Manifest {
+    Cargo,
+    Npm,
+    Python,
+    Maven,
+    Gradle,
+    GoModule,
+}
+impl Manifest {
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        match file_name {
+            "Cargo.toml" => Some(Manifest::Cargo),
+            "package.json" => Some(Manifest::Npm),
+            "requirements.txt" | "Pipfile" | "pyproject.toml" | "setup.py" | "setup.cfg" => {
+                Some(Manifest::Python)
+            }
+            "pom.xml" => Some(Manifest::Maven),
+            "build.gradle" | "build.gradle.kts" => Some(Manifest::Gradle),
+            "go.mod" => Some(Manifest::GoModule),
+            _ => None,
+        }
+    }
+    /// Checks whether the manifest `contents` declare a dependency on `dependency`
+    fn declares(&self, contents: &str, dependency: &str) -> bool {
+        match self {
+            Manifest::Cargo => contents.lines().any(|line| {
+                let line = line.trim_start();
+                line.strip_prefix(dependency)
+                    .map(|rest| rest.starts_with([' ', '=', '.']))
+                    .unwrap_or(false)
+                    || line == format!("[dependencies.{}]", dependency)
+            }),
+            Manifest::Npm => contents.contains(&format!("\"{}\"", dependency)),
+            Manifest::Python => contents.lines().any(|line| {
+                let line = line.trim_start().trim_start_matches(['"', '\'']);
+                line.to_lowercase()
+                    .strip_prefix(dependency)
+                    .map(|rest| {
+                        rest.is_empty()
+                            || !rest
+                                .starts_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_')
+                    })
+                    .unwrap_or(false)
+            }),
+            Manifest::Maven | Manifest::Gradle | Manifest::GoModule => {
+                contents.contains(dependency)
+            }
+        }
+    }
+}
+
+/// Import statements start with a keyword, or with the quoted path inside a Go import block
+const IMPORT_STATEMENT_PREFIXES: [&str; 8] = [
+    "use ", "import ", "from ", "require", "const ", "let ", "var ", "\"",
+];
+/// The characters that may follow the name of an imported package or module, so that, e.g.,
+/// 'use rocket' doesn't match 'use rocket_sled'
+const IMPORT_BOUNDARIES: [char; 8] = [':', ';', ',', '.', '{', '\'', '"', '/'];
+
+/// A rule describing how to recognise a framework
+struct FrameworkRule {
+    name: &'static str,
+    ecosystem: &'static str,
+    dependencies: &'static [(Manifest, &'static str)],
+    imports: &'static [&'static str],
+}
+
+const FRAMEWORK_RULES: [FrameworkRule; 16] = [
+    FrameworkRule {
+        name: "Actix Web",
+        ecosystem: "Rust",
+        dependencies: &[(Manifest::Cargo, "actix-web")],
+        imports: &["use actix_web"],
+    },
+    FrameworkRule {
+        name: "Axum",
+        ecosystem: "Rust",
+        dependencies: &[(Manifest::Cargo, "axum")],
+        imports: &["use axum"],
+    },
+    FrameworkRule {
+        name: "Rocket",
+        ecosystem: "Rust",
+        dependencies: &[(Manifest::Cargo, "rocket")],
+        imports: &["use rocket"],
+    },
+    FrameworkRule {
+        name: "React",
+        ecosystem: "JavaScript",
+        dependencies: &[(Manifest::Npm, "react")],
+        imports: &["from 'react'", "from \"react\"", "require('react')"],
+    },
+    FrameworkRule {
+        name: "Vue",
+        ecosystem: "JavaScript",
+        dependencies: &[(Manifest::Npm, "vue")],
+        imports: &["from 'vue'", "from \"vue\""],
+    },
+    FrameworkRule {
+        name: "Angular",
+        ecosystem: "JavaScript",
+        dependencies: &[(Manifest::Npm, "@angular/core")],
+        imports: &["from '@angular/core'", "from \"@angular/core\""],
+    },
+    FrameworkRule {
+        name: "Svelte",
+        ecosystem: "JavaScript",
+        dependencies: &[(Manifest::Npm, "svelte")],
+        imports: &["from 'svelte'", "from \"svelte\""],
+    },
+    FrameworkRule {
+        name: "Express",
+        ecosystem: "JavaScript",
+        dependencies: &[(Manifest::Npm, "express")],
+        imports: &["require('express')", "from 'express'", "from \"express\""],
+    },
+    FrameworkRule {
+        name: "Next.js",
+        ecosystem: "JavaScript",
+        dependencies: &[(Manifest::Npm, "next")],
+        imports: &["from 'next/", "from \"next/"],
+    },
+    FrameworkRule {
+        name: "Django",
+        ecosystem: "Python",
+        dependencies: &[(Manifest::Python, "django")],
+        imports: &["from django", "import django"],
+    },
+    FrameworkRule {
+        name: "Flask",
+        ecosystem: "Python",
+        dependencies: &[(Manifest::Python, "flask")],
+        imports: &["from flask", "import flask"],
+    },
+    FrameworkRule {
+        name: "FastAPI",
+        ecosystem: "Python",
+        dependencies: &[(Manifest::Python, "fastapi")],
+        imports: &["from fastapi", "import fastapi"],
+    },
+    FrameworkRule {
+        name: "Spring",
+        ecosystem: "Java",
+        dependencies: &[
+            (Manifest::Maven, "org.springframework"),
+            (Manifest::Gradle, "org.springframework"),
+        ],
+        imports: &["import org.springframework"],
+    },
+    FrameworkRule {
+        name: "Gin",
+        ecosystem: "Go",
+        dependencies: &[(Manifest::GoModule, "github.com/gin-gonic/gin")],
+        imports: &["\"github.com/gin-gonic/gin\""],
+    },
+    FrameworkRule {
+        name: "Echo",
+        ecosystem: "Go",
+        dependencies: &[(Manifest::GoModule, "github.com/labstack/echo")],
+        imports: &["\"github.com/labstack/echo"],
+    },
+    FrameworkRule {
+        name: "Ruby on Rails",
+        ecosystem: "Ruby",
+        dependencies: &[],
+        imports: &["require 'rails", "require \"rails"],
+    },
+];
+
+/// How a [`Framework`] was detected
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FrameworkEvidence {
+    Manifest,
+    Import,
+}
+
+/// A framework in use in the repository
+///
+/// #Fields:
+/// * `name` - The name of the framework, e.g., 'Axum'
+/// * `ecosystem` - The language ecosystem of the framework, e.g., 'Rust'
+/// * `evidence` - Whether the framework was found in a manifest or only via imports
+/// * `detected_in` - The path of the first file the framework was detected in
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Framework {
+    pub name: String,
+    pub ecosystem: String,
+    pub evidence: FrameworkEvidence,
+    pub detected_in: String,
+}
+impl Framework {
+    /// Detects the frameworks used by the repository at `repo_path`, first from dependency
+    /// manifests, then from import statements in the [`SourceFileInfo`] contents for any
+    /// frameworks not declared in a manifest.
+    pub fn detect(repo_path: &str, source_files: &[SourceFileInfo]) -> Vec<Framework> {
+        let manifests: Vec<(Manifest, String, String)> = Self::find_manifests(repo_path);
+        let mut frameworks: Vec<Framework> = Vec::new();
+
+        for rule in &FRAMEWORK_RULES {
+            let in_manifest = manifests.iter().find(|(manifest, _, contents)| {
+                rule.dependencies.iter().any(|(kind, dependency)| {
+                    kind == manifest && manifest.declares(contents, dependency)
+                })
+            });
+            if let Some((_, path, _)) = in_manifest {
+                frameworks.push(Framework::new_from(rule, FrameworkEvidence::Manifest, path));
+                continue;
+            }
+            let languages = Self::get_ecosystem_languages(rule.ecosystem);
+            let in_import = source_files.iter().find(|sfi| {
+                sfi.language
+                    .as_ref()
+                    .map(|l| languages.contains(&l.name.as_str()))
+                    .unwrap_or(false)
+                    && sfi
+                        .get_contents()
+                        .is_some_and(|contents| Self::has_import(contents, rule.imports))
+            });
+            if let Some(sfi) = in_import {
+                let path = Path::new(&sfi.relative_path);
+                frameworks.push(Framework::new_from(
+                    rule,
+                    FrameworkEvidence::Import,
+                    &paths::to_slash(path.strip_prefix(repo_path).unwrap_or(path)),
+                ));
+            }
+        }
+        frameworks
+    }
+    /// Gets the languages whose source files may import the frameworks of an ecosystem
+    fn get_ecosystem_languages(ecosystem: &str) -> &'static [&'static str] {
+        match ecosystem {
+            "Rust" => &["Rust"],
+            "JavaScript" => &["JavaScript", "TypeScript", "JSX", "TSX", "Vue", "Svelte"],
+            "Python" => &["Python"],
+            "Java" => &["Java", "Kotlin"],
+            "Go" => &["Go"],
+            "Ruby" => &["Ruby"],
+            _ => &[],
+        }
+    }
+    /// Checks whether any import statement in `contents` matches one of the `imports` patterns
+    fn has_import(contents: &str, imports: &[&str]) -> bool {
+        contents
+            .lines()
+            .map(str::trim_start)
+            .filter(|line| {
+                IMPORT_STATEMENT_PREFIXES
+                    .iter()
+                    .any(|prefix| line.starts_with(prefix))
+            })
+            .any(|line| {
+                imports
+                    .iter()
+                    .any(|import| Self::matches_import(line, import))
+            })
+    }
+    /// Checks whether the `line` has the `import` pattern, ending at a boundary of the name it
+    /// imports: a pattern ending in a name must be followed by the end of the line, whitespace,
+    /// or one of the [`IMPORT_BOUNDARIES`]
+    fn matches_import(line: &str, import: &str) -> bool {
+        let ends_in_name = import
+            .chars()
+            .last()
+            .is_some_and(|last| last.is_alphanumeric() || last == '_' || last == '-');
+        line.match_indices(import).any(|(start, _)| {
+            !ends_in_name
+                || line[start + import.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|next| next.is_whitespace() || IMPORT_BOUNDARIES.contains(&next))
+        })
+    }
+    fn new_from(rule: &FrameworkRule, evidence: FrameworkEvidence, detected_in: &str) -> Self {
+        Self {
+            name: rule.name.to_string(),
+            ecosystem: rule.ecosystem.to_string(),
+            evidence,
+            detected_in: detected_in.to_string(),
+        }
+    }
+    /// Finds the dependency manifests in the repository, skipping vendored directories
+    fn find_manifests(repo_path: &str) -> Vec<(Manifest, String, String)> {
+        walk::read_files(repo_path, |name| Manifest::from_file_name(name).is_some())
+            .into_iter()
+            .filter_map(|(path, contents)| {
+                let file_name = Path::new(&path).file_name()?.to_str()?;
+                Some((Manifest::from_file_name(file_name)?, path, contents))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_imports_only_at_the_end_of_the_imported_name() {
+        assert!(Framework::has_import("use rocket::get;\n", &["use rocket"]));
+        assert!(Framework::has_import("use rocket;\n", &["use rocket"]));
+        assert!(Framework::has_import("  use rocket\n", &["use rocket"]));
+        assert!(Framework::has_import(
+            "import django.db\n",
+            &["import django"]
+        ));
+        assert!(Framework::has_import(
+            "from flask import Flask\n",
+            &["from flask"]
+        ));
+        assert!(Framework::has_import(
+            "\"github.com/labstack/echo/v4\"\n",
+            &["\"github.com/labstack/echo"]
+        ));
+        assert!(Framework::has_import(
+            "import Link from 'next/link';\n",
+            &["from 'next/"]
+        ));
+
+        assert!(!Framework::has_import(
+            "use rocket_sled::Db;\n",
+            &["use rocket"]
+        ));
+        assert!(!Framework::has_import(
+            "import djangorestframework\n",
+            &["import django"]
+        ));
+        assert!(!Framework::has_import(
+            "from flask_cors import CORS\n",
+            &["from flask"]
+        ));
+        assert!(!Framework::has_import(
+            "require 'railsy'\n",
+            &["require 'rails"]
+        ));
+        // Only import statements are matched
+        assert!(!Framework::has_import("// use rocket;\n", &["use rocket"]));
+    }
+}
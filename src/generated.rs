@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::source::SourceFileInfo;
+
+/// Markers that a comment line in the header of a file starts with if the file is generated,
+/// e.g., Go's '// Code generated by protoc-gen-go. DO NOT EDIT.' or C#'s
+/// '// <auto-generated />'
+const GENERATED_MARKERS: [&str; 4] = [
+    "@generated",
+    "DO NOT EDIT",
+    "Code generated by",
+    "auto-generated",
+];
+/// Phrases, in lower case, of a comment line in the header of a file, starting with 'this file'
+/// or 'this code', if the file is generated, e.g., 'This file is automatically generated'
+const GENERATED_PHRASES: [&str; 3] = ["auto-generated", "autogenerated", "automatically generated"];
+/// The characters that start a comment in the languages of generated files, e.g., '//' or '#'
+const COMMENT_CHARS: [char; 7] = ['/', '*', '#', '-', ';', '<', '!'];
+/// The lines at the start of a file searched for the [`GENERATED_MARKERS`] and
+/// [`GENERATED_PHRASES`]
+const HEADER_LINES: usize = 20;
+/// File name suffixes of generated files, e.g., 'message.pb.rs' or 'schema_generated.go'
+const GENERATED_SUFFIXES: [&str; 11] = [
+    ".pb.rs",
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    "_generated.go",
+    ".generated.cs",
+    ".g.dart",
+    ".designer.cs",
+    ".min.js",
+];
+/// The names of lockfiles, which are generated by package managers
+const LOCKFILES: [&str; 10] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Gemfile.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "composer.lock",
+    "go.sum",
+    "flake.lock",
+];
+
+/// The number of files, lines of code and size of a set of source files
+///
+/// #Fields:
+/// * `num_files` - The number of files
+/// * `loc` - The lines of code
+/// * `size` - The size in bytes
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CodeTotals {
+    pub num_files: u64,
+    pub loc: u64,
+    pub size: u64,
+}
+impl CodeTotals {
+    pub(crate) fn add(&mut self, source_file: &SourceFileInfo) {
+        self.num_files += 1;
+        self.loc = self.loc.saturating_add(source_file.statistics.loc);
+        self.size = self.size.saturating_add(source_file.statistics.size);
+    }
+    /// Adds the `other` totals, e.g., of another repository
+    pub(crate) fn merge(&mut self, other: &CodeTotals) {
+        self.num_files = self.num_files.saturating_add(other.num_files);
+        self.loc = self.loc.saturating_add(other.loc);
+        self.size = self.size.saturating_add(other.size);
+    }
+}
+
+/// The generated code of a repository, detected by heuristics: files with a generated marker,
+/// e.g., '@generated' or 'DO NOT EDIT', in their header, with a generated file name, e.g.,
+/// 'message.pb.rs' or 'schema_generated.go', and lockfiles, e.g., 'Cargo.lock'
+///
+/// #Fields:
+/// * `excluded` - Whether the generated files were excluded from the analysis, as requested in
+///   the [`crate::options::AnalysisOptions`], rather than tagged
+/// * `total` - The [`CodeTotals`] of all the source files, with the generated ones
+/// * `generated` - The [`CodeTotals`] of the generated source files
+/// * `without_generated` - The [`CodeTotals`] of the source files that aren't generated
+/// * `generated_files` - The relative paths of the generated source files
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GeneratedCodeReport {
+    pub excluded: bool,
+    pub total: CodeTotals,
+    pub generated: CodeTotals,
+    pub without_generated: CodeTotals,
+    pub generated_files: Vec<String>,
+}
+impl GeneratedCodeReport {
+    /// Gets the [`GeneratedCodeReport`] of the `source_files`, as classified by [`classify`],
+    /// before any are excluded
+    pub fn new(source_files: &[SourceFileInfo], excluded: bool) -> GeneratedCodeReport {
+        let mut report = GeneratedCodeReport {
+            excluded,
+            ..Default::default()
+        };
+        for source_file in source_files {
+            report.total.add(source_file);
+            match source_file.is_generated {
+                true => {
+                    report.generated.add(source_file);
+                    report
+                        .generated_files
+                        .push(source_file.relative_path.to_string());
+                }
+                false => report.without_generated.add(source_file),
+            }
+        }
+        report
+    }
+}
+
+/// Classifies each of the `source_files` as generated or not, by its name and the header of
+/// its contents
+pub fn classify(source_files: &mut [SourceFileInfo]) {
+    for source_file in source_files {
+        source_file.is_generated = is_generated_path(Path::new(&source_file.relative_path))
+            || source_file.get_contents().is_some_and(has_generated_marker);
+    }
+}
+
+/// Checks whether the file at the relative `path` is generated, by its name
+fn is_generated_path(path: &Path) -> bool {
+    let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+    LOCKFILES.contains(&name.as_ref())
+        || GENERATED_SUFFIXES
+            .iter()
+            .any(|suffix| name.to_lowercase().ends_with(suffix))
+}
+
+/// Checks whether a comment line in the header of the `contents` starts with one of the
+/// [`GENERATED_MARKERS`], or has one of the [`GENERATED_PHRASES`]. Only the start of a line is
+/// matched, so that code that mentions a marker, e.g., in a string, isn't taken as generated.
+fn has_generated_marker(contents: &str) -> bool {
+    contents.lines().take(HEADER_LINES).any(|line| {
+        let line = line.trim_start();
+        let comment = line.trim_start_matches(COMMENT_CHARS).trim_start();
+        if comment.len() == line.len() {
+            return false;
+        }
+        let lowercase_comment = comment.to_lowercase();
+        GENERATED_MARKERS
+            .iter()
+            .any(|marker| comment.starts_with(marker))
+            || ((lowercase_comment.starts_with("this file")
+                || lowercase_comment.starts_with("this code"))
+                && GENERATED_PHRASES
+                    .iter()
+                    .any(|phrase| lowercase_comment.contains(phrase)))
+    })
+}
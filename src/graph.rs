@@ -0,0 +1,365 @@
+use chrono::{DateTime, Utc};
+use git2::{ObjectType, Oid, Repository, Time, TreeWalkMode, TreeWalkResult};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+};
+
+use crate::{
+    cancel::{CancellationToken, PartialResults},
+    checkpoint::Checkpointer,
+    data::to_count,
+    errors::SourceCodeError,
+    head::{self, HeadState},
+    history::{HistoryMode, HistorySampling, SampledHistory},
+    identity::{Author, BotHandling, IdentityOptions, IdentityResolver},
+    observer::{AnalysisObserver, AnalysisStage},
+    source::MergeStrategy,
+};
+
+/// A commit of a [`CommitGraph`]
+///
+/// #Fields:
+/// * `id` - The id of the commit
+/// * `parents` - The ids of the parent commits, the first parent first
+/// * `author` - The [`Author`], as resolved by the [`IdentityResolver`]
+/// * `committer` - The [`Author`] who committed the commit, as resolved by the
+///   [`IdentityResolver`], e.g., a maintainer who applied a patch, or a forge that merged it
+/// * `author_time` - The time the commit was authored, with the author's offset
+/// * `commit_time` - The time the commit was committed, with the committer's offset
+/// * `message` - The commit message, with invalid UTF-8 replaced
+#[derive(Clone, Debug)]
+pub(crate) struct CommitNode {
+    pub(crate) id: Oid,
+    pub(crate) parents: Vec<Oid>,
+    pub(crate) author: Author,
+    pub(crate) committer: Author,
+    pub(crate) author_time: Time,
+    pub(crate) commit_time: Time,
+    pub(crate) message: String,
+}
+impl CommitNode {
+    pub(crate) fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+    /// Gets the `author_time` as a UTC date, or `None` if it is out of range
+    pub(crate) fn author_date(&self) -> Option<DateTime<Utc>> {
+        DateTime::<Utc>::from_timestamp(self.author_time.seconds(), 0)
+    }
+    /// Gets the `commit_time` as a UTC date, or `None` if it is out of range
+    pub(crate) fn commit_date(&self) -> Option<DateTime<Utc>> {
+        DateTime::<Utc>::from_timestamp(self.commit_time.seconds(), 0)
+    }
+}
+
+/// The paths changed by the commits of a [`CommitGraph`], as counted by its [`MergeStrategy`],
+/// indexed by path
+pub(crate) struct CommitChanges {
+    /// The paths changed by each commit, by its index; `None` for root commits, which add
+    /// rather than change their files, and for merge commits skipped by the strategy
+    changed: Vec<Option<BTreeSet<String>>>,
+    /// The indices of the commits that changed each path
+    by_path: HashMap<String, Vec<usize>>,
+    /// The indices of the root commits whose trees have each path
+    added: HashMap<String, Vec<usize>>,
+    /// The number of commits counted, i.e., without the merge commits skipped by the strategy
+    num_counted: u64,
+    /// The number of commits counted up to and including each commit, by its index, from HEAD
+    counted_through: Vec<u64>,
+}
+impl CommitChanges {
+    /// Gets the paths changed by the commit at `index`, or `None` for a root commit or a
+    /// skipped merge commit
+    pub(crate) fn changed_paths(&self, index: usize) -> Option<&BTreeSet<String>> {
+        self.changed.get(index).and_then(Option::as_ref)
+    }
+    /// Gets the indices of the commits that changed the `path`
+    pub(crate) fn commits_changing(&self, path: &str) -> &[usize] {
+        self.by_path.get(path).map_or(&[], Vec::as_slice)
+    }
+    /// Gets the indices of the root commits that added the `path`
+    pub(crate) fn commits_adding(&self, path: &str) -> &[usize] {
+        self.added.get(path).map_or(&[], Vec::as_slice)
+    }
+    /// Gets the paths added by the root commit at `index`; none for other commits
+    pub(crate) fn paths_added_by(&self, index: usize) -> impl Iterator<Item = &String> {
+        self.added
+            .iter()
+            .filter(move |(_, indices)| indices.contains(&index))
+            .map(|(path, _)| path)
+    }
+    /// Gets the number of commits counted, i.e., without the merge commits skipped by the
+    /// [`MergeStrategy`]
+    pub(crate) fn num_counted_commits(&self) -> u64 {
+        self.num_counted
+    }
+    /// Gets the number of commits counted from HEAD up to and including the commit at `index`,
+    /// i.e., the commit and those after it in the walk
+    pub(crate) fn num_counted_through(&self, index: usize) -> u64 {
+        self.counted_through.get(index).copied().unwrap_or_default()
+    }
+    /// Checks whether the commit at `index` is counted, i.e., isn't a merge commit skipped by
+    /// the [`MergeStrategy`]
+    pub(crate) fn is_counted(&self, index: usize) -> bool {
+        let before = match index {
+            0 => 0,
+            index => self.num_counted_through(index - 1),
+        };
+        self.num_counted_through(index) > before
+    }
+}
+
+/// The commits reachable from HEAD, as walked in a [`HistoryMode`] and within a
+/// [`HistorySampling`], read once per analysis so that the history, contributors, change
+/// frequencies, coupling and ownership are computed from memory, rather than each
+/// walking the history again. The paths each commit changed are only diffed the first time
+/// they are needed, e.g., not if every file's change frequency comes from a baseline.
+pub(crate) struct CommitGraph {
+    repo_path: String,
+    head: Option<Oid>,
+    commits: Vec<CommitNode>,
+    merge_strategy: MergeStrategy,
+    sampled: Option<SampledHistory>,
+    cancellation: CancellationToken,
+    changes: OnceLock<CommitChanges>,
+    building_changes: Mutex<()>,
+    checkpointer: Option<Arc<Checkpointer>>,
+}
+impl CommitGraph {
+    /// Reads the commits reachable from HEAD in the repository at `repo_path`, reporting each
+    /// commit to the [`AnalysisObserver`] and stopping early if the [`CancellationToken`] is
+    /// cancelled. Commits that can't be read are skipped with a warning, and those authored by
+    /// bots are skipped if the [`BotHandling`] of the `identity` is [`BotHandling::Exclude`].
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository
+    /// * `merge_strategy` - The [`MergeStrategy`] the changed paths of merge commits are
+    ///   counted with
+    /// * `history_mode` - The [`HistoryMode`] of the commits walked
+    /// * `sampling` - The [`HistorySampling`] that limits the commits walked
+    /// * `identity` - The [`IdentityOptions`] the authors and committers of the commits are
+    ///   resolved with
+    /// * `observer` - The [`AnalysisObserver`] of the walk
+    /// * `cancellation` - The [`CancellationToken`], which is also checked while the changed
+    ///   paths are diffed
+    pub(crate) fn new(
+        repo_path: &str,
+        merge_strategy: MergeStrategy,
+        history_mode: HistoryMode,
+        sampling: HistorySampling,
+        identity: &IdentityOptions,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<CommitGraph, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let head = HeadState::resolve(&repo)?
+            .get_commit()
+            .and_then(|head| Oid::from_str(head).ok());
+        // A repository with no commits yet, i.e., with an unborn HEAD, has an empty graph
+        let mut revwalk = repo.revwalk()?;
+        head::push_head(&repo, &mut revwalk)?;
+        history_mode.configure(&mut revwalk)?;
+        let oids: Vec<Result<Oid, git2::Error>> = revwalk.collect();
+        let num_reachable = oids.len();
+        let oids = sampling.sample(oids);
+        let num_commits = oids.len();
+        let sampled = sampling.is_limited().then_some(SampledHistory {
+            sampling,
+            num_reachable: to_count(num_reachable),
+            num_walked: to_count(num_commits),
+        });
+        let resolver = IdentityResolver::new(&repo, identity);
+
+        let mut commits: Vec<CommitNode> = Vec::with_capacity(num_commits);
+        for (index, oid) in oids.into_iter().enumerate() {
+            cancellation.check(AnalysisStage::History, || {
+                PartialResults::SourceFiles(vec![])
+            })?;
+            let commit = match oid.and_then(|oid| repo.find_commit(oid)) {
+                Ok(commit) => commit,
+                Err(err) => {
+                    tracing::warn!("Skipping unreadable commit in {}: {}", repo_path, err);
+                    continue;
+                }
+            };
+            let author = commit.author();
+            let resolved = resolver.resolve(&author);
+            observer.on_commit_walked(&commit.id().to_string(), index + 1, num_commits);
+            if resolved.is_bot && identity.bots.handling == BotHandling::Exclude {
+                continue;
+            }
+            commits.push(CommitNode {
+                id: commit.id(),
+                parents: commit.parent_ids().collect(),
+                author: resolved,
+                committer: resolver.resolve(&commit.committer()),
+                author_time: author.when(),
+                commit_time: commit.time(),
+                message: String::from_utf8_lossy(commit.message_bytes()).to_string(),
+            });
+        }
+        Ok(CommitGraph {
+            repo_path: repo_path.to_string(),
+            head,
+            commits,
+            merge_strategy,
+            sampled,
+            cancellation: cancellation.clone(),
+            changes: OnceLock::new(),
+            building_changes: Mutex::new(()),
+            checkpointer: None,
+        })
+    }
+    /// Records the commits diffed in the [`Checkpointer`], and takes those it resumed rather
+    /// than diffing them again
+    pub(crate) fn with_checkpointer(mut self, checkpointer: Arc<Checkpointer>) -> Self {
+        self.checkpointer = Some(checkpointer);
+        self
+    }
+    /// Gets the id of the commit at HEAD, or `None` if HEAD is unborn
+    pub(crate) fn head(&self) -> Option<Oid> {
+        self.head
+    }
+    /// Gets the [`SampledHistory`] of the commits, or `None` if all of them were walked
+    pub(crate) fn sampled(&self) -> Option<&SampledHistory> {
+        self.sampled.as_ref()
+    }
+    /// Gets the [`CommitNode`]s, in the order of a revwalk from HEAD
+    pub(crate) fn commits(&self) -> &[CommitNode] {
+        &self.commits
+    }
+    /// Gets the [`CommitChanges`] of the commits, diffing them the first time they are needed
+    pub(crate) fn changes(&self) -> Result<&CommitChanges, SourceCodeError> {
+        if let Some(changes) = self.changes.get() {
+            return Ok(changes);
+        }
+        // Only one caller diffs the commits; any others wait for its changes
+        let _building = self
+            .building_changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(changes) = self.changes.get() {
+            return Ok(changes);
+        }
+        let changes = self.diff_commits()?;
+        Ok(self.changes.get_or_init(|| changes))
+    }
+    /// Gets the graph of the commits that changed, or as root commits added, the files under
+    /// the `scope` subdirectory, with only the paths under it, e.g., for the history and
+    /// contributors of a package of a monorepo
+    pub(crate) fn scoped(&self, scope: &str) -> Result<CommitGraph, SourceCodeError> {
+        let changes = self.changes()?;
+        let prefix = format!("{}/", scope.trim_end_matches('/'));
+        let in_scope = |path: &&String| path.starts_with(&prefix);
+        let mut added_by_commit: HashMap<usize, Vec<&String>> = HashMap::new();
+        for (path, indices) in changes.added.iter().filter(|(path, _)| in_scope(path)) {
+            for index in indices {
+                added_by_commit.entry(*index).or_default().push(path);
+            }
+        }
+        let mut scoped = CommitChanges {
+            changed: Vec::new(),
+            by_path: HashMap::new(),
+            added: HashMap::new(),
+            num_counted: 0,
+            counted_through: Vec::new(),
+        };
+        let mut commits: Vec<CommitNode> = Vec::new();
+        for (index, node) in self.commits.iter().enumerate() {
+            let changed: Option<BTreeSet<String>> = changes
+                .changed_paths(index)
+                .map(|paths| paths.iter().filter(in_scope).cloned().collect());
+            let added = added_by_commit.remove(&index).unwrap_or_default();
+            if added.is_empty() && changed.as_ref().is_none_or(BTreeSet::is_empty) {
+                continue;
+            }
+            let scoped_index = commits.len();
+            for path in added {
+                scoped
+                    .added
+                    .entry(path.clone())
+                    .or_default()
+                    .push(scoped_index);
+            }
+            for path in changed.iter().flatten() {
+                scoped
+                    .by_path
+                    .entry(path.clone())
+                    .or_default()
+                    .push(scoped_index);
+            }
+            scoped.changed.push(changed);
+            scoped.num_counted += 1;
+            scoped.counted_through.push(scoped.num_counted);
+            commits.push(node.clone());
+        }
+        Ok(CommitGraph {
+            repo_path: self.repo_path.clone(),
+            head: self.head,
+            commits,
+            merge_strategy: self.merge_strategy,
+            sampled: self.sampled.clone(),
+            cancellation: self.cancellation.clone(),
+            changes: OnceLock::from(scoped),
+            building_changes: Mutex::new(()),
+            checkpointer: None,
+        })
+    }
+    /// Diffs each commit for the paths it changed, as counted by the [`MergeStrategy`]
+    fn diff_commits(&self) -> Result<CommitChanges, SourceCodeError> {
+        let repo = Repository::open(&self.repo_path)?;
+        let mut changes = CommitChanges {
+            changed: Vec::with_capacity(self.commits.len()),
+            by_path: HashMap::new(),
+            added: HashMap::new(),
+            num_counted: 0,
+            counted_through: Vec::with_capacity(self.commits.len()),
+        };
+        for (index, node) in self.commits.iter().enumerate() {
+            self.cancellation.check(AnalysisStage::History, || {
+                PartialResults::SourceFiles(vec![])
+            })?;
+            let skipped = node.is_merge() && self.merge_strategy == MergeStrategy::SkipMerges;
+            changes.num_counted += !skipped as u64;
+            changes.counted_through.push(changes.num_counted);
+            let resumed = self
+                .checkpointer
+                .as_ref()
+                .and_then(|checkpointer| checkpointer.resume_commit(node.id));
+            let (changed, added) = match resumed {
+                Some(diffed) => (diffed.changed, diffed.added),
+                None => {
+                    let commit = repo.find_commit(node.id)?;
+                    let mut added: Vec<String> = Vec::new();
+                    if node.parents.is_empty() {
+                        commit
+                            .tree()?
+                            .walk(TreeWalkMode::PreOrder, |directory, entry| {
+                                if entry.kind() == Some(ObjectType::Blob) {
+                                    added.push(format!(
+                                        "{}{}",
+                                        directory,
+                                        entry.name().unwrap_or_default()
+                                    ));
+                                }
+                                TreeWalkResult::Ok
+                            })?;
+                    }
+                    let changed = self.merge_strategy.get_changed_paths(&repo, &commit)?;
+                    if let Some(checkpointer) = &self.checkpointer {
+                        checkpointer.add_commit(node.id, changed.as_ref(), &added);
+                    }
+                    (changed, added)
+                }
+            };
+            for path in added {
+                changes.added.entry(path).or_default().push(index);
+            }
+            for path in changed.iter().flatten() {
+                changes.by_path.entry(path.clone()).or_default().push(index);
+            }
+            changes.changed.push(changed);
+        }
+        Ok(changes)
+    }
+}
@@ -0,0 +1,73 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::SourceCodeError, source::SourceFileInfo};
+
+/// A pattern to search the contents of source files for
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentPattern {
+    /// A substring, matched exactly
+    Literal(String),
+    /// A regular expression, in the syntax of the `regex` crate
+    Regex(String),
+}
+impl ContentPattern {
+    fn compile(&self) -> Result<Regex, SourceCodeError> {
+        let pattern = match self {
+            ContentPattern::Literal(literal) => regex::escape(literal),
+            ContentPattern::Regex(pattern) => pattern.clone(),
+        };
+        Ok(Regex::new(&pattern)?)
+    }
+}
+
+/// A match of a [`ContentPattern`] in the contents of a source file
+///
+/// #Fields:
+/// * `path` - The relative path of the source file
+/// * `line` - The line of the match (1-based)
+/// * `column` - The column of the start of the match, in characters (1-based)
+/// * `matched` - The text matched
+/// * `line_text` - The whole line of the match
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContentMatch {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub matched: String,
+    pub line_text: String,
+}
+impl ContentMatch {
+    /// Finds the matches of the `pattern` in the contents of the `source_files`, line by line,
+    /// so a match never spans lines. Files whose contents weren't kept, e.g., binary files,
+    /// are skipped.
+    ///
+    /// #Returns:
+    /// * The [`ContentMatch`]es, in file order then by position
+    /// * Err([`SourceCodeError::PatternError`]) if the regular expression is invalid
+    pub fn find_all(
+        source_files: &[SourceFileInfo],
+        pattern: &ContentPattern,
+    ) -> Result<Vec<ContentMatch>, SourceCodeError> {
+        let regex = pattern.compile()?;
+        let mut matches: Vec<ContentMatch> = Vec::new();
+        for source_file in source_files {
+            let Some(contents) = source_file.load_contents() else {
+                continue;
+            };
+            for (index, line) in contents.lines().enumerate() {
+                for found in regex.find_iter(line) {
+                    matches.push(ContentMatch {
+                        path: source_file.relative_path.to_string(),
+                        line: index + 1,
+                        column: line[..found.start()].chars().count() + 1,
+                        matched: found.as_str().to_string(),
+                        line_text: line.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(matches)
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "git")]
+use git2::{ErrorCode, Repository, Revwalk};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "git")]
+use crate::errors::SourceCodeError;
+
+/// What HEAD pointed at when a repository was analysed, i.e., the commit and ref the
+/// statistics were computed against
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum HeadState {
+    /// HEAD is on the branch `name`, at the `commit`
+    Branch { name: String, commit: String },
+    /// HEAD is detached at the `commit`, e.g., a tag or commit checked out in CI
+    Detached { commit: String },
+    /// HEAD is on the branch `name`, which has no commits yet, e.g., a freshly initialised
+    /// repository or an orphan branch
+    Unborn { name: String },
+}
+impl HeadState {
+    /// Resolves what HEAD points at in the `repo`
+    #[cfg(feature = "git")]
+    pub fn resolve(repo: &Repository) -> Result<HeadState, SourceCodeError> {
+        let head = match repo.head() {
+            Ok(head) => head,
+            Err(err) if err.code() == ErrorCode::UnbornBranch => {
+                let head = repo.find_reference("HEAD")?;
+                let name = head
+                    .symbolic_target()
+                    .map(|target| target.trim_start_matches("refs/heads/").to_string())
+                    .unwrap_or_default();
+                return Ok(HeadState::Unborn { name });
+            }
+            Err(err) => return Err(SourceCodeError::for_reference("HEAD")(err)),
+        };
+        let commit = head
+            .peel_to_commit()
+            .map_err(SourceCodeError::for_reference("HEAD"))?
+            .id()
+            .to_string();
+        match repo.head_detached()? {
+            true => Ok(HeadState::Detached { commit }),
+            false => Ok(HeadState::Branch {
+                name: head.shorthand().unwrap_or_default().to_string(),
+                commit,
+            }),
+        }
+    }
+    /// Gets the id of the commit at HEAD, or `None` if HEAD is unborn
+    pub fn get_commit(&self) -> Option<&str> {
+        match self {
+            HeadState::Branch { commit, .. } | HeadState::Detached { commit } => Some(commit),
+            HeadState::Unborn { .. } => None,
+        }
+    }
+}
+
+/// Pushes HEAD onto the `revwalk`, whether HEAD is on a branch or detached. Nothing is pushed
+/// if HEAD is unborn, so the walk is empty.
+#[cfg(feature = "git")]
+pub(crate) fn push_head(
+    repo: &Repository,
+    revwalk: &mut Revwalk<'_>,
+) -> Result<(), SourceCodeError> {
+    match HeadState::resolve(repo)? {
+        HeadState::Unborn { .. } => Ok(()),
+        _ => Ok(revwalk.push_head()?),
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(feature = "git")]
+use chrono::{DateTime, Datelike, FixedOffset, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// The number of commits by day of the week and hour of the day, in the local time of each
+/// commit's author, e.g., to show when a repository's contributors work
+///
+/// #Fields:
+/// * `num_commits` - The number of commits counted
+/// * `commits` - The number of commits by day of the week, from Monday (0) to Sunday (6), and
+///   by hour of the day (0 - 23), i.e., `commits[day][hour]`
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommitHeatmap {
+    pub num_commits: u64,
+    pub commits: [[u64; 24]; 7],
+}
+impl CommitHeatmap {
+    /// Counts a commit at the (author) `time`, in the time zone of its offset
+    #[cfg(feature = "git")]
+    pub fn add(&mut self, time: git2::Time) {
+        let Some(date) = FixedOffset::east_opt(time.offset_minutes() * 60).and_then(|offset| {
+            DateTime::from_timestamp(time.seconds(), 0).map(|date| date.with_timezone(&offset))
+        }) else {
+            return;
+        };
+        self.num_commits += 1;
+        self.commits[date.weekday().num_days_from_monday() as usize][date.hour() as usize] += 1;
+    }
+    /// Adds the commits counted by the `other` heatmap, e.g., of another repository
+    pub(crate) fn merge(&mut self, other: &CommitHeatmap) {
+        self.num_commits = self.num_commits.saturating_add(other.num_commits);
+        for (day, other_day) in self.commits.iter_mut().zip(other.commits.iter()) {
+            for (hour, other_hour) in day.iter_mut().zip(other_day.iter()) {
+                *hour = hour.saturating_add(*other_hour);
+            }
+        }
+    }
+}
@@ -0,0 +1,582 @@
+use chrono::{DateTime, Datelike, Utc};
+#[cfg(feature = "git")]
+use git2::{ObjectType, Oid, Repository, Revwalk, Sort, TreeWalkMode, TreeWalkResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "git")]
+use std::{collections::HashMap, path::Path};
+#[cfg(feature = "git")]
+use tokei::{Config, LanguageType};
+
+#[cfg(feature = "git")]
+use crate::{
+    cancel::CancellationToken,
+    data::to_count,
+    graph::CommitGraph,
+    head,
+    identity::IdentityOptions,
+    messages::CommitMessageCounter,
+    observer::NoopObserver,
+    source::{ContentEncoding, MergeStrategy},
+    walk::VENDORED_DIRS,
+};
+use crate::{
+    errors::SourceCodeError, heatmap::CommitHeatmap, messages::CommitMessageStatistics,
+    ratio::Percentage,
+};
+
+/// Which commits reachable from HEAD are walked for the history, contributors and change
+/// frequencies
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryMode {
+    /// Every commit reachable from HEAD, including those of merged branches
+    #[default]
+    AllCommits,
+    /// Only the commits on the first-parent line from HEAD, i.e., the linear history of the
+    /// main branch, where each merged branch counts as its merge commit, so that repositories
+    /// with heavy merge traffic don't count the work of a branch twice
+    FirstParent,
+}
+impl HistoryMode {
+    /// Configures the `revwalk` to walk the commits of the mode
+    #[cfg(feature = "git")]
+    pub(crate) fn configure(self, revwalk: &mut Revwalk<'_>) -> Result<(), SourceCodeError> {
+        if self == HistoryMode::FirstParent {
+            revwalk.simplify_first_parent()?;
+        }
+        Ok(())
+    }
+}
+
+/// The number of commits reachable from HEAD in each [`HistoryMode`], e.g., to see how much
+/// of the history is on merged branches
+///
+/// #Fields:
+/// * `all_commits` - The number of commits with [`HistoryMode::AllCommits`]
+/// * `first_parent` - The number of commits with [`HistoryMode::FirstParent`]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommitCounts {
+    pub all_commits: u64,
+    pub first_parent: u64,
+}
+impl CommitCounts {
+    /// Counts the commits reachable from HEAD of the repository at `repo_path` in each
+    /// [`HistoryMode`]; both are zero if HEAD is unborn
+    #[cfg(feature = "git")]
+    pub fn new(repo_path: &str) -> Result<CommitCounts, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let count = |history_mode: HistoryMode| -> Result<u64, SourceCodeError> {
+            let mut revwalk = repo.revwalk()?;
+            head::push_head(&repo, &mut revwalk)?;
+            history_mode.configure(&mut revwalk)?;
+            Ok(to_count(revwalk.count()))
+        };
+        Ok(CommitCounts {
+            all_commits: count(HistoryMode::AllCommits)?,
+            first_parent: count(HistoryMode::FirstParent)?,
+        })
+    }
+}
+
+/// The limits on the commits walked for the history, contributors and change frequencies, to
+/// trade accuracy for speed on repositories with hundreds of thousands of commits. The commits
+/// are sampled at the `sample_rate` across the whole history first, then the most recent
+/// `max_commits` of them are walked.
+///
+/// #Fields:
+/// * `max_commits` - The maximum number of commits walked, or `None` for no maximum
+/// * `sample_rate` - The fraction of the commits walked, spread evenly along the history, from
+///   0 (exclusive) to 1, or `None` for all of them
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct HistorySampling {
+    pub max_commits: Option<usize>,
+    pub sample_rate: Option<f64>,
+}
+impl HistorySampling {
+    /// Walks at most `max_commits` commits
+    pub fn with_max_commits(mut self, max_commits: usize) -> Self {
+        self.max_commits = Some(max_commits);
+        self
+    }
+    /// Walks the `sample_rate` fraction of the commits, e.g., 0.1 for every tenth commit
+    pub fn with_sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+    /// Checks whether any commits are left out, i.e., either limit is set
+    pub fn is_limited(&self) -> bool {
+        self.max_commits.is_some() || self.sample_rate.is_some_and(|rate| rate < 1.0)
+    }
+    /// Checks that the `sample_rate` is more than 0 and at most 1
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if it isn't
+    pub fn validate(&self) -> Result<(), SourceCodeError> {
+        match self.sample_rate {
+            Some(rate) if !(rate > 0.0 && rate <= 1.0) => Err(SourceCodeError::ConfigError(
+                format!("the sample rate {} is not more than 0 and at most 1", rate),
+            )),
+            _ => Ok(()),
+        }
+    }
+    /// Keeps the `commits`, in the order of a revwalk from HEAD, within the limits: every
+    /// `1 / sample_rate`-th commit, starting with HEAD, then the first `max_commits` of those
+    pub(crate) fn sample<T>(&self, commits: Vec<T>) -> Vec<T> {
+        let rate = self.sample_rate.unwrap_or(1.0).clamp(0.0, 1.0);
+        let mut sampled: Vec<T> = match rate < 1.0 {
+            true => commits
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| {
+                    (*index as f64 * rate).ceil() != ((*index + 1) as f64 * rate).ceil()
+                })
+                .map(|(_, commit)| commit)
+                .collect(),
+            false => commits,
+        };
+        if let Some(max_commits) = self.max_commits {
+            sampled.truncate(max_commits);
+        }
+        sampled
+    }
+}
+
+/// The [`HistorySampling`] applied to an analysis, and how many of the commits it walked, so
+/// that statistics computed from part of the history aren't taken for the whole
+///
+/// #Fields:
+/// * `sampling` - The [`HistorySampling`] applied
+/// * `num_reachable` - The number of commits reachable from HEAD, in the [`HistoryMode`] walked
+/// * `num_walked` - The number of those commits walked
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SampledHistory {
+    pub sampling: HistorySampling,
+    pub num_reachable: u64,
+    pub num_walked: u64,
+}
+
+/// A summary of the commit history reachable from HEAD
+///
+/// #Fields:
+/// * `head` - The id of the commit at HEAD
+/// * `num_commits` - The number of commits reachable from HEAD, in the [`HistoryMode`] walked
+///   and within the [`HistorySampling`], if any
+/// * `first_commit` - The (author) date of the oldest commit
+/// * `last_commit` - The (author) date of the newest commit
+/// * `commit_messages` - The [`CommitMessageStatistics`] of the commits' messages, e.g., the
+///   number of fixes and features
+/// * `commit_times` - The [`CommitHeatmap`] of the commits, by the author's local day and hour
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommitHistory {
+    pub head: String,
+    pub num_commits: u64,
+    pub first_commit: DateTime<Utc>,
+    pub last_commit: DateTime<Utc>,
+    #[serde(default)]
+    pub commit_messages: CommitMessageStatistics,
+    #[serde(default)]
+    pub commit_times: CommitHeatmap,
+}
+impl CommitHistory {
+    /// Gets the [`CommitHistory`] of the repository at `repo_path`
+    ///
+    /// #Returns:
+    /// * The [`CommitHistory`], or `None` if the repository has no commits yet
+    #[cfg(feature = "git")]
+    pub fn new(repo_path: &str) -> Result<Option<CommitHistory>, SourceCodeError> {
+        let graph = CommitGraph::new(
+            repo_path,
+            MergeStrategy::default(),
+            HistoryMode::default(),
+            HistorySampling::default(),
+            &IdentityOptions::default(),
+            &NoopObserver,
+            &CancellationToken::new(),
+        )?;
+        Ok(Self::from_graph(&graph))
+    }
+    /// Gets the [`CommitHistory`] of the commits of the [`CommitGraph`], or `None` if it has
+    /// no HEAD commit
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(graph: &CommitGraph) -> Option<CommitHistory> {
+        let head = graph.head()?.to_string();
+        let mut num_commits: u64 = 0;
+        let mut first_commit = DateTime::<Utc>::MAX_UTC;
+        let mut last_commit = DateTime::<Utc>::MIN_UTC;
+        let mut commit_messages = CommitMessageCounter::default();
+        let mut commit_times = CommitHeatmap::default();
+        for commit in graph.commits() {
+            num_commits += 1;
+            commit_messages.add(&commit.message);
+            commit_times.add(commit.author_time);
+            if let Some(date) = commit.author_date() {
+                first_commit = first_commit.min(date);
+                last_commit = last_commit.max(date);
+            }
+        }
+        Some(CommitHistory {
+            head,
+            num_commits,
+            first_commit,
+            last_commit,
+            commit_messages: commit_messages.get_statistics(),
+            commit_times,
+        })
+    }
+}
+
+/// The calendar periods a [`RepositoryTrend`] is sampled by, in UTC
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TrendInterval {
+    Month,
+    Quarter,
+    #[default]
+    Year,
+}
+impl TrendInterval {
+    /// Gets the label of the period the `date` is in, e.g., '2024' for a year, '2024-Q2' for a
+    /// quarter or '2024-06' for a month
+    pub fn get_period(&self, date: &DateTime<Utc>) -> String {
+        match self {
+            TrendInterval::Month => format!("{}-{:02}", date.year(), date.month()),
+            TrendInterval::Quarter => format!("{}-Q{}", date.year(), date.month0() / 3 + 1),
+            TrendInterval::Year => date.year().to_string(),
+        }
+    }
+}
+
+/// A time series of the size of a repository, sampled along its (first-parent) history
+///
+/// #Fields:
+/// * `sample_every_n_commits` - The sampling interval used to compute the series, or `0` if
+///   it was sampled by `interval`
+/// * `interval` - The [`TrendInterval`] the series was sampled by, if it was
+/// * `points` - The [`TrendPoint`]s, ordered from the oldest to the newest commit
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RepositoryTrend {
+    pub sample_every_n_commits: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<TrendInterval>,
+    pub points: Vec<TrendPoint>,
+}
+impl RepositoryTrend {
+    /// Computes the [`RepositoryTrend`] for the repository at `repo_path` by reading the tree
+    /// of every `sample_every_n_commits`-th commit along the first-parent history of HEAD.
+    /// The working tree is never checked out; blobs are read from the object database.
+    /// The first and the HEAD commit are always sampled.
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository
+    /// * `sample_every_n_commits` - The sampling interval; `0` is treated as `1`
+    #[cfg(feature = "git")]
+    pub fn compute(
+        repo_path: &str,
+        sample_every_n_commits: usize,
+    ) -> Result<RepositoryTrend, SourceCodeError> {
+        let sample_every_n_commits = sample_every_n_commits.max(1);
+        let repo: Repository = Repository::open(repo_path)?;
+        let commit_ids = Self::get_first_parent_commits(&repo)?;
+        let last = commit_ids.len().saturating_sub(1);
+        let sampled: Vec<Oid> = commit_ids
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| index % sample_every_n_commits == 0 || *index == last)
+            .map(|(_, commit_id)| *commit_id)
+            .collect();
+
+        Ok(RepositoryTrend {
+            sample_every_n_commits,
+            interval: None,
+            points: Self::get_points(&repo, &sampled)?,
+        })
+    }
+    /// Computes the [`RepositoryTrend`] for the repository at `repo_path` by reading the tree
+    /// of the last commit of each period of the `interval`, e.g., of each year, along the
+    /// first-parent history of HEAD, by the commits' author dates; periods without commits have
+    /// no point. The HEAD commit is always sampled.
+    #[cfg(feature = "git")]
+    pub fn compute_by_interval(
+        repo_path: &str,
+        interval: TrendInterval,
+    ) -> Result<RepositoryTrend, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+        let commit_ids = Self::get_first_parent_commits(&repo)?;
+        let periods: Vec<String> = commit_ids
+            .iter()
+            .map(|commit_id| {
+                let time = repo.find_commit(*commit_id)?.author().when();
+                let date = DateTime::<Utc>::from_timestamp(time.seconds(), 0).unwrap_or_default();
+                Ok(interval.get_period(&date))
+            })
+            .collect::<Result<_, git2::Error>>()?;
+        let sampled: Vec<Oid> = commit_ids
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| periods.get(index + 1) != periods.get(*index))
+            .map(|(_, commit_id)| *commit_id)
+            .collect();
+
+        Ok(RepositoryTrend {
+            sample_every_n_commits: 0,
+            interval: Some(interval),
+            points: Self::get_points(&repo, &sampled)?,
+        })
+    }
+    /// Gets the ids of the commits on the first-parent history of HEAD, from the oldest
+    #[cfg(feature = "git")]
+    fn get_first_parent_commits(repo: &Repository) -> Result<Vec<Oid>, SourceCodeError> {
+        let mut revwalk: Revwalk<'_> = repo.revwalk()?;
+        head::push_head(repo, &mut revwalk)?;
+        revwalk.simplify_first_parent()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        Ok(revwalk.collect::<Result<Vec<Oid>, git2::Error>>()?)
+    }
+    /// Gets the [`TrendPoint`]s of the `sampled` commits, reading each blob once per language
+    /// it is counted as
+    #[cfg(feature = "git")]
+    fn get_points(repo: &Repository, sampled: &[Oid]) -> Result<Vec<TrendPoint>, SourceCodeError> {
+        let config = Config::default();
+        let mut blob_cache: HashMap<(LanguageType, Oid), Option<u64>> = HashMap::new();
+        let mut points: Vec<TrendPoint> = Vec::new();
+        for commit_id in sampled {
+            let commit = repo.find_commit(*commit_id)?;
+            points.push(TrendPoint::new_from(
+                repo,
+                &commit,
+                &config,
+                &mut blob_cache,
+            )?);
+        }
+        Ok(points)
+    }
+    /// Gets the [`LanguageTrend`] of the language mix over the points of the trend
+    pub fn get_language_trend(&self) -> LanguageTrend {
+        LanguageTrend::new(self)
+    }
+    /// Gets the [`RepositoryTrend`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
+
+/// The language mix of a repository over its history, as a time series of each language, e.g.,
+/// to track a migration from one language to another
+///
+/// #Fields:
+/// * `interval` - The [`TrendInterval`] of the [`RepositoryTrend`] the series are of, if it was
+///   sampled by one
+/// * `periods` - The label of the period of each point, e.g., '2024', see
+///   [`TrendInterval::get_period`], or else the short id of its commit
+/// * `dates` - The (author) date of the commit of each point
+/// * `languages` - The [`LanguageSeries`] of each language, by name
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LanguageTrend {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<TrendInterval>,
+    pub periods: Vec<String>,
+    pub dates: Vec<DateTime<Utc>>,
+    pub languages: Vec<LanguageSeries>,
+}
+impl LanguageTrend {
+    /// Gets the [`LanguageTrend`] of the points of the `trend`; a language has 0 lines of code
+    /// at the points it isn't in
+    pub fn new(trend: &RepositoryTrend) -> LanguageTrend {
+        let periods = trend
+            .points
+            .iter()
+            .map(|point| match trend.interval {
+                Some(interval) => interval.get_period(&point.date),
+                None => point.commit_id.chars().take(7).collect(),
+            })
+            .collect();
+        let names: BTreeSet<&String> = trend
+            .points
+            .iter()
+            .flat_map(|point| point.languages.keys())
+            .collect();
+        let languages = names
+            .into_iter()
+            .map(|name| {
+                let loc: Vec<u64> = trend
+                    .points
+                    .iter()
+                    .map(|point| point.languages.get(name).copied().unwrap_or_default())
+                    .collect();
+                let share = trend
+                    .points
+                    .iter()
+                    .zip(&loc)
+                    .map(|(point, loc)| {
+                        Percentage::of(*loc as f64, point.loc as f64).unwrap_or_default()
+                    })
+                    .collect();
+                LanguageSeries {
+                    name: name.clone(),
+                    loc,
+                    share,
+                }
+            })
+            .collect();
+        LanguageTrend {
+            interval: trend.interval,
+            periods,
+            dates: trend.points.iter().map(|point| point.date).collect(),
+            languages,
+        }
+    }
+    /// Computes the [`LanguageTrend`] of the repository at `repo_path`, sampled by the
+    /// `interval`, see [`RepositoryTrend::compute_by_interval`]
+    #[cfg(feature = "git")]
+    pub fn compute(
+        repo_path: &str,
+        interval: TrendInterval,
+    ) -> Result<LanguageTrend, SourceCodeError> {
+        Ok(RepositoryTrend::compute_by_interval(repo_path, interval)?.get_language_trend())
+    }
+    /// Gets the [`LanguageSeries`] of the language `name`, if it is in any of the points
+    pub fn get_language(&self, name: &str) -> Option<&LanguageSeries> {
+        self.languages.iter().find(|language| language.name == name)
+    }
+    /// Gets the [`LanguageTrend`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
+
+/// The time series of a language in a [`LanguageTrend`], with a value for each of its points
+///
+/// #Fields:
+/// * `name` - The name of the language
+/// * `loc` - The lines of code in the language at each point
+/// * `share` - The [`Percentage`] of the lines of code of the repository in the language at
+///   each point
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LanguageSeries {
+    pub name: String,
+    pub loc: Vec<u64>,
+    pub share: Vec<Percentage>,
+}
+
+/// The size of the repository at a single commit
+///
+/// #Fields:
+/// * `commit_id` - The id of the sampled commit
+/// * `date` - The commit (author) date
+/// * `loc` - The total lines of code across all recognised source files
+/// * `num_files` - The number of recognised source files
+/// * `languages` - The lines of code per language name
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TrendPoint {
+    pub commit_id: String,
+    pub date: DateTime<Utc>,
+    pub loc: u64,
+    pub num_files: u64,
+    pub languages: BTreeMap<String, u64>,
+}
+#[cfg(feature = "git")]
+impl TrendPoint {
+    fn new_from(
+        repo: &Repository,
+        commit: &git2::Commit<'_>,
+        config: &Config,
+        blob_cache: &mut HashMap<(LanguageType, Oid), Option<u64>>,
+    ) -> Result<Self, SourceCodeError> {
+        let tree = commit.tree()?;
+        let mut blobs: Vec<(String, Oid)> = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            let name = entry.name().unwrap_or_default();
+            match entry.kind() {
+                Some(ObjectType::Tree) if VENDORED_DIRS.contains(&name) => TreeWalkResult::Skip,
+                Some(ObjectType::Blob) => {
+                    blobs.push((format!("{}{}", root, name), entry.id()));
+                    TreeWalkResult::Ok
+                }
+                _ => TreeWalkResult::Ok,
+            }
+        })?;
+
+        let mut loc: u64 = 0;
+        let mut num_files: u64 = 0;
+        let mut languages: BTreeMap<String, u64> = BTreeMap::new();
+        for (path, blob_id) in blobs {
+            let Some(language) = LanguageType::from_path(Path::new(&path), config) else {
+                continue;
+            };
+            // The same blob may be at paths of different languages, e.g., an empty file
+            let code = match blob_cache.get(&(language, blob_id)) {
+                Some(code) => *code,
+                None => {
+                    let code = Self::get_blob_code(repo, language, blob_id, config)?;
+                    blob_cache.insert((language, blob_id), code);
+                    code
+                }
+            };
+            if let Some(code) = code {
+                loc = loc.saturating_add(code);
+                num_files += 1;
+                let language_loc = languages.entry(language.name().to_string()).or_insert(0);
+                *language_loc = language_loc.saturating_add(code);
+            }
+        }
+
+        let time = commit.author().when();
+        Ok(Self {
+            commit_id: commit.id().to_string(),
+            date: DateTime::<Utc>::from_timestamp(time.seconds(), 0).unwrap_or_default(),
+            loc,
+            num_files,
+            languages,
+        })
+    }
+    /// Gets the lines of code of a blob in the `language`, or `None` if the blob is binary
+    fn get_blob_code(
+        repo: &Repository,
+        language: LanguageType,
+        blob_id: Oid,
+        config: &Config,
+    ) -> Result<Option<u64>, SourceCodeError> {
+        let blob = repo.find_blob(blob_id)?;
+        let Some(encoding) = ContentEncoding::detect(blob.content()) else {
+            return Ok(None);
+        };
+        let stats = language.parse_from_slice(encoding.decode(blob.content().to_vec()), config);
+        Ok(Some(to_count(stats.code)))
+    }
+}
+
+#[cfg(all(test, feature = "git"))]
+mod tests {
+    use super::*;
+    use crate::testing::FixtureRepo;
+
+    #[test]
+    fn counts_a_blob_shared_by_paths_of_different_languages_in_each() -> Result<(), SourceCodeError>
+    {
+        let fixture = FixtureRepo::new()?
+            .with_file("settings.py", "x = 1\n")
+            .with_file("settings.rb", "x = 1\n")
+            .with_file("settings", "x = 1\n")
+            .commit("Add the settings")?;
+        let trend = RepositoryTrend::compute(&fixture.path().to_string_lossy(), 1)?;
+
+        let point = &trend.points[0];
+        assert_eq!(point.num_files, 2);
+        assert_eq!(point.loc, 2);
+        assert_eq!(
+            point.languages,
+            BTreeMap::from([("Python".to_string(), 1), ("Ruby".to_string(), 1)])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+//! Git hooks that analyse only the files of the pending change, and check them against a
+//! [`Policy`], for fast local feedback, e.g., before pushing. The crate has no binary of its own,
+//! so a hook runs a command of the integrating application, which calls [`run_hook_mode`].
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    errors::SourceCodeError,
+    options::AnalysisOptions,
+    policy::{Policy, PolicyReport, PolicySeverity},
+    repository::RepositoryInfo,
+    review::ReviewTarget,
+};
+
+/// The line that marks a hook as installed by [`install`], so that other hooks aren't replaced
+const HOOK_MARKER: &str = "# Installed by repository_statistics";
+
+/// The git hooks that can be installed
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum GitHook {
+    /// Runs before the commits are pushed, on the files they change since the upstream branch;
+    /// a failing check stops the push
+    PrePush,
+    /// Runs after each commit, on the files it changed; the commit is kept either way
+    PostCommit,
+}
+impl GitHook {
+    /// Gets the file name of the hook in the hooks directory, e.g., 'pre-push'
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            GitHook::PrePush => "pre-push",
+            GitHook::PostCommit => "post-commit",
+        }
+    }
+}
+
+/// The result of a hook run by [`run_hook_mode`]
+///
+/// #Fields:
+/// * `hook` - The [`GitHook`] run
+/// * `changed_files` - The relative paths of the files of the pending change, including those it
+///   deletes
+/// * `policy` - The [`PolicyReport`] of the analysis of the changed files, which has no
+///   violations if no files changed
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HookReport {
+    pub hook: GitHook,
+    pub changed_files: Vec<String>,
+    pub policy: PolicyReport,
+}
+impl HookReport {
+    /// Gets the exit code of the hook: 1 if there are [`PolicySeverity::Error`] violations, which
+    /// stops a push, or else 0
+    pub fn exit_code(&self) -> i32 {
+        match self.policy.passes(PolicySeverity::Error) {
+            true => 0,
+            false => 1,
+        }
+    }
+    /// Gets the [`HookReport`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
+
+/// Installs the `hook` in the repository at `repo_path`, in its hooks directory, or that of its
+/// `core.hooksPath`, replacing a hook installed before by this function
+///
+/// #Arguments:
+/// * `repo_path` - The path to the repository
+/// * `hook` - The [`GitHook`] to install
+/// * `command` - The shell command the hook runs, e.g., a binary of the integrating application
+///   that calls [`run_hook_mode`] and exits with the [`HookReport::exit_code`]; it is passed the
+///   hook's file name, then the arguments git passes the hook
+///
+/// #Returns:
+/// * The path of the installed hook
+/// * Err([`SourceCodeError::ConfigError`]) if the repository already has another such hook
+pub fn install(repo_path: &str, hook: GitHook, command: &str) -> Result<PathBuf, SourceCodeError> {
+    let path = get_hooks_dir(repo_path)?.join(hook.file_name());
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if !existing.contains(HOOK_MARKER) {
+            return Err(SourceCodeError::ConfigError(format!(
+                "{} already exists, and wasn't installed by repository_statistics",
+                path.display()
+            )));
+        }
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(SourceCodeError::for_path(parent))?;
+    }
+    let script = format!(
+        "#!/bin/sh\n{}; uninstall with repository_statistics::hooks::uninstall\nexec {} {} \"$@\"\n",
+        HOOK_MARKER,
+        command,
+        hook.file_name()
+    );
+    fs::write(&path, script).map_err(SourceCodeError::for_path(&path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(SourceCodeError::for_path(&path))?;
+    }
+    tracing::info!(
+        "Installed the {} hook at {}",
+        hook.file_name(),
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Removes the `hook` from the repository at `repo_path`, if it was installed by [`install`];
+/// other hooks are left as they are
+///
+/// #Returns:
+/// * Whether the hook was removed
+pub fn uninstall(repo_path: &str, hook: GitHook) -> Result<bool, SourceCodeError> {
+    let path = get_hooks_dir(repo_path)?.join(hook.file_name());
+    match fs::read_to_string(&path) {
+        Ok(existing) if existing.contains(HOOK_MARKER) => {
+            fs::remove_file(&path).map_err(SourceCodeError::for_path(&path))?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Runs the `hook`: analyses only the source files of the pending change in the repository at
+/// `repo_path`, with the [`AnalysisOptions`] restricted to them, and evaluates the [`Policy`] on
+/// the analysis. The per-file rules are only checked on the changed files, while the rules of
+/// the repository, e.g., its bus factor, still cover its whole history.
+///
+/// The pending change of a [`GitHook::PrePush`] is the commits since the merge base with the
+/// upstream branch, or the default branch of 'origin' if there is no upstream, or else the last
+/// commit; that of a [`GitHook::PostCommit`] is the last commit.
+///
+/// #Returns:
+/// * The [`HookReport`], whose [`HookReport::exit_code`] the hook should exit with
+pub fn run_hook_mode(
+    repo_path: &str,
+    hook: GitHook,
+    policy: &Policy,
+    options: &AnalysisOptions,
+) -> Result<HookReport, SourceCodeError> {
+    let changed_files = get_pending_changes(repo_path, hook)?;
+    if changed_files.is_empty() {
+        return Ok(HookReport {
+            hook,
+            changed_files,
+            policy: PolicyReport {
+                num_rules: policy.rules.len(),
+                ..Default::default()
+            },
+        });
+    }
+    let included: Vec<&str> = changed_files.iter().map(String::as_str).collect();
+    let options = options.clone().with_included(&included);
+    let name = Path::new(repo_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| repo_path.to_string());
+    let repository_info = RepositoryInfo::new_with_options(name, repo_path, &options)?;
+    Ok(HookReport {
+        hook,
+        changed_files,
+        policy: policy.evaluate(&repository_info),
+    })
+}
+
+/// Gets the relative paths of the files of the pending change of the `hook`, see
+/// [`run_hook_mode`]
+fn get_pending_changes(repo_path: &str, hook: GitHook) -> Result<Vec<String>, SourceCodeError> {
+    let repo = Repository::open(repo_path)?;
+    let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else {
+        // No commits yet, so nothing to check
+        return Ok(vec![]);
+    };
+    let upstream = match hook {
+        GitHook::PrePush => ["HEAD@{upstream}", "refs/remotes/origin/HEAD"]
+            .iter()
+            .find_map(|upstream| repo.revparse_single(upstream).ok())
+            .and_then(|upstream| repo.merge_base(upstream.id(), head.id()).ok()),
+        GitHook::PostCommit => None,
+    };
+    let base = match upstream {
+        Some(base) => base,
+        None => match head.parent_id(0) {
+            Ok(parent) => parent,
+            // A root commit changes all of its files
+            Err(_) => {
+                let mut paths: Vec<String> = vec![];
+                head.tree()?
+                    .walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+                        if entry.kind() == Some(git2::ObjectType::Blob) {
+                            paths.push(format!("{}{}", root, entry.name().unwrap_or_default()));
+                        }
+                        git2::TreeWalkResult::Ok
+                    })?;
+                return Ok(paths);
+            }
+        },
+    };
+    ReviewTarget::Refs {
+        base: base.to_string(),
+        head: head.id().to_string(),
+    }
+    .get_changed_files(repo_path)
+}
+
+/// Gets the hooks directory of the repository at `repo_path`: its `core.hooksPath`, relative to
+/// the working tree, if set, or else the 'hooks' directory of the git directory
+fn get_hooks_dir(repo_path: &str) -> Result<PathBuf, SourceCodeError> {
+    let repo = Repository::open(repo_path)?;
+    let configured = repo.config()?.get_path("core.hooksPath").ok();
+    Ok(match configured {
+        Some(hooks_path) if hooks_path.is_absolute() => hooks_path,
+        Some(hooks_path) => repo
+            .workdir()
+            .unwrap_or_else(|| repo.path())
+            .join(hooks_path),
+        None => repo.path().join("hooks"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FixtureRepo;
+
+    #[test]
+    fn refuses_to_replace_a_foreign_hook() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("README.md", "# Example\n")
+            .commit("Add the README")?;
+        let repo_path = fixture.path().to_string_lossy().to_string();
+        let foreign = fixture.repository().path().join("hooks").join("pre-push");
+        fs::create_dir_all(foreign.parent().unwrap_or(&foreign))
+            .map_err(SourceCodeError::for_path(&foreign))?;
+        fs::write(&foreign, "#!/bin/sh\nexit 0\n").map_err(SourceCodeError::for_path(&foreign))?;
+
+        let result = install(&repo_path, GitHook::PrePush, "app");
+        assert!(matches!(result, Err(SourceCodeError::ConfigError(_))));
+        assert!(!uninstall(&repo_path, GitHook::PrePush)?);
+        assert_eq!(
+            fs::read_to_string(&foreign).map_err(SourceCodeError::for_path(&foreign))?,
+            "#!/bin/sh\nexit 0\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_and_uninstalls_its_own_hook() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("README.md", "# Example\n")
+            .commit("Add the README")?;
+        let repo_path = fixture.path().to_string_lossy().to_string();
+
+        install(&repo_path, GitHook::PostCommit, "old-app")?;
+        let path = install(&repo_path, GitHook::PostCommit, "new-app")?;
+        let script = fs::read_to_string(&path).map_err(SourceCodeError::for_path(&path))?;
+        assert!(script.contains("exec new-app post-commit \"$@\""));
+        assert!(uninstall(&repo_path, GitHook::PostCommit)?);
+        assert!(!path.exists());
+        Ok(())
+    }
+}
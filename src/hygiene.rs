@@ -0,0 +1,298 @@
+use chrono::{DateTime, Utc};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{errors::SourceCodeError, objects::ObjectStoreStatistics, paths, retry::retry_on_lock};
+
+/// Stashes older than this are flagged as forgotten
+pub const FORGOTTEN_STASH_DAYS: i64 = 30;
+/// The client-side hooks that git runs
+const HOOK_NAMES: [&str; 18] = [
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-commit",
+    "pre-merge-commit",
+    "prepare-commit-msg",
+    "commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-push",
+    "post-rewrite",
+    "pre-auto-gc",
+    "push-to-checkout",
+    "reference-transaction",
+    "fsmonitor-watchman",
+    "sendemail-validate",
+];
+/// Hook frameworks and the (committed) configuration files or directories that mark them
+const HOOK_FRAMEWORKS: [(&str, &str); 6] = [
+    ("husky", ".husky"),
+    ("pre-commit", ".pre-commit-config.yaml"),
+    ("lefthook", "lefthook.yml"),
+    ("lefthook", "lefthook.yaml"),
+    ("lefthook", ".lefthook.yml"),
+    ("overcommit", ".overcommit.yml"),
+];
+
+/// A report on the local state of a developer's clone, i.e., state that is not pushed or shared
+///
+/// #Fields:
+/// * `stashes` - The [`StashEntry`]s in the clone, newest first
+/// * `hooks` - The [`HookInventory`] of the clone
+/// * `objects` - The [`ObjectStoreStatistics`] of the clone, including its unreachable objects
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct HygieneReport {
+    pub stashes: Vec<StashEntry>,
+    pub hooks: HookInventory,
+    pub objects: ObjectStoreStatistics,
+}
+impl HygieneReport {
+    /// Creates the [`HygieneReport`] for the clone at `repo_path`
+    pub fn new(repo_path: &str) -> Result<HygieneReport, SourceCodeError> {
+        Ok(HygieneReport {
+            stashes: StashEntry::inventory(repo_path, FORGOTTEN_STASH_DAYS)?,
+            hooks: HookInventory::new(repo_path)?,
+            objects: ObjectStoreStatistics::new(repo_path)?,
+        })
+    }
+    /// Gets the [`HygieneReport`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
+
+/// A stash in the clone
+///
+/// #Fields:
+/// * `index` - The position of the stash, i.e., `stash@{index}`
+/// * `message` - The stash message, e.g., 'WIP on main: 1a2b3c4 Fix the thing'
+/// * `created` - The date and time the stash was created
+/// * `age_days` - The age of the stash in days
+/// * `touched_paths` - The relative paths changed in the stash, including stashed untracked files
+/// * `forgotten` - Whether the stash is older than the forgotten threshold
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct StashEntry {
+    pub index: usize,
+    pub message: String,
+    pub created: DateTime<Utc>,
+    pub age_days: i64,
+    pub touched_paths: Vec<String>,
+    pub forgotten: bool,
+}
+impl StashEntry {
+    /// Enumerates the stashes of the clone at `repo_path`
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository
+    /// * `forgotten_after_days` - The age in days after which a stash is flagged as forgotten
+    pub fn inventory(
+        repo_path: &str,
+        forgotten_after_days: i64,
+    ) -> Result<Vec<StashEntry>, SourceCodeError> {
+        let mut repo: Repository = Repository::open(repo_path)?;
+        let mut stashes: Vec<(usize, String, Oid)> = Vec::new();
+        repo.stash_foreach(|index, message, stash_id| {
+            stashes.push((index, message.to_string(), *stash_id));
+            true
+        })?;
+
+        let now = Utc::now();
+        stashes
+            .into_iter()
+            .map(|(index, message, stash_id)| {
+                let commit = repo.find_commit(stash_id)?;
+                let created =
+                    DateTime::<Utc>::from_timestamp(commit.time().seconds(), 0).unwrap_or_default();
+                let age_days = (now - created).num_days();
+                Ok(StashEntry {
+                    index,
+                    message,
+                    created,
+                    age_days,
+                    touched_paths: Self::get_touched_paths(&repo, &commit)?,
+                    forgotten: age_days > forgotten_after_days,
+                })
+            })
+            .collect()
+    }
+    /// Gets the paths touched by a stash commit: its working tree and index changes against
+    /// the commit it was made on, plus the files in its (optional) untracked files commit
+    fn get_touched_paths(
+        repo: &Repository,
+        commit: &git2::Commit<'_>,
+    ) -> Result<Vec<String>, SourceCodeError> {
+        let mut paths: BTreeSet<String> = BTreeSet::new();
+        let base_tree = commit.parent(0)?.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&commit.tree()?), None)?;
+        for delta in diff.deltas() {
+            if let Some(path) = delta.new_file().path().or(delta.old_file().path()) {
+                paths.insert(paths::to_slash(path));
+            }
+        }
+        if let Ok(untracked) = commit.parent(2) {
+            let diff = repo.diff_tree_to_tree(None, Some(&untracked.tree()?), None)?;
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path() {
+                    paths.insert(paths::to_slash(path));
+                }
+            }
+        }
+        Ok(paths.into_iter().collect())
+    }
+}
+
+/// The git hooks of a clone, and the hook frameworks committed to the repository
+///
+/// #Fields:
+/// * `hooks_path` - The `core.hooksPath` configuration, if set
+/// * `frameworks` - The [`HookFramework`]s configured in the repository
+/// * `hooks` - The [`GitHook`]s found in the hooks directory and in committed hook directories
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct HookInventory {
+    pub hooks_path: Option<String>,
+    pub frameworks: Vec<HookFramework>,
+    pub hooks: Vec<GitHook>,
+}
+impl HookInventory {
+    /// Creates the [`HookInventory`] for the clone at `repo_path`
+    pub fn new(repo_path: &str) -> Result<HookInventory, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+        let workdir: PathBuf = repo
+            .workdir()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from(repo_path));
+        let index = retry_on_lock(|| repo.index())?;
+        let is_tracked = |path: &Path| -> bool {
+            paths::relative_to(path, &workdir)
+                .map(|relative| index.get_path(&relative, 0).is_some())
+                .unwrap_or(false)
+        };
+
+        let hooks_path: Option<String> = repo.config()?.get_string("core.hooksPath").ok();
+        let active_dir: PathBuf = match &hooks_path {
+            Some(hooks_path) => workdir.join(hooks_path),
+            None => repo.path().join("hooks"),
+        };
+
+        let mut hooks: Vec<GitHook> = Self::find_hooks(&active_dir)
+            .into_iter()
+            .map(|path| GitHook::new(&workdir, &path, true, is_tracked(&path)))
+            .collect();
+
+        let mut frameworks: Vec<HookFramework> = Vec::new();
+        for (name, config) in HOOK_FRAMEWORKS {
+            let config_path = workdir.join(config);
+            if !config_path.exists() {
+                continue;
+            }
+            let tracked = match config_path.is_dir() {
+                // A directory is tracked if any of its hooks is
+                true => {
+                    let framework_hooks = Self::find_hooks(&config_path);
+                    let tracked = framework_hooks.iter().any(|path| is_tracked(path));
+                    if config_path != active_dir {
+                        hooks.extend(
+                            framework_hooks
+                                .iter()
+                                .map(|path| GitHook::new(&workdir, path, false, is_tracked(path))),
+                        );
+                    }
+                    tracked
+                }
+                false => is_tracked(&config_path),
+            };
+            frameworks.push(HookFramework {
+                name: name.to_string(),
+                config_path: config.to_string(),
+                tracked,
+            });
+        }
+        hooks.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(HookInventory {
+            hooks_path,
+            frameworks,
+            hooks,
+        })
+    }
+    /// Finds the hook scripts (ignoring git's '.sample' hooks) in the directory `dir`
+    fn find_hooks(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return vec![];
+        };
+        entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| HOOK_NAMES.contains(&name))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+}
+
+/// A hook framework configured in the repository, e.g., husky or pre-commit
+///
+/// #Fields:
+/// * `name` - The name of the framework
+/// * `config_path` - The relative path of the framework's configuration file or directory
+/// * `tracked` - Whether the configuration is committed to the repository
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct HookFramework {
+    pub name: String,
+    pub config_path: String,
+    pub tracked: bool,
+}
+
+/// A git hook script
+///
+/// #Fields:
+/// * `name` - The name of the hook, e.g., 'pre-commit'
+/// * `path` - The path of the script, relative to the working tree where possible
+/// * `active` - Whether the script is in the hooks directory git runs hooks from
+/// * `executable` - Whether the script is executable, which git requires to run it
+/// * `tracked` - Whether the script is committed to the repository
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct GitHook {
+    pub name: String,
+    pub path: String,
+    pub active: bool,
+    pub executable: bool,
+    pub tracked: bool,
+}
+impl GitHook {
+    fn new(workdir: &Path, path: &Path, active: bool, tracked: bool) -> Self {
+        Self {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: paths::normalise(path, workdir),
+            active,
+            executable: Self::is_executable(path),
+            tracked,
+        }
+    }
+    #[cfg(unix)]
+    fn is_executable(path: &Path) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path)
+            .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    fn is_executable(path: &Path) -> bool {
+        path.is_file()
+    }
+}
@@ -0,0 +1,217 @@
+#[cfg(feature = "git")]
+use git2::{Mailmap, Repository, Signature};
+#[cfg(feature = "git")]
+use regex::{RegexSet, RegexSetBuilder};
+use serde::{Deserialize, Serialize};
+
+/// The identity of a git author, as resolved by an [`IdentityResolver`]
+///
+/// #Fields:
+/// * `name` - The name of the author
+/// * `email` - The email address of the author, if any
+/// * `canonical_id` - The id an author's commits are grouped by, e.g., for the contributors;
+///   the name, unless an [`IdentityNormaliser`] sets it, e.g.,
+///   [`IdentityNormalisation::GroupByEmail`]
+/// * `is_bot` - Whether the name or email address matches one of the `patterns` of the
+///   [`BotOptions`], e.g., 'dependabot[bot]'
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Author {
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    pub canonical_id: String,
+    #[serde(default)]
+    pub is_bot: bool,
+}
+impl Author {
+    /// Gets the [`Author`] with the `name` and `email`, without any normalisation
+    pub fn new(name: &str, email: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            email: email.map(str::to_string),
+            canonical_id: name.to_string(),
+            is_bot: false,
+        }
+    }
+}
+
+/// A normalisation of the [`Author`]s of commits, applied by an [`IdentityResolver`] after the
+/// mailmap, e.g., to group the commits of an author who used several email addresses
+pub trait IdentityNormaliser {
+    fn normalise(&self, author: &mut Author);
+}
+
+/// The built-in [`IdentityNormaliser`]s, which can be set in the [`IdentityOptions`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityNormalisation {
+    /// Trims the name and canonical id and collapses the whitespace in them, e.g., 'Ada  Lovelace '
+    /// to 'Ada Lovelace', so that both spellings are grouped as one contributor
+    TrimWhitespace,
+    /// Lower cases the email address, which isn't case-sensitive in practice
+    LowercaseEmail,
+    /// Groups the commits by the lower-cased email address, for authors who changed their name
+    GroupByEmail,
+    /// Groups the commits by the lower-cased name, for authors who changed their email address
+    GroupByName,
+}
+impl IdentityNormaliser for IdentityNormalisation {
+    fn normalise(&self, author: &mut Author) {
+        match self {
+            IdentityNormalisation::TrimWhitespace => {
+                author.name = author.name.split_whitespace().collect::<Vec<_>>().join(" ");
+                author.canonical_id = author
+                    .canonical_id
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+            IdentityNormalisation::LowercaseEmail => {
+                author.email = author.email.as_ref().map(|email| email.to_lowercase());
+            }
+            IdentityNormalisation::GroupByEmail => {
+                if let Some(email) = author.email.as_ref().filter(|email| !email.is_empty()) {
+                    author.canonical_id = email.to_lowercase();
+                }
+            }
+            IdentityNormalisation::GroupByName => {
+                author.canonical_id = author.name.to_lowercase();
+            }
+        }
+    }
+}
+
+/// The options for resolving the [`Author`]s of commits
+///
+/// #Fields:
+/// * `mailmap` - Whether to map the names and email addresses with the repository's mailmap,
+///   i.e., its '.mailmap' file and the `mailmap.file` and `mailmap.blob` configuration
+/// * `normalisations` - The [`IdentityNormalisation`]s, applied in order after the mailmap
+/// * `bots` - The [`BotOptions`] for detecting the commits of bots, and whether they are counted
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct IdentityOptions {
+    pub mailmap: bool,
+    pub normalisations: Vec<IdentityNormalisation>,
+    pub bots: BotOptions,
+}
+
+/// Whether the commits authored by bots are counted
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BotHandling {
+    /// The commits of bots are counted like any other, with their authors flagged as bots
+    #[default]
+    Include,
+    /// The commits of bots are counted in the totals and churn, but bots aren't contributors,
+    /// nor counted in the contributors' shares of the commits
+    ExcludeFromContributors,
+    /// The commits of bots are left out of the history, so aren't counted in the totals, the
+    /// contributors, or the churn and change frequencies of the files
+    Exclude,
+}
+
+/// The options for detecting the commits of bots, e.g., dependency updates
+///
+/// #Fields:
+/// * `patterns` - The regular expressions, matched case-insensitively against the resolved name
+///   and email address of a commit's author, that identify a bot; by default, those of the bots
+///   of the common forges and dependency updaters, e.g., 'dependabot[bot]' or 'renovate'
+/// * `handling` - The [`BotHandling`] of the commits authored by bots
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct BotOptions {
+    pub patterns: Vec<String>,
+    pub handling: BotHandling,
+}
+impl Default for BotOptions {
+    fn default() -> Self {
+        Self {
+            patterns: [
+                r"\[bot\]",
+                r"^(dependabot|renovate|greenkeeper|snyk-bot|pre-commit-ci|mergify|allcontributors)\b",
+                r"^github-actions\b",
+                r"^action@github\.com$",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+            handling: BotHandling::default(),
+        }
+    }
+}
+
+/// Resolves the [`Author`]s of the commits of a repository, with its mailmap, if used, and then
+/// each [`IdentityNormaliser`] in order
+#[cfg(feature = "git")]
+pub struct IdentityResolver {
+    mailmap: Option<Mailmap>,
+    normalisers: Vec<Box<dyn IdentityNormaliser>>,
+    bots: Option<RegexSet>,
+}
+#[cfg(feature = "git")]
+impl IdentityResolver {
+    /// Gets the [`IdentityResolver`] of the `repo` as the [`IdentityOptions`] set. A mailmap
+    /// that can't be read, or bot patterns that aren't valid regular expressions, are ignored
+    /// with a warning.
+    pub fn new(repo: &Repository, options: &IdentityOptions) -> Self {
+        let mailmap = match options.mailmap {
+            true => repo
+                .mailmap()
+                .map_err(|err| {
+                    tracing::warn!("Not using the mailmap, which can't be read: {}", err)
+                })
+                .ok(),
+            false => None,
+        };
+        Self {
+            mailmap,
+            normalisers: options
+                .normalisations
+                .iter()
+                .map(|normalisation| Box::new(*normalisation) as Box<dyn IdentityNormaliser>)
+                .collect(),
+            bots: RegexSetBuilder::new(&options.bots.patterns)
+                .case_insensitive(true)
+                .build()
+                .map_err(|err| {
+                    tracing::warn!("Not detecting bots, as a pattern is invalid: {}", err)
+                })
+                .ok()
+                .filter(|bots| !bots.is_empty()),
+        }
+    }
+    /// Adds the `normaliser`, applied after those already added
+    pub fn with_normaliser(mut self, normaliser: impl IdentityNormaliser + 'static) -> Self {
+        self.normalisers.push(Box::new(normaliser));
+        self
+    }
+    /// Resolves the [`Author`] of the `signature`, e.g., of a commit's author or committer, or a
+    /// blamed line, flagging it if it is a bot
+    pub fn resolve(&self, signature: &Signature<'_>) -> Author {
+        let mapped = self
+            .mailmap
+            .as_ref()
+            .and_then(|mailmap| mailmap.resolve_signature(signature).ok());
+        let signature = mapped.as_ref().unwrap_or(signature);
+        let mut author = Author::new(
+            &String::from_utf8_lossy(signature.name_bytes()),
+            signature.email(),
+        );
+        for normaliser in &self.normalisers {
+            normaliser.normalise(&mut author);
+        }
+        author.is_bot = self.bots.as_ref().is_some_and(|bots| {
+            bots.is_match(&author.name)
+                || author
+                    .email
+                    .as_ref()
+                    .is_some_and(|email| bots.is_match(email))
+        });
+        author
+    }
+}
@@ -0,0 +1,64 @@
+//! The canonical registry of the file extensions of each language, so that every
+//! [`LanguageType`] of a language, of a source file or of the repository, is the same, with the
+//! full set of the language's extensions rather than those of the files it was found in
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use crate::{counting::CountingOptions, source::LanguageType};
+
+/// The file extensions of each language, by its `tokei` name, without the '.', lowercased and
+/// in alphabetical order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LanguageRegistry {
+    extensions: BTreeMap<String, Vec<String>>,
+}
+impl LanguageRegistry {
+    /// Gets the registry of the languages `tokei` has, with the extensions it recognises them by
+    pub fn tokei() -> &'static LanguageRegistry {
+        static REGISTRY: OnceLock<LanguageRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(|| {
+            let mut registry = LanguageRegistry::default();
+            for (language, extensions) in tokei::LanguageType::list() {
+                registry.add_extensions(language.name(), extensions.iter().copied());
+            }
+            registry
+        })
+    }
+    /// Gets the registry of the `tokei` languages, with the extensions of the
+    /// [`crate::counting::CustomLanguage`]s of the [`CountingOptions`] added to the languages
+    /// they are counted as, e.g., 'tpl' to HTML
+    pub fn new(counting: &CountingOptions) -> Self {
+        let mut registry = Self::tokei().clone();
+        for (name, custom) in &counting.languages {
+            if let Ok(language) = name.parse::<tokei::LanguageType>() {
+                registry.add_extensions(
+                    language.name(),
+                    custom.extensions.iter().map(String::as_str),
+                );
+            }
+        }
+        registry
+    }
+    /// Gets the extensions of the language named `name`, or none if it isn't registered
+    pub fn get_extensions(&self, name: &str) -> &[String] {
+        self.extensions.get(name).map_or(&[], Vec::as_slice)
+    }
+    /// Gets the [`LanguageType`] of the `tokei` language, with all of its extensions
+    pub fn get_language_type(&self, language: tokei::LanguageType) -> LanguageType {
+        LanguageType {
+            name: language.name().to_string(),
+            extensions: self.get_extensions(language.name()).to_vec(),
+            statistics: None,
+        }
+    }
+    /// Adds the `extensions` to those of the language named `name`
+    fn add_extensions<'a>(&mut self, name: &str, extensions: impl IntoIterator<Item = &'a str>) {
+        let known = self.extensions.entry(name.to_string()).or_default();
+        known.extend(
+            extensions
+                .into_iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase()),
+        );
+        known.sort_unstable();
+        known.dedup();
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use crate::{ratio::mean, walk};
+
+/// Files nested in this many directories or more are flagged as deeply nested
+pub const DEEP_NESTING_DEPTH: usize = 10;
+/// Directories with this many children (files and subdirectories) or more are flagged as large
+pub const LARGE_DIRECTORY_CHILDREN: usize = 5000;
+
+/// The directory structure of a repository: how deep files are nested, and how many children
+/// each directory has. Very deep or very wide structures slow down tooling, e.g., IDE indexers,
+/// file watchers and globbing build tools.
+///
+/// #Fields:
+/// * `max_depth` - The most directories a file is nested in; files at the root have depth 0
+/// * `average_depth` - The mean depth of the files
+/// * `max_fan_out` - The most children of any directory
+/// * `directories` - The [`DirectoryFanOut`] of each directory, most children first
+/// * `deep_paths` - The relative paths of the files nested [`DEEP_NESTING_DEPTH`] or more deep
+/// * `large_directories` - The relative paths of the directories with [`LARGE_DIRECTORY_CHILDREN`] or more children
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PathLayout {
+    pub max_depth: usize,
+    pub average_depth: f32,
+    pub max_fan_out: usize,
+    pub directories: Vec<DirectoryFanOut>,
+    pub deep_paths: Vec<String>,
+    pub large_directories: Vec<String>,
+}
+impl PathLayout {
+    /// Analyses the layout of the files in the repository at `repo_path`, skipping vendored
+    /// directories
+    pub fn new(repo_path: &str) -> PathLayout {
+        let paths: Vec<String> = walk::find_files(repo_path, |_| true)
+            .into_iter()
+            .map(|(relative_path, _)| relative_path)
+            .collect();
+        Self::from_paths(&paths)
+    }
+    /// Analyses the layout of the files at the relative `paths`
+    pub fn from_paths(paths: &[String]) -> PathLayout {
+        // (files, subdirectories) by directory; the root is "."
+        let mut tree: BTreeMap<String, (usize, BTreeSet<String>)> = BTreeMap::new();
+        let mut layout = PathLayout::default();
+        let mut total_depth: usize = 0;
+        for path in paths {
+            let directories: Vec<String> = Path::new(path)
+                .ancestors()
+                .skip(1)
+                .map(|dir| match dir.as_os_str().is_empty() {
+                    true => ".".to_string(),
+                    false => dir.to_string_lossy().to_string(),
+                })
+                .collect();
+            let depth = directories.len().saturating_sub(1);
+            total_depth += depth;
+            layout.max_depth = layout.max_depth.max(depth);
+            if depth >= DEEP_NESTING_DEPTH {
+                layout.deep_paths.push(path.clone());
+            }
+            if let Some(parent) = directories.first() {
+                tree.entry(parent.clone()).or_default().0 += 1;
+            }
+            for pair in directories.windows(2) {
+                tree.entry(pair[1].clone())
+                    .or_default()
+                    .1
+                    .insert(pair[0].clone());
+            }
+        }
+        layout.average_depth = mean(total_depth as f64, paths.len()).unwrap_or_default();
+
+        layout.directories = tree
+            .into_iter()
+            .map(|(path, (num_files, subdirectories))| {
+                let depth = match path.as_str() {
+                    "." => 0,
+                    _ => Path::new(&path).components().count(),
+                };
+                DirectoryFanOut {
+                    path,
+                    depth,
+                    num_files,
+                    num_subdirectories: subdirectories.len(),
+                    num_children: num_files + subdirectories.len(),
+                }
+            })
+            .collect();
+        layout.directories.sort_by(|a, b| {
+            b.num_children
+                .cmp(&a.num_children)
+                .then(a.path.cmp(&b.path))
+        });
+        layout.max_fan_out = layout
+            .directories
+            .first()
+            .map(|dir| dir.num_children)
+            .unwrap_or_default();
+        layout.large_directories = layout
+            .directories
+            .iter()
+            .filter(|dir| dir.num_children >= LARGE_DIRECTORY_CHILDREN)
+            .map(|dir| dir.path.clone())
+            .collect();
+        layout.deep_paths.sort();
+        layout
+    }
+}
+
+/// The children of a directory
+///
+/// #Fields:
+/// * `path` - The relative path of the directory; the root is '.'
+/// * `depth` - The depth of the files directly in the directory, i.e., 0 for the root
+/// * `num_files` - The number of files directly in the directory
+/// * `num_subdirectories` - The number of directories directly in the directory
+/// * `num_children` - The number of files and directories directly in the directory
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DirectoryFanOut {
+    pub path: String,
+    pub depth: usize,
+    pub num_files: usize,
+    pub num_subdirectories: usize,
+    pub num_children: usize,
+}
@@ -1,5 +1,112 @@
+// Without the `git` feature, the helpers only the analysis of a repository uses are unused
+#![cfg_attr(not(feature = "git"), allow(dead_code))]
+#[cfg(feature = "git")]
+pub mod analyzer;
+pub mod archetype;
+pub mod assets;
+#[cfg(feature = "git")]
+mod baseline;
+pub mod branches;
+pub mod cadence;
+pub mod cancel;
+pub mod catalog;
+pub mod checkpoint;
+pub mod chunk;
+pub mod churn;
+pub mod codeowners;
+#[cfg(feature = "git")]
+pub mod commits;
+pub mod context;
+pub mod coownership;
+pub mod counting;
+pub mod coupling;
+pub mod coverage;
 pub mod data;
+pub mod delta;
+pub mod dependencies;
+pub mod derived;
+#[cfg(feature = "git")]
+mod detect;
+pub mod duplication;
 pub mod embedding;
+pub mod embeddings_provider;
+pub mod encoding;
 pub mod errors;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod forge;
+pub mod framework;
+pub mod generated;
+#[cfg(feature = "git")]
+mod graph;
+pub mod grep;
+pub mod head;
+pub mod heatmap;
+pub mod history;
+#[cfg(feature = "git")]
+pub mod hooks;
+#[cfg(feature = "git")]
+pub mod hygiene;
+pub mod identity;
+#[cfg(feature = "git")]
+pub mod languages;
+pub mod layout;
+pub mod limits;
+#[cfg(feature = "git")]
+mod linecount;
+pub mod messages;
+pub mod metadata;
+pub mod migrate;
+pub mod naming;
+pub mod objects;
+pub mod observer;
+pub mod options;
+pub mod organisation;
+pub mod package;
+pub mod paths;
+pub mod policy;
+#[cfg(feature = "git")]
+pub mod provenance;
+pub mod provider;
+pub mod quadrant;
+pub mod quality;
+pub mod query;
+pub mod ratio;
+#[cfg(feature = "git")]
+pub mod refdiff;
+pub mod releases;
+pub mod report;
 pub mod repository;
+pub mod retention;
+#[cfg(feature = "git")]
+mod retry;
+#[cfg(feature = "git")]
+pub mod review;
+pub mod role;
+pub mod roots;
+pub mod runstats;
+pub mod sarif;
+pub mod scoring;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod shallow;
+#[cfg(feature = "compression")]
+pub mod snapshot;
 pub mod source;
+pub mod store;
+pub mod submodule;
+pub mod symbols;
+pub mod targets;
+pub mod techdebt;
+pub mod testcode;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod vector_store;
+mod walk;
+pub mod warning;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub mod whitespace;
+pub mod workspace;
+pub mod worktree;
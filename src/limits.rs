@@ -0,0 +1,185 @@
+//! Limits on the threads and IO an analysis uses, so that it doesn't saturate a developer's
+//! laptop or a shared CI agent
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+/// The niceness given to threads running at low priority (0 is normal, 19 the lowest)
+#[cfg(target_os = "linux")]
+const LOW_PRIORITY_NICE: libc::c_int = 10;
+/// The best-effort IO scheduling class, and its lowest priority level, for `ioprio_set`
+#[cfg(target_os = "linux")]
+const IOPRIO_BEST_EFFORT_LOWEST: libc::c_int = (2 << 13) | 7;
+/// `ioprio_set` for a thread (or process) id, where 0 is the calling thread
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
+thread_local! {
+    /// Whether the priority of the current thread has been lowered already
+    static LOWERED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The limits on the resources used by an analysis. Unset limits default to the available
+/// parallelism of the machine.
+///
+/// #Fields:
+/// * `max_threads` - The maximum number of worker threads, e.g., for analysing the
+///   repositories of a [`crate::workspace::WorkspaceInfo`] or preparing chunks for embedding
+/// * `max_io_concurrency` - The maximum number of threads reading and counting the lines of
+///   source files at once. `tokei` walks directories on its own threads, which are not limited.
+/// * `low_priority` - Whether to run the analysis at a lower CPU and IO priority, as a hint to
+///   the scheduler. Only supported on Linux; ignored elsewhere.
+/// * `max_file_size` - The size in bytes over which a source file isn't read into memory: it is
+///   hashed as it is streamed, and its contents aren't kept or embedded. `tokei` still reads it
+///   from the working tree to count its lines; read from the blobs at HEAD, its lines aren't
+///   counted, and a packed blob, which can't be streamed, isn't hashed.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ResourceLimits {
+    pub max_threads: Option<usize>,
+    pub max_io_concurrency: Option<usize>,
+    pub low_priority: bool,
+    pub max_file_size: Option<u64>,
+}
+impl ResourceLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Uses at most `max_threads` worker threads
+    pub fn with_max_threads(mut self, max_threads: usize) -> Self {
+        self.max_threads = Some(max_threads);
+        self
+    }
+    /// Reads at most `max_io_concurrency` source files at once
+    pub fn with_max_io_concurrency(mut self, max_io_concurrency: usize) -> Self {
+        self.max_io_concurrency = Some(max_io_concurrency);
+        self
+    }
+    /// Runs the analysis at a lower CPU and IO priority
+    pub fn with_low_priority(mut self, low_priority: bool) -> Self {
+        self.low_priority = low_priority;
+        self
+    }
+    /// Doesn't read source files over `max_file_size` bytes into memory
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = Some(max_file_size);
+        self
+    }
+    /// Gets the number of worker threads to use: the available parallelism, capped by
+    /// `max_threads`, and at least one
+    pub fn num_threads(&self) -> usize {
+        let available = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        self.max_threads.unwrap_or(available).clamp(1, available)
+    }
+    /// Runs `operation`, on a thread with its priority lowered if `low_priority` is set, so
+    /// that the caller's thread keeps its priority
+    pub(crate) fn run<T: Send>(&self, operation: impl FnOnce() -> T + Send) -> T {
+        if !self.low_priority || LOWERED.with(Cell::get) {
+            return operation();
+        }
+        std::thread::scope(|scope| {
+            match scope
+                .spawn(|| {
+                    lower_thread_priority();
+                    operation()
+                })
+                .join()
+            {
+                Ok(result) => result,
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        })
+    }
+    /// Runs the IO-bound `operation`, i.e., `tokei` reading and counting the source files, in
+    /// a thread pool limited to `max_io_concurrency` threads, at low priority if `low_priority`
+    /// is set. Without either limit, `operation` runs in the global thread pool.
+    pub(crate) fn run_io<T: Send>(&self, operation: impl FnOnce() -> T + Send) -> T {
+        if self.max_io_concurrency.is_none() && !self.low_priority {
+            return operation();
+        }
+        let low_priority = self.low_priority;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(
+                self.max_io_concurrency
+                    .unwrap_or_else(|| self.num_threads())
+                    .max(1),
+            )
+            .start_handler(move |_| {
+                if low_priority {
+                    lower_thread_priority();
+                }
+            })
+            .build();
+        match pool {
+            Ok(pool) => pool.install(operation),
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to create a limited thread pool, not limiting IO: {}",
+                    err
+                );
+                operation()
+            }
+        }
+    }
+    /// Splits `items` into contiguous chunks, one per worker thread (see [`Self::num_threads`]),
+    /// and runs `work` on each chunk on its own scoped thread
+    ///
+    /// #Returns:
+    /// * Each chunk, in order, with the result of `work` on it, or the panic of its thread
+    pub(crate) fn map_chunks<'a, I: Sync, T: Send>(
+        &self,
+        items: &'a [I],
+        work: impl Fn(&[I]) -> T + Sync,
+    ) -> Vec<(&'a [I], std::thread::Result<T>)> {
+        let per_thread = items.len().div_ceil(self.num_threads()).max(1);
+        let work = &work;
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = items
+                .chunks(per_thread)
+                .map(|items| {
+                    scope.spawn(move || {
+                        if self.low_priority {
+                            lower_thread_priority();
+                        }
+                        work(items)
+                    })
+                })
+                .collect();
+            items
+                .chunks(per_thread)
+                .zip(handles)
+                .map(|(items, handle)| (items, handle.join()))
+                .collect()
+        })
+    }
+}
+
+/// Lowers the CPU and IO priority of the current thread, as a hint to the scheduler. The
+/// priority can't be raised again without privileges, so this is only done on worker threads.
+fn lower_thread_priority() {
+    LOWERED.with(|lowered| lowered.set(true));
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: both calls only take integer arguments; on Linux, `who` 0 is the calling
+        // thread rather than the whole process
+        let nice = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, LOW_PRIORITY_NICE) };
+        let ioprio = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                IOPRIO_BEST_EFFORT_LOWEST,
+            )
+        };
+        if nice != 0 || ioprio != 0 {
+            tracing::debug!(
+                "Failed to lower the thread priority: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    tracing::debug!("Low priority hints are not supported on this platform");
+}
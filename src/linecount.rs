@@ -0,0 +1,43 @@
+//! Verifying the line counts of `tokei` against an independent count of the physical lines of
+//! each file, to catch misclassified files before their numbers are reported
+use tokei::CodeStats;
+
+use crate::warning::{AnalysisWarning, WarningKind};
+
+/// Counts the physical lines in `bytes`, as [`str::lines`] does, i.e., a final line without a
+/// line ending is counted, but there is no empty line after a final line ending
+pub(crate) fn count_physical_lines(bytes: &[u8]) -> usize {
+    let line_endings = bytes.iter().filter(|byte| **byte == b'\n').count();
+    match bytes.last() {
+        Some(b'\n') | None => line_endings,
+        Some(_) => line_endings + 1,
+    }
+}
+
+/// Verifies that the code, comment and blank lines in the `tokei` `stats` of the file at the
+/// relative `path`, including those of embedded languages, add up to its physical lines
+///
+/// #Returns:
+/// * An [`AnalysisWarning`] of [`WarningKind::LineCountMismatch`] if they don't, or `None`
+pub(crate) fn verify(path: &str, stats: &CodeStats, bytes: &[u8]) -> Option<AnalysisWarning> {
+    let stats = stats.summarise();
+    let tokei_lines = stats.lines();
+    let physical_lines = count_physical_lines(bytes);
+    if tokei_lines == physical_lines {
+        return None;
+    }
+    tracing::warn!(
+        "tokei counted {} lines in {}, which has {} physical lines",
+        tokei_lines,
+        path,
+        physical_lines
+    );
+    Some(
+        AnalysisWarning::new(path.to_string(), WarningKind::LineCountMismatch).with_message(
+            format!(
+                "tokei counted {} lines ({} code, {} comments, {} blanks), but the file has {} physical lines",
+                tokei_lines, stats.code, stats.comments, stats.blanks, physical_lines
+            ),
+        ),
+    )
+}
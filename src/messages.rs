@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{data::to_count, ratio::quotient};
+
+/// The kind of change a commit makes, from its message
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CommitType {
+    Feat,
+    Fix,
+    Docs,
+    Style,
+    Refactor,
+    Perf,
+    Test,
+    Build,
+    Ci,
+    Chore,
+    Revert,
+    Merge,
+    /// A message that matches neither a conventional commit type nor a keyword
+    Other,
+}
+impl CommitType {
+    /// Gets the [`CommitType`] of a conventional commit type, e.g., 'feat' or 'fix'
+    fn from_conventional(commit_type: &str) -> Option<Self> {
+        match commit_type.to_lowercase().as_str() {
+            "feat" | "feature" => Some(CommitType::Feat),
+            "fix" | "bugfix" | "hotfix" => Some(CommitType::Fix),
+            "docs" | "doc" => Some(CommitType::Docs),
+            "style" => Some(CommitType::Style),
+            "refactor" => Some(CommitType::Refactor),
+            "perf" => Some(CommitType::Perf),
+            "test" | "tests" => Some(CommitType::Test),
+            "build" => Some(CommitType::Build),
+            "ci" => Some(CommitType::Ci),
+            "chore" => Some(CommitType::Chore),
+            "revert" => Some(CommitType::Revert),
+            _ => None,
+        }
+    }
+    /// Gets the [`CommitType`] a word of a message suggests, e.g., 'fixes' or 'implement'
+    fn from_keyword(word: &str) -> Option<Self> {
+        match word {
+            "fix" | "fixes" | "fixed" | "fixing" | "bug" | "bugfix" | "hotfix" | "patch"
+            | "resolve" | "resolves" | "resolved" => Some(CommitType::Fix),
+            "add" | "adds" | "added" | "implement" | "implements" | "implemented" | "introduce"
+            | "introduces" | "feature" | "support" | "supports" => Some(CommitType::Feat),
+            "doc" | "docs" | "documentation" | "document" | "readme" => Some(CommitType::Docs),
+            "format" | "formatting" | "lint" | "whitespace" => Some(CommitType::Style),
+            "refactor" | "refactors" | "refactored" | "restructure" | "rename" | "renames"
+            | "cleanup" | "simplify" | "simplifies" => Some(CommitType::Refactor),
+            "perf" | "performance" | "optimise" | "optimize" | "speed" | "faster" => {
+                Some(CommitType::Perf)
+            }
+            "test" | "tests" | "testing" => Some(CommitType::Test),
+            "build" | "makefile" | "dockerfile" => Some(CommitType::Build),
+            "ci" | "pipeline" | "workflow" => Some(CommitType::Ci),
+            "chore" | "bump" | "bumps" | "upgrade" | "release" => Some(CommitType::Chore),
+            "revert" | "reverts" => Some(CommitType::Revert),
+            _ => None,
+        }
+    }
+    /// Classifies a commit `message` by the conventional commit type of its subject, e.g.,
+    /// 'feat(api): ...', or else by the first keyword in its subject, e.g., 'Fixes ...'
+    ///
+    /// #Returns:
+    /// * The [`CommitType`], and whether the message follows the conventional commit format
+    pub fn classify(message: &str) -> (Self, bool) {
+        let subject = message.trim_start().lines().next().unwrap_or_default();
+        if let Some(commit_type) = get_conventional_type(subject).and_then(Self::from_conventional)
+        {
+            return (commit_type, true);
+        }
+        let subject = subject.to_lowercase();
+        if subject.starts_with("merge ") {
+            return (CommitType::Merge, false);
+        }
+        let commit_type = subject
+            .split(|c: char| !c.is_alphanumeric())
+            .find_map(Self::from_keyword)
+            .unwrap_or(CommitType::Other);
+        (commit_type, false)
+    }
+}
+
+/// Checks whether a commit `message` is (likely) a bug fix: a conventional 'fix' commit, or
+/// else a message with a fix keyword, e.g., 'fixes' or 'bug', or an issue reference, e.g.,
+/// '#123' or 'ABC-123', anywhere in it. A merge commit, or a conventional commit of another
+/// type, e.g., 'feat: ... (#123)', isn't a bug fix, whatever it references.
+pub fn is_bugfix(message: &str) -> bool {
+    let subject = message.trim_start().lines().next().unwrap_or_default();
+    if let Some(commit_type) = get_conventional_type(subject) {
+        return CommitType::from_conventional(commit_type) == Some(CommitType::Fix);
+    }
+    match CommitType::classify(message) {
+        (CommitType::Merge, _) => false,
+        (CommitType::Fix, _) => true,
+        _ => message.split_whitespace().any(|word| {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '#');
+            CommitType::from_keyword(&word.to_lowercase()) == Some(CommitType::Fix)
+                || is_issue_reference(word)
+        }),
+    }
+}
+
+/// Checks whether a `word` is an issue reference, i.e., '#' or an upper case project key and
+/// '-' followed by digits, e.g., '#123' or 'ABC-123'
+fn is_issue_reference(word: &str) -> bool {
+    let number = match word.strip_prefix('#') {
+        Some(number) => number,
+        None => match word.split_once('-') {
+            Some((key, number))
+                if !key.is_empty() && key.chars().all(|c| c.is_ascii_uppercase()) =>
+            {
+                number
+            }
+            _ => return false,
+        },
+    };
+    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Gets the type of a conventional commit `subject`, i.e., 'type', 'type(scope)', 'type!' or
+/// 'type(scope)!' followed by ': ' and a description, where the type is a single word, e.g.,
+/// 'feat' rather than 'Fix crash'
+fn get_conventional_type(subject: &str) -> Option<&str> {
+    let (prefix, description) = subject.split_once(':')?;
+    if !description.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let commit_type = match prefix.split_once('(') {
+        Some((commit_type, scope)) if scope.ends_with(')') => commit_type,
+        Some(_) => return None,
+        None => prefix,
+    };
+    let is_word = !commit_type.is_empty()
+        && commit_type
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    is_word.then_some(commit_type)
+}
+
+/// The number of commits of a [`CommitType`]
+///
+/// #Fields:
+/// * `commit_type` - The [`CommitType`]
+/// * `num_commits` - The number of commits of the type
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommitTypeCount {
+    pub commit_type: CommitType,
+    pub num_commits: u64,
+}
+
+/// Statistics on the messages of a set of commits, e.g., of a repository or a contributor
+///
+/// #Fields:
+/// * `num_commits` - The number of commits
+/// * `num_conventional` - The number of commits whose messages follow the conventional commit
+///   format
+/// * `types` - The [`CommitTypeCount`] of each [`CommitType`] present
+/// * `num_bugfix` - The number of commits that are (likely) bug fixes, see [`is_bugfix`]
+/// * `average_length` - The mean length of the (trimmed) messages, in characters
+/// * `fix_to_feature_ratio` - The number of fixes per feature commit, or `None` if there are no
+///   feature commits
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommitMessageStatistics {
+    pub num_commits: u64,
+    pub num_conventional: u64,
+    pub types: Vec<CommitTypeCount>,
+    #[serde(default)]
+    pub num_bugfix: u64,
+    pub average_length: f32,
+    pub fix_to_feature_ratio: Option<f32>,
+}
+
+/// The counts of the commit messages seen during a walk of the history
+#[derive(Debug, Default)]
+pub(crate) struct CommitMessageCounter {
+    num_commits: u64,
+    num_conventional: u64,
+    num_bugfix: u64,
+    total_length: u64,
+    types: BTreeMap<CommitType, u64>,
+}
+impl CommitMessageCounter {
+    /// Classifies and counts the commit `message`
+    pub(crate) fn add(&mut self, message: &str) {
+        let (commit_type, conventional) = CommitType::classify(message);
+        self.num_commits += 1;
+        self.num_conventional += u64::from(conventional);
+        self.num_bugfix += u64::from(is_bugfix(message));
+        self.total_length = self
+            .total_length
+            .saturating_add(to_count(message.trim().chars().count()));
+        *self.types.entry(commit_type).or_default() += 1;
+    }
+    /// Counts the messages of the `statistics` again, e.g., of another repository; their
+    /// total length is taken from the `average_length`
+    pub(crate) fn add_statistics(&mut self, statistics: &CommitMessageStatistics) {
+        self.num_commits = self.num_commits.saturating_add(statistics.num_commits);
+        self.num_conventional = self
+            .num_conventional
+            .saturating_add(statistics.num_conventional);
+        self.num_bugfix = self.num_bugfix.saturating_add(statistics.num_bugfix);
+        // The float to integer conversion saturates
+        self.total_length = self.total_length.saturating_add(
+            (statistics.average_length as f64 * statistics.num_commits as f64).round() as u64,
+        );
+        for type_count in &statistics.types {
+            let num_commits = self.types.entry(type_count.commit_type).or_default();
+            *num_commits = num_commits.saturating_add(type_count.num_commits);
+        }
+    }
+    /// Gets the [`CommitMessageStatistics`] of the messages counted so far
+    pub(crate) fn get_statistics(&self) -> CommitMessageStatistics {
+        let count = |commit_type: CommitType| *self.types.get(&commit_type).unwrap_or(&0) as f64;
+        CommitMessageStatistics {
+            num_commits: self.num_commits,
+            num_conventional: self.num_conventional,
+            types: self
+                .types
+                .iter()
+                .map(|(commit_type, num_commits)| CommitTypeCount {
+                    commit_type: *commit_type,
+                    num_commits: *num_commits,
+                })
+                .collect(),
+            num_bugfix: self.num_bugfix,
+            average_length: quotient(self.total_length as f64, self.num_commits as f64)
+                .unwrap_or_default(),
+            fix_to_feature_ratio: quotient(count(CommitType::Fix), count(CommitType::Feat)),
+        }
+    }
+}
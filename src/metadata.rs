@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    head::HeadState, history::SampledHistory, observer::AnalysisTimings, options::AnalysisOptions,
+    runstats::RunStats,
+};
+
+/// How an analysis was run, so that archived snapshots can be interpreted, e.g., compared only
+/// with snapshots from the same crate version and options
+///
+/// #Fields:
+/// * `crate_version` - The version of this crate that ran the analysis
+/// * `head` - The [`HeadState`] when analysed, i.e., the branch (if any) and commit the statistics
+///   were computed against
+/// * `options` - The [`AnalysisOptions`] the analysis ran with
+/// * `started_at` - The date and time the analysis started
+/// * `finished_at` - The date and time the analysis finished
+/// * `duration_ms` - The time the analysis took, in milliseconds
+/// * `host` - The name of the host the analysis ran on, if known
+/// * `run_stats` - The [`RunStats`] of the work the analysis did, if requested in the
+///   [`AnalysisOptions`]
+/// * `sampled_history` - The [`SampledHistory`], i.e., the limits applied to the commits walked
+///   and how many were walked, if the history was sampled
+/// * `timings` - The [`AnalysisTimings`] of the stages of the analysis, e.g., to see where a slow
+///   run spends its time
+/// * `num_warnings` - The number of [`crate::warning::AnalysisWarning`]s of the analysis, e.g.,
+///   to spot a run that skipped or mis-read many files
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnalysisMetadata {
+    pub crate_version: String,
+    pub head: Option<HeadState>,
+    pub options: AnalysisOptions,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_stats: Option<RunStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampled_history: Option<SampledHistory>,
+    #[serde(default)]
+    pub timings: AnalysisTimings,
+    #[serde(default)]
+    pub num_warnings: u64,
+}
+impl AnalysisMetadata {
+    /// Creates the [`AnalysisMetadata`] for an analysis of the repository at `repo_path` that
+    /// started at `started_at` and has just finished
+    #[cfg(feature = "git")]
+    pub(crate) fn new(
+        repo_path: &str,
+        options: &AnalysisOptions,
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        let finished_at = Utc::now();
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            head: Repository::open(repo_path)
+                .ok()
+                .and_then(|repo| HeadState::resolve(&repo).ok()),
+            options: options.clone(),
+            started_at,
+            finished_at,
+            duration_ms: (finished_at - started_at).num_milliseconds(),
+            host: Self::get_host(),
+            run_stats: None,
+            sampled_history: None,
+            timings: AnalysisTimings::default(),
+            num_warnings: 0,
+        }
+    }
+    /// Gets the host name from the `HOSTNAME` environment variable, or '/etc/hostname'
+    #[cfg(feature = "git")]
+    fn get_host() -> Option<String> {
+        std::env::var("HOSTNAME")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+    }
+}
@@ -0,0 +1,236 @@
+use serde_json::{Map, Value};
+
+use crate::{
+    archetype::RepositoryArchetype, errors::SourceCodeError, identity::Author, layout::PathLayout,
+    repository::RepositoryInfo,
+};
+
+/// The version of the serialised [`RepositoryInfo`] layout, increased whenever a change to the
+/// structs would stop older snapshots deserialising, with a migration from the previous one:
+/// * 1 - Snapshots without a `schema_version`, i.e., from before it was added
+/// * 2 - The fields added since the first release are all present
+/// * 3 - The authors of the contributors, tech debt markers and file ownership are
+///   [`crate::identity::Author`]s, rather than names
+/// * 4 - The `frequency` of the [`crate::data::Statistics`] is split into the
+///   `change_frequency` of a source file, the `language_share` of a language and the
+///   `contribution_share` of a contributor
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// The migration of a serialised [`RepositoryInfo`] from each version to the next, where the
+/// migration at index `i` migrates version `i + 1`
+const MIGRATIONS: [fn(&mut Map<String, Value>); SCHEMA_VERSION as usize - 1] =
+    [migrate_v1, migrate_v2, migrate_v3];
+
+/// The version of a snapshot without a `schema_version`
+pub(crate) fn unversioned() -> u32 {
+    1
+}
+
+/// Deserialises a [`RepositoryInfo`] from the JSON of a snapshot of any version up to
+/// [`SCHEMA_VERSION`], migrating it to the current layout first
+///
+/// #Returns:
+/// * Err([`SourceCodeError::SerializationError`]) if the JSON isn't a snapshot, or is from a
+///   later version of this crate than this one supports
+pub(crate) fn deserialize_any_version(json: &str) -> Result<RepositoryInfo, SourceCodeError> {
+    let serialization_error =
+        |err: serde_json::Error| SourceCodeError::SerializationError(err.into());
+    let mut value: Value = serde_json::from_str(json).map_err(serialization_error)?;
+    let Some(snapshot) = value.as_object_mut() else {
+        return Err(SourceCodeError::SerializationError(anyhow::anyhow!(
+            "a snapshot must be a JSON object"
+        )));
+    };
+    let version = match snapshot.get("schema_version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .unwrap_or_default(),
+        None => unversioned(),
+    };
+    if !(1..=SCHEMA_VERSION).contains(&version) {
+        return Err(SourceCodeError::SerializationError(anyhow::anyhow!(
+            "unsupported snapshot schema version {} (supported: 1 to {})",
+            version,
+            SCHEMA_VERSION
+        )));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(snapshot);
+    }
+    snapshot.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+    serde_json::from_value(value).map_err(serialization_error)
+}
+
+/// Migrates a version 1 snapshot, which may lack any of the (required) fields added since the
+/// first release, by adding them with their empty values
+fn migrate_v1(snapshot: &mut Map<String, Value>) {
+    let empty_fields = [
+        ("languages", Value::Array(vec![])),
+        (
+            "archetype",
+            serde_json::to_value(RepositoryArchetype::default()).unwrap_or_default(),
+        ),
+        ("frameworks", Value::Array(vec![])),
+        ("targets", Value::Array(vec![])),
+        (
+            "layout",
+            serde_json::to_value(PathLayout::default()).unwrap_or_default(),
+        ),
+        ("submodules", Value::Array(vec![])),
+        ("unknown_files", Value::Array(vec![])),
+        ("metrics_catalog", Value::Array(vec![])),
+        ("warnings", Value::Array(vec![])),
+    ];
+    for (field, empty) in empty_fields {
+        snapshot.entry(field).or_insert(empty);
+    }
+}
+
+/// Migrates a version 2 snapshot, whose authors are names, by making each an author with that
+/// name, without an email address, and grouped by the name, as they were
+fn migrate_v2(snapshot: &mut Map<String, Value>) {
+    let to_author = |value: &mut Value| {
+        if let Value::String(name) = value {
+            *value = serde_json::to_value(Author::new(name, None)).unwrap_or_default();
+        }
+    };
+    for contributor in objects_mut(snapshot.get_mut("contributors")) {
+        if let Some(mut author) = contributor.remove("name") {
+            to_author(&mut author);
+            contributor.insert("author".to_string(), author);
+        }
+    }
+    for marker in objects_mut(snapshot.get_mut("tech_debt_markers")) {
+        if let Some(author) = marker.get_mut("author") {
+            to_author(author);
+        }
+    }
+    let files = snapshot
+        .get_mut("ownership")
+        .and_then(|ownership| ownership.get_mut("files"));
+    for file in objects_mut(files) {
+        if let Some(author) = file.get_mut("top_contributor") {
+            to_author(author);
+        }
+    }
+}
+
+/// Migrates a version 3 snapshot, whose statistics each have a `frequency`, by moving that of
+/// each language to its `language_share`, and replacing that of each contributor, which was
+/// always 0, with its `contribution_share`, i.e., its `percentage_contribution`; the others are
+/// read as the `change_frequency`
+fn migrate_v3(snapshot: &mut Map<String, Value>) {
+    for language in objects_mut(snapshot.get_mut("languages")) {
+        if let Some(Value::Object(statistics)) = language.get_mut("statistics") {
+            if let Some(frequency) = statistics.remove("frequency") {
+                statistics.insert("language_share".to_string(), frequency);
+                statistics.insert("change_frequency".to_string(), 0.0.into());
+            }
+        }
+    }
+    for contributor in objects_mut(snapshot.get_mut("contributors")) {
+        let share = contributor.get("percentage_contribution").cloned();
+        if let Some(Value::Object(statistics)) = contributor.get_mut("statistics") {
+            statistics.remove("frequency");
+            statistics.insert("change_frequency".to_string(), 0.0.into());
+            if let Some(share) = share {
+                statistics.insert("contribution_share".to_string(), share);
+            }
+        }
+    }
+}
+
+/// Gets the objects in the array `value`, if it is one
+fn objects_mut(value: Option<&mut Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    value
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "git")]
+    use crate::{options::AnalysisOptions, testing::FixtureRepo};
+    use crate::{ratio::Percentage, techdebt::TechDebtKind};
+
+    const V1: &str = include_str!("../tests/fixtures/snapshots/v1.json");
+
+    #[test]
+    fn migrates_a_version_1_snapshot() -> Result<(), SourceCodeError> {
+        let repository_info = deserialize_any_version(V1)?;
+        assert_eq!(repository_info.schema_version, SCHEMA_VERSION);
+        assert_eq!(repository_info.name, "fixture");
+        // Version 1: the fields added since are empty
+        assert!(repository_info.frameworks.is_empty());
+        assert!(repository_info.warnings.is_empty());
+        // Version 2: the authors are names
+        let alice = Author::new("Alice", None);
+        assert_eq!(repository_info.contributors[0].author, alice);
+        assert_eq!(
+            repository_info.tech_debt_markers[0].author,
+            Some(alice.clone())
+        );
+        assert_eq!(
+            repository_info.tech_debt_markers[0].kind,
+            TechDebtKind::Todo
+        );
+        let ownership = repository_info.ownership.as_ref();
+        assert_eq!(
+            ownership.and_then(|ownership| ownership.files[0].top_contributor.clone()),
+            Some(alice)
+        );
+        // Version 3: the frequency is a change frequency, a language share or a contribution
+        // share
+        let statistics = &repository_info.languages[0].statistics;
+        assert_eq!(
+            statistics
+                .as_ref()
+                .map(|statistics| (statistics.change_frequency, statistics.language_share)),
+            Some((Percentage(0.0), Some(Percentage(100.0))))
+        );
+        let statistics = &repository_info.contributors[0].statistics;
+        assert_eq!(statistics.change_frequency, Percentage(0.0));
+        assert_eq!(statistics.contribution_share, Some(Percentage(100.0)));
+        let statistics = &repository_info.source_files[0].statistics;
+        assert_eq!(statistics.change_frequency, Percentage(50.0));
+        assert_eq!(statistics.language_share, None);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unsupported_versions_and_non_snapshots() {
+        let unsupported = |version: u32| {
+            let mut snapshot: Value = serde_json::from_str(V1).unwrap_or_default();
+            snapshot["schema_version"] = version.into();
+            deserialize_any_version(&snapshot.to_string())
+        };
+        assert!(matches!(
+            unsupported(0),
+            Err(SourceCodeError::SerializationError(_))
+        ));
+        assert!(matches!(
+            unsupported(SCHEMA_VERSION + 1),
+            Err(SourceCodeError::SerializationError(_))
+        ));
+        assert!(matches!(
+            deserialize_any_version("[]"),
+            Err(SourceCodeError::SerializationError(_))
+        ));
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn reads_a_current_snapshot_as_it_is() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("src/lib.rs", "pub fn one() -> i32 {\n    1\n}\n")
+            .commit("Add the library")?;
+        let repository_info = fixture.analyse(&AnalysisOptions::default())?;
+        // The local paths of the source files aren't serialised, so the JSON is compared
+        let json = repository_info.get_as_json()?;
+        assert_eq!(deserialize_any_version(&json)?.get_as_json()?, json);
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use crate::{errors::SourceCodeError, paths, repository::RepositoryInfo};
+
+/// The casing style of a file name (without extensions)
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NamingStyle {
+    /// e.g., 'source_file'
+    SnakeCase,
+    /// e.g., 'source-file'
+    KebabCase,
+    /// e.g., 'sourceFile'
+    CamelCase,
+    /// e.g., 'SourceFile'
+    PascalCase,
+    /// e.g., 'README' or 'SOURCE_FILE', which is conventional for some files in any style
+    UpperCase,
+    /// A single lowercase word, e.g., 'source', which fits snake, kebab and camel case alike
+    Lowercase,
+    /// A mix of styles, e.g., 'Source_file'
+    Mixed,
+}
+impl NamingStyle {
+    /// Whether the style fits in with any convention, so is neither counted nor flagged
+    fn is_neutral(&self) -> bool {
+        matches!(self, NamingStyle::Lowercase | NamingStyle::UpperCase)
+    }
+    /// Classifies the casing style of a file `stem`, ignoring leading and trailing underscores,
+    /// e.g., Python's '__init__'
+    ///
+    /// #Returns:
+    /// * The [`NamingStyle`], or `None` if the stem has no letters
+    pub fn classify(stem: &str) -> Option<NamingStyle> {
+        let stem = stem.trim_matches('_');
+        if !stem.chars().any(char::is_alphabetic) {
+            return None;
+        }
+        let has_upper = stem.chars().any(char::is_uppercase);
+        let has_lower = stem.chars().any(char::is_lowercase);
+        let has_underscore = stem.contains('_');
+        let has_hyphen = stem.contains('-');
+        let starts_upper = stem.chars().next().is_some_and(char::is_uppercase);
+        let style = match (has_underscore, has_hyphen) {
+            (false, false) if !has_upper => NamingStyle::Lowercase,
+            (_, false) if !has_lower => NamingStyle::UpperCase,
+            (false, false) if starts_upper => NamingStyle::PascalCase,
+            (false, false) => NamingStyle::CamelCase,
+            (true, false) if !has_upper => NamingStyle::SnakeCase,
+            (false, true) if !has_upper => NamingStyle::KebabCase,
+            _ => NamingStyle::Mixed,
+        };
+        Some(style)
+    }
+}
+
+/// A report on the consistency of source file naming within each directory and language
+///
+/// #Fields:
+/// * `groups` - The [`NamingGroup`] of each directory and language, ordered by directory
+/// * `num_inconsistent` - The number of files named in a style other than their group's
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct NamingReport {
+    pub groups: Vec<NamingGroup>,
+    pub num_inconsistent: usize,
+}
+impl NamingReport {
+    /// Analyses the names of the source files of the [`RepositoryInfo`], grouped by directory
+    /// and language, as languages in one directory (e.g., Rust and Markdown) often have
+    /// different conventions
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository
+    /// * `repository_info` - The [`RepositoryInfo`] of the repository
+    pub fn new(repo_path: &str, repository_info: &RepositoryInfo) -> NamingReport {
+        let mut files: HashMap<(String, String), Vec<(String, NamingStyle)>> = HashMap::new();
+        for source_file in &repository_info.source_files {
+            let full_path = Path::new(&source_file.relative_path);
+            let path = full_path.strip_prefix(repo_path).unwrap_or(full_path);
+            let stem = source_file
+                .name
+                .split('.')
+                .next()
+                .filter(|stem| !stem.is_empty());
+            let Some(style) = stem.and_then(NamingStyle::classify) else {
+                // Dotfiles, e.g., '.eslintrc.js', follow their tool's convention
+                continue;
+            };
+            let directory = path.parent().map(paths::to_slash).unwrap_or_default();
+            let language = source_file
+                .language
+                .as_ref()
+                .map(|language| language.name.clone())
+                .unwrap_or_default();
+            files
+                .entry((directory, language))
+                .or_default()
+                .push((paths::to_slash(path), style));
+        }
+
+        let mut groups: Vec<NamingGroup> = files
+            .into_iter()
+            .map(|((directory, language), files)| NamingGroup::new(directory, language, files))
+            .collect();
+        groups.sort_by(|a, b| (&a.directory, &a.language).cmp(&(&b.directory, &b.language)));
+        NamingReport {
+            num_inconsistent: groups.iter().map(|g| g.inconsistent_files.len()).sum(),
+            groups,
+        }
+    }
+    /// Gets the [`NamingReport`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
+
+/// The naming of the source files of one language in one directory
+///
+/// #Fields:
+/// * `directory` - The relative path of the directory; the root is ''
+/// * `language` - The language name of the files
+/// * `dominant_style` - The most common [`NamingStyle`], ignoring single lowercase words and upper case, if any
+/// * `styles` - The number of files in each [`NamingStyle`]
+/// * `inconsistent_files` - The relative paths of the files not in the dominant style
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct NamingGroup {
+    pub directory: String,
+    pub language: String,
+    pub dominant_style: Option<NamingStyle>,
+    pub styles: BTreeMap<NamingStyle, usize>,
+    pub inconsistent_files: Vec<String>,
+}
+impl NamingGroup {
+    fn new(directory: String, language: String, files: Vec<(String, NamingStyle)>) -> Self {
+        let mut styles: BTreeMap<NamingStyle, usize> = BTreeMap::new();
+        for (_, style) in &files {
+            *styles.entry(*style).or_default() += 1;
+        }
+        // Ties go to the first style in declaration order, so the result is stable
+        let dominant_style = styles
+            .iter()
+            .filter(|(style, _)| !style.is_neutral())
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(style, _)| *style);
+        let mut inconsistent_files: Vec<String> = files
+            .into_iter()
+            .filter(|(_, style)| !style.is_neutral() && Some(*style) != dominant_style)
+            .map(|(path, _)| path)
+            .collect();
+        inconsistent_files.sort();
+        NamingGroup {
+            directory,
+            language,
+            dominant_style,
+            styles,
+            inconsistent_files,
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! The object database of a clone: how it is stored, how much of it `git gc` can prune, and
+//! the blobs ever committed to it, for storage audits, e.g., finding committed binaries that
+//! bloat every clone even once deleted
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::{ObjectType, Oid, Repository, Sort, TreeWalkMode, TreeWalkResult};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::errors::SourceCodeError;
+#[cfg(feature = "git")]
+use crate::{
+    cancel::{CancellationToken, PartialResults},
+    graph::CommitGraph,
+    head,
+    observer::AnalysisStage,
+};
+
+/// Statistics on the object database of a clone: how it is stored, and how much of it is
+/// cruft that `git gc` can prune
+///
+/// #Fields:
+/// * `num_objects` - The number of distinct objects in the object database
+/// * `loose_objects` - The number of loose (unpacked) object files
+/// * `loose_size` - The on-disk size in bytes of the loose object files
+/// * `packs` - The number of pack files
+/// * `pack_size` - The on-disk size in bytes of the pack files and their indexes
+/// * `unreachable` - The [`UnreachableObjects`] in the object database
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ObjectStoreStatistics {
+    pub num_objects: usize,
+    pub loose_objects: usize,
+    pub loose_size: u64,
+    pub packs: usize,
+    pub pack_size: u64,
+    pub unreachable: UnreachableObjects,
+}
+#[cfg(feature = "git")]
+impl ObjectStoreStatistics {
+    /// Computes the [`ObjectStoreStatistics`] for the clone at `repo_path`
+    ///
+    /// Objects are reachable, as for `git fsck`, from the refs, HEAD, the reflogs and the
+    /// index, so the unreachable objects are those `git gc` prunes once past its grace period.
+    pub fn new(repo_path: &str) -> Result<ObjectStoreStatistics, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+        let objects_dir = Self::get_common_dir(&repo).join("objects");
+        let mut statistics = ObjectStoreStatistics::default();
+        for size in Self::find_loose_object_sizes(&objects_dir) {
+            statistics.loose_objects += 1;
+            statistics.loose_size += size;
+        }
+        for entry in fs::read_dir(objects_dir.join("pack")).into_iter().flatten() {
+            let Ok(entry) = entry else { continue };
+            let path = entry.path();
+            let extension = path.extension().and_then(|e| e.to_str());
+            if !matches!(extension, Some("pack" | "idx")) {
+                continue;
+            }
+            if extension == Some("pack") {
+                statistics.packs += 1;
+            }
+            statistics.pack_size += entry.metadata().map(|m| m.len()).unwrap_or_default();
+        }
+
+        let odb = repo.odb()?;
+        let mut all_objects: HashSet<Oid> = HashSet::new();
+        odb.foreach(|oid| {
+            all_objects.insert(*oid);
+            true
+        })?;
+        statistics.num_objects = all_objects.len();
+
+        let reachable = Self::find_reachable_objects(&repo)?;
+        let unreachable = &mut statistics.unreachable;
+        let mut unreachable_commits: Vec<Oid> = Vec::new();
+        for oid in all_objects.difference(&reachable) {
+            let (size, kind) = odb.read_header(*oid)?;
+            unreachable.size += size as u64;
+            match kind {
+                ObjectType::Commit => {
+                    unreachable.commits += 1;
+                    unreachable_commits.push(*oid);
+                }
+                ObjectType::Tree => unreachable.trees += 1,
+                ObjectType::Blob => unreachable.blobs += 1,
+                ObjectType::Tag => unreachable.tags += 1,
+                _ => {}
+            }
+            let hex = oid.to_string();
+            let loose_path = objects_dir.join(&hex[..2]).join(&hex[2..]);
+            if let Ok(metadata) = fs::metadata(loose_path) {
+                unreachable.loose_size += metadata.len();
+            }
+        }
+
+        // Dangling commits are the tips of unreachable history: not the parent of any other
+        let mut parents: HashSet<Oid> = HashSet::new();
+        for oid in &unreachable_commits {
+            parents.extend(repo.find_commit(*oid)?.parent_ids());
+        }
+        unreachable.dangling_commits = unreachable_commits
+            .iter()
+            .filter(|oid| !parents.contains(oid))
+            .map(|oid| oid.to_string())
+            .collect();
+        unreachable.dangling_commits.sort();
+
+        Ok(statistics)
+    }
+    /// Gets the [`ObjectStoreStatistics`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Gets the directory shared by all worktrees of the repository, which holds the objects
+    fn get_common_dir(repo: &Repository) -> PathBuf {
+        match fs::read_to_string(repo.path().join("commondir")) {
+            Ok(common_dir) => repo.path().join(common_dir.trim()),
+            Err(_) => repo.path().to_path_buf(),
+        }
+    }
+    /// Finds the sizes of the loose object files, i.e., `objects/xx/yyyy...`
+    fn find_loose_object_sizes(objects_dir: &Path) -> Vec<u64> {
+        let mut sizes: Vec<u64> = Vec::new();
+        for dir in fs::read_dir(objects_dir).into_iter().flatten().flatten() {
+            let name = dir.file_name().to_string_lossy().to_string();
+            if name.len() != 2 || !name.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+            for file in fs::read_dir(dir.path()).into_iter().flatten().flatten() {
+                sizes.push(file.metadata().map(|m| m.len()).unwrap_or_default());
+            }
+        }
+        sizes
+    }
+    /// Finds the objects reachable from the refs, HEAD, the reflogs and the index
+    fn find_reachable_objects(repo: &Repository) -> Result<HashSet<Oid>, SourceCodeError> {
+        let mut pending: Vec<Oid> = Vec::new();
+        if let Ok(head) = repo.head() {
+            pending.extend(head.target());
+        }
+        for reference in repo.references()?.flatten() {
+            pending.extend(reference.target());
+            let Some(name) = reference.name() else {
+                continue;
+            };
+            if let Ok(reflog) = repo.reflog(name) {
+                for entry in reflog.iter() {
+                    pending.extend([entry.id_old(), entry.id_new()]);
+                }
+            }
+        }
+        if let Ok(reflog) = repo.reflog("HEAD") {
+            for entry in reflog.iter() {
+                pending.extend([entry.id_old(), entry.id_new()]);
+            }
+        }
+        if let Ok(index) = repo.index() {
+            pending.extend(index.iter().map(|entry| entry.id));
+        }
+
+        let mut reachable: HashSet<Oid> = HashSet::new();
+        while let Some(oid) = pending.pop() {
+            if oid.is_zero() || !reachable.insert(oid) {
+                continue;
+            }
+            // Missing objects, e.g., in shallow clones or submodule commits, end the walk
+            let Ok(object) = repo.find_object(oid, None) else {
+                continue;
+            };
+            match object.kind() {
+                Some(ObjectType::Commit) => {
+                    if let Some(commit) = object.as_commit() {
+                        pending.push(commit.tree_id());
+                        pending.extend(commit.parent_ids());
+                    }
+                }
+                Some(ObjectType::Tree) => {
+                    if let Some(tree) = object.as_tree() {
+                        pending.extend(tree.iter().map(|entry| entry.id()));
+                    }
+                }
+                Some(ObjectType::Tag) => {
+                    if let Some(tag) = object.as_tag() {
+                        pending.push(tag.target_id());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(reachable)
+    }
+}
+
+/// The objects in the object database that are not reachable
+///
+/// #Fields:
+/// * `commits` - The number of unreachable commits
+/// * `trees` - The number of unreachable trees
+/// * `blobs` - The number of unreachable blobs
+/// * `tags` - The number of unreachable annotated tags
+/// * `size` - The total uncompressed size in bytes of the unreachable objects
+/// * `loose_size` - The on-disk size in bytes of the unreachable loose objects, i.e., the
+///   space `git gc` frees outright; unreachable packed objects are freed when repacked
+/// * `dangling_commits` - The ids of the unreachable commits that no other commit has as parent
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UnreachableObjects {
+    pub commits: usize,
+    pub trees: usize,
+    pub blobs: usize,
+    pub tags: usize,
+    pub size: u64,
+    pub loose_size: u64,
+    pub dangling_commits: Vec<String>,
+}
+
+/// The options for the storage audit of the object database
+///
+/// #Fields:
+/// * `max_blobs` - The number of largest [`CommittedBlob`]s kept in the [`StorageReport`]
+/// * `min_blob_size` - The size in bytes under which a blob isn't listed as one of the largest,
+///   though it is still counted
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct StorageOptions {
+    pub max_blobs: usize,
+    pub min_blob_size: u64,
+}
+impl Default for StorageOptions {
+    fn default() -> Self {
+        Self {
+            max_blobs: 20,
+            min_blob_size: 0,
+        }
+    }
+}
+
+/// A blob committed in the history of a repository
+///
+/// #Fields:
+/// * `oid` - The id of the blob
+/// * `path` - The relative path the blob was first committed at
+/// * `size` - The uncompressed size in bytes of the blob
+/// * `commit` - The id of the earliest commit whose tree has the blob
+/// * `committed_at` - The time of that commit
+/// * `in_head` - Whether the blob is still in the tree of HEAD; if not, it only bloats the
+///   history, and removing it needs the history rewritten
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CommittedBlob {
+    pub oid: String,
+    pub path: String,
+    pub size: u64,
+    pub commit: String,
+    pub committed_at: Option<DateTime<Utc>>,
+    pub in_head: bool,
+}
+
+/// The storage audit of a repository: the size of its object database on disk, and the blobs
+/// ever committed to its history, even if since deleted, as each clone fetches them; the
+/// history is that reachable from the refs, see [`StorageReport::new`], or, in an analysis,
+/// that of its commit graph
+///
+/// #Fields:
+/// * `objects` - The [`ObjectStoreStatistics`] of the object database, e.g., its pack files
+/// * `num_blobs` - The number of distinct blobs committed in the history
+/// * `blob_size` - The total uncompressed size in bytes of those blobs
+/// * `head_blob_size` - The total uncompressed size in bytes of the distinct blobs in the tree
+///   of HEAD, so `blob_size - head_blob_size` is the size of the history alone
+/// * `largest_blobs` - The largest [`CommittedBlob`]s, largest first, limited by the
+///   [`StorageOptions`]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StorageReport {
+    pub objects: ObjectStoreStatistics,
+    pub num_blobs: usize,
+    pub blob_size: u64,
+    pub head_blob_size: u64,
+    pub largest_blobs: Vec<CommittedBlob>,
+}
+impl StorageReport {
+    /// Gets the [`StorageReport`] of the repository at `repo_path`, walking the trees of all of
+    /// the commits reachable from its refs, from the oldest, so each blob is attributed to the
+    /// commit that first committed it; trees already walked are skipped, so each is read once
+    #[cfg(feature = "git")]
+    pub fn new(
+        repo_path: &str,
+        options: &StorageOptions,
+    ) -> Result<StorageReport, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let odb = repo.odb()?;
+        let mut revwalk = repo.revwalk()?;
+        head::push_head(&repo, &mut revwalk)?;
+        revwalk.push_glob("*")?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+        let mut trees: HashSet<Oid> = HashSet::new();
+        let mut blobs: HashMap<Oid, CommittedBlob> = HashMap::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            let committed_at = DateTime::<Utc>::from_timestamp(commit.time().seconds(), 0);
+            let tree = commit.tree()?;
+            if !trees.insert(tree.id()) {
+                continue;
+            }
+            tree.walk(TreeWalkMode::PreOrder, |root, entry| match entry.kind() {
+                Some(ObjectType::Tree) if !trees.insert(entry.id()) => TreeWalkResult::Skip,
+                Some(ObjectType::Blob) => {
+                    if let (false, Ok((size, _))) =
+                        (blobs.contains_key(&entry.id()), odb.read_header(entry.id()))
+                    {
+                        blobs.insert(
+                            entry.id(),
+                            CommittedBlob {
+                                oid: entry.id().to_string(),
+                                path: format!("{}{}", root, entry.name().unwrap_or_default()),
+                                size: size as u64,
+                                commit: commit.id().to_string(),
+                                committed_at,
+                                in_head: false,
+                            },
+                        );
+                    }
+                    TreeWalkResult::Ok
+                }
+                _ => TreeWalkResult::Ok,
+            })?;
+        }
+
+        Self::from_blobs(&repo, repo_path, blobs, options)
+    }
+    /// Gets the [`StorageReport`] of the repository at `repo_path` from the [`CommitGraph`] of
+    /// the analysis, rather than walking the history again: the blob of each path each commit
+    /// added or changed, as counted by its [`crate::source::MergeStrategy`], is looked up in
+    /// the commit's tree, from the oldest commit, so each blob is attributed to the commit that
+    /// introduced it. Only the history walked for the graph is covered, i.e., that reachable
+    /// from HEAD in its [`crate::history::HistoryMode`] and sampling.
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        repo_path: &str,
+        graph: &CommitGraph,
+        options: &StorageOptions,
+        cancellation: &CancellationToken,
+    ) -> Result<StorageReport, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let odb = repo.odb()?;
+        let changes = graph.changes()?;
+        let mut blobs: HashMap<Oid, CommittedBlob> = HashMap::new();
+        for (index, node) in graph.commits().iter().enumerate().rev() {
+            cancellation.check(AnalysisStage::Storage, || {
+                PartialResults::SourceFiles(vec![])
+            })?;
+            let paths: BTreeSet<&String> = changes
+                .changed_paths(index)
+                .into_iter()
+                .flatten()
+                .chain(changes.paths_added_by(index))
+                .collect();
+            if paths.is_empty() {
+                continue;
+            }
+            let tree = repo.find_commit(node.id)?.tree()?;
+            for path in paths {
+                // Deleted paths, and submodules, have no blob in the tree
+                let Ok(entry) = tree.get_path(Path::new(path)) else {
+                    continue;
+                };
+                if entry.kind() != Some(ObjectType::Blob) || blobs.contains_key(&entry.id()) {
+                    continue;
+                }
+                let Ok((size, _)) = odb.read_header(entry.id()) else {
+                    continue;
+                };
+                blobs.insert(
+                    entry.id(),
+                    CommittedBlob {
+                        oid: entry.id().to_string(),
+                        path: path.clone(),
+                        size: size as u64,
+                        commit: node.id.to_string(),
+                        committed_at: node.commit_date(),
+                        in_head: false,
+                    },
+                );
+            }
+        }
+        Self::from_blobs(&repo, repo_path, blobs, options)
+    }
+    /// Gets the [`StorageReport`] of the `blobs` committed in the history of the repository
+    /// at `repo_path`, marking those still in the tree of HEAD
+    #[cfg(feature = "git")]
+    fn from_blobs(
+        repo: &Repository,
+        repo_path: &str,
+        mut blobs: HashMap<Oid, CommittedBlob>,
+        options: &StorageOptions,
+    ) -> Result<StorageReport, SourceCodeError> {
+        let mut report = StorageReport {
+            objects: ObjectStoreStatistics::new(repo_path)?,
+            num_blobs: blobs.len(),
+            blob_size: blobs.values().map(|blob| blob.size).sum(),
+            ..Default::default()
+        };
+        if let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) {
+            tree.walk(TreeWalkMode::PreOrder, |_, entry| {
+                if let Some(blob) = blobs.get_mut(&entry.id()) {
+                    if !blob.in_head {
+                        blob.in_head = true;
+                        report.head_blob_size += blob.size;
+                    }
+                }
+                TreeWalkResult::Ok
+            })?;
+        }
+        let mut largest: Vec<CommittedBlob> = blobs
+            .into_values()
+            .filter(|blob| blob.size >= options.min_blob_size)
+            .collect();
+        largest.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        largest.truncate(options.max_blobs);
+        report.largest_blobs = largest;
+        Ok(report)
+    }
+    /// Gets the largest blobs that are no longer in the tree of HEAD, i.e., deleted or replaced
+    /// since, which still bloat every clone; they are the candidates for rewriting the history,
+    /// e.g., with `git filter-repo`
+    pub fn get_deleted_blobs(&self) -> impl Iterator<Item = &CommittedBlob> {
+        self.largest_blobs.iter().filter(|blob| !blob.in_head)
+    }
+    /// Gets the [`StorageReport`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
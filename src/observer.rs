@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::embedding::EmbeddingProgress;
+
+/// The stages of an analysis, reported to [`AnalysisObserver::on_stage_complete`] as each
+/// finishes
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisStage {
+    /// Counting the source files and their change frequency
+    SourceFiles,
+    /// Scanning the source files for tech-debt markers and blaming their authors
+    TechDebt,
+    /// Aggregating languages and detecting the archetype, frameworks, targets, dependencies and
+    /// layout
+    Structure,
+    /// Summarising the commit history and the releases
+    History,
+    /// Walking the commits for the contributors
+    Contributors,
+    /// Walking the commits for the files that change together
+    Coupling,
+    /// Counting the commits of each contributor to each directory for the co-ownership matrix
+    CoOwnership,
+    /// Diffing the commits of the source files for the functions that change most often
+    FunctionChurn,
+    /// Walking the commits for the ownership of the files declared in CODEOWNERS
+    Ownership,
+    /// Comparing the branches with the default branch
+    Branches,
+    /// Looking up the blobs the commits introduced, and scanning the object database, for the
+    /// storage audit
+    Storage,
+    /// Computing the uncommitted changes
+    WorkingTree,
+    /// Computing the derived metrics, the metrics catalog and the quality of the metrics
+    Metrics,
+    /// Embedding the source files into a vector store
+    Embedding,
+}
+
+/// Receives progress events from long analyses, e.g., to show progress bars and ETAs in a GUI
+/// or CLI. All methods do nothing by default, so implementations only override the events they
+/// need.
+///
+/// The observer is passed to [`crate::repository::RepositoryInfo::new_with_observer`] and
+/// [`crate::embedding::create_repository_embedding`].
+pub trait AnalysisObserver: Send + Sync {
+    /// Called after each source file is scanned
+    ///
+    /// #Arguments:
+    /// * `path` - The path of the scanned file
+    /// * `num_scanned` - The number of files scanned so far
+    /// * `num_files` - The number of files known so far; files whose language is detected
+    ///   after `tokei` ran are added as they are found
+    fn on_file_scanned(&self, _path: &Path, _num_scanned: usize, _num_files: usize) {}
+    /// Called after each commit is walked for the commit graph
+    ///
+    /// #Arguments:
+    /// * `commit_id` - The id of the walked commit
+    /// * `num_walked` - The number of commits walked so far
+    /// * `num_commits` - The number of commits reachable from HEAD
+    fn on_commit_walked(&self, _commit_id: &str, _num_walked: usize, _num_commits: usize) {}
+    /// Called when a [`AnalysisStage`] completes, with the time it took
+    fn on_stage_complete(&self, _stage: AnalysisStage, _elapsed: Duration) {}
+    /// Called after each batch of files is embedded, with the [`EmbeddingProgress`] so far
+    fn on_embedding_progress(&self, _progress: &EmbeddingProgress) {}
+}
+
+/// An [`AnalysisObserver`] that ignores all events
+pub struct NoopObserver;
+impl AnalysisObserver for NoopObserver {}
+
+/// The time an [`AnalysisStage`] took
+///
+/// #Fields:
+/// * `stage` - The [`AnalysisStage`]
+/// * `elapsed_ms` - The time the stage took, in milliseconds
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StageTiming {
+    pub stage: AnalysisStage,
+    pub elapsed_ms: i64,
+}
+
+/// Where an analysis spent its time, stage by stage, e.g., to see why a run is slow
+///
+/// #Fields:
+/// * `stages` - The [`StageTiming`] of each stage that ran, in the order they ran
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnalysisTimings {
+    pub stages: Vec<StageTiming>,
+}
+impl AnalysisTimings {
+    /// Gets the time the `stage` took, or `None` if it didn't run
+    pub fn get(&self, stage: AnalysisStage) -> Option<Duration> {
+        self.stages
+            .iter()
+            .find(|timing| timing.stage == stage)
+            .map(|timing| Duration::from_millis(timing.elapsed_ms.max(0) as u64))
+    }
+    /// Gets the [`StageTiming`] of the stage that took the longest, if any ran
+    pub fn slowest(&self) -> Option<&StageTiming> {
+        self.stages.iter().max_by_key(|timing| timing.elapsed_ms)
+    }
+    /// Gets the total time of the stages, in milliseconds
+    pub fn total_ms(&self) -> i64 {
+        self.stages.iter().map(|timing| timing.elapsed_ms).sum()
+    }
+}
+
+/// Times the stages of an analysis, each in an `analysis_stage` tracing span, entered until the
+/// stage completes, with its `stage` and, once it completes, its `elapsed_ms`, reporting each
+/// completed stage to the [`AnalysisObserver`]
+pub(crate) struct StageTimer<'a> {
+    observer: &'a dyn AnalysisObserver,
+    current: Option<(AnalysisStage, Instant, tracing::span::EnteredSpan)>,
+    timings: AnalysisTimings,
+}
+impl<'a> StageTimer<'a> {
+    pub(crate) fn new(observer: &'a dyn AnalysisObserver) -> Self {
+        Self {
+            observer,
+            current: None,
+            timings: AnalysisTimings::default(),
+        }
+    }
+    /// Starts the `stage`, completing the current stage, if any
+    pub(crate) fn start(&mut self, stage: AnalysisStage) {
+        self.complete();
+        let span = tracing::info_span!(
+            "analysis_stage",
+            stage = ?stage,
+            elapsed_ms = tracing::field::Empty
+        )
+        .entered();
+        self.current = Some((stage, Instant::now(), span));
+    }
+    /// Completes the current stage, if any, recording its time
+    pub(crate) fn complete(&mut self) {
+        let Some((stage, started, span)) = self.current.take() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let elapsed_ms = elapsed.as_millis() as i64;
+        span.record("elapsed_ms", elapsed_ms);
+        tracing::debug!(?stage, elapsed_ms, "Completed the analysis stage");
+        self.observer.on_stage_complete(stage, elapsed);
+        self.timings.stages.push(StageTiming { stage, elapsed_ms });
+    }
+    /// Drops the current stage, if any, without recording it, e.g., as it had nothing to do
+    pub(crate) fn skip(&mut self) {
+        self.current = None;
+    }
+    /// Completes the current stage, if any, and gets the [`AnalysisTimings`] of the stages
+    pub(crate) fn finish(mut self) -> AnalysisTimings {
+        self.complete();
+        self.timings
+    }
+}
@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    branches::BranchOptions,
+    cadence::CadenceOptions,
+    checkpoint::CheckpointOptions,
+    churn::FunctionChurnOptions,
+    coownership::CoOwnershipOptions,
+    counting::CountingOptions,
+    coupling::CouplingOptions,
+    derived::DerivedMetric,
+    duplication::DuplicationOptions,
+    errors::SourceCodeError,
+    export::anonymise::AnonymisationOptions,
+    history::{HistoryMode, HistorySampling},
+    identity::IdentityOptions,
+    limits::ResourceLimits,
+    objects::StorageOptions,
+    organisation::OrganisationOptions,
+    quadrant::QuadrantThresholds,
+    shallow::ShallowHandling,
+    source::{ContentMode, FrequencyDenominator, HashAlgorithm, MergeStrategy},
+};
+
+/// The options for analysing a repository into a [`crate::repository::RepositoryInfo`]
+///
+/// #Fields:
+/// * `excluded` - The paths (or patterns) to exclude from the analysis
+/// * `scope` - The subdirectory, relative to the root of the repository, to restrict the analysis
+///   to, e.g., a package of a monorepo, or `None` for the whole repository
+/// * `included` - The paths, relative to the root of the repository, of the files and
+///   directories to restrict the source files to, e.g., the files of a change, or none for all
+///   of them
+/// * `include_working_tree` - Whether to report the
+///   [`crate::worktree::WorkingTreeStatistics`] of the uncommitted changes in the working tree
+/// * `derived_metrics` - The [`DerivedMetric`]s to compute for each source file and the repository
+/// * `include_uncommitted` - Whether to read the source files from the working tree, including
+///   its uncommitted changes and untracked files, rather than from the blobs of the commit at
+///   HEAD, so that the statistics and hashes match HEAD even if the working tree is dirty
+/// * `recurse_submodules` - Whether to analyse each checked out git submodule as a nested
+///   [`crate::repository::RepositoryInfo`]
+/// * `merge_strategy` - The [`MergeStrategy`] for counting merge commits in the change frequency
+///   of each source file
+/// * `history_mode` - The [`HistoryMode`] of the commits walked for the history, contributors and
+///   change frequencies, e.g., only the first-parent line from HEAD
+/// * `compare_history_modes` - Whether to report the [`crate::history::CommitCounts`] of both
+///   [`HistoryMode`]s, whichever is used
+/// * `frequency_denominator` - The [`FrequencyDenominator`], i.e., the commits the change
+///   frequency of each source file is a share of
+/// * `history_sampling` - The [`HistorySampling`] that limits the commits walked, e.g., for
+///   repositories with hundreds of thousands of commits, which is recorded in the metadata
+/// * `hash_algorithm` - The [`HashAlgorithm`] of the `id_hash` of each source file
+/// * `content_mode` - The [`ContentMode`], i.e., whether the contents of the source files are
+///   kept once the analysis is done
+/// * `limits` - The [`ResourceLimits`] on the threads, IO and priority of the analysis
+/// * `coupling` - The [`CouplingOptions`] for the temporal coupling analysis, which is only run
+///   if set
+/// * `function_churn` - The [`FunctionChurnOptions`] for the function-level churn analysis,
+///   which is only run if set
+/// * `duplication` - The [`DuplicationOptions`] for the duplicate code detection, which is only
+///   run if set
+/// * `branches` - The [`BranchOptions`] for the branch analysis, which is only run if set
+/// * `storage` - The [`StorageOptions`] for the storage audit of the object database, which is
+///   only run if set, as it looks up the blob of each path each commit changed, and scans
+///   every object
+/// * `co_ownership` - The [`CoOwnershipOptions`] for the co-ownership matrix of the
+///   contributors and directories, which is only computed if set
+/// * `verify_line_counts` - Whether to recount the physical lines of each source file and warn
+///   of files where `tokei`'s code, comment and blank lines don't add up to them
+/// * `unsorted` - Whether to leave the source files and contributors in the order they are
+///   found, which can differ between runs, rather than sorting them by path and name
+/// * `exclude_generated` - Whether to exclude the generated source files from the analysis,
+///   rather than only tagging them
+/// * `exclude_ci` - Whether to exclude the CI definitions from the analysis, e.g., from the
+///   lines of code, rather than only tagging them with [`crate::role::FileRole::Ci`]
+/// * `quadrant_thresholds` - The [`QuadrantThresholds`] the source files are classified into
+///   [`crate::quadrant::ChurnQuadrant`]s by
+/// * `count_identical_once` - Whether to count each group of identical source files once in the
+///   lines of code and size of the repository's [`crate::data::Statistics`], rather than once
+///   per copy
+/// * `cadence` - The [`CadenceOptions`] for the [`crate::cadence::ContributorCadence`] of each
+///   contributor
+/// * `organisations` - The [`OrganisationOptions`] mapping the email domains of the commit
+///   authors to the organisations of the [`crate::organisation::OrganisationReport`]
+/// * `shallow` - The [`ShallowHandling`] of a shallow clone, i.e., whether more of its history is
+///   fetched before it is analysed
+/// * `counting` - The [`CountingOptions`] for counting the lines of code with `tokei`, e.g., to
+///   count hidden files or custom languages
+/// * `identity` - The [`IdentityOptions`] for resolving the authors of the commits, e.g., with
+///   the mailmap, for the contributors, tech debt markers and ownership, and for detecting and
+///   counting the commits of bots
+/// * `run_stats` - Whether to report the [`crate::runstats::RunStats`] of the work the analysis
+///   did in its metadata
+/// * `index_symbols` - Whether to index the [`crate::symbols::Symbol`]s, e.g., the functions and
+///   types, defined in each source file whose contents are read; needs the `tree-sitter` feature
+/// * `anonymisation` - The [`AnonymisationOptions`] the [`crate::repository::RepositoryInfo`] is
+///   anonymised with, once analysed, e.g., to share it externally; without them, it isn't
+/// * `checkpoint` - The [`CheckpointOptions`] for checkpointing the analysis, e.g., of a massive
+///   repository, so that it can be resumed if interrupted; not serialised, so it doesn't change
+///   the metadata or fingerprint of the analysis
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct AnalysisOptions {
+    pub excluded: Vec<String>,
+    pub scope: Option<String>,
+    pub included: Vec<String>,
+    pub include_working_tree: bool,
+    pub derived_metrics: Vec<DerivedMetric>,
+    pub include_uncommitted: bool,
+    pub recurse_submodules: bool,
+    pub merge_strategy: MergeStrategy,
+    pub history_mode: HistoryMode,
+    pub compare_history_modes: bool,
+    pub frequency_denominator: FrequencyDenominator,
+    pub history_sampling: HistorySampling,
+    pub hash_algorithm: HashAlgorithm,
+    pub content_mode: ContentMode,
+    pub limits: ResourceLimits,
+    pub coupling: Option<CouplingOptions>,
+    pub function_churn: Option<FunctionChurnOptions>,
+    pub duplication: Option<DuplicationOptions>,
+    pub branches: Option<BranchOptions>,
+    pub storage: Option<StorageOptions>,
+    pub co_ownership: Option<CoOwnershipOptions>,
+    pub verify_line_counts: bool,
+    pub unsorted: bool,
+    pub exclude_generated: bool,
+    pub exclude_ci: bool,
+    pub quadrant_thresholds: QuadrantThresholds,
+    pub count_identical_once: bool,
+    pub cadence: CadenceOptions,
+    pub organisations: OrganisationOptions,
+    pub shallow: ShallowHandling,
+    pub counting: CountingOptions,
+    pub identity: IdentityOptions,
+    pub run_stats: bool,
+    pub index_symbols: bool,
+    pub anonymisation: Option<AnonymisationOptions>,
+    #[serde(skip_serializing)]
+    pub checkpoint: Option<CheckpointOptions>,
+}
+impl AnalysisOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Reads the [`AnalysisOptions`] from a TOML configuration, e.g.:
+    ///
+    /// ```toml
+    /// excluded = ["target"]
+    ///
+    /// [limits]
+    /// max_threads = 2
+    /// low_priority = true
+    ///
+    /// [[derived_metrics]]
+    /// name = "churn"
+    /// expression = "loc * num_commits / 100"
+    /// ```
+    pub fn from_toml(config: &str) -> Result<Self, SourceCodeError> {
+        let options: AnalysisOptions =
+            toml::from_str(config).map_err(|err| SourceCodeError::ConfigError(err.to_string()))?;
+        for metric in &options.derived_metrics {
+            metric.get_variables()?;
+        }
+        options.counting.validate()?;
+        options.history_sampling.validate()?;
+        Ok(options)
+    }
+    /// Excludes the `excluded` paths (or patterns) from the analysis
+    pub fn with_excluded(mut self, excluded: &[&str]) -> Self {
+        self.excluded = excluded.iter().map(|e| e.to_string()).collect();
+        self
+    }
+    /// Restricts the analysis to the files under the `scope` subdirectory, e.g., a package of a
+    /// monorepo: the source files, totals, history and contributors only cover the scope, while
+    /// the change frequency of each file is still counted over the whole history
+    pub fn with_scope(mut self, scope: &str) -> Self {
+        self.scope = Some(scope.to_string());
+        self
+    }
+    /// Restricts the source files to those at, or under, the `included` paths, e.g., the files a
+    /// change touches, for a fast check of them; the history and contributors still cover the
+    /// whole repository
+    pub fn with_included(mut self, included: &[&str]) -> Self {
+        self.included = included.iter().map(|i| i.to_string()).collect();
+        self
+    }
+    /// Reports the [`crate::worktree::WorkingTreeStatistics`] of the uncommitted changes in the
+    /// working tree, whether or not the source files are read from it
+    pub fn with_working_tree(mut self, include_working_tree: bool) -> Self {
+        self.include_working_tree = include_working_tree;
+        self
+    }
+    /// Reads the source files from the tree at HEAD (the object database), which is the default,
+    /// rather than the working tree, so that files being written, or uncommitted changes, don't
+    /// affect the results
+    pub fn with_snapshot(mut self, snapshot: bool) -> Self {
+        self.include_uncommitted = !snapshot;
+        self
+    }
+    /// Reads the source files from the working tree, including its uncommitted changes and
+    /// untracked files, rather than from the tree at HEAD; a repository without commits is
+    /// always read from its working tree
+    pub fn with_uncommitted_changes(mut self, include_uncommitted: bool) -> Self {
+        self.include_uncommitted = include_uncommitted;
+        self
+    }
+    /// Analyses each checked out git submodule, with these options, as a nested
+    /// [`crate::repository::RepositoryInfo`]. Submodule files are never counted in the parent
+    /// repository's statistics.
+    pub fn with_recurse_submodules(mut self, recurse_submodules: bool) -> Self {
+        self.recurse_submodules = recurse_submodules;
+        self
+    }
+    /// Counts merge commits in the change frequency of each source file with `merge_strategy`
+    pub fn with_merge_strategy(mut self, merge_strategy: MergeStrategy) -> Self {
+        self.merge_strategy = merge_strategy;
+        self
+    }
+    /// Counts the change frequency of each source file as a share of the commits of the
+    /// `frequency_denominator`, e.g., those since the file was added
+    pub fn with_frequency_denominator(
+        mut self,
+        frequency_denominator: FrequencyDenominator,
+    ) -> Self {
+        self.frequency_denominator = frequency_denominator;
+        self
+    }
+    /// Walks the commits of the [`HistoryMode`] for the history, contributors and change
+    /// frequencies, e.g., [`HistoryMode::FirstParent`] so that the work of merged branches isn't
+    /// counted twice
+    pub fn with_history_mode(mut self, history_mode: HistoryMode) -> Self {
+        self.history_mode = history_mode;
+        self
+    }
+    /// Reports the number of commits in both [`HistoryMode`]s, as
+    /// [`crate::history::CommitCounts`], whichever is used
+    pub fn with_history_mode_comparison(mut self, compare_history_modes: bool) -> Self {
+        self.compare_history_modes = compare_history_modes;
+        self
+    }
+    /// Walks at most the commits the [`HistorySampling`] allows, trading the accuracy of the
+    /// history, contributors and change frequencies for speed, as recorded in the
+    /// [`crate::metadata::AnalysisMetadata`]
+    pub fn with_history_sampling(mut self, history_sampling: HistorySampling) -> Self {
+        self.history_sampling = history_sampling;
+        self
+    }
+    /// Hashes the raw bytes of each source file for its `id_hash` with `hash_algorithm`
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+    /// Keeps the contents of the source files, once the analysis is done, as the
+    /// `content_mode` sets
+    pub fn with_content_mode(mut self, content_mode: ContentMode) -> Self {
+        self.content_mode = content_mode;
+        self
+    }
+    /// Limits the threads, IO and priority of the analysis with `limits`
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    /// Analyses which source files change together, with the [`CouplingOptions`]
+    pub fn with_coupling(mut self, coupling: CouplingOptions) -> Self {
+        self.coupling = Some(coupling);
+        self
+    }
+    /// Attributes the lines changed by each commit to the functions they are in, for the most
+    /// frequently modified functions, with the [`FunctionChurnOptions`]; needs the `tree-sitter`
+    /// feature
+    pub fn with_function_churn(mut self, function_churn: FunctionChurnOptions) -> Self {
+        self.function_churn = Some(function_churn);
+        self
+    }
+    /// Finds the code duplicated across (or within) source files, with the [`DuplicationOptions`]
+    pub fn with_duplication(mut self, duplication: DuplicationOptions) -> Self {
+        self.duplication = Some(duplication);
+        self
+    }
+    /// Compares each branch with the default branch, flagging stale branches, with the
+    /// [`BranchOptions`]
+    pub fn with_branches(mut self, branches: BranchOptions) -> Self {
+        self.branches = Some(branches);
+        self
+    }
+    /// Audits the storage of the object database, e.g., its pack files and the largest blobs
+    /// ever committed, with the [`StorageOptions`]
+    pub fn with_storage(mut self, storage: StorageOptions) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+    /// Computes the co-ownership matrix of the commits of each contributor to each directory,
+    /// with the [`CoOwnershipOptions`]
+    pub fn with_co_ownership(mut self, co_ownership: CoOwnershipOptions) -> Self {
+        self.co_ownership = Some(co_ownership);
+        self
+    }
+    /// Recounts the physical lines of each source file, adding a
+    /// [`crate::warning::WarningKind::LineCountMismatch`] warning for each file where `tokei`'s
+    /// counts don't add up to them, e.g., because its language was misdetected
+    pub fn with_line_count_verification(mut self, verify_line_counts: bool) -> Self {
+        self.verify_line_counts = verify_line_counts;
+        self
+    }
+    /// Sorts the source files by path, and the contributors by name, so that two analyses of
+    /// the same repository give the same output, e.g., for diff-based checks; `sorted` is the
+    /// default, and unsorted output saves the sorting on large repositories
+    pub fn with_sorting(mut self, sorted: bool) -> Self {
+        self.unsorted = !sorted;
+        self
+    }
+    /// Excludes the generated source files, e.g., those with a '@generated' marker or
+    /// lockfiles, from the analysis, rather than only tagging them as `is_generated`
+    pub fn with_generated_excluded(mut self, exclude_generated: bool) -> Self {
+        self.exclude_generated = exclude_generated;
+        self
+    }
+    /// Excludes the CI definitions, e.g., in '.github/workflows', from the analysis, rather
+    /// than only tagging them with [`crate::role::FileRole::Ci`]
+    pub fn with_ci_excluded(mut self, exclude_ci: bool) -> Self {
+        self.exclude_ci = exclude_ci;
+        self
+    }
+    /// Classifies the source files into [`crate::quadrant::ChurnQuadrant`]s by the
+    /// [`QuadrantThresholds`], rather than the default ones
+    pub fn with_quadrant_thresholds(mut self, quadrant_thresholds: QuadrantThresholds) -> Self {
+        self.quadrant_thresholds = quadrant_thresholds;
+        self
+    }
+    /// Counts each group of identical source files, i.e., with the same `id_hash`, once in the
+    /// repository's lines of code and size, e.g., for vendored copies, rather than once per copy
+    pub fn with_identical_files_counted_once(mut self, count_identical_once: bool) -> Self {
+        self.count_identical_once = count_identical_once;
+        self
+    }
+    /// Gets the [`crate::cadence::ContributorCadence`] of each contributor with the
+    /// [`CadenceOptions`], e.g., over a longer trend window
+    pub fn with_cadence_options(mut self, cadence: CadenceOptions) -> Self {
+        self.cadence = cadence;
+        self
+    }
+    /// Attributes the commits to organisations by the email domains of their authors with the
+    /// [`OrganisationOptions`], e.g., 'acme.com' to 'ACME'
+    pub fn with_organisations(mut self, organisations: OrganisationOptions) -> Self {
+        self.organisations = organisations;
+        self
+    }
+    /// Fetches more of the history of a shallow clone from its 'origin' remote before it is
+    /// analysed, as the [`ShallowHandling`] sets
+    pub fn with_shallow_handling(mut self, shallow: ShallowHandling) -> Self {
+        self.shallow = shallow;
+        self
+    }
+    /// Counts the lines of code with `tokei` as the [`CountingOptions`] set, e.g., to match
+    /// the counts of the `tokei` CLI with the same settings
+    pub fn with_counting(mut self, counting: CountingOptions) -> Self {
+        self.counting = counting;
+        self
+    }
+    /// Resolves the authors of the commits as the [`IdentityOptions`] set, e.g., mapping them
+    /// with the mailmap and grouping their commits by email address
+    pub fn with_identity(mut self, identity: IdentityOptions) -> Self {
+        self.identity = identity;
+        self
+    }
+    /// Reports the [`crate::runstats::RunStats`] of the work the analysis did, e.g., the files
+    /// scanned and commits walked, in its [`crate::metadata::AnalysisMetadata`]
+    pub fn with_run_stats(mut self, run_stats: bool) -> Self {
+        self.run_stats = run_stats;
+        self
+    }
+    /// Indexes the [`crate::symbols::Symbol`]s defined in each source file, with their line
+    /// ranges, e.g., to attribute changes and embedding chunks to functions; without the
+    /// `tree-sitter` feature, or for languages without a grammar, no symbols are indexed
+    pub fn with_symbols(mut self, index_symbols: bool) -> Self {
+        self.index_symbols = index_symbols;
+        self
+    }
+    /// Anonymises the analysis with the [`AnonymisationOptions`], see
+    /// [`crate::export::anonymise::PathHasher`]: its paths are redacted, and its contributors
+    /// pseudonymised if set, while the source files are still analysed as they are
+    pub fn with_anonymisation(mut self, anonymisation: AnonymisationOptions) -> Self {
+        self.anonymisation = Some(anonymisation);
+        self
+    }
+    /// Checkpoints the commits diffed and the source files scanned, with the
+    /// [`CheckpointOptions`], so that the analysis can be resumed with
+    /// [`crate::repository::RepositoryInfo::resume_from_checkpoint`] if interrupted
+    pub fn with_checkpoint(mut self, checkpoint: CheckpointOptions) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+    /// Computes the [`DerivedMetric`]s, in order, for each source file and the repository
+    pub fn with_derived_metrics(mut self, derived_metrics: Vec<DerivedMetric>) -> Self {
+        self.derived_metrics = derived_metrics;
+        self
+    }
+}
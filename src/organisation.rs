@@ -0,0 +1,226 @@
+//! The roll-up of a repository's contributions by organisation, from the email domains of the
+//! commit authors, e.g., to see which companies drive an open-source project
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{data::saturating_sum, ratio::Percentage};
+#[cfg(feature = "git")]
+use crate::{errors::SourceCodeError, graph::CommitGraph, source::SourceFileInfo};
+
+/// The organisation of the commits whose author has no email address, or one without a domain
+pub const UNKNOWN_ORGANISATION: &str = "unknown";
+
+/// The options for attributing commits to organisations
+///
+/// #Fields:
+/// * `domains` - The organisation of each email domain, e.g., 'acme.com' to 'ACME'; a domain
+///   also maps its subdomains, e.g., 'eng.acme.com', and the commits of an unmapped domain are
+///   attributed to an organisation named after the domain
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct OrganisationOptions {
+    pub domains: BTreeMap<String, String>,
+}
+impl OrganisationOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Attributes the email addresses of the `domain`, and of its subdomains, to the
+    /// `organisation`
+    pub fn with_domain(mut self, domain: &str, organisation: &str) -> Self {
+        self.domains
+            .insert(domain.to_lowercase(), organisation.to_string());
+        self
+    }
+    /// Gets the organisation and (lowercased) domain of the `email` address, as mapped by the
+    /// `domains`, or [`UNKNOWN_ORGANISATION`] if it has no domain
+    pub fn get_organisation(&self, email: Option<&str>) -> (String, Option<String>) {
+        let Some(domain) = email
+            .and_then(|email| email.rsplit_once('@'))
+            .map(|(_, domain)| domain.trim_end_matches('>').to_lowercase())
+            .filter(|domain| !domain.is_empty())
+        else {
+            return (UNKNOWN_ORGANISATION.to_string(), None);
+        };
+        // The most specific mapped domain wins, e.g., 'eng.acme.com' over 'acme.com'
+        let mut parent: &str = &domain;
+        loop {
+            if let Some(organisation) = self.domains.get(parent) {
+                return (organisation.clone(), Some(domain));
+            }
+            match parent.split_once('.') {
+                Some((_, rest)) if rest.contains('.') => parent = rest,
+                _ => return (domain.clone(), Some(domain)),
+            }
+        }
+    }
+}
+
+/// The contributions of an organisation
+///
+/// #Fields:
+/// * `organisation` - The name of the organisation
+/// * `domains` - The email domains of its commits, in alphabetical order
+/// * `num_contributors` - The number of contributors with a commit from one of the `domains`
+/// * `num_commits` - The number of commits from one of the `domains`
+/// * `commit_share` - The [`Percentage`] of the repository's commits that are the
+///   organisation's
+/// * `loc` - The lines of code of the source files whose top contributor, by the commits
+///   changing them, is the organisation
+/// * `loc_share` - The [`Percentage`] of the lines of code of the source files with commits
+///   that are the organisation's
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OrganisationContribution {
+    pub organisation: String,
+    pub domains: Vec<String>,
+    pub num_contributors: u64,
+    pub num_commits: u64,
+    pub commit_share: Percentage,
+    pub loc: u64,
+    pub loc_share: Percentage,
+}
+
+/// The contributions to a repository by organisation, as attributed by the
+/// [`OrganisationOptions`]
+///
+/// #Fields:
+/// * `organisations` - The [`OrganisationContribution`]s, most commits first
+/// * `unattributed_loc` - The lines of code of the source files without commits, e.g.,
+///   untracked files, which have no organisation
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OrganisationReport {
+    pub organisations: Vec<OrganisationContribution>,
+    pub unattributed_loc: u64,
+}
+impl OrganisationReport {
+    /// Gets the [`OrganisationReport`] of the commits of the [`CommitGraph`], attributing the
+    /// lines of code of each of the `source_files` to the organisation with the most commits
+    /// changing it
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        graph: &CommitGraph,
+        source_files: &[SourceFileInfo],
+        options: &OrganisationOptions,
+    ) -> Result<OrganisationReport, SourceCodeError> {
+        let changes = graph.changes()?;
+
+        // The domains, contributors and commits of each organisation, and the organisation of
+        // each commit
+        let mut organisations: BTreeMap<String, (BTreeSet<String>, BTreeSet<&str>, u64)> =
+            BTreeMap::new();
+        let mut commit_organisations: Vec<String> = Vec::with_capacity(graph.commits().len());
+        for commit in graph.commits() {
+            let (organisation, domain) = options.get_organisation(commit.author.email.as_deref());
+            let (domains, contributors, num_commits) =
+                organisations.entry(organisation.clone()).or_default();
+            domains.extend(domain);
+            contributors.insert(commit.author.canonical_id.as_str());
+            *num_commits += 1;
+            commit_organisations.push(organisation);
+        }
+
+        let mut organisation_loc: HashMap<&str, u64> = HashMap::new();
+        let mut unattributed_loc: u64 = 0;
+        for source_file in source_files {
+            let path = source_file.relative_path.as_str();
+            // The root commits add the files in their trees, so count as changing them
+            let mut file_commits: HashMap<&str, u64> = HashMap::new();
+            for &index in changes
+                .commits_adding(path)
+                .iter()
+                .chain(changes.commits_changing(path))
+            {
+                *file_commits
+                    .entry(commit_organisations[index].as_str())
+                    .or_default() += 1;
+            }
+            let top = file_commits
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(organisation, _)| organisation);
+            match top {
+                Some(organisation) => {
+                    let loc = organisation_loc.entry(organisation).or_default();
+                    *loc = loc.saturating_add(source_file.statistics.loc);
+                }
+                None => {
+                    unattributed_loc = unattributed_loc.saturating_add(source_file.statistics.loc)
+                }
+            }
+        }
+
+        let total_commits = graph.commits().len() as f64;
+        let attributed_loc = saturating_sum(organisation_loc.values().copied()) as f64;
+        let mut organisations: Vec<OrganisationContribution> = organisations
+            .into_iter()
+            .map(|(organisation, (domains, contributors, num_commits))| {
+                let loc = organisation_loc
+                    .get(organisation.as_str())
+                    .copied()
+                    .unwrap_or(0);
+                OrganisationContribution {
+                    domains: domains.into_iter().collect(),
+                    num_contributors: contributors.len() as u64,
+                    num_commits,
+                    commit_share: Percentage::of(num_commits as f64, total_commits)
+                        .unwrap_or_default(),
+                    loc,
+                    loc_share: Percentage::of(loc as f64, attributed_loc).unwrap_or_default(),
+                    organisation,
+                }
+            })
+            .collect();
+        Self::sort(&mut organisations);
+        Ok(OrganisationReport {
+            organisations,
+            unattributed_loc,
+        })
+    }
+    /// Adds the contributions of the `other` report, e.g., of another root, to those of the
+    /// same organisation, recomputing the shares. A contributor to both is counted in each.
+    pub(crate) fn merge(&mut self, other: &OrganisationReport) {
+        for contribution in &other.organisations {
+            match self
+                .organisations
+                .iter_mut()
+                .find(|existing| existing.organisation == contribution.organisation)
+            {
+                Some(existing) => {
+                    let domains: BTreeSet<String> = existing
+                        .domains
+                        .drain(..)
+                        .chain(contribution.domains.clone())
+                        .collect();
+                    existing.domains = domains.into_iter().collect();
+                    existing.num_contributors += contribution.num_contributors;
+                    existing.num_commits += contribution.num_commits;
+                    existing.loc = existing.loc.saturating_add(contribution.loc);
+                }
+                None => self.organisations.push(contribution.clone()),
+            }
+        }
+        self.unattributed_loc = self.unattributed_loc.saturating_add(other.unattributed_loc);
+        let total_commits = saturating_sum(self.organisations.iter().map(|o| o.num_commits)) as f64;
+        let attributed_loc = saturating_sum(self.organisations.iter().map(|o| o.loc)) as f64;
+        for contribution in &mut self.organisations {
+            contribution.commit_share =
+                Percentage::of(contribution.num_commits as f64, total_commits).unwrap_or_default();
+            contribution.loc_share =
+                Percentage::of(contribution.loc as f64, attributed_loc).unwrap_or_default();
+        }
+        Self::sort(&mut self.organisations);
+    }
+    /// Sorts the `organisations` by their commits, most first, then by name
+    fn sort(organisations: &mut [OrganisationContribution]) {
+        organisations.sort_by(|a, b| {
+            b.num_commits
+                .cmp(&a.num_commits)
+                .then_with(|| a.organisation.cmp(&b.organisation))
+        });
+    }
+}
@@ -0,0 +1,396 @@
+//! The packages of a monorepo, as declared by its workspaces, with the statistics of each
+#[cfg(feature = "git")]
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+#[cfg(feature = "git")]
+use crate::{
+    cadence::CadenceOptions, data::to_count, errors::SourceCodeError, graph::CommitGraph,
+    history::CommitHistory, identity::BotHandling, repository::RepositoryInfo, retention,
+    source::SourceFileInfo,
+};
+use crate::{
+    data::Statistics, dependencies::DependencyEcosystem, repository::Contributor,
+    source::LanguageType, walk,
+};
+
+/// A package declared by a workspace of a repository
+///
+/// #Fields:
+/// * `name` - The name of the package, from its manifest, or else its directory
+/// * `path` - The directory of the package, relative to the root of the repository
+/// * `ecosystem` - The [`DependencyEcosystem`] of the workspace that declares it
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub path: String,
+    pub ecosystem: DependencyEcosystem,
+}
+impl WorkspacePackage {
+    /// Detects the packages of the repository at `repo_path`: the members of a Cargo workspace,
+    /// the `workspaces` of 'package.json' or 'pnpm-workspace.yaml', and the Go modules used by
+    /// 'go.work' or nested under the root
+    ///
+    /// #Returns:
+    /// * The [`WorkspacePackage`]s, ordered by path; empty if the repository declares no
+    ///   workspace
+    pub fn detect(repo_path: &str) -> Vec<WorkspacePackage> {
+        let root = Path::new(repo_path);
+        let mut packages: BTreeMap<String, WorkspacePackage> = BTreeMap::new();
+        let mut add = |path: String, ecosystem: DependencyEcosystem| {
+            let name = Self::get_name(root, &path, ecosystem)
+                .unwrap_or_else(|| path.rsplit('/').next().unwrap_or_default().to_string());
+            packages.entry(path.clone()).or_insert(WorkspacePackage {
+                name,
+                path,
+                ecosystem,
+            });
+        };
+        for path in Self::cargo_members(root) {
+            add(path, DependencyEcosystem::Cargo);
+        }
+        for path in Self::npm_workspaces(root) {
+            add(path, DependencyEcosystem::Npm);
+        }
+        for path in Self::go_modules(repo_path) {
+            add(path, DependencyEcosystem::Go);
+        }
+        packages.into_values().collect()
+    }
+    /// Gets the directories of the `members` of the `[workspace]` of the root 'Cargo.toml',
+    /// without its `exclude`d ones
+    fn cargo_members(root: &Path) -> Vec<String> {
+        let Some(manifest) =
+            read_file(root, "Cargo.toml").and_then(|contents| contents.parse::<toml::Table>().ok())
+        else {
+            return vec![];
+        };
+        let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+            return vec![];
+        };
+        let patterns = |key: &str| -> Vec<String> {
+            workspace
+                .get(key)
+                .and_then(|patterns| patterns.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|pattern| pattern.as_str().map(str::to_string))
+                .collect()
+        };
+        expand_patterns(
+            root,
+            &patterns("members"),
+            &patterns("exclude"),
+            "Cargo.toml",
+        )
+    }
+    /// Gets the directories of the `workspaces` of the root 'package.json', as a list or the
+    /// `packages` of an object, or else of the `packages` of 'pnpm-workspace.yaml'; patterns
+    /// starting with '!' exclude directories
+    fn npm_workspaces(root: &Path) -> Vec<String> {
+        let mut patterns: Vec<String> = read_file(root, "package.json")
+            .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+            .and_then(|package| {
+                let workspaces = package.get("workspaces")?;
+                workspaces
+                    .as_array()
+                    .or_else(|| workspaces.get("packages")?.as_array())
+                    .cloned()
+            })
+            .into_iter()
+            .flatten()
+            .filter_map(|pattern| pattern.as_str().map(str::to_string))
+            .collect();
+        if patterns.is_empty() {
+            patterns = read_file(root, "pnpm-workspace.yaml")
+                .map(|contents| parse_pnpm_packages(&contents))
+                .unwrap_or_default();
+        }
+        let (excluded, included): (Vec<String>, Vec<String>) = patterns
+            .into_iter()
+            .partition(|pattern| pattern.starts_with('!'));
+        let excluded: Vec<String> = excluded
+            .iter()
+            .map(|pattern| pattern.trim_start_matches('!').to_string())
+            .collect();
+        expand_patterns(root, &included, &excluded, "package.json")
+    }
+    /// Gets the directories of the modules used by 'go.work', and of the 'go.mod' files under
+    /// the root, other than its own
+    fn go_modules(repo_path: &str) -> Vec<String> {
+        let root = Path::new(repo_path);
+        let mut modules: Vec<String> = read_file(root, "go.work")
+            .map(|contents| parse_go_work(&contents))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|path| normalise_dir(&path))
+            .filter(|path| !path.is_empty() && root.join(path).join("go.mod").is_file())
+            .collect();
+        modules.extend(
+            walk::find_files(repo_path, |name| name == "go.mod")
+                .into_iter()
+                .filter_map(|(relative_path, _)| {
+                    let (dir, _) = relative_path.rsplit_once('/')?;
+                    Some(dir.to_string())
+                }),
+        );
+        modules
+    }
+    /// Gets the name of the package at `path` from its manifest, i.e., the `package.name` of
+    /// 'Cargo.toml', the `name` of 'package.json' or the `module` of 'go.mod'
+    fn get_name(root: &Path, path: &str, ecosystem: DependencyEcosystem) -> Option<String> {
+        let dir = root.join(path);
+        match ecosystem {
+            DependencyEcosystem::Cargo => read_file(&dir, "Cargo.toml")?
+                .parse::<toml::Table>()
+                .ok()?
+                .get("package")?
+                .get("name")?
+                .as_str()
+                .map(str::to_string),
+            DependencyEcosystem::Npm => {
+                serde_json::from_str::<serde_json::Value>(&read_file(&dir, "package.json")?)
+                    .ok()?
+                    .get("name")?
+                    .as_str()
+                    .map(str::to_string)
+            }
+            DependencyEcosystem::Go => read_file(&dir, "go.mod")?
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("module "))
+                .map(|module| module.trim().trim_matches('"').to_string()),
+            DependencyEcosystem::Python => None,
+        }
+    }
+}
+
+/// The statistics of a package of a monorepo
+///
+/// #Fields:
+/// * `name` - The name of the package, from its manifest, or else its directory
+/// * `path` - The directory of the package, relative to the root of the repository
+/// * `ecosystem` - The [`DependencyEcosystem`] of the workspace that declares it
+/// * `statistics` - The [`Statistics`] on the source files of the package, with the commits
+///   changing them
+/// * `predominant_language` - The predominant [`LanguageType`] of the package, if it has any
+///   source files in a known language
+/// * `contributors` - The [`Contributor`]s of the commits changing the package
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PackageInfo {
+    pub name: String,
+    pub path: String,
+    pub ecosystem: DependencyEcosystem,
+    pub statistics: Statistics,
+    pub predominant_language: Option<LanguageType>,
+    pub contributors: Vec<Contributor>,
+}
+impl PackageInfo {
+    /// Breaks the `source_files` down by the `packages` they are in, i.e., the innermost
+    /// package whose directory holds them, with the history and contributors of each from the
+    /// commits of the `graph` changing it. Packages without source files, e.g., outside the
+    /// scope of the analysis, are left out.
+    ///
+    /// #Arguments:
+    /// * `packages` - The [`WorkspacePackage`]s of the repository
+    /// * `source_files` - The [`SourceFileInfo`]s of the repository
+    /// * `graph` - The [`CommitGraph`] of the whole history
+    /// * `at` - The time of the analysis, as of which the contributors' activity is measured
+    /// * `cadence` - The [`CadenceOptions`] for the [`crate::cadence::ContributorCadence`] of
+    ///   the contributors
+    /// * `bots` - The [`BotHandling`] of the contributors' commits authored by bots
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        packages: Vec<WorkspacePackage>,
+        source_files: &[SourceFileInfo],
+        graph: &CommitGraph,
+        at: DateTime<Utc>,
+        cadence: &CadenceOptions,
+        bots: BotHandling,
+    ) -> Result<Vec<PackageInfo>, SourceCodeError> {
+        let active_since = at - Duration::days(retention::ACTIVE_WINDOW_DAYS);
+        let mut package_files: Vec<Vec<&SourceFileInfo>> = vec![Vec::new(); packages.len()];
+        for source_file in source_files {
+            let innermost = packages
+                .iter()
+                .enumerate()
+                .filter(|(_, package)| {
+                    source_file
+                        .relative_path
+                        .strip_prefix(package.path.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+                })
+                .max_by_key(|(_, package)| package.path.len());
+            if let Some((index, _)) = innermost {
+                package_files[index].push(source_file);
+            }
+        }
+        let mut package_infos: Vec<PackageInfo> = Vec::new();
+        for (package, files) in packages.into_iter().zip(package_files) {
+            if files.is_empty() {
+                continue;
+            }
+            let package_graph = graph.scoped(&package.path)?;
+            let mut statistics: Statistics = files.iter().map(|file| &file.statistics).sum();
+            // The commits of a package are those of its history, not the sum of its files'
+            let history = CommitHistory::from_graph(&package_graph);
+            statistics.num_commits = history
+                .as_ref()
+                .map_or(0, |history| to_count(history.num_commits));
+            statistics.bugfix_commits = history
+                .as_ref()
+                .map_or(0, |history| to_count(history.commit_messages.num_bugfix));
+            let languages = RepositoryInfo::get_languages(files.iter().copied());
+            let predominant_language =
+                (!languages.is_empty()).then(|| LanguageType::get_predominant_language(&languages));
+            let mut contributors = Contributor::from_graph(&package_graph, at, cadence, bots);
+            for contributor in &mut contributors {
+                contributor.is_active = contributor.last_contribution >= active_since;
+            }
+            contributors.sort_by(|a, b| {
+                b.statistics
+                    .num_commits
+                    .cmp(&a.statistics.num_commits)
+                    .then_with(|| a.author.name.cmp(&b.author.name))
+            });
+            package_infos.push(PackageInfo {
+                name: package.name,
+                path: package.path,
+                ecosystem: package.ecosystem,
+                statistics,
+                predominant_language,
+                contributors,
+            });
+        }
+        Ok(package_infos)
+    }
+}
+
+/// Reads the file `name` in the directory `dir`, if it can be read
+fn read_file(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok()
+}
+
+/// Gets a directory of a workspace pattern in the stored form, i.e., without a leading './'
+/// or trailing '/'
+fn normalise_dir(dir: &str) -> String {
+    dir.trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// Expands the `included` workspace patterns, e.g., 'crates/*', into the directories under
+/// `root` that match them, hold the `manifest` and don't match the `excluded` patterns
+fn expand_patterns(
+    root: &Path,
+    included: &[String],
+    excluded: &[String],
+    manifest: &str,
+) -> Vec<String> {
+    let excluded: Vec<String> = excluded
+        .iter()
+        .flat_map(|pattern| expand_pattern(root, pattern))
+        .collect();
+    included
+        .iter()
+        .flat_map(|pattern| expand_pattern(root, pattern))
+        .filter(|dir| !excluded.contains(dir))
+        .filter(|dir| root.join(dir).join(manifest).is_file())
+        .collect()
+}
+
+/// Expands a workspace `pattern`, whose segments may hold '*' wildcards, into the directories
+/// under `root` that match it, skipping hidden ones
+fn expand_pattern(root: &Path, pattern: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = vec![String::new()];
+    for segment in normalise_dir(pattern).split('/') {
+        let join = |dir: &str, name: &str| match dir.is_empty() {
+            true => name.to_string(),
+            false => format!("{}/{}", dir, name),
+        };
+        dirs = dirs
+            .into_iter()
+            .flat_map(|dir| match segment.contains('*') {
+                false => vec![join(&dir, segment)],
+                true => fs::read_dir(root.join(&dir))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                    .filter(|name| !name.starts_with('.') && matches_wildcard(segment, name))
+                    .map(|name| join(&dir, &name))
+                    .collect(),
+            })
+            .collect();
+    }
+    dirs.retain(|dir| !dir.is_empty() && root.join(dir).is_dir());
+    dirs.sort();
+    dirs
+}
+
+/// Checks whether the `name` matches the `pattern`, where '*' (or '**') matches any characters
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if parts.len() == 1 {
+        return name == pattern;
+    }
+    if name.len() < first.len() + last.len() || !name.starts_with(first) || !name.ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Gets the patterns of the `packages` list of 'pnpm-workspace.yaml'
+fn parse_pnpm_packages(contents: &str) -> Vec<String> {
+    let mut patterns: Vec<String> = Vec::new();
+    let mut in_packages = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with([' ', '\t', '-']) && !trimmed.is_empty() {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if let Some(pattern) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            let pattern = pattern.split(" #").next().unwrap_or_default().trim();
+            patterns.push(pattern.trim_matches(|c| c == '"' || c == '\'').to_string());
+        }
+    }
+    patterns
+}
+
+/// Gets the directories of the `use` directives of 'go.work', in a block or not
+fn parse_go_work(contents: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = Vec::new();
+    let mut in_use_block = false;
+    for line in contents
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or_default().trim())
+    {
+        match in_use_block {
+            true if line == ")" => in_use_block = false,
+            true if !line.is_empty() => dirs.push(line.to_string()),
+            true => {}
+            false if line == "use (" || line == "use(" => in_use_block = true,
+            false => {
+                if let Some(dir) = line.strip_prefix("use ") {
+                    dirs.push(dir.trim().to_string());
+                }
+            }
+        }
+    }
+    dirs
+}
@@ -0,0 +1,293 @@
+//! Normalising paths to the form they are stored in, i.e., relative to the root of the
+//! repository with forward slashes on every platform, and opening long paths on Windows
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    ffi::OsStr,
+    fmt,
+    ops::Deref,
+    path::{Component, Path, PathBuf},
+};
+
+/// The length of the longest path Windows opens without a verbatim (`\\?\`) prefix
+const MAX_PATH: usize = 260;
+
+/// Strips the Windows verbatim prefix, i.e., `\\?\` as used for long paths and returned by
+/// [`std::fs::canonicalize`], from `path`. Verbatim UNC paths (`\\?\UNC\server\share`) become
+/// `\\server\share`.
+pub fn strip_verbatim(path: &Path) -> PathBuf {
+    let Some(path_str) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    if let Some(unc) = path_str.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{}", unc));
+    }
+    match path_str.strip_prefix(r"\\?\") {
+        Some(path_str) => PathBuf::from(path_str),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Gets `path` relative to `root`, ignoring any verbatim prefixes and, on Windows, where file
+/// names are case-insensitive, the case of the components
+///
+/// #Returns:
+/// * The relative path, or `None` if `path` isn't under `root`
+pub fn relative_to(path: &Path, root: &Path) -> Option<PathBuf> {
+    let path = strip_verbatim(path);
+    let root = strip_verbatim(root);
+    let mut components = path.components();
+    for root_component in root.components() {
+        let component = components.next()?;
+        if !names_match(component.as_os_str(), root_component.as_os_str()) {
+            return None;
+        }
+    }
+    Some(components.as_path().to_path_buf())
+}
+
+/// Checks whether `path` is `base` or under it, comparing as [`relative_to`] does
+pub fn starts_with(path: &Path, base: &Path) -> bool {
+    relative_to(path, base).is_some()
+}
+
+/// Checks whether `path` is hidden, i.e., it, or a directory it is in, starts with '.', e.g.,
+/// '.github/workflows/ci.yml' or '.eslintrc.js'
+pub fn is_hidden(path: &Path) -> bool {
+    path.components().any(|component| match component {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+/// Converts `path` to a string with forward slashes as separators. Backslashes are only
+/// separators on Windows; elsewhere they are kept as part of the file name.
+pub fn to_slash(path: &Path) -> String {
+    let path = strip_verbatim(path).to_string_lossy().to_string();
+    match cfg!(windows) {
+        true => path.replace('\\', "/"),
+        false => path,
+    }
+}
+
+/// Gets `path` in the stored form: relative to `root`, if it is under it, with forward slashes
+pub fn normalise(path: &Path, root: &Path) -> String {
+    match relative_to(path, root) {
+        Some(relative) => to_slash(&relative),
+        None => to_slash(path),
+    }
+}
+
+/// A path in the stored form, i.e., relative to the root of the repository with forward slashes,
+/// so that the output of the same repository is the same on every platform
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct RepoRelativePath(String);
+impl RepoRelativePath {
+    /// Gets `path` relative to `root`, as [`normalise`] does
+    pub fn new(path: &Path, root: &Path) -> Self {
+        Self(normalise(path, root))
+    }
+    /// Gets the `path`, already relative to the root of the repository, e.g., from a git tree or
+    /// diff, with forward slashes, as [`to_slash`] does
+    pub fn from_relative(path: &Path) -> Self {
+        Self(to_slash(path))
+    }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    /// Gets the path as a [`Path`], e.g., to get its file name or join it to the root
+    pub fn as_path(&self) -> &Path {
+        Path::new(&self.0)
+    }
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+/// Takes a path that is already in the stored form, e.g., read back from a snapshot
+impl From<String> for RepoRelativePath {
+    fn from(path: String) -> Self {
+        Self(path)
+    }
+}
+/// Takes a path that is already in the stored form, e.g., read back from a snapshot
+impl From<&str> for RepoRelativePath {
+    fn from(path: &str) -> Self {
+        Self(path.to_string())
+    }
+}
+impl From<RepoRelativePath> for String {
+    fn from(path: RepoRelativePath) -> Self {
+        path.0
+    }
+}
+impl Deref for RepoRelativePath {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+impl AsRef<str> for RepoRelativePath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+impl AsRef<Path> for RepoRelativePath {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+impl AsRef<OsStr> for RepoRelativePath {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(&self.0)
+    }
+}
+impl Borrow<str> for RepoRelativePath {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Display for RepoRelativePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl PartialEq<str> for RepoRelativePath {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+impl PartialEq<&str> for RepoRelativePath {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+impl PartialEq<String> for RepoRelativePath {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+/// Resolves the `target` of a symlink at `relative_path`, both in the stored form, without the
+/// file system, e.g., in a git tree
+///
+/// #Returns:
+/// * The relative path the target resolves to, or `None` if it is absolute or outside the
+///   repository
+pub(crate) fn resolve_link(relative_path: &str, target: &str) -> Option<String> {
+    let mut resolved: Vec<&str> = relative_path.split('/').collect();
+    resolved.pop();
+    for component in target.split('/') {
+        match component {
+            "" if resolved.is_empty() => return None,
+            "" | "." => {}
+            ".." => {
+                resolved.pop()?;
+            }
+            name => resolved.push(name),
+        }
+    }
+    Some(resolved.join("/"))
+}
+
+/// Matches the paths of files in the reports of other tools, e.g., coverage reports, which can
+/// be absolute, relative to the repository or to a base directory, or from another checkout,
+/// to the relative paths of the source files of a repository
+pub(crate) struct ReportPathMatcher<'a> {
+    repo_path: &'a Path,
+    root: PathBuf,
+    relative_paths: Vec<&'a str>,
+    indices: HashMap<&'a str, usize>,
+}
+impl<'a> ReportPathMatcher<'a> {
+    /// Creates the matcher of the `relative_paths` of the source files of the repository at
+    /// `repo_path`
+    pub(crate) fn new(relative_paths: Vec<&'a str>, repo_path: &'a str) -> Self {
+        let repo_path = Path::new(repo_path);
+        Self {
+            repo_path,
+            root: repo_path
+                .canonicalize()
+                .map(|root| strip_verbatim(&root))
+                .unwrap_or(repo_path.to_path_buf()),
+            indices: relative_paths
+                .iter()
+                .enumerate()
+                .map(|(index, path)| (*path, index))
+                .collect(),
+            relative_paths,
+        }
+    }
+    /// Finds the source file of the `path` of a report: by its path relative to the repository,
+    /// as is or joined to one of the `bases` directories, or else the source file with the
+    /// longest relative path that the `path` ends with
+    ///
+    /// #Returns:
+    /// * The index of the source file in the relative paths, or `None` if none matches
+    pub(crate) fn find(&self, path: &str, bases: &[String]) -> Option<usize> {
+        let candidates = std::iter::once(Path::new(path).to_path_buf())
+            .chain(bases.iter().map(|base| Path::new(base).join(path)));
+        for candidate in candidates {
+            for root in [self.repo_path, self.root.as_path()] {
+                let relative = normalise(&candidate, root);
+                if let Some(index) = self.indices.get(relative.trim_start_matches("./")) {
+                    return Some(*index);
+                }
+            }
+        }
+        let path = to_slash(Path::new(path));
+        self.relative_paths
+            .iter()
+            .enumerate()
+            .filter(|(_, relative_path)| {
+                path.strip_suffix(**relative_path)
+                    .is_some_and(|prefix| prefix.ends_with('/'))
+            })
+            .max_by_key(|(_, relative_path)| relative_path.len())
+            .map(|(index, _)| index)
+    }
+}
+
+/// Checks whether two file names match, ignoring case on Windows
+pub(crate) fn names_match(a: &OsStr, b: &OsStr) -> bool {
+    match cfg!(windows) {
+        true => a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase(),
+        false => a == b,
+    }
+}
+
+/// Gets a path to `path` that Windows can open even if it is longer than `MAX_PATH`, by making
+/// it absolute and verbatim. Other platforms have no such limit, so `path` is returned as is.
+pub(crate) fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows)
+        || path.as_os_str().len() < MAX_PATH
+        || path.to_string_lossy().starts_with(r"\\?\")
+    {
+        return path.to_path_buf();
+    }
+    let absolute = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => match std::env::current_dir() {
+            Ok(current_dir) => current_dir.join(path),
+            Err(_) => return path.to_path_buf(),
+        },
+    };
+    // Verbatim paths are not normalised by Windows, so '.' and '..' have to be resolved here
+    let mut normalised = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalised.pop();
+            }
+            component => normalised.push(component),
+        }
+    }
+    let normalised = normalised.to_string_lossy().to_string();
+    match normalised.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", normalised)),
+    }
+}
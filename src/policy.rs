@@ -0,0 +1,399 @@
+//! Threshold-based policies on an analysed repository, e.g., to gate CI on the size, churn and
+//! ownership of its source files
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::SourceCodeError, ratio::Ratio, repository::RepositoryInfo, scoring::HealthReport,
+};
+
+/// The severity of a [`PolicyRule`], and of its violations
+#[derive(
+    Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PolicySeverity {
+    /// Reported, without warning
+    Info,
+    /// Reported as a warning
+    #[default]
+    Warning,
+    /// Reported as an error, e.g., to fail a CI job
+    Error,
+}
+
+/// What a [`PolicyRule`] checks, against its `limit`
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyCheck {
+    /// The most lines of code of a source file
+    MaxFileLoc,
+    /// The highest change frequency of a source file, i.e., the percentage of the commits that
+    /// changed it
+    MaxChurnFrequency,
+    /// The fewest contributors that authored over half of the commits of the repository
+    MinBusFactor,
+    /// The most source files whose commits are all by one author, as counted by the
+    /// [`crate::codeowners::OwnershipReport`] of the repository if it has a CODEOWNERS file, or
+    /// else by the `num_authors` of its source files
+    MaxSingleOwnerFiles,
+}
+impl PolicyCheck {
+    /// Gets whether the check is of each source file, rather than of the repository
+    pub fn is_per_file(&self) -> bool {
+        matches!(
+            self,
+            PolicyCheck::MaxFileLoc | PolicyCheck::MaxChurnFrequency
+        )
+    }
+}
+
+/// A rule of a [`Policy`]
+///
+/// #Fields:
+/// * `name` - The name of the rule, reported with its violations, or `None` to use the name of
+///   its [`PolicyCheck`]
+/// * `check` - The [`PolicyCheck`]
+/// * `limit` - The maximum, or minimum, the `check` allows
+/// * `severity` - The [`PolicySeverity`] of the violations of the rule
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PolicyRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub check: PolicyCheck,
+    pub limit: f64,
+    #[serde(default)]
+    pub severity: PolicySeverity,
+}
+impl PolicyRule {
+    pub fn new(check: PolicyCheck, limit: f64, severity: PolicySeverity) -> Self {
+        Self {
+            name: None,
+            check,
+            limit,
+            severity,
+        }
+    }
+    /// Names the rule, e.g., to tell apart two rules of the same [`PolicyCheck`]
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+    /// Gets the name of the rule, or of its [`PolicyCheck`] if it isn't named
+    pub fn get_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => serde_json::to_value(self.check)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A set of [`PolicyRule`]s to evaluate on a [`RepositoryInfo`]
+///
+/// #Fields:
+/// * `rules` - The [`PolicyRule`]s
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct Policy {
+    pub rules: Vec<PolicyRule>,
+}
+impl Policy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Reads the [`Policy`] from a TOML configuration, e.g.:
+    ///
+    /// ```toml
+    /// [[rules]]
+    /// check = "max_file_loc"
+    /// limit = 1000
+    /// severity = "error"
+    ///
+    /// [[rules]]
+    /// check = "min_bus_factor"
+    /// limit = 2
+    /// ```
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if the configuration isn't valid
+    pub fn from_toml(config: &str) -> Result<Self, SourceCodeError> {
+        let policy: Policy =
+            toml::from_str(config).map_err(|err| SourceCodeError::ConfigError(err.to_string()))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+    /// Adds the `rule` to the policy
+    pub fn with_rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+    /// Checks that the limit of each rule is a number that isn't negative
+    pub fn validate(&self) -> Result<(), SourceCodeError> {
+        match self
+            .rules
+            .iter()
+            .find(|rule| !rule.limit.is_finite() || rule.limit < 0.0)
+        {
+            Some(rule) => Err(SourceCodeError::ConfigError(format!(
+                "the limit of the policy rule '{}' must be a number of at least 0, not {}",
+                rule.get_name(),
+                rule.limit
+            ))),
+            None => Ok(()),
+        }
+    }
+    /// Evaluates the rules of the policy on the `repository_info`
+    ///
+    /// #Returns:
+    /// * The [`PolicyReport`] of the violations, by rule then path
+    pub fn evaluate(&self, repository_info: &RepositoryInfo) -> PolicyReport {
+        let mut violations: Vec<PolicyViolation> = Vec::new();
+        let mut skipped: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            let name = rule.get_name();
+            let mut violation = |path: Option<&str>, value: f64, message: String| {
+                violations.push(PolicyViolation {
+                    rule: name.clone(),
+                    check: rule.check,
+                    severity: rule.severity,
+                    path: path.map(str::to_string),
+                    value,
+                    limit: rule.limit,
+                    message,
+                })
+            };
+            match rule.check {
+                PolicyCheck::MaxFileLoc => {
+                    for source_file in &repository_info.source_files {
+                        let loc = source_file.statistics.loc as f64;
+                        if loc > rule.limit {
+                            violation(
+                                Some(source_file.relative_path.as_str()),
+                                loc,
+                                format!(
+                                    "{} has {} lines of code, over the limit of {}",
+                                    source_file.relative_path, loc, rule.limit
+                                ),
+                            );
+                        }
+                    }
+                }
+                PolicyCheck::MaxChurnFrequency => {
+                    for source_file in &repository_info.source_files {
+                        let frequency = source_file.statistics.change_frequency.0 as f64;
+                        if frequency > rule.limit {
+                            violation(
+                                Some(source_file.relative_path.as_str()),
+                                frequency,
+                                format!(
+                                    "{} is changed by {:.1}% of the commits, over the limit of {}%",
+                                    source_file.relative_path, frequency, rule.limit
+                                ),
+                            );
+                        }
+                    }
+                }
+                PolicyCheck::MinBusFactor => {
+                    let bus_factor = HealthReport::get_bus_factor(repository_info) as f64;
+                    if bus_factor < rule.limit {
+                        violation(
+                            None,
+                            bus_factor,
+                            format!(
+                                "the bus factor is {}, under the limit of {}",
+                                bus_factor, rule.limit
+                            ),
+                        );
+                    }
+                }
+                PolicyCheck::MaxSingleOwnerFiles => {
+                    // The CODEOWNERS report, if any, else the authors of each file's commits
+                    let num_single_owner = match &repository_info.ownership {
+                        Some(ownership) => ownership
+                            .files
+                            .iter()
+                            .filter(|file| file.top_contributor_share == Some(Ratio(1.0)))
+                            .count(),
+                        None if repository_info
+                            .source_files
+                            .iter()
+                            .all(|source_file| source_file.num_authors.is_none()) =>
+                        {
+                            skipped.push(name);
+                            continue;
+                        }
+                        None => repository_info
+                            .source_files
+                            .iter()
+                            .filter(|source_file| source_file.num_authors == Some(1))
+                            .count(),
+                    } as f64;
+                    if num_single_owner > rule.limit {
+                        violation(
+                            None,
+                            num_single_owner,
+                            format!(
+                                "{} source files are only changed by one author, over the limit of {}",
+                                num_single_owner, rule.limit
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        PolicyReport {
+            num_rules: self.rules.len(),
+            violations,
+            skipped,
+        }
+    }
+}
+
+/// A violation of a [`PolicyRule`]
+///
+/// #Fields:
+/// * `rule` - The name of the [`PolicyRule`]
+/// * `check` - The [`PolicyCheck`] of the rule
+/// * `severity` - The [`PolicySeverity`] of the rule
+/// * `path` - The relative path of the source file that violates the rule, or `None` if the
+///   repository does
+/// * `value` - The value that violates the rule
+/// * `limit` - The limit of the rule
+/// * `message` - A description of the violation
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PolicyViolation {
+    pub rule: String,
+    pub check: PolicyCheck,
+    pub severity: PolicySeverity,
+    pub path: Option<String>,
+    pub value: f64,
+    pub limit: f64,
+    pub message: String,
+}
+
+/// The result of evaluating a [`Policy`] with [`Policy::evaluate`]
+///
+/// #Fields:
+/// * `num_rules` - The number of rules evaluated
+/// * `violations` - The [`PolicyViolation`]s, by rule then path
+/// * `skipped` - The names of the rules that couldn't be evaluated, e.g., a
+///   [`PolicyCheck::MaxSingleOwnerFiles`] rule for a repository without commits
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PolicyReport {
+    pub num_rules: usize,
+    pub violations: Vec<PolicyViolation>,
+    pub skipped: Vec<String>,
+}
+impl PolicyReport {
+    /// Gets the highest [`PolicySeverity`] of the violations, or `None` if there are none
+    pub fn max_severity(&self) -> Option<PolicySeverity> {
+        self.violations
+            .iter()
+            .map(|violation| violation.severity)
+            .max()
+    }
+    /// Checks whether no violation is at, or above, the `severity`, e.g., to pass a CI job
+    /// unless there are errors
+    pub fn passes(&self, severity: PolicySeverity) -> bool {
+        self.max_severity()
+            .is_none_or(|max_severity| max_severity < severity)
+    }
+    /// Gets the [`PolicyViolation`]s of the `severity`
+    pub fn violations_of(
+        &self,
+        severity: PolicySeverity,
+    ) -> impl Iterator<Item = &PolicyViolation> {
+        self.violations
+            .iter()
+            .filter(move |violation| violation.severity == severity)
+    }
+    /// Gets the [`PolicyReport`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "git")]
+    use crate::{options::AnalysisOptions, testing::FixtureRepo};
+
+    /// Gets the [`Policy`] of a [`PolicyCheck::MaxSingleOwnerFiles`] rule with the `limit`
+    fn max_single_owner_files(limit: f64) -> Policy {
+        Policy {
+            rules: vec![PolicyRule::new(
+                PolicyCheck::MaxSingleOwnerFiles,
+                limit,
+                PolicySeverity::Error,
+            )],
+        }
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn counts_single_owner_files_by_their_authors_without_codeowners() -> Result<(), SourceCodeError>
+    {
+        let fixture = FixtureRepo::new()?
+            .with_file("src/solo.rs", "pub fn solo() {}\n")
+            .with_file("src/shared.rs", "pub fn shared() {}\n")
+            .with_file("src/untouched.rs", "pub fn untouched() {}\n")
+            .commit("Add the modules")?
+            .with_file("src/solo.rs", "pub fn solo() -> i32 {\n    1\n}\n")
+            .commit("Return one")?
+            .with_author("Bob", "bob@example.com")
+            .with_file("src/shared.rs", "pub fn shared() -> i32 {\n    2\n}\n")
+            .commit("Return two")?;
+        let repository_info = fixture.analyse(&AnalysisOptions::default())?;
+        assert!(repository_info.ownership.is_none());
+        let num_authors: Vec<(&str, Option<u64>)> = repository_info
+            .source_files
+            .iter()
+            .map(|source_file| (source_file.relative_path.as_str(), source_file.num_authors))
+            .collect();
+        // The file only added by the root commit still has its author
+        assert_eq!(
+            num_authors,
+            vec![
+                ("src/shared.rs", Some(2)),
+                ("src/solo.rs", Some(1)),
+                ("src/untouched.rs", Some(1)),
+            ]
+        );
+
+        let report = max_single_owner_files(1.0).evaluate(&repository_info);
+        assert!(report.skipped.is_empty());
+        let violations: Vec<(Option<&str>, f64)> = report
+            .violations
+            .iter()
+            .map(|violation| (violation.path.as_deref(), violation.value))
+            .collect();
+        assert_eq!(violations, vec![(None, 2.0)]);
+        assert!(!report.passes(PolicySeverity::Error));
+        let report = max_single_owner_files(2.0).evaluate(&repository_info);
+        assert!(report.violations.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn skips_single_owner_files_without_authors() -> Result<(), SourceCodeError> {
+        // A snapshot from before the authors of the source files were counted
+        let mut repository_info = RepositoryInfo::deserialize_any_version(include_str!(
+            "../tests/fixtures/snapshots/v1.json"
+        ))?;
+        repository_info.ownership = None;
+        let report = max_single_owner_files(0.0).evaluate(&repository_info);
+        assert_eq!(report.num_rules, 1);
+        assert!(report.violations.is_empty());
+        assert_eq!(report.skipped, vec!["max_single_owner_files"]);
+        Ok(())
+    }
+}
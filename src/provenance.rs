@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+use git2::{BranchType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+use crate::errors::SourceCodeError;
+
+/// The armour headers of the signature formats git supports, and the format they mark
+const SIGNATURE_HEADERS: [(&str, SignatureFormat); 3] = [
+    ("-----BEGIN PGP SIGNATURE-----", SignatureFormat::Pgp),
+    ("-----BEGIN SSH SIGNATURE-----", SignatureFormat::Ssh),
+    ("-----BEGIN SIGNED MESSAGE-----", SignatureFormat::X509),
+];
+
+/// Whether a tag is an annotated tag object or a lightweight ref to a commit
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TagKind {
+    Annotated,
+    Lightweight,
+}
+
+/// The format of a tag signature
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureFormat {
+    Pgp,
+    Ssh,
+    X509,
+}
+
+/// A report on how the releases (tags) of a repository were made
+///
+/// #Fields:
+/// * `default_branch` - The name of the default branch reachability is checked against, if found
+/// * `tags` - The [`TagProvenance`] of each tag, ordered by name
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ReleaseProvenanceReport {
+    pub default_branch: Option<String>,
+    pub tags: Vec<TagProvenance>,
+}
+impl ReleaseProvenanceReport {
+    /// Creates the [`ReleaseProvenanceReport`] for the repository at `repo_path`
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository
+    /// * `verify_signatures` - Whether to verify signatures with `git verify-tag`, which needs
+    ///   `git` on the path and the signers' keys in the local keyring (or allowed signers file)
+    pub fn new(
+        repo_path: &str,
+        verify_signatures: bool,
+    ) -> Result<ReleaseProvenanceReport, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+        let default_branch = Self::find_default_branch(&repo);
+        let default_tip: Option<Oid> = default_branch
+            .as_ref()
+            .and_then(|branch| repo.revparse_single(branch).ok())
+            .and_then(|object| object.peel_to_commit().ok())
+            .map(|commit| commit.id());
+
+        let mut tags: Vec<TagProvenance> = Vec::new();
+        for name in repo.tag_names(None)?.iter().flatten() {
+            let reference = repo
+                .find_reference(&format!("refs/tags/{}", name))
+                .map_err(SourceCodeError::for_reference(name))?;
+            let Ok(commit) = reference.peel_to_commit() else {
+                // Tags of trees or blobs are not releases
+                continue;
+            };
+            let reachable_from_default_branch = match default_tip {
+                Some(tip) => tip == commit.id() || repo.graph_descendant_of(tip, commit.id())?,
+                None => false,
+            };
+            let mut provenance = TagProvenance {
+                name: name.to_string(),
+                commit_id: commit.id().to_string(),
+                kind: TagKind::Lightweight,
+                tagger: None,
+                date: None,
+                signature: None,
+                verified: None,
+                reachable_from_default_branch,
+            };
+            if let Ok(tag) = reference.peel_to_tag() {
+                provenance.kind = TagKind::Annotated;
+                provenance.tagger = tag
+                    .tagger()
+                    .and_then(|tagger| tagger.name().map(String::from));
+                provenance.date = tag
+                    .tagger()
+                    .and_then(|tagger| DateTime::<Utc>::from_timestamp(tagger.when().seconds(), 0));
+                provenance.signature = tag.message().and_then(Self::get_signature_format);
+                if verify_signatures && provenance.signature.is_some() {
+                    provenance.verified = Self::verify_tag(repo_path, name);
+                }
+            }
+            tags.push(provenance);
+        }
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(ReleaseProvenanceReport {
+            default_branch,
+            tags,
+        })
+    }
+    /// Gets the [`ReleaseProvenanceReport`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Finds the default branch: the remote's HEAD if known, else `init.defaultBranch`, else a
+    /// local 'main' or 'master' branch
+    fn find_default_branch(repo: &Repository) -> Option<String> {
+        if let Ok(remote_head) = repo.find_reference("refs/remotes/origin/HEAD") {
+            if let Some(target) = remote_head.symbolic_target() {
+                return target.strip_prefix("refs/remotes/").map(String::from);
+            }
+        }
+        let configured = repo
+            .config()
+            .ok()
+            .and_then(|config| config.get_string("init.defaultBranch").ok());
+        configured
+            .into_iter()
+            .chain(["main".to_string(), "master".to_string()])
+            .find(|name| repo.find_branch(name, BranchType::Local).is_ok())
+    }
+    /// Gets the format of the signature appended to an annotated tag's message, if signed
+    fn get_signature_format(message: &str) -> Option<SignatureFormat> {
+        SIGNATURE_HEADERS
+            .iter()
+            .find(|(header, _)| message.lines().any(|line| line == *header))
+            .map(|(_, format)| *format)
+    }
+    /// Verifies a tag signature with `git verify-tag`, or `None` if git couldn't be run
+    fn verify_tag(repo_path: &str, name: &str) -> Option<bool> {
+        Command::new("git")
+            .args(["-C", repo_path, "verify-tag", name])
+            .output()
+            .map(|output| output.status.success())
+            .map_err(|err| tracing::warn!("Failed to run git verify-tag for {}: {}", name, err))
+            .ok()
+    }
+}
+
+/// The provenance of a single tag
+///
+/// #Fields:
+/// * `name` - The name of the tag
+/// * `commit_id` - The id of the commit the tag points to
+/// * `kind` - Whether the tag is annotated or lightweight
+/// * `tagger` - The name of the tagger, for annotated tags
+/// * `date` - The date the tag was made, for annotated tags
+/// * `signature` - The [`SignatureFormat`] of the tag's signature, if signed
+/// * `verified` - Whether the signature verified, if verification was requested and could run
+/// * `reachable_from_default_branch` - Whether the tagged commit is in the default branch's history
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TagProvenance {
+    pub name: String,
+    pub commit_id: String,
+    pub kind: TagKind,
+    pub tagger: Option<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub signature: Option<SignatureFormat>,
+    pub verified: Option<bool>,
+    pub reachable_from_default_branch: bool,
+}
@@ -0,0 +1,126 @@
+//! The extension point for custom per-file metrics: a [`MetricProvider`] registered with
+//! [`crate::analyzer::RepositoryAnalyzer::with_metric_provider`] computes a [`MetricSet`] for
+//! each source file, e.g., from a proprietary complexity tool, which is added to the `custom`
+//! metrics of the file's [`Statistics`] alongside the built-in ones
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    catalog::{MetricDefinition, MetricScope, MetricUnit, MetricWindow},
+    data::Statistics,
+    repository::RepositoryInfo,
+    source::{LanguageType, SourceFileInfo},
+};
+
+/// The source file a [`MetricProvider`] computes its metrics for, with its contents if they are
+/// still held
+///
+/// #Fields:
+/// * `repo_path` - The path to the repository being analysed
+/// * `source_file` - The [`SourceFileInfo`] of the file, with its built-in [`Statistics`]
+pub struct FileContext<'a> {
+    pub repo_path: &'a str,
+    pub source_file: &'a SourceFileInfo,
+}
+impl FileContext<'_> {
+    /// Gets the relative path of the file from the root of the repository
+    pub fn relative_path(&self) -> &str {
+        self.source_file.relative_path.as_str()
+    }
+    /// Gets the [`LanguageType`] of the file, if known
+    pub fn language(&self) -> Option<&LanguageType> {
+        self.source_file.language.as_ref()
+    }
+    /// Gets the built-in [`Statistics`] of the file
+    pub fn statistics(&self) -> &Statistics {
+        &self.source_file.statistics
+    }
+    /// Gets the contents of the file, or `None` if they aren't held, e.g., for a binary or
+    /// oversized file
+    pub fn contents(&self) -> Option<&str> {
+        self.source_file.get_contents()
+    }
+}
+
+/// The values of the custom metrics of a source file, by name
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct MetricSet(pub BTreeMap<String, f64>);
+impl MetricSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds the metric `name` with the `value`, replacing any value it had
+    pub fn with(mut self, name: &str, value: f64) -> Self {
+        self.insert(name, value);
+        self
+    }
+    /// Sets the metric `name` to the `value`, replacing any value it had
+    pub fn insert(&mut self, name: &str, value: f64) {
+        self.0.insert(name.to_string(), value);
+    }
+    /// Gets the value of the metric `name`, if set
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.0.get(name).copied()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A source of custom per-file metrics, e.g., a proprietary complexity tool, run for each
+/// source file of an analysis before its contents are dropped
+pub trait MetricProvider: Send + Sync {
+    /// Computes the metrics of the file of the [`FileContext`]; values that aren't finite are
+    /// left out
+    fn compute(&self, ctx: &FileContext) -> MetricSet;
+}
+
+/// Computes the metrics of each of the `providers`, in order, for each source file of the
+/// [`RepositoryInfo`], and adds them to the `custom` metrics of its [`Statistics`], a later
+/// provider's value replacing an earlier one's of the same name. Each custom metric is added to
+/// the `metrics_catalog`.
+pub(crate) fn compute_custom_metrics(
+    repository_info: &mut RepositoryInfo,
+    repo_path: &str,
+    providers: &[&dyn MetricProvider],
+) {
+    if providers.is_empty() {
+        return;
+    }
+    let mut names: Vec<String> = Vec::new();
+    for source_file in &mut repository_info.source_files {
+        let mut custom: BTreeMap<String, f64> = BTreeMap::new();
+        let ctx = FileContext {
+            repo_path,
+            source_file,
+        };
+        for provider in providers {
+            custom.extend(
+                provider
+                    .compute(&ctx)
+                    .0
+                    .into_iter()
+                    .filter(|(_, value)| value.is_finite()),
+            );
+        }
+        for name in custom.keys() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        source_file.statistics.custom = custom;
+    }
+    names.sort();
+    for name in names {
+        MetricDefinition {
+            path: format!("source_files[].statistics.custom.{}", name),
+            scope: MetricScope::SourceFile,
+            unit: MetricUnit::Number,
+            window: MetricWindow::Snapshot,
+            description: "Custom: computed by a MetricProvider".to_string(),
+        }
+        .add_to(&mut repository_info.metrics_catalog);
+    }
+}
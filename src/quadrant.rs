@@ -0,0 +1,148 @@
+//! The classification of source files into quadrants of their age and churn, e.g., old files
+//! that still change often, for a quick overview of where the architectural risk is
+use serde::{Deserialize, Serialize};
+
+use crate::{generated::CodeTotals, ratio::Percentage, source::SourceFileInfo};
+
+/// The thresholds between the quadrants of a [`ChurnQuadrant`] classification
+///
+/// #Fields:
+/// * `old_age_days` - The age, in whole days since the file's first commit, at (or over) which
+///   a file is old rather than new
+/// * `hot_frequency` - The change frequency, as a [`Percentage`] of the commits of the
+///   repository, at (or over) which a file is hot rather than stable
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct QuadrantThresholds {
+    pub old_age_days: i64,
+    pub hot_frequency: Percentage,
+}
+impl Default for QuadrantThresholds {
+    /// Files are old after 180 days, and hot if 5% or more of the commits change them
+    fn default() -> Self {
+        Self {
+            old_age_days: 180,
+            hot_frequency: Percentage(5.0),
+        }
+    }
+}
+
+/// The quadrant of a source file by its age and change frequency
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ChurnQuadrant {
+    /// A new file that changes often, e.g., code under active development
+    NewHot,
+    /// An old file that still changes often, e.g., a god class or a bottleneck, the riskiest
+    OldHot,
+    /// An old file that rarely changes, e.g., settled code
+    OldStable,
+    /// A new file that rarely changes, e.g., finished or abandoned work
+    NewStable,
+}
+impl ChurnQuadrant {
+    /// All the quadrants, in the order they are reported
+    pub const ALL: [ChurnQuadrant; 4] = [
+        ChurnQuadrant::NewHot,
+        ChurnQuadrant::OldHot,
+        ChurnQuadrant::OldStable,
+        ChurnQuadrant::NewStable,
+    ];
+    /// Gets the quadrant of a file `age_days` old and changed by a `change_frequency` of the
+    /// commits, by the `thresholds`
+    pub fn new(
+        age_days: i64,
+        change_frequency: Percentage,
+        thresholds: &QuadrantThresholds,
+    ) -> ChurnQuadrant {
+        let is_old = age_days >= thresholds.old_age_days;
+        let is_hot = change_frequency >= thresholds.hot_frequency;
+        match (is_old, is_hot) {
+            (false, true) => ChurnQuadrant::NewHot,
+            (true, true) => ChurnQuadrant::OldHot,
+            (true, false) => ChurnQuadrant::OldStable,
+            (false, false) => ChurnQuadrant::NewStable,
+        }
+    }
+}
+
+/// The [`CodeTotals`] of the source files of a quadrant
+///
+/// #Fields:
+/// * `quadrant` - The [`ChurnQuadrant`] of the files
+/// * `totals` - The [`CodeTotals`] of the files
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QuadrantTotals {
+    pub quadrant: ChurnQuadrant,
+    pub totals: CodeTotals,
+}
+
+/// The roll-up of a repository's source files by their [`ChurnQuadrant`]
+///
+/// #Fields:
+/// * `thresholds` - The [`QuadrantThresholds`] the files were classified by
+/// * `quadrants` - The [`QuadrantTotals`] of each quadrant, in the order of
+///   [`ChurnQuadrant::ALL`], including those without files
+/// * `unclassified` - The number of source files without commits, e.g., untracked files, which
+///   have no age to classify them by
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QuadrantReport {
+    pub thresholds: QuadrantThresholds,
+    pub quadrants: Vec<QuadrantTotals>,
+    pub unclassified: u64,
+}
+impl QuadrantReport {
+    /// Gets the [`QuadrantReport`] of the `source_files`, as classified by [`classify`] with
+    /// the `thresholds`
+    pub fn new(source_files: &[SourceFileInfo], thresholds: &QuadrantThresholds) -> QuadrantReport {
+        let mut report = QuadrantReport {
+            thresholds: *thresholds,
+            quadrants: ChurnQuadrant::ALL
+                .into_iter()
+                .map(|quadrant| QuadrantTotals {
+                    quadrant,
+                    totals: CodeTotals::default(),
+                })
+                .collect(),
+            unclassified: 0,
+        };
+        for source_file in source_files {
+            let quadrant_totals = source_file.quadrant.and_then(|quadrant| {
+                report
+                    .quadrants
+                    .iter_mut()
+                    .find(|quadrant_totals| quadrant_totals.quadrant == quadrant)
+            });
+            match quadrant_totals {
+                Some(quadrant_totals) => quadrant_totals.totals.add(source_file),
+                None => report.unclassified += 1,
+            }
+        }
+        report
+    }
+    /// Gets the [`CodeTotals`] of the files of the `quadrant`
+    pub fn get(&self, quadrant: ChurnQuadrant) -> Option<&CodeTotals> {
+        self.quadrants
+            .iter()
+            .find(|quadrant_totals| quadrant_totals.quadrant == quadrant)
+            .map(|quadrant_totals| &quadrant_totals.totals)
+    }
+}
+
+/// Classifies each of the `source_files` into its [`ChurnQuadrant`] by the `thresholds`, after
+/// their ages are set; files without commits aren't classified
+pub fn classify(source_files: &mut [SourceFileInfo], thresholds: &QuadrantThresholds) {
+    for source_file in source_files {
+        source_file.quadrant = source_file.age_days.map(|age_days| {
+            ChurnQuadrant::new(
+                age_days,
+                source_file.statistics.change_frequency,
+                thresholds,
+            )
+        });
+    }
+}
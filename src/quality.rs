@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{repository::RepositoryInfo, warning::WarningKind};
+
+/// A family of related metrics in a [`RepositoryInfo`], whose data quality is assessed together
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MetricFamily {
+    /// The lines of code, sizes and file counts
+    Size,
+    /// The languages and their shares of the code
+    Languages,
+    /// The commit history, i.e., commit counts, change frequencies and file ages
+    History,
+    /// The contributors and their shares of the commits
+    Contributors,
+    /// The temporal coupling of files that change together
+    Coupling,
+    /// The ownership of the files declared in CODEOWNERS, and by commits
+    Ownership,
+    /// The code duplicated across source files
+    Duplication,
+    /// The tech-debt markers and their authors
+    TechDebt,
+    /// The split of the code into test and production code
+    TestCode,
+}
+impl fmt::Display for MetricFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MetricFamily::Size => "size",
+            MetricFamily::Languages => "languages",
+            MetricFamily::History => "history",
+            MetricFamily::Contributors => "contributors",
+            MetricFamily::Coupling => "coupling",
+            MetricFamily::Ownership => "ownership",
+            MetricFamily::Duplication => "duplication",
+            MetricFamily::TechDebt => "tech_debt",
+            MetricFamily::TestCode => "test_code",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How far the metrics of a [`MetricFamily`] can be trusted, from best to worst
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum QualityLevel {
+    /// Computed from all the data
+    Exact,
+    /// Computed from all the data, but estimated, e.g., by heuristics or over a shallow history
+    Approximate,
+    /// Computed from only part of the data, e.g., with binary files skipped
+    Partial,
+    /// Not computed, e.g., the history of a repository without commits
+    Unavailable,
+}
+impl fmt::Display for QualityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            QualityLevel::Exact => "exact",
+            QualityLevel::Approximate => "approximate",
+            QualityLevel::Partial => "partial",
+            QualityLevel::Unavailable => "unavailable",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The data quality of a [`MetricFamily`] in a [`RepositoryInfo`], so that consumers can tell
+/// exact metrics from degraded ones, e.g., 'history: approximate (shallow history)'
+///
+/// #Fields:
+/// * `family` - The [`MetricFamily`]
+/// * `level` - The worst [`QualityLevel`] of the `reasons`, or [`QualityLevel::Exact`] if there
+///   are none
+/// * `reasons` - Why the metrics are degraded, e.g., 'shallow history'
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MetricQuality {
+    pub family: MetricFamily,
+    pub level: QualityLevel,
+    pub reasons: Vec<String>,
+}
+impl MetricQuality {
+    fn new(family: MetricFamily) -> Self {
+        Self {
+            family,
+            level: QualityLevel::Exact,
+            reasons: vec![],
+        }
+    }
+    /// Degrades the quality to at least `level`, for the `reason`
+    fn degrade(mut self, level: QualityLevel, reason: String) -> Self {
+        self.level = self.level.max(level);
+        self.reasons.push(reason);
+        self
+    }
+    /// Degrades the quality to at least `level`, for the `reason`, if `condition` holds
+    fn degrade_if(self, condition: bool, level: QualityLevel, reason: impl Fn() -> String) -> Self {
+        match condition {
+            true => self.degrade(level, reason()),
+            false => self,
+        }
+    }
+    /// Assesses the data quality of each [`MetricFamily`] present in the [`RepositoryInfo`]
+    ///
+    /// #Arguments:
+    /// * `repository_info` - The analysed [`RepositoryInfo`]; its history is truncated if it
+    ///   isn't `history_complete`, i.e., the repository is a shallow clone
+    pub(crate) fn assess(repository_info: &RepositoryInfo) -> Vec<MetricQuality> {
+        let count_warnings = |kinds: &[WarningKind]| {
+            repository_info
+                .warnings
+                .iter()
+                .filter(|warning| kinds.contains(&warning.kind))
+                .count()
+        };
+        let undecoded = count_warnings(&[
+            WarningKind::Binary,
+            WarningKind::InvalidUtf8,
+            WarningKind::Oversized,
+        ]);
+        let mismatched = count_warnings(&[WarningKind::LineCountMismatch]);
+        let unreadable = count_warnings(&[WarningKind::Unreadable]);
+        let unknown = repository_info.unknown_files.len();
+        let no_commits = repository_info.history.is_none();
+        let shallow = !repository_info.history_complete;
+        let shallow_reason = || match repository_info.history_depth {
+            Some(depth) => format!("shallow history of {} commits", depth),
+            None => "shallow history".to_string(),
+        };
+        let no_commits_reason = || "no commits".to_string();
+
+        let mut quality = vec![
+            MetricQuality::new(MetricFamily::Size)
+                .degrade_if(mismatched > 0, QualityLevel::Approximate, || {
+                    format!("{} files with line counts that don't match", mismatched)
+                })
+                .degrade_if(unreadable > 0, QualityLevel::Partial, || {
+                    format!("{} files that couldn't be analysed skipped", unreadable)
+                }),
+            MetricQuality::new(MetricFamily::Languages).degrade_if(
+                unknown > 0,
+                QualityLevel::Partial,
+                || format!("{} files in undetected languages skipped", unknown),
+            ),
+            MetricQuality::new(MetricFamily::History)
+                .degrade_if(shallow, QualityLevel::Approximate, shallow_reason)
+                .degrade_if(no_commits, QualityLevel::Unavailable, no_commits_reason),
+            MetricQuality::new(MetricFamily::Contributors)
+                .degrade_if(shallow, QualityLevel::Approximate, shallow_reason)
+                .degrade_if(no_commits, QualityLevel::Unavailable, no_commits_reason),
+        ];
+        if repository_info.coupling.is_some() {
+            quality.push(
+                MetricQuality::new(MetricFamily::Coupling)
+                    .degrade_if(shallow, QualityLevel::Approximate, shallow_reason)
+                    .degrade_if(no_commits, QualityLevel::Unavailable, no_commits_reason),
+            );
+        }
+        if repository_info.ownership.is_some() {
+            quality.push(
+                MetricQuality::new(MetricFamily::Ownership)
+                    .degrade(
+                        QualityLevel::Approximate,
+                        "owners matched to authors by name and email".to_string(),
+                    )
+                    .degrade_if(no_commits, QualityLevel::Unavailable, no_commits_reason),
+            );
+        }
+        if repository_info.duplication.is_some() {
+            quality.push(MetricQuality::new(MetricFamily::Duplication).degrade_if(
+                undecoded > 0,
+                QualityLevel::Partial,
+                || format!("{} binary, non-UTF-8 or oversized files skipped", undecoded),
+            ));
+        }
+        quality.push(
+            MetricQuality::new(MetricFamily::TechDebt)
+                .degrade_if(shallow, QualityLevel::Approximate, || {
+                    "authors blamed over a shallow history".to_string()
+                })
+                .degrade_if(undecoded > 0, QualityLevel::Partial, || {
+                    format!("{} binary, non-UTF-8 or oversized files skipped", undecoded)
+                }),
+        );
+        quality.push(MetricQuality::new(MetricFamily::TestCode).degrade(
+            QualityLevel::Approximate,
+            "classified by path and attribute heuristics".to_string(),
+        ));
+        quality
+    }
+}
+impl fmt::Display for MetricQuality {
+    /// Formats the quality as, e.g., 'history: approximate (shallow history)'
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.family, self.level)?;
+        if !self.reasons.is_empty() {
+            write!(f, " ({})", self.reasons.join(", "))?;
+        }
+        Ok(())
+    }
+}
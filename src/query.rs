@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+use crate::{data::Statistics, paths, source::SourceFileInfo};
+
+/// A query over the source files of a [`crate::repository::RepositoryInfo`], built up by
+/// chaining filters, e.g., `repo.files().language("Rust").min_loc(500).collect()`. A file is
+/// selected if it passes every filter.
+#[derive(Clone, Debug)]
+pub struct FileQuery<'a> {
+    source_files: &'a [SourceFileInfo],
+    language: Option<String>,
+    min_loc: Option<u64>,
+    max_loc: Option<u64>,
+    changed_since: Option<DateTime<Utc>>,
+    under: Option<String>,
+    is_test: Option<bool>,
+}
+impl<'a> FileQuery<'a> {
+    pub(crate) fn new(source_files: &'a [SourceFileInfo]) -> Self {
+        Self {
+            source_files,
+            language: None,
+            min_loc: None,
+            max_loc: None,
+            changed_since: None,
+            under: None,
+            is_test: None,
+        }
+    }
+    /// Selects the files in the language named `name`, ignoring case, e.g., 'Rust'
+    pub fn language(mut self, name: &str) -> Self {
+        self.language = Some(name.to_string());
+        self
+    }
+    /// Selects the files with at least `loc` lines of code
+    pub fn min_loc(mut self, loc: u64) -> Self {
+        self.min_loc = Some(loc);
+        self
+    }
+    /// Selects the files with at most `loc` lines of code
+    pub fn max_loc(mut self, loc: u64) -> Self {
+        self.max_loc = Some(loc);
+        self
+    }
+    /// Selects the files last changed by a commit at or after `date`; files without commits,
+    /// e.g., untracked files, aren't selected
+    pub fn changed_since(mut self, date: DateTime<Utc>) -> Self {
+        self.changed_since = Some(date);
+        self
+    }
+    /// Selects the files under the `path`, relative to the repository, e.g., 'src/api'
+    pub fn under(mut self, path: &str) -> Self {
+        self.under = Some(path.to_string());
+        self
+    }
+    /// Selects only the test files (`true`) or only the production files (`false`)
+    pub fn tests(mut self, is_test: bool) -> Self {
+        self.is_test = Some(is_test);
+        self
+    }
+    /// Gets the selected source files, in the order of the source files
+    pub fn collect(&self) -> Vec<&'a SourceFileInfo> {
+        self.source_files
+            .iter()
+            .filter(|source_file| self.matches(source_file))
+            .collect()
+    }
+    /// Gets the number of selected source files
+    pub fn count(&self) -> usize {
+        self.source_files
+            .iter()
+            .filter(|source_file| self.matches(source_file))
+            .count()
+    }
+    /// Gets the [`Statistics`] of the selected source files added together, e.g., of a
+    /// directory with `repo.files().under("src/parser").statistics()`
+    pub fn statistics(&self) -> Statistics {
+        self.source_files
+            .iter()
+            .filter(|source_file| self.matches(source_file))
+            .map(|source_file| &source_file.statistics)
+            .sum()
+    }
+    /// Checks whether the `source_file` passes every filter
+    fn matches(&self, source_file: &SourceFileInfo) -> bool {
+        let loc = source_file.statistics.loc;
+        self.language.as_ref().is_none_or(|name| {
+            source_file
+                .language
+                .as_ref()
+                .is_some_and(|language| language.name.eq_ignore_ascii_case(name))
+        }) && self.min_loc.is_none_or(|min_loc| loc >= min_loc)
+            && self.max_loc.is_none_or(|max_loc| loc <= max_loc)
+            && self.changed_since.is_none_or(|since| {
+                source_file
+                    .last_commit_date
+                    .is_some_and(|last_commit_date| last_commit_date >= since)
+            })
+            && self.under.as_ref().is_none_or(|under| {
+                paths::starts_with(Path::new(&source_file.relative_path), Path::new(under))
+            })
+            && self
+                .is_test
+                .is_none_or(|is_test| source_file.is_test == is_test)
+    }
+}
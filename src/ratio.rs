@@ -0,0 +1,87 @@
+//! Zero-guarded ratios, so that the metrics are never NaN or infinite, and the [`Ratio`] and
+//! [`Percentage`] types that tell the two scales apart
+use serde::{Deserialize, Serialize};
+
+/// Gets the ratio of `part` to `total`, clamped to 0.0 - 1.0
+///
+/// #Returns:
+/// * The ratio, or `None` if it is undefined, i.e., `total` is zero or either value isn't finite
+pub fn ratio(part: f64, total: f64) -> Option<f32> {
+    if total == 0.0 || !part.is_finite() || !total.is_finite() {
+        return None;
+    }
+    Some((part / total).clamp(0.0, 1.0) as f32)
+}
+
+/// Gets the quotient of `numerator` and `denominator`, unlike [`ratio`] not clamped, e.g.,
+/// for a test-to-code ratio that can be above 1.0
+///
+/// #Returns:
+/// * The quotient, or `None` if it is undefined, i.e., `denominator` is zero or either value
+///   isn't finite
+pub fn quotient(numerator: f64, denominator: f64) -> Option<f32> {
+    if denominator == 0.0 || !numerator.is_finite() || !denominator.is_finite() {
+        return None;
+    }
+    Some((numerator / denominator) as f32)
+}
+
+/// Gets `part` as a percentage (0.0 - 100.0) of `total`, or `None` if it is undefined
+pub fn percentage(part: f64, total: f64) -> Option<f32> {
+    ratio(part, total).map(|ratio| ratio * 100.0)
+}
+
+/// Gets the mean of `count` values that add up to `sum`, or `None` if there are no values
+pub fn mean(sum: f64, count: usize) -> Option<f32> {
+    match count {
+        0 => None,
+        _ => Some((sum / count as f64) as f32).filter(|mean| mean.is_finite()),
+    }
+}
+
+/// A share from 0.0 to 1.0, serialised as the bare number
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Ratio(pub f32);
+impl Ratio {
+    /// Gets the [`Ratio`] of `part` to `total`, as [`ratio`] does, or `None` if it is undefined
+    pub fn of(part: f64, total: f64) -> Option<Self> {
+        ratio(part, total).map(Ratio)
+    }
+    pub fn value(self) -> f32 {
+        self.0
+    }
+    pub fn to_percentage(self) -> Percentage {
+        Percentage(self.0 * 100.0)
+    }
+}
+impl From<Percentage> for Ratio {
+    fn from(percentage: Percentage) -> Self {
+        percentage.to_ratio()
+    }
+}
+
+/// A share from 0.0 to 100.0, serialised as the bare number
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Percentage(pub f32);
+impl Percentage {
+    /// Gets `part` as a [`Percentage`] of `total`, as [`percentage`] does, or `None` if it is
+    /// undefined
+    pub fn of(part: f64, total: f64) -> Option<Self> {
+        percentage(part, total).map(Percentage)
+    }
+    pub fn value(self) -> f32 {
+        self.0
+    }
+    pub fn to_ratio(self) -> Ratio {
+        Ratio(self.0 / 100.0)
+    }
+}
+impl From<Ratio> for Percentage {
+    fn from(ratio: Ratio) -> Self {
+        ratio.to_percentage()
+    }
+}
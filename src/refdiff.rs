@@ -0,0 +1,207 @@
+//! The changes between two refs of a repository, e.g., of a pull request, as a PR-review bot
+//! needs them: the lines added and removed per file and per language, and the contributors of
+//! the commits. Unlike [`crate::delta::RepositoryDelta`], which compares two analysed
+//! snapshots, it reads the refs straight from git.
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{
+    commits::{get_diff_changes, FileChangeKind},
+    data::saturating_sum,
+    errors::SourceCodeError,
+    identity::{Author, IdentityOptions, IdentityResolver},
+};
+
+/// A file changed between the refs of a [`RefDiff`]
+///
+/// #Fields:
+/// * `path` - The relative path of the file in `head`, or in `base` if it was deleted
+/// * `old_path` - The relative path of the file in `base`, if it was renamed
+/// * `language` - The name of the language of the file, by its extension, if known
+/// * `kind` - The [`FileChangeKind`] of the change
+/// * `lines_added` - The lines added to the file
+/// * `lines_removed` - The lines removed from the file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RefFileDiff {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub language: Option<String>,
+    pub kind: FileChangeKind,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+
+/// The lines changed in a language between the refs of a [`RefDiff`]
+///
+/// #Fields:
+/// * `language` - The name of the language
+/// * `num_files` - The number of files in the language changed
+/// * `lines_added` - The lines added to the files in the language
+/// * `lines_removed` - The lines removed from the files in the language
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RefLanguageDiff {
+    pub language: String,
+    pub num_files: u64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+
+/// A contributor to the commits between the refs of a [`RefDiff`]
+///
+/// #Fields:
+/// * `author` - The [`Author`], as resolved with the mailmap
+/// * `num_commits` - The number of the commits by the author
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RefContributor {
+    pub author: Author,
+    pub num_commits: u64,
+}
+
+/// The changes from a `base` ref to a `head` ref, as a pull request of `head` into `base` shows
+/// them, i.e., from the merge base of the two, so that changes made on `base` since `head`
+/// branched off aren't counted
+///
+/// #Fields:
+/// * `base` - The `base` ref, as given
+/// * `head` - The `head` ref, as given
+/// * `merge_base` - The id of the commit the changes are counted from: the merge base of the
+///   refs, or the `base` commit if they have none
+/// * `head_commit` - The id of the `head` commit
+/// * `files` - The [`RefFileDiff`]s, by path
+/// * `languages` - The [`RefLanguageDiff`]s, most lines changed first
+/// * `contributors` - The [`RefContributor`]s to the commits in `head` but not `base`, most
+///   commits first
+/// * `num_commits` - The number of commits in `head` but not `base`
+/// * `lines_added` - The lines added across the `files`
+/// * `lines_removed` - The lines removed across the `files`
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RefDiff {
+    pub base: String,
+    pub head: String,
+    pub merge_base: String,
+    pub head_commit: String,
+    pub files: Vec<RefFileDiff>,
+    pub languages: Vec<RefLanguageDiff>,
+    pub contributors: Vec<RefContributor>,
+    pub num_commits: u64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+impl RefDiff {
+    /// Diffs the `head` ref of the repository at `repo_path` against the `base` ref, e.g.,
+    /// 'main' and 'feature/x'
+    ///
+    /// #Returns:
+    /// * The [`RefDiff`] of the refs
+    /// * Err([`SourceCodeError::GitError`]) if either ref doesn't resolve to a commit
+    pub fn new(repo_path: &str, base: &str, head: &str) -> Result<RefDiff, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let resolve = |reference: &str| -> Result<Oid, SourceCodeError> {
+            repo.revparse_single(reference)
+                .and_then(|object| object.peel_to_commit())
+                .map(|commit| commit.id())
+                .map_err(SourceCodeError::for_reference(reference))
+        };
+        let (base_commit, head_commit) = (resolve(base)?, resolve(head)?);
+        let merge_base = repo
+            .merge_base(base_commit, head_commit)
+            .unwrap_or(base_commit);
+
+        let mut diff = repo.diff_tree_to_tree(
+            Some(&repo.find_commit(merge_base)?.tree()?),
+            Some(&repo.find_commit(head_commit)?.tree()?),
+            None,
+        )?;
+        diff.find_similar(None)?;
+        let config = tokei::Config::default();
+        let files: Vec<RefFileDiff> = get_diff_changes(&diff, true, None)?
+            .into_iter()
+            .map(|change| RefFileDiff {
+                language: tokei::LanguageType::from_path(Path::new(&change.path), &config)
+                    .map(|language| language.name().to_string()),
+                path: change.path,
+                old_path: change.old_path,
+                kind: change.kind,
+                lines_added: change.lines_added,
+                lines_removed: change.lines_removed,
+            })
+            .collect();
+
+        let (contributors, num_commits) = Self::get_contributors(&repo, base_commit, head_commit)?;
+        Ok(RefDiff {
+            base: base.to_string(),
+            head: head.to_string(),
+            merge_base: merge_base.to_string(),
+            head_commit: head_commit.to_string(),
+            languages: Self::get_languages(&files),
+            contributors,
+            num_commits,
+            lines_added: saturating_sum(files.iter().map(|file| file.lines_added)),
+            lines_removed: saturating_sum(files.iter().map(|file| file.lines_removed)),
+            files,
+        })
+    }
+    /// Gets the [`RefDiff`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Sums the lines changed in the `files` of each language
+    fn get_languages(files: &[RefFileDiff]) -> Vec<RefLanguageDiff> {
+        let mut languages: BTreeMap<&str, RefLanguageDiff> = BTreeMap::new();
+        for file in files {
+            let Some(language) = file.language.as_deref() else {
+                continue;
+            };
+            let entry = languages
+                .entry(language)
+                .or_insert_with(|| RefLanguageDiff {
+                    language: language.to_string(),
+                    num_files: 0,
+                    lines_added: 0,
+                    lines_removed: 0,
+                });
+            entry.num_files += 1;
+            entry.lines_added = entry.lines_added.saturating_add(file.lines_added);
+            entry.lines_removed = entry.lines_removed.saturating_add(file.lines_removed);
+        }
+        let mut languages: Vec<RefLanguageDiff> = languages.into_values().collect();
+        languages.sort_by_key(|language| {
+            std::cmp::Reverse(language.lines_added.saturating_add(language.lines_removed))
+        });
+        languages
+    }
+    /// Gets the authors of the commits reachable from `head` but not `base`, with the number
+    /// of commits
+    fn get_contributors(
+        repo: &Repository,
+        base: Oid,
+        head: Oid,
+    ) -> Result<(Vec<RefContributor>, u64), SourceCodeError> {
+        let resolver = IdentityResolver::new(repo, &IdentityOptions::default());
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(head)?;
+        revwalk.hide(base)?;
+        let mut contributors: BTreeMap<String, RefContributor> = BTreeMap::new();
+        let mut num_commits: u64 = 0;
+        for oid in revwalk {
+            let author = resolver.resolve(&repo.find_commit(oid?)?.author());
+            contributors
+                .entry(author.canonical_id.clone())
+                .or_insert(RefContributor {
+                    author,
+                    num_commits: 0,
+                })
+                .num_commits += 1;
+            num_commits += 1;
+        }
+        let mut contributors: Vec<RefContributor> = contributors.into_values().collect();
+        contributors.sort_by_key(|contributor| std::cmp::Reverse(contributor.num_commits));
+        Ok((contributors, num_commits))
+    }
+}
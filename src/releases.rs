@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::collections::HashSet;
+
+#[cfg(feature = "git")]
+use crate::{data::to_count, errors::SourceCodeError};
+
+/// A release of a repository, i.e., a tag of a commit
+///
+/// #Fields:
+/// * `tag` - The name of the tag, e.g., 'v1.2.0'
+/// * `commit` - The id of the tagged commit
+/// * `date` - The date of the release: the tagger date of an annotated tag, else the (commit)
+///   date of the tagged commit
+/// * `days_since_previous` - The whole days since the previous release, or `None` for the first
+/// * `num_commits` - The number of commits in the release that aren't in any earlier release
+/// * `num_contributors` - The number of authors of the `num_commits` commits
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReleaseInfo {
+    pub tag: String,
+    pub commit: String,
+    pub date: DateTime<Utc>,
+    pub days_since_previous: Option<i64>,
+    pub num_commits: u64,
+    pub num_contributors: u64,
+}
+impl ReleaseInfo {
+    /// Gets the releases of the repository at `repo_path`, from its tags of commits
+    ///
+    /// #Returns:
+    /// * The [`ReleaseInfo`]s, from the oldest to the newest release
+    #[cfg(feature = "git")]
+    pub fn get_releases(repo_path: &str) -> Result<Vec<ReleaseInfo>, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let mut tags: Vec<(String, Oid, DateTime<Utc>)> = Vec::new();
+        for tag in repo.tag_names(None)?.iter().flatten() {
+            let reference = repo.find_reference(&format!("refs/tags/{}", tag))?;
+            // Tags of other objects, e.g., of trees or blobs, aren't releases
+            let Ok(commit) = reference.peel_to_commit() else {
+                continue;
+            };
+            let tagger_time = reference
+                .peel_to_tag()
+                .ok()
+                .and_then(|tag| tag.tagger().map(|tagger| tagger.when()));
+            let time = tagger_time.unwrap_or(commit.time());
+            let Some(date) = DateTime::<Utc>::from_timestamp(time.seconds(), 0) else {
+                continue;
+            };
+            tags.push((tag.to_string(), commit.id(), date));
+        }
+        tags.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+
+        let mut releases: Vec<ReleaseInfo> = Vec::new();
+        for (index, (tag, commit_id, date)) in tags.iter().enumerate() {
+            let mut revwalk = repo.revwalk()?;
+            revwalk.push(*commit_id)?;
+            for (_, earlier_commit_id, _) in &tags[..index] {
+                revwalk.hide(*earlier_commit_id)?;
+            }
+            let mut num_commits: u64 = 0;
+            let mut authors: HashSet<String> = HashSet::new();
+            for oid in revwalk {
+                let commit = repo.find_commit(oid?)?;
+                num_commits += 1;
+                authors.insert(String::from(commit.author().name().unwrap_or_default()));
+            }
+            releases.push(ReleaseInfo {
+                tag: tag.clone(),
+                commit: commit_id.to_string(),
+                date: *date,
+                days_since_previous: index
+                    .checked_sub(1)
+                    .map(|previous| (*date - tags[previous].2).num_days()),
+                num_commits,
+                num_contributors: to_count(authors.len()),
+            });
+        }
+        Ok(releases)
+    }
+}
@@ -0,0 +1,584 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+use crate::{
+    archetype::RepositoryArchetype,
+    data::Distribution,
+    delta::RepositoryDelta,
+    errors::SourceCodeError,
+    ratio::Percentage,
+    repository::RepositoryInfo,
+    scoring::{HealthReport, HealthWeights, ScoringModel},
+};
+
+/// The units of the human-readable byte sizes, in powers of 1024
+const BYTE_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+/// The width of the health score ranges of the histogram of a [`HealthDistribution`]
+const HEALTH_BUCKET_WIDTH: f32 = 20.0;
+
+/// The locale the numbers, byte sizes and dates of the human-readable reports are formatted in,
+/// e.g., 12,345 or 12.345 lines; the labels of the reports aren't translated
+///
+/// #Fields:
+/// * `tag` - The BCP 47 language tag of the locale, e.g., 'en' or 'de-DE', set as the language
+///   of HTML documents
+/// * `thousands_separator` - The separator of each group of three digits of whole numbers
+/// * `decimal_separator` - The separator of the fractional digits
+/// * `date_format` - The `chrono` format of dates, e.g., '%d.%m.%Y'
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct ReportLocale {
+    pub tag: String,
+    pub thousands_separator: String,
+    pub decimal_separator: String,
+    pub date_format: String,
+}
+impl Default for ReportLocale {
+    fn default() -> Self {
+        Self {
+            tag: "en".to_string(),
+            thousands_separator: ",".to_string(),
+            decimal_separator: ".".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+        }
+    }
+}
+impl ReportLocale {
+    /// Gets the [`ReportLocale`] of a BCP 47 language `tag`, e.g., 'de-DE' or 'fr', with the
+    /// separators and date format usual for it; a language without them uses those of the
+    /// default, English, locale, with ISO 8601 dates
+    pub fn from_tag(tag: &str) -> Self {
+        let normalised = tag.to_lowercase().replace('_', "-");
+        let mut subtags = normalised.split('-');
+        let language = subtags.next().unwrap_or_default();
+        let region = subtags.next().unwrap_or_default();
+        let (thousands_separator, decimal_separator, date_format) = match (language, region) {
+            ("en", "us") => (",", ".", "%m/%d/%Y"),
+            ("en", "gb" | "au" | "nz" | "ie") => (",", ".", "%d/%m/%Y"),
+            ("de", "ch") => ("\u{2019}", ".", "%d.%m.%Y"),
+            ("de", _) => (".", ",", "%d.%m.%Y"),
+            // A narrow no-break space, so that a number isn't wrapped
+            ("fr", _) => ("\u{202f}", ",", "%d/%m/%Y"),
+            ("es" | "it" | "pt", _) => (".", ",", "%d/%m/%Y"),
+            ("nl", _) => (".", ",", "%d-%m-%Y"),
+            ("sv", _) => ("\u{a0}", ",", "%Y-%m-%d"),
+            ("nb" | "no" | "fi", _) => ("\u{a0}", ",", "%d.%m.%Y"),
+            ("ja" | "zh" | "ko", _) => (",", ".", "%Y/%m/%d"),
+            _ => {
+                return Self {
+                    tag: tag.to_string(),
+                    ..Self::default()
+                }
+            }
+        };
+        Self {
+            tag: tag.to_string(),
+            thousands_separator: thousands_separator.to_string(),
+            decimal_separator: decimal_separator.to_string(),
+            date_format: date_format.to_string(),
+        }
+    }
+    /// Formats a whole `number` with the thousands separator, e.g., '1,234,567'
+    pub fn format_number(&self, number: u64) -> String {
+        self.group_digits(&number.to_string())
+    }
+    /// Formats a `number` with the thousands and decimal separators, rounded to `decimals`
+    /// fractional digits, e.g., '1,234.5'
+    pub fn format_decimal(&self, number: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, number.abs());
+        let (whole, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        // A negative number that rounds to zero has no sign
+        let sign = match number < 0.0 && formatted.chars().any(|c| ('1'..='9').contains(&c)) {
+            true => "-",
+            false => "",
+        };
+        let whole = self.group_digits(whole);
+        match fraction.is_empty() {
+            true => format!("{}{}", sign, whole),
+            false => format!("{}{}{}{}", sign, whole, self.decimal_separator, fraction),
+        }
+    }
+    /// Formats a size in `bytes` in the largest binary unit it has at least one of, to one
+    /// fractional digit, e.g., '512 B', '1.5 KiB' or '3.2 MiB'
+    pub fn format_bytes(&self, bytes: u64) -> String {
+        let mut size = bytes as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < BYTE_UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        match unit {
+            0 => format!("{} {}", self.format_number(bytes), BYTE_UNITS[0]),
+            _ => format!("{} {}", self.format_decimal(size, 1), BYTE_UNITS[unit]),
+        }
+    }
+    /// Formats a `date` with the date format, in UTC
+    pub fn format_date(&self, date: &DateTime<Utc>) -> String {
+        let mut formatted = String::new();
+        // An invalid format is written as it is rather than panicking
+        match write!(formatted, "{}", date.format(&self.date_format)) {
+            Ok(()) => formatted,
+            Err(_) => self.date_format.clone(),
+        }
+    }
+    /// Separates the `digits` of a whole number into groups of three
+    fn group_digits(&self, digits: &str) -> String {
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                grouped.push_str(&self.thousands_separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+}
+
+/// Compares the repositories side by side, e.g., for platform teams choosing between services to
+/// consolidate, see [`ComparativeReport`]
+pub fn compare_repositories(repositories: &[RepositoryInfo]) -> ComparativeReport {
+    ComparativeReport::new(repositories)
+}
+
+/// A comparative report across several repositories: a table of repositories × key metrics,
+/// with the language mix of each, plus a ranking of the repositories for each metric.
+///
+/// #Fields:
+/// * `repositories` - One [`RepositoryRow`] per repository, in the order given
+/// * `rankings` - One [`MetricRanking`] per metric in [`ComparativeReport::METRICS`]
+/// * `health_distribution` - The [`HealthDistribution`] of the health scores of the
+///   repositories, e.g., of a portfolio
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ComparativeReport {
+    pub repositories: Vec<RepositoryRow>,
+    pub rankings: Vec<MetricRanking>,
+    #[serde(default)]
+    pub health_distribution: HealthDistribution,
+}
+impl ComparativeReport {
+    /// The metrics that each repository is ranked by
+    pub const METRICS: [&'static str; 8] = [
+        "loc",
+        "size",
+        "num_files",
+        "num_commits",
+        "num_contributors",
+        "num_languages",
+        "churn",
+        "health_score",
+    ];
+
+    /// Creates a new [`ComparativeReport`] from the [`RepositoryInfo`]s, with their health
+    /// scored by the default [`HealthWeights`]
+    pub fn new(repositories: &[RepositoryInfo]) -> Self {
+        Self::new_with_model(repositories, &HealthWeights::default())
+    }
+    /// Creates a new [`ComparativeReport`] from the [`RepositoryInfo`]s, with their health
+    /// scored by the [`ScoringModel`]
+    pub fn new_with_model(repositories: &[RepositoryInfo], model: &dyn ScoringModel) -> Self {
+        let rows: Vec<RepositoryRow> = repositories
+            .iter()
+            .map(|repository_info| RepositoryRow::new_with_model(repository_info, model))
+            .collect();
+        let rankings = Self::METRICS
+            .iter()
+            .map(|metric| MetricRanking::new(metric, &rows))
+            .collect();
+        let health_distribution = HealthDistribution::new(&rows);
+
+        Self {
+            repositories: rows,
+            rankings,
+            health_distribution,
+        }
+    }
+    /// Gets the [`ComparativeReport`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Gets the [`ComparativeReport`] as a self-contained HTML document, with the numbers and
+    /// sizes formatted in the default [`ReportLocale`]
+    pub fn get_as_html(&self) -> String {
+        self.get_as_html_with_locale(&ReportLocale::default())
+    }
+    /// Gets the [`ComparativeReport`] as a self-contained HTML document, with the numbers and
+    /// sizes formatted in the [`ReportLocale`]
+    pub fn get_as_html_with_locale(&self, locale: &ReportLocale) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"{}\">\n<head><meta charset=\"utf-8\"><title>Repository comparison</title></head>\n<body>\n",
+            escape_html(&locale.tag)
+        );
+        html.push_str("<h1>Repository comparison</h1>\n<table>\n<tr><th>Repository</th><th>Predominant language</th><th>Archetype</th>");
+        for metric in Self::METRICS {
+            let _ = write!(html, "<th>{}</th>", escape_html(metric));
+        }
+        html.push_str("<th>Language mix</th></tr>\n");
+        for row in &self.repositories {
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td>",
+                escape_html(&row.name),
+                escape_html(row.predominant_language.as_deref().unwrap_or_default()),
+                escape_html(&row.archetype.to_string())
+            );
+            for metric in Self::METRICS {
+                let _ = write!(
+                    html,
+                    "<td>{}</td>",
+                    escape_html(&row.format_metric(metric, locale))
+                );
+            }
+            let _ = write!(
+                html,
+                "<td>{}</td>",
+                escape_html(&row.format_languages(locale))
+            );
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n<h2>Health score distribution</h2>\n");
+        let _ = write!(
+            html,
+            "<p>{}</p>\n<table>\n<tr><th>Health score</th><th>Repositories</th></tr>\n",
+            escape_html(&self.health_distribution.format_scores(locale))
+        );
+        for bucket in &self.health_distribution.histogram {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape_html(&bucket.format_range(locale)),
+                locale.format_number(bucket.count)
+            );
+        }
+        html.push_str("</table>\n<h2>Rankings</h2>\n");
+        for ranking in &self.rankings {
+            let _ = write!(html, "<h3>{}</h3>\n<ol>\n", escape_html(&ranking.metric));
+            for name in &ranking.ranked {
+                let _ = writeln!(html, "<li>{}</li>", escape_html(name));
+            }
+            html.push_str("</ol>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+    /// Gets the [`ComparativeReport`] as a Markdown document, with the numbers and sizes
+    /// formatted in the default [`ReportLocale`]
+    pub fn get_as_markdown(&self) -> String {
+        self.get_as_markdown_with_locale(&ReportLocale::default())
+    }
+    /// Gets the [`ComparativeReport`] as a Markdown document, with the numbers and sizes
+    /// formatted in the [`ReportLocale`]: a table of the metrics, a list of the language mix of
+    /// each repository, and the rankings
+    pub fn get_as_markdown_with_locale(&self, locale: &ReportLocale) -> String {
+        let mut markdown = String::from(
+            "# Repository comparison\n\n| Repository | Predominant language | Archetype |",
+        );
+        for metric in Self::METRICS {
+            let _ = write!(markdown, " {} |", metric);
+        }
+        markdown.push_str("\n|---|---|---|");
+        for _ in Self::METRICS {
+            markdown.push_str("---:|");
+        }
+        markdown.push('\n');
+        for row in &self.repositories {
+            let _ = write!(
+                markdown,
+                "| {} | {} | {} |",
+                escape_markdown(&row.name),
+                escape_markdown(row.predominant_language.as_deref().unwrap_or_default()),
+                escape_markdown(&row.archetype.to_string())
+            );
+            for metric in Self::METRICS {
+                let _ = write!(markdown, " {} |", row.format_metric(metric, locale));
+            }
+            markdown.push('\n');
+        }
+        markdown.push_str("\n## Language mix\n\n");
+        for row in &self.repositories {
+            let _ = writeln!(
+                markdown,
+                "- **{}**: {}",
+                escape_markdown(&row.name),
+                escape_markdown(&row.format_languages(locale))
+            );
+        }
+        let _ = write!(
+            markdown,
+            "\n## Health score distribution\n\n{}\n\n| Health score | Repositories |\n|---|---:|\n",
+            self.health_distribution.format_scores(locale)
+        );
+        for bucket in &self.health_distribution.histogram {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} |",
+                bucket.format_range(locale),
+                locale.format_number(bucket.count)
+            );
+        }
+        markdown.push_str("\n## Rankings\n");
+        for ranking in &self.rankings {
+            let _ = write!(markdown, "\n### {}\n\n", ranking.metric);
+            for (index, name) in ranking.ranked.iter().enumerate() {
+                let _ = writeln!(markdown, "{}. {}", index + 1, escape_markdown(name));
+            }
+        }
+        markdown
+    }
+}
+
+/// The share of a language of the lines of code of a repository in a [`ComparativeReport`]
+///
+/// #Fields:
+/// * `name` - The name of the language
+/// * `loc` - The lines of code in the language
+/// * `share` - The [`Percentage`] of the lines of code of the repository in the language
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LanguageShare {
+    pub name: String,
+    pub loc: u64,
+    pub share: Percentage,
+}
+
+/// The key metrics for a single repository in a [`ComparativeReport`]
+///
+/// #Fields:
+/// * `name` - The name of the repository
+/// * `predominant_language` - The name of the predominant language, if known
+/// * `archetype` - The [`RepositoryArchetype`] of the repository
+/// * `loc` - The total lines of code
+/// * `size` - The total size of the source files in bytes
+/// * `num_files` - The number of source files
+/// * `num_commits` - The number of commits
+/// * `num_contributors` - The number of contributors
+/// * `num_languages` - The number of distinct languages in the source files
+/// * `churn` - The churn, i.e., the sum of the per-file commit counts, as in
+///   [`crate::delta::RepositoryDelta`]
+/// * `health_score` - The health score of the repository, from 0 (unhealthy) to 100 (healthy),
+///   see [`HealthReport`]
+/// * `languages` - The [`LanguageShare`] of each language, the most lines of code first
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RepositoryRow {
+    pub name: String,
+    pub predominant_language: Option<String>,
+    pub archetype: RepositoryArchetype,
+    pub loc: u64,
+    pub size: u64,
+    pub num_files: u64,
+    pub num_commits: u64,
+    pub num_contributors: u64,
+    pub num_languages: u64,
+    #[serde(default)]
+    pub churn: u64,
+    #[serde(default)]
+    pub health_score: f32,
+    #[serde(default)]
+    pub languages: Vec<LanguageShare>,
+}
+impl RepositoryRow {
+    /// Creates a new [`RepositoryRow`] from a [`RepositoryInfo`], with its health scored by the
+    /// default [`HealthWeights`]
+    pub fn new_from(repository_info: &RepositoryInfo) -> Self {
+        Self::new_with_model(repository_info, &HealthWeights::default())
+    }
+    /// Creates a new [`RepositoryRow`] from a [`RepositoryInfo`], with its health scored by the
+    /// [`ScoringModel`]
+    pub fn new_with_model(repository_info: &RepositoryInfo, model: &dyn ScoringModel) -> Self {
+        let mut languages: Vec<&str> = repository_info
+            .source_files
+            .iter()
+            .filter_map(|sfi| sfi.language.as_ref().map(|l| l.name.as_str()))
+            .collect();
+        languages.sort_unstable();
+        languages.dedup();
+
+        let total_loc = repository_info.statistics.loc;
+        let mut language_shares: Vec<LanguageShare> = repository_info
+            .languages
+            .iter()
+            .filter_map(|language| {
+                let loc = language.statistics.as_ref()?.loc;
+                Some(LanguageShare {
+                    name: language.name.clone(),
+                    loc,
+                    share: Percentage::of(loc as f64, total_loc as f64).unwrap_or_default(),
+                })
+            })
+            .collect();
+        language_shares.sort_by(|a, b| b.loc.cmp(&a.loc).then_with(|| a.name.cmp(&b.name)));
+
+        Self {
+            name: repository_info.name.clone(),
+            predominant_language: repository_info
+                .predominant_language
+                .as_ref()
+                .map(|l| l.name.clone())
+                .filter(|name| !name.is_empty()),
+            archetype: repository_info.archetype,
+            loc: total_loc,
+            size: repository_info.statistics.size,
+            num_files: repository_info.statistics.num_files,
+            num_commits: repository_info.statistics.num_commits,
+            num_contributors: repository_info.contributors.len() as u64,
+            num_languages: languages.len() as u64,
+            churn: RepositoryDelta::get_churn(repository_info),
+            health_score: HealthReport::new(repository_info, model).score,
+            languages: language_shares,
+        }
+    }
+    /// Gets the value of a metric by name, as listed in [`ComparativeReport::METRICS`]
+    pub fn get_metric(&self, metric: &str) -> f64 {
+        match metric {
+            "loc" => self.loc as f64,
+            "size" => self.size as f64,
+            "num_files" => self.num_files as f64,
+            "num_commits" => self.num_commits as f64,
+            "num_contributors" => self.num_contributors as f64,
+            "num_languages" => self.num_languages as f64,
+            "churn" => self.churn as f64,
+            "health_score" => self.health_score as f64,
+            _ => 0.0,
+        }
+    }
+    /// Gets the value of a metric by name, formatted in the [`ReportLocale`]: the size as a
+    /// byte size, the health score to one decimal place, and the other metrics as numbers
+    pub fn format_metric(&self, metric: &str, locale: &ReportLocale) -> String {
+        match metric {
+            "size" => locale.format_bytes(self.size),
+            "health_score" => locale.format_decimal(self.health_score as f64, 1),
+            _ => locale.format_number(self.get_metric(metric) as u64),
+        }
+    }
+    /// Gets the language mix formatted in the [`ReportLocale`], e.g., 'Rust 80.5%, Python 19.5%'
+    pub fn format_languages(&self, locale: &ReportLocale) -> String {
+        self.languages
+            .iter()
+            .map(|language| {
+                format!(
+                    "{} {}%",
+                    language.name,
+                    locale.format_decimal(language.share.value() as f64, 1)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// The number of repositories whose health score is in a range of a [`HealthDistribution`]
+///
+/// #Fields:
+/// * `from` - The lowest score of the range
+/// * `to` - The score the range ends before, or the highest score, 100, of the last range
+/// * `count` - The number of repositories with a score in the range
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct HealthBucket {
+    pub from: f32,
+    pub to: f32,
+    pub count: u64,
+}
+impl HealthBucket {
+    /// Gets the range formatted in the [`ReportLocale`], e.g., '40-60'
+    pub fn format_range(&self, locale: &ReportLocale) -> String {
+        format!(
+            "{}-{}",
+            locale.format_number(self.from as u64),
+            locale.format_number(self.to as u64)
+        )
+    }
+}
+
+/// The distribution of the health scores of the repositories of a [`ComparativeReport`], e.g.,
+/// to see how healthy a portfolio is overall, and how many of its repositories need attention
+///
+/// #Fields:
+/// * `scores` - The [`Distribution`] of the scores, e.g., their median and 90th percentile; all
+///   zero if there are no repositories
+/// * `histogram` - The [`HealthBucket`]s of the scores, in ranges of 20 from 0 to 100
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct HealthDistribution {
+    pub scores: Distribution,
+    pub histogram: Vec<HealthBucket>,
+}
+impl HealthDistribution {
+    /// Gets the [`HealthDistribution`] of the health scores of the `rows`
+    pub fn new(rows: &[RepositoryRow]) -> Self {
+        let num_buckets = (100.0 / HEALTH_BUCKET_WIDTH) as usize;
+        let mut histogram: Vec<HealthBucket> = (0..num_buckets)
+            .map(|index| HealthBucket {
+                from: index as f32 * HEALTH_BUCKET_WIDTH,
+                to: (index + 1) as f32 * HEALTH_BUCKET_WIDTH,
+                count: 0,
+            })
+            .collect();
+        for row in rows {
+            // A score of 100 is in the last range
+            let index = ((row.health_score / HEALTH_BUCKET_WIDTH) as usize).min(num_buckets - 1);
+            histogram[index].count += 1;
+        }
+        Self {
+            scores: Distribution::new(rows.iter().map(|row| row.health_score as f64).collect()),
+            histogram,
+        }
+    }
+    /// Gets the minimum, median, 90th percentile and maximum of the scores formatted in the
+    /// [`ReportLocale`], e.g., 'min 42.0, median 71.5, p90 88.0, max 93.5'
+    pub fn format_scores(&self, locale: &ReportLocale) -> String {
+        format!(
+            "min {}, median {}, p90 {}, max {}",
+            locale.format_decimal(self.scores.min, 1),
+            locale.format_decimal(self.scores.median, 1),
+            locale.format_decimal(self.scores.p90, 1),
+            locale.format_decimal(self.scores.max, 1)
+        )
+    }
+}
+
+/// The repositories ranked (highest first) by a single metric
+///
+/// #Fields:
+/// * `metric` - The name of the metric
+/// * `ranked` - The repository names, ordered from the highest to the lowest value
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MetricRanking {
+    pub metric: String,
+    pub ranked: Vec<String>,
+}
+impl MetricRanking {
+    fn new(metric: &str, rows: &[RepositoryRow]) -> Self {
+        let mut sorted: Vec<&RepositoryRow> = rows.iter().collect();
+        sorted.sort_by(|a, b| {
+            b.get_metric(metric)
+                .total_cmp(&a.get_metric(metric))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Self {
+            metric: metric.to_string(),
+            ranked: sorted.into_iter().map(|row| row.name.clone()).collect(),
+        }
+    }
+}
+
+/// Escapes the characters of `text` that would break a Markdown table or list, i.e., pipes,
+/// backslashes and line breaks
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\n', '\r'], " ")
+}
+
+/// Escapes the HTML special characters in `text`
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,207 +1,2076 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
-use git2::{Commit, Repository, Revwalk};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::{ErrorCode, FileMode, ObjectType, Odb, Oid, Repository, TreeWalkMode, TreeWalkResult};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tokei::{Config, Languages};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "git")]
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs::{self, File},
+    io::Read,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+#[cfg(feature = "git")]
+use tokei::{Languages, Report};
 
 use crate::{
-    data::Statistics,
+    archetype::RepositoryArchetype,
+    assets::AssetSummary,
+    branches::BranchReport,
+    cadence::ContributorCadence,
+    catalog::MetricDefinition,
+    churn::FunctionChurnReport,
+    codeowners::OwnershipReport,
+    context::CompactContext,
+    coownership::CoOwnershipMatrix,
+    coupling::CouplingReport,
+    data::{Statistics, StatisticsSummary},
+    delta::RepositoryDelta,
+    dependencies::DependencySummary,
+    duplication::{DuplicationReport, IdenticalFilesReport},
+    embedding::similar::SimilarFilesReport,
+    encoding::EncodingSummary,
     errors::SourceCodeError,
-    source::{LanguageType, SourceFileInfo},
+    forge::{ForgeMetadata, ForgeUser},
+    framework::Framework,
+    generated::GeneratedCodeReport,
+    grep::{ContentMatch, ContentPattern},
+    heatmap::CommitHeatmap,
+    history::{CommitCounts, CommitHistory},
+    identity::Author,
+    layout::PathLayout,
+    messages::CommitMessageStatistics,
+    metadata::AnalysisMetadata,
+    migrate,
+    objects::StorageReport,
+    organisation::OrganisationReport,
+    package::PackageInfo,
+    quadrant::QuadrantReport,
+    quality::MetricQuality,
+    query::FileQuery,
+    releases::ReleaseInfo,
+    retention::ContributorRetention,
+    role::RoleReport,
+    roots::AnalysisRoot,
+    scoring::{HealthReport, ScoringModel},
+    shallow,
+    source::{FrequencyDenominator, LanguageType, SourceFileInfo},
+    submodule::SubmoduleInfo,
+    targets::ExecutableTarget,
+    techdebt::TechDebtMarker,
+    testcode::TestCodeReport,
+    warning::AnalysisWarning,
+    whitespace::WhitespaceReport,
+    worktree::WorkingTreeStatistics,
+};
+#[cfg(feature = "git")]
+use crate::{
+    assets::AssetFile,
+    baseline::BaselineHistory,
+    cadence::CadenceOptions,
+    cancel::{self, CancellationToken, PartialResults},
+    checkpoint::{CheckpointOptions, Checkpointer},
+    counting::CountingOptions,
+    data::{saturating_sum, to_count},
+    derived::compute_derived_metrics,
+    detect,
+    export::anonymise::PathHasher,
+    generated,
+    graph::CommitGraph,
+    head::HeadState,
+    history::{HistoryMode, HistorySampling},
+    identity::{BotHandling, IdentityOptions},
+    languages::LanguageRegistry,
+    limits::ResourceLimits,
+    messages::CommitMessageCounter,
+    observer::{AnalysisObserver, AnalysisStage, NoopObserver, StageTimer},
+    options::AnalysisOptions,
+    package::WorkspacePackage,
+    paths,
+    provider::{compute_custom_metrics, MetricProvider},
+    quadrant,
+    ratio::{self, Percentage},
+    refdiff::RefDiff,
+    retention,
+    review::{ReviewerOptions, ReviewerSuggestion},
+    role::{self, FileRole},
+    roots::analyse_roots,
+    runstats::RunCounters,
+    source::{self, FrequencyBasis, MergeStrategy, SourceFileContext},
+    symbols, testcode, walk,
+    warning::WarningKind,
+    worktree,
 };
 
+/// The [`SourceFileInfo`]s, the relative paths of the files in undetected languages and the
+/// [`AssetFile`]s collected from a repository
+#[cfg(feature = "git")]
+type CollectedFiles = (Vec<SourceFileInfo>, Vec<String>, Vec<AssetFile>);
+
+/// A symlink in a repository
+///
+/// #Fields:
+/// * `relative_path` - The relative path of the symlink
+/// * `target` - The target, as written in the symlink
+/// * `resolved` - The relative path of the file the target resolves to, if it is a file in the
+///   repository
+#[cfg(feature = "git")]
+struct Symlink {
+    relative_path: String,
+    target: String,
+    resolved: Option<String>,
+}
+
 /// Represents the information for a software source repository (Git)
 ///
 /// #Fields:
+/// * `schema_version` - The version of the serialised layout, see [`migrate::SCHEMA_VERSION`]
 /// * `name` - The name of the repository
+/// * `scope` - The subdirectory the analysis was restricted to, e.g., a package of a monorepo, if
+///   scoped with [`AnalysisOptions::with_scope`]; its history and contributors only cover the
+///   commits changing it
 /// * `predominant_language` - The [`LanguageType`] of the repository
+/// * `languages` - The [`LanguageType`]s in the repository, with their aggregated [`Statistics`]
+/// * `archetype` - The [`RepositoryArchetype`] of the repository, e.g., library or service
+/// * `frameworks` - The [`Framework`]s detected in the repository
+/// * `targets` - The [`ExecutableTarget`]s the repository ships
+/// * `dependencies` - The [`DependencySummary`] of the repository's licenses and direct
+///   dependencies
+/// * `packages` - The [`PackageInfo`]s of the packages declared by the repository's workspaces,
+///   e.g., the members of a Cargo workspace, if it is a monorepo
+/// * `layout` - The [`PathLayout`] of the repository, i.e., its path depths and directory fan-out
+/// * `duplication` - The [`DuplicationReport`] of the duplicated code, if requested in the
+///   [`AnalysisOptions`]
+/// * `identical_files` - The [`IdenticalFilesReport`] of the source files that are identical
+///   copies of each other
+/// * `similar_files` - The [`SimilarFilesReport`] of the source files whose embeddings are nearly
+///   the same, if found with [`crate::embedding::EmbeddingPipeline::find_similar_files`] once the
+///   repository is embedded
+/// * `test_code` - The [`TestCodeReport`] of the lines of test and production code
+/// * `generated_code` - The [`GeneratedCodeReport`] of the generated source files, with the
+///   totals with and without them
+/// * `quadrants` - The [`QuadrantReport`] of the source files by their age and churn
+/// * `roles` - The [`RoleReport`] of the source files by their role, e.g., the lines of code
+///   without configuration and documentation
+/// * `roots` - The [`AnalysisRoot`]s of a multi-root analysis, see
+///   [`RepositoryInfo::new_multi_root`], or empty if a single repository was analysed
 /// * `statistics` - The [`Statistics`] on the repository
+/// * `statistics_summary` - The [`StatisticsSummary`] of how the lines of code, size and churn
+///   are distributed over the source files
+/// * `frequency_denominator` - The [`FrequencyDenominator`] of the change frequencies of the
+///   source files, i.e., the commits each is a share of
 /// * `contributors` - The [`Contributor`]s to the repository
+/// * `contributor_retention` - The [`ContributorRetention`] of the contributors, i.e., whether
+///   their number is growing or shrinking
+/// * `organisations` - The [`OrganisationReport`] of the commits and lines of code of each
+///   organisation, by the email domains of the contributors
+/// * `history` - The [`CommitHistory`] of HEAD, or `None` if the repository has no commits yet
+/// * `history_complete` - Whether the whole history was analysed, i.e., the repository isn't a
+///   shallow clone; if not, the commit counts and change frequencies only cover part of it
+/// * `commit_counts` - The [`CommitCounts`] of both [`HistoryMode`]s, if requested in the
+///   [`AnalysisOptions`]
+/// * `history_depth` - The number of commits on the first-parent line from HEAD to the shallow
+///   boundary, if the repository is a shallow clone
+/// * `releases` - The [`ReleaseInfo`]s of the repository's tags, from the oldest to the newest
+/// * `tech_debt_markers` - The [`TechDebtMarker`]s in the comments of the source files
+/// * `coupling` - The [`CouplingReport`] of the files that change together, if requested in the
+///   [`AnalysisOptions`]
+/// * `function_churn` - The [`FunctionChurnReport`] of the functions that change most often, if
+///   requested in the [`AnalysisOptions`]
+/// * `branches` - The [`BranchReport`] of the branches, if requested in the [`AnalysisOptions`]
+/// * `storage` - The [`StorageReport`] of the object database, if requested in the
+///   [`AnalysisOptions`]
+/// * `co_ownership` - The [`CoOwnershipMatrix`] of the contributors' commits to each directory,
+///   if requested in the [`AnalysisOptions`]
+/// * `ownership` - The [`OwnershipReport`] of the files' owners, if the repository has a
+///   CODEOWNERS file
+/// * `forge` - The [`ForgeMetadata`] of the repository on its forge, e.g., GitHub, if enriched
+///   with [`crate::forge`]
+/// * `submodules` - The [`SubmoduleInfo`]s of the repository's git submodules, whose files aren't counted
 /// * `source_files` - The [`SourceFileInfo`]s for the source files of the repository
+/// * `unknown_files` - The relative paths of the (text) files whose language couldn't be detected
+/// * `assets` - The [`AssetSummary`] of the binary files, e.g., images, archives and models
+/// * `encodings` - The [`EncodingSummary`] of the source files that aren't UTF-8
+/// * `whitespace` - The [`WhitespaceReport`] of the line endings, trailing whitespace and final
+///   newlines of the source files
+/// * `working_tree` - The [`WorkingTreeStatistics`] on uncommitted changes, if requested in the [`AnalysisOptions`]
+/// * `dirty_worktree` - Whether the working tree had uncommitted changes to tracked files when
+///   analysed, which are only counted if `include_uncommitted` is set in the [`AnalysisOptions`]
+/// * `metrics_catalog` - The [`MetricDefinition`]s of the metrics present in the output
+/// * `quality` - The [`MetricQuality`] of each metric family, e.g., approximate for a shallow
+///   history
+/// * `warnings` - The [`AnalysisWarning`]s for files that couldn't be fully analysed, e.g., binary files,
+///   or were left out as they couldn't be analysed at all, e.g., unreadable files
+/// * `metadata` - The [`AnalysisMetadata`] on how the analysis was run
+/// * `fingerprint` - The [`RepositoryInfo::fingerprint`] of the analysis, computed before any
+///   contents or hashes were dropped, e.g., to key caches or vector store collections
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RepositoryInfo {
+    #[serde(default = "migrate::unversioned")]
+    pub schema_version: u32,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
     pub predominant_language: Option<LanguageType>,
+    pub languages: Vec<LanguageType>,
+    pub archetype: RepositoryArchetype,
+    pub frameworks: Vec<Framework>,
+    pub targets: Vec<ExecutableTarget>,
+    #[serde(default)]
+    pub dependencies: DependencySummary,
+    #[serde(default)]
+    pub packages: Vec<PackageInfo>,
+    pub layout: PathLayout,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplication: Option<DuplicationReport>,
+    #[serde(default)]
+    pub identical_files: IdenticalFilesReport,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similar_files: Option<SimilarFilesReport>,
+    #[serde(default)]
+    pub test_code: TestCodeReport,
+    #[serde(default)]
+    pub generated_code: GeneratedCodeReport,
+    #[serde(default)]
+    pub quadrants: QuadrantReport,
+    #[serde(default)]
+    pub roles: RoleReport,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<AnalysisRoot>,
     pub statistics: Statistics,
+    #[serde(default)]
+    pub statistics_summary: StatisticsSummary,
+    #[serde(default)]
+    pub frequency_denominator: FrequencyDenominator,
     pub contributors: Vec<Contributor>,
+    #[serde(default)]
+    pub contributor_retention: ContributorRetention,
+    #[serde(default)]
+    pub organisations: OrganisationReport,
+    pub history: Option<CommitHistory>,
+    #[serde(default = "shallow::assumed_complete")]
+    pub history_complete: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_counts: Option<CommitCounts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_depth: Option<u64>,
+    #[serde(default)]
+    pub releases: Vec<ReleaseInfo>,
+    #[serde(default)]
+    pub tech_debt_markers: Vec<TechDebtMarker>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coupling: Option<CouplingReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_churn: Option<FunctionChurnReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branches: Option<BranchReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub co_ownership: Option<CoOwnershipMatrix>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ownership: Option<OwnershipReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forge: Option<ForgeMetadata>,
+    pub submodules: Vec<SubmoduleInfo>,
     pub source_files: Vec<SourceFileInfo>,
+    pub unknown_files: Vec<String>,
+    #[serde(default)]
+    pub assets: AssetSummary,
+    #[serde(default)]
+    pub encodings: EncodingSummary,
+    #[serde(default)]
+    pub whitespace: WhitespaceReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_tree: Option<WorkingTreeStatistics>,
+    #[serde(default)]
+    pub dirty_worktree: bool,
+    pub metrics_catalog: Vec<MetricDefinition>,
+    #[serde(default)]
+    pub quality: Vec<MetricQuality>,
+    pub warnings: Vec<AnalysisWarning>,
+    pub metadata: Option<AnalysisMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
 }
 impl RepositoryInfo {
+    #[cfg(feature = "git")]
     pub fn new(name: String, repo_path: &str, excluded: &[&str]) -> Result<Self, SourceCodeError> {
-        let source_files: Vec<SourceFileInfo> =
-            Self::get_source_file_info_for_repo(&[repo_path], excluded)?;
+        Self::new_with_options(
+            name,
+            repo_path,
+            &AnalysisOptions::new().with_excluded(excluded),
+        )
+    }
+    /// Analyses the files under the `subdir` of the repository at `repo_path`, e.g., a package
+    /// of a monorepo, as [`AnalysisOptions::with_scope`] does
+    #[cfg(feature = "git")]
+    pub fn new_scoped(
+        name: String,
+        repo_path: &str,
+        subdir: &str,
+    ) -> Result<Self, SourceCodeError> {
+        Self::new_with_options(name, repo_path, &AnalysisOptions::new().with_scope(subdir))
+    }
+    /// Analyses the repository at `repo_path` as configured by the [`AnalysisOptions`]
+    #[cfg(feature = "git")]
+    pub fn new_with_options(
+        name: String,
+        repo_path: &str,
+        options: &AnalysisOptions,
+    ) -> Result<Self, SourceCodeError> {
+        Self::new_with_observer(name, repo_path, options, &NoopObserver)
+    }
+    /// Analyses several roots as one repository, e.g., checkouts of related repositories or the
+    /// source and test trees of one, each in the git repository it is in, so that the churn of
+    /// each file, and the contributors, come from the history of its own repository. The roots
+    /// are analysed as configured by the [`AnalysisOptions`], each scoped to its subdirectory
+    /// of its repository, if it is one; a root under another is analysed as part of it.
+    ///
+    /// #Returns:
+    /// * The [`RepositoryInfo`] of the roots, with the files of each prefixed with the name of
+    ///   its repository's directory if they are in more than one repository, and the
+    ///   [`AnalysisRoot`] of each in `roots`; a commit changing two roots of a repository is
+    ///   counted by both in the totals
+    /// * Err([`SourceCodeError::GitError`]) if a root isn't in a git repository
+    #[cfg(feature = "git")]
+    pub fn new_multi_root(
+        name: String,
+        roots: &[&str],
+        options: &AnalysisOptions,
+    ) -> Result<Self, SourceCodeError> {
+        analyse_roots(name, roots, options)
+    }
+    /// Analyses the repository at `repo_path` as configured by the [`AnalysisOptions`],
+    /// reporting progress to the [`AnalysisObserver`], e.g., to show a progress bar
+    #[cfg(feature = "git")]
+    pub fn new_with_observer(
+        name: String,
+        repo_path: &str,
+        options: &AnalysisOptions,
+        observer: &dyn AnalysisObserver,
+    ) -> Result<Self, SourceCodeError> {
+        Self::new_with_cancellation(
+            name,
+            repo_path,
+            options,
+            observer,
+            &CancellationToken::new(),
+        )
+    }
+    /// Analyses the repository at `repo_path` as [`RepositoryInfo::new_with_observer`] does,
+    /// stopping early if the [`CancellationToken`] is cancelled
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::Cancelled`]) with the source files analysed so far, if cancelled
+    #[cfg(feature = "git")]
+    pub fn new_with_cancellation(
+        name: String,
+        repo_path: &str,
+        options: &AnalysisOptions,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<Self, SourceCodeError> {
+        Self::new_with_baseline(name, repo_path, options, observer, cancellation, None, &[])
+    }
+    /// Analyses the repository at `repo_path` as [`RepositoryInfo::new_with_cancellation`]
+    /// does, resuming from the checkpoint of an interrupted analysis of the same HEAD, with the
+    /// same options, at the path of the [`CheckpointOptions`] of the [`AnalysisOptions`]: the
+    /// commits it diffed, and the source files it read from the blobs of HEAD, aren't analysed
+    /// again. The analysis goes on being checkpointed; without a checkpoint to resume, e.g.,
+    /// if HEAD moved since, it starts from the beginning.
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if the [`AnalysisOptions`] have no
+    ///   [`CheckpointOptions`]
+    #[cfg(feature = "git")]
+    pub fn resume_from_checkpoint(
+        name: String,
+        repo_path: &str,
+        options: &AnalysisOptions,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<Self, SourceCodeError> {
+        let Some(checkpoint) = &options.checkpoint else {
+            return Err(SourceCodeError::ConfigError(
+                "No checkpoint to resume from is configured".to_string(),
+            ));
+        };
+        let options = AnalysisOptions {
+            checkpoint: Some(CheckpointOptions {
+                resume: true,
+                ..checkpoint.clone()
+            }),
+            ..options.clone()
+        };
+        Self::new_with_cancellation(name, repo_path, &options, observer, cancellation)
+    }
+    /// Analyses the repository at `repo_path` as [`RepositoryInfo::new_with_cancellation`]
+    /// does, reusing the change frequencies of the files of the `baseline`, if it can be
+    /// reused, and walking only the commits made since it, and adding the custom metrics of the
+    /// `metric_providers` to each source file
+    #[cfg(feature = "git")]
+    pub(crate) fn new_with_baseline(
+        name: String,
+        repo_path: &str,
+        options: &AnalysisOptions,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+        baseline: Option<&RepositoryInfo>,
+        metric_providers: &[&dyn MetricProvider],
+    ) -> Result<Self, SourceCodeError> {
+        options.limits.run(|| {
+            Self::analyse(
+                name,
+                repo_path,
+                options,
+                observer,
+                cancellation,
+                baseline,
+                metric_providers,
+            )
+        })
+    }
+    #[cfg(feature = "git")]
+    fn analyse(
+        name: String,
+        repo_path: &str,
+        options: &AnalysisOptions,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+        baseline: Option<&RepositoryInfo>,
+        metric_providers: &[&dyn MetricProvider],
+    ) -> Result<Self, SourceCodeError> {
+        let started_at = Utc::now();
+        let _span = tracing::info_span!("analysis", repository = %name, repo_path).entered();
+        let mut stages = StageTimer::new(observer);
+        stages.start(AnalysisStage::SourceFiles);
+        Self::check_supported(repo_path)?;
+        // A failed fetch leaves the history as it is, which is then reported as incomplete
+        if let Err(err) = shallow::deepen(repo_path, options.shallow) {
+            tracing::warn!("Failed to deepen the history of {}: {}", repo_path, err);
+        }
+        let history_depth = shallow::get_history_depth(repo_path)?;
+        options.history_sampling.validate()?;
+        // The source files are read from the blobs of HEAD, unless there is no HEAD commit yet
+        let snapshot = !options.include_uncommitted
+            && HeadState::resolve(&Repository::open(repo_path)?)?
+                .get_commit()
+                .is_some();
+        let dirty_worktree = worktree::is_dirty(repo_path);
+        let scope = options
+            .scope
+            .as_deref()
+            .map(|scope| Self::check_scope(repo_path, scope, snapshot))
+            .transpose()?;
+        let mut submodules: Vec<SubmoduleInfo> = SubmoduleInfo::detect(repo_path)?;
+        // Submodules are separate repositories, so their files aren't counted in this one
+        let excluded: Vec<&str> = options
+            .excluded
+            .iter()
+            .map(String::as_str)
+            .chain(submodules.iter().map(|submodule| submodule.path.as_str()))
+            .collect();
+        let counters = RunCounters::default();
+        // The history is read once, for the change frequencies and every statistic after them
+        let graph = CommitGraph::new(
+            repo_path,
+            options.merge_strategy,
+            options.history_mode,
+            options.history_sampling,
+            &options.identity,
+            observer,
+            cancellation,
+        )?;
+        // The commits diffed and the source files scanned are checkpointed, if configured, so
+        // that an interrupted analysis can be resumed
+        let head = graph.head().map(|head| head.to_string());
+        let checkpointer = options.checkpoint.as_ref().map(|checkpoint| {
+            Arc::new(Checkpointer::new(
+                checkpoint,
+                Checkpointer::get_key(repo_path, head.as_deref(), options),
+            ))
+        });
+        let graph = match &checkpointer {
+            Some(checkpointer) => graph.with_checkpointer(checkpointer.clone()),
+            None => graph,
+        };
+        let baseline = match baseline {
+            // The baseline's change frequencies are brought up to date as shares of all commits
+            Some(_) if options.frequency_denominator != FrequencyDenominator::TotalCommits => {
+                tracing::info!(
+                    "Not reusing the baseline, as the frequency denominator isn't the total commits"
+                );
+                None
+            }
+            Some(baseline) => BaselineHistory::new(
+                repo_path,
+                baseline,
+                options.merge_strategy,
+                options.history_mode,
+                options.history_sampling,
+            )?,
+            None => None,
+        };
+        let language_registry = LanguageRegistry::new(&options.counting);
+        let context = SourceFileContext {
+            counting: &options.counting,
+            languages: &language_registry,
+            graph: &graph,
+            frequency_basis: FrequencyBasis::new(&graph, options.frequency_denominator)?,
+            hash_algorithm: options.hash_algorithm,
+            baseline: baseline.as_ref(),
+            verify_line_counts: options.verify_line_counts,
+            max_file_size: options.limits.max_file_size,
+            scope: scope.as_deref(),
+            included: &options.included,
+            counters: &counters,
+            checkpointer: checkpointer.as_deref(),
+        };
+        let mut warnings: Vec<AnalysisWarning> = Vec::new();
+        let (mut source_files, unknown_files, assets) = match snapshot {
+            true => Self::get_source_file_infos_from_head(
+                repo_path,
+                &excluded,
+                context,
+                &mut warnings,
+                observer,
+                cancellation,
+            )?,
+            false => {
+                let mut source_files: Vec<SourceFileInfo> = Self::get_source_file_info_for_repo(
+                    repo_path,
+                    &excluded,
+                    context,
+                    &options.limits,
+                    &mut warnings,
+                    observer,
+                    cancellation,
+                )?;
+                let (unknown_files, assets) = Self::add_fallback_source_file_infos(
+                    repo_path,
+                    &excluded,
+                    context,
+                    &mut source_files,
+                    &mut warnings,
+                    observer,
+                    cancellation,
+                )?;
+                Self::add_symlink_source_file_infos(
+                    repo_path,
+                    Self::find_symlinks(repo_path, &excluded, context),
+                    context,
+                    &mut source_files,
+                    &mut warnings,
+                    |resolved| {
+                        fs::read(paths::long_path(&Path::new(repo_path).join(resolved))).ok()
+                    },
+                )?;
+                (source_files, unknown_files, assets)
+            }
+        };
+        let assets = AssetSummary::new(assets);
+        if !options.unsorted {
+            source_files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        }
+        for source_file in &mut source_files {
+            source_file.set_ages(started_at);
+            source_file.set_num_authors(&graph)?;
+        }
+        Self::set_git_blob_sizes(repo_path, &mut source_files)?;
+        testcode::classify(&mut source_files);
+        if options.index_symbols {
+            symbols::index(&mut source_files);
+        }
+        role::classify(&mut source_files);
+        if options.exclude_ci {
+            source_files.retain(|source_file| source_file.role != FileRole::Ci);
+        }
+        quadrant::classify(&mut source_files, &options.quadrant_thresholds);
+        generated::classify(&mut source_files);
+        let generated_code = GeneratedCodeReport::new(&source_files, options.exclude_generated);
+        if options.exclude_generated {
+            source_files.retain(|source_file| !source_file.is_generated);
+        }
+        let encodings = EncodingSummary::new(&source_files);
+        let whitespace = WhitespaceReport::new(&source_files);
+        stages.complete();
+
+        stages.start(AnalysisStage::TechDebt);
+        let tech_debt_markers = cancel::with_source_files(
+            TechDebtMarker::scan(
+                repo_path,
+                &mut source_files,
+                &options.identity,
+                cancellation,
+            ),
+            &mut source_files,
+        )?;
+        stages.complete();
 
-        // FIXME: The predominant language is not being set.
-        let predominant_language = Some(Self::get_predominant_language(&source_files));
+        stages.start(AnalysisStage::Structure);
+        let languages: Vec<LanguageType> = Self::get_languages(&source_files);
+        let predominant_language = Some(LanguageType::get_predominant_language(&languages));
+        let archetype = RepositoryArchetype::classify(repo_path, &source_files);
+        let frameworks = Framework::detect(repo_path, &source_files);
+        let targets = ExecutableTarget::inventory(repo_path, &source_files);
+        let dependencies = DependencySummary::detect(repo_path, &source_files);
+        let layout = match &scope {
+            Some(scope) => PathLayout::new(&Path::new(repo_path).join(scope).to_string_lossy()),
+            None => PathLayout::new(repo_path),
+        };
+        let test_code = TestCodeReport::new(&source_files);
+        let quadrants = QuadrantReport::new(&source_files, &options.quadrant_thresholds);
+        let roles = RoleReport::new(&source_files);
+        let duplication = options
+            .duplication
+            .as_ref()
+            .map(|duplication_options| DuplicationReport::new(&source_files, duplication_options));
+        let identical_files =
+            IdenticalFilesReport::new(&source_files, options.count_identical_once);
+        stages.complete();
 
+        stages.start(AnalysisStage::History);
         let mut statistics = Statistics::new();
         statistics.loc = Self::get_total_lines_of_code(&source_files);
-        statistics.num_files = source_files.len() as i32;
-        statistics.num_commits = Self::get_total_commits(repo_path).unwrap_or_default();
+        statistics.num_files = to_count(source_files.len());
+        statistics.num_markers = to_count(tech_debt_markers.len());
+        // The history and contributors of a scoped analysis only cover the commits changing it,
+        // while the change frequencies of its files are counted over the whole history
+        let scoped_graph = cancel::with_source_files(
+            scope
+                .as_deref()
+                .map(|scope| graph.scoped(scope))
+                .transpose(),
+            &mut source_files,
+        )?;
+        let history_graph = scoped_graph.as_ref().unwrap_or(&graph);
+        let history = CommitHistory::from_graph(history_graph);
+        statistics.num_commits = history
+            .as_ref()
+            .map_or(0, |history| to_count(history.num_commits));
+        statistics.bugfix_commits = history
+            .as_ref()
+            .map_or(0, |history| to_count(history.commit_messages.num_bugfix));
         statistics.size = Self::get_total_size(&source_files);
+        if identical_files.counted_once {
+            statistics.loc = statistics.loc.saturating_sub(identical_files.duplicate_loc);
+            statistics.size = statistics.size.saturating_sub(identical_files.wasted_bytes);
+        }
+        let commit_counts = match options.compare_history_modes {
+            true => Some(CommitCounts::new(repo_path)?),
+            false => None,
+        };
+        let releases = ReleaseInfo::get_releases(repo_path)?;
+        stages.complete();
 
-        let contributors: Vec<Contributor> = Contributor::get_git_contributors(repo_path);
+        stages.start(AnalysisStage::Contributors);
+        let mut contributors: Vec<Contributor> = Contributor::from_graph(
+            history_graph,
+            started_at,
+            &options.cadence,
+            options.identity.bots.handling,
+        );
+        let active_since = started_at - chrono::Duration::days(retention::ACTIVE_WINDOW_DAYS);
+        if !options.unsorted {
+            contributors.sort_by(|a, b| {
+                (&a.author.name, &a.author.canonical_id)
+                    .cmp(&(&b.author.name, &b.author.canonical_id))
+            });
+        }
+        for contributor in &mut contributors {
+            contributor.is_active = contributor.last_contribution >= active_since;
+        }
+        let contributor_retention = ContributorRetention::from_graph(history_graph, started_at);
+        let organisations = cancel::with_source_files(
+            OrganisationReport::from_graph(history_graph, &source_files, &options.organisations),
+            &mut source_files,
+        )?;
+        let packages = cancel::with_source_files(
+            PackageInfo::from_graph(
+                WorkspacePackage::detect(repo_path),
+                &source_files,
+                &graph,
+                started_at,
+                &options.cadence,
+                options.identity.bots.handling,
+            ),
+            &mut source_files,
+        )?;
+        stages.complete();
+        let coupling = match &options.coupling {
+            Some(coupling_options) => {
+                stages.start(AnalysisStage::Coupling);
+                let coupling = cancel::with_source_files(
+                    CouplingReport::from_graph(history_graph, &source_files, coupling_options),
+                    &mut source_files,
+                )?;
+                stages.complete();
+                Some(coupling)
+            }
+            None => None,
+        };
+        let co_ownership = match &options.co_ownership {
+            Some(co_ownership_options) => {
+                stages.start(AnalysisStage::CoOwnership);
+                let co_ownership = cancel::with_source_files(
+                    CoOwnershipMatrix::from_graph(
+                        history_graph,
+                        co_ownership_options,
+                        options.identity.bots.handling,
+                    ),
+                    &mut source_files,
+                )?;
+                stages.complete();
+                Some(co_ownership)
+            }
+            None => None,
+        };
+        let function_churn = match &options.function_churn {
+            Some(function_churn_options) => {
+                stages.start(AnalysisStage::FunctionChurn);
+                let function_churn = cancel::with_source_files(
+                    FunctionChurnReport::from_graph(
+                        repo_path,
+                        history_graph,
+                        &source_files,
+                        function_churn_options,
+                        cancellation,
+                    ),
+                    &mut source_files,
+                )?;
+                stages.complete();
+                Some(function_churn)
+            }
+            None => None,
+        };
+        stages.start(AnalysisStage::Ownership);
+        let ownership = cancel::with_source_files(
+            OwnershipReport::from_graph(repo_path, history_graph, &source_files),
+            &mut source_files,
+        )?;
+        match ownership.is_some() {
+            true => stages.complete(),
+            false => stages.skip(),
+        }
+        let branches = match &options.branches {
+            Some(branch_options) => {
+                stages.start(AnalysisStage::Branches);
+                let branches = BranchReport::new(repo_path, branch_options, started_at)?;
+                stages.complete();
+                Some(branches)
+            }
+            None => None,
+        };
+        let storage = match &options.storage {
+            Some(storage_options) => {
+                stages.start(AnalysisStage::Storage);
+                let storage = cancel::with_source_files(
+                    StorageReport::from_graph(repo_path, &graph, storage_options, cancellation),
+                    &mut source_files,
+                )?;
+                stages.complete();
+                Some(storage)
+            }
+            None => None,
+        };
+        if options.recurse_submodules {
+            for submodule in &mut submodules {
+                submodule.analyse(repo_path, options)?;
+            }
+        }
+        let working_tree = match options.include_working_tree {
+            true => {
+                stages.start(AnalysisStage::WorkingTree);
+                let working_tree = WorkingTreeStatistics::compute(repo_path)?;
+                stages.complete();
+                Some(working_tree)
+            }
+            false => None,
+        };
 
-        Ok(Self {
+        stages.start(AnalysisStage::Metrics);
+        let statistics_summary = StatisticsSummary::new(&source_files);
+        let mut repository_info = Self {
+            schema_version: migrate::SCHEMA_VERSION,
             name,
+            scope,
             predominant_language,
+            languages,
+            archetype,
+            frameworks,
+            targets,
+            dependencies,
+            packages,
+            layout,
+            duplication,
+            identical_files,
+            similar_files: None,
+            test_code,
+            generated_code,
+            quadrants,
+            roles,
+            roots: vec![],
             statistics,
+            statistics_summary,
+            frequency_denominator: options.frequency_denominator,
             contributors,
+            contributor_retention,
+            organisations,
+            history,
+            history_complete: history_depth.is_none(),
+            commit_counts,
+            history_depth,
+            releases,
+            tech_debt_markers,
+            coupling,
+            function_churn,
+            branches,
+            storage,
+            co_ownership,
+            ownership,
+            forge: None,
+            submodules,
             source_files,
-        })
+            unknown_files,
+            assets,
+            encodings,
+            whitespace,
+            working_tree,
+            dirty_worktree,
+            metrics_catalog: vec![],
+            quality: vec![],
+            warnings,
+            metadata: None,
+            fingerprint: None,
+        };
+        compute_derived_metrics(&mut repository_info, &options.derived_metrics)?;
+        repository_info.metrics_catalog =
+            MetricDefinition::catalog(&repository_info, &options.derived_metrics);
+        compute_custom_metrics(&mut repository_info, repo_path, metric_providers);
+        repository_info.quality = MetricQuality::assess(&repository_info);
+        stages.complete();
+        // The commit graph, and the baseline if used, each walk all the commits from HEAD
+        let num_walks = 1 + u64::from(baseline.is_some());
+        counters.add_commits_walked(to_count(graph.commits().len()).saturating_mul(num_walks));
+        let mut metadata = AnalysisMetadata::new(repo_path, options, started_at);
+        if options.run_stats {
+            metadata.run_stats = Some(counters.get_run_stats());
+        }
+        metadata.sampled_history = graph.sampled().cloned();
+        metadata.timings = stages.finish();
+        metadata.num_warnings = to_count(repository_info.warnings.len());
+        repository_info.metadata = Some(metadata);
+        repository_info.fingerprint = Some(repository_info.fingerprint());
+        for source_file in &mut repository_info.source_files {
+            source_file.retain_contents(options.content_mode);
+        }
+        if let Some(checkpointer) = &checkpointer {
+            checkpointer.finish();
+        }
+        if let Some(anonymisation) = &options.anonymisation {
+            repository_info =
+                PathHasher::from_options(anonymisation).anonymise(repo_path, &repository_info);
+        }
+        Ok(repository_info)
+    }
+    /// Gets a deterministic identifier of the snapshot: the SHA-256 hash, in hex, of the HEAD
+    /// commit and the [`AnalysisOptions`] in the `metadata`, and the path and blob id (or else
+    /// the `id_hash`) of each source file, so that two analyses of the same commit and files
+    /// with the same options have the same fingerprint. Once the hashes are dropped, e.g., by
+    /// [`crate::source::ContentMode::None`], it differs from the stored `fingerprint`.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        let head = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.head.as_ref());
+        let commit = head.and_then(|head| head.get_commit()).unwrap_or_default();
+        hasher.update(commit.as_bytes());
+        hasher.update([0]);
+        if let Some(metadata) = &self.metadata {
+            hasher.update(serde_json::to_vec(&metadata.options).unwrap_or_default());
+        }
+        hasher.update([0]);
+        let mut files: Vec<(&str, &str)> = self
+            .source_files
+            .iter()
+            .map(|source_file| {
+                let hash = source_file
+                    .blob_oid
+                    .as_deref()
+                    .or(source_file.id_hash.as_deref())
+                    .unwrap_or_default();
+                (source_file.relative_path.as_str(), hash)
+            })
+            .collect();
+        files.sort_unstable();
+        for (path, hash) in files {
+            hasher.update(path.as_bytes());
+            hasher.update([0]);
+            hasher.update(hash.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+    /// Gets a digest of the statistics, i.e., the languages, largest files, hotspots and
+    /// contributors, as compact JSON of at most `max_bytes`, for the context of an LLM prompt;
+    /// see [`CompactContext::to_json`] for how it is truncated to fit
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if `max_bytes` is too small for any digest
+    pub fn to_compact_context(&self, max_bytes: usize) -> Result<String, SourceCodeError> {
+        CompactContext::new(self).to_json(max_bytes)
     }
     /// Gets the [`RepositoryInfo`] as a JSON string
     pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
         serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
     }
-    /// Builds up the [`SourceFileInfo`]s for the repository
-    fn get_source_file_info_for_repo(
+    /// Gets the [`RepositoryInfo`] as MessagePack, with the fields by name, e.g., to exchange
+    /// it between services, where it is smaller and quicker to parse than JSON
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, SourceCodeError> {
+        rmp_serde::to_vec_named(&self)
+            .map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Deserialises a [`RepositoryInfo`] from MessagePack, as from
+    /// [`RepositoryInfo::to_msgpack`]; unlike [`RepositoryInfo::deserialize_any_version`], it
+    /// isn't migrated, so it must be from the same version of this crate
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::SerializationError`]) if the bytes aren't a [`RepositoryInfo`]
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, SourceCodeError> {
+        rmp_serde::from_slice(bytes).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Deserialises a [`RepositoryInfo`] from the JSON of a snapshot, as from
+    /// [`RepositoryInfo::get_as_json`], migrating snapshots from older versions of this crate
+    /// to the current layout, so that persisted historical snapshots keep loading
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::SerializationError`]) if the JSON isn't a snapshot, or is from a
+    ///   later version of this crate
+    pub fn deserialize_any_version(json: &str) -> Result<Self, SourceCodeError> {
+        migrate::deserialize_any_version(json)
+    }
+    /// Gets the JSON Schema of the [`RepositoryInfo`] JSON output, e.g., to validate it or to
+    /// define the tables it is loaded into; it is derived from the structs, so stays in sync
+    #[cfg(feature = "schema")]
+    pub fn json_schema() -> schemars::schema::RootSchema {
+        schemars::schema_for!(RepositoryInfo)
+    }
+    /// Compares this snapshot (as the base) with a later `other` snapshot (as the head)
+    ///
+    /// #Returns:
+    /// * A [`RepositoryDelta`] of the added, removed and changed files, language and contributor changes
+    pub fn diff(&self, other: &RepositoryInfo) -> RepositoryDelta {
+        RepositoryDelta::new(self, other)
+    }
+    /// Diffs the `head` ref of the repository at `repo_path` against the `base` ref, e.g., of a
+    /// pull request, straight from git rather than from analysed snapshots
+    ///
+    /// #Returns:
+    /// * A [`RefDiff`] of the lines added and removed per file and per language, and the
+    ///   contributors of the commits in `head` but not `base`
+    #[cfg(feature = "git")]
+    pub fn diff_refs(repo_path: &str, base: &str, head: &str) -> Result<RefDiff, SourceCodeError> {
+        RefDiff::new(repo_path, base, head)
+    }
+    /// Searches the contents of the source files, as kept or else read on demand (see
+    /// [`crate::source::ContentMode`]), for the `pattern`
+    ///
+    /// #Returns:
+    /// * The [`ContentMatch`]es, with their path, line and column
+    pub fn search_contents(
+        &self,
+        pattern: &ContentPattern,
+    ) -> Result<Vec<ContentMatch>, SourceCodeError> {
+        ContentMatch::find_all(&self.source_files, pattern)
+    }
+    /// Starts a [`FileQuery`] over the source files, e.g.,
+    /// `repo.files().language("Rust").min_loc(500).collect()`
+    pub fn files(&self) -> FileQuery<'_> {
+        FileQuery::new(&self.source_files)
+    }
+    /// Gets the (up to) `n` source files with the most lines of code, largest first
+    pub fn top_files_by_loc(&self, n: usize) -> Vec<&SourceFileInfo> {
+        self.top_files_by(n, |source_file| source_file.statistics.loc)
+    }
+    /// Gets the (up to) `n` largest source files in bytes, largest first
+    pub fn top_files_by_size(&self, n: usize) -> Vec<&SourceFileInfo> {
+        self.top_files_by(n, |source_file| source_file.statistics.size)
+    }
+    /// Gets the (up to) `n` source files with the most churn, i.e., the most commits changing
+    /// them, as in [`crate::workspace::RepositoryChurn`], highest first
+    pub fn top_files_by_churn(&self, n: usize) -> Vec<&SourceFileInfo> {
+        self.top_files_by(n, |source_file| source_file.statistics.num_commits)
+    }
+    /// Gets the (up to) `n` source files with the most untested churn, i.e., the commits
+    /// changing them weighted by the share of their lines not covered, highest first; only
+    /// files with a line coverage, as from [`crate::coverage::apply`], are ranked
+    pub fn top_files_by_uncovered_churn(&self, n: usize) -> Vec<&SourceFileInfo> {
+        let mut source_files = self.top_files_by(self.source_files.len(), |source_file| {
+            source_file
+                .statistics
+                .line_coverage
+                .map(|coverage| {
+                    let uncovered = (100.0 - coverage.clamp(0.0, 100.0)) as f64;
+                    (source_file.statistics.num_commits as f64 * uncovered).round() as i64
+                })
+                .unwrap_or(-1)
+        });
+        source_files.retain(|source_file| source_file.statistics.line_coverage.is_some());
+        source_files.truncate(n);
+        source_files
+    }
+    /// Scores the health of the repository, and of each source file, from 0 to 100 with the
+    /// `model`, e.g., [`crate::scoring::HealthWeights::default`]
+    pub fn health(&self, model: &dyn ScoringModel) -> HealthReport {
+        HealthReport::new(self, model)
+    }
+    /// Suggests who to ask to review a change to the `paths` (files or directories) of the
+    /// repository at `repo_path`, e.g., for a review-assignment bot: its contributors scored by
+    /// their commits changing the source files under the paths, weighted by their age, and their
+    /// share of the files' lines by `git blame`, with the [`ReviewerOptions`]; the history is
+    /// walked with the options the repository was analysed with
+    ///
+    /// #Returns:
+    /// * The [`ReviewerSuggestion`]s, the most relevant first
+    #[cfg(feature = "git")]
+    pub fn suggest_reviewers(
+        &self,
+        repo_path: &str,
         paths: &[&str],
+        options: &ReviewerOptions,
+    ) -> Result<Vec<ReviewerSuggestion>, SourceCodeError> {
+        ReviewerSuggestion::suggest(repo_path, self, paths, options)
+    }
+    /// Gets the (up to) `n` source files with the highest `key`, highest first, then by path
+    fn top_files_by<K: Ord>(
+        &self,
+        n: usize,
+        key: impl Fn(&SourceFileInfo) -> K,
+    ) -> Vec<&SourceFileInfo> {
+        let mut source_files: Vec<&SourceFileInfo> = self.source_files.iter().collect();
+        source_files.sort_by(|a, b| {
+            key(b)
+                .cmp(&key(a))
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
+        });
+        source_files.truncate(n);
+        source_files
+    }
+    /// Checks that the repository at `repo_path` is a git repository with a working tree
+    #[cfg(feature = "git")]
+    fn check_supported(repo_path: &str) -> Result<(), SourceCodeError> {
+        let unsupported = |reason: String| SourceCodeError::UnsupportedRepo {
+            path: PathBuf::from(repo_path),
+            reason,
+        };
+        let repo =
+            Repository::open(repo_path).map_err(|err| unsupported(err.message().to_string()))?;
+        if repo.is_bare() {
+            return Err(unsupported(
+                "bare repositories have no working tree to analyse".to_string(),
+            ));
+        }
+        Ok(())
+    }
+    /// Checks that the `scope` of an analysis is a subdirectory of the repository at
+    /// `repo_path`, which has to exist in the working tree unless the files are read from the
+    /// `snapshot` at HEAD
+    ///
+    /// #Returns:
+    /// * The scope in the stored form, i.e., with forward slashes and without a trailing one
+    /// * Err([`SourceCodeError::ConfigError`]) if it is absolute, leaves the repository or isn't a
+    ///   directory
+    #[cfg(feature = "git")]
+    fn check_scope(
+        repo_path: &str,
+        scope: &str,
+        snapshot: bool,
+    ) -> Result<String, SourceCodeError> {
+        let invalid = |reason: &str| {
+            SourceCodeError::ConfigError(format!("invalid scope '{}': {}", scope, reason))
+        };
+        let path = Path::new(scope);
+        if path.is_absolute() || path.has_root() {
+            return Err(invalid("it must be relative to the root of the repository"));
+        }
+        if path
+            .components()
+            .any(|component| matches!(component, Component::ParentDir | Component::Prefix(_)))
+        {
+            return Err(invalid("it must be inside the repository"));
+        }
+        let normalised: PathBuf = path
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .collect();
+        if normalised.as_os_str().is_empty() {
+            return Err(invalid("it must be a subdirectory"));
+        }
+        if !snapshot && !Path::new(repo_path).join(&normalised).is_dir() {
+            return Err(invalid("it isn't a directory of the working tree"));
+        }
+        Ok(paths::to_slash(&normalised))
+    }
+    /// Builds up the [`SourceFileInfo`]s for the repository at `repo_path`; several roots are
+    /// analysed with [`RepositoryInfo::new_multi_root`], each in its own repository
+    #[cfg(feature = "git")]
+    fn get_source_file_info_for_repo(
+        repo_path: &str,
         excluded: &[&str],
+        context: SourceFileContext<'_>,
+        limits: &ResourceLimits,
+        warnings: &mut Vec<AnalysisWarning>,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
     ) -> Result<Vec<SourceFileInfo>, SourceCodeError> {
-        let languages = Self::get_tokei_stats_for_repo(paths, excluded);
+        // Only the scope is scanned, but the paths stay relative to the root of the repository
+        let scope_path = context.scope.map(|scope| {
+            Path::new(repo_path)
+                .join(scope)
+                .to_string_lossy()
+                .to_string()
+        });
+        let scanned: Vec<&str> = match &scope_path {
+            Some(scope_path) => vec![scope_path.as_str()],
+            None => vec![repo_path],
+        };
+        let languages =
+            Self::get_tokei_stats_for_repo(&scanned, excluded, context.counting, limits);
+        let num_files: usize = languages
+            .values()
+            .map(|language| language.reports.len())
+            .sum();
 
-        let mut source_file_infos: Vec<SourceFileInfo> = Vec::new();
+        let mut source_file_infos: Vec<SourceFileInfo> = Vec::with_capacity(num_files);
 
         for (language_name, language) in languages.iter() {
-            let lang_type: LanguageType = LanguageType::new_from(language_name.to_owned());
+            if language.inaccurate {
+                tracing::warn!(
+                    "tokei failed to parse some {} files in {}",
+                    language_name,
+                    repo_path
+                );
+            }
+            let lang_type: LanguageType = context
+                .languages
+                .get_language_type(language_name.to_owned());
             for file_report in &language.reports {
-                let source_file_info = SourceFileInfo::get_source_file_info(
-                    paths.first().unwrap(),
-                    file_report,
-                    &lang_type,
-                )?;
+                cancellation.check(AnalysisStage::SourceFiles, || {
+                    PartialResults::SourceFiles(std::mem::take(&mut source_file_infos))
+                })?;
+                // `tokei` matches excluded names, not nested paths such as submodules, and
+                // counts all the hidden files if any are counted
+                let relative = paths::relative_to(&file_report.name, Path::new(repo_path))
+                    .unwrap_or_else(|| file_report.name.clone());
+                if !context.in_scope(&relative)
+                    || Self::is_hidden_or_excluded(
+                        &relative,
+                        excluded,
+                        context.counting.counts_hidden(&relative),
+                    )
+                {
+                    context.counters.add_skipped();
+                    continue;
+                }
+                let Some(source_file_info) = Self::collect_file_error(
+                    SourceFileInfo::get_source_file_info(
+                        repo_path,
+                        file_report,
+                        &lang_type,
+                        context,
+                        warnings,
+                    ),
+                    &relative,
+                    context,
+                    warnings,
+                )?
+                else {
+                    continue;
+                };
 
                 source_file_infos.push(source_file_info);
+                observer.on_file_scanned(&file_report.name, source_file_infos.len(), num_files);
             }
         }
 
         Ok(source_file_infos)
     }
-    /// Gets `tokei` statistics for the repository
-    fn get_tokei_stats_for_repo(paths: &[&str], excluded: &[&str]) -> Languages {
-        let config = Config::default();
+    /// Detects the language of the files `tokei` didn't recognise, as a custom language of the
+    /// [`CountingOptions`], or by file name or shebang, and adds a [`SourceFileInfo`] for each
+    /// detected file to `source_file_infos`. Hidden and git ignored files are skipped, unless
+    /// counted, and excluded files are skipped, and binary files are only read far enough to
+    /// detect them. A file that can't be read or decoded gets a [`WarningKind::Unreadable`]
+    /// warning.
+    ///
+    /// #Returns:
+    /// * The relative paths of the remaining (text) files whose language couldn't be detected,
+    ///   and the binary [`AssetFile`]s
+    #[cfg(feature = "git")]
+    fn add_fallback_source_file_infos(
+        repo_path: &str,
+        excluded: &[&str],
+        context: SourceFileContext<'_>,
+        source_file_infos: &mut Vec<SourceFileInfo>,
+        warnings: &mut Vec<AnalysisWarning>,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<(Vec<String>, Vec<AssetFile>), SourceCodeError> {
+        let repo = Repository::open(repo_path)
+            .ok()
+            .filter(|_| !context.counting.counts_git_ignored());
+        let config = context.counting.get_config();
+        let known: HashSet<String> = source_file_infos
+            .iter()
+            .map(|sfi| sfi.relative_path.to_string())
+            .collect();
+
+        let mut unknown_files: Vec<String> = Vec::new();
+        let mut assets: Vec<AssetFile> = Vec::new();
+        for (relative_path, path) in walk::find_files(repo_path, |_| true) {
+            let relative = Path::new(&relative_path);
+            if !context.in_scope(relative) {
+                continue;
+            }
+            let is_ignored = repo
+                .as_ref()
+                .map(|repo| repo.is_path_ignored(relative).unwrap_or(false))
+                .unwrap_or(false);
+            if known.contains(&relative_path) {
+                continue;
+            }
+            if Self::is_hidden_or_excluded(
+                relative,
+                excluded,
+                context.counting.counts_hidden(relative),
+            ) || is_ignored
+            {
+                context.counters.add_skipped();
+                continue;
+            }
+            let bytes = match Self::read_unless_binary(&path) {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    tracing::warn!("Skipping file {}: {}", relative_path, err);
+                    context.counters.add_skipped();
+                    warnings.push(
+                        AnalysisWarning::new(relative_path, WarningKind::Unreadable)
+                            .with_message(err.to_string()),
+                    );
+                    continue;
+                }
+            };
+            let bytes = match bytes {
+                Ok(bytes) => bytes,
+                Err(size) => {
+                    context.counters.add_skipped();
+                    assets.push(AssetFile::new(relative_path, size));
+                    continue;
+                }
+            };
+            let size = to_count(bytes.len());
+            let encoding = source::ContentEncoding::detect(&bytes).unwrap_or_default();
+            let contents = match String::from_utf8(encoding.decode(bytes)) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    context.counters.add_skipped();
+                    warnings.push(
+                        AnalysisWarning::new(relative_path, WarningKind::Unreadable)
+                            .with_message(format!("The file couldn't be decoded: {}", err)),
+                    );
+                    continue;
+                }
+            };
+            // NUL characters past the bytes checked for binary files, so it is binary after all
+            if contents.contains('\0') {
+                context.counters.add_skipped();
+                assets.push(AssetFile::new(relative_path, size));
+                continue;
+            }
+            let language = context
+                .counting
+                .get_custom_language(&path)
+                .or_else(|| detect::detect_language(&path, &contents));
+            match language {
+                Some(language) => {
+                    cancellation.check(AnalysisStage::SourceFiles, || {
+                        PartialResults::SourceFiles(std::mem::take(source_file_infos))
+                    })?;
+                    let mut file_report = Report::new(path);
+                    file_report.stats = language.parse_from_str(&contents, &config);
+                    let Some(source_file_info) = Self::collect_file_error(
+                        SourceFileInfo::get_source_file_info(
+                            repo_path,
+                            &file_report,
+                            &context.languages.get_language_type(language),
+                            context,
+                            warnings,
+                        ),
+                        relative,
+                        context,
+                        warnings,
+                    )?
+                    else {
+                        continue;
+                    };
+                    source_file_infos.push(source_file_info);
+                    let num_scanned = source_file_infos.len();
+                    observer.on_file_scanned(&file_report.name, num_scanned, num_scanned);
+                }
+                None => {
+                    context.counters.add_skipped();
+                    unknown_files.push(relative_path);
+                }
+            }
+        }
+        unknown_files.sort();
+        Ok((unknown_files, assets))
+    }
+    /// Finds the symlinks in the working tree, skipping hidden, excluded and git ignored ones as
+    /// [`Self::add_fallback_source_file_infos`] does, and resolves their targets; `tokei` and
+    /// the fallback skip symlinks
+    #[cfg(feature = "git")]
+    fn find_symlinks(
+        repo_path: &str,
+        excluded: &[&str],
+        context: SourceFileContext<'_>,
+    ) -> Vec<Symlink> {
+        let root = fs::canonicalize(repo_path).ok();
+        let repo = Repository::open(repo_path)
+            .ok()
+            .filter(|_| !context.counting.counts_git_ignored());
+        walk::find_symlinks(repo_path)
+            .into_iter()
+            .filter_map(|(relative_path, path)| {
+                let relative = Path::new(&relative_path);
+                if !context.in_scope(relative) {
+                    return None;
+                }
+                let is_ignored = repo
+                    .as_ref()
+                    .map(|repo| repo.is_path_ignored(relative).unwrap_or(false))
+                    .unwrap_or(false);
+                if Self::is_hidden_or_excluded(
+                    relative,
+                    excluded,
+                    context.counting.counts_hidden(relative),
+                ) || is_ignored
+                {
+                    context.counters.add_skipped();
+                    return None;
+                }
+                let target = fs::read_link(&path).ok()?;
+                // The target must be a file in the repository, outside its '.git' directory
+                let resolved = fs::canonicalize(&path)
+                    .ok()
+                    .filter(|resolved| resolved.is_file())
+                    .and_then(|resolved| paths::relative_to(&resolved, root.as_ref()?))
+                    .map(|resolved| paths::to_slash(&resolved))
+                    .filter(|resolved| resolved.split('/').next() != Some(".git"));
+                Some(Symlink {
+                    relative_path,
+                    target: paths::to_slash(&target),
+                    resolved,
+                })
+            })
+            .collect()
+    }
+    /// Adds a [`SourceFileInfo`] for each of the `symlinks` whose language is detected, by its
+    /// path or else that of the file its target resolves to. If the [`CountingOptions`] follow
+    /// symlinks, a symlink is counted as its target, read with `read_target`, unless the target
+    /// is counted already, so that no file is counted through more than one path.
+    #[cfg(feature = "git")]
+    fn add_symlink_source_file_infos(
+        repo_path: &str,
+        symlinks: Vec<Symlink>,
+        context: SourceFileContext<'_>,
+        source_file_infos: &mut Vec<SourceFileInfo>,
+        warnings: &mut Vec<AnalysisWarning>,
+        read_target: impl Fn(&str) -> Option<Vec<u8>>,
+    ) -> Result<(), SourceCodeError> {
+        let config = context.counting.get_config();
+        let detect = |path: &Path| {
+            tokei::LanguageType::from_path(path, &config)
+                .or_else(|| context.counting.get_custom_language(path))
+        };
+        let mut counted: HashSet<String> = source_file_infos
+            .iter()
+            .map(|sfi| sfi.relative_path.to_string())
+            .collect();
+        for symlink in symlinks {
+            let path = Path::new(repo_path).join(&symlink.relative_path);
+            let language = detect(&path).or_else(|| {
+                let resolved = symlink.resolved.as_ref()?;
+                detect(&Path::new(repo_path).join(resolved))
+            });
+            let Some(language) = language else {
+                context.counters.add_skipped();
+                continue;
+            };
+            let target_bytes = match symlink.resolved {
+                Some(resolved)
+                    if context.counting.follow_symlinks && counted.insert(resolved.clone()) =>
+                {
+                    read_target(&resolved)
+                }
+                _ => None,
+            };
+            let mut file_report = Report::new(path);
+            if let Some(target_bytes) = &target_bytes {
+                let encoding = source::ContentEncoding::detect(target_bytes).unwrap_or_default();
+                file_report.stats =
+                    language.parse_from_slice(encoding.decode(target_bytes.clone()), &config);
+            }
+            let source_file_info = Self::collect_file_error(
+                SourceFileInfo::get_symlink_source_file_info(
+                    repo_path,
+                    &file_report,
+                    &context.languages.get_language_type(language),
+                    context,
+                    symlink.target,
+                    target_bytes,
+                    warnings,
+                ),
+                Path::new(&symlink.relative_path),
+                context,
+                warnings,
+            )?;
+            source_file_infos.extend(source_file_info);
+        }
+        Ok(())
+    }
+    /// Gets the [`SourceFileInfo`] of the `result` of analysing the source file at the
+    /// `relative` path, or, if the file itself couldn't be analysed, e.g., it is unreadable or
+    /// was deleted during the analysis, adds a [`WarningKind::Unreadable`] warning for it to
+    /// `warnings` and gets `None`, so that the analysis carries on without it
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError`]) if the error isn't of the file, see
+    ///   [`SourceCodeError::is_file_error`], e.g., the analysis was cancelled
+    #[cfg(feature = "git")]
+    fn collect_file_error(
+        result: Result<SourceFileInfo, SourceCodeError>,
+        relative: &Path,
+        context: SourceFileContext<'_>,
+        warnings: &mut Vec<AnalysisWarning>,
+    ) -> Result<Option<SourceFileInfo>, SourceCodeError> {
+        match result {
+            Ok(source_file_info) => Ok(Some(source_file_info)),
+            Err(err) if err.is_file_error() => {
+                let relative_path = paths::to_slash(relative);
+                tracing::warn!("Skipping source file {}: {}", relative_path, err);
+                context.counters.add_skipped();
+                warnings.push(
+                    AnalysisWarning::new(relative_path, WarningKind::Unreadable)
+                        .with_message(err.to_string()),
+                );
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+    /// Reads the file at `path`, unless its first [`source::BINARY_CHECK_LENGTH`] bytes show it
+    /// is binary
+    ///
+    /// #Returns:
+    /// * Ok(the contents), or Err(the size of the file) if it is binary
+    #[cfg(feature = "git")]
+    fn read_unless_binary(path: &Path) -> std::io::Result<Result<Vec<u8>, u64>> {
+        let mut file = File::open(paths::long_path(path))?;
+        let mut bytes: Vec<u8> = Vec::new();
+        (&mut file)
+            .take(source::BINARY_CHECK_LENGTH as u64)
+            .read_to_end(&mut bytes)?;
+        if source::is_binary(&bytes) {
+            return Ok(Err(file.metadata()?.len()));
+        }
+        file.read_to_end(&mut bytes)?;
+        Ok(Ok(bytes))
+    }
+    /// Reads the first [`source::BINARY_CHECK_LENGTH`] bytes of the blob `blob_id` at the
+    /// `relative` path, streaming them from the object database `odb`, e.g., to detect the
+    /// language of a blob too large to read into memory
+    ///
+    /// #Returns:
+    /// * The bytes, or `None` if the blob can't be streamed, as only loose objects can be
+    #[cfg(feature = "git")]
+    fn read_blob_prefix(
+        odb: &Odb<'_>,
+        blob_id: Oid,
+        relative: &Path,
+    ) -> Result<Option<Vec<u8>>, SourceCodeError> {
+        let reader = match odb.reader(blob_id) {
+            Ok((reader, _, _)) => reader,
+            Err(err) if err.code() == ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let mut bytes: Vec<u8> = Vec::new();
+        reader
+            .take(source::BINARY_CHECK_LENGTH as u64)
+            .read_to_end(&mut bytes)
+            .map_err(SourceCodeError::for_path(relative))?;
+        Ok(Some(bytes))
+    }
+    /// Builds up the [`SourceFileInfo`]s from the blobs in the tree at HEAD rather than the
+    /// files in the working tree, so that concurrent changes to the working tree don't affect
+    /// them. Languages are detected as `tokei` does, then as a custom language of the
+    /// [`CountingOptions`], then by file name or shebang; hidden files, unless counted, and
+    /// excluded, vendored and binary files are skipped. Symlinks are resolved in the tree. The
+    /// size of each blob is read from its header first, so that blobs over the `max_file_size`
+    /// are streamed rather than read into memory.
+    ///
+    /// #Returns:
+    /// * The [`SourceFileInfo`]s, the relative paths of the (text) files whose language couldn't
+    ///   be detected and the binary [`AssetFile`]s; all empty if HEAD is unborn
+    #[cfg(feature = "git")]
+    fn get_source_file_infos_from_head(
+        repo_path: &str,
+        excluded: &[&str],
+        context: SourceFileContext<'_>,
+        warnings: &mut Vec<AnalysisWarning>,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<CollectedFiles, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let mut source_file_infos: Vec<SourceFileInfo> = Vec::new();
+        let mut unknown_files: Vec<String> = Vec::new();
+        let mut assets: Vec<AssetFile> = Vec::new();
+        if HeadState::resolve(&repo)?.get_commit().is_none() {
+            return Ok((source_file_infos, unknown_files, assets));
+        }
+        let tree = repo.head()?.peel_to_tree()?;
+        let mut blobs: Vec<(String, Oid)> = Vec::new();
+        let mut links: Vec<(String, Oid)> = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            let name = entry.name().unwrap_or_default();
+            let path = format!("{}{}", root, name);
+            match entry.kind() {
+                Some(ObjectType::Tree) if walk::VENDORED_DIRS.contains(&name) => {
+                    TreeWalkResult::Skip
+                }
+                Some(ObjectType::Blob) if !context.in_scope(Path::new(&path)) => TreeWalkResult::Ok,
+                Some(ObjectType::Blob) if entry.filemode() == i32::from(FileMode::Link) => {
+                    links.push((path, entry.id()));
+                    TreeWalkResult::Ok
+                }
+                Some(ObjectType::Blob) => {
+                    blobs.push((path, entry.id()));
+                    TreeWalkResult::Ok
+                }
+                _ => TreeWalkResult::Ok,
+            }
+        })?;
+
+        let blob_ids: HashMap<String, Oid> = blobs.iter().cloned().collect();
+        let config = context.counting.get_config();
+        let odb = repo.odb()?;
+        let num_files = blobs.len();
+        for (index, (relative_path, blob_id)) in blobs.into_iter().enumerate() {
+            cancellation.check(AnalysisStage::SourceFiles, || {
+                PartialResults::SourceFiles(std::mem::take(&mut source_file_infos))
+            })?;
+            let relative = Path::new(&relative_path);
+            if Self::is_hidden_or_excluded(
+                relative,
+                excluded,
+                context.counting.counts_hidden(relative),
+            ) {
+                context.counters.add_skipped();
+                continue;
+            }
+            let path = Path::new(repo_path).join(relative);
+            observer.on_file_scanned(&path, index + 1, num_files);
+            if let Some((mut source_file_info, file_warnings)) = context
+                .checkpointer
+                .and_then(|checkpointer| checkpointer.resume_source_file(&relative_path))
+            {
+                source_file_info.set_source_file_path(path);
+                warnings.extend(file_warnings);
+                source_file_infos.push(source_file_info);
+                continue;
+            }
+            // A blob over the maximum file size isn't read into memory: its language is
+            // detected from its first bytes, and its lines aren't counted
+            let (size, _) = odb.read_header(blob_id)?;
+            let size = size as u64;
+            let oversized = context.is_oversized(size);
+            let bytes = if oversized {
+                Self::read_blob_prefix(&odb, blob_id, relative)?
+            } else {
+                Some(repo.find_blob(blob_id)?.content().to_vec())
+            };
+            let language = tokei::LanguageType::from_path(&path, &config)
+                .or_else(|| context.counting.get_custom_language(&path));
+            let language = match (language, &bytes) {
+                (Some(language), _) => Some(language),
+                (None, Some(bytes)) if source::is_binary(bytes) => {
+                    context.counters.add_skipped();
+                    assets.push(AssetFile::new(relative_path, size));
+                    continue;
+                }
+                (None, Some(bytes)) => {
+                    let encoding = source::ContentEncoding::detect(bytes).unwrap_or_default();
+                    let contents = encoding.decode_slice(bytes);
+                    detect::detect_language(&path, &String::from_utf8_lossy(&contents))
+                }
+                (None, None) => None,
+            };
+            let Some(language) = language else {
+                context.counters.add_skipped();
+                unknown_files.push(relative_path);
+                continue;
+            };
+            let mut file_report = Report::new(path);
+            let language_type = context.languages.get_language_type(language);
+            let num_warnings = warnings.len();
+            let source_file_info = match bytes {
+                Some(bytes) if !oversized => {
+                    let encoding = source::ContentEncoding::detect(&bytes).unwrap_or_default();
+                    file_report.stats =
+                        language.parse_from_slice(encoding.decode_slice(&bytes), &config);
+                    SourceFileInfo::get_source_file_info_from_bytes(
+                        repo_path,
+                        &file_report,
+                        &language_type,
+                        context,
+                        bytes,
+                        warnings,
+                    )
+                }
+                _ => SourceFileInfo::get_oversized_blob_source_file_info(
+                    repo_path,
+                    &file_report,
+                    &language_type,
+                    context,
+                    &odb,
+                    blob_id,
+                    size,
+                    warnings,
+                ),
+            };
+            let Some(source_file_info) = Self::collect_file_error(
+                source_file_info,
+                Path::new(&relative_path),
+                context,
+                warnings,
+            )?
+            else {
+                continue;
+            };
+            if let Some(checkpointer) = context.checkpointer {
+                checkpointer.add_source_file(&source_file_info, &warnings[num_warnings..]);
+            }
+            source_file_infos.push(source_file_info);
+        }
+        let mut symlinks: Vec<Symlink> = Vec::new();
+        for (relative_path, blob_id) in links {
+            let relative = Path::new(&relative_path);
+            if Self::is_hidden_or_excluded(
+                relative,
+                excluded,
+                context.counting.counts_hidden(relative),
+            ) {
+                context.counters.add_skipped();
+                continue;
+            }
+            let target = String::from_utf8_lossy(repo.find_blob(blob_id)?.content()).into_owned();
+            let resolved = paths::resolve_link(&relative_path, &target)
+                .filter(|resolved| blob_ids.contains_key(resolved));
+            symlinks.push(Symlink {
+                relative_path,
+                target,
+                resolved,
+            });
+        }
+        Self::add_symlink_source_file_infos(
+            repo_path,
+            symlinks,
+            context,
+            &mut source_file_infos,
+            warnings,
+            |resolved| {
+                let blob = repo.find_blob(*blob_ids.get(resolved)?).ok()?;
+                Some(blob.content().to_vec())
+            },
+        )?;
+        unknown_files.sort();
+        Ok((source_file_infos, unknown_files, assets))
+    }
+    /// Checks whether a path, relative to the repository, is hidden (i.e., in a directory or
+    /// file starting with '.'), unless hidden files are `counted`, or in the '.git' directory,
+    /// has a component in `excluded` or is under an `excluded` path
+    #[cfg(feature = "git")]
+    fn is_hidden_or_excluded(relative: &Path, excluded: &[&str], counted: bool) -> bool {
+        excluded
+            .iter()
+            .any(|e| paths::starts_with(relative, Path::new(e)))
+            || relative.components().any(|component| match component {
+                Component::Normal(name) => {
+                    let name_str = name.to_string_lossy();
+                    (name_str.starts_with('.') && (!counted || name_str == ".git"))
+                        || excluded
+                            .iter()
+                            .any(|e| paths::names_match(name, OsStr::new(e)))
+                }
+                _ => false,
+            })
+    }
+    /// Gets `tokei` statistics for the repository, counted as the [`CountingOptions`] set
+    #[cfg(feature = "git")]
+    fn get_tokei_stats_for_repo(
+        paths: &[&str],
+        excluded: &[&str],
+        counting: &CountingOptions,
+        limits: &ResourceLimits,
+    ) -> Languages {
+        let config = counting.get_config();
 
         // Get the [`tokei::Languages`] for the repository (via 'paths')
         let mut languages = Languages::new();
-        languages.get_statistics(paths, excluded, &config);
+        limits.run_io(|| languages.get_statistics(paths, excluded, &config));
 
         languages
     }
+    /// Sets the `git_blob_size` of each of the `source_files` committed at HEAD, from the header
+    /// of its blob in the object database
+    #[cfg(feature = "git")]
+    fn set_git_blob_sizes(
+        repo_path: &str,
+        source_files: &mut [SourceFileInfo],
+    ) -> Result<(), SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        if HeadState::resolve(&repo)?.get_commit().is_none() {
+            return Ok(());
+        }
+        let tree = repo.head()?.peel_to_tree()?;
+        let odb = repo.odb()?;
+        for source_file in source_files {
+            let Ok(entry) = tree.get_path(Path::new(source_file.relative_path.as_str())) else {
+                continue;
+            };
+            if entry.kind() == Some(ObjectType::Blob) {
+                source_file.git_blob_size = odb
+                    .read_header(entry.id())
+                    .ok()
+                    .map(|(size, _)| size as u64);
+            }
+        }
+        Ok(())
+    }
     /// Gets the total size of the repository from the Vec of [`SourceFileInfo`]s
-    fn get_total_size(source_file_infos: &[SourceFileInfo]) -> i64 {
-        source_file_infos
-            .iter()
-            .map(|sfi| sfi.statistics.size)
-            .sum()
+    #[cfg(feature = "git")]
+    fn get_total_size(source_file_infos: &[SourceFileInfo]) -> u64 {
+        saturating_sum(source_file_infos.iter().map(|sfi| sfi.statistics.size))
     }
     /// Gets the total number of lines of code for the repository from the Vec of [`SourceFileInfo`]s
-    fn get_total_lines_of_code(source_file_infos: &[SourceFileInfo]) -> i64 {
-        source_file_infos.iter().map(|sfi| sfi.statistics.loc).sum()
+    #[cfg(feature = "git")]
+    fn get_total_lines_of_code(source_file_infos: &[SourceFileInfo]) -> u64 {
+        saturating_sum(source_file_infos.iter().map(|sfi| sfi.statistics.loc))
     }
-    /// Gets the predominant [`LanguageType`] for the repository from the Vec of [`SourceFileInfo`]s
+    /// Gets the [`LanguageType`]s for the repository from the Vec of [`SourceFileInfo`]s, with
+    /// the LOC, size, number of files and file commits summed per language, and the percentage
+    /// of the total LOC as the language share. The LOC of each language includes its code embedded
+    /// in the files of other languages, e.g., JavaScript in HTML, so that a templated codebase
+    /// isn't taken as one of its templates' language.
+    ///
     /// #Arguments:
     /// * `source_file_infos` - The Vec of [`SourceFileInfo`]s
+    ///
     /// #Returns:
-    /// * The [`LanguageType`] of the predominant language
-    fn get_predominant_language(source_file_infos: &[SourceFileInfo]) -> LanguageType {
+    /// * The [`LanguageType`]s, ordered by LOC, largest first
+    #[cfg(feature = "git")]
+    pub(crate) fn get_languages<'a>(
+        source_file_infos: impl IntoIterator<Item = &'a SourceFileInfo>,
+    ) -> Vec<LanguageType> {
         let mut languages: Vec<LanguageType> = Vec::new();
         for source_file_info in source_file_infos {
-            if let Some(language) = &source_file_info.language {
-                languages.push(language.clone());
+            let Some(file_language) = &source_file_info.language else {
+                continue;
+            };
+            let language = Self::get_language_entry(&mut languages, &file_language.name);
+            for extension in &file_language.extensions {
+                if !language.extensions.contains(extension) {
+                    language.extensions.push(extension.clone());
+                }
+            }
+            if let Some(statistics) = &mut language.statistics {
+                *statistics += &source_file_info.statistics;
+            }
+            for embedded in &source_file_info.embedded {
+                let language = Self::get_language_entry(&mut languages, &embedded.name);
+                if let Some(statistics) = &mut language.statistics {
+                    statistics.loc = statistics.loc.saturating_add(embedded.loc);
+                }
             }
         }
-        LanguageType::get_predominant_language(&languages)
+        LanguageType::calculate_percentage_distribution(&mut languages);
+        languages.sort_by_key(|l| std::cmp::Reverse(l.statistics.as_ref().map(|s| s.loc)));
+        languages
     }
-    /// Gets the total number of commits for a git repository
-    fn get_total_commits(repo_path: &str) -> Result<i32, SourceCodeError> {
-        let repo: Repository = Repository::open(repo_path)?;
-        let mut revwalk: Revwalk<'_> = repo.revwalk()?;
-        revwalk.push_head()?;
-
-        let mut total_commits: i32 = 0;
-
-        for commit_id in revwalk {
-            let _: Commit<'_> = repo.find_commit(commit_id?)?;
-            total_commits += 1;
-        }
-        Ok(total_commits)
+    /// Gets the [`LanguageType`] named `name` in the `languages`, adding it if it isn't there
+    #[cfg(feature = "git")]
+    fn get_language_entry<'a>(
+        languages: &'a mut Vec<LanguageType>,
+        name: &str,
+    ) -> &'a mut LanguageType {
+        let index = match languages.iter().position(|l| l.name == name) {
+            Some(index) => index,
+            None => {
+                languages.push(LanguageType {
+                    name: name.to_string(),
+                    extensions: LanguageRegistry::tokei().get_extensions(name).to_vec(),
+                    statistics: Some(Statistics::new()),
+                });
+                languages.len() - 1
+            }
+        };
+        &mut languages[index]
     }
 }
 /// Struct to hold the data on a repository's contributors
 ///
 /// # Fields:
-/// * `name` - The name of the contributor
+/// * `author` - The [`Author`] of the contributor's commits, with the name and email address of
+///   the last one
 /// * `last_contribution` - The date and time of the last contribution made by the contributor
 /// * `percentage_contribution` - The percentage of the total contributions made by the contributor
 /// * `statistics` - The [`Statistics`] on the contributor's contributions
+/// * `commit_messages` - The [`CommitMessageStatistics`] of the contributor's commit messages
+/// * `commit_times` - The [`CommitHeatmap`] of the contributor's commits, by their local day and
+///   hour
+/// * `first_contribution` - The date and time of the first contribution made by the contributor
+/// * `is_active` - Whether the contributor made a contribution in the
+///   [`retention::ACTIVE_WINDOW_DAYS`] before the analysis
+/// * `cadence` - The [`ContributorCadence`] of the contributor's commits, e.g., their longest
+///   streak of weeks with a commit
+/// * `forge_user` - The [`ForgeUser`] of the contributor, if enriched with [`crate::forge`]
+/// * `num_committed_by_others` - The number of the contributor's commits that were committed by
+///   someone else, e.g., a patch applied by a maintainer, or a pull request merged by a forge
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Contributor {
-    name: String,
-    last_contribution: DateTime<Utc>,
-    percentage_contribution: f32,
-    statistics: Statistics,
+    pub author: Author,
+    pub last_contribution: DateTime<Utc>,
+    pub percentage_contribution: f32,
+    pub statistics: Statistics,
+    #[serde(default)]
+    pub commit_messages: CommitMessageStatistics,
+    #[serde(default)]
+    pub commit_times: CommitHeatmap,
+    #[serde(default)]
+    pub first_contribution: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub is_active: bool,
+    #[serde(default)]
+    pub cadence: ContributorCadence,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forge_user: Option<ForgeUser>,
+    #[serde(default)]
+    pub num_committed_by_others: u64,
 }
 impl Contributor {
     pub fn new(
-        name: String,
+        author: Author,
         last_contribution: DateTime<Utc>,
         percentage_contribution: f32,
         statistics: Statistics,
     ) -> Self {
         Self {
-            name,
+            author,
             last_contribution,
             percentage_contribution,
             statistics,
+            commit_messages: CommitMessageStatistics::default(),
+            commit_times: CommitHeatmap::default(),
+            cadence: ContributorCadence::default(),
+            first_contribution: None,
+            is_active: false,
+            forge_user: None,
+            num_committed_by_others: 0,
         }
     }
     /// Gets the contributors from the repository passed as the 'repo_path'.
     ///
-    /// TODO: add other contributor statistics: frequency of contribution, lines of code changed in commits(?), num_files changed in commits(?), etc.
+    /// TODO: add other contributor statistics: lines of code changed in commits(?), num_files changed in commits(?), etc.
     ///
     /// #Arguments:
     /// * `repo_path` - The path to the repository
     ///
     /// #Returns:
-    /// * A [`Vec`] of [`Contributor`]s
-    pub fn get_git_contributors(repo_path: &str) -> Vec<Contributor> {
-        let repo = Repository::open(repo_path).expect("Failed to open repository");
-        let mut revwalk = repo.revwalk().expect("Failed to get revwalk");
-        revwalk.push_head().expect("Failed to push head");
-
-        let mut contributions = HashMap::<String, (DateTime<Utc>, i32)>::new();
+    /// * A [`Vec`] of [`Contributor`]s, empty if the repository has no commits. Commits that
+    ///   can't be read are skipped with a warning.
+    #[cfg(feature = "git")]
+    pub fn get_git_contributors(repo_path: &str) -> Result<Vec<Contributor>, SourceCodeError> {
+        Self::get_git_contributors_with_observer(
+            repo_path,
+            &NoopObserver,
+            &CancellationToken::new(),
+        )
+    }
+    /// Gets the [`Contributor`]s as [`Contributor::get_git_contributors`] does, reporting each
+    /// commit walked to the [`AnalysisObserver`] and stopping early if the [`CancellationToken`]
+    /// is cancelled
+    #[cfg(feature = "git")]
+    pub fn get_git_contributors_with_observer(
+        repo_path: &str,
+        observer: &dyn AnalysisObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<Vec<Contributor>, SourceCodeError> {
+        let graph = CommitGraph::new(
+            repo_path,
+            MergeStrategy::default(),
+            HistoryMode::default(),
+            HistorySampling::default(),
+            &IdentityOptions::default(),
+            observer,
+            cancellation,
+        )?;
+        Ok(Self::from_graph(
+            &graph,
+            Utc::now(),
+            &CadenceOptions::default(),
+            BotHandling::default(),
+        ))
+    }
+    /// Gets the [`Contributor`]s of the commits of the [`CommitGraph`], with their
+    /// [`ContributorCadence`] as of the time `at`; commits with an invalid author time are
+    /// skipped with a warning, as are those authored by bots, unless the [`BotHandling`] is
+    /// [`BotHandling::Include`]
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        graph: &CommitGraph,
+        at: DateTime<Utc>,
+        cadence: &CadenceOptions,
+        bots: BotHandling,
+    ) -> Vec<Contributor> {
+        // The author of the last contribution, its date, the first contribution date, and the
+        // number of commits, messages, times, dates and commits committed by others, by
+        // canonical id
+        let mut contributions = HashMap::<
+            &str,
+            (
+                &Author,
+                DateTime<Utc>,
+                DateTime<Utc>,
+                u64,
+                CommitMessageCounter,
+                CommitHeatmap,
+                Vec<DateTime<Utc>>,
+                u64,
+            ),
+        >::new();
         let mut total_contributions = 0;
 
-        for oid in revwalk {
-            if let Ok(commit) = repo.find_commit(oid.expect("Invalid oid")) {
-                let name = String::from(commit.author().name().unwrap_or_default());
-                let time = commit.author().when();
-
-                let naive_date_time = NaiveDateTime::from_timestamp_opt(time.seconds(), 0).unwrap();
-                let date = DateTime::<Utc>::from_naive_utc_and_offset(naive_date_time, Utc);
+        for commit in graph.commits() {
+            if commit.author.is_bot && bots != BotHandling::Include {
+                continue;
+            }
+            let Some(date) = commit.author_date() else {
+                tracing::warn!(
+                    "Skipping commit {} with an invalid author time: {}",
+                    commit.id,
+                    commit.author_time.seconds()
+                );
+                continue;
+            };
 
-                let entry = contributions.entry(name).or_insert((date, 0));
-                entry.1 += 1; // Increment contribution count
-                if date > entry.0 {
-                    entry.0 = date; // Update last contribution date if newer
-                }
-                total_contributions += 1;
+            let entry = contributions
+                .entry(commit.author.canonical_id.as_str())
+                .or_insert((
+                    &commit.author,
+                    date,
+                    date,
+                    0,
+                    CommitMessageCounter::default(),
+                    CommitHeatmap::default(),
+                    Vec::new(),
+                    0,
+                ));
+            entry.3 += 1; // Increment contribution count
+            entry.4.add(&commit.message);
+            entry.5.add(commit.author_time);
+            entry.6.push(date);
+            if commit.committer.canonical_id != commit.author.canonical_id {
+                entry.7 += 1;
             }
+            if date > entry.1 {
+                // Update the author and last contribution date if newer
+                entry.0 = &commit.author;
+                entry.1 = date;
+            }
+            entry.2 = entry.2.min(date); // Update first contribution date if older
+            total_contributions += 1;
         }
         contributions
-            .into_iter()
-            .map(|(name, (last_contribution, num_commits))| {
-                let percentage = num_commits as f32 / total_contributions as f32 * 100.0;
-                let statistics = Statistics {
-                    size: 0, // Not relevant for contributors
-                    loc: 0,
-                    num_files: 0,
+            .into_values()
+            .map(
+                |(
+                    author,
+                    last_contribution,
+                    first_contribution,
                     num_commits,
-                    frequency: 0.0,
-                };
-                Contributor::new(name, last_contribution, percentage, statistics)
-            })
+                    commit_messages,
+                    commit_times,
+                    dates,
+                    num_committed_by_others,
+                )| {
+                    let percentage =
+                        ratio::percentage(num_commits as f64, total_contributions as f64)
+                            .unwrap_or_default();
+                    let statistics = Statistics {
+                        size: 0, // Not relevant for contributors
+                        loc: 0,
+                        num_files: 0,
+                        num_commits,
+                        contribution_share: Some(Percentage(percentage)),
+                        ..Default::default()
+                    };
+                    Contributor {
+                        first_contribution: Some(first_contribution),
+                        commit_messages: commit_messages.get_statistics(),
+                        commit_times,
+                        cadence: ContributorCadence::new(&dates, at, cadence),
+                        num_committed_by_others,
+                        ..Contributor::new(
+                            author.clone(),
+                            last_contribution,
+                            percentage,
+                            statistics,
+                        )
+                    }
+                },
+            )
             .collect()
     }
 }
+
+#[cfg(all(test, feature = "git"))]
+mod tests {
+    use super::*;
+    use crate::testing::FixtureRepo;
+
+    #[test]
+    fn counts_a_file_with_nul_characters_past_the_binary_check_as_an_asset(
+    ) -> Result<(), SourceCodeError> {
+        let mut contents = "text\n".repeat(source::BINARY_CHECK_LENGTH);
+        contents.push('\0');
+        let fixture = FixtureRepo::new()?
+            .with_file("src/main.rs", "fn main() {}\n")
+            .with_file("payload", &contents)
+            .commit("Add a payload")?;
+        // The fallback detects the languages of the files of the working tree
+        let stats = fixture.analyse(&AnalysisOptions::default().with_uncommitted_changes(true))?;
+
+        assert_eq!(stats.source_files.len(), 1);
+        assert_eq!(stats.assets.num_files, 1);
+        assert_eq!(stats.assets.largest[0].path, "payload");
+        assert_eq!(stats.assets.largest[0].size, to_count(contents.len()));
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+#[cfg(feature = "git")]
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[cfg(feature = "git")]
+use crate::{
+    cancel::CancellationToken,
+    data::to_count,
+    errors::SourceCodeError,
+    graph::CommitGraph,
+    history::{HistoryMode, HistorySampling},
+    identity::IdentityOptions,
+    observer::NoopObserver,
+    ratio::ratio,
+    source::MergeStrategy,
+};
+
+/// The days before the analysis in which a contributor with a commit is active
+pub const ACTIVE_WINDOW_DAYS: i64 = 90;
+
+/// Whether the number of active contributors is growing or shrinking
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ContributorTrend {
+    /// More contributors are active in the last window than in the window before
+    Growing,
+    /// As many contributors are active in the last window as in the window before
+    #[default]
+    Stable,
+    /// Fewer contributors are active in the last window than in the window before
+    Shrinking,
+}
+
+/// The contributors of a calendar quarter
+///
+/// #Fields:
+/// * `quarter` - The quarter, e.g., '2024-Q1'
+/// * `num_active` - The number of contributors with a commit in the quarter
+/// * `num_new` - The number of contributors whose first commit is in the quarter
+/// * `num_departed` - The number of contributors whose last commit is in the quarter, and who
+///   aren't active now
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QuarterlyContributors {
+    pub quarter: String,
+    pub num_active: u64,
+    pub num_new: u64,
+    pub num_departed: u64,
+}
+
+/// Whether the contributors of a repository are growing or shrinking in number, from the
+/// contributors active in the [`ACTIVE_WINDOW_DAYS`] before the analysis, and in the window
+/// before that
+///
+/// #Fields:
+/// * `num_active` - The number of contributors active in the last window
+/// * `num_previously_active` - The number of contributors active in the window before
+/// * `num_new` - The number of active contributors whose first commit is in the last window
+/// * `num_departed` - The number of contributors active in the window before, but not since
+/// * `retention_rate` - The share (0.0 - 1.0) of the contributors active in the window before
+///   that are still active, or `None` if there were none
+/// * `trend` - The [`ContributorTrend`] from the window before to the last window
+/// * `quarters` - The [`QuarterlyContributors`] of each quarter from the first commit to the
+///   analysis, oldest first
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ContributorRetention {
+    pub num_active: u64,
+    pub num_previously_active: u64,
+    pub num_new: u64,
+    pub num_departed: u64,
+    pub retention_rate: Option<f32>,
+    pub trend: ContributorTrend,
+    pub quarters: Vec<QuarterlyContributors>,
+}
+impl ContributorRetention {
+    /// Gets the [`ContributorRetention`] of the repository at `repo_path`, as of the time `at`,
+    /// e.g., the time of the analysis
+    #[cfg(feature = "git")]
+    pub fn new(repo_path: &str, at: DateTime<Utc>) -> Result<Self, SourceCodeError> {
+        Self::new_with_cancellation(repo_path, at, &CancellationToken::new())
+    }
+    /// Gets the [`ContributorRetention`] as [`ContributorRetention::new`] does, stopping early
+    /// if the [`CancellationToken`] is cancelled
+    #[cfg(feature = "git")]
+    pub fn new_with_cancellation(
+        repo_path: &str,
+        at: DateTime<Utc>,
+        cancellation: &CancellationToken,
+    ) -> Result<Self, SourceCodeError> {
+        let graph = CommitGraph::new(
+            repo_path,
+            MergeStrategy::default(),
+            HistoryMode::default(),
+            HistorySampling::default(),
+            &IdentityOptions::default(),
+            &NoopObserver,
+            cancellation,
+        )?;
+        Ok(Self::from_graph(&graph, at))
+    }
+    /// Gets the [`ContributorRetention`] of the commits of the [`CommitGraph`], as of the time
+    /// `at`
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(graph: &CommitGraph, at: DateTime<Utc>) -> Self {
+        // The dates of each contributor's commits
+        let mut contributions: HashMap<&str, Vec<DateTime<Utc>>> = HashMap::new();
+        for commit in graph.commits() {
+            if let Some(date) = commit.author_date() {
+                contributions
+                    .entry(commit.author.canonical_id.as_str())
+                    .or_default()
+                    .push(date);
+            }
+        }
+        if contributions.is_empty() {
+            return Self::default();
+        }
+
+        let window_start = at - Duration::days(ACTIVE_WINDOW_DAYS);
+        let previous_window_start = window_start - Duration::days(ACTIVE_WINDOW_DAYS);
+        let mut retention = Self::default();
+        let mut quarters: BTreeMap<(i32, u32), (HashSet<&str>, u64, u64)> = BTreeMap::new();
+        for (name, dates) in &contributions {
+            let first = dates.iter().min().copied().unwrap_or(at);
+            let last = dates.iter().max().copied().unwrap_or(at);
+            let active = last >= window_start;
+            let previously_active = dates
+                .iter()
+                .any(|date| *date >= previous_window_start && *date < window_start);
+            retention.num_active += u64::from(active);
+            retention.num_previously_active += u64::from(previously_active);
+            retention.num_new += u64::from(first >= window_start);
+            retention.num_departed += u64::from(previously_active && !active);
+            for date in dates {
+                quarters
+                    .entry(quarter_of(*date))
+                    .or_default()
+                    .0
+                    .insert(*name);
+            }
+            quarters.entry(quarter_of(first)).or_default().1 += 1;
+            if !active {
+                quarters.entry(quarter_of(last)).or_default().2 += 1;
+            }
+        }
+        let num_retained = retention
+            .num_previously_active
+            .saturating_sub(retention.num_departed);
+        retention.retention_rate =
+            ratio(num_retained as f64, retention.num_previously_active as f64);
+        retention.trend = match retention.num_active.cmp(&retention.num_previously_active) {
+            std::cmp::Ordering::Greater => ContributorTrend::Growing,
+            std::cmp::Ordering::Equal => ContributorTrend::Stable,
+            std::cmp::Ordering::Less => ContributorTrend::Shrinking,
+        };
+
+        // Every quarter from the first commit to the analysis, including those without commits
+        let mut quarter = quarters.keys().next().copied().unwrap_or(quarter_of(at));
+        let last_quarter = quarter_of(at).max(quarters.keys().last().copied().unwrap_or(quarter));
+        while quarter <= last_quarter {
+            let (active, num_new, num_departed) = quarters.remove(&quarter).unwrap_or_default();
+            retention.quarters.push(QuarterlyContributors {
+                quarter: format!("{}-Q{}", quarter.0, quarter.1),
+                num_active: to_count(active.len()),
+                num_new,
+                num_departed,
+            });
+            quarter = match quarter.1 {
+                4 => (quarter.0 + 1, 1),
+                number => (quarter.0, number + 1),
+            };
+        }
+        retention
+    }
+}
+
+/// Gets the calendar quarter of the `date`, as its year and number (1 - 4)
+#[cfg(feature = "git")]
+fn quarter_of(date: DateTime<Utc>) -> (i32, u32) {
+    (date.year(), (date.month() - 1) / 3 + 1)
+}
@@ -0,0 +1,38 @@
+//! Retrying git operations that fail on transient locks, e.g., an `index.lock` held by a
+//! concurrent `git` command on an active working tree
+use git2::ErrorCode;
+use std::{thread, time::Duration};
+
+/// The number of times a locked git operation is attempted
+const MAX_ATTEMPTS: u32 = 5;
+/// The wait before the first retry, doubled for each retry after it
+const INITIAL_BACKOFF: Duration = Duration::from_millis(20);
+
+/// Runs the git `operation`, retrying with exponential backoff while it fails because a lock
+/// file is held
+///
+/// #Returns:
+/// * The result of the first attempt that isn't locked, or the last locked error
+pub(crate) fn retry_on_lock<T>(
+    mut operation: impl FnMut() -> Result<T, git2::Error>,
+) -> Result<T, git2::Error> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match operation() {
+            Err(err) if err.code() == ErrorCode::Locked && attempt < MAX_ATTEMPTS => {
+                tracing::debug!(
+                    "Git operation locked (attempt {} of {}), retrying in {:?}: {}",
+                    attempt,
+                    MAX_ATTEMPTS,
+                    backoff,
+                    err
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
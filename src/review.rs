@@ -0,0 +1,497 @@
+use chrono::{DateTime, Utc};
+use git2::{BlameOptions, Diff, DiffOptions, Patch, Repository, Revwalk, Tree};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use crate::{
+    cancel::CancellationToken,
+    errors::SourceCodeError,
+    graph::{CommitGraph, CommitNode},
+    identity::{Author, IdentityResolver},
+    observer::NoopObserver,
+    paths,
+    ratio::ratio,
+    repository::RepositoryInfo,
+    retry::retry_on_lock,
+    source::SourceFileInfo,
+};
+
+/// The number of commits touching each file, in total and per author name
+type Ownership<'a> = HashMap<&'a str, (i32, HashMap<String, i32>)>;
+
+/// The change set to estimate the review effort for
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReviewTarget {
+    /// The uncommitted changes (staged and unstaged) in the working tree, relative to HEAD
+    WorkingTree,
+    /// The changes between two refs (e.g., a branch and its merge base)
+    Refs { base: String, head: String },
+}
+impl ReviewTarget {
+    /// Gets the paths, relative to the repository, of the files the change set adds, changes or
+    /// deletes in the repository at `repo_path`, e.g., to check only the files a pull request
+    /// touches
+    pub fn get_changed_files(&self, repo_path: &str) -> Result<Vec<String>, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+        let (diff, _) = self.get_diff(&repo)?;
+        let mut paths: Vec<String> = diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+            .map(paths::to_slash)
+            .collect();
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+    /// Diffs the change set in the `repo`, with the untracked files of the working tree
+    ///
+    /// #Returns:
+    /// * The [`Diff`], and the default author of the change: the configured `user.name` for the
+    ///   working tree, or the author of the `head` commit for refs
+    fn get_diff<'r>(&self, repo: &'r Repository) -> Result<(Diff<'r>, String), SourceCodeError> {
+        let mut diff_options = DiffOptions::new();
+        diff_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true);
+
+        let (diff, default_author): (Diff<'_>, String) = match self {
+            ReviewTarget::WorkingTree => {
+                let head_tree: Option<Tree<'_>> =
+                    repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+                let diff = retry_on_lock(|| {
+                    repo.diff_tree_to_workdir_with_index(
+                        head_tree.as_ref(),
+                        Some(&mut diff_options),
+                    )
+                })?;
+                let user = repo.config()?.get_string("user.name").unwrap_or_default();
+                (diff, user)
+            }
+            ReviewTarget::Refs { base, head } => {
+                let base_tree = repo
+                    .revparse_single(base)
+                    .and_then(|object| object.peel_to_tree())
+                    .map_err(SourceCodeError::for_reference(base))?;
+                let head_commit = repo
+                    .revparse_single(head)
+                    .and_then(|object| object.peel_to_commit())
+                    .map_err(SourceCodeError::for_reference(head))?;
+                let diff = repo.diff_tree_to_tree(
+                    Some(&base_tree),
+                    Some(&head_commit.tree()?),
+                    Some(&mut diff_options),
+                )?;
+                let user = String::from(head_commit.author().name().unwrap_or_default());
+                (diff, user)
+            }
+        };
+        Ok((diff, default_author))
+    }
+}
+
+/// The estimated review effort for a change set, with a per-file breakdown
+///
+/// #Fields:
+/// * `author` - The author the unfamiliarity of each file is calculated for
+/// * `total_score` - The sum of the per-file scores
+/// * `files` - The [`FileReviewEffort`]s, highest score first
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReviewEffort {
+    pub author: String,
+    pub total_score: f32,
+    pub files: Vec<FileReviewEffort>,
+}
+impl ReviewEffort {
+    /// Estimates the review effort of the `target` change set in the repository at `repo_path`,
+    /// using the per-file statistics of a previously computed [`RepositoryInfo`].
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository
+    /// * `repository_info` - The [`RepositoryInfo`] of the repository, for per-file LOC and change frequency
+    /// * `target` - The [`ReviewTarget`] change set
+    /// * `author` - The author of the change; defaults to the configured `user.name` for the
+    ///   working tree, or the author of the `head` commit for refs
+    pub fn estimate(
+        repo_path: &str,
+        repository_info: &RepositoryInfo,
+        target: &ReviewTarget,
+        author: Option<&str>,
+    ) -> Result<ReviewEffort, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+        let (diff, default_author) = target.get_diff(&repo)?;
+        let author = author.map(String::from).unwrap_or(default_author);
+
+        let mut changes: Vec<(String, usize, usize)> = Vec::new();
+        for index in 0..diff.deltas().len() {
+            let Some(delta) = diff.get_delta(index) else {
+                continue;
+            };
+            let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+                continue;
+            };
+            let path = paths::to_slash(path);
+            let (added, removed) = match Patch::from_diff(&diff, index)? {
+                Some(patch) => {
+                    let (_, added, removed) = patch.line_stats()?;
+                    (added, removed)
+                }
+                None => (0, 0),
+            };
+            changes.push((path, added, removed));
+        }
+
+        let touched: HashSet<&str> = changes.iter().map(|(path, _, _)| path.as_str()).collect();
+        let ownership = Self::get_ownership(&repo, &touched)?;
+
+        let mut files: Vec<FileReviewEffort> = changes
+            .iter()
+            .map(|(path, added, removed)| {
+                let source_file = repository_info.source_files.iter().find(|sfi| {
+                    let relative = Path::new(&sfi.relative_path);
+                    relative.strip_prefix(repo_path).unwrap_or(relative) == Path::new(path)
+                });
+                let loc = source_file
+                    .map(|sfi| sfi.statistics.loc)
+                    .unwrap_or_default();
+                let hotspot = source_file
+                    .map(|sfi| sfi.statistics.change_frequency.to_ratio().0)
+                    .unwrap_or_default();
+                let unfamiliarity = match ownership.get(path.as_str()) {
+                    Some((total, by_author)) if *total > 0 => {
+                        let by_author = by_author.get(&author).copied().unwrap_or_default();
+                        1.0 - ratio(by_author as f64, *total as f64).unwrap_or_default()
+                    }
+                    // Files without history are new, and so familiar to their author
+                    _ => 0.0,
+                };
+                FileReviewEffort::new(path, *added, *removed, loc, hotspot, unfamiliarity)
+            })
+            .collect();
+        files.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        Ok(ReviewEffort {
+            author,
+            total_score: files.iter().map(|f| f.score).sum(),
+            files,
+        })
+    }
+    /// Gets the [`ReviewEffort`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    /// Counts, for each of the `paths`, the commits touching it in total and per author
+    fn get_ownership<'a>(
+        repo: &Repository,
+        paths: &HashSet<&'a str>,
+    ) -> Result<Ownership<'a>, SourceCodeError> {
+        let mut ownership: Ownership<'a> = HashMap::new();
+        let mut revwalk: Revwalk<'_> = repo.revwalk()?;
+        if revwalk.push_head().is_err() {
+            // An empty repository has no history to be familiar with
+            return Ok(ownership);
+        }
+
+        for commit_id in revwalk {
+            let commit = repo.find_commit(commit_id?)?;
+            let parent_tree = match commit.parent_count() {
+                0 => None,
+                _ => Some(commit.parent(0)?.tree()?),
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let author = String::from(commit.author().name().unwrap_or_default());
+            for delta in diff.deltas() {
+                let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+                    continue;
+                };
+                let Some(path) = path.to_str().and_then(|p| paths.get(p)) else {
+                    continue;
+                };
+                let entry = ownership.entry(path).or_default();
+                entry.0 += 1;
+                *entry.1.entry(author.clone()).or_insert(0) += 1;
+            }
+        }
+        Ok(ownership)
+    }
+}
+
+/// The estimated review effort for a single file
+///
+/// The score is `ln(1 + lines changed) * (1 + ln(1 + loc) / 10) * (1 + hotspot) * (1 + unfamiliarity)`,
+/// so small changes stay cheap while large, hot, or unfamiliar files are weighted up.
+///
+/// #Fields:
+/// * `path` - The path of the file relative to the repository root
+/// * `lines_added` - The number of lines added
+/// * `lines_removed` - The number of lines removed
+/// * `loc` - The lines of code of the file, as a proxy for its complexity
+/// * `hotspot` - The change frequency of the file as a ratio (0.0 - 1.0)
+/// * `unfamiliarity` - The ratio of the file's commits not made by the author (0.0 - 1.0)
+/// * `score` - The estimated review effort
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FileReviewEffort {
+    pub path: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub loc: u64,
+    pub hotspot: f32,
+    pub unfamiliarity: f32,
+    pub score: f32,
+}
+impl FileReviewEffort {
+    fn new(
+        path: &str,
+        lines_added: usize,
+        lines_removed: usize,
+        loc: u64,
+        hotspot: f32,
+        unfamiliarity: f32,
+    ) -> Self {
+        let lines_changed = (lines_added + lines_removed) as f32;
+        let score = (1.0 + lines_changed).ln()
+            * (1.0 + (1.0 + loc as f32).ln() / 10.0)
+            * (1.0 + hotspot)
+            * (1.0 + unfamiliarity);
+        Self {
+            path: path.to_string(),
+            lines_added,
+            lines_removed,
+            loc,
+            hotspot,
+            unfamiliarity,
+            score,
+        }
+    }
+}
+
+/// The options for suggesting the reviewers of a change with
+/// [`RepositoryInfo::suggest_reviewers`]
+///
+/// #Fields:
+/// * `half_life_days` - The age, in days, at which a commit counts half as much as one made at
+///   the time of the analysis, and a quarter at twice it
+/// * `commit_weight` - The weight of a contributor's share of the recency-weighted commits to
+///   the paths in their score
+/// * `blame_weight` - The weight of a contributor's share of the lines of the paths at HEAD, by
+///   `git blame`, in their score
+/// * `max_blamed_files` - The most files of the paths that are blamed, the most changed first, as
+///   blaming a file walks its history
+/// * `max_reviewers` - The number of [`ReviewerSuggestion`]s returned
+/// * `excluded` - The contributors not to suggest, e.g., the author of the change, by name,
+///   email address or canonical id, ignoring case
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct ReviewerOptions {
+    pub half_life_days: f64,
+    pub commit_weight: f64,
+    pub blame_weight: f64,
+    pub max_blamed_files: usize,
+    pub max_reviewers: usize,
+    pub excluded: Vec<String>,
+}
+impl Default for ReviewerOptions {
+    fn default() -> Self {
+        Self {
+            half_life_days: 180.0,
+            commit_weight: 0.5,
+            blame_weight: 0.5,
+            max_blamed_files: 50,
+            max_reviewers: 5,
+            excluded: vec![],
+        }
+    }
+}
+
+/// A contributor suggested to review a change, by their relevance to the paths it touches
+///
+/// #Fields:
+/// * `author` - The [`Author`] of the contributor, with the name and email address of their
+///   latest commit to the paths, or else of a line they authored
+/// * `score` - The relevance, from 0.0 to 1.0: the weighted mean of the `commit_share` and
+///   `blame_share`, as the [`ReviewerOptions`] weigh them
+/// * `num_commits` - The number of the contributor's commits changing the paths
+/// * `weighted_commits` - The `num_commits` weighted by their age, see
+///   [`ReviewerOptions::half_life_days`]
+/// * `commit_share` - The ratio (0.0 - 1.0) of the recency-weighted commits to the paths by
+///   the contributor
+/// * `blamed_lines` - The number of lines of the blamed files last changed by the contributor
+/// * `blame_share` - The ratio (0.0 - 1.0) of the lines of the blamed files last changed by the
+///   contributor
+/// * `last_commit` - The time of the contributor's latest commit changing the paths, if any
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReviewerSuggestion {
+    pub author: Author,
+    pub score: f64,
+    pub num_commits: u32,
+    pub weighted_commits: f64,
+    pub commit_share: f64,
+    pub blamed_lines: u64,
+    pub blame_share: f64,
+    pub last_commit: Option<DateTime<Utc>>,
+}
+impl ReviewerSuggestion {
+    fn new(author: &Author) -> Self {
+        Self {
+            author: author.clone(),
+            score: 0.0,
+            num_commits: 0,
+            weighted_commits: 0.0,
+            commit_share: 0.0,
+            blamed_lines: 0,
+            blame_share: 0.0,
+            last_commit: None,
+        }
+    }
+    /// Suggests the reviewers of a change to the `paths` (files or directories) of the analysed
+    /// repository at `repo_path`, see [`RepositoryInfo::suggest_reviewers`]
+    pub(crate) fn suggest(
+        repo_path: &str,
+        repository_info: &RepositoryInfo,
+        paths: &[&str],
+        options: &ReviewerOptions,
+    ) -> Result<Vec<ReviewerSuggestion>, SourceCodeError> {
+        let analysis_options = repository_info
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.options.clone())
+            .unwrap_or_default();
+        let graph = CommitGraph::new(
+            repo_path,
+            analysis_options.merge_strategy,
+            analysis_options.history_mode,
+            analysis_options.history_sampling,
+            &analysis_options.identity,
+            &NoopObserver,
+            &CancellationToken::new(),
+        )?;
+        let changes = graph.changes()?;
+        let commits = graph.commits();
+        let mut files: Vec<&SourceFileInfo> = repository_info
+            .source_files
+            .iter()
+            .filter(|source_file| {
+                let path = Path::new(source_file.relative_path.as_str());
+                paths
+                    .iter()
+                    .any(|base| paths::starts_with(path, Path::new(base.trim_end_matches('/'))))
+            })
+            .collect();
+        files.sort_by(|a, b| {
+            b.statistics
+                .num_commits
+                .cmp(&a.statistics.num_commits)
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
+        });
+
+        // Commits are aged from the time of the analysis, or else of the latest commit
+        let now = repository_info
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.started_at)
+            .or_else(|| commits.iter().filter_map(CommitNode::commit_date).max())
+            .unwrap_or_else(Utc::now);
+        let mut indices: Vec<usize> = files
+            .iter()
+            .flat_map(|file| {
+                let path = file.relative_path.as_str();
+                changes
+                    .commits_changing(path)
+                    .iter()
+                    .chain(changes.commits_adding(path))
+                    .copied()
+            })
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let mut suggestions: HashMap<String, ReviewerSuggestion> = HashMap::new();
+        for index in indices {
+            let commit = &commits[index];
+            let date = commit.commit_date();
+            let age_days = date
+                .map(|date| (now - date).num_seconds().max(0) as f64 / 86_400.0)
+                .unwrap_or_default();
+            let reviewer = suggestions
+                .entry(commit.author.canonical_id.clone())
+                .or_insert_with(|| ReviewerSuggestion::new(&commit.author));
+            reviewer.num_commits += 1;
+            reviewer.weighted_commits += 0.5_f64.powf(age_days / options.half_life_days.max(1.0));
+            if date > reviewer.last_commit {
+                reviewer.last_commit = date;
+                reviewer.author = commit.author.clone();
+            }
+        }
+
+        let repo = Repository::open(repo_path)?;
+        let resolver = IdentityResolver::new(&repo, &analysis_options.identity);
+        for file in files.iter().take(options.max_blamed_files) {
+            let path = file.relative_path.as_str();
+            let blame = match repo.blame_file(Path::new(path), Some(&mut BlameOptions::new())) {
+                Ok(blame) => blame,
+                Err(err) => {
+                    // E.g., the file isn't committed yet
+                    tracing::debug!("Could not blame {}: {}", path, err);
+                    continue;
+                }
+            };
+            for hunk in blame
+                .iter()
+                .filter(|hunk| !hunk.final_commit_id().is_zero())
+            {
+                let author = resolver.resolve(&hunk.final_signature());
+                suggestions
+                    .entry(author.canonical_id.clone())
+                    .or_insert_with(|| ReviewerSuggestion::new(&author))
+                    .blamed_lines += hunk.lines_in_hunk() as u64;
+            }
+        }
+
+        let excluded: Vec<String> = options
+            .excluded
+            .iter()
+            .map(|excluded| excluded.to_lowercase())
+            .collect();
+        let is_excluded = |author: &Author| {
+            [
+                Some(&author.name),
+                author.email.as_ref(),
+                Some(&author.canonical_id),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|id| excluded.contains(&id.to_lowercase()))
+        };
+        let mut suggestions: Vec<ReviewerSuggestion> = suggestions
+            .into_values()
+            .filter(|suggestion| !is_excluded(&suggestion.author))
+            .collect();
+        let total_weighted: f64 = suggestions.iter().map(|s| s.weighted_commits).sum();
+        let total_blamed: u64 = suggestions.iter().map(|s| s.blamed_lines).sum();
+        let total_weight = options.commit_weight + options.blame_weight;
+        for suggestion in &mut suggestions {
+            suggestion.commit_share =
+                ratio(suggestion.weighted_commits, total_weighted).unwrap_or_default() as f64;
+            suggestion.blame_share = ratio(suggestion.blamed_lines as f64, total_blamed as f64)
+                .unwrap_or_default() as f64;
+            suggestion.score = match total_weight > 0.0 {
+                true => {
+                    (options.commit_weight * suggestion.commit_share
+                        + options.blame_weight * suggestion.blame_share)
+                        / total_weight
+                }
+                false => 0.0,
+            };
+        }
+        suggestions.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.last_commit.cmp(&a.last_commit))
+                .then_with(|| a.author.name.cmp(&b.author.name))
+        });
+        suggestions.truncate(options.max_reviewers);
+        Ok(suggestions)
+    }
+}
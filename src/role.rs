@@ -0,0 +1,239 @@
+//! The classification of source files into roles, e.g., tests, configuration or build scripts,
+//! so that lines of code can be compared without the noise of files that aren't code
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{generated::CodeTotals, paths, source::SourceFileInfo};
+
+/// The directories, relative to the root of the repository, that hold only CI definitions
+const CI_DIRS: [&str; 4] = [".github/workflows", ".circleci", ".buildkite", ".gitlab"];
+/// The names of files that define CI pipelines
+const CI_FILES: [&str; 8] = [
+    ".gitlab-ci.yml",
+    ".travis.yml",
+    ".drone.yml",
+    "azure-pipelines.yml",
+    "bitbucket-pipelines.yml",
+    "appveyor.yml",
+    "cloudbuild.yaml",
+    "Jenkinsfile",
+];
+/// The roles of the files of languages by their file names, where a name starting with '*'
+/// matches any file name with the rest as its suffix, e.g., '*.config.js' matches
+/// 'webpack.config.js'
+const LANGUAGE_RULES: [(&str, &[(&str, FileRole)]); 10] = [
+    ("Rust", &[("build.rs", FileRole::BuildScript)]),
+    (
+        "Python",
+        &[
+            ("setup.py", FileRole::BuildScript),
+            ("noxfile.py", FileRole::BuildScript),
+            ("fabfile.py", FileRole::BuildScript),
+            ("conftest.py", FileRole::Test),
+        ],
+    ),
+    (
+        "JavaScript",
+        &[
+            ("Gruntfile.js", FileRole::BuildScript),
+            ("gulpfile.js", FileRole::BuildScript),
+            ("*.config.js", FileRole::Config),
+            ("*.config.cjs", FileRole::Config),
+            ("*.config.mjs", FileRole::Config),
+            (".eslintrc.js", FileRole::Config),
+        ],
+    ),
+    (
+        "TypeScript",
+        &[
+            ("gulpfile.ts", FileRole::BuildScript),
+            ("*.config.ts", FileRole::Config),
+        ],
+    ),
+    (
+        "Ruby",
+        &[
+            ("Rakefile", FileRole::BuildScript),
+            ("Gemfile", FileRole::BuildScript),
+            ("*.gemspec", FileRole::BuildScript),
+        ],
+    ),
+    ("Go", &[("magefile.go", FileRole::BuildScript)]),
+    (
+        "Groovy",
+        &[
+            ("build.gradle", FileRole::BuildScript),
+            ("settings.gradle", FileRole::BuildScript),
+        ],
+    ),
+    ("Kotlin", &[("*.gradle.kts", FileRole::BuildScript)]),
+    (
+        "XML",
+        &[
+            ("pom.xml", FileRole::BuildScript),
+            ("build.xml", FileRole::BuildScript),
+        ],
+    ),
+    (
+        "Shell",
+        &[
+            ("configure", FileRole::BuildScript),
+            ("build.sh", FileRole::BuildScript),
+        ],
+    ),
+];
+/// The roles of all the files of languages, other than by the [`LANGUAGE_RULES`]
+const LANGUAGE_ROLES: [(&str, FileRole); 17] = [
+    ("Markdown", FileRole::Docs),
+    ("ReStructuredText", FileRole::Docs),
+    ("AsciiDoc", FileRole::Docs),
+    ("Plain Text", FileRole::Docs),
+    ("Org", FileRole::Docs),
+    ("TOML", FileRole::Config),
+    ("YAML", FileRole::Config),
+    ("JSON", FileRole::Config),
+    ("INI", FileRole::Config),
+    ("Makefile", FileRole::BuildScript),
+    ("CMake", FileRole::BuildScript),
+    ("Autoconf", FileRole::BuildScript),
+    ("Meson", FileRole::BuildScript),
+    ("Bazel", FileRole::BuildScript),
+    ("MSBuild", FileRole::BuildScript),
+    ("Just", FileRole::BuildScript),
+    ("Dockerfile", FileRole::BuildScript),
+];
+
+/// The role of a source file in a repository
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FileRole {
+    /// Production code
+    #[default]
+    Source,
+    /// Code that only holds tests, as classified by [`crate::testcode::classify`]
+    Test,
+    /// Configuration, e.g., 'Cargo.toml' or 'webpack.config.js'
+    Config,
+    /// Scripts and definitions of the build, e.g., 'build.rs', 'Makefile' or 'setup.py'
+    BuildScript,
+    /// Prose documentation, e.g., Markdown
+    Docs,
+    /// Definitions of CI pipelines, e.g., in '.github/workflows/'
+    Ci,
+}
+impl FileRole {
+    /// All the roles, in the order they are reported
+    pub const ALL: [FileRole; 6] = [
+        FileRole::Source,
+        FileRole::Test,
+        FileRole::Config,
+        FileRole::BuildScript,
+        FileRole::Docs,
+        FileRole::Ci,
+    ];
+    /// Checks whether files of the role are code, i.e., source or test files
+    pub fn is_code(&self) -> bool {
+        matches!(self, FileRole::Source | FileRole::Test)
+    }
+}
+
+/// The [`CodeTotals`] of the source files of a role
+///
+/// #Fields:
+/// * `role` - The [`FileRole`] of the files
+/// * `totals` - The [`CodeTotals`] of the files
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoleTotals {
+    pub role: FileRole,
+    pub totals: CodeTotals,
+}
+
+/// The roll-up of a repository's source files by their [`FileRole`]
+///
+/// #Fields:
+/// * `roles` - The [`RoleTotals`] of each role with files, in the order of [`FileRole::ALL`]
+/// * `code` - The [`CodeTotals`] of the source and test files, i.e., without configuration,
+///   build scripts, documentation and CI definitions
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoleReport {
+    pub roles: Vec<RoleTotals>,
+    pub code: CodeTotals,
+}
+impl RoleReport {
+    /// Gets the [`RoleReport`] of the `source_files`, as classified by [`classify`]
+    pub fn new(source_files: &[SourceFileInfo]) -> RoleReport {
+        let mut report = RoleReport::default();
+        for role in FileRole::ALL {
+            let mut totals = CodeTotals::default();
+            for source_file in source_files.iter().filter(|file| file.role == role) {
+                totals.add(source_file);
+                if role.is_code() {
+                    report.code.add(source_file);
+                }
+            }
+            if totals.num_files > 0 {
+                report.roles.push(RoleTotals { role, totals });
+            }
+        }
+        report
+    }
+    /// Gets the [`CodeTotals`] of the files of the `role`, or `None` if there are none
+    pub fn get(&self, role: FileRole) -> Option<&CodeTotals> {
+        self.roles
+            .iter()
+            .find(|role_totals| role_totals.role == role)
+            .map(|role_totals| &role_totals.totals)
+    }
+}
+
+/// Classifies each of the `source_files` into its [`FileRole`], after they are classified as
+/// tests or not by [`crate::testcode::classify`], and tags the hidden ones, e.g., dotfiles
+pub fn classify(source_files: &mut [SourceFileInfo]) {
+    for source_file in source_files {
+        source_file.role = get_role(source_file);
+        source_file.is_hidden = paths::is_hidden(Path::new(&source_file.relative_path));
+    }
+}
+
+/// Checks whether the file at the `path`, relative to the repository, is a CI definition, by
+/// its directory, e.g., '.github/workflows', or its name, e.g., '.gitlab-ci.yml'
+pub(crate) fn is_ci_definition(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| CI_FILES.contains(&name))
+        || CI_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Gets the [`FileRole`] of the `source_file`: CI definitions by their directories and names,
+/// then tests, then by the rules of its language, e.g., 'build.rs' for Rust, and otherwise by
+/// its language, e.g., Markdown for docs
+fn get_role(source_file: &SourceFileInfo) -> FileRole {
+    let name = source_file.name.as_str();
+    if is_ci_definition(Path::new(&source_file.relative_path)) {
+        return FileRole::Ci;
+    }
+    if source_file.is_test {
+        return FileRole::Test;
+    }
+    let Some(language) = source_file.language.as_ref().map(|language| &language.name) else {
+        return FileRole::Source;
+    };
+    LANGUAGE_RULES
+        .iter()
+        .filter(|(rule_language, _)| rule_language == language)
+        .flat_map(|(_, rules)| rules.iter())
+        .find(|(pattern, _)| match pattern.strip_prefix('*') {
+            Some(suffix) => name.ends_with(suffix),
+            None => name == *pattern,
+        })
+        .or_else(|| {
+            LANGUAGE_ROLES
+                .iter()
+                .find(|(role_language, _)| role_language == language)
+        })
+        .map(|(_, role)| *role)
+        .unwrap_or_default()
+}
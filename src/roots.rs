@@ -0,0 +1,410 @@
+//! The analysis of several roots as one repository, e.g., checkouts of related repositories or
+//! the split source and test trees of one, with each file attributed to the git repository it
+//! is in, so that its churn, and the contributors, come from that repository's history
+#[cfg(feature = "git")]
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+#[cfg(feature = "git")]
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+};
+
+#[cfg(feature = "git")]
+use crate::{
+    catalog::{MetricDefinition, MetricScope, MetricUnit, MetricWindow},
+    data::{saturating_sum, StatisticsSummary},
+    derived::compute_derived_metrics,
+    duplication::{DuplicationReport, IdenticalFilesReport},
+    encoding::EncodingSummary,
+    errors::SourceCodeError,
+    export::anonymise::PathHasher,
+    messages::CommitMessageCounter,
+    options::AnalysisOptions,
+    paths::RepoRelativePath,
+    quadrant::QuadrantReport,
+    quality::MetricQuality,
+    ratio::{self, Percentage},
+    repository::{Contributor, RepositoryInfo},
+    role::RoleReport,
+    source::{ContentMode, LanguageType},
+    testcode::TestCodeReport,
+    whitespace::WhitespaceReport,
+};
+use crate::{data::Statistics, paths};
+
+/// A root of a multi-root analysis, see [`RepositoryInfo::new_multi_root`]
+///
+/// #Fields:
+/// * `path` - The path to the root, as given
+/// * `repository` - The path to the working directory of the git repository the root is in
+/// * `scope` - The subdirectory of the `repository` the root is, if it isn't its working
+///   directory, e.g., 'tests'
+/// * `prefix` - The directory the relative paths of the root's files are prefixed with, if the
+///   roots are in more than one repository, i.e., the name of the `repository`'s directory
+/// * `statistics` - The [`Statistics`] of the root, with the commits of its repository (or of
+///   its `scope`)
+/// * `num_contributors` - The number of contributors to the root
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnalysisRoot {
+    pub path: String,
+    pub repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    pub statistics: Statistics,
+    pub num_contributors: u64,
+}
+impl AnalysisRoot {
+    /// Finds the git repository the root at `path` is in
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::GitError`]) if the root isn't in a git repository
+    /// * Err([`SourceCodeError::UnsupportedRepo`]) if the repository is bare
+    #[cfg(feature = "git")]
+    fn resolve(path: &str) -> Result<AnalysisRoot, SourceCodeError> {
+        let unsupported = |reason: &str| SourceCodeError::UnsupportedRepo {
+            path: path.into(),
+            reason: reason.to_string(),
+        };
+        let repo = Repository::discover(path)?;
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| unsupported("it is a bare repository"))?;
+        let workdir = fs::canonicalize(workdir).map_err(|_| unsupported("it can't be resolved"))?;
+        let root = fs::canonicalize(path).map_err(|_| unsupported("it can't be resolved"))?;
+        let scope = paths::relative_to(&root, &workdir)
+            .map(|scope| paths::to_slash(&scope))
+            .filter(|scope| !scope.is_empty());
+        Ok(AnalysisRoot {
+            path: path.to_string(),
+            repository: workdir.to_string_lossy().to_string(),
+            scope,
+            prefix: None,
+            statistics: Statistics::new(),
+            num_contributors: 0,
+        })
+    }
+    /// Whether the file at the `relative_path`, as in the merged [`RepositoryInfo`], is one of
+    /// the root's
+    pub fn contains(&self, relative_path: &str) -> bool {
+        let relative_path = match &self.prefix {
+            Some(prefix) => match relative_path.strip_prefix(&format!("{}/", prefix)) {
+                Some(relative_path) => relative_path,
+                None => return false,
+            },
+            None => relative_path,
+        };
+        self.scope
+            .as_deref()
+            .is_none_or(|scope| paths::starts_with(Path::new(relative_path), Path::new(scope)))
+    }
+}
+
+/// Analyses each of the `roots` in its git repository, scoped to the root if it is a
+/// subdirectory, and merges the analyses into one [`RepositoryInfo`]
+#[cfg(feature = "git")]
+pub(crate) fn analyse_roots(
+    name: String,
+    roots: &[&str],
+    options: &AnalysisOptions,
+) -> Result<RepositoryInfo, SourceCodeError> {
+    let resolved: Vec<AnalysisRoot> = roots
+        .iter()
+        .map(|root| AnalysisRoot::resolve(root))
+        .collect::<Result<_, _>>()?;
+    // A root under another root of its repository, or given twice, is only analysed once, as
+    // part of the outer (or first) root
+    let contains = |outer: &AnalysisRoot, root: &AnalysisRoot| {
+        outer.repository == root.repository
+            && match (&outer.scope, &root.scope) {
+                (None, _) => true,
+                (Some(_), None) => false,
+                (Some(outer_scope), Some(scope)) => {
+                    paths::starts_with(Path::new(scope), Path::new(outer_scope))
+                }
+            }
+    };
+    let mut resolved: Vec<AnalysisRoot> = resolved
+        .iter()
+        .enumerate()
+        .filter(|(index, root)| {
+            !resolved.iter().enumerate().any(|(other_index, outer)| {
+                other_index != *index
+                    && contains(outer, root)
+                    && (!contains(root, outer) || other_index < *index)
+            })
+        })
+        .map(|(_, root)| root.clone())
+        .collect();
+    if resolved.is_empty() {
+        return Err(SourceCodeError::ConfigError(
+            "No roots given to analyse".to_string(),
+        ));
+    }
+    set_prefixes(&mut resolved);
+
+    // The contents are kept until the roots are merged, for the reports computed from them
+    let mut analyses: Vec<(AnalysisRoot, RepositoryInfo)> = Vec::with_capacity(resolved.len());
+    for mut root in resolved {
+        let mut root_options = options.clone();
+        root_options.scope = root.scope.clone();
+        root_options.content_mode = ContentMode::Full;
+        // The roots would each replace the checkpoint file of the others
+        root_options.checkpoint = None;
+        // The merged roots are anonymised, as their paths are prefixed when merged
+        root_options.anonymisation = None;
+        let mut repository_info =
+            RepositoryInfo::new_with_options(name.clone(), &root.repository, &root_options)?;
+        if let Some(prefix) = &root.prefix {
+            prefix_paths(&mut repository_info, prefix);
+        }
+        root.statistics = repository_info.statistics.clone();
+        root.num_contributors = repository_info.contributors.len() as u64;
+        analyses.push((root, repository_info));
+    }
+    merge(analyses, options)
+}
+
+/// Prefixes the paths of the files of each root with the name of its repository's directory,
+/// if the roots are in more than one repository, so that they don't collide; the roots of one
+/// repository share its paths, which are relative to its working directory
+#[cfg(feature = "git")]
+fn set_prefixes(roots: &mut [AnalysisRoot]) {
+    let repositories: BTreeSet<&str> = roots.iter().map(|root| root.repository.as_str()).collect();
+    if repositories.len() < 2 {
+        return;
+    }
+    let mut prefixes: BTreeMap<String, String> = BTreeMap::new();
+    for repository in repositories {
+        let name = Path::new(repository)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "repository".to_string());
+        let mut prefix = name.clone();
+        let mut suffix = 2;
+        while prefixes.values().any(|other| *other == prefix) {
+            prefix = format!("{}-{}", name, suffix);
+            suffix += 1;
+        }
+        prefixes.insert(repository.to_string(), prefix);
+    }
+    for root in roots {
+        root.prefix = prefixes.get(&root.repository).cloned();
+    }
+}
+
+/// Prefixes the paths of the files of the [`RepositoryInfo`] with the `prefix` directory
+#[cfg(feature = "git")]
+fn prefix_paths(repository_info: &mut RepositoryInfo, prefix: &str) {
+    let prefixed = |path: &str| format!("{}/{}", prefix, path);
+    for source_file in &mut repository_info.source_files {
+        source_file.relative_path = RepoRelativePath::from_relative(Path::new(&prefixed(
+            source_file.relative_path.as_str(),
+        )));
+    }
+    for path in &mut repository_info.unknown_files {
+        *path = prefixed(path);
+    }
+    for marker in &mut repository_info.tech_debt_markers {
+        marker.path = prefixed(&marker.path);
+    }
+    for warning in &mut repository_info.warnings {
+        warning.path = prefixed(&warning.path);
+    }
+    for path in &mut repository_info.generated_code.generated_files {
+        *path = prefixed(path);
+    }
+}
+
+/// Merges the analyses of the roots into the first: the files, and the reports of them, are
+/// combined, the statistics and organisations are summed, and the contributors are merged by
+/// their canonical id.
+/// The reports of a single checkout or history, e.g., the history itself, the releases, the
+/// coupling and the ownership, are those of the first root.
+#[cfg(feature = "git")]
+fn merge(
+    analyses: Vec<(AnalysisRoot, RepositoryInfo)>,
+    options: &AnalysisOptions,
+) -> Result<RepositoryInfo, SourceCodeError> {
+    let mut analyses = analyses.into_iter();
+    let Some((first_root, mut merged)) = analyses.next() else {
+        return Err(SourceCodeError::ConfigError(
+            "No roots given to analyse".to_string(),
+        ));
+    };
+    let mut roots: Vec<AnalysisRoot> = vec![first_root];
+    let mut contributors: Vec<Vec<Contributor>> = vec![std::mem::take(&mut merged.contributors)];
+    for (root, repository_info) in analyses {
+        merged.statistics = merged.statistics.merge_weighted(
+            &repository_info.statistics,
+            merged.statistics.num_commits as f64,
+            repository_info.statistics.num_commits as f64,
+        );
+        merged.source_files.extend(repository_info.source_files);
+        merged.unknown_files.extend(repository_info.unknown_files);
+        merged
+            .tech_debt_markers
+            .extend(repository_info.tech_debt_markers);
+        merged.warnings.extend(repository_info.warnings);
+        let generated_code = repository_info.generated_code;
+        merged.generated_code.total.merge(&generated_code.total);
+        merged
+            .generated_code
+            .generated
+            .merge(&generated_code.generated);
+        merged
+            .generated_code
+            .without_generated
+            .merge(&generated_code.without_generated);
+        merged
+            .generated_code
+            .generated_files
+            .extend(generated_code.generated_files);
+        merged.organisations.merge(&repository_info.organisations);
+        merged.history_complete &= repository_info.history_complete;
+        contributors.push(repository_info.contributors);
+        roots.push(root);
+    }
+    if !options.unsorted {
+        merged
+            .source_files
+            .sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    }
+    merged.unknown_files.sort();
+    merged.scope = None;
+
+    let source_files = &merged.source_files;
+    merged.languages = RepositoryInfo::get_languages(source_files);
+    merged.predominant_language = Some(LanguageType::get_predominant_language(&merged.languages));
+    merged.identical_files = IdenticalFilesReport::new(source_files, options.count_identical_once);
+    merged.duplication = options
+        .duplication
+        .as_ref()
+        .map(|duplication_options| DuplicationReport::new(source_files, duplication_options));
+    merged.test_code = TestCodeReport::new(source_files);
+    merged.quadrants = QuadrantReport::new(source_files, &options.quadrant_thresholds);
+    merged.roles = RoleReport::new(source_files);
+    merged.encodings = EncodingSummary::new(source_files);
+    merged.whitespace = WhitespaceReport::new(source_files);
+    merged.statistics_summary = StatisticsSummary::new(source_files);
+    merged.statistics.loc = saturating_sum(source_files.iter().map(|sfi| sfi.statistics.loc));
+    merged.statistics.size = saturating_sum(source_files.iter().map(|sfi| sfi.statistics.size));
+    merged.statistics.num_files = source_files.len() as u64;
+    if merged.identical_files.counted_once {
+        let identical_files = &merged.identical_files;
+        merged.statistics.loc = merged
+            .statistics
+            .loc
+            .saturating_sub(identical_files.duplicate_loc);
+        merged.statistics.size = merged
+            .statistics
+            .size
+            .saturating_sub(identical_files.wasted_bytes);
+    }
+    merged.contributors = merge_contributors(contributors, options.unsorted);
+
+    compute_derived_metrics(&mut merged, &options.derived_metrics)?;
+    merged.metrics_catalog = MetricDefinition::catalog(&merged, &options.derived_metrics);
+    for (path, unit, window, description) in [
+        (
+            "roots[].statistics.loc",
+            MetricUnit::Lines,
+            MetricWindow::Snapshot,
+            "The lines of code of the root",
+        ),
+        (
+            "roots[].statistics.num_commits",
+            MetricUnit::Commits,
+            MetricWindow::History,
+            "The commits of the root's repository, or of its scope",
+        ),
+    ] {
+        MetricDefinition {
+            path: path.to_string(),
+            scope: MetricScope::Repository,
+            unit,
+            window,
+            description: description.to_string(),
+        }
+        .add_to(&mut merged.metrics_catalog);
+    }
+    merged.roots = roots;
+    if let Some(metadata) = &mut merged.metadata {
+        metadata.num_warnings = merged.warnings.len() as u64;
+    }
+    merged.quality = MetricQuality::assess(&merged);
+    merged.fingerprint = Some(merged.fingerprint());
+    for source_file in &mut merged.source_files {
+        source_file.retain_contents(options.content_mode);
+    }
+    if let Some(anonymisation) = &options.anonymisation {
+        merged = PathHasher::from_options(anonymisation).anonymise("", &merged);
+    }
+    Ok(merged)
+}
+
+/// Merges the contributors of the roots by their canonical id, e.g., someone contributing to
+/// two of the repositories, with their shares of the commits of all the roots
+#[cfg(feature = "git")]
+fn merge_contributors(roots: Vec<Vec<Contributor>>, unsorted: bool) -> Vec<Contributor> {
+    let mut merged: BTreeMap<String, (Contributor, CommitMessageCounter)> = BTreeMap::new();
+    for contributor in roots.into_iter().flatten() {
+        let canonical_id = contributor.author.canonical_id.clone();
+        match merged.get_mut(&canonical_id) {
+            Some((total, commit_messages)) => {
+                // The cadence can't be merged without the dates of the commits, so it is that
+                // of the root with the most of the contributor's commits
+                if contributor.statistics.num_commits > total.statistics.num_commits {
+                    total.cadence = contributor.cadence.clone();
+                }
+                total.statistics += &contributor.statistics;
+                commit_messages.add_statistics(&contributor.commit_messages);
+                total.commit_times.merge(&contributor.commit_times);
+                if contributor.last_contribution > total.last_contribution {
+                    total.author = contributor.author;
+                    total.last_contribution = contributor.last_contribution;
+                }
+                total.first_contribution =
+                    match (total.first_contribution, contributor.first_contribution) {
+                        (Some(first), Some(other_first)) => Some(first.min(other_first)),
+                        (first, other_first) => first.or(other_first),
+                    };
+                total.is_active |= contributor.is_active;
+            }
+            None => {
+                let mut commit_messages = CommitMessageCounter::default();
+                commit_messages.add_statistics(&contributor.commit_messages);
+                merged.insert(canonical_id, (contributor, commit_messages));
+            }
+        }
+    }
+    let total_commits = saturating_sum(
+        merged
+            .values()
+            .map(|(contributor, _)| contributor.statistics.num_commits),
+    );
+    let mut contributors: Vec<Contributor> = merged
+        .into_values()
+        .map(|(mut contributor, commit_messages)| {
+            let percentage = ratio::percentage(
+                contributor.statistics.num_commits as f64,
+                total_commits as f64,
+            )
+            .unwrap_or_default();
+            contributor.percentage_contribution = percentage;
+            contributor.statistics.contribution_share = Some(Percentage(percentage));
+            contributor.commit_messages = commit_messages.get_statistics();
+            contributor
+        })
+        .collect();
+    if !unsorted {
+        contributors.sort_by(|a, b| {
+            (&a.author.name, &a.author.canonical_id).cmp(&(&b.author.name, &b.author.canonical_id))
+        });
+    }
+    contributors
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::ratio::ratio;
+
+/// The work done by an analysis, so that operators can tune the
+/// [`crate::options::AnalysisOptions`] of their repositories without external profiling. It is
+/// computed locally and never sent anywhere.
+///
+/// #Fields:
+/// * `files_scanned` - The number of source files read and counted
+/// * `files_skipped` - The number of files passed over, e.g., excluded, binary or in an
+///   undetected language
+/// * `bytes_read` - The total size of the source files read
+/// * `commits_walked` - The number of commits visited over all the walks of the history, i.e.,
+///   once for the commit graph, and once more for the baseline if used
+/// * `cache_hits` - The number of source files whose change frequency was reused from a baseline
+/// * `cache_misses` - The number of source files not in the baseline, whose change frequency was
+///   computed from the commit graph
+/// * `cache_hit_rate` - The `cache_hits` as a share (0.0 - 1.0) of the source files, or `None` if
+///   the analysis had no baseline
+/// * `peak_memory_bytes` - The peak resident memory of the process, if known (only on Linux);
+///   it includes anything else the process did before or during the analysis
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RunStats {
+    pub files_scanned: u64,
+    pub files_skipped: u64,
+    pub bytes_read: u64,
+    pub commits_walked: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub cache_hit_rate: Option<f32>,
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// The counters of the work done by an analysis, updated as it runs
+#[derive(Debug, Default)]
+pub(crate) struct RunCounters {
+    files_scanned: AtomicU64,
+    files_skipped: AtomicU64,
+    bytes_read: AtomicU64,
+    commits_walked: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+impl RunCounters {
+    /// Counts a source file of `bytes` read
+    pub(crate) fn add_scanned(&self, bytes: u64) {
+        self.files_scanned.fetch_add(1, Ordering::Relaxed);
+        Self::saturating_add(&self.bytes_read, bytes);
+    }
+    pub(crate) fn add_skipped(&self) {
+        self.files_skipped.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn add_commits_walked(&self, commits: u64) {
+        Self::saturating_add(&self.commits_walked, commits);
+    }
+    /// Adds the `value` to the `counter`, saturating at `u64::MAX` rather than wrapping
+    fn saturating_add(counter: &AtomicU64, value: u64) {
+        // The closure always returns `Some`, so the update can't fail
+        let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            Some(count.saturating_add(value))
+        });
+    }
+    /// Counts a source file whose change frequency was reused from a baseline (`hit`) or not
+    pub(crate) fn add_cache_lookup(&self, hit: bool) {
+        match hit {
+            true => self.cache_hits.fetch_add(1, Ordering::Relaxed),
+            false => self.cache_misses.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+    /// Gets the [`RunStats`] of the work counted so far
+    pub(crate) fn get_run_stats(&self) -> RunStats {
+        let cache_hits = self.cache_hits.load(Ordering::Relaxed);
+        let cache_misses = self.cache_misses.load(Ordering::Relaxed);
+        RunStats {
+            files_scanned: self.files_scanned.load(Ordering::Relaxed),
+            files_skipped: self.files_skipped.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            commits_walked: self.commits_walked.load(Ordering::Relaxed),
+            cache_hits,
+            cache_misses,
+            cache_hit_rate: ratio(
+                cache_hits as f64,
+                cache_hits.saturating_add(cache_misses) as f64,
+            ),
+            peak_memory_bytes: get_peak_memory(),
+        }
+    }
+}
+
+/// Gets the peak resident memory (high water mark) of the process from '/proc/self/status'
+#[cfg(target_os = "linux")]
+fn get_peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes.saturating_mul(1024))
+}
+#[cfg(not(target_os = "linux"))]
+fn get_peak_memory() -> Option<u64> {
+    None
+}
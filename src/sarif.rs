@@ -0,0 +1,602 @@
+//! The findings of static analysis tools, e.g., clippy or semgrep, read from SARIF logs and
+//! counted on the source files by severity, so that churn and defect-prone files can be
+//! correlated with the noise of static analysis
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use crate::{
+    catalog::{MetricDefinition, MetricScope, MetricUnit, MetricWindow},
+    data::{saturating_sum, to_count},
+    errors::SourceCodeError,
+    paths::ReportPathMatcher,
+    repository::RepositoryInfo,
+};
+
+/// The severity of a [`Finding`], as the SARIF `level` of its result
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FindingLevel {
+    /// A serious problem, e.g., a compile error or a vulnerability
+    Error,
+    /// A problem, the default level of a result
+    #[default]
+    Warning,
+    /// A minor problem or an opportunity for improvement
+    Note,
+    /// A result that isn't a problem, e.g., a passing check
+    None,
+}
+impl FindingLevel {
+    /// All the levels, from the most severe
+    pub const ALL: [FindingLevel; 4] = [
+        FindingLevel::Error,
+        FindingLevel::Warning,
+        FindingLevel::Note,
+        FindingLevel::None,
+    ];
+    /// Gets the level of its SARIF name, e.g., 'error'
+    fn from_sarif(level: &str) -> Option<FindingLevel> {
+        match level {
+            "error" => Some(FindingLevel::Error),
+            "warning" => Some(FindingLevel::Warning),
+            "note" => Some(FindingLevel::Note),
+            "none" => Some(FindingLevel::None),
+            _ => None,
+        }
+    }
+}
+
+/// The number of [`Finding`]s of each [`FindingLevel`]
+///
+/// #Fields:
+/// * `error` - The number of errors
+/// * `warning` - The number of warnings
+/// * `note` - The number of notes
+/// * `none` - The number of results that aren't problems
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct FindingCounts {
+    pub error: u64,
+    pub warning: u64,
+    pub note: u64,
+    pub none: u64,
+}
+impl FindingCounts {
+    /// Counts a finding of the `level`
+    pub fn add(&mut self, level: FindingLevel) {
+        match level {
+            FindingLevel::Error => self.error += 1,
+            FindingLevel::Warning => self.warning += 1,
+            FindingLevel::Note => self.note += 1,
+            FindingLevel::None => self.none += 1,
+        }
+    }
+    /// Adds the `other` counts
+    pub fn add_counts(&mut self, other: &FindingCounts) {
+        self.error = self.error.saturating_add(other.error);
+        self.warning = self.warning.saturating_add(other.warning);
+        self.note = self.note.saturating_add(other.note);
+        self.none = self.none.saturating_add(other.none);
+    }
+    /// Gets the number of findings that are problems, i.e., errors, warnings and notes
+    pub fn num_problems(&self) -> u64 {
+        saturating_sum([self.error, self.warning, self.note])
+    }
+}
+
+/// A result of a static analysis tool in a SARIF log
+///
+/// #Fields:
+/// * `tool` - The name of the tool, e.g., 'clippy'
+/// * `rule_id` - The id of the rule, e.g., 'clippy::unwrap_used', if any
+/// * `level` - The [`FindingLevel`], from the result or else the default of its rule
+/// * `path` - The path of the file of its first location, resolved against its base, or `None`
+///   if it has no file, e.g., a finding on the whole project
+/// * `line` - The line the finding starts on, if known
+/// * `message` - The message of the finding
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Finding {
+    pub tool: String,
+    pub rule_id: Option<String>,
+    pub level: FindingLevel,
+    pub path: Option<String>,
+    pub line: Option<i64>,
+    pub message: String,
+}
+
+/// The [`Finding`]s of a SARIF log, of all its runs
+///
+/// #Fields:
+/// * `findings` - The [`Finding`]s, in the order of the log
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SarifReport {
+    pub findings: Vec<Finding>,
+}
+impl SarifReport {
+    /// Reads the SARIF log at `path`
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::SerializationError`]) if it isn't a SARIF log
+    pub fn read(path: &Path) -> Result<SarifReport, SourceCodeError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+    /// Parses the `contents` of a SARIF (2.1.0) log, e.g., from `clippy-sarif` or
+    /// `semgrep --sarif`
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::SerializationError`]) if it isn't a SARIF log
+    pub fn parse(contents: &str) -> Result<SarifReport, SourceCodeError> {
+        let log: SarifLog = serde_json::from_str(contents.trim_start_matches('\u{feff}'))
+            .map_err(|err| SourceCodeError::SerializationError(err.into()))?;
+        let mut findings: Vec<Finding> = Vec::new();
+        for run in log.runs {
+            let tool = run.tool.driver.name;
+            let rules = &run.tool.driver.rules;
+            let bases: HashMap<&str, &str> = run
+                .original_uri_base_ids
+                .iter()
+                .filter_map(|(id, base)| Some((id.as_str(), base.uri.as_deref()?)))
+                .collect();
+            for result in run.results {
+                let rule = result
+                    .rule_index
+                    .and_then(|index| rules.get(index))
+                    .or_else(|| {
+                        let rule_id = result.rule_id.as_deref()?;
+                        rules.iter().find(|rule| rule.id == rule_id)
+                    });
+                let level = match result.kind.as_deref() {
+                    // Only failures are problems; passes and informational results aren't
+                    Some(kind) if kind != "fail" => FindingLevel::None,
+                    _ => result
+                        .level
+                        .as_deref()
+                        .or(rule.and_then(|rule| rule.default_configuration.level.as_deref()))
+                        .and_then(FindingLevel::from_sarif)
+                        .unwrap_or_default(),
+                };
+                let location = result
+                    .locations
+                    .first()
+                    .map(|location| &location.physical_location);
+                let path = location
+                    .and_then(|location| location.artifact_location.uri.as_deref())
+                    .map(|uri| {
+                        let base = location
+                            .and_then(|location| location.artifact_location.uri_base_id.as_deref())
+                            .and_then(|id| bases.get(id));
+                        resolve_uri(uri, base.copied())
+                    });
+                findings.push(Finding {
+                    tool: tool.clone(),
+                    rule_id: result
+                        .rule_id
+                        .clone()
+                        .or_else(|| rule.map(|rule| rule.id.clone())),
+                    level,
+                    path,
+                    line: location.and_then(|location| location.region.start_line),
+                    message: result.message.text.unwrap_or_default(),
+                });
+            }
+        }
+        Ok(SarifReport { findings })
+    }
+}
+
+/// The outcome of applying a [`SarifReport`] to a [`RepositoryInfo`]
+///
+/// #Fields:
+/// * `num_files` - The number of source files with findings
+/// * `findings` - The [`FindingCounts`] of the findings on those files
+/// * `by_tool` - The [`FindingCounts`] of those findings, by the name of their tool
+/// * `unmatched` - The paths of the findings' files that aren't source files of the repository,
+///   e.g., generated files, each once
+/// * `num_without_file` - The number of findings without a file
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FindingsSummary {
+    pub num_files: u64,
+    pub findings: FindingCounts,
+    pub by_tool: BTreeMap<String, FindingCounts>,
+    pub unmatched: Vec<String>,
+    pub num_without_file: u64,
+}
+
+/// Counts the findings of the `report` on the statistics of the source files of the
+/// `repository_info` of the repository at `repo_path`, by [`FindingLevel`], and their total on
+/// the repository's statistics, adding the metrics to its catalog. The files of the findings
+/// are matched as those of a [`crate::coverage::CoverageReport`] are; applying a report again
+/// adds its findings to the counts.
+pub fn apply(
+    repository_info: &mut RepositoryInfo,
+    repo_path: &str,
+    report: &SarifReport,
+) -> FindingsSummary {
+    let matches: Vec<Option<usize>> = {
+        let relative_paths = repository_info
+            .source_files
+            .iter()
+            .map(|source_file| source_file.relative_path.as_str())
+            .collect();
+        let matcher = ReportPathMatcher::new(relative_paths, repo_path);
+        report
+            .findings
+            .iter()
+            .map(|finding| {
+                let path = finding.path.as_deref()?;
+                matcher.find(path, &[])
+            })
+            .collect()
+    };
+    let mut summary = FindingsSummary::default();
+    let mut counts_by_index: BTreeMap<usize, FindingCounts> = BTreeMap::new();
+    for (finding, index) in report.findings.iter().zip(matches) {
+        match (index, &finding.path) {
+            (Some(index), _) => {
+                counts_by_index.entry(index).or_default().add(finding.level);
+                summary.findings.add(finding.level);
+                summary
+                    .by_tool
+                    .entry(finding.tool.clone())
+                    .or_default()
+                    .add(finding.level);
+            }
+            (None, Some(path)) => summary.unmatched.push(path.clone()),
+            (None, None) => summary.num_without_file += 1,
+        }
+    }
+    summary.unmatched.sort();
+    summary.unmatched.dedup();
+    summary.num_files = to_count(counts_by_index.len());
+
+    for (index, counts) in counts_by_index {
+        repository_info.source_files[index]
+            .statistics
+            .findings
+            .get_or_insert_with(FindingCounts::default)
+            .add_counts(&counts);
+    }
+    repository_info
+        .statistics
+        .findings
+        .get_or_insert_with(FindingCounts::default)
+        .add_counts(&summary.findings);
+    for level in FindingLevel::ALL {
+        let name = serde_json::to_value(level)
+            .ok()
+            .and_then(|name| name.as_str().map(String::from))
+            .unwrap_or_default();
+        for (path, scope, subject) in [
+            (
+                "statistics.findings",
+                MetricScope::Repository,
+                "the source files",
+            ),
+            (
+                "source_files[].statistics.findings",
+                MetricScope::SourceFile,
+                "the source file",
+            ),
+        ] {
+            MetricDefinition {
+                path: format!("{}.{}", path, name),
+                scope,
+                unit: MetricUnit::Number,
+                window: MetricWindow::Snapshot,
+                description: format!(
+                    "The number of static analysis findings of level '{}' on {}",
+                    name, subject
+                ),
+            }
+            .add_to(&mut repository_info.metrics_catalog);
+        }
+    }
+    summary
+}
+
+/// Resolves the `uri` of an artifact of a SARIF log to a path, against the `base` URI of its
+/// `uriBaseId`, if any: a 'file:' URI becomes its (percent-decoded) path
+fn resolve_uri(uri: &str, base: Option<&str>) -> String {
+    let to_path = |uri: &str| -> String {
+        let path = uri
+            .strip_prefix("file://")
+            .or_else(|| uri.strip_prefix("file:"))
+            .unwrap_or(uri);
+        // A Windows drive letter in a URI is preceded by a slash, e.g., 'file:///C:/src'
+        let path = match path.as_bytes() {
+            [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &path[1..],
+            _ => path,
+        };
+        percent_decode(path)
+    };
+    let path = to_path(uri);
+    match base {
+        Some(base) if !Path::new(&path).is_absolute() => {
+            format!("{}/{}", to_path(base).trim_end_matches('/'), path)
+        }
+        _ => path,
+    }
+}
+
+/// Decodes the percent-encoded bytes of the `text`, e.g., '%20' as a space
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// The parts of a SARIF log that findings are read from
+#[derive(Deserialize)]
+struct SarifLog {
+    #[serde(default)]
+    runs: Vec<SarifRun>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRun {
+    tool: SarifTool,
+    #[serde(default)]
+    original_uri_base_ids: HashMap<String, SarifArtifactLocation>,
+    #[serde(default)]
+    results: Vec<SarifResult>,
+}
+
+#[derive(Deserialize)]
+struct SarifTool {
+    driver: SarifDriver,
+}
+
+#[derive(Deserialize)]
+struct SarifDriver {
+    name: String,
+    #[serde(default)]
+    rules: Vec<SarifRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRule {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    default_configuration: SarifConfiguration,
+}
+
+#[derive(Deserialize, Default)]
+struct SarifConfiguration {
+    level: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: Option<String>,
+    rule_index: Option<usize>,
+    kind: Option<String>,
+    level: Option<String>,
+    #[serde(default)]
+    message: SarifMessage,
+    #[serde(default)]
+    locations: Vec<SarifLocation>,
+}
+
+#[derive(Deserialize, Default)]
+struct SarifMessage {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLocation {
+    #[serde(default)]
+    physical_location: SarifPhysicalLocation,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SarifPhysicalLocation {
+    #[serde(default)]
+    artifact_location: SarifArtifactLocation,
+    #[serde(default)]
+    region: SarifRegion,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SarifArtifactLocation {
+    #[serde(default)]
+    uri: Option<String>,
+    uri_base_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SarifRegion {
+    start_line: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "git")]
+    use crate::{options::AnalysisOptions, testing::FixtureRepo};
+
+    const SARIF: &str = include_str!("../tests/fixtures/sarif/findings.sarif");
+
+    /// The tool, rule id, level, path and line of a [`Finding`]
+    type FindingFields<'a> = (
+        &'a str,
+        Option<&'a str>,
+        FindingLevel,
+        Option<&'a str>,
+        Option<i64>,
+    );
+
+    #[test]
+    fn reads_the_findings_of_all_the_runs() -> Result<(), SourceCodeError> {
+        let report = SarifReport::read(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sarif/findings.sarif"),
+        )?;
+        let findings: Vec<FindingFields> = report
+            .findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.tool.as_str(),
+                    finding.rule_id.as_deref(),
+                    finding.level,
+                    finding.path.as_deref(),
+                    finding.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    "clippy",
+                    Some("clippy::unwrap_used"),
+                    FindingLevel::Warning,
+                    Some("/build/checkout/src/lib.rs"),
+                    Some(2)
+                ),
+                (
+                    "clippy",
+                    Some("clippy::panic"),
+                    FindingLevel::Error,
+                    Some("/build/checkout/src/main.rs"),
+                    Some(2)
+                ),
+                (
+                    "clippy",
+                    Some("clippy::panic"),
+                    FindingLevel::Note,
+                    Some("/build/checkout/src/main.rs"),
+                    Some(3)
+                ),
+                (
+                    "clippy",
+                    Some("clippy::unwrap_used"),
+                    FindingLevel::None,
+                    Some("/build/checkout/src/lib.rs"),
+                    None
+                ),
+                (
+                    "clippy",
+                    Some("clippy::missing_docs_in_crate"),
+                    FindingLevel::Warning,
+                    None,
+                    None
+                ),
+                (
+                    "semgrep",
+                    Some("rust.lang.security.unsafe-usage"),
+                    FindingLevel::Warning,
+                    Some("/build/checkout/src/lib.rs"),
+                    Some(1)
+                ),
+                (
+                    "semgrep",
+                    Some("generic.secrets.generic-api-key"),
+                    FindingLevel::Error,
+                    Some("generated/keys.rs"),
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            report.findings[0].message,
+            "used `unwrap()` on a `Result` value"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_a_log_that_isnt_sarif() {
+        assert!(matches!(
+            SarifReport::parse("<sarif/>"),
+            Err(SourceCodeError::SerializationError(_))
+        ));
+        assert_eq!(SarifReport::parse("{}").ok(), Some(SarifReport::default()));
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn counts_the_findings_on_the_source_files() -> Result<(), SourceCodeError> {
+        let fixture = FixtureRepo::new()?
+            .with_file("src/lib.rs", "pub fn one() -> i32 {\n    1\n}\n")
+            .with_file("src/main.rs", "fn main() {\n    panic!();\n}\n")
+            .commit("Add the library and binary")?;
+        let mut repository_info = fixture.analyse(&AnalysisOptions::default())?;
+        let repo_path = fixture.path().to_string_lossy();
+        let report = SarifReport::parse(SARIF)?;
+        let summary = apply(&mut repository_info, &repo_path, &report);
+        assert_eq!(summary.num_files, 2);
+        assert_eq!(
+            summary.findings,
+            FindingCounts {
+                error: 1,
+                warning: 2,
+                note: 1,
+                none: 1,
+            }
+        );
+        assert_eq!(
+            summary.by_tool.get("semgrep"),
+            Some(&FindingCounts {
+                warning: 1,
+                ..FindingCounts::default()
+            })
+        );
+        assert_eq!(summary.unmatched, vec!["generated/keys.rs"]);
+        assert_eq!(summary.num_without_file, 1);
+        let findings = |repository_info: &RepositoryInfo, path: &str| {
+            repository_info
+                .source_files
+                .iter()
+                .find(|source_file| source_file.relative_path.as_str() == path)
+                .and_then(|source_file| source_file.statistics.findings)
+        };
+        let lib = findings(&repository_info, "src/lib.rs");
+        assert_eq!(
+            lib.map(|counts| (counts.warning, counts.none)),
+            Some((2, 1))
+        );
+        assert_eq!(lib.map(|counts| counts.num_problems()), Some(2));
+        let main = findings(&repository_info, "src/main.rs");
+        assert_eq!(main.map(|counts| (counts.error, counts.note)), Some((1, 1)));
+        assert_eq!(repository_info.statistics.findings, Some(summary.findings));
+
+        // Applying the report again adds its findings to the counts
+        apply(&mut repository_info, &repo_path, &report);
+        let lib = findings(&repository_info, "src/lib.rs");
+        assert_eq!(lib.map(|counts| counts.warning), Some(4));
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{repository::RepositoryInfo, source::SourceFileInfo};
+
+/// The lines of code at (and above) which a file counts as fully complex, as lines of code are
+/// the complexity measure
+const COMPLEX_LOC: f32 = 1000.0;
+/// The days without a change at (and above) which a file, or repository, counts as fully stale
+const STALE_DAYS: f32 = 365.0;
+
+/// The signals a health score is computed from, each from 0.0 (healthy) to 1.0 (unhealthy)
+///
+/// #Fields:
+/// * `churn` - The commits changing the file, relative to the 90th percentile of the files of
+///   the repository; for the repository, the median of its files
+/// * `complexity` - The lines of code of the file, relative to 1000 lines; for the repository,
+///   the median of its files
+/// * `bus_factor` - The inverse of the bus factor of the repository, i.e., of the fewest
+///   contributors that authored over half of its commits; the same for each file
+/// * `test_ratio` - The lack of tests: one less the test-to-code ratio, capped at one, of the
+///   repository; 0.0 for test files
+/// * `staleness` - The days since the file, or repository, last changed, relative to a year
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HealthSignals {
+    pub churn: f32,
+    pub complexity: f32,
+    pub bus_factor: f32,
+    pub test_ratio: f32,
+    pub staleness: f32,
+}
+
+/// The weight of each of the [`HealthSignals`] in a health score, relative to the others
+///
+/// #Fields:
+/// * `churn` - The weight of the churn
+/// * `complexity` - The weight of the complexity
+/// * `bus_factor` - The weight of the bus factor
+/// * `test_ratio` - The weight of the lack of tests
+/// * `staleness` - The weight of the staleness
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct HealthWeights {
+    pub churn: f32,
+    pub complexity: f32,
+    pub bus_factor: f32,
+    pub test_ratio: f32,
+    pub staleness: f32,
+}
+impl Default for HealthWeights {
+    fn default() -> Self {
+        Self {
+            churn: 0.3,
+            complexity: 0.25,
+            bus_factor: 0.2,
+            test_ratio: 0.15,
+            staleness: 0.1,
+        }
+    }
+}
+
+/// A model that scores the health of a source file, or repository, from its [`HealthSignals`],
+/// e.g., with the weights an organisation has tuned. [`HealthWeights`] is the default model.
+pub trait ScoringModel {
+    /// Gets the [`HealthWeights`] of the signals
+    fn weights(&self) -> HealthWeights;
+    /// Scores the `signals` from 0 (unhealthy) to 100 (healthy); by default, 100 less the
+    /// weighted mean of the signals, as a percentage
+    fn score(&self, signals: &HealthSignals) -> f32 {
+        let weights = self.weights();
+        let weighted = [
+            (weights.churn, signals.churn),
+            (weights.complexity, signals.complexity),
+            (weights.bus_factor, signals.bus_factor),
+            (weights.test_ratio, signals.test_ratio),
+            (weights.staleness, signals.staleness),
+        ];
+        let total_weight: f32 = weighted.iter().map(|(weight, _)| weight.max(0.0)).sum();
+        if total_weight == 0.0 {
+            return 100.0;
+        }
+        let risk: f32 = weighted
+            .iter()
+            .map(|(weight, signal)| weight.max(0.0) * signal.clamp(0.0, 1.0))
+            .sum();
+        100.0 * (1.0 - risk / total_weight)
+    }
+}
+impl ScoringModel for HealthWeights {
+    fn weights(&self) -> HealthWeights {
+        self.clone()
+    }
+}
+
+/// The health of a source file
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `score` - The health score, from 0 (unhealthy) to 100 (healthy)
+/// * `signals` - The [`HealthSignals`] the score is computed from
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileHealth {
+    pub path: String,
+    pub score: f32,
+    pub signals: HealthSignals,
+}
+
+/// The health of a repository and of each of its source files, from a [`ScoringModel`]
+///
+/// #Fields:
+/// * `score` - The health score of the repository, from 0 (unhealthy) to 100 (healthy)
+/// * `signals` - The [`HealthSignals`] of the repository
+/// * `files` - The [`FileHealth`] of each source file, least healthy first
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HealthReport {
+    pub score: f32,
+    pub signals: HealthSignals,
+    pub files: Vec<FileHealth>,
+}
+impl HealthReport {
+    /// Scores the health of the repository of the `repository_info`, and of its source files,
+    /// with the `model`, e.g., [`HealthWeights::default`]
+    pub fn new(repository_info: &RepositoryInfo, model: &dyn ScoringModel) -> HealthReport {
+        let bus_factor = Self::get_bus_factor(repository_info);
+        let bus_factor = match bus_factor {
+            0 => 0.0,
+            bus_factor => 1.0 / bus_factor as f32,
+        };
+        let test_ratio = 1.0
+            - repository_info
+                .test_code
+                .repository
+                .ratio
+                .unwrap_or_default()
+                .min(1.0);
+        let p90_churn = repository_info.statistics_summary.churn.p90 as f32;
+
+        let mut files: Vec<FileHealth> = repository_info
+            .source_files
+            .iter()
+            .map(|source_file| {
+                let signals = HealthSignals {
+                    churn: Self::relative(source_file.statistics.num_commits as f32, p90_churn),
+                    complexity: Self::relative(source_file.statistics.loc as f32, COMPLEX_LOC),
+                    bus_factor,
+                    test_ratio: match source_file.is_test {
+                        true => 0.0,
+                        false => test_ratio,
+                    },
+                    staleness: Self::get_staleness(source_file),
+                };
+                FileHealth {
+                    path: source_file.relative_path.to_string(),
+                    score: model.score(&signals),
+                    signals,
+                }
+            })
+            .collect();
+        files.sort_by(|a, b| {
+            a.score
+                .total_cmp(&b.score)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        let median = |signal: fn(&HealthSignals) -> f32| {
+            let mut values: Vec<f32> = files.iter().map(|file| signal(&file.signals)).collect();
+            values.sort_by(f32::total_cmp);
+            match values.len() {
+                0 => 0.0,
+                len if len % 2 == 0 => (values[len / 2 - 1] + values[len / 2]) / 2.0,
+                len => values[len / 2],
+            }
+        };
+        let staleness = match (&repository_info.history, &repository_info.metadata) {
+            (Some(history), Some(metadata)) => Self::relative(
+                (metadata.started_at - history.last_commit)
+                    .num_days()
+                    .max(0) as f32,
+                STALE_DAYS,
+            ),
+            _ => 0.0,
+        };
+        let signals = HealthSignals {
+            churn: median(|signals| signals.churn),
+            complexity: median(|signals| signals.complexity),
+            bus_factor,
+            test_ratio,
+            staleness,
+        };
+        HealthReport {
+            score: model.score(&signals),
+            signals,
+            files,
+        }
+    }
+    /// Gets the bus factor of the repository, i.e., the fewest contributors that authored over
+    /// half of its commits, or 0 if it has no contributors
+    pub(crate) fn get_bus_factor(repository_info: &RepositoryInfo) -> usize {
+        let mut percentages: Vec<f32> = repository_info
+            .contributors
+            .iter()
+            .map(|contributor| contributor.percentage_contribution)
+            .collect();
+        percentages.sort_by(|a, b| b.total_cmp(a));
+        let mut total = 0.0;
+        for (index, percentage) in percentages.iter().enumerate() {
+            total += percentage;
+            if total > 50.0 {
+                return index + 1;
+            }
+        }
+        percentages.len()
+    }
+    /// Gets the staleness of the `source_file`, or 0.0 if it has no history
+    fn get_staleness(source_file: &SourceFileInfo) -> f32 {
+        source_file
+            .days_since_last_change
+            .map(|days| Self::relative(days as f32, STALE_DAYS))
+            .unwrap_or_default()
+    }
+    /// Gets `value` relative to `limit`, from 0.0 to 1.0, or 0.0 if the `limit` isn't positive
+    fn relative(value: f32, limit: f32) -> f32 {
+        match limit > 0.0 {
+            true => (value / limit).clamp(0.0, 1.0),
+            false => 0.0,
+        }
+    }
+}
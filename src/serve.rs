@@ -0,0 +1,290 @@
+//! An HTTP API over stored [`RepositoryInfo`] snapshots, so that dashboards can query the
+//! results of an analysis without running it again:
+//! * `GET /repositories/{name}` - The [`RepositorySummary`] of the latest snapshot
+//! * `GET /repositories/{name}/files` - The [`FileSummary`]s of its source files, filtered by
+//!   the [`FileFilters`] query parameters, e.g., `?language=rust&min_loc=500`
+//! * `GET /repositories/{name}/contributors` - Its [`Contributor`]s
+//! * `GET /repositories/{name}/hotspots` - The [`FileSummary`]s of the `?n=` (default 10)
+//!   source files with the most churn
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    data::{Statistics, StatisticsSummary},
+    errors::SourceCodeError,
+    history::CommitHistory,
+    repository::{Contributor, RepositoryInfo},
+    source::{LanguageType, SourceFileInfo},
+};
+
+/// The number of hotspots returned if the request doesn't set `n`
+const DEFAULT_NUM_HOTSPOTS: usize = 10;
+
+/// A store of [`RepositoryInfo`] snapshots that the API serves the latest of, by name
+pub trait SnapshotSource: Send + Sync {
+    /// Gets the latest snapshot of the repository `name`, if there is one
+    fn latest(&self, name: &str) -> Result<Option<RepositoryInfo>, SourceCodeError>;
+}
+
+/// An in-memory [`SnapshotSource`], e.g., of the results of analyses in the same process
+#[derive(Default)]
+pub struct MemorySnapshots {
+    snapshots: RwLock<HashMap<String, Arc<RepositoryInfo>>>,
+}
+impl MemorySnapshots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Keeps the `repository_info` as the latest snapshot of its repository
+    pub fn insert(&self, repository_info: RepositoryInfo) {
+        if let Ok(mut snapshots) = self.snapshots.write() {
+            snapshots.insert(repository_info.name.clone(), Arc::new(repository_info));
+        }
+    }
+}
+impl SnapshotSource for MemorySnapshots {
+    fn latest(&self, name: &str) -> Result<Option<RepositoryInfo>, SourceCodeError> {
+        let snapshots = self
+            .snapshots
+            .read()
+            .map_err(|err| SourceCodeError::ServeError(anyhow::anyhow!(err.to_string())))?;
+        Ok(snapshots
+            .get(name)
+            .map(|repository_info| repository_info.as_ref().clone()))
+    }
+}
+#[cfg(feature = "sqlite")]
+impl SnapshotSource for std::sync::Mutex<crate::store::sqlite::SqliteStore> {
+    fn latest(&self, name: &str) -> Result<Option<RepositoryInfo>, SourceCodeError> {
+        self.lock()
+            .map_err(|err| SourceCodeError::ServeError(anyhow::anyhow!(err.to_string())))?
+            .load_latest(name)
+    }
+}
+
+/// The summary of a repository, without its source files and contributors
+///
+/// #Fields:
+/// * `name` - The name of the repository
+/// * `predominant_language` - The [`LanguageType`] with the most lines of code
+/// * `languages` - The [`LanguageType`]s of the source files
+/// * `statistics` - The [`Statistics`] on the repository
+/// * `statistics_summary` - The [`StatisticsSummary`] of the source files
+/// * `history` - The [`CommitHistory`] of the repository, if it has commits
+/// * `num_source_files` - The number of source files
+/// * `num_contributors` - The number of contributors
+/// * `analysed_at` - The time the analysis started, if known
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RepositorySummary {
+    pub name: String,
+    pub predominant_language: Option<LanguageType>,
+    pub languages: Vec<LanguageType>,
+    pub statistics: Statistics,
+    pub statistics_summary: StatisticsSummary,
+    pub history: Option<CommitHistory>,
+    pub num_source_files: usize,
+    pub num_contributors: usize,
+    pub analysed_at: Option<DateTime<Utc>>,
+}
+impl From<&RepositoryInfo> for RepositorySummary {
+    fn from(repository_info: &RepositoryInfo) -> Self {
+        Self {
+            name: repository_info.name.clone(),
+            predominant_language: repository_info.predominant_language.clone(),
+            languages: repository_info.languages.clone(),
+            statistics: repository_info.statistics.clone(),
+            statistics_summary: repository_info.statistics_summary.clone(),
+            history: repository_info.history.clone(),
+            num_source_files: repository_info.source_files.len(),
+            num_contributors: repository_info.contributors.len(),
+            analysed_at: repository_info
+                .metadata
+                .as_ref()
+                .map(|metadata| metadata.started_at),
+        }
+    }
+}
+
+/// A source file, without its contents
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `language` - The name of the language of the file
+/// * `is_test` - Whether the file only holds tests
+/// * `statistics` - The [`Statistics`] on the file
+/// * `last_commit_date` - The time of the latest commit that changed the file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FileSummary {
+    pub path: String,
+    pub language: Option<String>,
+    pub is_test: bool,
+    pub statistics: Statistics,
+    pub last_commit_date: Option<DateTime<Utc>>,
+}
+impl From<&SourceFileInfo> for FileSummary {
+    fn from(source_file: &SourceFileInfo) -> Self {
+        Self {
+            path: source_file.relative_path.to_string(),
+            language: source_file
+                .language
+                .as_ref()
+                .map(|language| language.name.clone()),
+            is_test: source_file.is_test,
+            statistics: source_file.statistics.clone(),
+            last_commit_date: source_file.last_commit_date,
+        }
+    }
+}
+
+/// The query parameters that filter the source files, as [`crate::query::FileQuery`] does
+///
+/// #Fields:
+/// * `language` - The name of the language, ignoring case
+/// * `min_loc` - The fewest lines of code
+/// * `max_loc` - The most lines of code
+/// * `changed_since` - The earliest time of the last change, e.g., '2024-01-01T00:00:00Z'
+/// * `under` - The path the files are under, e.g., 'src/api'
+/// * `tests` - Whether to select only the test files (`true`) or only the production files
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct FileFilters {
+    pub language: Option<String>,
+    pub min_loc: Option<u64>,
+    pub max_loc: Option<u64>,
+    pub changed_since: Option<DateTime<Utc>>,
+    pub under: Option<String>,
+    pub tests: Option<bool>,
+}
+
+/// The query parameters of the hotspots
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct HotspotParameters {
+    /// The number of hotspots
+    pub n: Option<usize>,
+}
+
+/// Builds the [`Router`] of the API over the snapshots of the `source`
+pub fn router(source: Arc<dyn SnapshotSource>) -> Router {
+    Router::new()
+        .route("/repositories/:name", get(get_summary))
+        .route("/repositories/:name/files", get(get_files))
+        .route("/repositories/:name/contributors", get(get_contributors))
+        .route("/repositories/:name/hotspots", get(get_hotspots))
+        .with_state(source)
+}
+
+/// Serves the API over the snapshots of the `source` at the `address`, e.g., '127.0.0.1:8080',
+/// until the server fails
+pub async fn serve(
+    address: SocketAddr,
+    source: Arc<dyn SnapshotSource>,
+) -> Result<(), SourceCodeError> {
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    tracing::info!("serving repository statistics at {}", address);
+    axum::serve(listener, router(source))
+        .await
+        .map_err(|err| SourceCodeError::ServeError(err.into()))
+}
+
+/// An error response of the API
+enum ApiError {
+    NotFound(String),
+    Internal(SourceCodeError),
+}
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::NotFound(name) => (
+                StatusCode::NOT_FOUND,
+                format!("No snapshot of repository '{}'", name),
+            )
+                .into_response(),
+            ApiError::Internal(err) => {
+                tracing::error!("Failed to serve a request: {}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+            }
+        }
+    }
+}
+
+/// Gets the latest snapshot of the repository `name` from the `source`
+fn latest(source: &dyn SnapshotSource, name: &str) -> Result<RepositoryInfo, ApiError> {
+    source
+        .latest(name)
+        .map_err(ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound(name.to_string()))
+}
+
+async fn get_summary(
+    State(source): State<Arc<dyn SnapshotSource>>,
+    Path(name): Path<String>,
+) -> Result<Json<RepositorySummary>, ApiError> {
+    let repository_info = latest(source.as_ref(), &name)?;
+    Ok(Json(RepositorySummary::from(&repository_info)))
+}
+
+async fn get_files(
+    State(source): State<Arc<dyn SnapshotSource>>,
+    Path(name): Path<String>,
+    Query(filters): Query<FileFilters>,
+) -> Result<Json<Vec<FileSummary>>, ApiError> {
+    let repository_info = latest(source.as_ref(), &name)?;
+    let mut query = repository_info.files();
+    if let Some(language) = &filters.language {
+        query = query.language(language);
+    }
+    if let Some(min_loc) = filters.min_loc {
+        query = query.min_loc(min_loc);
+    }
+    if let Some(max_loc) = filters.max_loc {
+        query = query.max_loc(max_loc);
+    }
+    if let Some(changed_since) = filters.changed_since {
+        query = query.changed_since(changed_since);
+    }
+    if let Some(under) = &filters.under {
+        query = query.under(under);
+    }
+    if let Some(tests) = filters.tests {
+        query = query.tests(tests);
+    }
+    Ok(Json(
+        query.collect().into_iter().map(FileSummary::from).collect(),
+    ))
+}
+
+async fn get_contributors(
+    State(source): State<Arc<dyn SnapshotSource>>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<Contributor>>, ApiError> {
+    Ok(Json(latest(source.as_ref(), &name)?.contributors))
+}
+
+async fn get_hotspots(
+    State(source): State<Arc<dyn SnapshotSource>>,
+    Path(name): Path<String>,
+    Query(parameters): Query<HotspotParameters>,
+) -> Result<Json<Vec<FileSummary>>, ApiError> {
+    let repository_info = latest(source.as_ref(), &name)?;
+    let n = parameters.n.unwrap_or(DEFAULT_NUM_HOTSPOTS);
+    Ok(Json(
+        repository_info
+            .top_files_by_churn(n)
+            .into_iter()
+            .map(FileSummary::from)
+            .collect(),
+    ))
+}
@@ -0,0 +1,88 @@
+#[cfg(feature = "git")]
+use git2::{FetchOptions, Repository};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "git")]
+use crate::{data::to_count, errors::SourceCodeError, head};
+
+/// The depth that fetches the whole history, i.e., libgit2's `GIT_FETCH_DEPTH_UNSHALLOW`
+const UNSHALLOW_DEPTH: i32 = i32::MAX;
+
+/// How a shallow clone, whose commit counts and change frequencies only cover part of the
+/// history, is handled before it is analysed
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ShallowHandling {
+    /// Analyses the history as it is, reporting it as incomplete
+    #[default]
+    Report,
+    /// Fetches this many more commits of HEAD's history from the 'origin' remote
+    Deepen(i32),
+    /// Fetches the whole history from the 'origin' remote
+    Unshallow,
+}
+
+/// The old snapshots without a `history_complete` are taken as complete, as they were reported
+pub(crate) fn assumed_complete() -> bool {
+    true
+}
+
+/// Gets the depth of the history of the repository at `repo_path`, if it is a shallow clone
+///
+/// #Returns:
+/// * The number of commits on the first-parent line from HEAD to the shallow boundary, or `None`
+///   if the history is complete
+#[cfg(feature = "git")]
+pub fn get_history_depth(repo_path: &str) -> Result<Option<u64>, SourceCodeError> {
+    let repo = Repository::open(repo_path)?;
+    if !repo.is_shallow() {
+        return Ok(None);
+    }
+    let mut revwalk = repo.revwalk()?;
+    head::push_head(&repo, &mut revwalk)?;
+    revwalk.simplify_first_parent()?;
+    Ok(Some(to_count(revwalk.count())))
+}
+
+/// Fetches more of the history of the repository at `repo_path`, if it is a shallow clone, from
+/// its 'origin' remote, as the [`ShallowHandling`] sets. A repository without an 'origin'
+/// remote, or with a local one, e.g., a 'file://' URL, is left as it is.
+#[cfg(feature = "git")]
+pub fn deepen(repo_path: &str, handling: ShallowHandling) -> Result<(), SourceCodeError> {
+    let depth = match (handling, get_history_depth(repo_path)?) {
+        (ShallowHandling::Report, _) | (_, None) => return Ok(()),
+        (ShallowHandling::Deepen(commits), Some(depth)) => i32::try_from(depth)
+            .unwrap_or(UNSHALLOW_DEPTH)
+            .saturating_add(commits.max(0)),
+        (ShallowHandling::Unshallow, Some(_)) => UNSHALLOW_DEPTH,
+    };
+    let repo = Repository::open(repo_path)?;
+    let Ok(mut remote) = repo.find_remote("origin") else {
+        tracing::warn!(
+            "Not deepening the shallow clone at {}, which has no 'origin' remote",
+            repo_path
+        );
+        return Ok(());
+    };
+    // libgit2 can't fetch shallow histories over its local transport, and would leave the
+    // clone without the shallow boundary, but with the history missing
+    let url = remote.url().unwrap_or_default();
+    if url.starts_with("file://") || !url.contains(':') || std::path::Path::new(url).exists() {
+        tracing::warn!(
+            "Not deepening the shallow clone at {}, whose 'origin' remote is local",
+            repo_path
+        );
+        return Ok(());
+    }
+    tracing::info!(
+        "Fetching the history of {} to a depth of {}",
+        repo_path,
+        depth
+    );
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.depth(depth);
+    // The remote's configured refspecs are fetched, so the remote-tracking branches move too
+    remote.fetch::<&str>(&[], Some(&mut fetch_options), None)?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Writing and reading [`RepositoryInfo`] snapshots as files, optionally compressed with gzip or
+//! zstd, e.g., to archive the snapshots of large repositories, whose JSON can run to hundreds
+//! of megabytes with the file contents, or to ship them between machines
+use flate2::{read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{errors::SourceCodeError, repository::RepositoryInfo};
+
+/// The first bytes of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// The first bytes of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// The serialisation of a snapshot
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotFormat {
+    /// JSON, as from [`RepositoryInfo::get_as_json`]
+    #[default]
+    Json,
+    /// Indented JSON, e.g., to read or diff uncompressed
+    PrettyJson,
+}
+
+/// The compression of a snapshot file
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotCompression {
+    /// Not compressed
+    #[default]
+    None,
+    /// gzip, at the default level, for the widest support, e.g., by 'gunzip'
+    Gzip,
+    /// zstd, at the default level, which is faster and compresses better than gzip
+    Zstd,
+}
+impl SnapshotCompression {
+    /// Gets the conventional file extension of the compression, e.g., 'gz', if any
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            SnapshotCompression::None => None,
+            SnapshotCompression::Gzip => Some("gz"),
+            SnapshotCompression::Zstd => Some("zst"),
+        }
+    }
+    /// Detects the compression of a snapshot by its first bytes
+    fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&GZIP_MAGIC) {
+            SnapshotCompression::Gzip
+        } else if header.starts_with(&ZSTD_MAGIC) {
+            SnapshotCompression::Zstd
+        } else {
+            SnapshotCompression::None
+        }
+    }
+}
+
+/// Writes the [`RepositoryInfo`] as a snapshot file at `path`, replacing any file there
+///
+/// #Arguments:
+/// * `repository_info` - The [`RepositoryInfo`] to write
+/// * `path` - The path of the file, whose extension is left to the caller, e.g., '.json.zst'
+/// * `format` - The [`SnapshotFormat`] to serialise it in
+/// * `compression` - The [`SnapshotCompression`] to compress it with
+///
+/// #Returns:
+/// * Err([`SourceCodeError::SerializationError`]) if it can't be serialised
+/// * Err([`SourceCodeError::FileReadError`]) if the file can't be written
+pub fn write_snapshot(
+    repository_info: &RepositoryInfo,
+    path: &Path,
+    format: SnapshotFormat,
+    compression: SnapshotCompression,
+) -> Result<(), SourceCodeError> {
+    let file = BufWriter::new(File::create(path).map_err(SourceCodeError::for_path(path))?);
+    let written = match compression {
+        SnapshotCompression::None => serialise(repository_info, format, file)?.flush(),
+        SnapshotCompression::Gzip => {
+            let encoder = GzEncoder::new(file, flate2::Compression::default());
+            serialise(repository_info, format, encoder)?
+                .finish()
+                .and_then(|mut file| file.flush())
+        }
+        SnapshotCompression::Zstd => {
+            let encoder = zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .map_err(SourceCodeError::for_path(path))?;
+            serialise(repository_info, format, encoder)?
+                .finish()
+                .and_then(|mut file| file.flush())
+        }
+    };
+    written.map_err(SourceCodeError::for_path(path))
+}
+
+/// Reads the [`RepositoryInfo`] of a snapshot file at `path`, in any [`SnapshotFormat`] and
+/// [`SnapshotCompression`], which is detected from its contents, migrating snapshots from
+/// older versions of this crate as [`RepositoryInfo::deserialize_any_version`] does
+///
+/// #Returns:
+/// * Err([`SourceCodeError::FileReadError`]) if the file can't be read or decompressed
+/// * Err([`SourceCodeError::SerializationError`]) if it isn't a snapshot, or is from a later
+///   version of this crate
+pub fn read_snapshot(path: &Path) -> Result<RepositoryInfo, SourceCodeError> {
+    let json = read_json(path).map_err(SourceCodeError::for_path(path))?;
+    RepositoryInfo::deserialize_any_version(&json)
+}
+
+/// Reads the JSON of the snapshot file at `path`, decompressing it if it is compressed
+fn read_json(path: &Path) -> std::io::Result<String> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut json = String::new();
+    // The start of the file is peeked at, so it is still read when decompressing it
+    match SnapshotCompression::detect(file.fill_buf()?) {
+        SnapshotCompression::None => file.read_to_string(&mut json)?,
+        SnapshotCompression::Gzip => GzDecoder::new(file).read_to_string(&mut json)?,
+        SnapshotCompression::Zstd => zstd::Decoder::with_buffer(file)?.read_to_string(&mut json)?,
+    };
+    Ok(json)
+}
+
+/// Serialises the [`RepositoryInfo`] in the `format` to the `writer`, and returns it to be
+/// finished
+fn serialise<W: Write>(
+    repository_info: &RepositoryInfo,
+    format: SnapshotFormat,
+    mut writer: W,
+) -> Result<W, SourceCodeError> {
+    let result = match format {
+        SnapshotFormat::Json => serde_json::to_writer(&mut writer, repository_info),
+        SnapshotFormat::PrettyJson => serde_json::to_writer_pretty(&mut writer, repository_info),
+    };
+    result.map_err(|err| SourceCodeError::SerializationError(err.into()))?;
+    Ok(writer)
+}
@@ -1,127 +1,751 @@
-use git2::{Commit, DiffDelta, Repository, Revwalk, Tree};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::{Commit, ObjectType, Oid, Repository};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::{path::PathBuf, sync::Arc};
+#[cfg(feature = "git")]
+use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use crate::{data::Statistics, errors::SourceCodeError};
+#[cfg(feature = "git")]
+use crate::{
+    baseline::BaselineHistory,
+    cancel::CancellationToken,
+    checkpoint::Checkpointer,
+    counting::CountingOptions,
+    data::to_count,
+    graph::{CommitGraph, CommitNode},
+    history::{HistoryMode, HistorySampling},
+    identity::IdentityOptions,
+    languages::LanguageRegistry,
+    linecount, messages,
+    observer::NoopObserver,
+    paths,
+    runstats::RunCounters,
+};
+use crate::{
+    data::{saturating_sum, Statistics},
+    errors::SourceCodeError,
+    paths::RepoRelativePath,
+    quadrant::ChurnQuadrant,
+    ratio::Percentage,
+    role::FileRole,
+    symbols::Symbol,
+    warning::{AnalysisWarning, WarningKind},
+    whitespace::WhitespaceStatistics,
+};
+
+/// The number of bytes at the start of a file checked for NUL bytes to detect binary files, as
+/// git does
+pub(crate) const BINARY_CHECK_LENGTH: usize = 8000;
 
 /// Represents the information for a specific source file during the static retrieval phase
 ///
 /// #Fields:
 /// * `name` - The name of the file
-/// * `relative_path` - The relative path of the file from the root of the repository
+/// * `relative_path` - The [`RepoRelativePath`] of the file from the root of the repository,
+///   with forward slashes on every platform
 /// * `language` - The [`LanguageType`] of the file
-/// * `id_hash` - The (SHA256) hash of the file
+/// * `id_hash` - The hash of the raw bytes of the file, with the [`HashAlgorithm`] of the analysis
+/// * `blob_oid` - The id of the git blob of the raw bytes of the file, e.g., to look it up with
+///   `git cat-file`; it differs from the committed blob if the file is changed, or converted on
+///   checkout, e.g., for line endings
+/// * `byte_size` - The size in bytes of the file on disk, in the working tree, or `None` if it
+///   was read from a blob, or is a symlink, whose `statistics` have the size of its target, if
+///   followed
+/// * `git_blob_size` - The size in bytes of the blob of the file committed at HEAD, or `None`
+///   if it isn't committed; it differs from the `byte_size` if the file is changed, converted
+///   on checkout, e.g., for line endings, or a pointer to its contents is committed, e.g., with
+///   Git LFS
 /// * `source_file` - The contents of the file in a [`SourceFile`] container
 /// * `statistics` - The [`Statistics`] on the file
+/// * `first_commit_date` - The time of the earliest commit that added or changed the file
+/// * `last_commit_date` - The time of the latest commit that changed the file
+/// * `age_days` - The whole days from the `first_commit_date` to the time of the analysis
+/// * `days_since_last_change` - The whole days from the `last_commit_date` to the time of the
+///   analysis
+/// * `num_authors` - The number of authors, by canonical id, of the commits that added or
+///   changed the file, or `None` if it has none, e.g., it isn't in the history analysed
+/// * `quadrant` - The [`ChurnQuadrant`] of the file by its age and change frequency, or `None`
+///   if it has no commits
+/// * `is_test` - Whether the file only holds tests, e.g., it is in a 'tests/' directory
+/// * `test_loc` - The lines of test code in the file: all of them for a test file, or those of
+///   its `#[cfg(test)]` modules for a Rust file
+/// * `is_generated` - Whether the file is generated, e.g., it has a '@generated' marker or is a
+///   lockfile
+/// * `role` - The [`FileRole`] of the file, e.g., a test, configuration or build script
+/// * `is_hidden` - Whether the file is hidden, i.e., a dotfile or in a dot-directory, e.g.,
+///   '.eslintrc.js' or '.github/workflows/ci.yml'
+/// * `kind` - The [`FileKind`] of the file
+/// * `symlink_target` - The target of the symlink, as written in it, if the file is a
+///   [`FileKind::Symlink`]
+/// * `oversized` - Whether the file is over the `max_file_size` of the
+///   [`crate::limits::ResourceLimits`], so was hashed without being read into memory, and its
+///   contents aren't kept or embedded
+/// * `encoding` - The [`ContentEncoding`] the file was read in, or `None` if it is binary or
+///   oversized
+/// * `whitespace` - The [`WhitespaceStatistics`] of the file, e.g., its line endings, or `None`
+///   if it is binary or oversized
+/// * `embedded` - The [`EmbeddedLanguage`]s of the code of other languages in the file, e.g.,
+///   JavaScript in an HTML file, which isn't counted in its `statistics`
+/// * `symbols` - The [`Symbol`]s defined in the file, e.g., its functions and types, if they
+///   were indexed, see [`AnalysisOptions::with_symbols`](crate::options::AnalysisOptions::with_symbols)
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SourceFileInfo {
     pub name: String,
-    pub relative_path: String,
+    pub relative_path: RepoRelativePath,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<LanguageType>,
     pub id_hash: Option<String>,
+    #[serde(default)]
+    pub blob_oid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_blob_size: Option<u64>,
     pub source_file: Option<Box<SourceFile>>,
     pub statistics: Statistics,
+    #[serde(default)]
+    pub first_commit_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_commit_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub age_days: Option<i64>,
+    #[serde(default)]
+    pub days_since_last_change: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_authors: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quadrant: Option<ChurnQuadrant>,
+    #[serde(default)]
+    pub is_test: bool,
+    #[serde(default)]
+    pub test_loc: u64,
+    #[serde(default)]
+    pub is_generated: bool,
+    #[serde(default)]
+    pub role: FileRole,
+    #[serde(default)]
+    pub is_hidden: bool,
+    #[serde(default)]
+    pub kind: FileKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+    #[serde(default)]
+    pub oversized: bool,
+    #[serde(default)]
+    pub encoding: Option<ContentEncoding>,
+    #[serde(default)]
+    pub whitespace: Option<WhitespaceStatistics>,
+    #[serde(default)]
+    pub embedded: Vec<EmbeddedLanguage>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<Symbol>,
 }
 impl SourceFileInfo {
-    pub(crate) fn set_source_file_contents(&mut self, contents: String) {
+    /// Sets the `age_days` and `days_since_last_change` of the file as of the time `at`
+    pub(crate) fn set_ages(&mut self, at: DateTime<Utc>) {
+        self.age_days = self
+            .first_commit_date
+            .map(|date| (at - date).num_days().max(0));
+        self.days_since_last_change = self
+            .last_commit_date
+            .map(|date| (at - date).num_days().max(0));
+    }
+    /// Sets the `num_authors` of the file from the commits of the [`CommitGraph`] that changed
+    /// it, and the root commits that added it
+    #[cfg(feature = "git")]
+    pub(crate) fn set_num_authors(&mut self, graph: &CommitGraph) -> Result<(), SourceCodeError> {
+        let commits = graph.commits();
+        let changes = graph.changes()?;
+        let path = self.relative_path.as_str();
+        let authors: BTreeSet<&str> = changes
+            .commits_changing(path)
+            .iter()
+            .chain(changes.commits_adding(path))
+            .map(|&index| commits[index].author.canonical_id.as_str())
+            .collect();
+        self.num_authors = (!authors.is_empty()).then_some(authors.len() as u64);
+        Ok(())
+    }
+    /// Sets the raw `contents` of the file, e.g., of a [`SourceFileInfo`] deserialised without
+    /// them, detecting their [`ContentEncoding`] and decoding them to UTF-8; binary contents are
+    /// kept as they are, with an [`ContentEncoding::Unknown`] encoding. The `id_hash`,
+    /// `blob_oid`, `encoding` and statistics of the file aren't changed.
+    pub fn set_source_file_contents(&mut self, contents: impl Into<Vec<u8>>) {
+        let contents: Vec<u8> = contents.into();
+        let encoding = ContentEncoding::detect(&contents).unwrap_or(ContentEncoding::Unknown);
+        let path = self
+            .source_file
+            .take()
+            .and_then(|source_file| source_file.path);
         self.source_file = Some(Box::new(SourceFile {
-            contents: Arc::new(contents),
+            contents: Some(encoding.decode(contents).into()),
+            encoding,
+            path,
         }));
     }
-    pub fn get_source_file_contents(&self) -> String {
-        match &self.source_file {
-            Some(source_file) => source_file.contents.to_string(),
-            None => {
-                log::error!("Failed to retrieve source file: {}", self.name);
-                String::new()
+    /// Sets the `path` the contents of the file are read from again once they are dropped, e.g.,
+    /// of a [`SourceFileInfo`] restored from a checkpoint, as the path isn't serialised
+    pub(crate) fn set_source_file_path(&mut self, path: PathBuf) {
+        if let Some(source_file) = self.source_file.as_mut() {
+            source_file.path = Some(path);
+        }
+    }
+    /// Drops the kept contents of the file, to free their memory; they can still be read from
+    /// the file on demand, as [`ContentMode::Hashes`] allows
+    pub fn clear_source_file_contents(&mut self) {
+        if let Some(source_file) = self.source_file.as_mut() {
+            source_file.contents = None;
+        }
+    }
+    /// Gets the contents of the file without copying them, if they were kept and are valid
+    /// UTF-8; see [`SourceFileInfo::get_source_file_contents`] to read them if they weren't
+    pub fn get_contents(&self) -> Option<&str> {
+        self.source_file.as_ref()?.as_str()
+    }
+    /// Gets the innermost [`Symbol`] whose definition spans the `line` (1-based), e.g., the
+    /// method a changed line is in, if the symbols were indexed
+    pub fn get_symbol_at(&self, line: usize) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|symbol| symbol.contains(line))
+            .min_by_key(|symbol| symbol.end_line - symbol.start_line)
+    }
+    /// Gets the contents of the file if they were kept, or else reads them from its path, if
+    /// the file there still has the same contents, i.e., the same `blob_oid`; text read in
+    /// another [`ContentEncoding`] than UTF-8 is decoded to UTF-8, as it is kept
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ContentsUnavailable`]) if the contents weren't kept and can't
+    ///   be read, e.g., the file is binary, or changed since the analysis
+    /// * Err([`SourceCodeError::FileReadError`]) if reading the file failed
+    pub fn load_bytes(&self) -> Result<Cow<'_, [u8]>, SourceCodeError> {
+        let unavailable = |reason: &str| SourceCodeError::ContentsUnavailable {
+            path: self.relative_path.to_string(),
+            reason: reason.to_string(),
+        };
+        let Some(source_file) = self.source_file.as_ref() else {
+            return Err(unavailable("the contents weren't kept, e.g., it is binary"));
+        };
+        if let Some(contents) = source_file.as_bytes() {
+            return Ok(Cow::Borrowed(contents));
+        }
+        let Some(path) = source_file.path.as_ref() else {
+            return Err(unavailable("the contents weren't kept, nor its path"));
+        };
+        // Without libgit2, the file read again can't be checked against its blob id
+        #[cfg(not(feature = "git"))]
+        return Err(unavailable(&format!(
+            "the contents weren't kept, and reading {} again needs the `git` feature",
+            path.display()
+        )));
+        #[cfg(feature = "git")]
+        {
+            let bytes =
+                std::fs::read(paths::long_path(path)).map_err(SourceCodeError::for_path(path))?;
+            let blob_oid = Oid::hash_object(ObjectType::Blob, &bytes)?.to_string();
+            if self.blob_oid.as_ref() != Some(&blob_oid) {
+                return Err(unavailable("the file changed since the analysis"));
             }
+            Ok(Cow::Owned(source_file.encoding.decode(bytes)))
         }
     }
+    /// Gets the contents of the file, as [`SourceFileInfo::load_bytes`] does, if they are valid
+    /// UTF-8
+    pub(crate) fn load_contents(&self) -> Option<Cow<'_, str>> {
+        match self.load_bytes() {
+            Ok(Cow::Borrowed(bytes)) => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+            Ok(Cow::Owned(bytes)) => String::from_utf8(bytes).ok().map(Cow::Owned),
+            Err(err) => {
+                tracing::debug!("No contents for source file {}: {}", self.name, err);
+                None
+            }
+        }
+    }
+    /// Gets the contents of the file as text, reading them from its path if they weren't kept
+    /// (see [`ContentMode`]); contents that aren't valid UTF-8 are decoded lossily, see
+    /// [`SourceFile::as_str_lossy`]
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ContentsUnavailable`]) if the contents weren't kept and can't
+    ///   be read, e.g., the file is binary, or changed since the analysis
+    /// * Err([`SourceCodeError::FileReadError`]) if reading the file failed
+    pub fn get_source_file_contents(&self) -> Result<String, SourceCodeError> {
+        Ok(String::from_utf8_lossy(&self.load_bytes()?).into_owned())
+    }
+    /// Drops the contents, and the hashes, of the file that the `content_mode` doesn't retain
+    pub(crate) fn retain_contents(&mut self, content_mode: ContentMode) {
+        match content_mode {
+            ContentMode::Full => {}
+            ContentMode::Hashes => self.clear_source_file_contents(),
+            ContentMode::None => {
+                self.source_file = None;
+                self.id_hash = None;
+                self.blob_oid = None;
+            }
+        }
+    }
+    /// Gets the [`SourceFileInfo`] for a file from its `tokei` report. Binary files are kept
+    /// without their contents, and an [`AnalysisWarning`] is added to `warnings`; so are files
+    /// over the `max_file_size`, which are hashed as they are streamed rather than read into
+    /// memory.
+    #[cfg(feature = "git")]
     pub(crate) fn get_source_file_info(
         source_file_path: &str,
         file_report: &tokei::Report,
         lang_type: &LanguageType,
+        context: SourceFileContext<'_>,
+        warnings: &mut Vec<AnalysisWarning>,
+    ) -> Result<SourceFileInfo, SourceCodeError> {
+        let path = paths::long_path(&file_report.name);
+        let size = std::fs::metadata(&path)
+            .map_err(SourceCodeError::for_path(&file_report.name))?
+            .len();
+        if context.is_oversized(size) {
+            context.counters.add_scanned(size);
+            let id_hash = context
+                .hash_algorithm
+                .hash_file(&path)
+                .map_err(SourceCodeError::for_path(&file_report.name))?;
+            let blob_oid = Oid::hash_file(ObjectType::Blob, &path)?;
+            let mut source_file_info = Self::new_from_report(
+                source_file_path,
+                file_report,
+                lang_type,
+                context,
+                size,
+                id_hash,
+                blob_oid,
+            )?;
+            source_file_info.set_oversized(warnings);
+            source_file_info.byte_size = Some(size);
+            return Ok(source_file_info);
+        }
+        let src_file_bytes =
+            std::fs::read(&path).map_err(SourceCodeError::for_path(&file_report.name))?;
+        let mut source_file_info = Self::get_source_file_info_from_bytes(
+            source_file_path,
+            file_report,
+            lang_type,
+            context,
+            src_file_bytes,
+            warnings,
+        )?;
+        source_file_info.byte_size = Some(size);
+        Ok(source_file_info)
+    }
+    /// Gets the [`SourceFileInfo`] for the blob `blob_id` of `size` bytes, over the
+    /// `max_file_size`, from its `tokei` report without line counts. The blob is hashed as it
+    /// is streamed from the object database `odb` rather than read into memory; a packed blob,
+    /// which can't be streamed, is left unhashed. An [`AnalysisWarning`] is added to
+    /// `warnings`.
+    #[cfg(feature = "git")]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn get_oversized_blob_source_file_info(
+        source_file_path: &str,
+        file_report: &tokei::Report,
+        lang_type: &LanguageType,
+        context: SourceFileContext<'_>,
+        odb: &git2::Odb<'_>,
+        blob_id: Oid,
+        size: u64,
+        warnings: &mut Vec<AnalysisWarning>,
+    ) -> Result<SourceFileInfo, SourceCodeError> {
+        context.counters.add_scanned(size);
+        let id_hash = context
+            .hash_algorithm
+            .hash_blob(odb, blob_id)
+            .map_err(SourceCodeError::for_path(&file_report.name))?;
+        let mut source_file_info = Self::new_from_report(
+            source_file_path,
+            file_report,
+            lang_type,
+            context,
+            size,
+            String::new(),
+            blob_id,
+        )?;
+        source_file_info.id_hash = id_hash;
+        source_file_info.set_oversized(warnings);
+        Ok(source_file_info)
+    }
+    /// Gets the [`SourceFileInfo`] for a file from its `tokei` report and its contents as
+    /// `src_file_bytes`, e.g., read from a blob rather than the working tree
+    #[cfg(feature = "git")]
+    pub(crate) fn get_source_file_info_from_bytes(
+        source_file_path: &str,
+        file_report: &tokei::Report,
+        lang_type: &LanguageType,
+        context: SourceFileContext<'_>,
+        src_file_bytes: Vec<u8>,
+        warnings: &mut Vec<AnalysisWarning>,
     ) -> Result<SourceFileInfo, SourceCodeError> {
-        // Get the source file contents
-        let src_file_contents =
-            std::fs::read_to_string(&file_report.name).map_err(SourceCodeError::FileReadError)?;
-        let src_file_contents_size = Self::get_file_contents_size(&src_file_contents)?;
-        let src_file_hash = Self::calculate_hash_from(&src_file_contents);
+        context.counters.add_scanned(to_count(src_file_bytes.len()));
+        // The size and hash are of the raw bytes
+        let mut source_file_info = Self::new_from_report(
+            source_file_path,
+            file_report,
+            lang_type,
+            context,
+            src_file_bytes.len() as u64,
+            context.hash_algorithm.hash(&src_file_bytes),
+            Oid::hash_object(ObjectType::Blob, &src_file_bytes)?,
+        )?;
+        if context.is_oversized(src_file_bytes.len() as u64) {
+            source_file_info.set_oversized(warnings);
+            return Ok(source_file_info);
+        }
+        let relative_path = &source_file_info.relative_path;
+        if context.verify_line_counts {
+            warnings.extend(linecount::verify(
+                relative_path,
+                &file_report.stats,
+                &src_file_bytes,
+            ));
+        }
+        let encoding = match ContentEncoding::detect(&src_file_bytes) {
+            Some(encoding) => {
+                if !encoding.is_utf8() {
+                    tracing::info!(
+                        "Decoding the contents of {} from {:?}",
+                        relative_path,
+                        encoding
+                    );
+                }
+                Some(encoding)
+            }
+            None => {
+                tracing::warn!("Not keeping the contents of binary file {}", relative_path);
+                warnings.push(AnalysisWarning::new(
+                    relative_path.to_string(),
+                    WarningKind::Binary,
+                ));
+                None
+            }
+        };
+        let contents = encoding.map(|encoding| encoding.decode(src_file_bytes));
+        source_file_info.encoding = encoding;
+        source_file_info.whitespace = contents.as_deref().map(WhitespaceStatistics::new);
+        // The path is kept as the handle to read the contents again if they are dropped
+        source_file_info.source_file = encoding.zip(contents).map(|(encoding, contents)| {
+            Box::new(SourceFile {
+                contents: Some(contents.into()),
+                encoding,
+                path: Some(file_report.name.clone()),
+            })
+        });
 
-        let mut statistics =
-            Statistics::get_statistics_for_source_file(source_file_path, &file_report.name)?;
-        statistics.loc = file_report.stats.code as i64;
-        statistics.size = src_file_contents_size;
+        Ok(source_file_info)
+    }
+    /// Gets the [`SourceFileInfo`] for a symlink from its `tokei` report, with the `target`
+    /// written in it. If the symlink is followed, its statistics and contents are of the
+    /// `target_bytes`; otherwise it has no lines or size, and is hashed by its target.
+    #[cfg(feature = "git")]
+    pub(crate) fn get_symlink_source_file_info(
+        source_file_path: &str,
+        file_report: &tokei::Report,
+        lang_type: &LanguageType,
+        context: SourceFileContext<'_>,
+        target: String,
+        target_bytes: Option<Vec<u8>>,
+        warnings: &mut Vec<AnalysisWarning>,
+    ) -> Result<SourceFileInfo, SourceCodeError> {
+        let mut source_file_info = match target_bytes {
+            Some(target_bytes) => Self::get_source_file_info_from_bytes(
+                source_file_path,
+                file_report,
+                lang_type,
+                context,
+                target_bytes,
+                warnings,
+            )?,
+            None => Self::new_from_report(
+                source_file_path,
+                file_report,
+                lang_type,
+                context,
+                0,
+                context.hash_algorithm.hash(target.as_bytes()),
+                Oid::hash_object(ObjectType::Blob, target.as_bytes())?,
+            )?,
+        };
+        source_file_info.kind = FileKind::Symlink;
+        source_file_info.symlink_target = Some(target);
+        Ok(source_file_info)
+    }
+    /// Gets the [`SourceFileInfo`] for a file from its `tokei` report, with the `size`,
+    /// `id_hash` and `blob_oid` of its raw bytes, and without its contents
+    #[cfg(feature = "git")]
+    fn new_from_report(
+        source_file_path: &str,
+        file_report: &tokei::Report,
+        lang_type: &LanguageType,
+        context: SourceFileContext<'_>,
+        size: u64,
+        id_hash: String,
+        blob_oid: Oid,
+    ) -> Result<SourceFileInfo, SourceCodeError> {
+        let relative_path = RepoRelativePath::new(&file_report.name, Path::new(source_file_path));
+        let cached = context.baseline.and_then(|b| b.get(&relative_path));
+        if context.baseline.is_some() {
+            context.counters.add_cache_lookup(cached.is_some());
+        }
+        let change_frequency = match cached {
+            Some(change_frequency) => change_frequency.clone(),
+            None => SourceFileChangeFrequency::from_graph(
+                context.graph,
+                &relative_path,
+                &context.frequency_basis,
+            )?,
+        };
+        let mut statistics = Statistics::new_from_change_frequency(&change_frequency);
+        statistics.loc = file_report.stats.code as u64;
+        statistics.size = size;
 
-        let mut source_file_info = SourceFileInfo {
+        Ok(SourceFileInfo {
             name: file_report
                 .name
                 .file_name()
                 .map(|name| name.to_string_lossy().to_string())
                 .unwrap_or_else(|| "Name not resolved".to_string()),
-            relative_path: file_report.name.as_os_str().to_string_lossy().to_string(),
-            language: Some({
-                let mut lang_type = lang_type.clone();
-                lang_type.extensions = file_report
-                    .name
-                    .extension()
-                    .map(|ext| {
-                        ext.to_str()
-                            .map(|ext_str| ext_str.to_string())
-                            .unwrap_or_else(|| "Extension not resolved".to_string())
-                    })
-                    .into_iter()
-                    .collect();
-                lang_type
-            }),
-            id_hash: Some(src_file_hash),
+            relative_path,
+            language: Some(lang_type.clone()),
+            id_hash: Some(id_hash),
+            blob_oid: Some(blob_oid.to_string()),
+            byte_size: None,
+            git_blob_size: None,
             source_file: None,
             statistics,
-        };
-        source_file_info.set_source_file_contents(src_file_contents);
-
-        Ok(source_file_info)
+            first_commit_date: change_frequency.first_commit_date,
+            last_commit_date: change_frequency.last_commit_date,
+            age_days: None,
+            days_since_last_change: None,
+            num_authors: None,
+            quadrant: None,
+            is_test: false,
+            test_loc: 0,
+            is_generated: false,
+            role: FileRole::default(),
+            is_hidden: false,
+            kind: FileKind::Regular,
+            symlink_target: None,
+            oversized: false,
+            encoding: None,
+            whitespace: None,
+            embedded: EmbeddedLanguage::from_code_stats(&file_report.stats),
+            symbols: vec![],
+        })
+    }
+    /// Marks the file as over the `max_file_size`, so its contents aren't kept, and adds an
+    /// [`AnalysisWarning`] to `warnings`
+    fn set_oversized(&mut self, warnings: &mut Vec<AnalysisWarning>) {
+        tracing::warn!(
+            "Not keeping the contents of {}, which is over the maximum file size",
+            self.relative_path
+        );
+        self.oversized = true;
+        warnings.push(AnalysisWarning::new(
+            self.relative_path.to_string(),
+            WarningKind::Oversized,
+        ));
     }
 
     /// Gets the [`SourceFileInfo`] as a JSON string
     pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
         serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
     }
-    fn get_file_contents_size(file_contents: &String) -> Result<i64, SourceCodeError> {
-        let length: i64 = file_contents
-            .len()
-            .try_into()
-            .map_err(SourceCodeError::ConversionError)?;
-        Ok(length)
-    }
-    /// Calculates a (SHA256) hash from a string (source file contents)
-    fn calculate_hash_from(file_contents: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(file_contents);
-        let result = hasher.finalize();
-
-        format!("{:x}", result)
-    }
 }
 
-/// Represents the contents of a source file
+/// Represents the contents of a source file, if they are kept, decoded to UTF-8 from the
+/// [`ContentEncoding`] detected when it was read, and the path to read them from on demand, if
+/// not, as the [`ContentMode`] sets. The contents are serialised as text; the path isn't
+/// serialised.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SourceFile {
-    contents: Arc<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_contents",
+        deserialize_with = "deserialize_contents"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    contents: Option<Arc<[u8]>>,
+    #[serde(default)]
+    encoding: ContentEncoding,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+impl SourceFile {
+    /// Gets the contents, if they are kept, as UTF-8 unless the file is binary
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.contents.as_deref()
+    }
+    /// Gets the contents, if they are kept and are valid UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.as_bytes()?).ok()
+    }
+    /// Gets the contents as text, if they are kept, replacing any bytes that aren't valid
+    /// UTF-8 with U+FFFD
+    pub fn as_str_lossy(&self) -> Option<Cow<'_, str>> {
+        self.as_bytes().map(String::from_utf8_lossy)
+    }
+    /// Gets the [`ContentEncoding`] detected when the file was read
+    pub fn encoding(&self) -> ContentEncoding {
+        self.encoding
+    }
+}
+
+/// Serialises the raw `contents` of a [`SourceFile`] as text
+fn serialize_contents<S: serde::Serializer>(
+    contents: &Option<Arc<[u8]>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match contents {
+        Some(contents) => serializer.serialize_some(&String::from_utf8_lossy(contents)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Deserialises the `contents` of a [`SourceFile`] from text
+fn deserialize_contents<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Arc<[u8]>>, D::Error> {
+    let contents: Option<String> = Option::deserialize(deserializer)?;
+    Ok(contents.map(|contents| contents.into_bytes().into()))
+}
+
+/// The kind of a source file in the file system, or the git tree
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    /// A regular file
+    #[default]
+    Regular,
+    /// A symlink to a file, which isn't counted in the lines of code unless it is followed, see
+    /// [`CountingOptions`]
+    Symlink,
+}
+
+/// The text encoding of the contents of a source file, as detected when it was read
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ContentEncoding {
+    /// Valid UTF-8, including ASCII
+    #[default]
+    Utf8,
+    /// Valid UTF-8 that starts with a byte order mark
+    Utf8Bom,
+    /// UTF-16, little-endian, that starts with a byte order mark
+    Utf16Le,
+    /// UTF-16, big-endian, that starts with a byte order mark
+    Utf16Be,
+    /// Not valid UTF-8, so taken as Latin-1, i.e., as its superset Windows-1252, in which any
+    /// bytes are valid
+    Latin1,
+    /// Not valid UTF-8, and not decoded; only in snapshots from before Latin-1 was detected
+    Unknown,
+}
+impl ContentEncoding {
+    /// The UTF-8 byte order mark
+    const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+    /// The UTF-16 little-endian byte order mark
+    const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+    /// The UTF-16 big-endian byte order mark
+    const UTF16BE_BOM: [u8; 2] = [0xFE, 0xFF];
+
+    /// Detects the [`ContentEncoding`] of the raw `bytes` of a file, by its byte order mark,
+    /// and then whether it is valid UTF-8
+    ///
+    /// #Returns:
+    /// * The [`ContentEncoding`], or `None` if the file is binary, i.e., has a NUL byte near its
+    ///   start, without a UTF-16 byte order mark
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if is_binary(bytes) {
+            return None;
+        }
+        if bytes.starts_with(&Self::UTF16LE_BOM) {
+            return Some(ContentEncoding::Utf16Le);
+        }
+        if bytes.starts_with(&Self::UTF16BE_BOM) {
+            return Some(ContentEncoding::Utf16Be);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(_) if bytes.starts_with(&Self::UTF8_BOM) => Some(ContentEncoding::Utf8Bom),
+            Ok(_) => Some(ContentEncoding::Utf8),
+            Err(_) => Some(ContentEncoding::Latin1),
+        }
+    }
+    /// Whether the encoding is UTF-8, with or without a byte order mark
+    pub fn is_utf8(self) -> bool {
+        matches!(self, ContentEncoding::Utf8 | ContentEncoding::Utf8Bom)
+    }
+    /// Decodes the raw `bytes` of a file in this encoding to UTF-8, without the UTF-16 byte
+    /// order mark; UTF-8 (and undecoded) bytes are returned as they are
+    pub fn decode(self, bytes: Vec<u8>) -> Vec<u8> {
+        let encoding = match self {
+            ContentEncoding::Utf8 | ContentEncoding::Utf8Bom | ContentEncoding::Unknown => {
+                return bytes
+            }
+            ContentEncoding::Utf16Le => encoding_rs::UTF_16LE,
+            ContentEncoding::Utf16Be => encoding_rs::UTF_16BE,
+            ContentEncoding::Latin1 => encoding_rs::WINDOWS_1252,
+        };
+        let (decoded, _) = encoding.decode_with_bom_removal(&bytes);
+        decoded.into_owned().into_bytes()
+    }
+    /// Decodes the raw `bytes` of a file in this encoding to UTF-8, as
+    /// [`ContentEncoding::decode`] does, borrowing UTF-8 (and undecoded) bytes rather than
+    /// copying them
+    pub fn decode_slice(self, bytes: &[u8]) -> Cow<'_, [u8]> {
+        let encoding = match self {
+            ContentEncoding::Utf8 | ContentEncoding::Utf8Bom | ContentEncoding::Unknown => {
+                return Cow::Borrowed(bytes)
+            }
+            ContentEncoding::Utf16Le => encoding_rs::UTF_16LE,
+            ContentEncoding::Utf16Be => encoding_rs::UTF_16BE,
+            ContentEncoding::Latin1 => encoding_rs::WINDOWS_1252,
+        };
+        let (decoded, _) = encoding.decode_with_bom_removal(bytes);
+        Cow::Owned(decoded.into_owned().into_bytes())
+    }
+}
+
+/// What of the contents of each source file the [`crate::repository::RepositoryInfo`] keeps
+/// once the analysis is done; the analysis itself always reads them
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ContentMode {
+    /// Neither the contents nor the hashes of the files are kept
+    None,
+    /// The hashes of the files are kept, and their contents are read from the files on demand
+    #[default]
+    Hashes,
+    /// The contents of the files are kept in memory, and serialised
+    Full,
 }
 
 /// Top-level struct to hold statistics on the [`LanguageType`]s found in the repository.
 /// Each source file will be assigned a [`LanguageType`] based on the language and file extensions.
-/// Note that the "Language", e.g., 'Rust', may have multiple file extensionss, e.g., '.rs', '.toml', etc. and therefore multiple [`LanguageType`]s.
+/// Note that a language, e.g., 'C', may have multiple file extensions, e.g., '.c' and '.h', which
+/// the one [`LanguageType`] of the language has, as the [`LanguageRegistry`] gives them.
 ///
 /// #Fields:
 /// * `language` - The name of the language
-/// * `extensions` - A [`Vec`] of file extensionss for this language
+/// * `extensions` - A [`Vec`] of all the file extensions of this language, in alphabetical order
 /// * `percentage` - The percentage of the total lines of code in the repository that are of this [`LanguageType`]
 /// * `statistics` - The [`Statistics`] on the file type
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LanguageType {
     pub name: String,
     pub extensions: Vec<String>,
@@ -129,34 +753,33 @@ pub struct LanguageType {
     pub statistics: Option<Statistics>,
 }
 impl LanguageType {
-    /// Creates a new [`LanguageType`] from a tokei::LanguageType
+    /// Creates a new [`LanguageType`] from a tokei::LanguageType, with the extensions `tokei`
+    /// recognises it by
+    #[cfg(feature = "git")]
     pub fn new_from(tokei_language_type: tokei::LanguageType) -> Self {
-        Self {
-            name: tokei_language_type.name().to_string(),
-            extensions: vec![],
-            statistics: None,
-        }
+        LanguageRegistry::tokei().get_language_type(tokei_language_type)
     }
     /// Sums the lines of code for an array of [`LanguageType`]s
-    pub fn sum_lines_of_code(language_types: &[LanguageType]) -> i64 {
-        language_types
-            .iter()
-            .filter_map(|lt| lt.statistics.as_ref().map(|s| s.loc))
-            .sum()
+    pub fn sum_lines_of_code(language_types: &[LanguageType]) -> u64 {
+        saturating_sum(
+            language_types
+                .iter()
+                .filter_map(|lt| lt.statistics.as_ref().map(|s| s.loc)),
+        )
     }
     /// Gets the predominant language from an array of [`LanguageType`]s
     pub fn get_predominant_language(languages: &[LanguageType]) -> LanguageType {
         let mut predominant_language = LanguageType::default();
-        let mut highest_percentage = 0.0;
-        let mut largest_size = 0_i64;
+        let mut highest_percentage = Percentage::default();
+        let mut largest_size = 0_u64;
 
         for lang in languages {
             if let Some(statistics) = &lang.statistics {
-                if statistics.frequency > highest_percentage
-                    || (statistics.frequency == highest_percentage
-                        && statistics.size > largest_size)
+                let share = statistics.language_share.unwrap_or_default();
+                if share > highest_percentage
+                    || (share == highest_percentage && statistics.size > largest_size)
                 {
-                    highest_percentage = statistics.frequency;
+                    highest_percentage = share;
                     largest_size = statistics.size;
                     predominant_language = lang.clone();
                 }
@@ -165,27 +788,222 @@ impl LanguageType {
         predominant_language
     }
     /// Calculates percentage distribution of the [`LanguageType`]s - i.e., the percentage of
-    /// lines of code that each [`LanguageType`] in relation to each other and updates the [`Statistics`].frequency field for each [`LanguageType`]
+    /// lines of code that each [`LanguageType`] in relation to each other and updates the [`Statistics`].language_share field for each [`LanguageType`]
     pub fn calculate_percentage_distribution(languages: &mut [LanguageType]) {
         let total_lines_of_code = LanguageType::sum_lines_of_code(languages);
         for language in languages {
             if let Some(statistics) = &mut language.statistics {
-                statistics.frequency = (statistics.loc as f32 / total_lines_of_code as f32) * 100.0;
+                statistics.language_share = Some(
+                    Percentage::of(statistics.loc as f64, total_lines_of_code as f64)
+                        .unwrap_or_default(),
+                );
             }
         }
     }
 }
 
+/// The code of a language embedded in a source file of another, e.g., JavaScript in an HTML
+/// file or a code block in a Markdown file, as `tokei` reports it
+///
+/// #Fields:
+/// * `name` - The name of the embedded language
+/// * `loc` - The lines of code of the language in the file
+/// * `comments` - The comment lines of the language in the file
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EmbeddedLanguage {
+    pub name: String,
+    pub loc: u64,
+    pub comments: u64,
+}
+impl EmbeddedLanguage {
+    /// Gets the [`EmbeddedLanguage`]s of a file's `tokei` `stats`, by name, with the code
+    /// embedded in the embedded code in turn, e.g., CSS in HTML in Markdown
+    #[cfg(feature = "git")]
+    pub(crate) fn from_code_stats(stats: &tokei::CodeStats) -> Vec<EmbeddedLanguage> {
+        let mut embedded: BTreeMap<&str, EmbeddedLanguage> = BTreeMap::new();
+        let mut blobs: Vec<(&tokei::LanguageType, &tokei::CodeStats)> =
+            stats.blobs.iter().collect();
+        while let Some((language, stats)) = blobs.pop() {
+            let entry = embedded
+                .entry(language.name())
+                .or_insert_with(|| EmbeddedLanguage {
+                    name: language.name().to_string(),
+                    loc: 0,
+                    comments: 0,
+                });
+            entry.loc += stats.code as u64;
+            entry.comments += stats.comments as u64;
+            blobs.extend(stats.blobs.iter());
+        }
+        embedded.into_values().collect()
+    }
+}
+
+/// Checks whether the `bytes` of a file are binary, i.e., have a NUL byte near their start, as
+/// git does, unless they start with a UTF-16 byte order mark; only the first
+/// [`BINARY_CHECK_LENGTH`] bytes are needed
+pub(crate) fn is_binary(bytes: &[u8]) -> bool {
+    !bytes.starts_with(&ContentEncoding::UTF16LE_BOM)
+        && !bytes.starts_with(&ContentEncoding::UTF16BE_BOM)
+        && bytes
+            .iter()
+            .take(BINARY_CHECK_LENGTH)
+            .any(|byte| *byte == 0)
+}
+
+/// How each source file is analysed: how its lines are counted, where its change frequency
+/// comes from, i.e., a baseline, for the files it has, or else the [`CommitGraph`] of the
+/// history, how its contents are hashed, whether its line counts are verified, the size over
+/// which its contents aren't read into memory, the subdirectory the analysis is scoped to, if
+/// any, and the paths its source files are restricted to, the [`RunCounters`] of the work done, and the [`Checkpointer`] the files scanned are
+/// recorded in, if the analysis is checkpointed
+#[cfg(feature = "git")]
+#[derive(Clone, Copy)]
+pub(crate) struct SourceFileContext<'a> {
+    pub(crate) counting: &'a CountingOptions,
+    pub(crate) languages: &'a LanguageRegistry,
+    pub(crate) graph: &'a CommitGraph,
+    pub(crate) frequency_basis: FrequencyBasis,
+    pub(crate) hash_algorithm: HashAlgorithm,
+    pub(crate) baseline: Option<&'a BaselineHistory>,
+    pub(crate) verify_line_counts: bool,
+    pub(crate) max_file_size: Option<u64>,
+    pub(crate) scope: Option<&'a str>,
+    pub(crate) included: &'a [String],
+    pub(crate) counters: &'a RunCounters,
+    pub(crate) checkpointer: Option<&'a Checkpointer>,
+}
+#[cfg(feature = "git")]
+impl SourceFileContext<'_> {
+    /// Whether the file at the `relative` path is in the scope of the analysis, i.e., under its
+    /// subdirectory, if it is scoped, and at or under one of its included paths, if any
+    pub(crate) fn in_scope(&self, relative: &Path) -> bool {
+        self.scope
+            .is_none_or(|scope| paths::starts_with(relative, Path::new(scope)))
+            && (self.included.is_empty()
+                || self
+                    .included
+                    .iter()
+                    .any(|included| paths::starts_with(relative, Path::new(included))))
+    }
+    /// Whether a file of `size` bytes is over the `max_file_size`
+    pub(crate) fn is_oversized(&self, size: u64) -> bool {
+        self.max_file_size
+            .is_some_and(|max_file_size| size > max_file_size)
+    }
+}
+
+/// The commits the change frequency of a source file is a share of. The share of all of the
+/// commits penalises the files of long-lived repositories, and those added late in the history,
+/// which can't have changed in the commits before them.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FrequencyDenominator {
+    /// All of the commits walked for the history, as counted by the [`MergeStrategy`]
+    #[default]
+    TotalCommits,
+    /// The commits since the file was added, including the commit that added it
+    SinceCreation,
+    /// The commits of the last `days` days of the history, up to the latest commit, of which
+    /// only the file's commits in the window are counted too
+    Window { days: u32 },
+}
+impl FrequencyDenominator {
+    /// Gets a description of the change frequency as counted with the denominator, e.g., for
+    /// the [`crate::catalog::MetricDefinition`] of the change frequency
+    pub fn describe(&self) -> String {
+        match self {
+            FrequencyDenominator::TotalCommits => {
+                "The share of the repository's commits that changed the source file".to_string()
+            }
+            FrequencyDenominator::SinceCreation => {
+                "The share of the repository's commits since the source file was added that \
+                 changed it"
+                    .to_string()
+            }
+            FrequencyDenominator::Window { days } => format!(
+                "The share of the repository's commits of the last {} days of its history that \
+                 changed the source file",
+                days
+            ),
+        }
+    }
+}
+
+/// The [`FrequencyDenominator`] of an analysis, with the window it counts the commits of
+/// resolved once from the [`CommitGraph`], rather than for each file
+#[cfg(feature = "git")]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct FrequencyBasis {
+    denominator: FrequencyDenominator,
+    window_start: Option<DateTime<Utc>>,
+    window_commits: u64,
+}
+#[cfg(feature = "git")]
+impl FrequencyBasis {
+    /// Resolves the `denominator` for the commits of the `graph`
+    pub(crate) fn new(
+        graph: &CommitGraph,
+        denominator: FrequencyDenominator,
+    ) -> Result<FrequencyBasis, SourceCodeError> {
+        let FrequencyDenominator::Window { days } = denominator else {
+            return Ok(FrequencyBasis {
+                denominator,
+                ..Default::default()
+            });
+        };
+        let commits = graph.commits();
+        let Some(latest) = commits.iter().filter_map(CommitNode::commit_date).max() else {
+            return Ok(FrequencyBasis {
+                denominator,
+                ..Default::default()
+            });
+        };
+        let window_start = latest - chrono::Duration::days(days as i64);
+        let changes = graph.changes()?;
+        let window_commits = commits
+            .iter()
+            .enumerate()
+            .filter(|(index, commit)| {
+                changes.is_counted(*index)
+                    && commit
+                        .commit_date()
+                        .is_some_and(|date| date >= window_start)
+            })
+            .count() as u64;
+        Ok(FrequencyBasis {
+            denominator,
+            window_start: Some(window_start),
+            window_commits,
+        })
+    }
+}
+
 /// Captures the file change frequency for a file
 /// #Fields:
 /// * file_commits: the number of commits that the file has been changed in
-/// * total_commits: the total number of commits in the repository as reference
-/// * frequency: the frequency of the file being changed, as a ratio of file_commits to total_commits
+/// * total_commits: the number of commits the frequency is a share of, as counted by the
+///   [`FrequencyDenominator`]: by default, the total number of commits in the repository
+/// * frequency: the frequency of the file being changed, as a [`Percentage`] of file_commits to total_commits,
+///   or `None` if there are no such commits; of a [`FrequencyDenominator::Window`], only the
+///   file_commits in the window are counted
+/// * first_commit_date: the time of the earliest commit that added or changed the file, including
+///   the root commit (which isn't counted in file_commits)
+/// * last_commit_date: the time of the latest commit that added or changed the file
+/// * bugfix_commits: the number of the file_commits whose messages are (likely) bug fixes
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct SourceFileChangeFrequency {
-    pub file_commits: i32,
-    pub total_commits: i32,
-    pub frequency: f32,
+    pub file_commits: u64,
+    pub total_commits: u64,
+    pub frequency: Option<Percentage>,
+    #[serde(default)]
+    pub first_commit_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_commit_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub bugfix_commits: u64,
 }
 impl SourceFileChangeFrequency {
     pub fn get_as_statistics(&self) -> Statistics {
@@ -194,67 +1012,264 @@ impl SourceFileChangeFrequency {
             loc: 0,
             num_files: 0,
             num_commits: self.file_commits,
-            frequency: self.frequency,
+            change_frequency: self.frequency.unwrap_or_default(),
+            bugfix_commits: self.bugfix_commits,
+            ..Default::default()
         }
     }
     /// Gets the file change frequency for the file passed as 'source_file_path' in the repository passed as 'repo_path'
     /// #Arguments:
     /// * `repo_path` - The path to the repository
     /// * `source_file_path` - The path to the source file
-    /// Returns:
-    ///   - Ok([`SourceFileChangeFrequency`]) if successful
-    ///   - Err([`SourceCodeError`]) if unsuccessful
+    /// * `merge_strategy` - The [`MergeStrategy`] for counting changes made in merge commits
+    ///
+    /// #Returns:
+    /// * Ok([`SourceFileChangeFrequency`]) if successful
+    /// * Err([`SourceCodeError`]) if unsuccessful
+    #[cfg(feature = "git")]
     pub fn get_from_source_file(
         repo_path: &str,
-        file_path: &PathBuf,
+        file_path: &Path,
+        merge_strategy: MergeStrategy,
     ) -> Result<SourceFileChangeFrequency, SourceCodeError> {
-        // Need to trim the 'file_path' relative to the 'repo_path'
-        let repo_path_buf = PathBuf::from(repo_path);
-        let file_path = PathBuf::from(file_path);
-        let file_path = file_path
-            .strip_prefix(repo_path_buf)
-            .map_err(SourceCodeError::FilePathError)?;
-
-        let repo: Repository = Repository::open(repo_path)?;
-        let mut revwalk: Revwalk<'_> = repo.revwalk()?;
-        revwalk.push_head()?;
-
-        let mut total_commits: i32 = 0;
-        let mut file_commits: i32 = 0;
-
-        for commit_id in revwalk {
-            let commit: Commit<'_> = repo.find_commit(commit_id?)?;
-            total_commits += 1;
-
-            if commit.parent_count() > 0 {
-                let parent: Commit<'_> = commit.parent(0)?;
-                let commit_tree: Tree<'_> = commit.tree()?;
-                let parent_tree: Tree<'_> = parent.tree()?;
-
-                let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?;
-                diff.foreach(
-                    &mut |delta: DiffDelta<'_>, _| {
-                        let filepath = delta
-                            .new_file()
-                            .path()
-                            .unwrap_or(delta.old_file().path().unwrap());
-                        if filepath == file_path {
-                            file_commits += 1;
-                        }
-                        true
-                    },
-                    None,
-                    None,
-                    None,
-                )?;
+        // Need to trim the 'file_path' relative to the 'repo_path', unless it is relative already
+        let file_path: PathBuf = match paths::relative_to(file_path, Path::new(repo_path)) {
+            Some(relative) => relative,
+            None if file_path.is_relative() => file_path.to_path_buf(),
+            None => file_path
+                .strip_prefix(repo_path)
+                .map_err(SourceCodeError::FilePathError)?
+                .to_path_buf(),
+        };
+        let graph = CommitGraph::new(
+            repo_path,
+            merge_strategy,
+            HistoryMode::default(),
+            HistorySampling::default(),
+            &IdentityOptions::default(),
+            &NoopObserver,
+            &CancellationToken::new(),
+        )?;
+        Self::from_graph(
+            &graph,
+            &paths::to_slash(&file_path),
+            &FrequencyBasis::default(),
+        )
+    }
+    /// Gets the file change frequency of the file at `relative_path` from the paths changed by
+    /// the commits of the [`CommitGraph`], as counted by its [`MergeStrategy`], as a share of
+    /// the commits of the [`FrequencyBasis`]
+    #[cfg(feature = "git")]
+    pub(crate) fn from_graph(
+        graph: &CommitGraph,
+        relative_path: &str,
+        basis: &FrequencyBasis,
+    ) -> Result<SourceFileChangeFrequency, SourceCodeError> {
+        let changes = graph.changes()?;
+        let commits = graph.commits();
+        let mut first_commit_date: Option<DateTime<Utc>> = None;
+        let mut last_commit_date: Option<DateTime<Utc>> = None;
+        // The commits aren't in time order, as commit times can be skewed
+        let mut add_commit_date = |commit: &CommitNode| {
+            if let Some(date) = commit.commit_date() {
+                first_commit_date = Some(first_commit_date.map_or(date, |first| first.min(date)));
+                last_commit_date = Some(last_commit_date.map_or(date, |last| last.max(date)));
             }
+        };
+        // The root commit adds the files in its tree, which only dates the file
+        for &index in changes.commits_adding(relative_path) {
+            add_commit_date(&commits[index]);
         }
-        let frequency = file_commits as f32 / total_commits as f32 * 100.00;
+        let changing = changes.commits_changing(relative_path);
+        let mut bugfix_commits: u64 = 0;
+        for &index in changing {
+            bugfix_commits += messages::is_bugfix(&commits[index].message) as u64;
+            add_commit_date(&commits[index]);
+        }
+        let file_commits = changing.len() as u64;
+        let (counted_commits, total_commits) = match basis.denominator {
+            FrequencyDenominator::TotalCommits => (file_commits, changes.num_counted_commits()),
+            FrequencyDenominator::SinceCreation => {
+                // The commits are walked from HEAD, so the oldest of the file's is the last
+                let created = changing
+                    .iter()
+                    .chain(changes.commits_adding(relative_path))
+                    .max();
+                let since_creation = created.map_or(0, |&index| changes.num_counted_through(index));
+                (file_commits, since_creation)
+            }
+            FrequencyDenominator::Window { .. } => {
+                let in_window = changing
+                    .iter()
+                    .filter(|&&index| {
+                        commits[index]
+                            .commit_date()
+                            .zip(basis.window_start)
+                            .is_some_and(|(date, start)| date >= start)
+                    })
+                    .count() as u64;
+                (in_window, basis.window_commits)
+            }
+        };
+        let frequency = Percentage::of(counted_commits as f64, total_commits as f64);
 
         Ok(SourceFileChangeFrequency {
             file_commits,
             total_commits,
             frequency,
+            first_commit_date,
+            last_commit_date,
+            bugfix_commits,
         })
     }
 }
+
+/// The algorithm of the `id_hash` of each source file, from its raw bytes
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// The SHA-256 hash, in hex
+    #[default]
+    Sha256,
+    /// The BLAKE3 hash, in hex, which is faster to compute for large files
+    Blake3,
+    /// The id of the git blob of the bytes, i.e., the SHA-1 hash of a 'blob <length>' header
+    /// and the bytes, as `git hash-object` computes it; it is computed by libgit2, so is empty
+    /// without the `git` feature
+    GitBlob,
+}
+impl HashAlgorithm {
+    /// Hashes the `bytes` with the algorithm, as a lower case hex string
+    pub fn hash(self, bytes: &[u8]) -> String {
+        match self {
+            HashAlgorithm::Sha256 => format!("{:x}", Sha256::digest(bytes)),
+            HashAlgorithm::Blake3 => blake3::hash(bytes).to_hex().to_string(),
+            #[cfg(feature = "git")]
+            HashAlgorithm::GitBlob => Oid::hash_object(ObjectType::Blob, bytes)
+                .map(|oid| oid.to_string())
+                .unwrap_or_default(),
+            #[cfg(not(feature = "git"))]
+            HashAlgorithm::GitBlob => String::new(),
+        }
+    }
+    /// Hashes the file at `path` with the algorithm, as [`HashAlgorithm::hash`] does, streaming
+    /// it in chunks rather than reading it into memory
+    pub fn hash_file(self, path: &Path) -> std::io::Result<String> {
+        let mut file = std::fs::File::open(path)?;
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                std::io::copy(&mut file, &mut hasher)?;
+                Ok(format!("{:x}", hasher.finalize()))
+            }
+            HashAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                std::io::copy(&mut file, &mut hasher)?;
+                Ok(hasher.finalize().to_hex().to_string())
+            }
+            #[cfg(feature = "git")]
+            HashAlgorithm::GitBlob => Oid::hash_file(ObjectType::Blob, path)
+                .map(|oid| oid.to_string())
+                .map_err(std::io::Error::other),
+            #[cfg(not(feature = "git"))]
+            HashAlgorithm::GitBlob => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "git blob ids need the `git` feature",
+            )),
+        }
+    }
+    /// Hashes the blob `blob_id` in the object database `odb` with the algorithm, as
+    /// [`HashAlgorithm::hash`] does, streaming it in chunks rather than reading it into memory
+    ///
+    /// #Returns:
+    /// * The hash, or `None` if the blob can't be streamed, as only loose objects can be, not
+    ///   those in a pack; the git blob id is the blob's id, so is always known
+    #[cfg(feature = "git")]
+    pub(crate) fn hash_blob(
+        self,
+        odb: &git2::Odb<'_>,
+        blob_id: Oid,
+    ) -> std::io::Result<Option<String>> {
+        let reader = || match odb.reader(blob_id) {
+            Ok((reader, _, _)) => Ok(Some(reader)),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(std::io::Error::other(err)),
+        };
+        match self {
+            HashAlgorithm::Sha256 => {
+                let Some(mut reader) = reader()? else {
+                    return Ok(None);
+                };
+                let mut hasher = Sha256::new();
+                std::io::copy(&mut reader, &mut hasher)?;
+                Ok(Some(format!("{:x}", hasher.finalize())))
+            }
+            HashAlgorithm::Blake3 => {
+                let Some(mut reader) = reader()? else {
+                    return Ok(None);
+                };
+                let mut hasher = blake3::Hasher::new();
+                std::io::copy(&mut reader, &mut hasher)?;
+                Ok(Some(hasher.finalize().to_hex().to_string()))
+            }
+            HashAlgorithm::GitBlob => Ok(Some(blob_id.to_string())),
+        }
+    }
+}
+
+/// How merge commits count towards the change frequency of a file. A change made on a branch
+/// is counted in the branch's commit; the strategies differ in whether it is counted again in
+/// the commit that merges the branch.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Merge commits are diffed against their first parent, so a merge counts for every file
+    /// the merged branch changed, counting those changes twice in `file_commits`
+    #[default]
+    FirstParent,
+    /// Merge commits are diffed against each parent, and count for a file only if it differs
+    /// from all of them, i.e., the changes made in the merge itself, such as conflict
+    /// resolutions (as `git log --cc` shows)
+    AllParents,
+    /// Merge commits are skipped, and left out of `total_commits` too, so changes count once,
+    /// but changes made in merges themselves are missed
+    SkipMerges,
+}
+impl MergeStrategy {
+    /// Gets the paths the `commit` changed, as counted by the strategy
+    ///
+    /// #Returns:
+    /// * The changed paths, or `None` for the root commit, which adds rather than changes its
+    ///   files, and for merge commits skipped by the strategy
+    #[cfg(feature = "git")]
+    pub(crate) fn get_changed_paths(
+        self,
+        repo: &Repository,
+        commit: &Commit<'_>,
+    ) -> Result<Option<BTreeSet<String>>, SourceCodeError> {
+        let parents: Vec<Commit<'_>> = match (commit.parent_count(), self) {
+            (0, _) => return Ok(None),
+            (1, _) | (_, MergeStrategy::FirstParent) => vec![commit.parent(0)?],
+            (_, MergeStrategy::SkipMerges) => return Ok(None),
+            (_, MergeStrategy::AllParents) => commit.parents().collect(),
+        };
+        let tree = commit.tree()?;
+        let mut changed: Option<BTreeSet<String>> = None;
+        for parent in parents {
+            let diff = repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&tree), None)?;
+            let paths: BTreeSet<String> = diff
+                .deltas()
+                .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+                .map(paths::to_slash)
+                .collect();
+            // With all parents, only the changes made in the merge itself count
+            changed = Some(match changed {
+                Some(changed) => changed.intersection(&paths).cloned().collect(),
+                None => paths,
+            });
+        }
+        Ok(changed)
+    }
+}
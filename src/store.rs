@@ -0,0 +1,3 @@
+//! Persistence backends for [`RepositoryInfo`](crate::repository::RepositoryInfo) snapshots
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
@@ -0,0 +1,319 @@
+use chrono::Utc;
+#[cfg(feature = "git")]
+use git2::{Repository, Revwalk};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, path::Path};
+
+#[cfg(feature = "git")]
+use crate::head;
+use crate::{errors::SourceCodeError, repository::RepositoryInfo};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS repos (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    snapshot_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    loc INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    num_files INTEGER NOT NULL,
+    num_commits INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    UNIQUE (repo_id, snapshot_hash)
+);
+CREATE TABLE IF NOT EXISTS files (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+    relative_path TEXT NOT NULL,
+    name TEXT NOT NULL,
+    language TEXT,
+    id_hash TEXT,
+    size INTEGER NOT NULL,
+    loc INTEGER NOT NULL,
+    num_commits INTEGER NOT NULL,
+    frequency REAL NOT NULL,
+    PRIMARY KEY (snapshot_id, relative_path)
+);
+CREATE TABLE IF NOT EXISTS languages (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    num_files INTEGER NOT NULL,
+    loc INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (snapshot_id, name)
+);
+CREATE TABLE IF NOT EXISTS contributors (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    last_contribution TEXT NOT NULL,
+    percentage_contribution REAL NOT NULL,
+    num_commits INTEGER NOT NULL,
+    PRIMARY KEY (snapshot_id, name)
+);
+CREATE TABLE IF NOT EXISTS derived_metrics (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+    relative_path TEXT NOT NULL, -- '' for the repository
+    name TEXT NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (snapshot_id, relative_path, name)
+);
+CREATE TABLE IF NOT EXISTS commits (
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    id TEXT NOT NULL,
+    author TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    parent_count INTEGER NOT NULL,
+    PRIMARY KEY (repo_id, id)
+);
+";
+
+/// A SQLite-backed store of [`RepositoryInfo`] snapshots
+///
+/// Each snapshot is stored both as its full JSON (so it can be loaded back losslessly) and as
+/// normalised `files`, `languages`, `contributors` and `derived_metrics` rows for querying
+/// across runs.
+/// Snapshots are keyed by repository name and a hash of their contents (without their
+/// [`crate::metadata::AnalysisMetadata`]), so storing an unchanged snapshot again only
+/// refreshes its `created_at` timestamp.
+pub struct SqliteStore {
+    conn: Connection,
+}
+impl SqliteStore {
+    /// Opens (or creates) a store at `path` and ensures the schema exists
+    pub fn open(path: &Path) -> Result<Self, SourceCodeError> {
+        let conn = Connection::open(path).map_err(store_error)?;
+        Self::new_from(conn)
+    }
+    /// Opens a transient in-memory store, mainly useful for short-lived analyses
+    pub fn open_in_memory() -> Result<Self, SourceCodeError> {
+        let conn = Connection::open_in_memory().map_err(store_error)?;
+        Self::new_from(conn)
+    }
+    fn new_from(conn: Connection) -> Result<Self, SourceCodeError> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(store_error)?;
+        conn.execute_batch(SCHEMA).map_err(store_error)?;
+        Ok(Self { conn })
+    }
+    /// Inserts the [`RepositoryInfo`] as a new snapshot, or refreshes an identical existing one
+    ///
+    /// #Returns:
+    /// * The id of the stored snapshot
+    pub fn upsert_snapshot(
+        &mut self,
+        repository_info: &RepositoryInfo,
+    ) -> Result<i64, SourceCodeError> {
+        let data = repository_info.get_as_json()?;
+        // The run metadata differs on every analysis, so it is left out of the hash
+        let unstamped = RepositoryInfo {
+            metadata: None,
+            ..repository_info.clone()
+        };
+        let snapshot_hash = format!("{:x}", Sha256::digest(unstamped.get_as_json()?.as_bytes()));
+        let created_at = Utc::now().to_rfc3339();
+
+        let tx = self.conn.transaction().map_err(store_error)?;
+        let repo_id = Self::upsert_repo(&tx, &repository_info.name)?;
+
+        let existing: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM snapshots WHERE repo_id = ?1 AND snapshot_hash = ?2",
+                params![repo_id, snapshot_hash],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(store_error)?;
+        if let Some(snapshot_id) = existing {
+            tx.execute(
+                "UPDATE snapshots SET created_at = ?1 WHERE id = ?2",
+                params![created_at, snapshot_id],
+            )
+            .map_err(store_error)?;
+            tx.commit().map_err(store_error)?;
+            return Ok(snapshot_id);
+        }
+
+        let statistics = &repository_info.statistics;
+        tx.execute(
+            "INSERT INTO snapshots (repo_id, snapshot_hash, created_at, loc, size, num_files, num_commits, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                repo_id,
+                snapshot_hash,
+                created_at,
+                statistics.loc,
+                statistics.size,
+                statistics.num_files,
+                statistics.num_commits,
+                data
+            ],
+        )
+        .map_err(store_error)?;
+        let snapshot_id = tx.last_insert_rowid();
+
+        {
+            let mut insert_file = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO files (snapshot_id, relative_path, name, language, id_hash, size, loc, num_commits, frequency)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                )
+                .map_err(store_error)?;
+            let mut languages: HashMap<&str, (u64, u64, u64)> = HashMap::new();
+            for sfi in &repository_info.source_files {
+                let language = sfi.language.as_ref().map(|l| l.name.as_str());
+                insert_file
+                    .execute(params![
+                        snapshot_id,
+                        sfi.relative_path.as_str(),
+                        sfi.name,
+                        language,
+                        sfi.id_hash,
+                        sfi.statistics.size,
+                        sfi.statistics.loc,
+                        sfi.statistics.num_commits,
+                        sfi.statistics.change_frequency.0
+                    ])
+                    .map_err(store_error)?;
+                if let Some(language) = language {
+                    let entry = languages.entry(language).or_insert((0, 0, 0));
+                    entry.0 += 1;
+                    entry.1 = entry.1.saturating_add(sfi.statistics.loc);
+                    entry.2 = entry.2.saturating_add(sfi.statistics.size);
+                }
+            }
+
+            let mut insert_language = tx
+                .prepare(
+                    "INSERT INTO languages (snapshot_id, name, num_files, loc, size) VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(store_error)?;
+            for (name, (num_files, loc, size)) in languages {
+                insert_language
+                    .execute(params![snapshot_id, name, num_files, loc, size])
+                    .map_err(store_error)?;
+            }
+
+            let mut insert_contributor = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO contributors (snapshot_id, name, last_contribution, percentage_contribution, num_commits)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(store_error)?;
+            for contributor in &repository_info.contributors {
+                insert_contributor
+                    .execute(params![
+                        snapshot_id,
+                        contributor.author.name,
+                        contributor.last_contribution.to_rfc3339(),
+                        contributor.percentage_contribution,
+                        contributor.statistics.num_commits
+                    ])
+                    .map_err(store_error)?;
+            }
+
+            let mut insert_derived = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO derived_metrics (snapshot_id, relative_path, name, value) VALUES (?1, ?2, ?3, ?4)",
+                )
+                .map_err(store_error)?;
+            let statistics = std::iter::once(("", &repository_info.statistics)).chain(
+                repository_info
+                    .source_files
+                    .iter()
+                    .map(|sfi| (sfi.relative_path.as_str(), &sfi.statistics)),
+            );
+            for (relative_path, statistics) in statistics {
+                for (name, value) in &statistics.derived {
+                    insert_derived
+                        .execute(params![snapshot_id, relative_path, name, value])
+                        .map_err(store_error)?;
+                }
+            }
+        }
+        tx.commit().map_err(store_error)?;
+
+        Ok(snapshot_id)
+    }
+    /// Records the commits reachable from HEAD of the repository at `repo_path` against the
+    /// repository `name`. Commits already stored are left untouched.
+    ///
+    /// #Returns:
+    /// * The number of newly stored commits
+    #[cfg(feature = "git")]
+    pub fn upsert_commits(
+        &mut self,
+        name: &str,
+        repo_path: &str,
+    ) -> Result<usize, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+        let mut revwalk: Revwalk<'_> = repo.revwalk()?;
+        head::push_head(&repo, &mut revwalk)?;
+
+        let tx = self.conn.transaction().map_err(store_error)?;
+        let repo_id = Self::upsert_repo(&tx, name)?;
+        let mut inserted = 0;
+        {
+            let mut insert_commit = tx
+                .prepare(
+                    "INSERT OR IGNORE INTO commits (repo_id, id, author, time, parent_count) VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(store_error)?;
+            for commit_id in revwalk {
+                let commit = repo.find_commit(commit_id?)?;
+                inserted += insert_commit
+                    .execute(params![
+                        repo_id,
+                        commit.id().to_string(),
+                        commit.author().name().unwrap_or_default(),
+                        commit.author().when().seconds(),
+                        commit.parent_count()
+                    ])
+                    .map_err(store_error)?;
+            }
+        }
+        tx.commit().map_err(store_error)?;
+
+        Ok(inserted)
+    }
+    /// Loads the most recently stored snapshot for the repository `name`, if any
+    pub fn load_latest(&self, name: &str) -> Result<Option<RepositoryInfo>, SourceCodeError> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT s.data FROM snapshots s JOIN repos r ON r.id = s.repo_id
+                 WHERE r.name = ?1 ORDER BY s.created_at DESC, s.id DESC LIMIT 1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(store_error)?;
+
+        data.map(|data| RepositoryInfo::deserialize_any_version(&data))
+            .transpose()
+    }
+    /// Gets the underlying [`Connection`], for running custom (e.g. trend) queries
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+    fn upsert_repo(conn: &Connection, name: &str) -> Result<i64, SourceCodeError> {
+        conn.execute(
+            "INSERT OR IGNORE INTO repos (name) VALUES (?1)",
+            params![name],
+        )
+        .map_err(store_error)?;
+        conn.query_row(
+            "SELECT id FROM repos WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .map_err(store_error)
+    }
+}
+
+fn store_error(err: rusqlite::Error) -> SourceCodeError {
+    SourceCodeError::StoreError(err.into())
+}
@@ -0,0 +1,79 @@
+#[cfg(feature = "git")]
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::path::Path;
+
+use crate::repository::RepositoryInfo;
+#[cfg(feature = "git")]
+use crate::{errors::SourceCodeError, options::AnalysisOptions, paths};
+
+/// A git submodule of a repository. Its files are left out of the parent repository's
+/// statistics, so that the numbers of separate repositories aren't blended; it can be analysed
+/// as a nested [`RepositoryInfo`] instead.
+///
+/// #Fields:
+/// * `name` - The name of the submodule
+/// * `path` - The path of the submodule, relative to the parent repository
+/// * `url` - The URL of the submodule's remote, if set
+/// * `commit` - The id of the commit the parent repository's HEAD records for the submodule
+/// * `repository` - The [`RepositoryInfo`] of the submodule, if the submodules were recursed
+///   into (see [`AnalysisOptions::recurse_submodules`]) and it is checked out
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SubmoduleInfo {
+    pub name: String,
+    pub path: String,
+    pub url: Option<String>,
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<Box<RepositoryInfo>>,
+}
+impl SubmoduleInfo {
+    /// Detects the submodules of the repository at `repo_path`, without analysing them
+    #[cfg(feature = "git")]
+    pub fn detect(repo_path: &str) -> Result<Vec<SubmoduleInfo>, SourceCodeError> {
+        let repo = Repository::open(repo_path)?;
+        let mut submodules: Vec<SubmoduleInfo> = repo
+            .submodules()?
+            .iter()
+            .map(|submodule| SubmoduleInfo {
+                name: submodule.name().unwrap_or_default().to_string(),
+                path: paths::to_slash(submodule.path()),
+                url: submodule.url().map(String::from),
+                commit: submodule.head_id().map(|id| id.to_string()),
+                repository: None,
+            })
+            .collect();
+        submodules.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(submodules)
+    }
+    /// Analyses the submodule, if it is checked out, as a nested [`RepositoryInfo`] with the
+    /// [`AnalysisOptions`] of the parent repository at `repo_path`
+    #[cfg(feature = "git")]
+    pub(crate) fn analyse(
+        &mut self,
+        repo_path: &str,
+        options: &AnalysisOptions,
+    ) -> Result<(), SourceCodeError> {
+        let path = Path::new(repo_path).join(&self.path);
+        // Submodules that aren't initialised and updated have no repository to analyse
+        if Repository::open(&path).is_err() {
+            tracing::info!("Skipping submodule that is not checked out: {}", self.path);
+            return Ok(());
+        }
+        // The checkpoint file is that of the superproject's analysis, and the submodule is
+        // anonymised with it
+        let options = AnalysisOptions {
+            checkpoint: None,
+            anonymisation: None,
+            ..options.clone()
+        };
+        self.repository = Some(Box::new(RepositoryInfo::new_with_options(
+            self.name.clone(),
+            &path.to_string_lossy(),
+            &options,
+        )?));
+        Ok(())
+    }
+}
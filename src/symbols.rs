@@ -0,0 +1,277 @@
+//! Indexing the symbols, i.e., the functions, methods and types, defined in each source file,
+//! with their line ranges, so that changes and embedding chunks can be attributed to the
+//! functions and types they touch. The symbols are parsed with tree-sitter, so are only indexed
+//! with the `tree-sitter` feature, for the languages with a grammar: Rust, Python, JavaScript
+//! and Go.
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "tree-sitter")]
+use tree_sitter::{Language, Node, Parser};
+
+use crate::source::SourceFileInfo;
+
+/// The kind of a [`Symbol`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    /// A free function
+    Function,
+    /// A function of a class, impl, trait or type
+    Method,
+    Class,
+    Struct,
+    Enum,
+    Trait,
+    Interface,
+    /// A Rust impl block, named after the type it implements
+    Impl,
+    Module,
+    Macro,
+    /// Another type definition, e.g., an alias
+    Type,
+}
+
+/// A symbol defined in a source file
+///
+/// #Fields:
+/// * `name` - The name of the symbol, e.g., the function name; an impl block is named after its
+///   type, e.g., 'Display for Point'
+/// * `kind` - The [`SymbolKind`]
+/// * `start_line` - The first line of the definition (1-based)
+/// * `end_line` - The last line of the definition (1-based, inclusive)
+/// * `parent` - The name of the symbol the definition is nested in, e.g., the class of a method;
+///   a method of a Rust impl block or a Go type has the name of the type
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub start_line: usize,
+    pub end_line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+impl Symbol {
+    /// Checks whether the definition spans the `line` (1-based)
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start_line..=self.end_line).contains(&line)
+    }
+    /// Checks whether the definition overlaps the lines `start_line..=end_line` (1-based)
+    pub fn overlaps(&self, start_line: usize, end_line: usize) -> bool {
+        self.start_line <= end_line && start_line <= self.end_line
+    }
+    /// Gets the name qualified by the `parent`, e.g., 'Parser::parse'
+    pub fn get_qualified_name(&self) -> String {
+        match &self.parent {
+            Some(parent) => format!("{}::{}", parent, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Indexes the [`Symbol`]s of each of the `source_files` whose contents were read, setting its
+/// `symbols`; without the `tree-sitter` feature, or for a language without a grammar, a file has
+/// no symbols
+pub fn index(source_files: &mut [SourceFileInfo]) {
+    for source_file in source_files {
+        let Some(language) = &source_file.language else {
+            continue;
+        };
+        source_file.symbols = source_file
+            .get_contents()
+            .and_then(|contents| extract_symbols(contents, &language.name))
+            .unwrap_or_default();
+    }
+}
+
+/// Extracts the [`Symbol`]s defined in the `contents` of a source file in `language` (the
+/// [`crate::source::LanguageType`] name)
+///
+/// #Returns:
+/// * The symbols ordered by position, the enclosing ones before those nested in them, or `None`
+///   if the language has no grammar or fails to parse
+#[cfg(feature = "tree-sitter")]
+pub fn extract_symbols(contents: &str, language: &str) -> Option<Vec<Symbol>> {
+    let (grammar, kinds) = get_grammar(language)?;
+    let mut parser = Parser::new();
+    parser.set_language(grammar).ok()?;
+    let tree = parser.parse(contents, None)?;
+    let mut symbols: Vec<Symbol> = vec![];
+    collect_symbols(
+        tree.root_node(),
+        contents.as_bytes(),
+        kinds,
+        None,
+        &mut symbols,
+    );
+    symbols.sort_by(|a, b| {
+        a.start_line
+            .cmp(&b.start_line)
+            .then_with(|| b.end_line.cmp(&a.end_line))
+    });
+    Some(symbols)
+}
+
+/// Extracts the [`Symbol`]s defined in the `contents` of a source file, which needs the
+/// `tree-sitter` feature
+///
+/// #Returns:
+/// * `None`, as there are no grammars without the `tree-sitter` feature
+#[cfg(not(feature = "tree-sitter"))]
+pub fn extract_symbols(_contents: &str, _language: &str) -> Option<Vec<Symbol>> {
+    None
+}
+
+/// Gets the tree-sitter grammar for a language, and the [`SymbolKind`] of each node kind that
+/// defines a symbol
+#[cfg(feature = "tree-sitter")]
+fn get_grammar(language: &str) -> Option<(Language, &'static [(&'static str, SymbolKind)])> {
+    match language {
+        "Rust" => Some((
+            tree_sitter_rust::language(),
+            &[
+                ("function_item", SymbolKind::Function),
+                ("function_signature_item", SymbolKind::Function),
+                ("impl_item", SymbolKind::Impl),
+                ("trait_item", SymbolKind::Trait),
+                ("struct_item", SymbolKind::Struct),
+                ("union_item", SymbolKind::Struct),
+                ("enum_item", SymbolKind::Enum),
+                ("type_item", SymbolKind::Type),
+                ("mod_item", SymbolKind::Module),
+                ("macro_definition", SymbolKind::Macro),
+            ],
+        )),
+        "Python" => Some((
+            tree_sitter_python::language(),
+            &[
+                ("function_definition", SymbolKind::Function),
+                ("class_definition", SymbolKind::Class),
+            ],
+        )),
+        "JavaScript" | "JSX" => Some((
+            tree_sitter_javascript::language(),
+            &[
+                ("function_declaration", SymbolKind::Function),
+                ("generator_function_declaration", SymbolKind::Function),
+                ("class_declaration", SymbolKind::Class),
+                ("method_definition", SymbolKind::Method),
+            ],
+        )),
+        "Go" => Some((
+            tree_sitter_go::language(),
+            &[
+                ("function_declaration", SymbolKind::Function),
+                ("method_declaration", SymbolKind::Method),
+                ("type_spec", SymbolKind::Type),
+            ],
+        )),
+        _ => None,
+    }
+}
+
+/// Collects the symbols defined under the `node`, nested in the symbol named `parent`, if any
+#[cfg(feature = "tree-sitter")]
+fn collect_symbols(
+    node: Node<'_>,
+    source: &[u8],
+    kinds: &[(&str, SymbolKind)],
+    parent: Option<(&str, SymbolKind)>,
+    symbols: &mut Vec<Symbol>,
+) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        let Some(kind) = kinds
+            .iter()
+            .find(|(node_kind, _)| *node_kind == child.kind())
+            .map(|(_, kind)| *kind)
+        else {
+            // Definitions may be nested, e.g., in exports, decorators or declaration lists
+            collect_symbols(child, source, kinds, parent, symbols);
+            continue;
+        };
+        let Some(name) = get_name(child, source) else {
+            collect_symbols(child, source, kinds, parent, symbols);
+            continue;
+        };
+        let kind = get_kind(child, kind, parent.map(|(_, kind)| kind));
+        // The methods of an impl block are scoped by its type, and those of a Go type by their
+        // receiver
+        let scope = match child.kind() {
+            "impl_item" => get_text(child, "type", source),
+            _ => None,
+        };
+        let parent_name = match child.kind() {
+            "method_declaration" => get_text(child, "receiver", source)
+                .map(|receiver| get_receiver_type(&receiver))
+                .or_else(|| parent.map(|(parent, _)| parent.to_string())),
+            _ => parent.map(|(parent, _)| parent.to_string()),
+        };
+        symbols.push(Symbol {
+            name: name.clone(),
+            kind,
+            start_line: child.start_position().row + 1,
+            end_line: child.end_position().row + 1,
+            parent: parent_name,
+        });
+        let scope = scope.unwrap_or(name);
+        collect_symbols(child, source, kinds, Some((&scope, kind)), symbols);
+    }
+}
+
+/// Gets the name of a definition: its `name` field, or the type of a Rust impl block, with the
+/// trait it implements, if any
+#[cfg(feature = "tree-sitter")]
+fn get_name(node: Node<'_>, source: &[u8]) -> Option<String> {
+    match node.kind() {
+        "impl_item" => {
+            let type_name = get_text(node, "type", source)?;
+            Some(match get_text(node, "trait", source) {
+                Some(trait_name) => format!("{} for {}", trait_name, type_name),
+                None => type_name,
+            })
+        }
+        _ => get_text(node, "name", source),
+    }
+}
+
+/// Gets the text of the `field` of the `node`, with its whitespace collapsed
+#[cfg(feature = "tree-sitter")]
+fn get_text(node: Node<'_>, field: &str, source: &[u8]) -> Option<String> {
+    node.child_by_field_name(field)
+        .and_then(|child| child.utf8_text(source).ok())
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Gets the type of a Go method's `receiver`, e.g., 'Server' of '(s *Server[T])'
+#[cfg(feature = "tree-sitter")]
+fn get_receiver_type(receiver: &str) -> String {
+    let receiver = receiver.trim_matches(|c| c == '(' || c == ')');
+    let type_name = receiver.rsplit(' ').next().unwrap_or(receiver);
+    let type_name = type_name.trim_start_matches('*');
+    type_name.split('[').next().unwrap_or(type_name).to_string()
+}
+
+/// Refines the `kind` of a definition by its context: a function in a class, impl or trait is a
+/// method, and a Go type is a struct or interface by its definition
+#[cfg(feature = "tree-sitter")]
+fn get_kind(node: Node<'_>, kind: SymbolKind, parent: Option<SymbolKind>) -> SymbolKind {
+    match kind {
+        SymbolKind::Function
+            if matches!(
+                parent,
+                Some(SymbolKind::Class | SymbolKind::Impl | SymbolKind::Trait)
+            ) =>
+        {
+            SymbolKind::Method
+        }
+        SymbolKind::Type if node.kind() == "type_spec" => {
+            match node.child_by_field_name("type").map(|child| child.kind()) {
+                Some("struct_type") => SymbolKind::Struct,
+                Some("interface_type") => SymbolKind::Interface,
+                _ => SymbolKind::Type,
+            }
+        }
+        kind => kind,
+    }
+}
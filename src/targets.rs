@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{paths, source::SourceFileInfo, walk};
+
+/// The ecosystem an [`ExecutableTarget`] was declared in
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TargetEcosystem {
+    Cargo,
+    Npm,
+    Python,
+}
+
+/// An executable that the repository ships, e.g., a Cargo binary or an npm `bin` script
+///
+/// #Fields:
+/// * `name` - The name the executable is invoked by
+/// * `ecosystem` - The [`TargetEcosystem`] the executable was declared in
+/// * `entry_point` - The path (relative to the repository root) or module reference of the entry point
+/// * `declared_in` - The path of the manifest or source file that declares the executable
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ExecutableTarget {
+    pub name: String,
+    pub ecosystem: TargetEcosystem,
+    pub entry_point: String,
+    pub declared_in: String,
+}
+impl ExecutableTarget {
+    /// Enumerates the executable targets of the repository at `repo_path`:
+    /// * Cargo `[[bin]]` targets, plus the implicit `src/main.rs` and `src/bin/` targets
+    /// * npm `bin` entries in `package.json`
+    /// * Python `[project.scripts]`/`[tool.poetry.scripts]`, `__main__.py` modules and
+    ///   scripts guarded by `if __name__ == "__main__":`
+    pub fn inventory(repo_path: &str, source_files: &[SourceFileInfo]) -> Vec<ExecutableTarget> {
+        let mut targets: Vec<ExecutableTarget> = Vec::new();
+
+        for (path, contents) in walk::read_files(repo_path, |name| name == "Cargo.toml") {
+            targets.extend(Self::from_cargo_manifest(repo_path, &path, &contents));
+        }
+        for (path, contents) in walk::read_files(repo_path, |name| name == "package.json") {
+            targets.extend(Self::from_package_json(&path, &contents));
+        }
+        for (path, contents) in walk::read_files(repo_path, |name| name == "pyproject.toml") {
+            targets.extend(Self::from_pyproject(&path, &contents));
+        }
+        targets.extend(Self::from_python_sources(repo_path, source_files));
+
+        targets
+    }
+    fn new(name: &str, ecosystem: TargetEcosystem, entry_point: &str, declared_in: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ecosystem,
+            entry_point: entry_point.to_string(),
+            declared_in: declared_in.to_string(),
+        }
+    }
+    fn from_cargo_manifest(repo_path: &str, path: &str, contents: &str) -> Vec<Self> {
+        let Ok(manifest) = contents.parse::<toml::Table>() else {
+            tracing::warn!("Failed to parse Cargo manifest: {}", path);
+            return vec![];
+        };
+        let Some(package) = manifest.get("package").and_then(|p| p.as_table()) else {
+            // A virtual (workspace-only) manifest has no targets of its own
+            return vec![];
+        };
+        let package_name = package
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or_default();
+        let crate_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut targets: Vec<Self> = Vec::new();
+
+        if let Some(bins) = manifest.get("bin").and_then(|b| b.as_array()) {
+            for bin in bins.iter().filter_map(|b| b.as_table()) {
+                let name = bin
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or(package_name);
+                let entry_point = bin
+                    .get("path")
+                    .and_then(|p| p.as_str())
+                    .map(|p| paths::to_slash(&crate_dir.join(p)))
+                    .unwrap_or_else(|| {
+                        paths::to_slash(&crate_dir.join("src/bin").join(format!("{}.rs", name)))
+                    });
+                targets.push(Self::new(name, TargetEcosystem::Cargo, &entry_point, path));
+            }
+        }
+
+        let autobins = package
+            .get("autobins")
+            .and_then(|a| a.as_bool())
+            .unwrap_or(true);
+        if autobins {
+            let crate_root = Path::new(repo_path).join(crate_dir);
+            let mut implicit: Vec<(String, String)> = Vec::new();
+            if crate_root.join("src/main.rs").is_file() {
+                implicit.push((
+                    package_name.to_string(),
+                    paths::to_slash(&crate_dir.join("src/main.rs")),
+                ));
+            }
+            if let Ok(entries) = std::fs::read_dir(crate_root.join("src/bin")) {
+                for entry in entries.filter_map(Result::ok) {
+                    let entry_path = entry.path();
+                    let name = if entry_path.is_dir() && entry_path.join("main.rs").is_file() {
+                        entry.file_name().to_string_lossy().to_string()
+                    } else if entry_path.extension().map(|e| e == "rs").unwrap_or(false) {
+                        entry_path
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().to_string())
+                            .unwrap_or_default()
+                    } else {
+                        continue;
+                    };
+                    let relative = paths::normalise(&entry_path, Path::new(repo_path));
+                    implicit.push((name, relative));
+                }
+            }
+            for (name, entry_point) in implicit {
+                if !targets.iter().any(|t| t.name == name) {
+                    targets.push(Self::new(&name, TargetEcosystem::Cargo, &entry_point, path));
+                }
+            }
+        }
+        targets
+    }
+    fn from_package_json(path: &str, contents: &str) -> Vec<Self> {
+        let Ok(package) = serde_json::from_str::<serde_json::Value>(contents) else {
+            tracing::warn!("Failed to parse package.json: {}", path);
+            return vec![];
+        };
+        let package_name = package["name"].as_str().unwrap_or_default();
+        // Scoped packages expose their `bin` string under the unscoped name
+        let unscoped_name = package_name.rsplit('/').next().unwrap_or(package_name);
+        let package_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let entry = |script: &str| paths::to_slash(&package_dir.join(script));
+
+        match &package["bin"] {
+            serde_json::Value::String(script) => vec![Self::new(
+                unscoped_name,
+                TargetEcosystem::Npm,
+                &entry(script),
+                path,
+            )],
+            serde_json::Value::Object(bins) => bins
+                .iter()
+                .filter_map(|(name, script)| {
+                    Some(Self::new(
+                        name,
+                        TargetEcosystem::Npm,
+                        &entry(script.as_str()?),
+                        path,
+                    ))
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+    fn from_pyproject(path: &str, contents: &str) -> Vec<Self> {
+        let Ok(pyproject) = contents.parse::<toml::Table>() else {
+            tracing::warn!("Failed to parse pyproject.toml: {}", path);
+            return vec![];
+        };
+        let project_scripts = pyproject
+            .get("project")
+            .and_then(|p| p.get("scripts"))
+            .and_then(|s| s.as_table());
+        let poetry_scripts = pyproject
+            .get("tool")
+            .and_then(|t| t.get("poetry"))
+            .and_then(|p| p.get("scripts"))
+            .and_then(|s| s.as_table());
+
+        project_scripts
+            .into_iter()
+            .chain(poetry_scripts)
+            .flat_map(|scripts| scripts.iter())
+            .filter_map(|(name, reference)| {
+                Some(Self::new(
+                    name,
+                    TargetEcosystem::Python,
+                    reference.as_str()?,
+                    path,
+                ))
+            })
+            .collect()
+    }
+    fn from_python_sources(repo_path: &str, source_files: &[SourceFileInfo]) -> Vec<Self> {
+        source_files
+            .iter()
+            .filter(|sfi| sfi.name.ends_with(".py"))
+            .filter_map(|sfi| {
+                let contents = sfi.get_contents()?;
+                let relative_path = Path::new(&sfi.relative_path)
+                    .strip_prefix(repo_path)
+                    .unwrap_or(Path::new(&sfi.relative_path));
+                let name = if sfi.name == "__main__.py" {
+                    // `python -m <package>` runs the package's `__main__.py`
+                    relative_path
+                        .parent()
+                        .and_then(|p| p.file_name())
+                        .map(|p| p.to_string_lossy().to_string())?
+                } else if Self::has_main_guard(contents) {
+                    relative_path.file_stem()?.to_string_lossy().to_string()
+                } else {
+                    return None;
+                };
+                let relative_path = paths::to_slash(relative_path);
+                Some(Self::new(
+                    &name,
+                    TargetEcosystem::Python,
+                    &relative_path,
+                    &relative_path,
+                ))
+            })
+            .collect()
+    }
+    fn has_main_guard(contents: &str) -> bool {
+        contents.lines().any(|line| {
+            let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+            line == "if__name__==\"__main__\":" || line == "if__name__=='__main__':"
+        })
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(feature = "git")]
+use git2::{BlameOptions, Repository};
+#[cfg(feature = "git")]
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::path::Path;
+
+use crate::identity::Author;
+#[cfg(feature = "git")]
+use crate::{
+    cancel::{CancellationToken, PartialResults},
+    errors::SourceCodeError,
+    identity::{IdentityOptions, IdentityResolver},
+    observer::AnalysisStage,
+    source::SourceFileInfo,
+};
+
+/// A marker, at a word boundary, with an optional '(owner)' and ':', and the rest of the comment
+const MARKER_PATTERN: &str = r"\b(TODO|FIXME|HACK|XXX)\b(?:\([^)]*\))?:?\s*(.*)";
+/// The tokens that start a comment in most languages; a marker that doesn't follow one of them
+/// on its line, e.g., in a string, isn't counted
+const COMMENT_STARTS: [&str; 5] = ["//", "/*", "#", "--", "<!--"];
+/// The tokens that start a continuation line of a block comment, or a comment line in
+/// languages such as Lisp, Assembly and LaTeX
+const COMMENT_LINE_STARTS: [&str; 3] = ["*", ";", "%"];
+/// The tokens that end a block comment, trimmed from the text of a marker
+const COMMENT_ENDS: [&str; 2] = ["*/", "-->"];
+
+/// The kind of a [`TechDebtMarker`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TechDebtKind {
+    /// Work still to be done
+    Todo,
+    /// A known bug
+    Fixme,
+    /// A workaround that should be replaced
+    Hack,
+    /// Code that is dangerous or needs attention
+    Xxx,
+}
+impl TechDebtKind {
+    fn from_marker(marker: &str) -> Option<TechDebtKind> {
+        match marker {
+            "TODO" => Some(TechDebtKind::Todo),
+            "FIXME" => Some(TechDebtKind::Fixme),
+            "HACK" => Some(TechDebtKind::Hack),
+            "XXX" => Some(TechDebtKind::Xxx),
+            _ => None,
+        }
+    }
+}
+
+/// A TODO, FIXME, HACK or XXX marker in a comment of a source file
+///
+/// #Fields:
+/// * `path` - The relative path of the source file
+/// * `line` - The line of the marker (1-based)
+/// * `kind` - The [`TechDebtKind`] of the marker
+/// * `text` - The text of the comment after the marker, e.g., 'handle timeouts'
+/// * `author` - The [`Author`] of the line, from `git blame`, or `None` if the line isn't
+///   committed
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TechDebtMarker {
+    pub path: String,
+    pub line: usize,
+    pub kind: TechDebtKind,
+    pub text: String,
+    pub author: Option<Author>,
+}
+impl TechDebtMarker {
+    /// Finds the [`TechDebtMarker`]s in the comments of the `source_files` of the repository at
+    /// `repo_path`, and sets the `num_markers` of each file's statistics. Files whose contents
+    /// weren't kept, e.g., binary files, are skipped.
+    ///
+    /// #Returns:
+    /// * The [`TechDebtMarker`]s, in file order then by line
+    #[cfg(feature = "git")]
+    pub fn scan(
+        repo_path: &str,
+        source_files: &mut [SourceFileInfo],
+        identity: &IdentityOptions,
+        cancellation: &CancellationToken,
+    ) -> Result<Vec<TechDebtMarker>, SourceCodeError> {
+        let regex = Regex::new(MARKER_PATTERN)?;
+        let repo = Repository::open(repo_path).ok();
+        let resolver = repo
+            .as_ref()
+            .map(|repo| IdentityResolver::new(repo, identity));
+        let mut markers: Vec<TechDebtMarker> = Vec::new();
+        for source_file in source_files {
+            cancellation.check(AnalysisStage::TechDebt, || {
+                PartialResults::SourceFiles(vec![])
+            })?;
+            let Some(contents) = source_file.get_contents() else {
+                continue;
+            };
+            let mut file_markers = Self::find_in(&regex, &source_file.relative_path, contents);
+            if let (Some(repo), Some(resolver), false) = (&repo, &resolver, file_markers.is_empty())
+            {
+                let path = &source_file.relative_path;
+                if let Err(err) = Self::blame(repo, resolver, path, contents, &mut file_markers) {
+                    // E.g., the file isn't committed yet
+                    tracing::debug!("Could not blame {}: {}", path, err);
+                }
+            }
+            source_file.statistics.num_markers = file_markers.len() as u64;
+            markers.append(&mut file_markers);
+        }
+        Ok(markers)
+    }
+    /// Finds the markers in the comments of the `contents` of the file at `path`, without authors
+    #[cfg(feature = "git")]
+    fn find_in(regex: &Regex, path: &str, contents: &str) -> Vec<TechDebtMarker> {
+        let mut markers: Vec<TechDebtMarker> = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let Some(captures) = regex.captures(line) else {
+                continue;
+            };
+            let (Some(marker), Some(text)) = (captures.get(1), captures.get(2)) else {
+                continue;
+            };
+            let before = &line[..marker.start()];
+            let in_comment = COMMENT_STARTS.iter().any(|start| before.contains(start))
+                || COMMENT_LINE_STARTS
+                    .iter()
+                    .any(|start| before.trim_start().starts_with(start));
+            let Some(kind) = TechDebtKind::from_marker(marker.as_str()).filter(|_| in_comment)
+            else {
+                continue;
+            };
+            let mut text = text.as_str().trim();
+            for end in COMMENT_ENDS {
+                text = text.strip_suffix(end).unwrap_or(text).trim_end();
+            }
+            markers.push(TechDebtMarker {
+                path: path.to_string(),
+                line: index + 1,
+                kind,
+                text: text.to_string(),
+                author: None,
+            });
+        }
+        markers
+    }
+    /// Sets the author of each of the `markers` of the file at `path` with `contents` from
+    /// `git blame`, as resolved by the [`IdentityResolver`]; authors are left as `None` for
+    /// lines that aren't committed
+    #[cfg(feature = "git")]
+    fn blame(
+        repo: &Repository,
+        resolver: &IdentityResolver,
+        path: &str,
+        contents: &str,
+        markers: &mut [TechDebtMarker],
+    ) -> Result<(), git2::Error> {
+        let committed = repo.blame_file(Path::new(path), Some(&mut BlameOptions::new()))?;
+        // Blaming the contents maps the committed lines onto the lines as analysed
+        let blame = committed.blame_buffer(contents.as_bytes())?;
+        for marker in markers {
+            marker.author = blame
+                .get_line(marker.line)
+                .filter(|hunk| !hunk.final_commit_id().is_zero())
+                .map(|hunk| resolver.resolve(&hunk.final_signature()));
+        }
+        Ok(())
+    }
+}
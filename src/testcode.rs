@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{ratio::quotient, source::SourceFileInfo};
+
+/// Directories that conventionally hold only tests
+const TEST_DIRS: [&str; 6] = ["test", "tests", "__tests__", "spec", "specs", "testdata"];
+/// File name suffixes (before the extension) of test files, e.g., 'parser_test.go'
+const TEST_SUFFIXES: [&str; 6] = ["_test", ".test", "_spec", ".spec", "Test", "Tests"];
+/// File name prefixes of test files, e.g., 'test_parser.py'
+const TEST_PREFIXES: [&str; 1] = ["test_"];
+/// The attribute that marks a Rust module (or item) as test-only
+const RUST_TEST_ATTRIBUTE: &str = "#[cfg(test)]";
+
+/// The split of the lines of code into test and production code
+///
+/// #Fields:
+/// * `test_loc` - The lines of test code
+/// * `production_loc` - The lines of production code
+/// * `test_files` - The number of test files, i.e., files that only hold tests
+/// * `production_files` - The number of other source files
+/// * `ratio` - The test-to-code ratio, i.e., `test_loc` / `production_loc`, or `None` if there is
+///   no production code
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TestCodeStatistics {
+    pub test_loc: u64,
+    pub production_loc: u64,
+    pub test_files: u64,
+    pub production_files: u64,
+    pub ratio: Option<f32>,
+}
+impl TestCodeStatistics {
+    fn add(&mut self, source_file: &SourceFileInfo) {
+        self.test_loc = self.test_loc.saturating_add(source_file.test_loc);
+        self.production_loc = self.production_loc.saturating_add(
+            source_file
+                .statistics
+                .loc
+                .saturating_sub(source_file.test_loc),
+        );
+        match source_file.is_test {
+            true => self.test_files += 1,
+            false => self.production_files += 1,
+        }
+        self.ratio = quotient(self.test_loc as f64, self.production_loc as f64);
+    }
+}
+
+/// The [`TestCodeStatistics`] of a language
+///
+/// #Fields:
+/// * `name` - The name of the language
+/// * `statistics` - The [`TestCodeStatistics`] of the source files in the language
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LanguageTestCode {
+    pub name: String,
+    pub statistics: TestCodeStatistics,
+}
+
+/// The split of a repository's code into test and production code, detected by heuristics:
+/// files in test directories (e.g., 'tests/') or with test file names (e.g., 'parser_test.go',
+/// 'app.spec.ts', 'test_parser.py') are tests, as are `#[cfg(test)]` modules in Rust files
+///
+/// #Fields:
+/// * `repository` - The [`TestCodeStatistics`] of the whole repository
+/// * `languages` - The [`LanguageTestCode`] of each language, in alphabetical order
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TestCodeReport {
+    pub repository: TestCodeStatistics,
+    pub languages: Vec<LanguageTestCode>,
+}
+impl TestCodeReport {
+    /// Gets the [`TestCodeReport`] of the `source_files`, as classified by [`classify`]
+    pub fn new(source_files: &[SourceFileInfo]) -> TestCodeReport {
+        let mut report = TestCodeReport::default();
+        let mut languages: BTreeMap<&str, TestCodeStatistics> = BTreeMap::new();
+        for source_file in source_files {
+            report.repository.add(source_file);
+            if let Some(language) = &source_file.language {
+                languages
+                    .entry(language.name.as_str())
+                    .or_default()
+                    .add(source_file);
+            }
+        }
+        report.languages = languages
+            .into_iter()
+            .map(|(name, statistics)| LanguageTestCode {
+                name: name.to_string(),
+                statistics,
+            })
+            .collect();
+        report
+    }
+}
+
+/// Classifies each of the `source_files` as a test file or not, and sets its lines of test code
+pub fn classify(source_files: &mut [SourceFileInfo]) {
+    for source_file in source_files {
+        source_file.is_test = is_test_path(Path::new(&source_file.relative_path));
+        source_file.test_loc = match source_file.is_test {
+            true => source_file.statistics.loc,
+            false if source_file.relative_path.ends_with(".rs") => source_file
+                .get_contents()
+                .map(count_rust_test_lines)
+                .unwrap_or_default()
+                .min(source_file.statistics.loc),
+            false => 0,
+        };
+    }
+}
+
+/// Checks whether the file at the relative `path` is a test file, by its directories or name
+fn is_test_path(path: &Path) -> bool {
+    let in_test_dir = path
+        .parent()
+        .map(|parent| {
+            parent
+                .iter()
+                .any(|dir| TEST_DIRS.contains(&dir.to_string_lossy().as_ref()))
+        })
+        .unwrap_or(false);
+    let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy()) else {
+        return in_test_dir;
+    };
+    in_test_dir
+        || TEST_SUFFIXES.iter().any(|suffix| stem.ends_with(suffix))
+        || TEST_PREFIXES.iter().any(|prefix| stem.starts_with(prefix))
+}
+
+/// Counts the lines of code, i.e., not blank or `//` comments, of the items marked
+/// `#[cfg(test)]` in Rust `contents`, by matching the braces of each item
+fn count_rust_test_lines(contents: &str) -> u64 {
+    let mut test_lines: u64 = 0;
+    // The brace depth inside a test item, once its opening brace is seen
+    let mut depth: Option<i64> = None;
+    let mut in_test_item = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if !in_test_item {
+            if trimmed.starts_with(RUST_TEST_ATTRIBUTE) {
+                in_test_item = true;
+                depth = None;
+            } else {
+                continue;
+            }
+        }
+        if !trimmed.is_empty() && !trimmed.starts_with("//") {
+            test_lines += 1;
+        }
+        for character in trimmed.chars() {
+            match character {
+                '{' => *depth.get_or_insert(0) += 1,
+                '}' => *depth.get_or_insert(0) -= 1,
+                _ => {}
+            }
+        }
+        // The item ends when its braces are balanced, or at a ';' for items without a body
+        let ended = match depth {
+            Some(depth) => depth <= 0,
+            None => trimmed.ends_with(';'),
+        };
+        if ended {
+            in_test_item = false;
+        }
+    }
+    test_lines
+}
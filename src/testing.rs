@@ -0,0 +1,390 @@
+//! Test doubles for the embedding pipeline, so that crates integrating it can test without a
+//! vector database or a model download, and the [`FixtureRepo`] builder of temporary git
+//! repositories, so that analyses can be tested hermetically
+use async_trait::async_trait;
+#[cfg(feature = "git")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "git")]
+use git2::{Commit, Repository, RepositoryInitOptions, Signature, Time};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+#[cfg(feature = "git")]
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+#[cfg(feature = "git")]
+use tempfile::TempDir;
+
+use crate::{
+    embeddings_provider::EmbeddingsProvider,
+    errors::SourceCodeError,
+    vector_store::{memory::InMemoryVectorStore, ScoredVectorPoint, VectorPoint, VectorStore},
+};
+
+#[cfg(feature = "git")]
+use crate::{options::AnalysisOptions, repository::RepositoryInfo};
+
+/// The dimension of the vectors of a [`DeterministicModel`], by default
+const DEFAULT_DIMENSIONS: usize = 64;
+
+/// An [`EmbeddingsProvider`] that embeds texts deterministically, without a model: each word is
+/// hashed into a dimension of the vector, which is then normalised, so texts that share words
+/// are similar. It counts the calls and texts embedded, for assertions.
+#[derive(Debug)]
+pub struct DeterministicModel {
+    dimensions: usize,
+    max_tokens: Option<usize>,
+    num_calls: AtomicUsize,
+    num_texts: AtomicUsize,
+}
+impl Default for DeterministicModel {
+    fn default() -> Self {
+        Self::new(DEFAULT_DIMENSIONS)
+    }
+}
+impl DeterministicModel {
+    /// Creates the model, embedding into vectors of `dimensions`
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+            max_tokens: None,
+            num_calls: AtomicUsize::new(0),
+            num_texts: AtomicUsize::new(0),
+        }
+    }
+    /// Sets the maximum number of tokens of a text, e.g., to test splitting long chunks
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+    /// Gets the number of calls to embed texts or queries
+    pub fn num_calls(&self) -> usize {
+        self.num_calls.load(Ordering::Relaxed)
+    }
+    /// Gets the number of texts and queries embedded
+    pub fn num_texts(&self) -> usize {
+        self.num_texts.load(Ordering::Relaxed)
+    }
+    /// Embeds the `text`, without counting it
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            let hash = Sha256::digest(word.to_lowercase().as_bytes());
+            let bucket = u64::from_le_bytes(hash[..8].try_into().unwrap_or_default());
+            let sign = if hash[8] & 1 == 0 { 1.0 } else { -1.0 };
+            vector[(bucket % self.dimensions as u64) as usize] += sign;
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+#[async_trait]
+impl EmbeddingsProvider for DeterministicModel {
+    fn model_name(&self) -> &str {
+        "deterministic"
+    }
+    fn max_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, SourceCodeError> {
+        self.num_calls.fetch_add(1, Ordering::Relaxed);
+        self.num_texts.fetch_add(texts.len(), Ordering::Relaxed);
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
+
+/// A [`VectorStore`] held in memory, as an [`InMemoryVectorStore`], that records the points
+/// upserted and can fail the next calls, e.g., to test retries and resuming
+#[derive(Debug, Default)]
+pub struct MockVectorStore {
+    store: InMemoryVectorStore,
+    upserted: Mutex<Vec<(String, VectorPoint)>>,
+    num_upserts: AtomicUsize,
+    failures: AtomicUsize,
+}
+impl MockVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Makes the next `num_failures` calls to the store fail
+    pub fn fail_next(&self, num_failures: usize) {
+        self.failures.store(num_failures, Ordering::Relaxed);
+    }
+    /// Gets the number of successful calls to upsert points
+    pub fn num_upserts(&self) -> usize {
+        self.num_upserts.load(Ordering::Relaxed)
+    }
+    /// Gets the points upserted for the `repository`, in the order they were upserted,
+    /// including those upserted again
+    pub fn upserted(&self, repository: &str) -> Vec<VectorPoint> {
+        self.upserted
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .filter(|(upserted_repository, _)| upserted_repository == repository)
+            .map(|(_, point)| point.clone())
+            .collect()
+    }
+    /// Gets the number of points stored for the `repository`
+    pub fn len(&self, repository: &str) -> usize {
+        self.store.len(repository)
+    }
+    /// Checks whether no points are stored for the `repository`
+    pub fn is_empty(&self, repository: &str) -> bool {
+        self.store.is_empty(repository)
+    }
+    /// Fails the call if failures are left to inject
+    fn check_failure(&self) -> Result<(), SourceCodeError> {
+        let failing = self
+            .failures
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failures| {
+                failures.checked_sub(1)
+            })
+            .is_ok();
+        match failing {
+            true => Err(SourceCodeError::EmbeddingError(anyhow::anyhow!(
+                "Injected vector store failure"
+            ))),
+            false => Ok(()),
+        }
+    }
+}
+#[async_trait]
+impl VectorStore for MockVectorStore {
+    async fn upsert(
+        &self,
+        repository: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<usize, SourceCodeError> {
+        self.check_failure()?;
+        self.upserted
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .extend(
+                points
+                    .iter()
+                    .map(|point| (repository.to_string(), point.clone())),
+            );
+        self.num_upserts.fetch_add(1, Ordering::Relaxed);
+        self.store.upsert(repository, points).await
+    }
+    async fn get_payloads(
+        &self,
+        repository: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, Map<String, Value>>, SourceCodeError> {
+        self.check_failure()?;
+        self.store.get_payloads(repository, ids).await
+    }
+    async fn search(
+        &self,
+        repository: &str,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredVectorPoint>, SourceCodeError> {
+        self.check_failure()?;
+        self.store.search(repository, vector, limit).await
+    }
+    async fn delete_by_repo(&self, repository: &str) -> Result<(), SourceCodeError> {
+        self.check_failure()?;
+        self.store.delete_by_repo(repository).await
+    }
+    async fn list_repositories(&self) -> Result<Vec<String>, SourceCodeError> {
+        self.check_failure()?;
+        self.store.list_repositories().await
+    }
+    async fn list_points(
+        &self,
+        repository: &str,
+        with_vectors: bool,
+    ) -> Result<Vec<VectorPoint>, SourceCodeError> {
+        self.check_failure()?;
+        self.store.list_points(repository, with_vectors).await
+    }
+    async fn delete_points(&self, repository: &str, ids: &[String]) -> Result<(), SourceCodeError> {
+        self.check_failure()?;
+        self.store.delete_points(repository, ids).await
+    }
+}
+
+/// The time of the first commit of a [`FixtureRepo`], by default: 2024-01-01T00:00:00Z
+#[cfg(feature = "git")]
+const DEFAULT_START_SECS: i64 = 1_704_067_200;
+/// The seconds between the commits of a [`FixtureRepo`], unless their time is set
+#[cfg(feature = "git")]
+const COMMIT_INTERVAL_SECS: i64 = 60 * 60;
+
+/// Builds a git repository in a temporary directory, removed when it is dropped, commit by
+/// commit, for testing analyses hermetically. The commits are deterministic: their author and
+/// committer are the current author, and their times start at 2024-01-01T00:00:00Z and advance
+/// an hour per commit, unless set, so the same fixture has the same commit ids on any machine,
+/// e.g.:
+///
+/// ```text
+/// let fixture = FixtureRepo::new()?
+///     .with_file("src/main.rs", "fn main() {}\n")
+///     .commit("Add the entry point")?
+///     .with_author("Bob", "bob@example.com")
+///     .with_file("README.md", "# Example\n")
+///     .without_file("src/main.rs")
+///     .commit("Replace the code with a README")?
+///     .tag("v1.0.0")?;
+/// let stats = fixture.analyse(&AnalysisOptions::default())?;
+/// ```
+#[cfg(feature = "git")]
+pub struct FixtureRepo {
+    dir: TempDir,
+    repository: Repository,
+    name: String,
+    author: (String, String),
+    time: i64,
+    changes: Vec<(String, Option<Vec<u8>>)>,
+}
+#[cfg(feature = "git")]
+impl FixtureRepo {
+    /// Creates an empty repository, on the branch `main`, named 'fixture' and committed to by
+    /// 'Alice <alice@example.com>'
+    pub fn new() -> Result<Self, SourceCodeError> {
+        let dir = tempfile::Builder::new()
+            .prefix("fixture-repo-")
+            .tempdir()
+            .map_err(|err| SourceCodeError::FileReadError {
+                source: err,
+                path: None,
+            })?;
+        let repository = Repository::init_opts(
+            dir.path(),
+            RepositoryInitOptions::new().initial_head("main"),
+        )?;
+        Ok(Self {
+            dir,
+            repository,
+            name: "fixture".to_string(),
+            author: ("Alice".to_string(), "alice@example.com".to_string()),
+            time: DEFAULT_START_SECS,
+            changes: vec![],
+        })
+    }
+    /// Sets the name of the repository, as [`FixtureRepo::analyse`] names it
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+    /// Sets the author, and committer, of the next commits
+    pub fn with_author(mut self, name: &str, email: &str) -> Self {
+        self.author = (name.to_string(), email.to_string());
+        self
+    }
+    /// Sets the time of the next commit, the following ones an hour apart from it
+    pub fn with_time(mut self, time: DateTime<Utc>) -> Self {
+        self.time = time.timestamp();
+        self
+    }
+    /// Writes the file at the relative `path`, creating its directories, with the `contents` in
+    /// the next commit
+    pub fn with_file(mut self, path: &str, contents: impl AsRef<[u8]>) -> Self {
+        self.changes
+            .push((path.to_string(), Some(contents.as_ref().to_vec())));
+        self
+    }
+    /// Removes the file at the relative `path` in the next commit
+    pub fn without_file(mut self, path: &str) -> Self {
+        self.changes.push((path.to_string(), None));
+        self
+    }
+    /// Commits the files written and removed since the last commit to `main`, with the
+    /// `message`; a commit without changes is allowed
+    pub fn commit(mut self, message: &str) -> Result<Self, SourceCodeError> {
+        let mut index = self.repository.index()?;
+        for (path, contents) in std::mem::take(&mut self.changes) {
+            let full_path = self.dir.path().join(&path);
+            match contents {
+                Some(contents) => {
+                    if let Some(parent) = full_path.parent() {
+                        fs::create_dir_all(parent).map_err(SourceCodeError::for_path(parent))?;
+                    }
+                    fs::write(&full_path, contents)
+                        .map_err(SourceCodeError::for_path(&full_path))?;
+                    index.add_path(Path::new(&path))?;
+                }
+                None => {
+                    if let Err(err) = fs::remove_file(&full_path) {
+                        if err.kind() != ErrorKind::NotFound {
+                            return Err(SourceCodeError::for_path(&full_path)(err));
+                        }
+                    }
+                    index.remove_path(Path::new(&path))?;
+                }
+            }
+        }
+        index.write()?;
+        {
+            let tree = self.repository.find_tree(index.write_tree()?)?;
+            let signature =
+                Signature::new(&self.author.0, &self.author.1, &Time::new(self.time, 0))?;
+            let parent = match self.repository.head() {
+                Ok(head) => Some(head.peel_to_commit()?),
+                Err(_) => None,
+            };
+            let parents: Vec<&Commit> = parent.iter().collect();
+            self.repository.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )?;
+        }
+        self.time += COMMIT_INTERVAL_SECS;
+        Ok(self)
+    }
+    /// Tags the last commit with the lightweight tag `name`, e.g., to test releases
+    pub fn tag(self, name: &str) -> Result<Self, SourceCodeError> {
+        {
+            let head = self.repository.head()?.peel_to_commit()?;
+            self.repository
+                .tag_lightweight(name, head.as_object(), false)?;
+        }
+        Ok(self)
+    }
+    /// Gets the path of the repository's working directory
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+    /// Gets the repository, e.g., to make changes the builder doesn't, such as branches
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+    /// Analyses the repository, with the `options`, see [`RepositoryInfo::new_with_options`]
+    pub fn analyse(&self, options: &AnalysisOptions) -> Result<RepositoryInfo, SourceCodeError> {
+        RepositoryInfo::new_with_options(
+            self.name.clone(),
+            &self.dir.path().to_string_lossy(),
+            options,
+        )
+    }
+    /// Keeps the directory of the repository rather than removing it when dropped, e.g., to
+    /// inspect a failing test's fixture
+    ///
+    /// #Returns:
+    /// * The path of the directory kept
+    pub fn keep(self) -> PathBuf {
+        self.dir.keep()
+    }
+}
@@ -0,0 +1,81 @@
+//! Vector database backends for the embeddings of a repository's source files
+pub mod memory;
+#[cfg(feature = "embeddings")]
+pub mod qdrant;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::errors::SourceCodeError;
+
+/// A vector, with its payload, to be stored for a repository
+///
+/// #Fields:
+/// * `id` - The id of the point, unique within the repository (a UUID for Qdrant)
+/// * `vector` - The embedding vector
+/// * `payload` - The metadata stored (and returned from searches) with the vector
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct VectorPoint {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub payload: Map<String, Value>,
+}
+
+/// A point returned from a [`VectorStore::search`], with its similarity to the query vector
+///
+/// #Fields:
+/// * `id` - The id of the point
+/// * `score` - The similarity score; higher is more similar
+/// * `payload` - The metadata stored with the vector
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ScoredVectorPoint {
+    pub id: String,
+    pub score: f32,
+    pub payload: Map<String, Value>,
+}
+
+/// A vector database that stores the points of each repository separately
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Inserts or replaces (by id) the `points` of the `repository`
+    ///
+    /// #Returns:
+    /// * The number of points upserted
+    async fn upsert(
+        &self,
+        repository: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<usize, SourceCodeError>;
+    /// Gets the payloads of the points of the `repository` with the `ids`, by id; the ids that
+    /// aren't stored are left out
+    async fn get_payloads(
+        &self,
+        repository: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, Map<String, Value>>, SourceCodeError>;
+    /// Finds the `limit` points of the `repository` most similar to the `vector`, most similar first
+    async fn search(
+        &self,
+        repository: &str,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredVectorPoint>, SourceCodeError>;
+    /// Deletes all the points of the `repository`
+    async fn delete_by_repo(&self, repository: &str) -> Result<(), SourceCodeError>;
+    /// Gets the names of the repositories with points stored, in alphabetical order; a store
+    /// that names its collections after the repositories, e.g., Qdrant, gets the names of the
+    /// collections, which address the same points as the names they were made from
+    async fn list_repositories(&self) -> Result<Vec<String>, SourceCodeError>;
+    /// Gets all the points of the `repository`, with their vectors if `with_vectors` is set,
+    /// otherwise with empty vectors, e.g., to only read their payloads
+    async fn list_points(
+        &self,
+        repository: &str,
+        with_vectors: bool,
+    ) -> Result<Vec<VectorPoint>, SourceCodeError>;
+    /// Deletes the points of the `repository` with the `ids`; the ids that aren't stored are
+    /// ignored
+    async fn delete_points(&self, repository: &str, ids: &[String]) -> Result<(), SourceCodeError>;
+}
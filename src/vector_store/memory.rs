@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    errors::SourceCodeError,
+    vector_store::{ScoredVectorPoint, VectorPoint, VectorStore},
+};
+
+/// A [`VectorStore`] held in memory, searched by brute-force cosine similarity; for tests and
+/// small repositories that don't warrant running a vector database
+#[derive(Debug, Default)]
+pub struct InMemoryVectorStore {
+    repositories: RwLock<HashMap<String, HashMap<String, VectorPoint>>>,
+}
+impl InMemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Gets the number of points stored for the `repository`
+    pub fn len(&self, repository: &str) -> usize {
+        self.repositories
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(repository)
+            .map(HashMap::len)
+            .unwrap_or_default()
+    }
+    /// Checks whether no points are stored for the `repository`
+    pub fn is_empty(&self, repository: &str) -> bool {
+        self.len(repository) == 0
+    }
+    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return 0.0;
+        }
+        dot / (norm_a * norm_b)
+    }
+}
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    async fn upsert(
+        &self,
+        repository: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<usize, SourceCodeError> {
+        let num_points = points.len();
+        let mut repositories = self
+            .repositories
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stored = repositories.entry(repository.to_string()).or_default();
+        for point in points {
+            stored.insert(point.id.clone(), point);
+        }
+        Ok(num_points)
+    }
+    async fn get_payloads(
+        &self,
+        repository: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, Map<String, Value>>, SourceCodeError> {
+        let repositories = self
+            .repositories
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(stored) = repositories.get(repository) else {
+            return Ok(HashMap::new());
+        };
+        Ok(ids
+            .iter()
+            .filter_map(|id| {
+                stored
+                    .get(id)
+                    .map(|point| (id.clone(), point.payload.clone()))
+            })
+            .collect())
+    }
+    async fn search(
+        &self,
+        repository: &str,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredVectorPoint>, SourceCodeError> {
+        let repositories = self
+            .repositories
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(stored) = repositories.get(repository) else {
+            return Ok(vec![]);
+        };
+        let mut scored: Vec<ScoredVectorPoint> = stored
+            .values()
+            .map(|point| ScoredVectorPoint {
+                id: point.id.clone(),
+                score: Self::cosine_similarity(vector, &point.vector),
+                payload: point.payload.clone(),
+            })
+            .collect();
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+    async fn delete_by_repo(&self, repository: &str) -> Result<(), SourceCodeError> {
+        self.repositories
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(repository);
+        Ok(())
+    }
+    async fn list_repositories(&self) -> Result<Vec<String>, SourceCodeError> {
+        let mut repositories: Vec<String> = self
+            .repositories
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .filter(|(_, stored)| !stored.is_empty())
+            .map(|(repository, _)| repository.clone())
+            .collect();
+        repositories.sort_unstable();
+        Ok(repositories)
+    }
+    async fn list_points(
+        &self,
+        repository: &str,
+        with_vectors: bool,
+    ) -> Result<Vec<VectorPoint>, SourceCodeError> {
+        Ok(self
+            .repositories
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(repository)
+            .map(|stored| {
+                stored
+                    .values()
+                    .map(|point| VectorPoint {
+                        id: point.id.clone(),
+                        vector: match with_vectors {
+                            true => point.vector.clone(),
+                            false => vec![],
+                        },
+                        payload: point.payload.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+    async fn delete_points(&self, repository: &str, ids: &[String]) -> Result<(), SourceCodeError> {
+        let mut repositories = self
+            .repositories
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(stored) = repositories.get_mut(repository) {
+            for id in ids {
+                stored.remove(id);
+            }
+            if stored.is_empty() {
+                repositories.remove(repository);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,601 @@
+use async_trait::async_trait;
+use qdrant_client::{
+    qdrant::{
+        point_id::PointIdOptions, vector_output::Vector, vectors_config::Config,
+        vectors_output::VectorsOptions, CreateCollection, DeletePoints, Distance, GetPoints,
+        PointId, PointStruct, ScrollPoints, SearchPoints, UpsertPoints, VectorParams,
+        VectorsConfig,
+    },
+    Payload, Qdrant, QdrantError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use crate::{
+    errors::SourceCodeError,
+    vector_store::{ScoredVectorPoint, VectorPoint, VectorStore},
+};
+
+/// The Qdrant server used when `QDRANT_URL` is not set
+const DEFAULT_QDRANT_URL: &str = "http://localhost:6334";
+/// The placeholder for the repository name in the [`QdrantConfig`] collection name
+const REPOSITORY_PLACEHOLDER: &str = "{repository}";
+/// The number of points sent to Qdrant per upsert request
+const UPSERT_BATCH_SIZE: usize = 64;
+/// The number of points fetched per scroll request when listing the points of a collection
+const SCROLL_BATCH_SIZE: u32 = 256;
+/// The number of points sent to Qdrant per delete request
+const DELETE_BATCH_SIZE: usize = 256;
+
+/// The metric the similarity of vectors is measured with
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    /// The cosine similarity, for normalised embeddings, as most models produce
+    #[default]
+    Cosine,
+    /// The Euclidean distance
+    Euclid,
+    /// The dot product
+    Dot,
+    /// The Manhattan distance
+    Manhattan,
+}
+impl From<DistanceMetric> for Distance {
+    fn from(metric: DistanceMetric) -> Self {
+        match metric {
+            DistanceMetric::Cosine => Distance::Cosine,
+            DistanceMetric::Euclid => Distance::Euclid,
+            DistanceMetric::Dot => Distance::Dot,
+            DistanceMetric::Manhattan => Distance::Manhattan,
+        }
+    }
+}
+
+/// The configuration of the connection to a Qdrant server, and of the collections the points
+/// of each repository are stored in
+///
+/// #Fields:
+/// * `url` - The URL of the server's gRPC API, e.g., 'http://localhost:6334'
+/// * `api_key` - The API key of the server, e.g., of a Qdrant Cloud cluster; never serialised
+/// * `tls` - Whether to connect with TLS, which needs an 'https' URL
+/// * `collection_name` - The name of the collection of a repository, in which '{repository}' is
+///   replaced by the repository name; it is made a valid collection name. The collections of
+///   the repositories can only be listed, e.g., to drop those of archived repositories, if it
+///   has a prefix or suffix, e.g., 'code_{repository}'
+/// * `vector_size` - The dimension of the vectors of the embedding model, if known, checked
+///   against the vectors upserted; otherwise a collection is created with the dimension of the
+///   first vectors upserted into it
+/// * `distance` - The [`DistanceMetric`] of the collections created
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct QdrantConfig {
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    pub tls: bool,
+    pub collection_name: String,
+    pub vector_size: Option<u64>,
+    pub distance: DistanceMetric,
+}
+impl Default for QdrantConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_QDRANT_URL.to_string(),
+            api_key: None,
+            tls: false,
+            collection_name: REPOSITORY_PLACEHOLDER.to_string(),
+            vector_size: None,
+            distance: DistanceMetric::default(),
+        }
+    }
+}
+impl QdrantConfig {
+    /// Gets the [`QdrantConfig`] from the `QDRANT_URL`, `QDRANT_API_KEY`, `QDRANT_TLS`,
+    /// `QDRANT_COLLECTION`, `QDRANT_VECTOR_SIZE` and `QDRANT_DISTANCE` (e.g., 'cosine')
+    /// environment variables, defaulting to a local server
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if a variable isn't valid
+    pub fn from_env() -> Result<Self, SourceCodeError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let invalid = |name: &str, value: &str| {
+            SourceCodeError::ConfigError(format!("invalid {} '{}'", name, value))
+        };
+        let mut config = Self::default();
+        if let Some(url) = var("QDRANT_URL") {
+            config.url = url;
+        }
+        config.api_key = var("QDRANT_API_KEY");
+        if let Some(tls) = var("QDRANT_TLS") {
+            config.tls = tls.parse().map_err(|_| invalid("QDRANT_TLS", &tls))?;
+        }
+        if let Some(collection_name) = var("QDRANT_COLLECTION") {
+            config.collection_name = collection_name;
+        }
+        if let Some(vector_size) = var("QDRANT_VECTOR_SIZE") {
+            config.vector_size = Some(
+                vector_size
+                    .parse()
+                    .map_err(|_| invalid("QDRANT_VECTOR_SIZE", &vector_size))?,
+            );
+        }
+        if let Some(distance) = var("QDRANT_DISTANCE") {
+            config.distance = serde_json::from_value(Value::String(distance.to_lowercase()))
+                .map_err(|_| invalid("QDRANT_DISTANCE", &distance))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+    /// Gets the [`QdrantConfig`] from a TOML configuration, e.g.:
+    ///
+    /// ```toml
+    /// url = "https://xyz.cloud.qdrant.io:6334"
+    /// tls = true
+    /// collection_name = "code_{repository}"
+    /// vector_size = 384
+    /// ```
+    ///
+    /// The API key, which isn't read from the configuration, is taken from the `QDRANT_API_KEY`
+    /// environment variable.
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if the configuration isn't valid
+    pub fn from_toml(config: &str) -> Result<Self, SourceCodeError> {
+        let mut config: QdrantConfig =
+            toml::from_str(config).map_err(|err| SourceCodeError::ConfigError(err.to_string()))?;
+        config.api_key = std::env::var("QDRANT_API_KEY").ok();
+        config.validate()?;
+        Ok(config)
+    }
+    /// Checks that the URL matches `tls`, the collection name isn't empty and the vector size
+    /// isn't 0
+    fn validate(&self) -> Result<(), SourceCodeError> {
+        let https = self.url.starts_with("https://");
+        if self.tls != https {
+            return Err(SourceCodeError::ConfigError(format!(
+                "the Qdrant URL '{}' must {}use 'https' {} TLS",
+                self.url,
+                if self.tls { "" } else { "not " },
+                if self.tls { "with" } else { "without" },
+            )));
+        }
+        if self.collection_name.trim().is_empty() {
+            return Err(SourceCodeError::ConfigError(
+                "the Qdrant collection name is empty".to_string(),
+            ));
+        }
+        if self.vector_size == Some(0) {
+            return Err(SourceCodeError::ConfigError(
+                "the Qdrant vector size is 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A [`VectorStore`] backed by a Qdrant server, with a collection per repository
+pub struct QdrantVectorStore {
+    client: Qdrant,
+    config: QdrantConfig,
+    /// The collections known to exist with the vector size of the [`QdrantConfig`], or of the
+    /// vectors upserted, so they are only checked once
+    checked: Mutex<HashSet<String>>,
+}
+impl QdrantVectorStore {
+    /// Connects to the Qdrant server at `url`, e.g., 'http://localhost:6334'
+    pub fn new(url: &str) -> Result<Self, SourceCodeError> {
+        Self::with_config(QdrantConfig {
+            url: url.to_string(),
+            tls: url.starts_with("https://"),
+            ..Default::default()
+        })
+    }
+    /// Connects to the Qdrant server with the [`QdrantConfig`]
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if the configuration isn't valid
+    pub fn with_config(config: QdrantConfig) -> Result<Self, SourceCodeError> {
+        config.validate()?;
+        let client = Qdrant::from_url(&config.url)
+            .api_key(config.api_key.clone())
+            .build()
+            .map_err(Self::get_error)?;
+        Ok(Self {
+            client,
+            config,
+            checked: Mutex::new(HashSet::new()),
+        })
+    }
+    /// Connects to the Qdrant server configured by the environment variables, see
+    /// [`QdrantConfig::from_env`], defaulting to a local server
+    pub fn from_env() -> Result<Self, SourceCodeError> {
+        Self::with_config(QdrantConfig::from_env()?)
+    }
+    /// Gets the [`QdrantConfig`] of the store
+    pub fn config(&self) -> &QdrantConfig {
+        &self.config
+    }
+    /// Gets a valid Qdrant collection name from the repository name
+    pub fn get_collection_name(repository: &str) -> String {
+        repository
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c.to_ascii_lowercase(),
+                _ => '_',
+            })
+            .collect()
+    }
+    /// Gets the collection of the `repository`, from the collection name of the
+    /// [`QdrantConfig`]
+    fn collection_for(&self, repository: &str) -> String {
+        Self::get_collection_name(
+            &self
+                .config
+                .collection_name
+                .replace(REPOSITORY_PLACEHOLDER, repository),
+        )
+    }
+    /// Creates the collection, if it doesn't exist, for vectors of `vector_size`, or checks
+    /// that the existing collection holds vectors of that size, e.g., that it wasn't filled by
+    /// another embedding model
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if the size doesn't match that of the
+    ///   [`QdrantConfig`] or of the existing collection
+    async fn ensure_collection(
+        &self,
+        collection_name: &str,
+        vector_size: u64,
+    ) -> Result<(), SourceCodeError> {
+        if let Some(expected) = self.config.vector_size.filter(|size| *size != vector_size) {
+            return Err(SourceCodeError::ConfigError(format!(
+                "the embedding model produces vectors of size {}, but the Qdrant vector size is {}",
+                vector_size, expected
+            )));
+        }
+        if self
+            .checked
+            .lock()
+            .map(|checked| checked.contains(collection_name))
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+        if self
+            .client
+            .collection_exists(collection_name)
+            .await
+            .map_err(Self::get_error)?
+        {
+            let info = self
+                .client
+                .collection_info(collection_name)
+                .await
+                .map_err(Self::get_error)?;
+            let existing = info
+                .result
+                .and_then(|info| info.config)
+                .and_then(|config| config.params)
+                .and_then(|params| params.vectors_config)
+                .and_then(|vectors_config| vectors_config.config);
+            if let Some(Config::Params(params)) = existing {
+                if params.size != vector_size {
+                    return Err(SourceCodeError::ConfigError(format!(
+                        "the Qdrant collection '{}' holds vectors of size {}, not {}; delete it \
+                         to embed with another model",
+                        collection_name, params.size, vector_size
+                    )));
+                }
+            }
+        } else {
+            self.client
+                .create_collection(CreateCollection {
+                    collection_name: collection_name.to_string(),
+                    vectors_config: Some(VectorsConfig {
+                        config: Some(Config::Params(VectorParams {
+                            size: vector_size,
+                            distance: Distance::from(self.config.distance).into(),
+                            ..Default::default()
+                        })),
+                    }),
+                    ..Default::default()
+                })
+                .await
+                .map_err(Self::get_error)?;
+        }
+        if let Ok(mut checked) = self.checked.lock() {
+            checked.insert(collection_name.to_string());
+        }
+        Ok(())
+    }
+    /// Gets the prefix and suffix around the repository in the collection names, from the
+    /// collection name of the [`QdrantConfig`]
+    ///
+    /// #Returns:
+    /// * Err([`SourceCodeError::ConfigError`]) if the collection name has no '{repository}', or
+    ///   neither a prefix nor a suffix, as the collections of the repositories then can't be
+    ///   told apart from the server's other collections
+    fn get_collection_affixes(&self) -> Result<(String, String), SourceCodeError> {
+        self.config
+            .collection_name
+            .split_once(REPOSITORY_PLACEHOLDER)
+            .map(|(prefix, suffix)| {
+                (
+                    Self::get_collection_name(prefix),
+                    Self::get_collection_name(suffix),
+                )
+            })
+            .filter(|(prefix, suffix)| !prefix.is_empty() || !suffix.is_empty())
+            .ok_or_else(|| {
+                SourceCodeError::ConfigError(format!(
+                    "the Qdrant collection name '{}' needs a prefix or suffix around '{}', e.g., \
+                     'code_{}', to tell the collections of the repositories apart",
+                    self.config.collection_name, REPOSITORY_PLACEHOLDER, REPOSITORY_PLACEHOLDER
+                ))
+            })
+    }
+    /// Gets the repository a collection is named after, with the `prefix` and `suffix` of the
+    /// collection names, or `None` if the collection isn't named by them
+    fn repository_for(collection_name: &str, prefix: &str, suffix: &str) -> Option<String> {
+        collection_name
+            .strip_prefix(prefix)?
+            .strip_suffix(suffix)
+            .filter(|repository| !repository.is_empty())
+            .map(str::to_string)
+    }
+    fn get_error(err: QdrantError) -> SourceCodeError {
+        SourceCodeError::QdrantError(err.into())
+    }
+    fn get_point_id(point_id: Option<PointId>) -> String {
+        match point_id.and_then(|id| id.point_id_options) {
+            Some(PointIdOptions::Num(num)) => num.to_string(),
+            Some(PointIdOptions::Uuid(uuid)) => uuid,
+            None => String::new(),
+        }
+    }
+}
+#[async_trait]
+impl VectorStore for QdrantVectorStore {
+    async fn upsert(
+        &self,
+        repository: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<usize, SourceCodeError> {
+        let Some(vector_size) = points.first().map(|p| p.vector.len() as u64) else {
+            return Ok(0);
+        };
+        let collection_name = self.collection_for(repository);
+        self.ensure_collection(&collection_name, vector_size)
+            .await?;
+
+        let num_points = points.len();
+        let mut point_structs: Vec<PointStruct> = Vec::with_capacity(num_points);
+        for point in points {
+            let payload = Payload::from(point.payload);
+            point_structs.push(PointStruct::new(point.id, point.vector, payload));
+        }
+        for batch in point_structs.chunks(UPSERT_BATCH_SIZE) {
+            self.client
+                .upsert_points(UpsertPoints {
+                    collection_name: collection_name.clone(),
+                    wait: Some(true),
+                    points: batch.to_vec(),
+                    ..Default::default()
+                })
+                .await
+                .map_err(Self::get_error)?;
+        }
+        tracing::info!(
+            "Upserted {} points into collection: {}",
+            num_points,
+            collection_name
+        );
+        Ok(num_points)
+    }
+    async fn get_payloads(
+        &self,
+        repository: &str,
+        ids: &[String],
+    ) -> Result<HashMap<String, Map<String, Value>>, SourceCodeError> {
+        let collection_name = self.collection_for(repository);
+        if ids.is_empty()
+            || !self
+                .client
+                .collection_exists(&collection_name)
+                .await
+                .map_err(Self::get_error)?
+        {
+            return Ok(HashMap::new());
+        }
+        let point_ids: Vec<PointId> = ids.iter().cloned().map(PointId::from).collect();
+        let response = self
+            .client
+            .get_points(GetPoints {
+                collection_name,
+                ids: point_ids,
+                with_payload: Some(true.into()),
+                with_vectors: Some(false.into()),
+                ..Default::default()
+            })
+            .await
+            .map_err(Self::get_error)?;
+        Ok(response
+            .result
+            .into_iter()
+            .map(|point| {
+                (
+                    Self::get_point_id(point.id),
+                    point
+                        .payload
+                        .into_iter()
+                        .map(|(key, value)| (key, value.into_json()))
+                        .collect::<Map<String, Value>>(),
+                )
+            })
+            .collect())
+    }
+    async fn search(
+        &self,
+        repository: &str,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredVectorPoint>, SourceCodeError> {
+        let collection_name = self.collection_for(repository);
+        if !self
+            .client
+            .collection_exists(&collection_name)
+            .await
+            .map_err(Self::get_error)?
+        {
+            return Ok(vec![]);
+        }
+        let response = self
+            .client
+            .search_points(SearchPoints {
+                collection_name,
+                vector: vector.to_vec(),
+                limit: limit as u64,
+                with_payload: Some(true.into()),
+                ..Default::default()
+            })
+            .await
+            .map_err(Self::get_error)?;
+        Ok(response
+            .result
+            .into_iter()
+            .map(|point| ScoredVectorPoint {
+                id: Self::get_point_id(point.id),
+                score: point.score,
+                payload: point
+                    .payload
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into_json()))
+                    .collect::<Map<String, Value>>(),
+            })
+            .collect())
+    }
+    async fn delete_by_repo(&self, repository: &str) -> Result<(), SourceCodeError> {
+        let collection_name = self.collection_for(repository);
+        if self
+            .client
+            .collection_exists(&collection_name)
+            .await
+            .map_err(Self::get_error)?
+        {
+            self.client
+                .delete_collection(&collection_name)
+                .await
+                .map_err(Self::get_error)?;
+        }
+        if let Ok(mut checked) = self.checked.lock() {
+            checked.remove(&collection_name);
+        }
+        Ok(())
+    }
+    /// Gets the repositories of the collections named by the collection name of the
+    /// [`QdrantConfig`]; other collections on the server are left out. A collection name
+    /// without a prefix or suffix, such as the default '{repository}', would match every
+    /// collection, so is refused with a [`SourceCodeError::ConfigError`].
+    async fn list_repositories(&self) -> Result<Vec<String>, SourceCodeError> {
+        let (prefix, suffix) = self.get_collection_affixes()?;
+        let response = self
+            .client
+            .list_collections()
+            .await
+            .map_err(Self::get_error)?;
+        let mut repositories: Vec<String> = response
+            .collections
+            .iter()
+            .filter_map(|collection| Self::repository_for(&collection.name, &prefix, &suffix))
+            .collect();
+        repositories.sort_unstable();
+        Ok(repositories)
+    }
+    async fn list_points(
+        &self,
+        repository: &str,
+        with_vectors: bool,
+    ) -> Result<Vec<VectorPoint>, SourceCodeError> {
+        let collection_name = self.collection_for(repository);
+        let mut points: Vec<VectorPoint> = vec![];
+        if !self
+            .client
+            .collection_exists(&collection_name)
+            .await
+            .map_err(Self::get_error)?
+        {
+            return Ok(points);
+        }
+        let mut offset: Option<PointId> = None;
+        loop {
+            let response = self
+                .client
+                .scroll(ScrollPoints {
+                    collection_name: collection_name.clone(),
+                    offset,
+                    limit: Some(SCROLL_BATCH_SIZE),
+                    with_payload: Some(true.into()),
+                    with_vectors: Some(with_vectors.into()),
+                    ..Default::default()
+                })
+                .await
+                .map_err(Self::get_error)?;
+            points.extend(response.result.into_iter().map(|point| {
+                VectorPoint {
+                    id: Self::get_point_id(point.id),
+                    vector: match point.vectors.and_then(|vectors| vectors.vectors_options) {
+                        Some(VectorsOptions::Vector(vector)) => match vector.into_vector() {
+                            Vector::Dense(vector) => vector.data,
+                            _ => vec![],
+                        },
+                        _ => vec![],
+                    },
+                    payload: point
+                        .payload
+                        .into_iter()
+                        .map(|(key, value)| (key, value.into_json()))
+                        .collect::<Map<String, Value>>(),
+                }
+            }));
+            offset = response.next_page_offset;
+            if offset.is_none() {
+                return Ok(points);
+            }
+        }
+    }
+    async fn delete_points(&self, repository: &str, ids: &[String]) -> Result<(), SourceCodeError> {
+        let collection_name = self.collection_for(repository);
+        if ids.is_empty()
+            || !self
+                .client
+                .collection_exists(&collection_name)
+                .await
+                .map_err(Self::get_error)?
+        {
+            return Ok(());
+        }
+        for batch in ids.chunks(DELETE_BATCH_SIZE) {
+            let point_ids: Vec<PointId> = batch.iter().cloned().map(PointId::from).collect();
+            self.client
+                .delete_points(DeletePoints {
+                    collection_name: collection_name.clone(),
+                    wait: Some(true),
+                    points: Some(point_ids.into()),
+                    ..Default::default()
+                })
+                .await
+                .map_err(Self::get_error)?;
+        }
+        tracing::info!(
+            "Deleted {} points from collection: {}",
+            ids.len(),
+            collection_name
+        );
+        Ok(())
+    }
+}
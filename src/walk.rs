@@ -0,0 +1,78 @@
+//! Helpers for walking the files of a repository outside of `tokei`
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::paths;
+
+/// Directories that hold third-party or generated code and are never walked
+pub(crate) const VENDORED_DIRS: [&str; 6] =
+    [".git", "node_modules", "target", "vendor", "dist", "build"];
+
+/// Walks the entries of the repository, skipping vendored directories, without following
+/// symlinks
+fn walk_entries(root: &Path) -> impl Iterator<Item = DirEntry> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .map(|name| !VENDORED_DIRS.contains(&name))
+                .unwrap_or(true)
+        })
+        .filter_map(Result::ok)
+}
+/// Finds the files in the repository whose file name matches `predicate`, skipping
+/// vendored directories and symlinks
+///
+/// #Returns:
+/// * A [`Vec`] of (path relative to `repo_path`, with forward slashes, absolute path) pairs
+pub(crate) fn find_files<F>(repo_path: &str, predicate: F) -> Vec<(String, PathBuf)>
+where
+    F: Fn(&str) -> bool,
+{
+    let root = Path::new(repo_path);
+    walk_entries(root)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.file_name().to_str().map(&predicate).unwrap_or(false))
+        .map(|entry| {
+            let relative_path = paths::normalise(entry.path(), root);
+            (relative_path, entry.into_path())
+        })
+        .collect()
+}
+/// Finds the symlinks in the repository, skipping vendored directories
+///
+/// #Returns:
+/// * A [`Vec`] of (path relative to `repo_path`, with forward slashes, absolute path) pairs
+pub(crate) fn find_symlinks(repo_path: &str) -> Vec<(String, PathBuf)> {
+    let root = Path::new(repo_path);
+    walk_entries(root)
+        .filter(|entry| entry.path_is_symlink())
+        .map(|entry| {
+            let relative_path = paths::normalise(entry.path(), root);
+            (relative_path, entry.into_path())
+        })
+        .collect()
+}
+/// Finds and reads the files in the repository whose file name matches `predicate`, skipping
+/// vendored directories and files that can't be read as UTF-8
+///
+/// #Returns:
+/// * A [`Vec`] of (path relative to `repo_path`, file contents) pairs
+pub(crate) fn read_files<F>(repo_path: &str, predicate: F) -> Vec<(String, String)>
+where
+    F: Fn(&str) -> bool,
+{
+    find_files(repo_path, predicate)
+        .into_iter()
+        .filter_map(|(relative_path, path)| {
+            fs::read_to_string(paths::long_path(&path))
+                .ok()
+                .map(|contents| (relative_path, contents))
+        })
+        .collect()
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of problem an [`AnalysisWarning`] reports
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// The file looks binary (it contains NUL bytes), so its contents weren't kept
+    Binary,
+    /// The file isn't valid UTF-8, so its contents aren't analysed as text; only in snapshots
+    /// from before other encodings were decoded
+    InvalidUtf8,
+    /// The code, comment and blank lines `tokei` counted don't add up to the physical lines of
+    /// the file, so it may be misclassified; only checked if requested in the
+    /// [`crate::options::AnalysisOptions`]
+    LineCountMismatch,
+    /// The file is over the `max_file_size` of the [`crate::limits::ResourceLimits`], so it was
+    /// hashed without being read into memory, and its contents weren't kept
+    Oversized,
+    /// The file couldn't be analysed, e.g., it is unreadable or was deleted during the
+    /// analysis, so it was left out of it; the message has the error
+    Unreadable,
+}
+
+/// A problem with a file that didn't stop the analysis, e.g., a source file whose contents
+/// couldn't be decoded; the file is still counted, without its contents
+///
+/// #Fields:
+/// * `path` - The relative path of the file from the root of the repository
+/// * `kind` - The [`WarningKind`] of the problem
+/// * `message` - A description of the problem
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnalysisWarning {
+    pub path: String,
+    pub kind: WarningKind,
+    pub message: String,
+}
+impl AnalysisWarning {
+    pub fn new(path: String, kind: WarningKind) -> Self {
+        let message = match kind {
+            WarningKind::Binary => "Binary file; contents not kept",
+            WarningKind::InvalidUtf8 => "File is not valid UTF-8; contents not analysed as text",
+            WarningKind::LineCountMismatch => "Line counts don't match the physical lines",
+            WarningKind::Oversized => "File over the maximum file size; contents not kept",
+            WarningKind::Unreadable => "File couldn't be analysed; left out of the analysis",
+        };
+        Self {
+            path,
+            kind,
+            message: message.to_string(),
+        }
+    }
+    /// Replaces the default message of the [`WarningKind`] with a more specific `message`
+    pub fn with_message(mut self, message: String) -> Self {
+        self.message = message;
+        self
+    }
+}
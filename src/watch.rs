@@ -0,0 +1,227 @@
+//! Continuous analysis of a repository, re-run whenever HEAD moves, e.g., on a commit or a
+//! checkout, or whenever the files of its working tree change, e.g., for live dashboards
+//! during development
+use git2::Repository;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use crate::{
+    analyzer::RepositoryAnalyzer, cancel::CancellationToken, errors::SourceCodeError,
+    head::HeadState, options::AnalysisOptions, paths, repository::RepositoryInfo,
+};
+
+/// How long the working tree must be quiet after a change before it is analysed again
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+/// How often HEAD is checked for a new commit
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Why a [`RepositoryWatcher`] analysed the repository
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WatchTrigger {
+    /// The first analysis, when the watch started
+    Started,
+    /// HEAD moved to another commit, e.g., a new commit, a checkout or a reset
+    HeadMoved,
+    /// Files of the working tree were created, changed or removed
+    FilesChanged,
+}
+
+/// An analysis by a [`RepositoryWatcher`], passed to its callback with the [`RepositoryInfo`]
+///
+/// #Fields:
+/// * `trigger` - The [`WatchTrigger`] of the analysis
+/// * `head` - The id of the commit at HEAD, or `None` if HEAD is unborn
+/// * `previous_head` - The id of the commit at HEAD at the previous analysis, if any
+/// * `changed_paths` - The relative paths of the files of the working tree that changed since
+///   the previous analysis, by path, ignoring those git ignores
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WatchEvent {
+    pub trigger: WatchTrigger,
+    pub head: Option<String>,
+    pub previous_head: Option<String>,
+    pub changed_paths: Vec<String>,
+}
+
+/// Watches a repository, analysing it again, with the previous [`RepositoryInfo`] as the
+/// baseline so that only the new commits are walked, whenever HEAD moves. The files of the
+/// working tree are only watched if the [`AnalysisOptions`] read or report them, i.e.,
+/// `include_uncommitted` or `include_working_tree`, as otherwise the results only depend on
+/// HEAD.
+pub struct RepositoryWatcher {
+    name: String,
+    repo_path: String,
+    options: AnalysisOptions,
+    debounce: Duration,
+    poll_interval: Duration,
+    cancellation: CancellationToken,
+}
+impl RepositoryWatcher {
+    pub fn new(name: &str, repo_path: &str, options: AnalysisOptions) -> Self {
+        Self {
+            name: name.to_string(),
+            repo_path: repo_path.to_string(),
+            options,
+            debounce: DEFAULT_DEBOUNCE,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            cancellation: CancellationToken::new(),
+        }
+    }
+    /// Waits until the working tree has been quiet for the `debounce` after a change before
+    /// analysing it again, e.g., so that saving many files at once is analysed once
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+    /// Checks whether HEAD moved every `poll_interval`
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+    /// Stops watching, and any analysis in progress, when the [`CancellationToken`] is
+    /// cancelled
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+    /// Analyses the repository, then again on each change, passing each [`RepositoryInfo`] and
+    /// its [`WatchEvent`] to `on_analysis`, until the [`CancellationToken`] is cancelled. An
+    /// analysis that fails after the first, e.g., as a file is being written, is logged and
+    /// retried on the next change.
+    ///
+    /// #Returns:
+    /// * `Ok(())` once cancelled
+    /// * Err([`SourceCodeError`]) if the first analysis fails, or the repository can't be watched
+    pub fn watch(
+        &self,
+        mut on_analysis: impl FnMut(&RepositoryInfo, &WatchEvent),
+    ) -> Result<(), SourceCodeError> {
+        let repo = Repository::open(&self.repo_path)?;
+        let watch_files = self.options.include_uncommitted || self.options.include_working_tree;
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher: Option<(RecommendedWatcher, PathBuf)> = None;
+        if let (true, Some(workdir)) = (watch_files, repo.workdir()) {
+            let root = workdir.canonicalize()?;
+            let mut files_watcher = notify::recommended_watcher(sender)
+                .map_err(|err| SourceCodeError::WatchError(err.into()))?;
+            files_watcher
+                .watch(&root, RecursiveMode::Recursive)
+                .map_err(|err| SourceCodeError::WatchError(err.into()))?;
+            watcher = Some((files_watcher, root));
+        }
+
+        let analyzer = RepositoryAnalyzer::new(self.options.clone())
+            .with_cancellation(self.cancellation.clone());
+        let mut head = HeadState::resolve(&repo)?.get_commit().map(str::to_string);
+        let mut previous = match analyzer.analyse(self.name.clone(), &self.repo_path) {
+            Err(SourceCodeError::Cancelled { .. }) => return Ok(()),
+            result => result?,
+        };
+        on_analysis(
+            &previous,
+            &WatchEvent {
+                trigger: WatchTrigger::Started,
+                head: head.clone(),
+                previous_head: None,
+                changed_paths: Vec::new(),
+            },
+        );
+
+        while !self.cancellation.is_cancelled() {
+            let changed_paths = match &watcher {
+                Some((_, root)) => self.wait_for_changes(&repo, root, &receiver)?,
+                None => {
+                    std::thread::sleep(self.poll_interval);
+                    BTreeSet::new()
+                }
+            };
+            let current_head = HeadState::resolve(&repo)?.get_commit().map(str::to_string);
+            let trigger = match (current_head != head, changed_paths.is_empty()) {
+                (true, _) => WatchTrigger::HeadMoved,
+                (false, false) => WatchTrigger::FilesChanged,
+                (false, true) => continue,
+            };
+            let result = RepositoryAnalyzer::new(self.options.clone())
+                .with_cancellation(self.cancellation.clone())
+                .with_baseline(&previous)
+                .analyse(self.name.clone(), &self.repo_path);
+            match result {
+                Ok(repository_info) => {
+                    let event = WatchEvent {
+                        trigger,
+                        head: current_head.clone(),
+                        previous_head: head,
+                        changed_paths: changed_paths.into_iter().collect(),
+                    };
+                    on_analysis(&repository_info, &event);
+                    previous = repository_info;
+                    head = current_head;
+                }
+                Err(SourceCodeError::Cancelled { .. }) => break,
+                Err(err) => tracing::warn!("Failed to analyse {} on a change: {}", self.name, err),
+            }
+        }
+        Ok(())
+    }
+    /// Watches the repository as [`RepositoryWatcher::watch`] does, keeping each analysis as the
+    /// latest snapshot of the `snapshots`, e.g., those served by [`crate::serve::router`]
+    #[cfg(feature = "serve")]
+    pub fn watch_into(
+        &self,
+        snapshots: &crate::serve::MemorySnapshots,
+    ) -> Result<(), SourceCodeError> {
+        self.watch(|repository_info, _| snapshots.insert(repository_info.clone()))
+    }
+    /// Waits up to the poll interval for a change to the working tree under the `root`, then
+    /// until it is quiet for the debounce
+    ///
+    /// #Returns:
+    /// * The relative paths of the files that changed, if any, without those git ignores
+    fn wait_for_changes(
+        &self,
+        repo: &Repository,
+        root: &Path,
+        receiver: &Receiver<notify::Result<Event>>,
+    ) -> Result<BTreeSet<String>, SourceCodeError> {
+        let mut changed_paths: BTreeSet<String> = BTreeSet::new();
+        let mut timeout = self.poll_interval;
+        while !self.cancellation.is_cancelled() {
+            let event = match receiver.recv_timeout(timeout) {
+                Ok(event) => event.map_err(|err| SourceCodeError::WatchError(err.into()))?,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(SourceCodeError::WatchError(anyhow::anyhow!(
+                        "the file watcher stopped"
+                    )))
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                let Ok(relative) = path.strip_prefix(root) else {
+                    continue;
+                };
+                // Changes to the refs are picked up from HEAD
+                if relative.components().next() == Some(Component::Normal(".git".as_ref()))
+                    || repo.is_path_ignored(relative).unwrap_or(false)
+                {
+                    continue;
+                }
+                changed_paths.insert(paths::to_slash(relative));
+            }
+            if !changed_paths.is_empty() {
+                timeout = self.debounce;
+            }
+        }
+        Ok(changed_paths)
+    }
+}
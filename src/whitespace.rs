@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::source::SourceFileInfo;
+
+/// The line endings of a text file
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LineEndings {
+    /// Only '\n', as on Unix
+    Lf,
+    /// Only '\r\n', as on Windows
+    Crlf,
+    /// Both '\n' and '\r\n'
+    Mixed,
+}
+
+/// The whitespace of a text file, e.g., for a codebase audit
+///
+/// #Fields:
+/// * `line_endings` - The [`LineEndings`] of the file, or `None` if it has no line breaks
+/// * `trailing_whitespace_lines` - The number of lines that end with spaces or tabs
+/// * `final_newline` - Whether the file ends with a line break, or is empty
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WhitespaceStatistics {
+    pub line_endings: Option<LineEndings>,
+    pub trailing_whitespace_lines: u64,
+    pub final_newline: bool,
+}
+impl WhitespaceStatistics {
+    /// Gets the [`WhitespaceStatistics`] of the (UTF-8) `contents` of a file
+    pub fn new(contents: &[u8]) -> Self {
+        let mut num_lf = 0;
+        let mut num_crlf = 0;
+        let mut trailing_whitespace_lines = 0;
+        for line in contents.split_inclusive(|byte| *byte == b'\n') {
+            let line = match line.strip_suffix(b"\n") {
+                Some(line) => match line.strip_suffix(b"\r") {
+                    Some(line) => {
+                        num_crlf += 1;
+                        line
+                    }
+                    None => {
+                        num_lf += 1;
+                        line
+                    }
+                },
+                None => line,
+            };
+            if matches!(line.last(), Some(b' ' | b'\t')) {
+                trailing_whitespace_lines += 1;
+            }
+        }
+        let line_endings = match (num_lf, num_crlf) {
+            (0, 0) => None,
+            (_, 0) => Some(LineEndings::Lf),
+            (0, _) => Some(LineEndings::Crlf),
+            _ => Some(LineEndings::Mixed),
+        };
+        Self {
+            line_endings,
+            trailing_whitespace_lines,
+            final_newline: contents.is_empty() || contents.ends_with(b"\n"),
+        }
+    }
+}
+
+/// The whitespace hygiene of the (text) source files of a repository, from their
+/// [`WhitespaceStatistics`]
+///
+/// #Fields:
+/// * `num_lf` - The number of files with only '\n' line endings
+/// * `num_crlf` - The number of files with only '\r\n' line endings
+/// * `num_mixed` - The number of files with mixed line endings
+/// * `num_trailing_whitespace` - The number of files with lines that end with whitespace
+/// * `trailing_whitespace_lines` - The total number of lines that end with whitespace
+/// * `num_missing_final_newline` - The number of files that don't end with a line break
+/// * `mixed_files` - The relative paths of the files with mixed line endings
+/// * `missing_final_newline_files` - The relative paths of the files that don't end with a line
+///   break
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WhitespaceReport {
+    pub num_lf: u64,
+    pub num_crlf: u64,
+    pub num_mixed: u64,
+    pub num_trailing_whitespace: u64,
+    pub trailing_whitespace_lines: u64,
+    pub num_missing_final_newline: u64,
+    pub mixed_files: Vec<String>,
+    pub missing_final_newline_files: Vec<String>,
+}
+impl WhitespaceReport {
+    /// Gets the [`WhitespaceReport`] of the `source_files`; binary files aren't included
+    pub fn new(source_files: &[SourceFileInfo]) -> Self {
+        let mut report = Self::default();
+        for source_file in source_files {
+            let Some(whitespace) = &source_file.whitespace else {
+                continue;
+            };
+            match whitespace.line_endings {
+                Some(LineEndings::Lf) => report.num_lf += 1,
+                Some(LineEndings::Crlf) => report.num_crlf += 1,
+                Some(LineEndings::Mixed) => {
+                    report.num_mixed += 1;
+                    report
+                        .mixed_files
+                        .push(source_file.relative_path.to_string());
+                }
+                None => {}
+            }
+            if whitespace.trailing_whitespace_lines > 0 {
+                report.num_trailing_whitespace += 1;
+                report.trailing_whitespace_lines = report
+                    .trailing_whitespace_lines
+                    .saturating_add(whitespace.trailing_whitespace_lines);
+            }
+            if !whitespace.final_newline {
+                report.num_missing_final_newline += 1;
+                report
+                    .missing_final_newline_files
+                    .push(source_file.relative_path.to_string());
+            }
+        }
+        report.mixed_files.sort();
+        report.missing_final_newline_files.sort();
+        report
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "git")]
+use std::path::Path;
+
+#[cfg(feature = "git")]
+use crate::options::AnalysisOptions;
+use crate::{
+    data::Statistics, delta::RepositoryDelta, errors::SourceCodeError, report::ComparativeReport,
+    repository::RepositoryInfo,
+};
+
+/// The statistics of many repositories (e.g., an organisation's fleet), with statistics
+/// aggregated across them
+///
+/// #Fields:
+/// * `repositories` - The [`RepositoryInfo`]s of the repositories analysed, in the order given
+/// * `failures` - The [`WorkspaceFailure`]s for the repositories that couldn't be analysed
+/// * `languages` - The [`LanguageTotal`]s across the repositories, ordered by LOC, largest first
+/// * `shared_contributors` - The [`SharedContributor`]s to more than one of the repositories
+/// * `churn_ranking` - The [`RepositoryChurn`] of each repository, ordered from the highest churn
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct WorkspaceInfo {
+    pub repositories: Vec<RepositoryInfo>,
+    pub failures: Vec<WorkspaceFailure>,
+    pub languages: Vec<LanguageTotal>,
+    pub shared_contributors: Vec<SharedContributor>,
+    pub churn_ranking: Vec<RepositoryChurn>,
+}
+impl WorkspaceInfo {
+    /// Analyses the repositories at `paths`, each named after its directory, and aggregates
+    /// their statistics. A repository that fails to analyse is recorded as a
+    /// [`WorkspaceFailure`] rather than failing the whole workspace.
+    ///
+    /// #Arguments:
+    /// * `paths` - The paths to the repositories
+    /// * `options` - The [`AnalysisOptions`] used for every repository
+    /// * `parallel` - Whether to analyse the repositories across the available threads, up to
+    ///   the [`crate::limits::ResourceLimits`] of the `options`
+    #[cfg(feature = "git")]
+    pub fn analyze(paths: &[&str], options: &AnalysisOptions, parallel: bool) -> WorkspaceInfo {
+        let results: Vec<Result<RepositoryInfo, SourceCodeError>> = match parallel {
+            true => Self::analyze_parallel(paths, options),
+            false => paths
+                .iter()
+                .map(|path| Self::analyze_repository(path, options))
+                .collect(),
+        };
+
+        let mut repositories: Vec<RepositoryInfo> = Vec::new();
+        let mut failures: Vec<WorkspaceFailure> = Vec::new();
+        for (path, result) in paths.iter().zip(results) {
+            match result {
+                Ok(repository_info) => repositories.push(repository_info),
+                Err(err) => {
+                    tracing::warn!("Failed to analyse repository {}: {}", path, err);
+                    failures.push(WorkspaceFailure {
+                        path: path.to_string(),
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+        Self::new_from(repositories, failures)
+    }
+    /// Aggregates the statistics of already analysed `repositories`
+    pub fn new_from(
+        repositories: Vec<RepositoryInfo>,
+        failures: Vec<WorkspaceFailure>,
+    ) -> WorkspaceInfo {
+        let languages = LanguageTotal::aggregate(&repositories);
+        let shared_contributors = SharedContributor::find(&repositories);
+        let churn_ranking = RepositoryChurn::rank(&repositories);
+        WorkspaceInfo {
+            repositories,
+            failures,
+            languages,
+            shared_contributors,
+            churn_ranking,
+        }
+    }
+    /// Gets the [`Statistics`] of the repositories added together
+    pub fn get_total_statistics(&self) -> Statistics {
+        self.repositories
+            .iter()
+            .map(|repository_info| &repository_info.statistics)
+            .sum()
+    }
+    /// Gets a [`ComparativeReport`] of the repositories
+    pub fn get_comparative_report(&self) -> ComparativeReport {
+        ComparativeReport::new(&self.repositories)
+    }
+    /// Gets the [`WorkspaceInfo`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    #[cfg(feature = "git")]
+    fn analyze_repository(
+        path: &str,
+        options: &AnalysisOptions,
+    ) -> Result<RepositoryInfo, SourceCodeError> {
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        RepositoryInfo::new_with_options(name, path, options)
+    }
+    /// Analyses the repositories at `paths` across the worker threads allowed by the
+    /// [`crate::limits::ResourceLimits`] of the `options`, keeping their order
+    #[cfg(feature = "git")]
+    fn analyze_parallel(
+        paths: &[&str],
+        options: &AnalysisOptions,
+    ) -> Vec<Result<RepositoryInfo, SourceCodeError>> {
+        options
+            .limits
+            .map_chunks(paths, |paths| {
+                paths
+                    .iter()
+                    .map(|path| Self::analyze_repository(path, options))
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flat_map(|(paths, results)| {
+                results.unwrap_or_else(|_| {
+                    paths
+                        .iter()
+                        .map(|path| {
+                            Err(SourceCodeError::UnsupportedRepo {
+                                path: path.into(),
+                                reason: "the analysis panicked".to_string(),
+                            })
+                        })
+                        .collect()
+                })
+            })
+            .collect()
+    }
+}
+
+/// A repository of a [`WorkspaceInfo`] that couldn't be analysed
+///
+/// #Fields:
+/// * `path` - The path to the repository
+/// * `error` - The error from the analysis
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct WorkspaceFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// The statistics of a language summed across the repositories of a [`WorkspaceInfo`]
+///
+/// #Fields:
+/// * `name` - The name of the language
+/// * `loc` - The total lines of code in the language
+/// * `size` - The total size of the source files in the language, in bytes
+/// * `num_files` - The number of source files in the language
+/// * `num_repositories` - The number of repositories with source files in the language
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LanguageTotal {
+    pub name: String,
+    pub loc: u64,
+    pub size: u64,
+    pub num_files: u64,
+    pub num_repositories: u64,
+}
+impl LanguageTotal {
+    fn aggregate(repositories: &[RepositoryInfo]) -> Vec<LanguageTotal> {
+        let mut totals: BTreeMap<&str, LanguageTotal> = BTreeMap::new();
+        for repository_info in repositories {
+            for language in &repository_info.languages {
+                let total = totals
+                    .entry(language.name.as_str())
+                    .or_insert_with(|| LanguageTotal {
+                        name: language.name.clone(),
+                        loc: 0,
+                        size: 0,
+                        num_files: 0,
+                        num_repositories: 0,
+                    });
+                if let Some(statistics) = &language.statistics {
+                    total.loc = total.loc.saturating_add(statistics.loc);
+                    total.size = total.size.saturating_add(statistics.size);
+                    total.num_files = total.num_files.saturating_add(statistics.num_files);
+                }
+                total.num_repositories += 1;
+            }
+        }
+        let mut totals: Vec<LanguageTotal> = totals.into_values().collect();
+        totals.sort_by_key(|total| std::cmp::Reverse(total.loc));
+        totals
+    }
+}
+
+/// A contributor (by name) to more than one repository of a [`WorkspaceInfo`]
+///
+/// #Fields:
+/// * `name` - The name of the contributor
+/// * `repositories` - The names of the repositories contributed to, in alphabetical order
+/// * `num_commits` - The number of commits by the contributor across the repositories
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SharedContributor {
+    pub name: String,
+    pub repositories: Vec<String>,
+    pub num_commits: u64,
+}
+impl SharedContributor {
+    /// Finds the contributors to more than one repository, ordered by the number of
+    /// repositories, most first, then by name
+    fn find(repositories: &[RepositoryInfo]) -> Vec<SharedContributor> {
+        let mut contributions: BTreeMap<&str, (BTreeSet<&str>, u64)> = BTreeMap::new();
+        for repository_info in repositories {
+            for contributor in &repository_info.contributors {
+                let entry = contributions
+                    .entry(contributor.author.name.as_str())
+                    .or_default();
+                entry.0.insert(repository_info.name.as_str());
+                entry.1 = entry.1.saturating_add(contributor.statistics.num_commits);
+            }
+        }
+        let mut shared: Vec<SharedContributor> = contributions
+            .into_iter()
+            .filter(|(_, (repositories, _))| repositories.len() > 1)
+            .map(|(name, (repositories, num_commits))| SharedContributor {
+                name: name.to_string(),
+                repositories: repositories.into_iter().map(String::from).collect(),
+                num_commits,
+            })
+            .collect();
+        shared.sort_by_key(|contributor| std::cmp::Reverse(contributor.repositories.len()));
+        shared
+    }
+}
+
+/// The churn of a repository of a [`WorkspaceInfo`]
+///
+/// #Fields:
+/// * `name` - The name of the repository
+/// * `churn` - The churn, i.e., the sum of the per-file commit counts, as in [`RepositoryDelta`]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RepositoryChurn {
+    pub name: String,
+    pub churn: u64,
+}
+impl RepositoryChurn {
+    /// Ranks the repositories by churn, highest first, then by name
+    fn rank(repositories: &[RepositoryInfo]) -> Vec<RepositoryChurn> {
+        let mut ranking: Vec<RepositoryChurn> = repositories
+            .iter()
+            .map(|repository_info| RepositoryChurn {
+                name: repository_info.name.clone(),
+                churn: RepositoryDelta::get_churn(repository_info),
+            })
+            .collect();
+        ranking.sort_by(|a, b| b.churn.cmp(&a.churn).then_with(|| a.name.cmp(&b.name)));
+        ranking
+    }
+}
@@ -0,0 +1,299 @@
+#[cfg(feature = "git")]
+use git2::{Delta, Diff, DiffOptions, Patch, Repository, Status, StatusOptions};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "git")]
+use std::collections::HashMap;
+
+use crate::errors::SourceCodeError;
+#[cfg(feature = "git")]
+use crate::{
+    data::{saturating_sum, to_count},
+    paths,
+    retry::retry_on_lock,
+};
+
+/// The uncommitted state of a repository's working tree, relative to HEAD
+///
+/// #Fields:
+/// * `modified_files` - The relative paths of tracked files with staged or unstaged changes
+/// * `untracked_files` - The relative paths of untracked (and not ignored) files
+/// * `staged` - The [`DiffStatistics`] for the changes staged in the index
+/// * `unstaged` - The [`DiffStatistics`] for the changes to tracked files not yet staged
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkingTreeStatistics {
+    pub modified_files: Vec<String>,
+    pub untracked_files: Vec<String>,
+    pub staged: DiffStatistics,
+    pub unstaged: DiffStatistics,
+}
+impl WorkingTreeStatistics {
+    /// Computes the [`WorkingTreeStatistics`] for the repository at `repo_path`
+    ///
+    /// #Arguments:
+    /// * `repo_path` - The path to the repository, which must have a working tree (i.e., not bare)
+    #[cfg(feature = "git")]
+    pub fn compute(repo_path: &str) -> Result<WorkingTreeStatistics, SourceCodeError> {
+        let repo: Repository = Repository::open(repo_path)?;
+
+        let mut status_options = StatusOptions::new();
+        status_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        let mut modified_files: Vec<String> = Vec::new();
+        let mut untracked_files: Vec<String> = Vec::new();
+        let statuses = retry_on_lock(|| repo.statuses(Some(&mut status_options)))?;
+        for entry in statuses.iter() {
+            let Some(path) = entry.path() else {
+                continue;
+            };
+            let status = entry.status();
+            if status.contains(Status::WT_NEW) && !status.intersects(Self::staged_statuses()) {
+                untracked_files.push(path.to_string());
+            } else if !status.is_ignored() {
+                modified_files.push(path.to_string());
+            }
+        }
+        modified_files.sort();
+        untracked_files.sort();
+
+        // An unborn HEAD (no commits yet) stages everything against an empty tree
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let staged = retry_on_lock(|| repo.diff_tree_to_index(head_tree.as_ref(), None, None))?;
+        let unstaged = retry_on_lock(|| repo.diff_index_to_workdir(None, None))?;
+
+        Ok(WorkingTreeStatistics {
+            modified_files,
+            untracked_files,
+            staged: DiffStatistics::new_from(&staged)?,
+            unstaged: DiffStatistics::new_from(&unstaged)?,
+        })
+    }
+    /// Checks whether the working tree has no uncommitted changes or untracked files
+    pub fn is_clean(&self) -> bool {
+        self.modified_files.is_empty() && self.untracked_files.is_empty()
+    }
+    /// Gets the [`WorkingTreeStatistics`] as a JSON string
+    pub fn get_as_json(&self) -> Result<String, SourceCodeError> {
+        serde_json::to_string(&self).map_err(|err| SourceCodeError::SerializationError(err.into()))
+    }
+    #[cfg(feature = "git")]
+    fn staged_statuses() -> Status {
+        Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE
+    }
+}
+
+/// How a file of the working tree differs from HEAD
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    /// A file added to the index, that isn't at HEAD
+    Added,
+    /// A file at HEAD whose contents changed
+    Modified,
+    /// A file at HEAD that was deleted
+    Deleted,
+    /// A file at HEAD whose type changed, e.g., from a file to a symlink
+    TypeChange,
+    /// A file that isn't tracked (and isn't ignored)
+    Untracked,
+}
+
+/// A file of the working tree with uncommitted changes, and its lines changed relative to HEAD
+///
+/// #Fields:
+/// * `path` - The relative path of the file
+/// * `change` - The [`FileChange`] relative to HEAD
+/// * `staged` - Whether (some of) its changes are staged in the index
+/// * `unstaged` - Whether (some of) its changes are only in the working tree
+/// * `lines_added` - The number of lines added relative to HEAD, i.e., all its lines if it is
+///   new; none for binary files
+/// * `lines_removed` - The number of lines removed relative to HEAD
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileDelta {
+    pub path: String,
+    pub change: FileChange,
+    pub staged: bool,
+    pub unstaged: bool,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+
+/// The uncommitted changes of a repository's working tree, file by file, relative to HEAD, as
+/// from [`working_tree_status`], e.g., to annotate an analysis of HEAD with the work in flight
+///
+/// #Fields:
+/// * `head` - The id of the commit at HEAD, or `None` if HEAD is unborn
+/// * `files` - The [`FileDelta`]s of the files with changes, by path
+/// * `totals` - The [`DiffStatistics`] of all the changes
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorkingTreeDelta {
+    pub head: Option<String>,
+    pub files: Vec<FileDelta>,
+    pub totals: DiffStatistics,
+}
+impl WorkingTreeDelta {
+    /// Gets the [`FileDelta`]s of the files whose changes are (partly) staged
+    pub fn staged(&self) -> impl Iterator<Item = &FileDelta> {
+        self.files.iter().filter(|file| file.staged)
+    }
+    /// Gets the [`FileDelta`]s of the tracked files with changes in the working tree that aren't
+    /// staged
+    pub fn modified(&self) -> impl Iterator<Item = &FileDelta> {
+        self.files
+            .iter()
+            .filter(|file| file.unstaged && file.change != FileChange::Untracked)
+    }
+    /// Gets the [`FileDelta`]s of the untracked files
+    pub fn untracked(&self) -> impl Iterator<Item = &FileDelta> {
+        self.files
+            .iter()
+            .filter(|file| file.change == FileChange::Untracked)
+    }
+    /// Checks whether the working tree has no uncommitted changes or untracked files
+    pub fn is_clean(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Gets the [`WorkingTreeDelta`] of the repository at `repo_path`, i.e., each staged, modified
+/// and untracked (but not ignored) file, with the lines it adds and removes relative to HEAD.
+/// Renames are reported as a deletion and an addition.
+///
+/// #Arguments:
+/// * `repo_path` - The path to the repository, which must have a working tree (i.e., not bare)
+#[cfg(feature = "git")]
+pub fn working_tree_status(repo_path: &str) -> Result<WorkingTreeDelta, SourceCodeError> {
+    let repo: Repository = Repository::open(repo_path)?;
+    let mut status_options = StatusOptions::new();
+    status_options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+    let statuses = retry_on_lock(|| repo.statuses(Some(&mut status_options)))?;
+    let by_path: HashMap<String, Status> = statuses
+        .iter()
+        .filter_map(|entry| Some((entry.path()?.to_string(), entry.status())))
+        .collect();
+
+    // An unborn HEAD (no commits yet) is compared as an empty tree
+    let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let head_tree = head.as_ref().and_then(|commit| commit.tree().ok());
+    let mut diff_options = DiffOptions::new();
+    diff_options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    let diff = retry_on_lock(|| {
+        repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut diff_options))
+    })?;
+
+    let mut files: Vec<FileDelta> = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let Some(delta) = diff.get_delta(index) else {
+            continue;
+        };
+        let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+            continue;
+        };
+        let path = paths::to_slash(path);
+        let status = by_path.get(&path).copied().unwrap_or(Status::empty());
+        let change = match delta.status() {
+            Delta::Untracked => FileChange::Untracked,
+            Delta::Added if !status.intersects(WorkingTreeStatistics::staged_statuses()) => {
+                FileChange::Untracked
+            }
+            Delta::Added | Delta::Copied => FileChange::Added,
+            Delta::Deleted => FileChange::Deleted,
+            Delta::Typechange => FileChange::TypeChange,
+            Delta::Modified | Delta::Renamed => FileChange::Modified,
+            _ => continue,
+        };
+        let (lines_added, lines_removed) = match Patch::from_diff(&diff, index)? {
+            Some(patch) => {
+                let (_, added, removed) = patch.line_stats()?;
+                (to_count(added), to_count(removed))
+            }
+            None => (0, 0),
+        };
+        files.push(FileDelta {
+            path,
+            change,
+            staged: status.intersects(WorkingTreeStatistics::staged_statuses()),
+            unstaged: change != FileChange::Untracked
+                && status.intersects(
+                    Status::WT_MODIFIED
+                        | Status::WT_DELETED
+                        | Status::WT_RENAMED
+                        | Status::WT_TYPECHANGE,
+                ),
+            lines_added,
+            lines_removed,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let totals = DiffStatistics {
+        num_files: to_count(files.len()),
+        lines_added: saturating_sum(files.iter().map(|file| file.lines_added)),
+        lines_removed: saturating_sum(files.iter().map(|file| file.lines_removed)),
+    };
+    Ok(WorkingTreeDelta {
+        head: head.map(|commit| commit.id().to_string()),
+        files,
+        totals,
+    })
+}
+
+/// Checks whether the working tree of the repository at `repo_path` has staged or unstaged
+/// changes to tracked files, i.e., whether it differs from HEAD, ignoring untracked files; a
+/// bare repository, or one whose status can't be read, isn't dirty
+#[cfg(feature = "git")]
+pub(crate) fn is_dirty(repo_path: &str) -> bool {
+    let Ok(repo) = Repository::open(repo_path) else {
+        return false;
+    };
+    let mut status_options = StatusOptions::new();
+    status_options
+        .include_untracked(false)
+        .include_ignored(false);
+    retry_on_lock(|| repo.statuses(Some(&mut status_options)))
+        .map(|statuses| {
+            statuses
+                .iter()
+                .any(|entry| !entry.status().is_empty() && !entry.status().is_ignored())
+        })
+        .unwrap_or(false)
+}
+
+/// The size of a set of changes
+///
+/// #Fields:
+/// * `num_files` - The number of files changed
+/// * `lines_added` - The number of lines added
+/// * `lines_removed` - The number of lines removed
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DiffStatistics {
+    pub num_files: u64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+impl DiffStatistics {
+    #[cfg(feature = "git")]
+    fn new_from(diff: &Diff<'_>) -> Result<Self, SourceCodeError> {
+        let stats = diff.stats()?;
+        Ok(Self {
+            num_files: to_count(stats.files_changed()),
+            lines_added: to_count(stats.insertions()),
+            lines_removed: to_count(stats.deletions()),
+        })
+    }
+}